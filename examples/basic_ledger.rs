@@ -1,6 +1,6 @@
 //! Basic ledger usage example
 
-use accounting_core::utils::MemoryStorage;
+use accounting_core::utils::{Formatter, MemoryStorage, TextReportRenderer};
 use accounting_core::{
     patterns, AccountType, GstCalculator, GstCategory, Ledger, TransactionBuilder,
 };
@@ -14,6 +14,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a new ledger with in-memory storage
     let storage = MemoryStorage::new();
     let mut ledger = Ledger::new(storage);
+    let money = Formatter::indian();
 
     // 1. Set up a basic chart of accounts
     println!("📊 Setting up Chart of Accounts...");
@@ -88,11 +89,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         None,
     )?;
 
-    println!("  Sale Amount: ₹{}", sale_calculation.base_amount);
-    println!("  CGST (9%):   ₹{}", sale_calculation.cgst_amount);
-    println!("  SGST (9%):   ₹{}", sale_calculation.sgst_amount);
-    println!("  Total GST:   ₹{}", sale_calculation.total_gst_amount);
-    println!("  Total:       ₹{}", sale_calculation.total_amount);
+    println!(
+        "  Sale Amount: {}",
+        money.format(&sale_calculation.base_amount)
+    );
+    println!(
+        "  CGST (9%):   {}",
+        money.format(&sale_calculation.cgst_amount)
+    );
+    println!(
+        "  SGST (9%):   {}",
+        money.format(&sale_calculation.sgst_amount)
+    );
+    println!(
+        "  Total GST:   {}",
+        money.format(&sale_calculation.total_gst_amount)
+    );
+    println!(
+        "  Total:       {}",
+        money.format(&sale_calculation.total_amount)
+    );
 
     // Record the sale transaction
     let sale_transaction = TransactionBuilder::new(
@@ -142,8 +158,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await?;
 
     println!("🔍 Trial Balance as of January 31, 2024:");
-    println!("  Total Debits:  ₹{}", trial_balance.total_debits);
-    println!("  Total Credits: ₹{}", trial_balance.total_credits);
+    print!(
+        "{}",
+        TextReportRenderer::new(money.clone()).render_trial_balance(&trial_balance)
+    );
+    println!(
+        "  Total Debits:  {}",
+        money.format(&trial_balance.total_debits)
+    );
+    println!(
+        "  Total Credits: {}",
+        money.format(&trial_balance.total_credits)
+    );
     println!(
         "  Balanced: {}",
         if trial_balance.is_balanced {
@@ -162,27 +188,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("📊 Balance Sheet as of January 31, 2024:");
     println!("  Assets:");
     for asset in &balance_sheet.assets {
-        println!("    {}: ₹{}", asset.account.name, asset.balance_amount());
+        println!(
+            "    {}: {}",
+            asset.account.name,
+            money.format(&asset.balance_amount())
+        );
     }
-    println!("  Total Assets: ₹{}", balance_sheet.total_assets);
+    println!(
+        "  Total Assets: {}",
+        money.format(&balance_sheet.total_assets)
+    );
     println!();
 
     println!("  Liabilities:");
     for liability in &balance_sheet.liabilities {
         println!(
-            "    {}: ₹{}",
+            "    {}: {}",
             liability.account.name,
-            liability.balance_amount()
+            money.format(&liability.balance_amount())
         );
     }
-    println!("  Total Liabilities: ₹{}", balance_sheet.total_liabilities);
+    println!(
+        "  Total Liabilities: {}",
+        money.format(&balance_sheet.total_liabilities)
+    );
     println!();
 
     println!("  Equity:");
     for equity in &balance_sheet.equity {
-        println!("    {}: ₹{}", equity.account.name, equity.balance_amount());
+        println!(
+            "    {}: {}",
+            equity.account.name,
+            money.format(&equity.balance_amount())
+        );
     }
-    println!("  Total Equity: ₹{}", balance_sheet.total_equity);
+    println!(
+        "  Total Equity: {}",
+        money.format(&balance_sheet.total_equity)
+    );
     println!();
 
     println!(
@@ -206,26 +249,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Revenue:");
     for revenue in &income_statement.revenue {
         println!(
-            "    {}: ₹{}",
+            "    {}: {}",
             revenue.account.name,
-            revenue.balance_amount()
+            money.format(&revenue.balance_amount())
         );
     }
-    println!("  Total Revenue: ₹{}", income_statement.total_revenue);
+    println!(
+        "  Total Revenue: {}",
+        money.format(&income_statement.total_revenue)
+    );
     println!();
 
     println!("  Expenses:");
     for expense in &income_statement.expenses {
         println!(
-            "    {}: ₹{}",
+            "    {}: {}",
             expense.account.name,
-            expense.balance_amount()
+            money.format(&expense.balance_amount())
         );
     }
-    println!("  Total Expenses: ₹{}", income_statement.total_expenses);
+    println!(
+        "  Total Expenses: {}",
+        money.format(&income_statement.total_expenses)
+    );
     println!();
 
-    println!("  Net Income: ₹{}", income_statement.net_income);
+    println!(
+        "  Net Income: {}",
+        money.format(&income_statement.net_income)
+    );
 
     // 4. Validate ledger integrity
     println!("\n🔍 Validating Ledger Integrity...");
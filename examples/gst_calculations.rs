@@ -1,5 +1,6 @@
 //! GST calculation examples
 
+use accounting_core::utils::Formatter;
 use accounting_core::{
     GstCalculation, GstCalculator, GstCategory, GstInvoice, GstLineItem, GstRate,
 };
@@ -7,6 +8,7 @@ use bigdecimal::BigDecimal;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🧾 Accounting Core - GST Calculation Examples\n");
+    let money = Formatter::indian();
 
     // 1. Basic GST calculations for different categories
     println!("📊 Standard GST Rates by Category:");
@@ -34,12 +36,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         None,
     )?;
 
-    println!("  Base Amount: ₹{}", intra_state_calc.base_amount);
-    println!("  CGST (9%):   ₹{}", intra_state_calc.cgst_amount);
-    println!("  SGST (9%):   ₹{}", intra_state_calc.sgst_amount);
-    println!("  IGST:        ₹{}", intra_state_calc.igst_amount);
-    println!("  Total GST:   ₹{}", intra_state_calc.total_gst_amount);
-    println!("  Final Total: ₹{}", intra_state_calc.total_amount);
+    println!(
+        "  Base Amount: {}",
+        money.format(&intra_state_calc.base_amount)
+    );
+    println!(
+        "  CGST (9%):   {}",
+        money.format(&intra_state_calc.cgst_amount)
+    );
+    println!(
+        "  SGST (9%):   {}",
+        money.format(&intra_state_calc.sgst_amount)
+    );
+    println!(
+        "  IGST:        {}",
+        money.format(&intra_state_calc.igst_amount)
+    );
+    println!(
+        "  Total GST:   {}",
+        money.format(&intra_state_calc.total_gst_amount)
+    );
+    println!(
+        "  Final Total: {}",
+        money.format(&intra_state_calc.total_amount)
+    );
     println!();
 
     println!("🌍 Inter-state Transaction (IGST only):");
@@ -49,12 +69,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some(true), // force inter-state
     )?;
 
-    println!("  Base Amount: ₹{}", inter_state_calc.base_amount);
-    println!("  CGST:        ₹{}", inter_state_calc.cgst_amount);
-    println!("  SGST:        ₹{}", inter_state_calc.sgst_amount);
-    println!("  IGST (18%):  ₹{}", inter_state_calc.igst_amount);
-    println!("  Total GST:   ₹{}", inter_state_calc.total_gst_amount);
-    println!("  Final Total: ₹{}", inter_state_calc.total_amount);
+    println!(
+        "  Base Amount: {}",
+        money.format(&inter_state_calc.base_amount)
+    );
+    println!(
+        "  CGST:        {}",
+        money.format(&inter_state_calc.cgst_amount)
+    );
+    println!(
+        "  SGST:        {}",
+        money.format(&inter_state_calc.sgst_amount)
+    );
+    println!(
+        "  IGST (18%):  {}",
+        money.format(&inter_state_calc.igst_amount)
+    );
+    println!(
+        "  Total GST:   {}",
+        money.format(&inter_state_calc.total_gst_amount)
+    );
+    println!(
+        "  Final Total: {}",
+        money.format(&inter_state_calc.total_amount)
+    );
     println!();
 
     // 3. Reverse calculation (from total to base)
@@ -65,11 +103,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         GstRate::intra_state(BigDecimal::from(18)),
     )?;
 
-    println!("  Given Total: ₹{}", total_amount);
-    println!("  Base Amount: ₹{}", reverse_calc.base_amount);
-    println!("  GST Amount:  ₹{}", reverse_calc.total_gst_amount);
-    println!("  CGST:        ₹{}", reverse_calc.cgst_amount);
-    println!("  SGST:        ₹{}", reverse_calc.sgst_amount);
+    println!("  Given Total: {}", money.format(&total_amount));
+    println!("  Base Amount: {}", money.format(&reverse_calc.base_amount));
+    println!(
+        "  GST Amount:  {}",
+        money.format(&reverse_calc.total_gst_amount)
+    );
+    println!("  CGST:        {}", money.format(&reverse_calc.cgst_amount));
+    println!("  SGST:        {}", money.format(&reverse_calc.sgst_amount));
     println!();
 
     // 4. Complex invoice with multiple line items
@@ -118,24 +159,42 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Line Items:");
     for (i, item) in invoice.line_items.iter().enumerate() {
         println!(
-            "    {}. {} × {} @ ₹{} = ₹{} (GST: ₹{})",
+            "    {}. {} × {} @ {} = {} (GST: {})",
             i + 1,
             item.description,
             item.quantity,
-            item.unit_price,
-            item.line_total_before_gst,
-            item.gst_calculation.total_gst_amount
+            money.format(&item.unit_price),
+            money.format(&item.line_total_before_gst),
+            money.format(&item.gst_calculation.total_gst_amount)
         );
     }
     println!();
 
     println!("  Invoice Summary:");
-    println!("    Subtotal (before GST): ₹{}", invoice.total_before_gst);
-    println!("    Total CGST:            ₹{}", invoice.total_cgst);
-    println!("    Total SGST:            ₹{}", invoice.total_sgst);
-    println!("    Total IGST:            ₹{}", invoice.total_igst);
-    println!("    Total GST:             ₹{}", invoice.total_gst);
-    println!("    Grand Total:           ₹{}", invoice.grand_total);
+    println!(
+        "    Subtotal (before GST): {}",
+        money.format(&invoice.total_before_gst)
+    );
+    println!(
+        "    Total CGST:            {}",
+        money.format(&invoice.total_cgst)
+    );
+    println!(
+        "    Total SGST:            {}",
+        money.format(&invoice.total_sgst)
+    );
+    println!(
+        "    Total IGST:            {}",
+        money.format(&invoice.total_igst)
+    );
+    println!(
+        "    Total GST:             {}",
+        money.format(&invoice.total_gst)
+    );
+    println!(
+        "    Grand Total:           {}",
+        money.format(&invoice.grand_total)
+    );
     println!();
 
     // 5. Custom GST rates
@@ -149,9 +208,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let custom_calc = calculator.calculate_by_product(BigDecimal::from(5000), "PRODUCT_SEZ_001")?;
 
     println!("  Product: PRODUCT_SEZ_001");
-    println!("  Base Amount: ₹{}", custom_calc.base_amount);
-    println!("  Custom GST:  ₹{} (12%)", custom_calc.total_gst_amount);
-    println!("  Total:       ₹{}", custom_calc.total_amount);
+    println!("  Base Amount: {}", money.format(&custom_calc.base_amount));
+    println!(
+        "  Custom GST:  {} (12%)",
+        money.format(&custom_calc.total_gst_amount)
+    );
+    println!("  Total:       {}", money.format(&custom_calc.total_amount));
     println!();
 
     // 6. Validation examples
@@ -0,0 +1,412 @@
+//! Synchronous facade over the async API
+//!
+//! [`BlockingLedger`] runs ledger calls to completion on the calling thread,
+//! so CLI tools and scripts can use the crate without an async runtime. Any
+//! ledger method not wrapped here can be driven with [`block_on`]:
+//!
+//! ```rust
+//! use accounting_core::blocking::{block_on, BlockingLedger};
+//! use accounting_core::utils::MemoryStorage;
+//!
+//! let mut ledger = BlockingLedger::new(MemoryStorage::new());
+//! ledger.setup_standard_chart_of_accounts().unwrap();
+//! let tree = block_on(ledger.ledger().get_account_tree()).unwrap();
+//! assert!(!tree.is_empty());
+//! ```
+//!
+//! Backends whose operations never wait can implement the synchronous
+//! [`SyncLedgerStorage`] instead of [`LedgerStorage`] and be used through
+//! [`SyncStorage`]. Available with the `blocking` feature.
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+use crate::utils::MemoryStorage;
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run a future to completion on the current thread
+///
+/// Futures that wait on runtime-specific resources (e.g. tokio sockets or
+/// timers) need that runtime and cannot be driven this way.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Ledger whose operations block until complete
+pub struct BlockingLedger<S: LedgerStorage + Clone> {
+    ledger: Ledger<S>,
+}
+
+impl<S: LedgerStorage + Clone> BlockingLedger<S> {
+    /// Create a ledger over a storage backend
+    pub fn new(storage: S) -> Self {
+        Self {
+            ledger: Ledger::new(storage),
+        }
+    }
+
+    /// Wrap an already configured ledger
+    pub fn from_ledger(ledger: Ledger<S>) -> Self {
+        Self { ledger }
+    }
+
+    /// The async ledger, for use with [`block_on`]
+    pub fn ledger(&self) -> &Ledger<S> {
+        &self.ledger
+    }
+
+    /// The async ledger, for use with [`block_on`]
+    pub fn ledger_mut(&mut self) -> &mut Ledger<S> {
+        &mut self.ledger
+    }
+
+    /// Unwrap into the async ledger
+    pub fn into_inner(self) -> Ledger<S> {
+        self.ledger
+    }
+
+    /// Setup a standard chart of accounts for small business
+    pub fn setup_standard_chart_of_accounts(&mut self) -> LedgerResult<HashMap<String, Account>> {
+        block_on(self.ledger.setup_standard_chart_of_accounts())
+    }
+
+    /// Create a new account
+    pub fn create_account(
+        &mut self,
+        id: String,
+        name: String,
+        account_type: AccountType,
+        parent_id: Option<String>,
+    ) -> LedgerResult<Account> {
+        block_on(
+            self.ledger
+                .create_account(id, name, account_type, parent_id),
+        )
+    }
+
+    /// Get an account by ID
+    pub fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        block_on(self.ledger.get_account(account_id))
+    }
+
+    /// List all accounts
+    pub fn list_accounts(&self) -> LedgerResult<Vec<Account>> {
+        block_on(self.ledger.list_accounts())
+    }
+
+    /// Record and post a transaction
+    pub fn record_transaction(&mut self, transaction: Transaction) -> LedgerResult<Transaction> {
+        block_on(self.ledger.record_transaction(transaction))
+    }
+
+    /// Save a transaction as a draft
+    pub fn save_draft(&mut self, transaction: Transaction) -> LedgerResult<Transaction> {
+        block_on(self.ledger.save_draft(transaction))
+    }
+
+    /// Post a saved draft
+    pub fn post_draft(&mut self, transaction_id: &str) -> LedgerResult<Transaction> {
+        block_on(self.ledger.post_draft(transaction_id))
+    }
+
+    /// Get a transaction by ID
+    pub fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        block_on(self.ledger.get_transaction(transaction_id))
+    }
+
+    /// Get posted transactions within a date range
+    pub fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        block_on(self.ledger.get_transactions(start_date, end_date))
+    }
+
+    /// Delete a transaction, reversing its effect on balances
+    pub fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        block_on(self.ledger.delete_transaction(transaction_id))
+    }
+
+    /// Get account balance as of a specific date
+    pub fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        block_on(self.ledger.get_account_balance(account_id, as_of_date))
+    }
+
+    /// Get trial balance as of a specific date
+    pub fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        block_on(self.ledger.get_trial_balance(as_of_date))
+    }
+
+    /// Generate a balance sheet as of a specific date
+    pub fn generate_balance_sheet(&self, as_of_date: NaiveDate) -> LedgerResult<BalanceSheet> {
+        block_on(self.ledger.generate_balance_sheet(as_of_date))
+    }
+
+    /// Generate an income statement for a date range
+    pub fn generate_income_statement(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<IncomeStatement> {
+        block_on(self.ledger.generate_income_statement(start_date, end_date))
+    }
+}
+
+/// Synchronous storage backend
+///
+/// The blocking counterpart of [`LedgerStorage`]; wrap an implementation
+/// in [`SyncStorage`] to use it with a ledger.
+pub trait SyncLedgerStorage: Send + Sync {
+    fn save_account(&mut self, account: &Account) -> LedgerResult<()>;
+    fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>>;
+    fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>>;
+    fn update_account(&mut self, account: &Account) -> LedgerResult<()>;
+    fn delete_account(&mut self, account_id: &str) -> LedgerResult<()>;
+    fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()>;
+    fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>>;
+    fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>>;
+    fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>>;
+    fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()>;
+    fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()>;
+    fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal>;
+    fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance>;
+    fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>>;
+}
+
+/// Never waits, so its async operations complete on the first poll
+impl SyncLedgerStorage for MemoryStorage {
+    fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        block_on(LedgerStorage::save_account(self, account))
+    }
+
+    fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        block_on(LedgerStorage::get_account(self, account_id))
+    }
+
+    fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        block_on(LedgerStorage::list_accounts(self, account_type))
+    }
+
+    fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        block_on(LedgerStorage::update_account(self, account))
+    }
+
+    fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        block_on(LedgerStorage::delete_account(self, account_id))
+    }
+
+    fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        block_on(LedgerStorage::save_transaction(self, transaction))
+    }
+
+    fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        block_on(LedgerStorage::get_transaction(self, transaction_id))
+    }
+
+    fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        block_on(LedgerStorage::get_account_transactions(
+            self, account_id, start_date, end_date,
+        ))
+    }
+
+    fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        block_on(LedgerStorage::get_transactions(self, start_date, end_date))
+    }
+
+    fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        block_on(LedgerStorage::update_transaction(self, transaction))
+    }
+
+    fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        block_on(LedgerStorage::delete_transaction(self, transaction_id))
+    }
+
+    fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        block_on(LedgerStorage::get_account_balance(
+            self, account_id, as_of_date,
+        ))
+    }
+
+    fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        block_on(LedgerStorage::get_trial_balance(self, as_of_date))
+    }
+
+    fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        block_on(LedgerStorage::get_account_balances_by_type(
+            self, as_of_date,
+        ))
+    }
+}
+
+/// Adapter exposing a [`SyncLedgerStorage`] as a [`LedgerStorage`]
+#[derive(Debug, Clone)]
+pub struct SyncStorage<S: SyncLedgerStorage>(pub S);
+
+#[async_trait]
+impl<S: SyncLedgerStorage> LedgerStorage for SyncStorage<S> {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.0.save_account(account)
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        self.0.get_account(account_id)
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        self.0.list_accounts(account_type)
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.0.update_account(account)
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        self.0.delete_account(account_id)
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        self.0.save_transaction(transaction)
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        self.0.get_transaction(transaction_id)
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.0
+            .get_account_transactions(account_id, start_date, end_date)
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.0.get_transactions(start_date, end_date)
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        self.0.update_transaction(transaction)
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        self.0.delete_transaction(transaction_id)
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        self.0.get_account_balance(account_id, as_of_date)
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        self.0.get_trial_balance(as_of_date)
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        self.0.get_account_balances_by_type(as_of_date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+
+    #[test]
+    fn test_blocking_ledger_without_runtime() {
+        let mut ledger = BlockingLedger::new(SyncStorage(MemoryStorage::new()));
+        ledger.setup_standard_chart_of_accounts().unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 9, 1).unwrap();
+        let investment = patterns::create_owner_investment(
+            "inv".to_string(),
+            date,
+            "Capital".to_string(),
+            "1000".to_string(),
+            "3000".to_string(),
+            BigDecimal::from(2500),
+        )
+        .unwrap();
+        ledger.record_transaction(investment).unwrap();
+
+        assert_eq!(
+            ledger.get_account_balance("1000", None).unwrap(),
+            BigDecimal::from(2500)
+        );
+        assert!(ledger.get_trial_balance(date).unwrap().is_balanced);
+        let sheet = ledger.generate_balance_sheet(date).unwrap();
+        assert_eq!(sheet.total_assets, BigDecimal::from(2500));
+    }
+}
@@ -0,0 +1,544 @@
+//! Journal CSV import and export
+//!
+//! A plain CSV layout for moving journals between systems and spreadsheets.
+//! The first line is this header:
+//!
+//! ```text
+//! transaction_id,date,account,debit,credit,description,reference,tags
+//! ```
+//!
+//! followed by one row per entry:
+//!
+//! - `transaction_id` groups rows into transactions; rows of a transaction
+//!   need not be adjacent
+//! - `date` is `YYYY-MM-DD` and must be the same on every row of a transaction
+//! - `account` is the account id
+//! - exactly one of `debit` and `credit` holds a positive decimal amount
+//! - `description`, `reference` and `tags` describe the transaction; later
+//!   rows may leave them blank, but any value given must match the first row
+//! - `tags` are separated by `;`
+//!
+//! Fields follow RFC 4180 quoting. Import is all-or-nothing: every problem is
+//! reported against its row number, counting the header as row 1, and
+//! nothing is recorded unless the whole file is valid.
+
+use bigdecimal::{BigDecimal, Signed};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Header line of a journal CSV file
+pub const JOURNAL_CSV_HEADER: [&str; 8] = [
+    "transaction_id",
+    "date",
+    "account",
+    "debit",
+    "credit",
+    "description",
+    "reference",
+    "tags",
+];
+
+/// A problem with one row of a journal CSV file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JournalCsvError {
+    /// Row number, counting the header as row 1
+    pub row: usize,
+    /// Transaction the row belongs to, when it could be read
+    pub transaction_id: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for JournalCsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+/// Outcome of reading a journal CSV file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JournalCsvImport {
+    /// Transactions read, in order of first appearance; empty when there are
+    /// errors
+    pub transactions: Vec<Transaction>,
+    /// Problems found, in row order
+    pub errors: Vec<JournalCsvError>,
+}
+
+impl JournalCsvImport {
+    /// Whether the file was read without problems
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Transaction being assembled, with the row of each entry
+struct PendingTransaction {
+    transaction: Transaction,
+    first_row: usize,
+    entry_rows: Vec<usize>,
+}
+
+/// Split CSV text into records of fields, numbered from 1
+fn read_records(input: &str) -> Result<Vec<(usize, Vec<String>)>, JournalCsvError> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = input.chars().peekable();
+    let mut row = 1;
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                records.push((row, std::mem::take(&mut fields)));
+                row += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(JournalCsvError {
+            row,
+            transaction_id: None,
+            message: "unterminated quoted field".to_string(),
+        });
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((row, fields));
+    }
+    // Blank lines carry no entry
+    records.retain(|(_, fields)| !(fields.len() == 1 && fields[0].trim().is_empty()));
+    Ok(records)
+}
+
+/// Quote a field if it holds a separator, quote or line break
+pub(crate) fn write_field(out: &mut String, field: &str) {
+    if field.contains([',', '"', '\n', '\r']) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+fn parse_amount(text: &str) -> Result<Option<BigDecimal>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let amount = BigDecimal::from_str(text).map_err(|_| format!("invalid amount '{text}'"))?;
+    if !amount.is_positive() {
+        return Err(format!("amount must be positive, got {text}"));
+    }
+    Ok(Some(amount))
+}
+
+fn parse_tags(text: &str) -> Result<BTreeSet<String>, String> {
+    text.split(';')
+        .filter(|tag| !tag.trim().is_empty())
+        .map(|tag| normalize_tag(tag).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Read a journal CSV file into transactions
+///
+/// Checks the layout, amounts and that each transaction balances; whether
+/// the accounts exist is left to [`Ledger::import_journal_csv`].
+pub fn parse_journal_csv(input: &str) -> JournalCsvImport {
+    let (pending, errors) = read_journal(input);
+    into_report(pending, errors)
+}
+
+/// Read and validate transactions, keeping the row of each entry
+fn read_journal(input: &str) -> (Vec<PendingTransaction>, Vec<JournalCsvError>) {
+    let mut errors = Vec::new();
+    let pending = read_pending(input, &mut errors).unwrap_or_default();
+    for pending in &pending {
+        if let Err(error) = pending.transaction.validate() {
+            errors.push(JournalCsvError {
+                row: pending.first_row,
+                transaction_id: Some(pending.transaction.id.clone()),
+                message: error.to_string(),
+            });
+        }
+    }
+    (pending, errors)
+}
+
+fn into_report(
+    pending: Vec<PendingTransaction>,
+    mut errors: Vec<JournalCsvError>,
+) -> JournalCsvImport {
+    errors.sort_by_key(|error| error.row);
+    let transactions = if errors.is_empty() {
+        pending.into_iter().map(|p| p.transaction).collect()
+    } else {
+        Vec::new()
+    };
+    JournalCsvImport {
+        transactions,
+        errors,
+    }
+}
+
+fn read_pending(input: &str, errors: &mut Vec<JournalCsvError>) -> Option<Vec<PendingTransaction>> {
+    let records = match read_records(input) {
+        Ok(records) => records,
+        Err(error) => {
+            errors.push(error);
+            return None;
+        }
+    };
+    let mut records = records.into_iter();
+    match records.next() {
+        Some((_, header))
+            if header
+                .iter()
+                .map(|field| field.trim())
+                .eq(JOURNAL_CSV_HEADER.iter().copied()) => {}
+        _ => {
+            errors.push(JournalCsvError {
+                row: 1,
+                transaction_id: None,
+                message: format!("header must be '{}'", JOURNAL_CSV_HEADER.join(",")),
+            });
+            return None;
+        }
+    }
+
+    let mut pending: Vec<PendingTransaction> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (row, fields) in records {
+        let mut fail = |transaction_id: Option<&str>, message: String| {
+            errors.push(JournalCsvError {
+                row,
+                transaction_id: transaction_id.map(str::to_string),
+                message,
+            });
+        };
+        if fields.len() != JOURNAL_CSV_HEADER.len() {
+            fail(
+                None,
+                format!(
+                    "expected {} fields, found {}",
+                    JOURNAL_CSV_HEADER.len(),
+                    fields.len()
+                ),
+            );
+            continue;
+        }
+        let field = |i: usize| fields[i].trim();
+        let transaction_id = field(0);
+        if transaction_id.is_empty() {
+            fail(None, "transaction_id is required".to_string());
+            continue;
+        }
+        let id = Some(transaction_id);
+        let date = match NaiveDate::parse_from_str(field(1), "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                fail(id, format!("invalid date '{}'", field(1)));
+                continue;
+            }
+        };
+        let account_id = field(2);
+        if account_id.is_empty() {
+            fail(id, "account is required".to_string());
+            continue;
+        }
+        let (debit, credit) = match (parse_amount(field(3)), parse_amount(field(4))) {
+            (Ok(debit), Ok(credit)) => (debit, credit),
+            (Err(message), _) | (_, Err(message)) => {
+                fail(id, message);
+                continue;
+            }
+        };
+        let (entry_type, amount) = match (debit, credit) {
+            (Some(amount), None) => (EntryType::Debit, amount),
+            (None, Some(amount)) => (EntryType::Credit, amount),
+            _ => {
+                fail(
+                    id,
+                    "exactly one of debit and credit is required".to_string(),
+                );
+                continue;
+            }
+        };
+        let tags = match parse_tags(field(7)) {
+            Ok(tags) => tags,
+            Err(message) => {
+                fail(id, message);
+                continue;
+            }
+        };
+        let reference = Some(field(6)).filter(|r| !r.is_empty());
+
+        let slot = *index.entry(transaction_id.to_string()).or_insert_with(|| {
+            let mut transaction = Transaction::new(
+                transaction_id.to_string(),
+                date,
+                field(5).to_string(),
+                reference.map(str::to_string),
+            );
+            transaction.tags = tags.clone();
+            pending.push(PendingTransaction {
+                transaction,
+                first_row: row,
+                entry_rows: Vec::new(),
+            });
+            pending.len() - 1
+        });
+        let current = &mut pending[slot];
+        let transaction = &current.transaction;
+        if transaction.date != date {
+            fail(
+                id,
+                format!(
+                    "date {date} differs from {} on row {}",
+                    transaction.date, current.first_row
+                ),
+            );
+            continue;
+        }
+        let mismatch = if !field(5).is_empty() && field(5) != transaction.description {
+            Some("description")
+        } else if reference.is_some_and(|r| transaction.reference.as_deref() != Some(r)) {
+            Some("reference")
+        } else if !tags.is_empty() && tags != transaction.tags {
+            Some("tags")
+        } else {
+            None
+        };
+        if let Some(column) = mismatch {
+            fail(
+                id,
+                format!("{column} differs from row {}", current.first_row),
+            );
+            continue;
+        }
+        current
+            .transaction
+            .add_entry(Entry::new(account_id.to_string(), entry_type, amount, None));
+        current.entry_rows.push(row);
+    }
+    Some(pending)
+}
+
+/// Write transactions as a journal CSV file
+pub fn write_journal_csv(transactions: &[Transaction]) -> String {
+    let mut out = JOURNAL_CSV_HEADER.join(",");
+    out.push('\n');
+    for transaction in transactions {
+        let tags = transaction
+            .tags
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(";");
+        for entry in &transaction.entries {
+            let amount = entry.amount.to_plain_string();
+            let (debit, credit) = match entry.entry_type {
+                EntryType::Debit => (amount.as_str(), ""),
+                EntryType::Credit => ("", amount.as_str()),
+            };
+            let date = transaction.date.format("%Y-%m-%d").to_string();
+            let fields = [
+                transaction.id.as_str(),
+                date.as_str(),
+                entry.account_id.as_str(),
+                debit,
+                credit,
+                transaction.description.as_str(),
+                transaction.reference.as_deref().unwrap_or(""),
+                tags.as_str(),
+            ];
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_field(&mut out, field);
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Record the transactions of a journal CSV file
+    ///
+    /// Nothing is recorded unless every row is valid and refers to an
+    /// existing account; otherwise the returned report lists the problems.
+    /// A transaction the ledger refuses (for example in a locked period) is
+    /// reported against its first row and the ones already recorded from the
+    /// file are removed again; any that cannot be removed are reported too.
+    pub async fn import_journal_csv(&mut self, input: &str) -> LedgerResult<JournalCsvImport> {
+        let (pending, mut errors) = read_journal(input);
+        for pending in &pending {
+            for (entry, row) in pending.transaction.entries.iter().zip(&pending.entry_rows) {
+                if self.get_account(&entry.account_id).await?.is_none() {
+                    errors.push(JournalCsvError {
+                        row: *row,
+                        transaction_id: Some(pending.transaction.id.clone()),
+                        message: format!("account not found: {}", entry.account_id),
+                    });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Ok(into_report(pending, errors));
+        }
+
+        let mut recorded: Vec<&PendingTransaction> = Vec::new();
+        for pending in &pending {
+            let transaction = pending.transaction.clone();
+            if let Err(error) = self.record_transaction(transaction).await {
+                let mut errors = vec![JournalCsvError {
+                    row: pending.first_row,
+                    transaction_id: Some(pending.transaction.id.clone()),
+                    message: error.to_string(),
+                }];
+                for recorded in recorded.iter().rev() {
+                    let id = &recorded.transaction.id;
+                    if let Err(error) = self.transaction_manager.delete_transaction(id).await {
+                        errors.push(JournalCsvError {
+                            row: recorded.first_row,
+                            transaction_id: Some(id.clone()),
+                            message: format!("recorded but could not be removed again: {}", error),
+                        });
+                    }
+                }
+                return Ok(into_report(Vec::new(), errors));
+            }
+            recorded.push(pending);
+        }
+        Ok(into_report(pending, errors))
+    }
+
+    /// Posted transactions in a date range as a journal CSV file
+    pub async fn export_journal_csv(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<String> {
+        let mut transactions = self.get_transactions(start_date, end_date).await?;
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        Ok(write_journal_csv(&transactions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+    use bigdecimal::Zero;
+
+    #[test]
+    fn test_errors_are_reported_by_row() {
+        let input = "transaction_id,date,account,debit,credit,description,reference,tags\n\
+                     t1,2024-04-01,1000,100,,Capital,,\n\
+                     t1,2024-04-01,3000,,90,,,\n\
+                     t2,2024-04-02,6000,50,,Rent,,\n\
+                     t2,2024-04-03,1000,,50,,,\n\
+                     t3,2024-04-02,6000,10,10,Both,,\n";
+        let report = parse_journal_csv(input);
+        let rows: Vec<usize> = report.errors.iter().map(|e| e.row).collect();
+        assert_eq!(rows, vec![2, 4, 5, 6]);
+        assert!(report.errors[0].message.contains("not balanced"));
+        assert!(report.errors[2].message.contains("date"));
+        assert!(report.transactions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_journal_csv_round_trip() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let input = "transaction_id,date,account,debit,credit,description,reference,tags\r\n\
+                     cap,2024-04-01,1000,5000,,Owner capital,,\r\n\
+                     cap,2024-04-01,3000,,5000,,,\r\n\
+                     rent,2024-04-05,6000,1200.50,,\"Rent, April \"\"HQ\"\"\",CHQ-1,office;fixed\r\n\
+                     rent,2024-04-05,1000,,1200.50,,,\r\n";
+        let report = ledger.import_journal_csv(input).await.unwrap();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!(report.transactions.len(), 2);
+        let rent = ledger.get_transaction("rent").await.unwrap().unwrap();
+        assert_eq!(rent.description, "Rent, April \"HQ\"");
+        assert_eq!(rent.reference.as_deref(), Some("CHQ-1"));
+        assert!(rent.has_tag("office"));
+
+        let exported = ledger.export_journal_csv(None, None).await.unwrap();
+        let reread = parse_journal_csv(&exported);
+        assert!(reread.is_ok());
+        assert_eq!(reread.transactions.len(), 2);
+        assert_eq!(reread.transactions[1].description, rent.description);
+        assert_eq!(reread.transactions[1].total_debits(), rent.total_debits());
+
+        // Unknown accounts fail the whole file
+        let bad = "transaction_id,date,account,debit,credit,description,reference,tags\n\
+                   x,2024-04-06,9999,10,,Unknown,,\n\
+                   x,2024-04-06,1000,,10,,,\n";
+        let report = ledger.import_journal_csv(bad).await.unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].row, 2);
+        assert!(ledger.get_transaction("x").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refused_transaction_rolls_back_import() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .lock_period(NaiveDate::from_ymd_opt(2024, 3, 31).unwrap())
+            .unwrap();
+        // The importer may post but not void, so the rollback must not
+        // go through the authorized delete
+        ledger.set_authorization_policy(Box::new(
+            RoleBasedPolicy::new().require("void_transaction", "controller"),
+        ));
+        ledger.set_actor(Some(Actor::new("clerk".to_string(), vec![])));
+
+        let input = "transaction_id,date,account,debit,credit,description,reference,tags\n\
+                     a,2024-04-01,1000,100,,First,,\n\
+                     a,2024-04-01,3000,,100,,,\n\
+                     b,2024-04-02,1000,200,,Second,,\n\
+                     b,2024-04-02,3000,,200,,,\n\
+                     c,2024-03-15,1000,300,,Locked,,\n\
+                     c,2024-03-15,3000,,300,,,\n";
+        let report = ledger.import_journal_csv(input).await.unwrap();
+        assert_eq!(report.errors.len(), 1, "{:?}", report.errors);
+        assert_eq!(report.errors[0].row, 6);
+        assert!(report.transactions.is_empty());
+        assert!(ledger.get_transaction("a").await.unwrap().is_none());
+        assert!(ledger.get_transaction("b").await.unwrap().is_none());
+        assert_eq!(
+            ledger.get_account_balance("1000", None).await.unwrap(),
+            BigDecimal::zero()
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! Exchange formats shared with other accounting software
+
+pub mod journal_csv;
+pub mod tally;
+
+pub use journal_csv::*;
+pub use tally::*;
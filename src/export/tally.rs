@@ -0,0 +1,313 @@
+//! Tally XML export
+//!
+//! Produces an `ENVELOPE` document that TallyPrime and Tally.ERP 9 accept
+//! through *Import Data*: one `LEDGER` master per account, with its opening
+//! balance, followed by one `VOUCHER` per posted transaction.
+//!
+//! Every account becomes a ledger under the Tally primary group for its type
+//! (see [`tally_group`]); set the `tally_group` metadata text on an account
+//! to file it under another group such as `Bank Accounts` or `Sundry
+//! Debtors`. Tally ledger names must be unique, so accounts sharing a name
+//! are exported as `Name (id)`.
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Metadata key overriding the Tally group of an account
+pub const TALLY_GROUP_KEY: &str = "tally_group";
+
+/// Tally primary group for accounts of a type
+pub fn tally_group(account_type: &AccountType) -> &'static str {
+    match account_type {
+        AccountType::Asset => "Current Assets",
+        AccountType::Liability => "Current Liabilities",
+        AccountType::Equity => "Capital Account",
+        AccountType::Income => "Indirect Incomes",
+        AccountType::Expense => "Indirect Expenses",
+    }
+}
+
+/// Tally voucher type for a transaction kind
+pub fn tally_voucher_type(kind: &TransactionKind) -> &'static str {
+    match kind {
+        TransactionKind::Sales => "Sales",
+        TransactionKind::Purchase => "Purchase",
+        TransactionKind::Receipt => "Receipt",
+        TransactionKind::Payment => "Payment",
+        TransactionKind::Contra => "Contra",
+        TransactionKind::Journal | TransactionKind::Adjustment => "Journal",
+    }
+}
+
+/// Masters and vouchers to write as one Tally import file
+#[derive(Debug, Clone, PartialEq)]
+pub struct TallyExport {
+    /// Tally company to import into
+    pub company: String,
+    /// Accounts exported as ledger masters
+    pub accounts: Vec<Account>,
+    /// Balances exported as ledger opening balances; accounts missing here
+    /// open at zero
+    pub opening_balances: HashMap<String, AccountBalance>,
+    /// Transactions exported as vouchers
+    pub transactions: Vec<Transaction>,
+}
+
+impl TallyExport {
+    /// An empty export for a company
+    pub fn new(company: String) -> Self {
+        Self {
+            company,
+            accounts: Vec::new(),
+            opening_balances: HashMap::new(),
+            transactions: Vec::new(),
+        }
+    }
+
+    /// Tally ledger name for each account id
+    pub fn ledger_names(&self) -> HashMap<String, String> {
+        let mut name_counts: HashMap<&str, usize> = HashMap::new();
+        for account in &self.accounts {
+            *name_counts.entry(account.name.as_str()).or_default() += 1;
+        }
+        self.accounts
+            .iter()
+            .map(|account| {
+                let name = if name_counts[account.name.as_str()] > 1 {
+                    format!("{} ({})", account.name, account.id)
+                } else {
+                    account.name.clone()
+                };
+                (account.id.clone(), name)
+            })
+            .collect()
+    }
+
+    /// Render the import file
+    pub fn to_xml(&self) -> String {
+        let names = self.ledger_names();
+        let ledger_name = |account_id: &str| {
+            names
+                .get(account_id)
+                .cloned()
+                .unwrap_or_else(|| account_id.to_string())
+        };
+
+        let mut xml = String::new();
+        xml.push_str("<ENVELOPE>\n");
+        xml.push_str(" <HEADER>\n  <TALLYREQUEST>Import Data</TALLYREQUEST>\n </HEADER>\n");
+        xml.push_str(" <BODY>\n  <IMPORTDATA>\n   <REQUESTDESC>\n");
+        xml.push_str("    <REPORTNAME>All Masters</REPORTNAME>\n");
+        let _ = writeln!(
+            xml,
+            "    <STATICVARIABLES>\n     <SVCURRENTCOMPANY>{}</SVCURRENTCOMPANY>\n    </STATICVARIABLES>",
+            escape(&self.company)
+        );
+        xml.push_str("   </REQUESTDESC>\n   <REQUESTDATA>\n");
+
+        for account in &self.accounts {
+            let name = escape(&ledger_name(&account.id));
+            let group = account
+                .metadata
+                .get(TALLY_GROUP_KEY)
+                .and_then(MetaValue::as_str)
+                .unwrap_or_else(|| tally_group(&account.account_type));
+            let opening = self
+                .opening_balances
+                .get(&account.id)
+                .map(signed_balance)
+                .unwrap_or_default();
+            xml.push_str("    <TALLYMESSAGE xmlns:UDF=\"TallyUDF\">\n");
+            let _ = writeln!(xml, "     <LEDGER NAME=\"{name}\" ACTION=\"Create\">");
+            let _ = writeln!(xml, "      <NAME>{name}</NAME>");
+            let _ = writeln!(xml, "      <PARENT>{}</PARENT>", escape(group));
+            let _ = writeln!(
+                xml,
+                "      <OPENINGBALANCE>{}</OPENINGBALANCE>",
+                opening.to_plain_string()
+            );
+            xml.push_str("     </LEDGER>\n    </TALLYMESSAGE>\n");
+        }
+
+        for transaction in &self.transactions {
+            let voucher_type = tally_voucher_type(&transaction.kind);
+            xml.push_str("    <TALLYMESSAGE xmlns:UDF=\"TallyUDF\">\n");
+            let _ = writeln!(
+                xml,
+                "     <VOUCHER VCHTYPE=\"{voucher_type}\" ACTION=\"Create\">"
+            );
+            let _ = writeln!(
+                xml,
+                "      <DATE>{}</DATE>",
+                transaction.date.format("%Y%m%d")
+            );
+            let _ = writeln!(
+                xml,
+                "      <VOUCHERTYPENAME>{voucher_type}</VOUCHERTYPENAME>"
+            );
+            let _ = writeln!(
+                xml,
+                "      <VOUCHERNUMBER>{}</VOUCHERNUMBER>",
+                escape(&transaction.id)
+            );
+            if let Some(reference) = &transaction.reference {
+                let _ = writeln!(xml, "      <REFERENCE>{}</REFERENCE>", escape(reference));
+            }
+            let _ = writeln!(
+                xml,
+                "      <NARRATION>{}</NARRATION>",
+                escape(&transaction.description)
+            );
+            for entry in &transaction.entries {
+                // Tally signs debits negative and flags them as deemed positive
+                let (deemed_positive, amount) = match entry.entry_type {
+                    EntryType::Debit => ("Yes", -entry.amount.clone()),
+                    EntryType::Credit => ("No", entry.amount.clone()),
+                };
+                xml.push_str("      <ALLLEDGERENTRIES.LIST>\n");
+                let _ = writeln!(
+                    xml,
+                    "       <LEDGERNAME>{}</LEDGERNAME>",
+                    escape(&ledger_name(&entry.account_id))
+                );
+                let _ = writeln!(
+                    xml,
+                    "       <ISDEEMEDPOSITIVE>{deemed_positive}</ISDEEMEDPOSITIVE>"
+                );
+                let _ = writeln!(xml, "       <AMOUNT>{}</AMOUNT>", amount.to_plain_string());
+                xml.push_str("      </ALLLEDGERENTRIES.LIST>\n");
+            }
+            xml.push_str("     </VOUCHER>\n    </TALLYMESSAGE>\n");
+        }
+
+        xml.push_str("   </REQUESTDATA>\n  </IMPORTDATA>\n </BODY>\n</ENVELOPE>\n");
+        xml
+    }
+}
+
+/// Trial balance line as a Tally amount: debit balances are negative
+fn signed_balance(balance: &AccountBalance) -> BigDecimal {
+    match (&balance.debit_balance, &balance.credit_balance) {
+        (Some(debit), _) => -debit.clone(),
+        (None, Some(credit)) => credit.clone(),
+        (None, None) => BigDecimal::from(0),
+    }
+}
+
+/// Escape text for an XML element or attribute
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Collect the masters and posted vouchers for a period as a Tally export
+    ///
+    /// Opening balances are the trial balance on the day before
+    /// `start_date`, so importing into a fresh Tally company reproduces the
+    /// closing position.
+    pub async fn tally_export(
+        &self,
+        company: String,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<TallyExport> {
+        let mut export = TallyExport::new(company);
+        export.accounts = self.list_accounts().await?;
+        export.accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        if let Some(opening_date) = start_date.pred_opt() {
+            export.opening_balances = self.get_trial_balance(opening_date).await?.balances;
+        }
+        export.transactions = self
+            .get_transactions(Some(start_date), Some(end_date))
+            .await?;
+        export
+            .transactions
+            .sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        Ok(export)
+    }
+
+    /// Tally import XML for the masters and posted vouchers of a period
+    pub async fn export_tally_xml(
+        &self,
+        company: String,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<String> {
+        Ok(self
+            .tally_export(company, start_date, end_date)
+            .await?
+            .to_xml())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_tally_xml_has_masters_openings_and_vouchers() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let capital = patterns::create_owner_investment(
+            "cap-1".to_string(),
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+            "Capital".to_string(),
+            "1000".to_string(),
+            "3000".to_string(),
+            BigDecimal::from(5000),
+        )
+        .unwrap();
+        ledger.record_transaction(capital).await.unwrap();
+        let rent = patterns::create_expense_payment(
+            "rent-1".to_string(),
+            NaiveDate::from_ymd_opt(2024, 4, 5).unwrap(),
+            "Rent for April & May".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(1200),
+        )
+        .unwrap();
+        ledger.record_transaction(rent).await.unwrap();
+
+        let start = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 4, 30).unwrap();
+        let export = ledger
+            .tally_export("Acme Traders".to_string(), start, end)
+            .await
+            .unwrap();
+        assert_eq!(export.transactions.len(), 1);
+
+        let xml = export.to_xml();
+        let cash = &export.ledger_names()["1000"];
+        assert!(xml.contains("<SVCURRENTCOMPANY>Acme Traders</SVCURRENTCOMPANY>"));
+        assert!(xml.contains(&format!(
+            "<NAME>{cash}</NAME>\n      <PARENT>Current Assets</PARENT>\n      <OPENINGBALANCE>-5000</OPENINGBALANCE>"
+        )));
+        assert!(xml.contains("<VOUCHER VCHTYPE=\"Payment\" ACTION=\"Create\">"));
+        assert!(xml.contains("<DATE>20240405</DATE>"));
+        assert!(xml.contains("<NARRATION>Rent for April &amp; May</NARRATION>"));
+        assert!(xml.contains(&format!(
+            "<LEDGERNAME>{cash}</LEDGERNAME>\n       <ISDEEMEDPOSITIVE>No</ISDEEMEDPOSITIVE>\n       <AMOUNT>1200</AMOUNT>"
+        )));
+        assert!(xml.contains("<AMOUNT>-1200</AMOUNT>"));
+    }
+}
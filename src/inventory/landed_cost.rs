@@ -0,0 +1,341 @@
+//! Landed cost allocation: capitalising freight, duty and clearing charges
+//! into the cost of purchased stock
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::stock::*;
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::traits::*;
+use crate::types::*;
+
+/// Basis for spreading a charge across purchase lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum AllocationBasis {
+    Value,
+    Quantity,
+    Weight,
+}
+
+/// A purchased line item receiving a share of landed cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PurchaseLine {
+    pub item_id: String,
+    pub quantity: BigDecimal,
+    /// Purchase value of the line before landed cost
+    pub value: BigDecimal,
+    /// Total weight of the line, required for weight-based allocation
+    pub weight: Option<BigDecimal>,
+}
+
+impl PurchaseLine {
+    pub fn new(item_id: String, quantity: BigDecimal, value: BigDecimal) -> Self {
+        Self {
+            item_id,
+            quantity,
+            value,
+            weight: None,
+        }
+    }
+
+    /// Set the line's total weight
+    pub fn with_weight(mut self, weight: BigDecimal) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    fn basis_amount(&self, basis: AllocationBasis) -> LedgerResult<BigDecimal> {
+        match basis {
+            AllocationBasis::Value => Ok(self.value.clone()),
+            AllocationBasis::Quantity => Ok(self.quantity.clone()),
+            AllocationBasis::Weight => self.weight.clone().ok_or_else(|| {
+                LedgerError::Validation(format!(
+                    "Purchase line for '{}' has no weight for weight-based allocation",
+                    self.item_id
+                ))
+            }),
+        }
+    }
+}
+
+/// A freight, duty or clearing charge to be capitalised
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LandedCharge {
+    pub description: String,
+    pub amount: BigDecimal,
+    /// Account credited for the charge (payable or clearing account)
+    pub account_id: String,
+    pub basis: AllocationBasis,
+}
+
+/// Share of landed cost allocated to a purchase line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LandedCostAllocation {
+    pub item_id: String,
+    pub amount: BigDecimal,
+}
+
+/// Parameters for capitalising landed cost into purchased stock
+#[derive(Debug, Clone)]
+pub struct LandedCostParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub lines: Vec<PurchaseLine>,
+    pub charges: Vec<LandedCharge>,
+}
+
+/// Spread `amount` across purchase lines in proportion to `basis`
+///
+/// Shares are split with [`AmountPolicy::allocate`], so they always sum to
+/// `amount` and a line with nothing to weigh by gets nothing.
+pub fn allocate_landed_cost(
+    lines: &[PurchaseLine],
+    amount: &BigDecimal,
+    basis: AllocationBasis,
+    policy: &AmountPolicy,
+) -> LedgerResult<Vec<LandedCostAllocation>> {
+    if lines.is_empty() {
+        return Err(LedgerError::Validation(
+            "Landed cost needs at least one purchase line".to_string(),
+        ));
+    }
+
+    let weights = lines
+        .iter()
+        .map(|line| line.basis_amount(basis))
+        .collect::<LedgerResult<Vec<_>>>()?;
+    if weights.iter().any(|w| w.is_negative()) {
+        return Err(LedgerError::Validation(
+            "Allocation basis amounts cannot be negative".to_string(),
+        ));
+    }
+    let total: BigDecimal = weights.iter().sum();
+    if total.is_zero() {
+        return Err(LedgerError::Validation(format!(
+            "Purchase lines have no {:?} to allocate against",
+            basis
+        )));
+    }
+
+    let shares = policy.allocate(amount, &weights)?;
+    Ok(lines
+        .iter()
+        .zip(shares)
+        .map(|(line, amount)| LandedCostAllocation {
+            item_id: line.item_id.clone(),
+            amount,
+        })
+        .collect())
+}
+
+/// Allocate every charge and combine the shares per item, rounding with
+/// `policy`
+pub fn allocate_landed_charges(
+    lines: &[PurchaseLine],
+    charges: &[LandedCharge],
+    policy: &AmountPolicy,
+) -> LedgerResult<Vec<LandedCostAllocation>> {
+    let mut totals: Vec<LandedCostAllocation> = lines
+        .iter()
+        .map(|line| LandedCostAllocation {
+            item_id: line.item_id.clone(),
+            amount: BigDecimal::zero(),
+        })
+        .collect();
+    for charge in charges {
+        if !charge.amount.is_positive() {
+            return Err(LedgerError::Validation(format!(
+                "Landed charge '{}' must be positive",
+                charge.description
+            )));
+        }
+        let shares = allocate_landed_cost(lines, &charge.amount, charge.basis, policy)?;
+        for (total, share) in totals.iter_mut().zip(shares) {
+            total.amount += share.amount;
+        }
+    }
+    Ok(totals)
+}
+
+/// Build the capitalisation journal: debit each item's inventory account with
+/// its allocated cost and credit each charge account
+pub fn create_landed_cost_journal(
+    params: &LandedCostParams,
+    inventory: &Inventory,
+    allocations: &[LandedCostAllocation],
+) -> LedgerResult<Transaction> {
+    let mut debits: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    for allocation in allocations {
+        let item = inventory.get_item(&allocation.item_id).ok_or_else(|| {
+            LedgerError::Validation(format!("Unknown inventory item '{}'", allocation.item_id))
+        })?;
+        *debits
+            .entry(item.inventory_account_id.clone())
+            .or_insert_with(BigDecimal::zero) += &allocation.amount;
+    }
+
+    let mut builder =
+        TransactionBuilder::new(params.id.clone(), params.date, params.description.clone())
+            .kind(TransactionKind::Purchase);
+    for (account_id, amount) in debits {
+        if !amount.is_zero() {
+            builder = builder.debit(account_id, amount, Some("Landed cost".to_string()));
+        }
+    }
+    for charge in &params.charges {
+        builder = builder.credit(
+            charge.account_id.clone(),
+            charge.amount.clone(),
+            Some(charge.description.clone()),
+        );
+    }
+    builder.build()
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Allocate landed charges across purchase lines, add them to the items'
+    /// stock cost and post the capitalisation journal
+    ///
+    /// Returns the allocations together with the journal. The stock cost is
+    /// only changed once the journal is posted; while it is held for
+    /// approval `inventory` is left as it was.
+    pub async fn capitalize_landed_cost(
+        &mut self,
+        inventory: &mut Inventory,
+        params: LandedCostParams,
+    ) -> LedgerResult<(Vec<LandedCostAllocation>, Transaction)> {
+        let allocations = allocate_landed_charges(
+            &params.lines,
+            &params.charges,
+            &self.currency_amount_policy(),
+        )?;
+
+        let mut updated = inventory.clone();
+        for allocation in allocations.iter().filter(|a| !a.amount.is_zero()) {
+            updated.capitalize_cost(
+                &allocation.item_id,
+                params.date,
+                allocation.amount.clone(),
+                Some(params.id.clone()),
+            )?;
+        }
+
+        let journal = create_landed_cost_journal(&params, &updated, &allocations)?;
+        let journal = self.record_transaction(journal).await?;
+
+        if journal.is_posted() {
+            *inventory = updated;
+        }
+        Ok((allocations, journal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines() -> Vec<PurchaseLine> {
+        vec![
+            PurchaseLine::new(
+                "bolts".to_string(),
+                BigDecimal::from(100),
+                BigDecimal::from(3000),
+            )
+            .with_weight(BigDecimal::from(50)),
+            PurchaseLine::new(
+                "nuts".to_string(),
+                BigDecimal::from(200),
+                BigDecimal::from(1000),
+            )
+            .with_weight(BigDecimal::from(25)),
+        ]
+    }
+
+    #[test]
+    fn test_allocation_bases() {
+        let amount = BigDecimal::from(400);
+
+        let by_value = allocate_landed_cost(
+            &lines(),
+            &amount,
+            AllocationBasis::Value,
+            &AmountPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(by_value[0].amount, BigDecimal::from(300));
+        assert_eq!(by_value[1].amount, BigDecimal::from(100));
+
+        let by_quantity = allocate_landed_cost(
+            &lines(),
+            &amount,
+            AllocationBasis::Quantity,
+            &AmountPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            by_quantity[0].amount,
+            "133.33".parse::<BigDecimal>().unwrap()
+        );
+        assert_eq!(
+            by_quantity[1].amount,
+            "266.67".parse::<BigDecimal>().unwrap()
+        );
+
+        let by_weight = allocate_landed_cost(
+            &lines(),
+            &amount,
+            AllocationBasis::Weight,
+            &AmountPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(by_weight[0].amount, "266.67".parse::<BigDecimal>().unwrap());
+        assert_eq!(by_weight[1].amount, "133.33".parse::<BigDecimal>().unwrap());
+    }
+
+    #[test]
+    fn test_zero_weight_line_gets_nothing() {
+        let lines: Vec<PurchaseLine> = [("bolts", 1), ("nuts", 1), ("washers", 0)]
+            .into_iter()
+            .map(|(item_id, value)| {
+                PurchaseLine::new(
+                    item_id.to_string(),
+                    BigDecimal::from(1),
+                    BigDecimal::from(value),
+                )
+            })
+            .collect();
+        let shares = allocate_landed_cost(
+            &lines,
+            &"0.01".parse().unwrap(),
+            AllocationBasis::Value,
+            &AmountPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(shares[0].amount, "0.01".parse::<BigDecimal>().unwrap());
+        assert!(shares[1].amount.is_zero());
+        assert!(shares[2].amount.is_zero());
+    }
+
+    #[test]
+    fn test_weight_basis_requires_weights() {
+        let lines = vec![PurchaseLine::new(
+            "bolts".to_string(),
+            BigDecimal::from(1),
+            BigDecimal::from(10),
+        )];
+        assert!(allocate_landed_cost(
+            &lines,
+            &BigDecimal::from(5),
+            AllocationBasis::Weight,
+            &AmountPolicy::default()
+        )
+        .is_err());
+    }
+}
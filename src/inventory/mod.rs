@@ -0,0 +1,10 @@
+//! Inventory module: stock items, receipts and issues, valuation and
+//! cost of goods sold
+
+pub mod landed_cost;
+pub mod stock;
+pub mod valuation;
+
+pub use landed_cost::*;
+pub use stock::*;
+pub use valuation::*;
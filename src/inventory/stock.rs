@@ -0,0 +1,513 @@
+//! Stock items and movements with FIFO and weighted average costing
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::types::*;
+
+/// Costing method used to value stock issues
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ValuationMethod {
+    /// First in, first out: issues consume the oldest receipts first
+    Fifo,
+    /// Issues are costed at the running average cost of stock on hand
+    WeightedAverage,
+}
+
+/// A stocked item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InventoryItem {
+    pub id: String,
+    pub name: String,
+    /// Unit of measure (e.g. "pcs", "kg")
+    pub unit: String,
+    pub valuation_method: ValuationMethod,
+    /// Inventory control account the item's value is carried in
+    pub inventory_account_id: String,
+    /// Expense account charged when the item is sold
+    pub cogs_account_id: String,
+}
+
+impl InventoryItem {
+    /// Create a new inventory item
+    pub fn new(
+        id: String,
+        name: String,
+        unit: String,
+        valuation_method: ValuationMethod,
+        inventory_account_id: String,
+        cogs_account_id: String,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            unit,
+            valuation_method,
+            inventory_account_id,
+            cogs_account_id,
+        }
+    }
+}
+
+/// Direction of a stock movement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum StockMovementType {
+    Receipt,
+    Issue,
+    /// Additional cost capitalised into stock on hand, such as landed cost
+    CostAdjustment,
+}
+
+/// A costed stock receipt or issue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StockMovement {
+    pub item_id: String,
+    pub date: NaiveDate,
+    pub movement_type: StockMovementType,
+    pub quantity: BigDecimal,
+    pub unit_cost: BigDecimal,
+    pub total_cost: BigDecimal,
+    /// Source document or transaction ID
+    pub reference: Option<String>,
+}
+
+/// Quantity received at a single unit cost, consumed by FIFO issues
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CostLayer {
+    pub date: NaiveDate,
+    pub quantity: BigDecimal,
+    pub unit_cost: BigDecimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct ItemStock {
+    item: InventoryItem,
+    /// Open receipt layers (FIFO items only)
+    layers: VecDeque<CostLayer>,
+    quantity: BigDecimal,
+    value: BigDecimal,
+    movements: Vec<StockMovement>,
+}
+
+/// Stock sub-ledger holding items, their cost layers and movement history
+///
+/// Movements are costed when recorded, so each item's movements must be
+/// entered in date order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Inventory {
+    items: BTreeMap<String, ItemStock>,
+    #[serde(default)]
+    amount_policy: AmountPolicy,
+}
+
+impl Inventory {
+    /// Create an empty inventory costing to two decimal places
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty inventory rounding costs with `policy`, usually the
+    /// ledger's [`currency_amount_policy`](crate::ledger::Ledger::currency_amount_policy)
+    pub fn with_amount_policy(policy: AmountPolicy) -> Self {
+        Self {
+            amount_policy: policy,
+            ..Self::default()
+        }
+    }
+
+    /// How costs are rounded
+    pub fn amount_policy(&self) -> &AmountPolicy {
+        &self.amount_policy
+    }
+
+    /// Add an item to the inventory
+    pub fn add_item(&mut self, item: InventoryItem) -> LedgerResult<()> {
+        if self.items.contains_key(&item.id) {
+            return Err(LedgerError::Validation(format!(
+                "Inventory item '{}' already exists",
+                item.id
+            )));
+        }
+        self.items.insert(
+            item.id.clone(),
+            ItemStock {
+                item,
+                layers: VecDeque::new(),
+                quantity: BigDecimal::zero(),
+                value: BigDecimal::zero(),
+                movements: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Get an item by ID
+    pub fn get_item(&self, item_id: &str) -> Option<&InventoryItem> {
+        self.items.get(item_id).map(|stock| &stock.item)
+    }
+
+    /// All items, ordered by ID
+    pub fn items(&self) -> impl Iterator<Item = &InventoryItem> {
+        self.items.values().map(|stock| &stock.item)
+    }
+
+    /// Quantity currently on hand
+    pub fn quantity_on_hand(&self, item_id: &str) -> LedgerResult<BigDecimal> {
+        Ok(self.stock(item_id)?.quantity.clone())
+    }
+
+    /// Value of the stock currently on hand
+    pub fn value_on_hand(&self, item_id: &str) -> LedgerResult<BigDecimal> {
+        Ok(self.stock(item_id)?.value.clone())
+    }
+
+    /// Open FIFO cost layers for an item
+    pub fn cost_layers(&self, item_id: &str) -> LedgerResult<Vec<CostLayer>> {
+        Ok(self.stock(item_id)?.layers.iter().cloned().collect())
+    }
+
+    /// Movement history for an item, in the order recorded
+    pub fn movements(&self, item_id: &str) -> LedgerResult<&[StockMovement]> {
+        Ok(&self.stock(item_id)?.movements)
+    }
+
+    /// Quantity and value of an item as of a date
+    pub fn position_as_of(
+        &self,
+        item_id: &str,
+        as_of: NaiveDate,
+    ) -> LedgerResult<(BigDecimal, BigDecimal)> {
+        let stock = self.stock(item_id)?;
+        let mut quantity = BigDecimal::zero();
+        let mut value = BigDecimal::zero();
+        for movement in stock.movements.iter().filter(|m| m.date <= as_of) {
+            match movement.movement_type {
+                StockMovementType::Receipt => {
+                    quantity += &movement.quantity;
+                    value += &movement.total_cost;
+                }
+                StockMovementType::Issue => {
+                    quantity -= &movement.quantity;
+                    value -= &movement.total_cost;
+                }
+                StockMovementType::CostAdjustment => {
+                    value += &movement.total_cost;
+                }
+            }
+        }
+        Ok((quantity, value))
+    }
+
+    /// Record a stock receipt at the given unit cost
+    pub fn receive_stock(
+        &mut self,
+        item_id: &str,
+        date: NaiveDate,
+        quantity: BigDecimal,
+        unit_cost: BigDecimal,
+        reference: Option<String>,
+    ) -> LedgerResult<StockMovement> {
+        if !quantity.is_positive() {
+            return Err(LedgerError::Validation(
+                "Receipt quantity must be positive".to_string(),
+            ));
+        }
+        if unit_cost.is_negative() {
+            return Err(LedgerError::Validation(
+                "Unit cost cannot be negative".to_string(),
+            ));
+        }
+
+        let policy = self.amount_policy.clone();
+        let stock = self.stock_mut(item_id)?;
+        Self::ensure_in_order(stock, date)?;
+
+        let total_cost = policy.round(&(&quantity * &unit_cost));
+        if stock.item.valuation_method == ValuationMethod::Fifo {
+            stock.layers.push_back(CostLayer {
+                date,
+                quantity: quantity.clone(),
+                unit_cost: unit_cost.clone(),
+            });
+        }
+        stock.quantity += &quantity;
+        stock.value += &total_cost;
+
+        let movement = StockMovement {
+            item_id: item_id.to_string(),
+            date,
+            movement_type: StockMovementType::Receipt,
+            quantity,
+            unit_cost,
+            total_cost,
+            reference,
+        };
+        stock.movements.push(movement.clone());
+        Ok(movement)
+    }
+
+    /// Issue stock, costing it with the item's valuation method
+    pub fn issue_stock(
+        &mut self,
+        item_id: &str,
+        date: NaiveDate,
+        quantity: BigDecimal,
+        reference: Option<String>,
+    ) -> LedgerResult<StockMovement> {
+        if !quantity.is_positive() {
+            return Err(LedgerError::Validation(
+                "Issue quantity must be positive".to_string(),
+            ));
+        }
+
+        let policy = self.amount_policy.clone();
+        let stock = self.stock_mut(item_id)?;
+        Self::ensure_in_order(stock, date)?;
+        if quantity > stock.quantity {
+            return Err(LedgerError::Validation(format!(
+                "Insufficient stock of '{}': {} on hand, {} requested",
+                item_id, stock.quantity, quantity
+            )));
+        }
+
+        let total_cost = if quantity == stock.quantity {
+            // Clear out the item entirely so no rounding residue is left
+            stock.layers.clear();
+            stock.value.clone()
+        } else {
+            match stock.item.valuation_method {
+                ValuationMethod::Fifo => {
+                    policy.round(&Self::consume_layers(&mut stock.layers, &quantity))
+                }
+                ValuationMethod::WeightedAverage => {
+                    policy.round(&(&stock.value * &quantity / &stock.quantity))
+                }
+            }
+        };
+        stock.quantity -= &quantity;
+        stock.value -= &total_cost;
+
+        let movement = StockMovement {
+            item_id: item_id.to_string(),
+            date,
+            movement_type: StockMovementType::Issue,
+            unit_cost: policy.round(&(&total_cost / &quantity)),
+            quantity,
+            total_cost,
+            reference,
+        };
+        stock.movements.push(movement.clone());
+        Ok(movement)
+    }
+
+    /// Capitalise an additional cost (freight, duty, ...) into stock on hand
+    /// without changing its quantity
+    ///
+    /// For FIFO items the cost is spread over the open layers by quantity.
+    pub fn capitalize_cost(
+        &mut self,
+        item_id: &str,
+        date: NaiveDate,
+        amount: BigDecimal,
+        reference: Option<String>,
+    ) -> LedgerResult<StockMovement> {
+        let stock = self.stock_mut(item_id)?;
+        Self::ensure_in_order(stock, date)?;
+        if !stock.quantity.is_positive() {
+            return Err(LedgerError::Validation(format!(
+                "Cannot capitalise cost into '{}' with no stock on hand",
+                item_id
+            )));
+        }
+
+        if stock.item.valuation_method == ValuationMethod::Fifo {
+            let on_hand = stock.quantity.clone();
+            for layer in stock.layers.iter_mut() {
+                layer.unit_cost += &amount / &on_hand;
+            }
+        }
+        stock.value += &amount;
+
+        let movement = StockMovement {
+            item_id: item_id.to_string(),
+            date,
+            movement_type: StockMovementType::CostAdjustment,
+            quantity: BigDecimal::zero(),
+            unit_cost: BigDecimal::zero(),
+            total_cost: amount,
+            reference,
+        };
+        stock.movements.push(movement.clone());
+        Ok(movement)
+    }
+
+    fn consume_layers(layers: &mut VecDeque<CostLayer>, quantity: &BigDecimal) -> BigDecimal {
+        let mut remaining = quantity.clone();
+        let mut cost = BigDecimal::zero();
+        while remaining.is_positive() {
+            let Some(layer) = layers.front_mut() else {
+                break;
+            };
+            if layer.quantity <= remaining {
+                cost += &layer.quantity * &layer.unit_cost;
+                remaining -= &layer.quantity;
+                layers.pop_front();
+            } else {
+                cost += &remaining * &layer.unit_cost;
+                layer.quantity -= &remaining;
+                remaining = BigDecimal::zero();
+            }
+        }
+        cost
+    }
+
+    fn ensure_in_order(stock: &ItemStock, date: NaiveDate) -> LedgerResult<()> {
+        match stock.movements.last() {
+            Some(last) if date < last.date => Err(LedgerError::Validation(format!(
+                "Stock movement for '{}' on {} is earlier than the last movement on {}",
+                stock.item.id, date, last.date
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn stock(&self, item_id: &str) -> LedgerResult<&ItemStock> {
+        self.items
+            .get(item_id)
+            .ok_or_else(|| LedgerError::Validation(format!("Unknown inventory item '{}'", item_id)))
+    }
+
+    fn stock_mut(&mut self, item_id: &str) -> LedgerResult<&mut ItemStock> {
+        self.items
+            .get_mut(item_id)
+            .ok_or_else(|| LedgerError::Validation(format!("Unknown inventory item '{}'", item_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 4, day).unwrap()
+    }
+
+    fn inventory_with(method: ValuationMethod) -> Inventory {
+        let mut inventory = Inventory::new();
+        inventory
+            .add_item(InventoryItem::new(
+                "widget".to_string(),
+                "Widget".to_string(),
+                "pcs".to_string(),
+                method,
+                "1300".to_string(),
+                "5000".to_string(),
+            ))
+            .unwrap();
+        inventory
+            .receive_stock(
+                "widget",
+                date(1),
+                BigDecimal::from(10),
+                BigDecimal::from(100),
+                None,
+            )
+            .unwrap();
+        inventory
+            .receive_stock(
+                "widget",
+                date(5),
+                BigDecimal::from(10),
+                BigDecimal::from(130),
+                None,
+            )
+            .unwrap();
+        inventory
+    }
+
+    #[test]
+    fn test_fifo_issue_consumes_oldest_layers() {
+        let mut inventory = inventory_with(ValuationMethod::Fifo);
+        let issue = inventory
+            .issue_stock("widget", date(10), BigDecimal::from(15), None)
+            .unwrap();
+
+        // 10 @ 100 + 5 @ 130
+        assert_eq!(issue.total_cost, BigDecimal::from(1650));
+        assert_eq!(
+            inventory.value_on_hand("widget").unwrap(),
+            BigDecimal::from(650)
+        );
+        assert_eq!(inventory.cost_layers("widget").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_weighted_average_issue() {
+        let mut inventory = inventory_with(ValuationMethod::WeightedAverage);
+        let issue = inventory
+            .issue_stock("widget", date(10), BigDecimal::from(15), None)
+            .unwrap();
+
+        // Average cost is 115
+        assert_eq!(issue.total_cost, BigDecimal::from(1725));
+        assert_eq!(
+            inventory.value_on_hand("widget").unwrap(),
+            BigDecimal::from(575)
+        );
+    }
+
+    #[test]
+    fn test_costs_round_to_the_amount_policy() {
+        let mut inventory =
+            Inventory::with_amount_policy(AmountPolicy::new(0, AmountRounding::HalfUp));
+        inventory
+            .add_item(InventoryItem::new(
+                "widget".to_string(),
+                "Widget".to_string(),
+                "pcs".to_string(),
+                ValuationMethod::WeightedAverage,
+                "1300".to_string(),
+                "5000".to_string(),
+            ))
+            .unwrap();
+        let receipt = inventory
+            .receive_stock(
+                "widget",
+                date(1),
+                BigDecimal::from(3),
+                "10.5".parse().unwrap(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(receipt.total_cost, BigDecimal::from(32));
+        let issue = inventory
+            .issue_stock("widget", date(2), BigDecimal::from(2), None)
+            .unwrap();
+        assert_eq!(issue.total_cost, BigDecimal::from(21));
+        assert_eq!(issue.unit_cost, BigDecimal::from(11));
+    }
+
+    #[test]
+    fn test_issue_validation() {
+        let mut inventory = inventory_with(ValuationMethod::Fifo);
+        assert!(inventory
+            .issue_stock("widget", date(10), BigDecimal::from(25), None)
+            .is_err());
+        assert!(inventory
+            .issue_stock("widget", date(2), BigDecimal::from(1), None)
+            .is_err());
+
+        let (quantity, value) = inventory.position_as_of("widget", date(3)).unwrap();
+        assert_eq!(quantity, BigDecimal::from(10));
+        assert_eq!(value, BigDecimal::from(1000));
+    }
+}
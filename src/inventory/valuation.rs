@@ -0,0 +1,195 @@
+//! Cost of goods sold postings and stock valuation reporting
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::stock::*;
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::traits::*;
+use crate::types::*;
+
+/// Quantity of an item sold on a sales transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SaleLine {
+    pub item_id: String,
+    pub quantity: BigDecimal,
+}
+
+impl SaleLine {
+    pub fn new(item_id: String, quantity: BigDecimal) -> Self {
+        Self { item_id, quantity }
+    }
+}
+
+/// Build the COGS journal for costed stock issues
+///
+/// Debits each item's COGS account and credits its inventory account,
+/// combining lines that share the same pair of accounts.
+pub fn create_cogs_journal(
+    id: String,
+    date: NaiveDate,
+    description: String,
+    inventory: &Inventory,
+    issues: &[StockMovement],
+) -> LedgerResult<Transaction> {
+    let mut totals: BTreeMap<(String, String), BigDecimal> = BTreeMap::new();
+    for issue in issues {
+        let item = inventory.get_item(&issue.item_id).ok_or_else(|| {
+            LedgerError::Validation(format!("Unknown inventory item '{}'", issue.item_id))
+        })?;
+        *totals
+            .entry((
+                item.cogs_account_id.clone(),
+                item.inventory_account_id.clone(),
+            ))
+            .or_insert_with(BigDecimal::zero) += &issue.total_cost;
+    }
+
+    let mut builder = TransactionBuilder::new(id, date, description);
+    for ((cogs_account_id, inventory_account_id), amount) in totals {
+        if amount.is_zero() {
+            continue;
+        }
+        builder = builder.debit(cogs_account_id, amount.clone(), None).credit(
+            inventory_account_id,
+            amount,
+            None,
+        );
+    }
+    builder.build()
+}
+
+/// Valuation of a single item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StockValuationLine {
+    pub item_id: String,
+    pub item_name: String,
+    pub inventory_account_id: String,
+    pub quantity: BigDecimal,
+    pub value: BigDecimal,
+}
+
+/// Stock value compared with the balance of its inventory control account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StockControlReconciliation {
+    pub account_id: String,
+    pub stock_value: BigDecimal,
+    pub ledger_balance: BigDecimal,
+    /// Ledger balance minus stock value
+    pub difference: BigDecimal,
+}
+
+/// Stock valuation report reconciled to the inventory control accounts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StockValuationReport {
+    pub as_of_date: NaiveDate,
+    pub lines: Vec<StockValuationLine>,
+    pub total_value: BigDecimal,
+    pub control_accounts: Vec<StockControlReconciliation>,
+    pub is_reconciled: bool,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Record a sale, issue the sold stock and post the matching COGS journal
+    ///
+    /// Stock is only issued once both transactions are recorded. Returns the
+    /// COGS journal, whose ID is the sale ID suffixed with `-cogs`.
+    pub async fn record_sale_with_cogs(
+        &mut self,
+        inventory: &mut Inventory,
+        sale: Transaction,
+        lines: &[SaleLine],
+    ) -> LedgerResult<Transaction> {
+        let mut updated = inventory.clone();
+        let mut issues = Vec::new();
+        for line in lines {
+            issues.push(updated.issue_stock(
+                &line.item_id,
+                sale.date,
+                line.quantity.clone(),
+                Some(sale.id.clone()),
+            )?);
+        }
+
+        let mut cogs = create_cogs_journal(
+            format!("{}-cogs", sale.id),
+            sale.date,
+            format!("Cost of goods sold: {}", sale.description),
+            &updated,
+            &issues,
+        )?;
+        cogs.metadata
+            .insert("cogs_for".to_string(), sale.id.clone().into());
+
+        let sale_id = sale.id.clone();
+        self.record_transaction(sale).await?;
+        let cogs = match self.record_transaction(cogs).await {
+            Ok(cogs) => cogs,
+            Err(error) => {
+                // Not the authorized delete: recording a sale must not need
+                // the right to void transactions
+                self.transaction_manager
+                    .delete_transaction(&sale_id)
+                    .await?;
+                return Err(error);
+            }
+        };
+
+        *inventory = updated;
+        Ok(cogs)
+    }
+
+    /// Value stock on hand and reconcile it to the inventory control accounts
+    pub async fn generate_stock_valuation(
+        &self,
+        inventory: &Inventory,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<StockValuationReport> {
+        let mut lines = Vec::new();
+        let mut total_value = BigDecimal::zero();
+        let mut by_account: BTreeMap<String, BigDecimal> = BTreeMap::new();
+
+        for item in inventory.items() {
+            let (quantity, value) = inventory.position_as_of(&item.id, as_of_date)?;
+            total_value += &value;
+            *by_account
+                .entry(item.inventory_account_id.clone())
+                .or_insert_with(BigDecimal::zero) += &value;
+            lines.push(StockValuationLine {
+                item_id: item.id.clone(),
+                item_name: item.name.clone(),
+                inventory_account_id: item.inventory_account_id.clone(),
+                quantity,
+                value,
+            });
+        }
+
+        let mut control_accounts = Vec::new();
+        for (account_id, stock_value) in by_account {
+            let ledger_balance = self
+                .get_account_balance(&account_id, Some(as_of_date))
+                .await?;
+            control_accounts.push(StockControlReconciliation {
+                difference: &ledger_balance - &stock_value,
+                account_id,
+                stock_value,
+                ledger_balance,
+            });
+        }
+        let is_reconciled = control_accounts.iter().all(|c| c.difference.is_zero());
+
+        Ok(StockValuationReport {
+            as_of_date,
+            lines,
+            total_value,
+            control_accounts,
+            is_reconciled,
+        })
+    }
+}
@@ -1,7 +1,8 @@
 //! Account management functionality
 
-use bigdecimal::BigDecimal;
-use std::collections::HashMap;
+use bigdecimal::{BigDecimal, Zero};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use crate::traits::*;
 use crate::types::*;
@@ -10,6 +11,10 @@ use crate::types::*;
 pub struct AccountManager<S: LedgerStorage> {
     pub(crate) storage: S,
     validator: Box<dyn AccountValidator>,
+    clock: Arc<dyn Clock>,
+    /// Whether a child account may differ in type from its parent
+    allow_mixed_types: bool,
+    limits: HierarchyLimits,
 }
 
 impl<S: LedgerStorage> AccountManager<S> {
@@ -18,12 +23,130 @@ impl<S: LedgerStorage> AccountManager<S> {
         Self {
             storage,
             validator: Box::new(DefaultAccountValidator),
+            clock: Arc::new(SystemClock),
+            allow_mixed_types: false,
+            limits: HierarchyLimits::default(),
         }
     }
 
     /// Create a new account manager with custom validator
     pub fn with_validator(storage: S, validator: Box<dyn AccountValidator>) -> Self {
-        Self { storage, validator }
+        Self {
+            storage,
+            validator,
+            clock: Arc::new(SystemClock),
+            allow_mixed_types: false,
+            limits: HierarchyLimits::default(),
+        }
+    }
+
+    /// Use `clock` for account timestamps
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Allow child accounts of a different type than their parent, for
+    /// unusual charts
+    pub fn set_allow_mixed_types(&mut self, allow: bool) {
+        self.allow_mixed_types = allow;
+    }
+
+    /// Whether child accounts may differ in type from their parent
+    pub fn allows_mixed_types(&self) -> bool {
+        self.allow_mixed_types
+    }
+
+    /// Limit the depth and breadth of the chart; existing accounts are left
+    /// as they are
+    pub fn set_hierarchy_limits(&mut self, limits: HierarchyLimits) {
+        self.limits = limits;
+    }
+
+    /// Limits on the shape of the chart
+    pub fn hierarchy_limits(&self) -> HierarchyLimits {
+        self.limits
+    }
+
+    /// IDs from the top-level ancestor of an account down to the account
+    pub async fn account_path_ids(&self, account_id: &str) -> LedgerResult<Vec<String>> {
+        Ok(self
+            .account_path(account_id)
+            .await?
+            .into_iter()
+            .map(|account| account.id)
+            .collect())
+    }
+
+    /// Accounts from the top-level ancestor of an account down to the account
+    ///
+    /// The path is read from storage on every call, so it reflects changes
+    /// made through any handle on the same storage.
+    pub async fn account_path(&self, account_id: &str) -> LedgerResult<Vec<Account>> {
+        let mut path = Vec::new();
+        let mut seen = HashSet::new();
+        let mut next = Some(account_id.to_string());
+        while let Some(id) = next {
+            if !seen.insert(id.clone()) {
+                return Err(LedgerError::CircularHierarchy(id));
+            }
+            let account = self.get_account_required(&id).await?;
+            next = account.parent_id.clone();
+            path.push(account);
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Reject placing an account (and its subtree) under `parent_id` beyond
+    /// the hierarchy limits
+    async fn check_limits(&self, account_id: &str, parent_id: &str) -> LedgerResult<()> {
+        if self.limits == HierarchyLimits::default() {
+            return Ok(());
+        }
+        let accounts = self.storage.list_accounts(None).await?;
+        let siblings = accounts
+            .iter()
+            .filter(|a| a.parent_id.as_deref() == Some(parent_id) && a.id != account_id)
+            .count();
+        self.limit_children(parent_id, siblings)?;
+        let depth =
+            self.account_path_ids(parent_id).await?.len() + subtree_height(&accounts, account_id);
+        self.limit_depth(account_id, parent_id, depth)
+    }
+
+    /// Reject another child for a parent that already has `siblings`
+    fn limit_children(&self, parent_id: &str, siblings: usize) -> LedgerResult<()> {
+        match self.limits.max_children {
+            Some(max_children) if siblings >= max_children => {
+                Err(LedgerError::Validation(format!(
+                    "Account '{}' already has the maximum of {} child accounts",
+                    parent_id, max_children
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject a placement that nests the chart `depth` levels deep
+    fn limit_depth(&self, account_id: &str, parent_id: &str, depth: usize) -> LedgerResult<()> {
+        match self.limits.max_depth {
+            Some(max_depth) if depth > max_depth => Err(LedgerError::Validation(format!(
+                "Placing account '{}' under '{}' would nest the chart {} levels deep; the limit is {}",
+                account_id, parent_id, depth, max_depth
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject placing `account` under a parent of another type
+    fn check_parent_type(&self, account: &Account, parent: &Account) -> LedgerResult<()> {
+        if self.allow_mixed_types || parent.account_type == account.account_type {
+            return Ok(());
+        }
+        Err(LedgerError::Validation(format!(
+            "{:?} account '{}' cannot be placed under {:?} account '{}'",
+            account.account_type, account.id, parent.account_type, parent.id
+        )))
     }
 
     /// Create a new account
@@ -35,39 +158,217 @@ impl<S: LedgerStorage> AccountManager<S> {
         parent_id: Option<String>,
     ) -> LedgerResult<Account> {
         let account = Account::new(id, name, account_type, parent_id);
+        self.add_account(account).await
+    }
 
+    /// Create an account from a fully populated `Account`, e.g. one carrying
+    /// metadata that must pass validation on creation
+    ///
+    /// The account is stamped as created now by the ledger clock. It must
+    /// start at a zero balance; opening balances are posted as transactions.
+    pub async fn add_account(&mut self, mut account: Account) -> LedgerResult<Account> {
         // Validate the account
         self.validator.validate_account(&account)?;
-
-        // Check if account already exists
-        if let Some(_existing) = self.storage.get_account(&account.id).await? {
+        if !account.balance.is_zero() {
             return Err(LedgerError::Validation(format!(
-                "Account with ID '{}' already exists",
-                account.id
+                "Account {} cannot be created with a balance of {}; post an opening balance instead",
+                account.id, account.balance
             )));
         }
 
+        // Check if account already exists
+        if self.storage.get_account(&account.id).await?.is_some() {
+            return Err(LedgerError::DuplicateAccount(account.id.clone()));
+        }
+
         // Validate parent account exists if specified
         if let Some(ref parent_id) = account.parent_id {
-            if self.storage.get_account(parent_id).await?.is_none() {
-                return Err(LedgerError::Validation(format!(
-                    "Parent account '{}' does not exist",
-                    parent_id
-                )));
-            }
+            let parent = self
+                .storage
+                .get_account(parent_id)
+                .await?
+                .ok_or_else(|| LedgerError::ParentAccountNotFound(parent_id.clone()))?;
+            self.check_parent_type(&account, &parent)?;
+            self.check_limits(&account.id, parent_id).await?;
         }
 
         // Save the account
+        account.created_at = self.clock.now();
+        account.updated_at = account.created_at;
         self.storage.save_account(&account).await?;
 
         Ok(account)
     }
 
+    /// Create a batch of accounts, such as an imported chart
+    ///
+    /// The whole batch is checked before anything is saved, with parents
+    /// given anywhere in the batch resolved against each other. The accounts
+    /// are then saved parents first in one
+    /// [`save_accounts`](LedgerStorage::save_accounts) call and returned in
+    /// that order.
+    pub async fn create_accounts(&mut self, batch: Vec<NewAccount>) -> LedgerResult<Vec<Account>> {
+        let existing: HashMap<String, Account> = self
+            .storage
+            .list_accounts(None)
+            .await?
+            .into_iter()
+            .map(|account| (account.id.clone(), account))
+            .collect();
+        let mut batch_ids = HashSet::new();
+        for new in &batch {
+            if existing.contains_key(&new.id) || !batch_ids.insert(new.id.clone()) {
+                return Err(LedgerError::DuplicateAccount(new.id.clone()));
+            }
+        }
+
+        let mut child_counts: HashMap<String, usize> = HashMap::new();
+        for parent_id in existing.values().filter_map(|a| a.parent_id.clone()) {
+            *child_counts.entry(parent_id).or_default() += 1;
+        }
+        let mut depths: HashMap<String, usize> = HashMap::new();
+        let mut placed: HashMap<String, Account> = HashMap::new();
+        let mut ordered = Vec::new();
+        let now = self.clock.now();
+
+        // Place accounts whose parent is stored or already placed, a round
+        // at a time; a round placing nothing means the rest form a cycle
+        let mut pending = batch;
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<_>, Vec<_>) =
+                pending.into_iter().partition(|new| match &new.parent_id {
+                    Some(parent_id) => {
+                        !batch_ids.contains(parent_id) || placed.contains_key(parent_id)
+                    }
+                    None => true,
+                });
+            if ready.is_empty() {
+                return Err(LedgerError::CircularHierarchy(waiting[0].id.clone()));
+            }
+            for new in ready {
+                let account = new.into_account(now);
+                self.validator.validate_account(&account)?;
+                let mut depth = 1;
+                if let Some(parent_id) = &account.parent_id {
+                    let parent = placed
+                        .get(parent_id)
+                        .or_else(|| existing.get(parent_id))
+                        .ok_or_else(|| LedgerError::ParentAccountNotFound(parent_id.clone()))?;
+                    self.check_parent_type(&account, parent)?;
+                    let siblings = child_counts.entry(parent_id.clone()).or_default();
+                    self.limit_children(parent_id, *siblings)?;
+                    *siblings += 1;
+                    depth += match depths.get(parent_id) {
+                        Some(parent_depth) => *parent_depth,
+                        None => self.account_path_ids(parent_id).await?.len(),
+                    };
+                    self.limit_depth(&account.id, parent_id, depth)?;
+                }
+                depths.insert(account.id.clone(), depth);
+                placed.insert(account.id.clone(), account.clone());
+                ordered.push(account);
+            }
+            pending = waiting;
+        }
+
+        self.storage.save_accounts(&ordered).await?;
+        Ok(ordered)
+    }
+
     /// Get an account by ID
     pub async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
         self.storage.get_account(account_id).await
     }
 
+    /// Find an account by code, name or alias
+    ///
+    /// Fails with [`LedgerError::Validation`] when the text matches several
+    /// accounts equally well.
+    pub async fn find_account(&self, by: &AccountLookup) -> LedgerResult<Option<Account>> {
+        let query = by.text().trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(None);
+        }
+        let mut accounts = self.storage.list_accounts(None).await?;
+        accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        if let AccountLookup::Any(_) = by {
+            if let Some(account) = accounts.iter().find(|a| a.id.to_lowercase() == query) {
+                return Ok(Some(account.clone()));
+            }
+        }
+
+        let matching = |prefix: bool| -> Vec<&Account> {
+            accounts
+                .iter()
+                .filter(|account| {
+                    by.keys(account).iter().any(|key| {
+                        let key = key.to_lowercase();
+                        if prefix {
+                            key.starts_with(&query)
+                        } else {
+                            key == query
+                        }
+                    })
+                })
+                .collect()
+        };
+        let mut found = matching(false);
+        if found.is_empty() {
+            found = matching(true);
+        }
+        match found.as_slice() {
+            [] => Ok(None),
+            [account] => Ok(Some((*account).clone())),
+            several => Err(LedgerError::Validation(format!(
+                "'{}' matches several accounts: {}",
+                by.text(),
+                several
+                    .iter()
+                    .map(|a| a.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Let an account be found by `alias`
+    ///
+    /// An alias may not repeat another account's code or alias.
+    pub async fn add_alias(&mut self, account_id: &str, alias: &str) -> LedgerResult<Account> {
+        let alias = alias.trim();
+        if alias.is_empty() {
+            return Err(LedgerError::Validation("Alias cannot be empty".to_string()));
+        }
+        let lowered = alias.to_lowercase();
+        let mut account = self.get_account_required(account_id).await?;
+        for other in self.storage.list_accounts(None).await? {
+            let taken = other.id.to_lowercase() == lowered
+                || other.aliases.iter().any(|a| a.to_lowercase() == lowered);
+            if taken && other.id != account.id {
+                return Err(LedgerError::Validation(format!(
+                    "Alias '{}' already refers to account '{}'",
+                    alias, other.id
+                )));
+            }
+        }
+        if !account.aliases.iter().any(|a| a.to_lowercase() == lowered) {
+            account.aliases.push(alias.to_string());
+            account.updated_at = self.clock.now();
+            self.storage.update_account(&account).await?;
+        }
+        Ok(account)
+    }
+
+    /// Stop an account being found by `alias`
+    pub async fn remove_alias(&mut self, account_id: &str, alias: &str) -> LedgerResult<Account> {
+        let mut account = self.get_account_required(account_id).await?;
+        let lowered = alias.trim().to_lowercase();
+        account.aliases.retain(|a| a.to_lowercase() != lowered);
+        account.updated_at = self.clock.now();
+        self.storage.update_account(&account).await?;
+        Ok(account)
+    }
+
     /// Get an account by ID, returning an error if not found
     pub async fn get_account_required(&self, account_id: &str) -> LedgerResult<Account> {
         self.storage
@@ -81,6 +382,17 @@ impl<S: LedgerStorage> AccountManager<S> {
         self.storage.list_accounts(None).await
     }
 
+    /// List accounts that have not been archived
+    pub async fn list_active_accounts(&self) -> LedgerResult<Vec<Account>> {
+        Ok(self
+            .storage
+            .list_accounts(None)
+            .await?
+            .into_iter()
+            .filter(|account| account.is_active())
+            .collect())
+    }
+
     /// List accounts by type
     pub async fn list_accounts_by_type(
         &self,
@@ -94,25 +406,147 @@ impl<S: LedgerStorage> AccountManager<S> {
         // Validate the account
         self.validator.validate_account(account)?;
 
-        // Ensure the account exists
-        if self.storage.get_account(&account.id).await?.is_none() {
-            return Err(LedgerError::AccountNotFound(account.id.clone()));
+        let existing = self.get_account_required(&account.id).await?;
+        if let Some(ref parent_id) = account.parent_id {
+            let parent = self
+                .storage
+                .get_account(parent_id)
+                .await?
+                .ok_or_else(|| LedgerError::ParentAccountNotFound(parent_id.clone()))?;
+            self.check_parent_type(account, &parent)?;
+            if existing.parent_id.as_ref() != Some(parent_id) {
+                self.check_cycle(&account.id, parent_id).await?;
+                self.check_limits(&account.id, parent_id).await?;
+            }
+        }
+        if existing.account_type != account.account_type {
+            for child in self.storage.list_accounts(None).await? {
+                if child.parent_id.as_deref() == Some(account.id.as_str()) {
+                    self.check_parent_type(&child, account)?;
+                }
+            }
         }
 
-        self.storage.update_account(account).await
+        self.storage.update_account(account).await?;
+        Ok(())
     }
 
-    /// Delete an account
+    /// Delete an account once the validator accepts its usage
     pub async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
-        // Validate deletion
-        self.validator.validate_account_deletion(account_id)?;
+        let account = self.get_account_required(account_id).await?;
+        let usage = self.account_usage(&account).await?;
+        self.validator.validate_account_deletion(&account, &usage)?;
+        self.storage.delete_account(account_id).await?;
+        Ok(())
+    }
 
-        // Ensure the account exists
-        if self.storage.get_account(account_id).await?.is_none() {
-            return Err(LedgerError::AccountNotFound(account_id.to_string()));
+    /// Gather what refers to an account from storage
+    pub async fn account_usage(&self, account: &Account) -> LedgerResult<AccountUsage> {
+        // Book entries are not indexed by account, so every transaction is
+        // checked
+        let transaction_count = self
+            .storage
+            .get_transactions(None, None)
+            .await?
+            .iter()
+            .filter(|t| t.all_entries().any(|e| e.account_id == account.id))
+            .count();
+        let child_ids = self
+            .storage
+            .list_accounts(None)
+            .await?
+            .into_iter()
+            .filter(|a| a.parent_id.as_deref() == Some(account.id.as_str()))
+            .map(|a| a.id)
+            .collect();
+        Ok(AccountUsage {
+            transaction_count,
+            balance: account.balance.clone(),
+            child_ids,
+        })
+    }
+
+    /// Flag whether an account's balance may go below zero
+    pub async fn set_non_negative(
+        &mut self,
+        account_id: &str,
+        non_negative: bool,
+    ) -> LedgerResult<Account> {
+        let mut account = self.get_account_required(account_id).await?;
+        account.non_negative = non_negative;
+        account.updated_at = self.clock.now();
+        self.storage.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Archive an account so it accepts no new postings
+    ///
+    /// The account and its history are kept for reporting. Only accounts with
+    /// a zero balance can be archived.
+    pub async fn archive_account(&mut self, account_id: &str) -> LedgerResult<Account> {
+        let mut account = self.get_account_required(account_id).await?;
+        if !account.is_active() {
+            return Err(LedgerError::AccountArchived(account_id.to_string()));
+        }
+        if !account.balance.is_zero() {
+            return Err(LedgerError::Validation(format!(
+                "Account '{}' has a balance of {} and cannot be archived",
+                account_id, account.balance
+            )));
+        }
+
+        let now = self.clock.now();
+        account.archived_at = Some(now);
+        account.updated_at = now;
+        self.storage.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Reopen an archived account for postings
+    pub async fn restore_account(&mut self, account_id: &str) -> LedgerResult<Account> {
+        let mut account = self.get_account_required(account_id).await?;
+        account.archived_at = None;
+        account.updated_at = self.clock.now();
+        self.storage.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Move an account under a new parent, or to the top level with `None`
+    pub async fn move_account(
+        &mut self,
+        account_id: &str,
+        new_parent_id: Option<&str>,
+    ) -> LedgerResult<Account> {
+        let mut account = self.get_account_required(account_id).await?;
+
+        if let Some(parent_id) = new_parent_id {
+            let parent = self
+                .storage
+                .get_account(parent_id)
+                .await?
+                .ok_or_else(|| LedgerError::ParentAccountNotFound(parent_id.to_string()))?;
+            self.check_parent_type(&account, &parent)?;
+            self.check_cycle(account_id, parent_id).await?;
+            self.check_limits(account_id, parent_id).await?;
         }
 
-        self.storage.delete_account(account_id).await
+        account.parent_id = new_parent_id.map(str::to_string);
+        account.updated_at = self.clock.now();
+        self.storage.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Reject a parent that is the account itself or one of its descendants
+    async fn check_cycle(&self, account_id: &str, parent_id: &str) -> LedgerResult<()> {
+        if self
+            .account_path_ids(parent_id)
+            .await?
+            .iter()
+            .any(|id| id == account_id)
+        {
+            return Err(LedgerError::CircularHierarchy(account_id.to_string()));
+        }
+        Ok(())
     }
 
     /// Get account balance
@@ -127,6 +561,23 @@ impl<S: LedgerStorage> AccountManager<S> {
     }
 }
 
+/// Levels in the subtree rooted at `account_id`, counting the account itself
+fn subtree_height(accounts: &[Account], account_id: &str) -> usize {
+    let mut seen = HashSet::from([account_id.to_string()]);
+    let mut level = vec![account_id.to_string()];
+    let mut height = 0;
+    while !level.is_empty() {
+        height += 1;
+        level = accounts
+            .iter()
+            .filter(|a| a.parent_id.as_ref().is_some_and(|p| level.contains(p)))
+            .filter(|a| seen.insert(a.id.clone()))
+            .map(|a| a.id.clone())
+            .collect();
+    }
+    height
+}
+
 /// Chart of accounts implementation
 pub struct StandardChartOfAccounts<S: LedgerStorage> {
     account_manager: AccountManager<S>,
@@ -160,25 +611,24 @@ impl<S: LedgerStorage> ChartOfAccounts for StandardChartOfAccounts<S> {
     }
 
     async fn get_account_path(&self, account_id: &str) -> LedgerResult<Vec<Account>> {
-        let mut path = Vec::new();
-        let mut current_account_id = Some(account_id.to_string());
-
-        while let Some(id) = current_account_id {
-            match self.account_manager.get_account(&id).await? {
-                Some(account) => {
-                    current_account_id = account.parent_id.clone();
-                    path.insert(0, account);
-                }
-                None => {
-                    return Err(LedgerError::AccountNotFound(id));
-                }
-            }
-        }
+        self.account_manager.account_path(account_id).await
+    }
 
-        Ok(path)
+    async fn move_account(
+        &mut self,
+        account_id: &str,
+        new_parent_id: Option<&str>,
+    ) -> LedgerResult<Account> {
+        self.account_manager
+            .move_account(account_id, new_parent_id)
+            .await
     }
 }
 
+/// Account metadata key naming the standard chart template an account was
+/// created from, such as `cash` or `rent_expense`
+pub const STANDARD_ACCOUNT_KEY: &str = "standard_account";
+
 /// Utility functions for working with accounts
 pub mod utils {
     use super::*;
@@ -314,6 +764,272 @@ pub mod utils {
             .await?;
         accounts.insert("utilities_expense".to_string(), utilities_expense);
 
+        for (template, account) in accounts.iter_mut() {
+            account
+                .metadata
+                .insert(STANDARD_ACCOUNT_KEY.to_string(), template.as_str().into());
+            account_manager.update_account(account).await?;
+        }
+
         Ok(accounts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_move_account_rejects_cycles_and_type_mismatch() {
+        let mut chart = StandardChartOfAccounts::new(MemoryStorage::new());
+        let manager = &mut chart.account_manager;
+        for (id, parent) in [
+            ("assets", None),
+            ("current", Some("assets")),
+            ("cash", None),
+        ] {
+            manager
+                .create_account(
+                    id.to_string(),
+                    id.to_string(),
+                    AccountType::Asset,
+                    parent.map(str::to_string),
+                )
+                .await
+                .unwrap();
+        }
+        manager
+            .create_account(
+                "capital".to_string(),
+                "Capital".to_string(),
+                AccountType::Equity,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let moved = chart.move_account("cash", Some("current")).await.unwrap();
+        assert_eq!(moved.parent_id.as_deref(), Some("current"));
+        let path: Vec<String> = chart
+            .get_account_path("cash")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(path, vec!["assets", "current", "cash"]);
+
+        assert!(matches!(
+            chart.move_account("assets", Some("cash")).await,
+            Err(LedgerError::CircularHierarchy(_))
+        ));
+        assert!(matches!(
+            chart.move_account("assets", Some("assets")).await,
+            Err(LedgerError::CircularHierarchy(_))
+        ));
+        assert!(chart.move_account("cash", Some("capital")).await.is_err());
+
+        chart.move_account("cash", None).await.unwrap();
+        assert_eq!(chart.get_account_path("cash").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_child_type_must_match_parent() {
+        let mut manager = AccountManager::new(MemoryStorage::new());
+        let account = |id: &str, account_type, parent: Option<&str>| {
+            Account::new(
+                id.to_string(),
+                id.to_string(),
+                account_type,
+                parent.map(str::to_string),
+            )
+        };
+        manager
+            .add_account(account("current", AccountType::Asset, None))
+            .await
+            .unwrap();
+        manager
+            .add_account(account("cash", AccountType::Asset, Some("current")))
+            .await
+            .unwrap();
+
+        // A preset balance would bypass the journal
+        let mut funded = account("bank", AccountType::Asset, Some("current"));
+        funded.balance = BigDecimal::from(500);
+        assert!(matches!(
+            manager.add_account(funded).await,
+            Err(LedgerError::Validation(_))
+        ));
+        assert!(manager.get_account("bank").await.unwrap().is_none());
+
+        // An expense under current assets is refused on create and update
+        assert!(matches!(
+            manager
+                .add_account(account("rent", AccountType::Expense, Some("current")))
+                .await,
+            Err(LedgerError::Validation(_))
+        ));
+        let mut cash = manager.get_account_required("cash").await.unwrap();
+        cash.account_type = AccountType::Expense;
+        assert!(manager.update_account(&cash).await.is_err());
+        // Retyping a parent away from its children is refused too
+        let mut current = manager.get_account_required("current").await.unwrap();
+        current.account_type = AccountType::Expense;
+        assert!(manager.update_account(&current).await.is_err());
+
+        manager.set_allow_mixed_types(true);
+        manager
+            .add_account(account("rent", AccountType::Expense, Some("current")))
+            .await
+            .unwrap();
+        manager.update_account(&cash).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hierarchy_limits_and_paths() {
+        let mut manager = AccountManager::new(MemoryStorage::new());
+        manager.set_hierarchy_limits(HierarchyLimits {
+            max_depth: Some(3),
+            max_children: Some(2),
+        });
+        for (id, parent) in [
+            ("assets", None),
+            ("current", Some("assets")),
+            ("cash", Some("current")),
+            ("fixed", Some("assets")),
+            ("bank", None),
+            ("branch", Some("bank")),
+        ] {
+            manager
+                .create_account(
+                    id.to_string(),
+                    id.to_string(),
+                    AccountType::Asset,
+                    parent.map(str::to_string),
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            manager.account_path_ids("cash").await.unwrap(),
+            vec!["assets", "current", "cash"]
+        );
+
+        // A fourth level, a third child, or a two-level subtree moved to the
+        // third level are all refused
+        let petty = Account::new(
+            "petty".to_string(),
+            "Petty".to_string(),
+            AccountType::Asset,
+            Some("cash".to_string()),
+        );
+        assert!(manager.add_account(petty).await.is_err());
+        assert!(manager.move_account("bank", Some("assets")).await.is_err());
+        assert!(manager.move_account("bank", Some("current")).await.is_err());
+
+        // Paths follow re-parenting
+        manager
+            .move_account("current", Some("fixed"))
+            .await
+            .unwrap_err();
+        manager.move_account("current", None).await.unwrap();
+        assert_eq!(
+            manager.account_path_ids("cash").await.unwrap(),
+            vec!["current", "cash"]
+        );
+        let path: Vec<String> = manager
+            .account_path("cash")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(path, vec!["current", "cash"]);
+
+        // A move made through another handle on the same storage is seen
+        // when checking for cycles
+        let mut other = AccountManager::new(manager.storage.clone());
+        other.move_account("current", Some("assets")).await.unwrap();
+        assert_eq!(
+            manager.account_path_ids("cash").await.unwrap(),
+            vec!["assets", "current", "cash"]
+        );
+        assert!(matches!(
+            manager.move_account("assets", Some("cash")).await,
+            Err(LedgerError::CircularHierarchy(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_find_account_by_code_name_or_alias() {
+        let mut manager = AccountManager::new(MemoryStorage::new());
+        utils::create_standard_chart(&mut manager).await.unwrap();
+        manager.add_alias("1000", "Petty Cash").await.unwrap();
+        assert!(manager.add_alias("1200", "petty cash").await.is_err());
+        assert!(manager.add_alias("1200", "1000").await.is_err());
+
+        let find = |by| {
+            let manager = &manager;
+            async move { manager.find_account(&by).await.map(|a| a.map(|a| a.id)) }
+        };
+        let cash = Some("1000".to_string());
+        assert_eq!(
+            find(AccountLookup::Code("1000".into())).await.unwrap(),
+            cash
+        );
+        assert_eq!(
+            find(AccountLookup::Name("CASH".into())).await.unwrap(),
+            cash
+        );
+        assert_eq!(
+            find(AccountLookup::Alias("petty".into())).await.unwrap(),
+            cash
+        );
+        assert_eq!(find(AccountLookup::Any("1000".into())).await.unwrap(), cash);
+        assert_eq!(find(AccountLookup::Any("cash".into())).await.unwrap(), cash);
+        assert_eq!(
+            find(AccountLookup::Name("Bogus".into())).await.unwrap(),
+            None
+        );
+        // "6" starts both rent (6000) and utilities (6100)
+        assert!(find(AccountLookup::Code("6".into())).await.is_err());
+
+        let cash = manager.remove_alias("1000", "PETTY CASH").await.unwrap();
+        assert!(cash.aliases.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_tree_rolls_up_balances() {
+        let storage = MemoryStorage::new();
+        let mut chart = StandardChartOfAccounts::new(storage.clone());
+        for (id, parent, balance) in [
+            ("assets", None, 0),
+            ("current", Some("assets"), 0),
+            ("cash", Some("current"), 500),
+            ("bank", Some("current"), 1500),
+            ("fixed", Some("assets"), 3000),
+        ] {
+            let mut account = Account::new(
+                id.to_string(),
+                id.to_string(),
+                AccountType::Asset,
+                parent.map(str::to_string),
+            );
+            account.balance = BigDecimal::from(balance);
+            chart.add_account(account).await.unwrap();
+        }
+
+        let tree = chart.get_tree().await.unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].rolled_up_balance, BigDecimal::from(5000));
+        let current = tree[0].find("current").unwrap();
+        assert_eq!(current.rolled_up_balance, BigDecimal::from(2000));
+        let children: Vec<&str> = current
+            .children
+            .iter()
+            .map(|c| c.account.id.as_str())
+            .collect();
+        assert_eq!(children, vec!["bank", "cash"]);
+    }
+}
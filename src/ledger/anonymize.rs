@@ -0,0 +1,249 @@
+//! Anonymized snapshots for sharing reproduction cases
+//!
+//! [`export_anonymized_snapshot`](Ledger::export_anonymized_snapshot) writes
+//! the same document as [`export_snapshot`](Ledger::export_snapshot), valid
+//! for [`import_snapshot`](Ledger::import_snapshot), with free text and party
+//! identities replaced by pseudonyms. IDs, amounts, dates, account types and
+//! balances are untouched, so the dump reproduces the same reports.
+//!
+//! Pseudonyms are numbered in order of first appearance and are consistent
+//! within a dump: two entries for the same party, or two transactions with
+//! the same description, still match after anonymizing. They carry nothing
+//! derived from the original text, so they cannot be reversed.
+//!
+//! Replaced:
+//!
+//! - transaction descriptions, references, tags, notes and attachments
+//! - entry descriptions and [`PARTY_DIMENSION`] values, in every book
+//! - the users who submitted, approved or rejected a transaction, with the
+//!   same pseudonyms as note authors
+//! - counterparty names and cheque banks in transaction metadata
+//! - names and aliases of accounts not created from the standard chart
+//! - project names and customers, template names and descriptions, and the
+//!   parties of payment terms
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use crate::ledger::backup::write_snapshot;
+use crate::ledger::cheque::CHEQUE_BANK_KEY;
+use crate::ledger::{
+    Ledger, SnapshotPayload, SnapshotSummary, PARTY_DIMENSION, STANDARD_ACCOUNT_KEY,
+};
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata keys whose values name a counterparty
+const NAME_KEYS: [&str; 2] = ["counterparty", CHEQUE_BANK_KEY];
+
+/// Transaction metadata keys whose values are user ids
+const USER_KEYS: [&str; 3] = ["submitted_by", "approved_by", "rejected_by"];
+
+/// Pseudonyms handed out so far, by kind and original text
+#[derive(Default)]
+struct Pseudonyms {
+    assigned: HashMap<(&'static str, String), String>,
+    counts: HashMap<&'static str, usize>,
+}
+
+impl Pseudonyms {
+    /// Pseudonym for `original`, formed from `kind` and a counter; empty
+    /// text stays empty
+    fn get(&mut self, kind: &'static str, original: &str) -> String {
+        if original.is_empty() {
+            return String::new();
+        }
+        let counts = &mut self.counts;
+        self.assigned
+            .entry((kind, original.to_string()))
+            .or_insert_with(|| {
+                let count = counts.entry(kind).or_default();
+                *count += 1;
+                format!("{} {}", kind, count)
+            })
+            .clone()
+    }
+
+    fn replace(&mut self, kind: &'static str, text: &mut String) {
+        *text = self.get(kind, text);
+    }
+
+    fn replace_option(&mut self, kind: &'static str, text: &mut Option<String>) {
+        if let Some(text) = text {
+            self.replace(kind, text);
+        }
+    }
+
+    fn anonymize(&mut self, payload: &mut SnapshotPayload) {
+        for account in &mut payload.accounts {
+            if !account.metadata.contains_key(STANDARD_ACCOUNT_KEY) {
+                self.replace("Account", &mut account.name);
+                for alias in &mut account.aliases {
+                    self.replace("Alias", alias);
+                }
+            }
+        }
+        for transaction in &mut payload.transactions {
+            self.anonymize_transaction(transaction);
+        }
+
+        let settings = &mut payload.settings;
+        for template in &mut settings.templates {
+            self.replace("Template", &mut template.name);
+            self.replace("Description", &mut template.description);
+            template.tags = template.tags.iter().map(|t| self.get("tag", t)).collect();
+            for line in &mut template.lines {
+                self.replace_option("Description", &mut line.description);
+            }
+        }
+        for project in &mut settings.projects {
+            self.replace("Project", &mut project.name);
+            self.replace_option("party", &mut project.customer_id);
+        }
+        settings.party_payment_terms = std::mem::take(&mut settings.party_payment_terms)
+            .into_iter()
+            .map(|(party, terms)| (self.get("party", &party), terms))
+            .collect::<BTreeMap<_, _>>();
+        settings.gst_registration = None;
+    }
+
+    fn anonymize_transaction(&mut self, transaction: &mut Transaction) {
+        self.replace("Description", &mut transaction.description);
+        self.replace_option("REF", &mut transaction.reference);
+        transaction.tags = transaction
+            .tags
+            .iter()
+            .map(|tag| self.get("tag", tag))
+            .collect();
+        for key in NAME_KEYS {
+            if let Some(MetaValue::Text(name)) = transaction.metadata.get_mut(key) {
+                self.replace("Name", name);
+            }
+        }
+        for key in USER_KEYS {
+            if let Some(MetaValue::Text(user)) = transaction.metadata.get_mut(key) {
+                self.replace("user", user);
+            }
+        }
+        for note in &mut transaction.notes {
+            self.replace("Note", &mut note.text);
+            self.replace_option("user", &mut note.author);
+        }
+        for attachment in &mut transaction.attachments {
+            self.replace("file", &mut attachment.filename);
+            self.replace("uri", &mut attachment.storage_uri);
+        }
+        let book_entries = transaction.book_entries.values_mut().flatten();
+        for entry in transaction.entries.iter_mut().chain(book_entries) {
+            self.replace_option("Description", &mut entry.description);
+            if let Some(party) = entry.dimensions.get_mut(PARTY_DIMENSION) {
+                self.replace("party", party);
+            }
+        }
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Write a snapshot with client-identifying text pseudonymized, for
+    /// sharing when reporting a bug
+    pub async fn export_anonymized_snapshot<W: Write>(
+        &self,
+        writer: W,
+    ) -> LedgerResult<SnapshotSummary> {
+        let mut payload = self.snapshot_payload().await?;
+        Pseudonyms::default().anonymize(&mut payload);
+        write_snapshot(&payload, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_anonymized_snapshot() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "1210".to_string(),
+                "Acme Traders Receivable".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        ledger.set_approval_threshold(Some(BigDecimal::from(500)));
+        ledger.set_actor(Some(Actor::new("jane.doe".to_string(), vec![])));
+        for (id, amount) in [("S1", 1000), ("S2", 250)] {
+            let mut transaction =
+                TransactionBuilder::new(id.to_string(), date, "Sale to Acme Traders".to_string())
+                    .reference(format!("INV-ACME-{}", id))
+                    .entry(
+                        Entry::debit("1210".to_string(), BigDecimal::from(amount), None)
+                            .with_dimension(PARTY_DIMENSION.to_string(), "acme".to_string()),
+                    )
+                    .credit("4000".to_string(), BigDecimal::from(amount), None)
+                    .build()
+                    .unwrap();
+            transaction.set_book_entries(
+                "tax",
+                vec![
+                    Entry::debit(
+                        "1210".to_string(),
+                        BigDecimal::from(amount),
+                        Some("Acme Traders".to_string()),
+                    )
+                    .with_dimension(PARTY_DIMENSION.to_string(), "acme".to_string()),
+                    Entry::credit("4000".to_string(), BigDecimal::from(amount), None),
+                ],
+            );
+            ledger.record_transaction(transaction).await.unwrap();
+        }
+        ledger.set_actor(Some(Actor::new("john.roe".to_string(), vec![])));
+        ledger.approve_transaction("S1", None).await.unwrap();
+
+        let mut file = Vec::new();
+        ledger.export_anonymized_snapshot(&mut file).await.unwrap();
+        let text = String::from_utf8(file.clone()).unwrap();
+        assert!(!text.to_lowercase().contains("acme"));
+        assert!(!text.contains("jane.doe") && !text.contains("john.roe"));
+
+        let mut copy = Ledger::new(MemoryStorage::new());
+        copy.import_snapshot(file.as_slice()).await.unwrap();
+        let s1 = copy.get_transaction("S1").await.unwrap().unwrap();
+        let s2 = copy.get_transaction("S2").await.unwrap().unwrap();
+        assert_eq!(s1.description, "Description 1");
+        assert_eq!(s2.description, s1.description);
+        assert_eq!(s1.reference.as_deref(), Some("REF 1"));
+        assert_eq!(s2.reference.as_deref(), Some("REF 2"));
+        assert_eq!(s1.entries[0].dimensions[PARTY_DIMENSION], "party 1");
+        assert_eq!(s2.entries[0].dimensions[PARTY_DIMENSION], "party 1");
+        assert_eq!(
+            s1.entries_for_book("tax")[0].dimensions[PARTY_DIMENSION],
+            "party 1"
+        );
+        assert_eq!(s1.metadata["submitted_by"], "user 1");
+        assert_eq!(s1.metadata["approved_by"], "user 2");
+        assert_eq!(
+            copy.get_account("1210").await.unwrap().unwrap().name,
+            "Account 1"
+        );
+        assert_eq!(
+            copy.get_account("4000").await.unwrap().unwrap().name,
+            ledger.get_account("4000").await.unwrap().unwrap().name
+        );
+        let copied = copy.get_trial_balance(date).await.unwrap();
+        let original = ledger.get_trial_balance(date).await.unwrap();
+        assert_eq!(copied.total_debits, original.total_debits);
+        assert_eq!(
+            copied.balances["1210"].debit_balance,
+            original.balances["1210"].debit_balance
+        );
+    }
+}
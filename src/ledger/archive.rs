@@ -0,0 +1,410 @@
+//! Archiving old transactions
+//!
+//! [`archive_period`](Ledger::archive_period) is the sanctioned way to trim a
+//! ledger. Every transaction dated before a cutoff is written to an
+//! [`ArchiveSink`] and then removed, and a single opening balance transaction
+//! dated the day before the cutoff carries each account's balance forward.
+//! Balances and the trial balance on any date from then on are unchanged;
+//! entry-level detail before the cutoff, including dimensions, lives only in
+//! the archive.
+//!
+//! The archived period must be locked first, so the figures carried forward
+//! are final, and its drafts and transactions awaiting approval must be
+//! posted, discarded or rejected. Balances in other books are carried
+//! forward as the opening transaction's book entries. The opening balance transaction is the record of the archival:
+//! its metadata holds the cutoff, the number of transactions archived and
+//! the archive checksum, and [`list_archives`](Ledger::list_archives) reads
+//! them back.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use crate::ledger::backup::checksum;
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key holding the cutoff of an archival
+pub const ARCHIVED_BEFORE_KEY: &str = "archived_before";
+/// Transaction metadata key holding the number of transactions archived
+pub const ARCHIVED_COUNT_KEY: &str = "archived_transactions";
+/// Transaction metadata key holding the archive checksum
+pub const ARCHIVE_CHECKSUM_KEY: &str = "archive_checksum";
+
+/// Transactions moved out of the ledger by one archival
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeriodArchive {
+    /// Transactions dated before this were archived
+    pub before: NaiveDate,
+    /// When the archive was taken
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub archived_at: DateTime<Utc>,
+    /// Chart of accounts at the time of archiving
+    pub accounts: Vec<Account>,
+    /// Archived transactions of every status, ordered by date
+    pub transactions: Vec<Transaction>,
+    /// Transaction left in the ledger in their place
+    pub opening_balances: Transaction,
+}
+
+/// Summary of an archival, as recorded on its opening balance transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ArchiveRecord {
+    pub before: NaiveDate,
+    pub transaction_count: usize,
+    pub opening_transaction_id: String,
+    /// `sha256:` followed by the hex digest of the archived transactions
+    pub checksum: String,
+}
+
+impl ArchiveRecord {
+    fn from_transaction(transaction: &Transaction) -> Option<Self> {
+        let metadata = &transaction.metadata;
+        Some(Self {
+            before: metadata.get(ARCHIVED_BEFORE_KEY)?.as_date()?,
+            transaction_count: usize::try_from(metadata.get(ARCHIVED_COUNT_KEY)?.as_i64()?).ok()?,
+            opening_transaction_id: transaction.id.clone(),
+            checksum: metadata.get(ARCHIVE_CHECKSUM_KEY)?.as_str()?.to_string(),
+        })
+    }
+}
+
+/// Sink writing each archive as a JSON document, e.g. to a file
+pub struct JsonArchiveSink<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> JsonArchiveSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Get the writer back
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[async_trait::async_trait]
+impl<W: Write + Send> ArchiveSink for JsonArchiveSink<W> {
+    async fn write_archive(&mut self, archive: &PeriodArchive) -> LedgerResult<()> {
+        serde_json::to_writer(&mut self.writer, archive)
+            .map_err(|e| LedgerError::storage_with_source("Cannot write archive", false, e))?;
+        self.writer
+            .flush()
+            .map_err(|e| LedgerError::storage_with_source("Cannot write archive", true, e))
+    }
+}
+
+/// Sink copying archived transactions into another storage backend
+///
+/// Accounts missing from the target are copied too, so the archived
+/// transactions can be read back with a ledger over that storage.
+pub struct StorageArchiveSink<T: LedgerStorage> {
+    storage: T,
+}
+
+impl<T: LedgerStorage> StorageArchiveSink<T> {
+    pub fn new(storage: T) -> Self {
+        Self { storage }
+    }
+
+    /// Get the storage back
+    pub fn into_inner(self) -> T {
+        self.storage
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: LedgerStorage> ArchiveSink for StorageArchiveSink<T> {
+    async fn write_archive(&mut self, archive: &PeriodArchive) -> LedgerResult<()> {
+        for account in &archive.accounts {
+            if self.storage.get_account(&account.id).await?.is_none() {
+                self.storage.save_account(account).await?;
+            }
+        }
+        for transaction in &archive.transactions {
+            self.storage.save_transaction(transaction).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Entries carrying forward the net of each account over a set of entry
+/// lists, in account ID order
+fn carried_forward<'a>(entry_lists: impl Iterator<Item = &'a [Entry]>) -> Vec<Entry> {
+    let mut balances: BTreeMap<&str, BigDecimal> = BTreeMap::new();
+    for entry in entry_lists.flatten() {
+        let net = balances.entry(entry.account_id.as_str()).or_default();
+        match entry.entry_type {
+            EntryType::Debit => *net += &entry.amount,
+            EntryType::Credit => *net -= &entry.amount,
+        }
+    }
+    balances
+        .into_iter()
+        .filter(|(_, net)| !net.is_zero())
+        .map(|(account_id, net)| {
+            let entry_type = if net.is_positive() {
+                EntryType::Debit
+            } else {
+                EntryType::Credit
+            };
+            Entry::new(account_id.to_string(), entry_type, net.abs(), None)
+        })
+        .collect()
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Move every transaction dated before `before` to `sink`, replacing the
+    /// posted ones with a single opening balance transaction
+    ///
+    /// The books must be locked through the day before `before`, with no
+    /// drafts or pending transactions left before it. Nothing is removed
+    /// unless the sink accepts the archive, and the transactions are
+    /// replaced in one [`LedgerStorage::replace_transactions`] call.
+    pub async fn archive_period(
+        &mut self,
+        before: NaiveDate,
+        sink: &mut dyn ArchiveSink,
+    ) -> LedgerResult<ArchiveRecord> {
+        self.authorize(LedgerOperation::ArchivePeriod { before })?;
+        let last_day = before
+            .pred_opt()
+            .ok_or_else(|| LedgerError::Validation("Nothing to archive".to_string()))?;
+        if !self.period_lock().is_some_and(|lock| lock.covers(last_day)) {
+            return Err(LedgerError::Validation(format!(
+                "Lock the books through {} before archiving",
+                last_day
+            )));
+        }
+        let opening_id = format!("archive-{}", before);
+        if self.get_transaction(&opening_id).await?.is_some() {
+            return Err(LedgerError::Validation(format!(
+                "Transactions before {} are already archived",
+                before
+            )));
+        }
+
+        let mut transactions = self
+            .transaction_manager
+            .get_all_transactions(None, Some(last_day))
+            .await?;
+        if let Some(open) = transactions.iter().find(|t| {
+            matches!(
+                t.status,
+                TransactionStatus::Draft | TransactionStatus::PendingApproval
+            )
+        }) {
+            return Err(LedgerError::Validation(format!(
+                "Transaction {} is still {:?}; post, discard or reject it before archiving",
+                open.id, open.status
+            )));
+        }
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+        let posted: Vec<&Transaction> = transactions.iter().filter(|t| t.is_posted()).collect();
+        let mut opening = Transaction::new(
+            opening_id,
+            last_day,
+            format!("Opening balances carried forward to {}", before),
+            None,
+        );
+        opening.entries = carried_forward(posted.iter().map(|t| t.entries.as_slice()));
+        let books: BTreeSet<&String> = posted.iter().flat_map(|t| t.book_entries.keys()).collect();
+        for book in books {
+            let entries = carried_forward(posted.iter().map(|t| t.entries_for_book(book)));
+            opening.book_entries.insert(book.clone(), entries);
+        }
+        let digest =
+            checksum(&serde_json::to_value(&transactions).map_err(|e| {
+                LedgerError::storage_with_source("Cannot encode archive", false, e)
+            })?)?;
+        let now = self.clock.now();
+        opening
+            .metadata
+            .insert(ARCHIVED_BEFORE_KEY.to_string(), before.into());
+        opening.metadata.insert(
+            ARCHIVED_COUNT_KEY.to_string(),
+            (transactions.len() as i64).into(),
+        );
+        opening
+            .metadata
+            .insert(ARCHIVE_CHECKSUM_KEY.to_string(), digest.clone().into());
+        opening.notes.push(TransactionNote {
+            text: format!(
+                "Archived {} transactions dated before {}",
+                transactions.len(),
+                before
+            ),
+            author: self.actor.as_ref().map(|actor| actor.id.clone()),
+            created_at: now,
+        });
+        opening.assign_entry_ids();
+        opening.created_at = now;
+        opening.updated_at = now;
+
+        let mut accounts = self.list_accounts().await?;
+        accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        let archive = PeriodArchive {
+            before,
+            archived_at: now,
+            accounts,
+            transactions,
+            opening_balances: opening,
+        };
+        sink.write_archive(&archive).await?;
+
+        // The opening balances net to the same stored balances as the
+        // transactions they replace, so storage is edited directly
+        self.account_manager
+            .storage
+            .replace_transactions(&archive.transactions, &archive.opening_balances)
+            .await?;
+        self.notify(LedgerEvent::PeriodArchived { before });
+
+        Ok(ArchiveRecord {
+            before,
+            transaction_count: archive.transactions.len(),
+            opening_transaction_id: archive.opening_balances.id,
+            checksum: digest,
+        })
+    }
+
+    /// Archivals still recorded in the ledger, oldest first
+    pub async fn list_archives(&self) -> LedgerResult<Vec<ArchiveRecord>> {
+        let mut records: Vec<ArchiveRecord> = self
+            .get_transactions(None, None)
+            .await?
+            .iter()
+            .filter_map(ArchiveRecord::from_transaction)
+            .collect();
+        records.sort_by_key(|record| record.before);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    type BookBalances = BTreeMap<String, (Option<BigDecimal>, Option<BigDecimal>)>;
+
+    async fn tax_trial_balance(ledger: &Ledger<MemoryStorage>, as_of: NaiveDate) -> BookBalances {
+        let book = ledger.book_ledger("tax").await.unwrap();
+        let trial_balance = book.get_trial_balance(as_of).await.unwrap();
+        trial_balance
+            .balances
+            .into_iter()
+            .map(|(id, b)| (id, (b.debit_balance, b.credit_balance)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_archive_period() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        for (id, day, debit, credit, amount) in [
+            ("T1", date(2022, 4, 1), "1000", "3000", 50000),
+            ("T2", date(2022, 6, 1), "6000", "1000", 12000),
+            ("T3", date(2023, 1, 15), "1000", "4000", 8000),
+            ("T4", date(2023, 5, 1), "6000", "1000", 3000),
+        ] {
+            let builder = TransactionBuilder::new(id.to_string(), day, id.to_string())
+                .debit(debit.to_string(), BigDecimal::from(amount), None)
+                .credit(credit.to_string(), BigDecimal::from(amount), None);
+            let mut transaction = builder.build().unwrap();
+            if id == "T2" {
+                // The tax book allows less of the expense
+                transaction.set_book_entries(
+                    "tax",
+                    vec![
+                        Entry::debit("6000".to_string(), BigDecimal::from(9000), None),
+                        Entry::credit("1000".to_string(), BigDecimal::from(9000), None),
+                    ],
+                );
+            }
+            ledger.record_transaction(transaction).await.unwrap();
+        }
+        let cutoff = date(2023, 4, 1);
+        let trial_balance = ledger.get_trial_balance(date(2023, 5, 31)).await.unwrap();
+        let tax_before = tax_trial_balance(&ledger, date(2023, 5, 31)).await;
+
+        let mut sink = JsonArchiveSink::new(Vec::new());
+        let error = ledger.archive_period(cutoff, &mut sink).await.unwrap_err();
+        assert!(error.to_string().contains("Lock the books"));
+
+        // Drafts in the period would vanish with the archive
+        let draft = TransactionBuilder::new("D1".to_string(), date(2023, 2, 1), "D1".to_string())
+            .debit("6000".to_string(), BigDecimal::from(10), None)
+            .credit("1000".to_string(), BigDecimal::from(10), None)
+            .build()
+            .unwrap();
+        ledger.save_draft(draft).await.unwrap();
+        ledger.lock_period(date(2023, 3, 31)).unwrap();
+        let error = ledger.archive_period(cutoff, &mut sink).await.unwrap_err();
+        assert!(error.to_string().contains("D1"));
+        ledger.unlock_period().unwrap();
+        ledger.discard_draft("D1").await.unwrap();
+
+        ledger.lock_period(date(2023, 3, 31)).unwrap();
+        let record = ledger.archive_period(cutoff, &mut sink).await.unwrap();
+        assert_eq!(record.transaction_count, 3);
+
+        let archive: PeriodArchive = serde_json::from_slice(&sink.into_inner()).unwrap();
+        let ids: Vec<&str> = archive.transactions.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["T1", "T2", "T3"]);
+
+        let remaining = ledger.get_transactions(None, None).await.unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(ledger.get_transaction("T1").await.unwrap().is_none());
+        let opening = ledger
+            .get_transaction(&record.opening_transaction_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(opening.date, date(2023, 3, 31));
+        assert_eq!(opening.total_debits(), BigDecimal::from(58000));
+        assert_eq!(
+            ledger.get_trial_balance(date(2023, 5, 31)).await.unwrap(),
+            trial_balance
+        );
+        assert_eq!(
+            tax_trial_balance(&ledger, date(2023, 5, 31)).await,
+            tax_before
+        );
+        assert_eq!(
+            ledger
+                .get_account_balance("1000", Some(date(2023, 3, 31)))
+                .await
+                .unwrap(),
+            BigDecimal::from(46000)
+        );
+        assert_eq!(ledger.list_archives().await.unwrap(), vec![record]);
+
+        // A second archive into another storage folds the first in
+        ledger.lock_period(date(2023, 12, 31)).unwrap();
+        let mut sink = StorageArchiveSink::new(MemoryStorage::new());
+        let record = ledger
+            .archive_period(date(2024, 1, 1), &mut sink)
+            .await
+            .unwrap();
+        assert_eq!(record.transaction_count, 2);
+        let archived = sink.into_inner();
+        assert!(archived
+            .get_transaction("archive-2023-04-01")
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(ledger.list_archives().await.unwrap().len(), 1);
+    }
+}
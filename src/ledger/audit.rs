@@ -0,0 +1,489 @@
+//! Audit analytics: Benford's law and transaction sampling
+//!
+//! [`analyze_benford`](Ledger::analyze_benford) compares the first digits of
+//! a period's entry amounts with the distribution Benford's law predicts,
+//! scoring the fit with the mean absolute deviation thresholds commonly used
+//! for first-digit tests (0.006, 0.012 and 0.015).
+//!
+//! [`sample_transactions`](Ledger::sample_transactions) draws a stratified
+//! random sample of posted transactions for audit testing. Strata are bands
+//! of transaction amount; each takes a fixed number of transactions or, for
+//! the high-value band usually tested in full, all of them. The draw is
+//! seeded, so the same plan over the same books always selects the same
+//! transactions and the sample can be re-performed.
+//! [`export_audit_sample_csv`](Ledger::export_audit_sample_csv) writes the
+//! selection with its entries, attachments and notes for the working papers.
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::export::write_field;
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Header line of an audit sample CSV file
+pub const AUDIT_SAMPLE_CSV_HEADER: [&str; 12] = [
+    "stratum",
+    "transaction_id",
+    "date",
+    "description",
+    "reference",
+    "account",
+    "account_name",
+    "debit",
+    "credit",
+    "attachments",
+    "notes",
+    "transaction_amount",
+];
+
+/// How closely first digits follow Benford's law
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BenfordConformity {
+    Close,
+    Acceptable,
+    Marginal,
+    Nonconforming,
+}
+
+impl BenfordConformity {
+    fn from_mad(mad: f64) -> Self {
+        if mad < 0.006 {
+            Self::Close
+        } else if mad < 0.012 {
+            Self::Acceptable
+        } else if mad < 0.015 {
+            Self::Marginal
+        } else {
+            Self::Nonconforming
+        }
+    }
+}
+
+/// Observed and expected frequency of one leading digit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BenfordDigit {
+    pub digit: u8,
+    pub count: usize,
+    /// Share of amounts starting with the digit
+    pub observed: f64,
+    /// Share Benford's law predicts
+    pub expected: f64,
+}
+
+/// First-digit analysis of the entry amounts of a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BenfordAnalysis {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Non-zero amounts analysed
+    pub amounts: usize,
+    /// Digits 1 to 9
+    pub digits: Vec<BenfordDigit>,
+    pub chi_square: f64,
+    pub mean_absolute_deviation: f64,
+    /// `None` when there are no amounts to judge
+    pub conformity: Option<BenfordConformity>,
+}
+
+impl BenfordAnalysis {
+    /// Digits whose observed share differs from the expected share by more
+    /// than `tolerance`, largest difference first
+    pub fn outlying_digits(&self, tolerance: f64) -> Vec<&BenfordDigit> {
+        let mut digits: Vec<&BenfordDigit> = self
+            .digits
+            .iter()
+            .filter(|d| (d.observed - d.expected).abs() > tolerance)
+            .collect();
+        digits.sort_by(|a, b| {
+            (b.observed - b.expected)
+                .abs()
+                .total_cmp(&(a.observed - a.expected).abs())
+        });
+        digits
+    }
+}
+
+/// Band of transaction amounts sampled together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Stratum {
+    pub name: String,
+    /// Smallest amount in the band
+    pub min_amount: BigDecimal,
+    /// Amount the band stops below; `None` for no upper bound
+    pub max_amount: Option<BigDecimal>,
+    /// Transactions to select; `None` to select all of them
+    pub sample_size: Option<usize>,
+}
+
+impl Stratum {
+    /// Band from which `sample_size` transactions are drawn at random
+    pub fn new(
+        name: String,
+        min_amount: BigDecimal,
+        max_amount: Option<BigDecimal>,
+        sample_size: usize,
+    ) -> Self {
+        Self {
+            name,
+            min_amount,
+            max_amount,
+            sample_size: Some(sample_size),
+        }
+    }
+
+    /// Band tested in full
+    pub fn all(name: String, min_amount: BigDecimal, max_amount: Option<BigDecimal>) -> Self {
+        Self {
+            name,
+            min_amount,
+            max_amount,
+            sample_size: None,
+        }
+    }
+
+    fn contains(&self, amount: &BigDecimal) -> bool {
+        *amount >= self.min_amount && self.max_amount.as_ref().is_none_or(|max| amount < max)
+    }
+}
+
+/// Strata and seed of a sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SamplingPlan {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// A transaction falls in the first stratum whose band contains its
+    /// total debits
+    pub strata: Vec<Stratum>,
+    pub seed: u64,
+}
+
+/// Transactions selected from one stratum
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StratumSample {
+    pub stratum: Stratum,
+    /// Transactions in the band
+    pub population: usize,
+    pub population_amount: BigDecimal,
+    /// Selected transactions, ordered by date
+    pub selected: Vec<Transaction>,
+    pub selected_amount: BigDecimal,
+}
+
+/// A stratified random sample of transactions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AuditSample {
+    pub plan: SamplingPlan,
+    pub strata: Vec<StratumSample>,
+    /// Transactions of the period outside every stratum
+    pub unstratified: usize,
+}
+
+impl AuditSample {
+    /// Number of transactions selected across the strata
+    pub fn len(&self) -> usize {
+        self.strata.iter().map(|s| s.selected.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// SplitMix64, enough to make a seeded draw reproducible
+struct SampleRng(u64);
+
+impl SampleRng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// Leading significant digit of an amount
+fn first_digit(amount: &BigDecimal) -> Option<u8> {
+    amount
+        .to_plain_string()
+        .bytes()
+        .find(|b| (b'1'..=b'9').contains(b))
+        .map(|b| b - b'0')
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Compare the first digits of a period's posted entry amounts with
+    /// Benford's law
+    pub async fn analyze_benford(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<BenfordAnalysis> {
+        let filter = EntryFilter {
+            start_date: Some(start_date),
+            end_date: Some(end_date),
+            ..Default::default()
+        };
+        let mut counts = [0usize; 9];
+        for row in self.get_entries(&filter).await? {
+            if let Some(digit) = first_digit(&row.entry.amount) {
+                counts[usize::from(digit) - 1] += 1;
+            }
+        }
+        let amounts: usize = counts.iter().sum();
+        let total = amounts as f64;
+
+        let mut chi_square = 0.0;
+        let mut deviation = 0.0;
+        let digits: Vec<BenfordDigit> = (1u8..=9)
+            .map(|digit| {
+                let count = counts[usize::from(digit) - 1];
+                let expected = (1.0 + 1.0 / f64::from(digit)).log10();
+                let observed = if amounts == 0 {
+                    0.0
+                } else {
+                    count as f64 / total
+                };
+                if amounts > 0 {
+                    let expected_count = expected * total;
+                    chi_square += (count as f64 - expected_count).powi(2) / expected_count;
+                    deviation += (observed - expected).abs();
+                }
+                BenfordDigit {
+                    digit,
+                    count,
+                    observed,
+                    expected,
+                }
+            })
+            .collect();
+        let mean_absolute_deviation = deviation / 9.0;
+        Ok(BenfordAnalysis {
+            start_date,
+            end_date,
+            amounts,
+            digits,
+            chi_square,
+            mean_absolute_deviation,
+            conformity: (amounts > 0).then(|| BenfordConformity::from_mad(mean_absolute_deviation)),
+        })
+    }
+
+    /// Draw a stratified random sample of posted transactions
+    pub async fn sample_transactions(&self, plan: &SamplingPlan) -> LedgerResult<AuditSample> {
+        if plan.start_date > plan.end_date {
+            return Err(LedgerError::Validation(
+                "Start date must not be after end date".to_string(),
+            ));
+        }
+        let mut transactions = self
+            .get_transactions(Some(plan.start_date), Some(plan.end_date))
+            .await?;
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+        let mut bands: Vec<Vec<Transaction>> = vec![Vec::new(); plan.strata.len()];
+        let mut unstratified = 0;
+        for transaction in transactions {
+            let amount = transaction.total_debits();
+            match plan.strata.iter().position(|s| s.contains(&amount)) {
+                Some(index) => bands[index].push(transaction),
+                None => unstratified += 1,
+            }
+        }
+
+        let mut rng = SampleRng(plan.seed);
+        let strata = plan
+            .strata
+            .iter()
+            .zip(bands)
+            .map(|(stratum, mut population)| {
+                let population_amount: BigDecimal =
+                    population.iter().map(Transaction::total_debits).sum();
+                let count = population.len();
+                let take = stratum.sample_size.unwrap_or(count).min(count);
+                // Partial Fisher-Yates: the first `take` slots end up holding
+                // a uniform draw without replacement
+                for i in 0..take {
+                    let j = i + rng.below(count - i);
+                    population.swap(i, j);
+                }
+                population.truncate(take);
+                population.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+                StratumSample {
+                    stratum: stratum.clone(),
+                    population: count,
+                    population_amount,
+                    selected_amount: population.iter().map(Transaction::total_debits).sum(),
+                    selected: population,
+                }
+            })
+            .collect();
+
+        Ok(AuditSample {
+            plan: plan.clone(),
+            strata,
+            unstratified,
+        })
+    }
+
+    /// A sample as CSV with one row per entry of each selected transaction
+    ///
+    /// Attachment file names are separated by `;` and notes by ` | `.
+    pub async fn export_audit_sample_csv(&self, sample: &AuditSample) -> LedgerResult<String> {
+        let names: HashMap<String, String> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|a| (a.id, a.name))
+            .collect();
+        let mut out = AUDIT_SAMPLE_CSV_HEADER.join(",");
+        out.push('\n');
+        for stratum in &sample.strata {
+            for transaction in &stratum.selected {
+                let date = transaction.date.to_string();
+                let attachments = transaction
+                    .attachments
+                    .iter()
+                    .map(|a| a.filename.as_str())
+                    .collect::<Vec<_>>()
+                    .join(";");
+                let notes = transaction
+                    .notes
+                    .iter()
+                    .map(|n| n.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                let total = transaction.total_debits().to_plain_string();
+                for entry in &transaction.entries {
+                    let amount = entry.amount.to_plain_string();
+                    let (debit, credit) = match entry.entry_type {
+                        EntryType::Debit => (amount.as_str(), ""),
+                        EntryType::Credit => ("", amount.as_str()),
+                    };
+                    let fields = [
+                        stratum.stratum.name.as_str(),
+                        transaction.id.as_str(),
+                        date.as_str(),
+                        transaction.description.as_str(),
+                        transaction.reference.as_deref().unwrap_or(""),
+                        entry.account_id.as_str(),
+                        names.get(&entry.account_id).map_or("", String::as_str),
+                        debit,
+                        credit,
+                        attachments.as_str(),
+                        notes.as_str(),
+                        total.as_str(),
+                    ];
+                    for (i, field) in fields.iter().enumerate() {
+                        if i > 0 {
+                            out.push(',');
+                        }
+                        write_field(&mut out, field);
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+    use bigdecimal::Zero;
+
+    async fn ledger_with_expenses(amounts: &[i64]) -> Ledger<MemoryStorage> {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        for (i, amount) in amounts.iter().enumerate() {
+            let transaction =
+                TransactionBuilder::new(format!("T{:03}", i), date, "Expense".to_string())
+                    .debit("6000".to_string(), BigDecimal::from(*amount), None)
+                    .credit("1000".to_string(), BigDecimal::from(*amount), None)
+                    .build()
+                    .unwrap();
+            ledger.record_transaction(transaction).await.unwrap();
+        }
+        ledger
+    }
+
+    #[test]
+    fn test_first_digit() {
+        assert_eq!(first_digit(&"0.0042".parse().unwrap()), Some(4));
+        assert_eq!(first_digit(&"-718.5".parse().unwrap()), Some(7));
+        assert_eq!(first_digit(&BigDecimal::zero()), None);
+    }
+
+    #[tokio::test]
+    async fn test_benford_flags_uniform_digits() {
+        // Nine amounts per leading digit: a flat distribution
+        let amounts: Vec<i64> = (1..=9)
+            .flat_map(|d| (0..9).map(move |i| d * 100 + i))
+            .collect();
+        let ledger = ledger_with_expenses(&amounts).await;
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let analysis = ledger.analyze_benford(date, date).await.unwrap();
+        assert_eq!(analysis.amounts, 162);
+        assert_eq!(analysis.digits[0].count, 18);
+        assert_eq!(analysis.conformity, Some(BenfordConformity::Nonconforming));
+        assert_eq!(analysis.outlying_digits(0.05)[0].digit, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stratified_sample_and_export() {
+        let amounts: Vec<i64> = (1..=40).map(|i| i * 100).chain([50000, 75000]).collect();
+        let mut ledger = ledger_with_expenses(&amounts).await;
+        let mut noted = ledger.get_transaction("T041").await.unwrap().unwrap();
+        noted.description = "Server, annual".to_string();
+        ledger.update_transaction(&noted).await.unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let plan = SamplingPlan {
+            start_date: date,
+            end_date: date,
+            strata: vec![
+                Stratum::all("key items".to_string(), BigDecimal::from(10000), None),
+                Stratum::new(
+                    "remainder".to_string(),
+                    BigDecimal::from(1000),
+                    Some(BigDecimal::from(10000)),
+                    5,
+                ),
+            ],
+            seed: 7,
+        };
+        let sample = ledger.sample_transactions(&plan).await.unwrap();
+        assert_eq!(sample.strata[0].selected.len(), 2);
+        assert_eq!(sample.strata[1].population, 31);
+        assert_eq!(sample.strata[1].selected.len(), 5);
+        assert_eq!(sample.unstratified, 9);
+        assert_eq!(sample.len(), 7);
+        // Same seed, same selection
+        assert_eq!(ledger.sample_transactions(&plan).await.unwrap(), sample);
+
+        let csv = ledger.export_audit_sample_csv(&sample).await.unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 1 + 7 * 2);
+        assert!(lines[3].starts_with("key items,T041,2024-06-01,\"Server, annual\""));
+        assert!(lines[3].contains(",6000,Rent Expense,75000,"));
+    }
+}
@@ -0,0 +1,352 @@
+//! Full ledger backup and restore
+//!
+//! A snapshot is a single JSON document holding every account and
+//! transaction (invoices are stored as sales and purchase transactions) and
+//! the ledger settings, wrapped with a format version and a SHA-256 checksum
+//! of the payload. Keys are written in sorted order so the checksum can be
+//! recomputed from the parsed document on import.
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use crate::ledger::{
+    default_base_currency, default_fiscal_year_start_month, BudgetEnforcement, BudgetLimit,
+    DefaultAccounts, Fund, Ledger, NetIncomePresentation, PostingRules, Project,
+    TransactionTemplate,
+};
+use crate::receivables::PaymentTerms;
+use crate::tax::GstRegistration;
+use crate::traits::*;
+use crate::types::*;
+
+/// Current snapshot format version
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+/// Ledger settings carried in a snapshot
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LedgerSettings {
+    pub period_lock: Option<PeriodLock>,
+    pub approval_threshold: Option<BigDecimal>,
+    pub suspense_account_id: Option<String>,
+    #[serde(default)]
+    pub retained_earnings_account_id: Option<String>,
+    #[serde(default)]
+    pub net_income_presentation: NetIncomePresentation,
+    #[serde(default)]
+    pub amount_policy: Option<AmountPolicy>,
+    #[serde(default)]
+    pub round_off_policy: Option<RoundOffPolicy>,
+    #[serde(default)]
+    pub templates: Vec<TransactionTemplate>,
+    #[serde(default)]
+    pub party_payment_terms: BTreeMap<String, PaymentTerms>,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub funds: Vec<Fund>,
+    #[serde(default)]
+    pub budget_limits: Vec<BudgetLimit>,
+    #[serde(default)]
+    pub budget_enforcement: BudgetEnforcement,
+    #[serde(default = "default_base_currency")]
+    pub base_currency: String,
+    #[serde(default = "default_fiscal_year_start_month")]
+    pub fiscal_year_start_month: u32,
+    #[serde(default)]
+    pub gst_registration: Option<GstRegistration>,
+    #[serde(default)]
+    pub posting_rules: Option<PostingRules>,
+    #[serde(default)]
+    pub allow_mixed_account_types: bool,
+    #[serde(default)]
+    pub hierarchy_limits: HierarchyLimits,
+    #[serde(default)]
+    pub default_accounts: DefaultAccounts,
+}
+
+/// Contents of a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SnapshotPayload {
+    /// When the snapshot was exported
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub created_at: DateTime<Utc>,
+    pub accounts: Vec<Account>,
+    pub transactions: Vec<Transaction>,
+    pub settings: LedgerSettings,
+}
+
+/// Summary of an exported or imported snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SnapshotSummary {
+    pub format_version: u32,
+    /// `sha256:` followed by the hex digest of the payload
+    pub checksum: String,
+    pub account_count: usize,
+    pub transaction_count: usize,
+}
+
+pub(crate) fn checksum(payload: &serde_json::Value) -> LedgerResult<String> {
+    let bytes = serde_json::to_vec(payload)
+        .map_err(|e| LedgerError::storage_with_source("Cannot encode snapshot", false, e))?;
+    let digest = Sha256::digest(&bytes);
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("sha256:{}", hex))
+}
+
+/// Write a payload as a snapshot document
+pub(crate) fn write_snapshot<W: Write>(
+    payload: &SnapshotPayload,
+    writer: W,
+) -> LedgerResult<SnapshotSummary> {
+    let value = serde_json::to_value(payload)
+        .map_err(|e| LedgerError::storage_with_source("Cannot encode snapshot", false, e))?;
+    let checksum = checksum(&value)?;
+    let document = serde_json::json!({
+        "format_version": SNAPSHOT_FORMAT_VERSION,
+        "checksum": checksum,
+        "payload": value,
+    });
+    serde_json::to_writer(writer, &document)
+        .map_err(|e| LedgerError::storage_with_source("Cannot write snapshot", false, e))?;
+
+    Ok(SnapshotSummary {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        checksum,
+        account_count: payload.accounts.len(),
+        transaction_count: payload.transactions.len(),
+    })
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Write every account, transaction and setting to `writer`
+    pub async fn export_snapshot<W: Write>(&self, writer: W) -> LedgerResult<SnapshotSummary> {
+        write_snapshot(&self.snapshot_payload().await?, writer)
+    }
+
+    /// Every account, transaction and setting, in snapshot order
+    pub(crate) async fn snapshot_payload(&self) -> LedgerResult<SnapshotPayload> {
+        let mut accounts = self.list_accounts().await?;
+        accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut transactions = self
+            .transaction_manager
+            .get_all_transactions(None, None)
+            .await?;
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(SnapshotPayload {
+            created_at: self.clock.now(),
+            accounts,
+            transactions,
+            settings: LedgerSettings {
+                period_lock: self.period_lock().cloned(),
+                approval_threshold: self.transaction_manager.approval_threshold().cloned(),
+                suspense_account_id: self.suspense_account_id.clone(),
+                retained_earnings_account_id: self.retained_earnings_account_id.clone(),
+                net_income_presentation: self.net_income_presentation,
+                amount_policy: self.amount_policy().cloned(),
+                round_off_policy: self.round_off_policy().cloned(),
+                templates: self.templates.values().cloned().collect(),
+                party_payment_terms: self.party_payment_terms.clone(),
+                projects: self.projects.values().cloned().collect(),
+                funds: self.funds.values().cloned().collect(),
+                budget_limits: self.budget_limits.clone(),
+                budget_enforcement: self.budget_enforcement,
+                base_currency: self.base_currency.clone(),
+                fiscal_year_start_month: self.fiscal_year_start_month,
+                gst_registration: self.gst_registration.clone(),
+                posting_rules: self.posting_rules.clone(),
+                allow_mixed_account_types: self.account_manager.allows_mixed_types(),
+                hierarchy_limits: self.hierarchy_limits(),
+                default_accounts: self.default_accounts.clone(),
+            },
+        })
+    }
+
+    /// Load a snapshot written by [`Ledger::export_snapshot`] into this
+    /// ledger, which must not hold any accounts or transactions
+    ///
+    /// Records are restored exactly as exported, including stored balances,
+    /// statuses and timestamps; nothing is re-posted.
+    pub async fn import_snapshot<R: Read>(&mut self, reader: R) -> LedgerResult<SnapshotSummary> {
+        self.authorize(LedgerOperation::RestoreSnapshot)?;
+        let mut document: serde_json::Value = serde_json::from_reader(reader)
+            .map_err(|e| LedgerError::InvalidSnapshot(e.to_string()))?;
+
+        let format_version = document["format_version"]
+            .as_u64()
+            .ok_or_else(|| LedgerError::InvalidSnapshot("missing format version".to_string()))?;
+        if format_version != u64::from(SNAPSHOT_FORMAT_VERSION) {
+            return Err(LedgerError::InvalidSnapshot(format!(
+                "unsupported format version {}",
+                format_version
+            )));
+        }
+        let expected = document["checksum"]
+            .as_str()
+            .ok_or_else(|| LedgerError::InvalidSnapshot("missing checksum".to_string()))?
+            .to_string();
+        let payload = document["payload"].take();
+        let actual = checksum(&payload)?;
+        if actual != expected {
+            return Err(LedgerError::InvalidSnapshot(format!(
+                "checksum mismatch: expected {}, computed {}",
+                expected, actual
+            )));
+        }
+        let payload: SnapshotPayload = serde_json::from_value(payload)
+            .map_err(|e| LedgerError::InvalidSnapshot(e.to_string()))?;
+
+        if !self.list_accounts().await?.is_empty()
+            || !self
+                .transaction_manager
+                .get_all_transactions(None, None)
+                .await?
+                .is_empty()
+        {
+            return Err(LedgerError::Validation(
+                "Snapshots can only be imported into an empty ledger".to_string(),
+            ));
+        }
+
+        let storage = &mut self.account_manager.storage;
+        for account in &payload.accounts {
+            storage.save_account(account).await?;
+        }
+        for transaction in &payload.transactions {
+            storage.save_transaction(transaction).await?;
+        }
+        self.transaction_manager
+            .restore_period_lock(payload.settings.period_lock);
+        self.transaction_manager
+            .set_approval_threshold(payload.settings.approval_threshold);
+        self.suspense_account_id = payload.settings.suspense_account_id;
+        self.retained_earnings_account_id = payload.settings.retained_earnings_account_id;
+        self.net_income_presentation = payload.settings.net_income_presentation;
+        self.transaction_manager
+            .set_amount_policy(payload.settings.amount_policy);
+        self.transaction_manager
+            .set_round_off_policy(payload.settings.round_off_policy);
+        self.replace_templates(payload.settings.templates).await?;
+        self.party_payment_terms = payload.settings.party_payment_terms;
+        self.projects = payload
+            .settings
+            .projects
+            .into_iter()
+            .map(|project| (project.id.clone(), project))
+            .collect();
+        self.funds = payload
+            .settings
+            .funds
+            .into_iter()
+            .map(|fund| (fund.id.clone(), fund))
+            .collect();
+        self.budget_limits = payload.settings.budget_limits;
+        self.budget_enforcement = payload.settings.budget_enforcement;
+        self.base_currency = payload.settings.base_currency;
+        self.fiscal_year_start_month = payload.settings.fiscal_year_start_month;
+        self.gst_registration = payload.settings.gst_registration;
+        self.posting_rules = payload.settings.posting_rules;
+        self.account_manager
+            .set_allow_mixed_types(payload.settings.allow_mixed_account_types);
+        self.account_manager
+            .set_hierarchy_limits(payload.settings.hierarchy_limits);
+        self.default_accounts = payload.settings.default_accounts;
+        self.notify(LedgerEvent::SnapshotImported);
+
+        Ok(SnapshotSummary {
+            format_version: SNAPSHOT_FORMAT_VERSION,
+            checksum: expected,
+            account_count: payload.accounts.len(),
+            transaction_count: payload.transactions.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, ReportCache};
+    use crate::utils::MemoryStorage;
+    use chrono::NaiveDate;
+
+    async fn sample_ledger() -> Ledger<MemoryStorage> {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let investment = patterns::create_owner_investment(
+            "inv1".to_string(),
+            date,
+            "Capital".to_string(),
+            "1000".to_string(),
+            "3000".to_string(),
+            BigDecimal::from(5000),
+        )
+        .unwrap();
+        ledger.record_transaction(investment).await.unwrap();
+        let draft = patterns::create_expense_payment(
+            "rent-draft".to_string(),
+            date,
+            "Rent".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(700),
+        )
+        .unwrap();
+        ledger.save_draft(draft).await.unwrap();
+        ledger.lock_period(date).unwrap();
+        ledger
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let source = sample_ledger().await;
+        let mut file = Vec::new();
+        let exported = source.export_snapshot(&mut file).await.unwrap();
+        assert_eq!(exported.transaction_count, 2);
+
+        let mut restored = Ledger::new(MemoryStorage::new());
+        let cache = ReportCache::new();
+        restored.set_report_cache(cache.clone());
+        let as_of = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        restored.get_trial_balance(as_of).await.unwrap();
+        assert_eq!(cache.len(), 1);
+        let imported = restored.import_snapshot(file.as_slice()).await.unwrap();
+        assert_eq!(imported, exported);
+        assert!(cache.is_empty());
+        assert_eq!(
+            restored.get_account_balance("1000", None).await.unwrap(),
+            BigDecimal::from(5000)
+        );
+        assert_eq!(restored.list_drafts(None, None).await.unwrap().len(), 1);
+        assert_eq!(restored.period_lock(), source.period_lock());
+
+        // Importing twice is refused
+        assert!(restored.import_snapshot(file.as_slice()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tampered_snapshot_is_rejected() {
+        let source = sample_ledger().await;
+        let mut file = Vec::new();
+        source.export_snapshot(&mut file).await.unwrap();
+        let tampered = String::from_utf8(file)
+            .unwrap()
+            .replace("\"5000\"", "\"9000\"");
+
+        let mut restored = Ledger::new(MemoryStorage::new());
+        let error = restored
+            .import_snapshot(tampered.as_bytes())
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "invalid_snapshot");
+        assert!(restored.list_accounts().await.unwrap().is_empty());
+    }
+}
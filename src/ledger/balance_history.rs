@@ -0,0 +1,211 @@
+//! Balance time series for charting
+//!
+//! The series starts from the account's balance the day before the range (the
+//! snapshot) and then adds each day's posted movement (the deltas), taken
+//! from the backend's daily totals when it keeps them.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Spacing of points in a balance history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Granularity {
+    Daily,
+    /// Weeks ending on Sunday
+    Weekly,
+    /// Calendar months
+    Monthly,
+}
+
+impl Granularity {
+    /// Last day of the period containing `date`
+    pub(crate) fn period_end(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Daily => date,
+            Granularity::Weekly => {
+                date + Days::new(6 - u64::from(date.weekday().num_days_from_monday()))
+            }
+            Granularity::Monthly => {
+                let (year, month) = if date.month() == 12 {
+                    (date.year() + 1, 1)
+                } else {
+                    (date.year(), date.month() + 1)
+                };
+                NaiveDate::from_ymd_opt(year, month, 1)
+                    .and_then(|first| first.pred_opt())
+                    .unwrap_or(date)
+            }
+        }
+    }
+}
+
+/// Closing balance of one period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BalancePoint {
+    /// Last day of the period, clipped to the end of the range
+    pub date: NaiveDate,
+    pub balance: BigDecimal,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Posted balance of an account at the end of each day, week or month
+    /// between two dates, in the account's normal direction
+    pub async fn get_balance_history(
+        &self,
+        account_id: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        granularity: Granularity,
+    ) -> LedgerResult<Vec<BalancePoint>> {
+        if start_date > end_date {
+            return Err(LedgerError::Validation(
+                "Start date must not be after end date".to_string(),
+            ));
+        }
+        let account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+
+        let mut balance = match start_date.pred_opt() {
+            Some(day_before) => {
+                self.get_account_balance(account_id, Some(day_before))
+                    .await?
+            }
+            None => BigDecimal::zero(),
+        };
+        let deltas = self.daily_movements(&account, start_date, end_date).await?;
+
+        let mut points = Vec::new();
+        let mut period_start = start_date;
+        while period_start <= end_date {
+            let period_end = granularity.period_end(period_start).min(end_date);
+            balance += deltas
+                .range(period_start..=period_end)
+                .map(|(_, delta)| delta)
+                .sum::<BigDecimal>();
+            points.push(BalancePoint {
+                date: period_end,
+                balance: balance.clone(),
+            });
+            match period_end.succ_opt() {
+                Some(next) => period_start = next,
+                None => break,
+            }
+        }
+        Ok(points)
+    }
+
+    /// Net posted movement of an account per day within a range
+    async fn daily_movements(
+        &self,
+        account: &Account,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<BTreeMap<NaiveDate, BigDecimal>> {
+        let storage = &self.account_manager.storage;
+        if let Some(reporting) = storage.reporting() {
+            return Ok(reporting
+                .get_daily_totals(&account.id, Some(start_date), end_date)
+                .await?
+                .into_iter()
+                .map(|day| (day.date, day.totals.balance(&account.account_type)))
+                .collect());
+        }
+
+        let mut deltas = BTreeMap::new();
+        let rows = self
+            .get_entries(&EntryFilter {
+                account_id: Some(account.id.clone()),
+                start_date: Some(start_date),
+                end_date: Some(end_date),
+                ..EntryFilter::default()
+            })
+            .await?;
+        for row in rows {
+            *deltas.entry(row.date).or_insert_with(BigDecimal::zero) += account
+                .account_type
+                .balance_effect(&row.entry.entry_type, &row.entry.amount);
+        }
+        Ok(deltas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::{AggregatingStorage, MemoryStorage};
+
+    async fn record_receipts<S: LedgerStorage + Clone>(ledger: &mut Ledger<S>) {
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, (month, day), amount) in [
+            ("c1", (1, 15), 1000),
+            ("c2", (2, 3), 500),
+            ("c3", (2, 28), 250),
+            ("c4", (4, 1), 100),
+        ] {
+            let investment = patterns::create_owner_investment(
+                id.to_string(),
+                NaiveDate::from_ymd_opt(2024, month, day).unwrap(),
+                "Capital".to_string(),
+                "1000".to_string(),
+                "3000".to_string(),
+                BigDecimal::from(amount),
+            )
+            .unwrap();
+            ledger.record_transaction(investment).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_monthly_history_matches_aggregated_path() {
+        let start = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 4, 10).unwrap();
+
+        let mut plain = Ledger::new(MemoryStorage::new());
+        record_receipts(&mut plain).await;
+        let history = plain
+            .get_balance_history("1000", start, end, Granularity::Monthly)
+            .await
+            .unwrap();
+        let balances: Vec<(u32, BigDecimal)> = history
+            .iter()
+            .map(|point| (point.date.day(), point.balance.clone()))
+            .collect();
+        assert_eq!(
+            balances,
+            vec![
+                (29, BigDecimal::from(1750)),
+                (31, BigDecimal::from(1750)),
+                (10, BigDecimal::from(1850)),
+            ]
+        );
+
+        let storage = AggregatingStorage::new(MemoryStorage::new()).await.unwrap();
+        let mut aggregated = Ledger::new(storage);
+        record_receipts(&mut aggregated).await;
+        assert_eq!(
+            aggregated
+                .get_balance_history("1000", start, end, Granularity::Monthly)
+                .await
+                .unwrap(),
+            history
+        );
+
+        let weekly = plain
+            .get_balance_history("1000", start, end, Granularity::Weekly)
+            .await
+            .unwrap();
+        assert_eq!(weekly[0].date, NaiveDate::from_ymd_opt(2024, 2, 4).unwrap());
+        assert_eq!(weekly[0].balance, BigDecimal::from(1500));
+    }
+}
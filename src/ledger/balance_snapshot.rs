@@ -0,0 +1,182 @@
+//! Point-in-time balance snapshots and diffs
+//!
+//! A [`BalanceSnapshot`] records every account's posted balance as of a date
+//! at the moment it is captured. Diffing it against a later capture of the
+//! same date shows which balances were changed after the fact, for example by
+//! back-dated postings or edits to a closed month.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ledger::{BalanceChange, Ledger};
+use crate::traits::*;
+use crate::types::*;
+
+/// Posted balances of every account as of a date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BalanceSnapshot {
+    pub as_of: NaiveDate,
+    /// When the balances were read
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub captured_at: DateTime<Utc>,
+    /// Balance lines keyed by account id
+    pub balances: BTreeMap<String, AccountBalance>,
+}
+
+/// Accounts whose balance differs between two snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BalanceSnapshotDiff {
+    pub from_as_of: NaiveDate,
+    /// When the earlier snapshot was taken
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub from_captured_at: DateTime<Utc>,
+    pub to_as_of: NaiveDate,
+    /// When the later snapshot was taken
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub to_captured_at: DateTime<Utc>,
+    /// One change per account whose balance moved, ordered by account id
+    pub changes: Vec<BalanceChange>,
+}
+
+impl BalanceSnapshotDiff {
+    /// Whether both snapshots hold the same balances
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Change for a single account, if its balance moved
+    pub fn change(&self, account_id: &str) -> Option<&BalanceChange> {
+        self.changes
+            .iter()
+            .find(|change| change.account_id == account_id)
+    }
+}
+
+impl BalanceSnapshot {
+    /// Signed balance of an account in its normal direction; zero when the
+    /// account is not in the snapshot
+    pub fn balance(&self, account_id: &str) -> BigDecimal {
+        self.balances
+            .get(account_id)
+            .map(|line| line.signed_balance())
+            .unwrap_or_else(BigDecimal::zero)
+    }
+
+    /// Compare this snapshot with a later one
+    ///
+    /// Accounts present in only one snapshot are treated as having a zero
+    /// balance in the other.
+    pub fn diff(&self, later: &BalanceSnapshot) -> BalanceSnapshotDiff {
+        let account_ids: BTreeSet<&String> =
+            self.balances.keys().chain(later.balances.keys()).collect();
+        let mut changes = Vec::new();
+        for account_id in account_ids {
+            let before = self.balance(account_id);
+            let after = later.balance(account_id);
+            if before == after {
+                continue;
+            }
+            let Some(line) = later
+                .balances
+                .get(account_id)
+                .or_else(|| self.balances.get(account_id))
+            else {
+                continue;
+            };
+            changes.push(BalanceChange {
+                account_id: account_id.clone(),
+                account_name: line.account.name.clone(),
+                account_type: line.account.account_type.clone(),
+                change: &after - &before,
+                balance_before: before,
+                balance_after: after,
+            });
+        }
+
+        BalanceSnapshotDiff {
+            from_as_of: self.as_of,
+            from_captured_at: self.captured_at,
+            to_as_of: later.as_of,
+            to_captured_at: later.captured_at,
+            changes,
+        }
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Capture the posted balance of every account as of a date
+    pub async fn capture_balance_snapshot(
+        &self,
+        as_of: NaiveDate,
+    ) -> LedgerResult<BalanceSnapshot> {
+        let trial_balance = self.get_trial_balance(as_of).await?;
+        Ok(BalanceSnapshot {
+            as_of,
+            captured_at: self.clock.now(),
+            balances: trial_balance.balances.into_iter().collect(),
+        })
+    }
+
+    /// Compare a snapshot with the ledger's current balances as of the same date
+    pub async fn diff_balance_snapshot(
+        &self,
+        snapshot: &BalanceSnapshot,
+    ) -> LedgerResult<BalanceSnapshotDiff> {
+        let current = self.capture_balance_snapshot(snapshot.as_of).await?;
+        Ok(snapshot.diff(&current))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_back_dated_posting_shows_in_diff() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let month_end = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let rent = |id: &str, day: u32, amount: i32| {
+            patterns::create_expense_payment(
+                id.to_string(),
+                NaiveDate::from_ymd_opt(2024, 5, day).unwrap(),
+                "Rent".to_string(),
+                "6000".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(amount),
+            )
+            .unwrap()
+        };
+
+        ledger.record_transaction(rent("r1", 1, 900)).await.unwrap();
+        let closed = ledger.capture_balance_snapshot(month_end).await.unwrap();
+        assert!(ledger
+            .diff_balance_snapshot(&closed)
+            .await
+            .unwrap()
+            .is_empty());
+
+        ledger
+            .record_transaction(rent("r2", 20, 100))
+            .await
+            .unwrap();
+        let diff = ledger.diff_balance_snapshot(&closed).await.unwrap();
+        assert_eq!(diff.changes.len(), 2);
+        let rent_change = diff.change("6000").unwrap();
+        assert_eq!(rent_change.balance_before, BigDecimal::from(900));
+        assert_eq!(rent_change.change, BigDecimal::from(100));
+        assert_eq!(
+            diff.change("1000").unwrap().balance_after,
+            BigDecimal::from(-1000)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Parallel books kept in one ledger
+//!
+//! Companies often keep a financial book under the Companies Act and a tax
+//! book under the Income Tax Act that differ only in a few postings, most
+//! often depreciation. Rather than keeping two ledgers, a transaction can
+//! carry replacement entries for a named book (see
+//! [`Transaction::set_book_entries`]); the ledger's own balances and reports
+//! stay on the principal book, and [`Ledger::book_ledger`] produces a
+//! read-only view of any other book that every report can be run against.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+use crate::utils::MemoryStorage;
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Names of the books any transaction carries replacement entries for
+    pub async fn list_books(&self) -> LedgerResult<Vec<String>> {
+        let books: BTreeSet<String> = self
+            .transaction_manager
+            .get_all_transactions(None, None)
+            .await?
+            .into_iter()
+            .flat_map(|t| t.book_entries.into_keys())
+            .collect();
+        Ok(books.into_iter().collect())
+    }
+
+    /// A copy of the ledger as kept in `book`
+    ///
+    /// Each transaction is posted with its replacement entries for the book,
+    /// or its own entries when it has none, and account balances are worked
+    /// out afresh. The copy lives in memory; changes to it do not reach this
+    /// ledger. A book no transaction names is identical to the principal book.
+    pub async fn book_ledger(&self, book: &str) -> LedgerResult<Ledger<MemoryStorage>> {
+        let mut accounts: HashMap<String, Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|mut account| {
+                account.balance = Default::default();
+                (account.id.clone(), account)
+            })
+            .collect();
+
+        let mut transactions = self
+            .transaction_manager
+            .get_all_transactions(None, None)
+            .await?;
+        for transaction in &mut transactions {
+            if let Some(entries) = transaction.book_entries.get(book) {
+                transaction.entries = entries.clone();
+            }
+            if !transaction.is_posted() {
+                continue;
+            }
+            for entry in &transaction.entries {
+                let account = accounts
+                    .get_mut(&entry.account_id)
+                    .ok_or_else(|| LedgerError::AccountNotFound(entry.account_id.clone()))?;
+                account.apply_entry(entry.entry_type.clone(), &entry.amount);
+            }
+        }
+
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.set_clock(self.clock.clone());
+        ledger.set_business_timezone(self.business_timezone);
+        let storage = &mut ledger.account_manager.storage;
+        for account in accounts.values() {
+            storage.save_account(account).await?;
+        }
+        for transaction in &transactions {
+            storage.save_transaction(transaction).await?;
+        }
+        Ok(ledger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_tax_book_depreciation_differs() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("1500", "Plant and Machinery", AccountType::Asset),
+            ("6800", "Depreciation", AccountType::Expense),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let date = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+
+        let purchase = TransactionBuilder::new(
+            "machine".to_string(),
+            NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+            "Machine bought".to_string(),
+        )
+        .debit("1500".to_string(), BigDecimal::from(100000), None)
+        .credit("1000".to_string(), BigDecimal::from(100000), None)
+        .build()
+        .unwrap();
+        ledger.record_transaction(purchase).await.unwrap();
+
+        // Straight-line 10% in the financial book, 15% WDV in the tax book
+        let mut depreciation =
+            TransactionBuilder::new("dep-fy25".to_string(), date, "Depreciation".to_string())
+                .debit("6800".to_string(), BigDecimal::from(10000), None)
+                .credit("1500".to_string(), BigDecimal::from(10000), None)
+                .build()
+                .unwrap();
+        depreciation.set_book_entries(
+            "tax",
+            vec![
+                Entry::debit("6800".to_string(), BigDecimal::from(15000), None),
+                Entry::credit("1500".to_string(), BigDecimal::from(15000), None),
+            ],
+        );
+        ledger.record_transaction(depreciation).await.unwrap();
+
+        let mut unbalanced = TransactionBuilder::new("bad".to_string(), date, "Bad".to_string())
+            .debit("6800".to_string(), BigDecimal::from(1), None)
+            .credit("1500".to_string(), BigDecimal::from(1), None)
+            .build()
+            .unwrap();
+        unbalanced.set_book_entries(
+            "tax",
+            vec![Entry::debit("6800".to_string(), BigDecimal::from(1), None)],
+        );
+        assert!(ledger.record_transaction(unbalanced).await.is_err());
+
+        assert_eq!(ledger.list_books().await.unwrap(), vec!["tax".to_string()]);
+        let financial = ledger.generate_income_statement(date, date).await.unwrap();
+        assert_eq!(financial.total_expenses, BigDecimal::from(10000));
+
+        let tax_book = ledger.book_ledger("tax").await.unwrap();
+        let tax = tax_book
+            .generate_income_statement(date, date)
+            .await
+            .unwrap();
+        assert_eq!(tax.total_expenses, BigDecimal::from(15000));
+        assert_eq!(
+            tax_book.get_account_balance("1500", None).await.unwrap(),
+            BigDecimal::from(85000)
+        );
+        assert!(tax_book.get_trial_balance(date).await.unwrap().is_balanced);
+
+        // An account posted to only in a book cannot be deleted
+        ledger
+            .create_account(
+                "6810".to_string(),
+                "Additional Depreciation".to_string(),
+                AccountType::Expense,
+                None,
+            )
+            .await
+            .unwrap();
+        let mut additional =
+            TransactionBuilder::new("add-fy25".to_string(), date, "Additional".to_string())
+                .debit("6800".to_string(), BigDecimal::from(500), None)
+                .credit("1500".to_string(), BigDecimal::from(500), None)
+                .build()
+                .unwrap();
+        additional.set_book_entries(
+            "tax",
+            vec![
+                Entry::debit("6810".to_string(), BigDecimal::from(500), None),
+                Entry::credit("1500".to_string(), BigDecimal::from(500), None),
+            ],
+        );
+        ledger.record_transaction(additional).await.unwrap();
+        assert!(matches!(
+            ledger.delete_account("6810").await,
+            Err(LedgerError::AccountInUse { .. })
+        ));
+        assert!(ledger.book_ledger("tax").await.is_ok());
+    }
+}
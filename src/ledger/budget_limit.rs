@@ -0,0 +1,403 @@
+//! Spending limits per department and period
+//!
+//! A [`BudgetLimit`] caps the net expense posted with an entry dimension
+//! value (usually a [`DEPARTMENT_DIMENSION`]) between two dates. Postings
+//! that would take spending past a limit are blocked or, under
+//! [`BudgetEnforcement::Flag`], posted with a note saying so. A blocked
+//! posting can still go through with
+//! [`record_transaction_with_budget_override`](Ledger::record_transaction_with_budget_override),
+//! which records who overrode the limit and why on the transaction itself.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Entry dimension identifying the department an entry is charged to
+pub const DEPARTMENT_DIMENSION: &str = "department";
+/// Transaction metadata key marking a posting that went over budget
+pub const BUDGET_EXCEEDED_KEY: &str = "budget_exceeded";
+
+/// What happens to a posting that exceeds a budget limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BudgetEnforcement {
+    /// Reject it unless overridden
+    #[default]
+    Block,
+    /// Post it, noting the breach on the transaction
+    Flag,
+}
+
+/// Cap on the net expense posted with a dimension value within a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BudgetLimit {
+    pub dimension: String,
+    pub value: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub amount: BigDecimal,
+}
+
+impl BudgetLimit {
+    /// Limit a department's spending within a period
+    pub fn department(
+        department: String,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        amount: BigDecimal,
+    ) -> Self {
+        Self {
+            dimension: DEPARTMENT_DIMENSION.to_string(),
+            value: department,
+            start_date,
+            end_date,
+            amount,
+        }
+    }
+
+    /// Whether an entry of a transaction on `date` counts against the limit
+    fn covers(&self, date: NaiveDate, entry: &Entry) -> bool {
+        date >= self.start_date
+            && date <= self.end_date
+            && entry.dimensions.get(&self.dimension) == Some(&self.value)
+    }
+
+    fn same_budget(&self, other: &BudgetLimit) -> bool {
+        self.dimension == other.dimension
+            && self.value == other.value
+            && self.start_date == other.start_date
+    }
+}
+
+/// A posting that would take spending past a limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BudgetBreach {
+    pub limit: BudgetLimit,
+    /// Spent before the posting
+    pub spent: BigDecimal,
+    /// Net expense the posting adds
+    pub requested: BigDecimal,
+    /// Amount by which the limit would be exceeded
+    pub excess: BigDecimal,
+}
+
+impl BudgetBreach {
+    fn describe(&self) -> String {
+        format!(
+            "{} {} budget of {} for {} to {} exceeded by {}",
+            self.limit.dimension,
+            self.limit.value,
+            self.limit.amount,
+            self.limit.start_date,
+            self.limit.end_date,
+            self.excess
+        )
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Set a spending limit, replacing any for the same dimension value and
+    /// start date
+    pub fn set_budget_limit(&mut self, limit: BudgetLimit) -> LedgerResult<()> {
+        if limit.end_date < limit.start_date {
+            return Err(LedgerError::Validation(format!(
+                "Budget for {} {} ends before it starts",
+                limit.dimension, limit.value
+            )));
+        }
+        self.budget_limits
+            .retain(|existing| !existing.same_budget(&limit));
+        self.budget_limits.push(limit);
+        Ok(())
+    }
+
+    /// Remove the limits for a dimension value
+    pub fn remove_budget_limits(&mut self, dimension: &str, value: &str) {
+        self.budget_limits
+            .retain(|limit| limit.dimension != dimension || limit.value != value);
+    }
+
+    /// All spending limits
+    pub fn budget_limits(&self) -> &[BudgetLimit] {
+        &self.budget_limits
+    }
+
+    /// Choose whether over-budget postings are blocked or flagged
+    pub fn set_budget_enforcement(&mut self, enforcement: BudgetEnforcement) {
+        self.budget_enforcement = enforcement;
+    }
+
+    /// How over-budget postings are handled
+    pub fn budget_enforcement(&self) -> BudgetEnforcement {
+        self.budget_enforcement
+    }
+
+    /// Net expense posted against a limit so far
+    pub async fn budget_spent(&self, limit: &BudgetLimit) -> LedgerResult<BigDecimal> {
+        self.spent_excluding(limit, None).await
+    }
+
+    /// Net expense posted against a limit, leaving out one transaction's
+    /// stored version so an edit is not counted twice
+    async fn spent_excluding(
+        &self,
+        limit: &BudgetLimit,
+        transaction_id: Option<&str>,
+    ) -> LedgerResult<BigDecimal> {
+        let filter = EntryFilter {
+            start_date: Some(limit.start_date),
+            end_date: Some(limit.end_date),
+            dimension: Some((limit.dimension.clone(), limit.value.clone())),
+            ..Default::default()
+        };
+        let accounts = self.account_types().await?;
+        Ok(self
+            .get_entries(&filter)
+            .await?
+            .iter()
+            .filter(|row| Some(row.transaction_id.as_str()) != transaction_id)
+            .filter(|row| accounts.get(&row.entry.account_id) == Some(&AccountType::Expense))
+            .map(|row| {
+                AccountType::Expense.balance_effect(&row.entry.entry_type, &row.entry.amount)
+            })
+            .sum())
+    }
+
+    /// Limits a transaction would exceed if posted, in place of any stored
+    /// version of it
+    pub async fn check_budget_limits(
+        &self,
+        transaction: &Transaction,
+    ) -> LedgerResult<Vec<BudgetBreach>> {
+        let mut breaches = Vec::new();
+        if self.budget_limits.is_empty() {
+            return Ok(breaches);
+        }
+        let mut account_types = HashMap::new();
+        for entry in &transaction.entries {
+            if !account_types.contains_key(&entry.account_id) {
+                let account = self
+                    .account_manager
+                    .get_account_required(&entry.account_id)
+                    .await?;
+                account_types.insert(entry.account_id.clone(), account.account_type);
+            }
+        }
+        for limit in &self.budget_limits {
+            let requested: BigDecimal = transaction
+                .entries
+                .iter()
+                .filter(|entry| {
+                    account_types[&entry.account_id] == AccountType::Expense
+                        && limit.covers(transaction.date, entry)
+                })
+                .map(|entry| AccountType::Expense.balance_effect(&entry.entry_type, &entry.amount))
+                .sum();
+            if requested <= BigDecimal::zero() {
+                continue;
+            }
+            let spent = self.spent_excluding(limit, Some(&transaction.id)).await?;
+            let excess = &spent + &requested - &limit.amount;
+            if excess > BigDecimal::zero() {
+                breaches.push(BudgetBreach {
+                    limit: limit.clone(),
+                    spent,
+                    requested,
+                    excess,
+                });
+            }
+        }
+        Ok(breaches)
+    }
+
+    /// Apply the budget limits to a transaction about to be recorded
+    ///
+    /// Under [`BudgetEnforcement::Flag`] the breaches are noted on the
+    /// transaction and it goes ahead.
+    pub(crate) async fn enforce_budget_limits(
+        &self,
+        transaction: &mut Transaction,
+    ) -> LedgerResult<()> {
+        let breaches = self.check_budget_limits(transaction).await?;
+        let Some(first) = breaches.first() else {
+            return Ok(());
+        };
+        match self.budget_enforcement {
+            BudgetEnforcement::Block => Err(LedgerError::BudgetExceeded {
+                dimension: first.limit.dimension.clone(),
+                value: first.limit.value.clone(),
+                excess: first.excess.clone(),
+            }),
+            BudgetEnforcement::Flag => {
+                self.note_breaches(transaction, &breaches, "Over budget");
+                Ok(())
+            }
+        }
+    }
+
+    fn note_breaches(
+        &self,
+        transaction: &mut Transaction,
+        breaches: &[BudgetBreach],
+        heading: &str,
+    ) {
+        let details: Vec<String> = breaches.iter().map(BudgetBreach::describe).collect();
+        transaction
+            .metadata
+            .insert(BUDGET_EXCEEDED_KEY.to_string(), true.into());
+        self.add_check_note(transaction, format!("{}: {}", heading, details.join("; ")));
+    }
+
+    /// Record a transaction even if it exceeds a budget limit
+    ///
+    /// The override must be authorized for the current actor, and the
+    /// reason and the limits exceeded are noted on the transaction.
+    pub async fn record_transaction_with_budget_override(
+        &mut self,
+        mut transaction: Transaction,
+        reason: &str,
+    ) -> LedgerResult<Transaction> {
+        if reason.trim().is_empty() {
+            return Err(LedgerError::Validation(
+                "A budget override needs a reason".to_string(),
+            ));
+        }
+        let breaches = self.check_budget_limits(&transaction).await?;
+        if !breaches.is_empty() {
+            self.authorize(LedgerOperation::OverrideBudget {
+                transaction: &transaction,
+            })?;
+            self.note_breaches(
+                &mut transaction,
+                &breaches,
+                &format!("Budget override ({})", reason.trim()),
+            );
+        }
+        self.post_transaction(transaction, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    fn spend(id: &str, date: NaiveDate, amount: i32) -> Transaction {
+        TransactionBuilder::new(id.to_string(), date, "Supplies".to_string())
+            .entry(
+                Entry::debit("6000".to_string(), BigDecimal::from(amount), None)
+                    .with_dimension(DEPARTMENT_DIMENSION.to_string(), "marketing".to_string()),
+            )
+            .credit("1000".to_string(), BigDecimal::from(amount), None)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_department_budget_block_flag_and_override() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        ledger
+            .set_budget_limit(BudgetLimit::department(
+                "marketing".to_string(),
+                date(4, 1),
+                date(6, 30),
+                BigDecimal::from(5000),
+            ))
+            .unwrap();
+
+        ledger
+            .record_transaction(spend("T1", date(4, 10), 4000))
+            .await
+            .unwrap();
+        let error = ledger
+            .record_transaction(spend("T2", date(5, 10), 1500))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "budget_exceeded");
+        // Outside the budget period
+        ledger
+            .record_transaction(spend("T3", date(7, 1), 1500))
+            .await
+            .unwrap();
+
+        ledger.set_actor(Some(Actor::new(
+            "cfo".to_string(),
+            vec!["finance".to_string()],
+        )));
+        ledger
+            .record_transaction_with_budget_override(
+                spend("T2", date(5, 10), 1500),
+                "Trade show deposit",
+            )
+            .await
+            .unwrap();
+        let overridden = ledger.get_transaction("T2").await.unwrap().unwrap();
+        assert_eq!(overridden.notes[0].author.as_deref(), Some("cfo"));
+        assert!(overridden.notes[0].text.contains("Trade show deposit"));
+        assert!(overridden.metadata.contains_key(BUDGET_EXCEEDED_KEY));
+
+        ledger.set_budget_enforcement(BudgetEnforcement::Flag);
+        ledger
+            .record_transaction(spend("T4", date(6, 1), 100))
+            .await
+            .unwrap();
+        let flagged = ledger.get_transaction("T4").await.unwrap().unwrap();
+        assert!(flagged.notes[0].text.starts_with("Over budget"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_override_leaves_limits_enforced() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        ledger
+            .set_budget_limit(BudgetLimit::department(
+                "marketing".to_string(),
+                date(4, 1),
+                date(6, 30),
+                BigDecimal::from(1000),
+            ))
+            .unwrap();
+        ledger.set_authorization_policy(Box::new(
+            RoleBasedPolicy::new().require("override_budget", "finance"),
+        ));
+        ledger.set_actor(Some(Actor::new("clerk".to_string(), vec![])));
+
+        let error = ledger
+            .record_transaction_with_budget_override(spend("T1", date(4, 10), 1500), "Urgent")
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "unauthorized");
+        let error = ledger
+            .record_transaction(spend("T1", date(4, 10), 1500))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "budget_exceeded");
+
+        // An authorized override that fails to post does not leak either
+        ledger.set_actor(Some(Actor::new(
+            "cfo".to_string(),
+            vec!["finance".to_string()],
+        )));
+        ledger.lock_period(date(4, 30)).unwrap();
+        assert!(ledger
+            .record_transaction_with_budget_override(spend("T1", date(4, 10), 1500), "Urgent")
+            .await
+            .is_err());
+        let error = ledger
+            .record_transaction(spend("T2", date(5, 10), 1500))
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), "budget_exceeded");
+    }
+}
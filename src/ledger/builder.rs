@@ -0,0 +1,239 @@
+//! Fluent construction of a fully configured ledger
+//!
+//! [`LedgerBuilder`] collects the pieces a ledger can be given (validators,
+//! configuration, clock, id generator, observers, report cache,
+//! authorization, signer, posting rules) and applies them in one go.
+//! Anything left unset gets the same default [`Ledger::new`] uses.
+
+use std::sync::Arc;
+
+use crate::ledger::{Ledger, LedgerConfig, PostingRules, ReportCache};
+use crate::traits::*;
+use crate::types::*;
+
+/// Builder for [`Ledger`], started with [`Ledger::builder`]
+pub struct LedgerBuilder<S: LedgerStorage> {
+    storage: S,
+    account_validator: Option<Box<dyn AccountValidator>>,
+    transaction_validator: Option<Box<dyn TransactionValidator>>,
+    config: LedgerConfig,
+    clock: Option<Arc<dyn Clock>>,
+    id_generator: Option<Arc<dyn IdGenerator>>,
+    business_timezone: Option<BusinessTimezone>,
+    observers: Vec<Arc<dyn LedgerObserver>>,
+    report_cache: Option<ReportCache>,
+    authorization_policy: Option<Box<dyn AuthorizationPolicy>>,
+    actor: Option<Actor>,
+    signer: Option<Arc<dyn Signer>>,
+    posting_rules: Option<PostingRules>,
+}
+
+impl<S: LedgerStorage + Clone> LedgerBuilder<S> {
+    /// Start a builder over a storage backend
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            account_validator: None,
+            transaction_validator: None,
+            config: LedgerConfig::default(),
+            clock: None,
+            id_generator: None,
+            business_timezone: None,
+            observers: Vec::new(),
+            report_cache: None,
+            authorization_policy: None,
+            actor: None,
+            signer: None,
+            posting_rules: None,
+        }
+    }
+
+    /// Validate accounts with `validator` instead of [`DefaultAccountValidator`]
+    pub fn account_validator(mut self, validator: Box<dyn AccountValidator>) -> Self {
+        self.account_validator = Some(validator);
+        self
+    }
+
+    /// Validate transactions with `validator` instead of
+    /// [`DefaultTransactionValidator`]
+    pub fn transaction_validator(mut self, validator: Box<dyn TransactionValidator>) -> Self {
+        self.transaction_validator = Some(validator);
+        self
+    }
+
+    /// Use `config` instead of [`LedgerConfig::default`]
+    pub fn config(mut self, config: LedgerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Take timestamps from `clock` instead of the system time
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Give transactions recorded without an id one from `generator`
+    pub fn id_generator(mut self, generator: Arc<dyn IdGenerator>) -> Self {
+        self.id_generator = Some(generator);
+        self
+    }
+
+    /// Show timestamps in `timezone`
+    pub fn business_timezone(mut self, timezone: BusinessTimezone) -> Self {
+        self.business_timezone = Some(timezone);
+        self
+    }
+
+    /// Notify `observer` of every stored change, such as an audit trail;
+    /// may be called more than once
+    pub fn observer(mut self, observer: Arc<dyn LedgerObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Memoize reports in `cache`
+    pub fn report_cache(mut self, cache: ReportCache) -> Self {
+        self.report_cache = Some(cache);
+        self
+    }
+
+    /// Consult `policy` before every state-changing operation
+    pub fn authorization_policy(mut self, policy: Box<dyn AuthorizationPolicy>) -> Self {
+        self.authorization_policy = Some(policy);
+        self
+    }
+
+    /// Authorize operations as `actor`
+    pub fn actor(mut self, actor: Actor) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Sign issued invoices and closing entries with `signer`
+    pub fn signer(mut self, signer: Arc<dyn Signer>) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Check recorded transactions against `rules`
+    pub fn posting_rules(mut self, rules: PostingRules) -> Self {
+        self.posting_rules = Some(rules);
+        self
+    }
+
+    /// Create the ledger, failing if the configuration is invalid
+    pub fn build(self) -> LedgerResult<Ledger<S>> {
+        let mut ledger = Ledger::with_validators(
+            self.storage,
+            self.account_validator
+                .unwrap_or_else(|| Box::new(DefaultAccountValidator)),
+            self.transaction_validator
+                .unwrap_or_else(|| Box::new(DefaultTransactionValidator)),
+        );
+        ledger.apply_config(self.config)?;
+        if let Some(clock) = self.clock {
+            ledger.set_clock(clock);
+        }
+        if let Some(generator) = self.id_generator {
+            ledger.set_id_generator(generator);
+        }
+        if let Some(timezone) = self.business_timezone {
+            ledger.set_business_timezone(timezone);
+        }
+        for observer in self.observers {
+            ledger.add_observer(observer);
+        }
+        if let Some(cache) = self.report_cache {
+            ledger.set_report_cache(cache);
+        }
+        if let Some(policy) = self.authorization_policy {
+            ledger.set_authorization_policy(policy);
+        }
+        ledger.set_actor(self.actor);
+        ledger.set_signer(self.signer);
+        ledger.set_posting_rules(self.posting_rules);
+        Ok(ledger)
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Start building a ledger over a storage backend
+    pub fn builder(storage: S) -> LedgerBuilder<S> {
+        LedgerBuilder::new(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::{MemoryStorage, SequenceIdGenerator};
+    use bigdecimal::BigDecimal;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct EventCounter(AtomicUsize);
+
+    impl LedgerObserver for EventCounter {
+        fn on_event(&self, _event: &LedgerEvent<'_>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_applies_every_setting() {
+        let now = Utc.with_ymd_and_hms(2024, 8, 1, 9, 0, 0).unwrap();
+        let counter = Arc::new(EventCounter::default());
+        let cache = ReportCache::default();
+        let mut ledger = Ledger::builder(MemoryStorage::new())
+            .config(LedgerConfig {
+                suspense_account_id: Some("1000".to_string()),
+                ..LedgerConfig::default()
+            })
+            .clock(Arc::new(FixedClock::new(now)))
+            .id_generator(Arc::new(SequenceIdGenerator::new("JV-")))
+            .observer(counter.clone())
+            .report_cache(cache.clone())
+            .build()
+            .unwrap();
+        assert_eq!(ledger.suspense_account(), Some("1000"));
+        assert!(ledger.report_cache().is_some());
+
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let rent = patterns::create_expense_payment(
+            "rent".to_string(),
+            NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+            "Rent".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(300),
+        )
+        .unwrap();
+        ledger.record_transaction(rent).await.unwrap();
+        let utilities = patterns::create_expense_payment(
+            String::new(),
+            NaiveDate::from_ymd_opt(2024, 8, 2).unwrap(),
+            "Electricity".to_string(),
+            "6100".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(40),
+        )
+        .unwrap();
+        ledger.record_transaction(utilities).await.unwrap();
+        assert!(ledger.get_transaction("JV-000001").await.unwrap().is_some());
+        assert_eq!(ledger.next_transaction_id(), "JV-000002");
+        let cash = ledger.get_account("1000").await.unwrap().unwrap();
+        assert_eq!(cash.created_at, now);
+        assert!(counter.0.load(Ordering::SeqCst) > 0);
+
+        let invalid = Ledger::builder(MemoryStorage::new())
+            .config(LedgerConfig {
+                fiscal_year_start_month: 13,
+                ..LedgerConfig::default()
+            })
+            .build();
+        assert!(invalid.is_err());
+    }
+}
@@ -0,0 +1,326 @@
+//! Cash-basis income statements
+//!
+//! On the cash basis, revenue and expenses count when money moves through a
+//! cash or bank account rather than when they are invoiced. The ledger works
+//! this out from postings it already has:
+//!
+//! - income and expense lines posted against a designated cash account (a
+//!   cash sale, a bank charge) count on the transaction date;
+//! - a receipt or payment naming the invoices or bills it settles under
+//!   [`SETTLES_KEY`] brings in the income and expense lines of those
+//!   documents, in proportion to the amount settled, on the receipt date;
+//! - everything else (invoices still unpaid, depreciation, accruals) is left
+//!   out.
+//!
+//! [`SETTLES_KEY`] holds one document ID, a list of IDs settled in order up
+//! to each document's total, or a map of ID to amount settled.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key naming the invoices or bills a receipt or
+/// payment settles
+pub const SETTLES_KEY: &str = "settles";
+
+/// Account metadata key marking a cash or bank account
+const CASH_ACCOUNT_KEY: &str = "cash_account";
+
+/// When revenue and expenses are recognized in reports
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ReportingBasis {
+    /// When invoiced or incurred
+    #[default]
+    Accrual,
+    /// When received or paid through a cash or bank account
+    Cash,
+}
+
+fn is_profit_and_loss(account_type: &AccountType) -> bool {
+    matches!(account_type, AccountType::Income | AccountType::Expense)
+}
+
+/// Documents a receipt or payment settles, with the amount settled against
+/// each (`None` when it is to be allocated)
+fn settled_documents(transaction: &Transaction) -> Vec<(String, Option<BigDecimal>)> {
+    match transaction.metadata.get(SETTLES_KEY) {
+        Some(MetaValue::Text(id)) => vec![(id.clone(), None)],
+        Some(MetaValue::List(ids)) => ids
+            .iter()
+            .filter_map(MetaValue::as_str)
+            .map(|id| (id.to_string(), None))
+            .collect(),
+        Some(MetaValue::Map(amounts)) => amounts
+            .iter()
+            .map(|(id, amount)| (id.clone(), amount.as_decimal()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Mark an account as a cash or bank account for cash-basis reporting
+    pub async fn designate_cash_account(&mut self, account_id: &str) -> LedgerResult<Account> {
+        let mut account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        if account.account_type != AccountType::Asset {
+            return Err(LedgerError::Validation(format!(
+                "Cash account {} must be an asset account",
+                account_id
+            )));
+        }
+        account
+            .metadata
+            .insert(CASH_ACCOUNT_KEY.to_string(), true.into());
+        account.updated_at = self.clock.now();
+        self.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// All designated cash and bank accounts
+    pub async fn list_cash_accounts(&self) -> LedgerResult<Vec<Account>> {
+        let mut accounts: Vec<Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .filter(|a| {
+                a.metadata
+                    .get(CASH_ACCOUNT_KEY)
+                    .and_then(MetaValue::as_bool)
+                    .unwrap_or(false)
+            })
+            .collect();
+        accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(accounts)
+    }
+
+    /// Generate an income statement on the accrual or cash basis
+    pub async fn generate_income_statement_on_basis(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        basis: ReportingBasis,
+    ) -> LedgerResult<IncomeStatement> {
+        match basis {
+            ReportingBasis::Accrual => self.generate_income_statement(start_date, end_date).await,
+            ReportingBasis::Cash => {
+                self.generate_cash_basis_income_statement(start_date, end_date)
+                    .await
+            }
+        }
+    }
+
+    /// Generate an income statement recognizing revenue and expenses when
+    /// cash moved (see the [module docs](self))
+    ///
+    /// Fails with [`LedgerError::Validation`] when no cash account has been
+    /// designated, and with [`LedgerError::TransactionNotFound`] when a
+    /// receipt settles a document that does not exist.
+    pub async fn generate_cash_basis_income_statement(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<IncomeStatement> {
+        let cash_accounts: HashSet<String> = self
+            .list_cash_accounts()
+            .await?
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        if cash_accounts.is_empty() {
+            return Err(LedgerError::Validation(
+                "Cash-basis reporting needs at least one designated cash account".to_string(),
+            ));
+        }
+        let accounts: HashMap<String, Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|a| (a.id.clone(), a))
+            .collect();
+        let account_type = |id: &str| -> LedgerResult<AccountType> {
+            accounts
+                .get(id)
+                .map(|a| a.account_type.clone())
+                .ok_or_else(|| LedgerError::AccountNotFound(id.to_string()))
+        };
+
+        let policy = self.currency_amount_policy();
+        let mut recognized: BTreeMap<String, BigDecimal> = BTreeMap::new();
+        for transaction in self
+            .get_transactions(Some(start_date), Some(end_date))
+            .await?
+        {
+            let cash_movement: BigDecimal = transaction
+                .entries
+                .iter()
+                .filter(|e| cash_accounts.contains(&e.account_id))
+                .map(|e| match e.entry_type {
+                    EntryType::Debit => e.amount.clone(),
+                    EntryType::Credit => -e.amount.clone(),
+                })
+                .sum();
+            if cash_movement.is_zero() {
+                continue;
+            }
+
+            // Income and expense settled in cash on the spot
+            let mut settled = BigDecimal::zero();
+            let settling_side = if cash_movement.is_positive() {
+                EntryType::Credit
+            } else {
+                EntryType::Debit
+            };
+            for entry in &transaction.entries {
+                if cash_accounts.contains(&entry.account_id) {
+                    continue;
+                }
+                let entry_account_type = account_type(&entry.account_id)?;
+                if is_profit_and_loss(&entry_account_type) {
+                    *recognized.entry(entry.account_id.clone()).or_default() +=
+                        entry_account_type.balance_effect(&entry.entry_type, &entry.amount);
+                } else if entry.entry_type == settling_side {
+                    settled += &entry.amount;
+                }
+            }
+
+            // Income and expense of the documents this settles
+            let mut unallocated = settled;
+            for (document_id, amount) in settled_documents(&transaction) {
+                let document = self
+                    .get_transaction(&document_id)
+                    .await?
+                    .ok_or_else(|| LedgerError::TransactionNotFound(document_id.clone()))?;
+                let total = document.total_debits();
+                if total.is_zero() {
+                    continue;
+                }
+                let applied = match amount {
+                    Some(amount) => amount,
+                    None => {
+                        let applied = unallocated.clone().min(total.clone());
+                        unallocated -= &applied;
+                        applied
+                    }
+                };
+                for entry in &document.entries {
+                    let entry_account_type = account_type(&entry.account_id)?;
+                    if !is_profit_and_loss(&entry_account_type) {
+                        continue;
+                    }
+                    let full = entry_account_type.balance_effect(&entry.entry_type, &entry.amount);
+                    let share = if applied == total {
+                        full
+                    } else {
+                        policy.round(&(full * &applied / &total))
+                    };
+                    *recognized.entry(entry.account_id.clone()).or_default() += share;
+                }
+            }
+        }
+
+        let mut revenue = Vec::new();
+        let mut expenses = Vec::new();
+        let mut profit_and_loss: Vec<&Account> = accounts
+            .values()
+            .filter(|a| is_profit_and_loss(&a.account_type))
+            .collect();
+        profit_and_loss.sort_by(|a, b| a.id.cmp(&b.id));
+        for account in profit_and_loss {
+            let balance = recognized.remove(&account.id).unwrap_or_default();
+            let line = AccountBalance::from_balance(account.clone(), balance);
+            match account.account_type {
+                AccountType::Income => revenue.push(line),
+                _ => expenses.push(line),
+            }
+        }
+
+        let total_revenue: BigDecimal = revenue.iter().map(|ab| ab.balance_amount()).sum();
+        let total_expenses: BigDecimal = expenses.iter().map(|ab| ab.balance_amount()).sum();
+        let net_income = &total_revenue - &total_expenses;
+        Ok(IncomeStatement {
+            start_date,
+            end_date,
+            revenue,
+            expenses,
+            total_revenue,
+            total_expenses,
+            net_income,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_cash_basis_follows_receipts_and_payments() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger.designate_cash_account("1000").await.unwrap();
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let amount = BigDecimal::from;
+
+        let transactions = [
+            // Two invoices in March, one paid in full and one half paid in April
+            TransactionBuilder::new("inv-1".to_string(), day(3, 10), "Invoice".to_string())
+                .debit("1200".to_string(), amount(1000), None)
+                .credit("4000".to_string(), amount(1000), None),
+            TransactionBuilder::new("inv-2".to_string(), day(3, 20), "Invoice".to_string())
+                .debit("1200".to_string(), amount(600), None)
+                .credit("4000".to_string(), amount(600), None),
+            TransactionBuilder::new("rcpt-1".to_string(), day(4, 5), "Receipt".to_string())
+                .debit("1000".to_string(), amount(1300), None)
+                .credit("1200".to_string(), amount(1300), None)
+                .metadata(
+                    SETTLES_KEY.to_string(),
+                    vec![MetaValue::from("inv-1"), MetaValue::from("inv-2")],
+                ),
+            // A bill for April rent still unpaid
+            TransactionBuilder::new("bill-1".to_string(), day(4, 1), "Rent bill".to_string())
+                .debit("6000".to_string(), amount(800), None)
+                .credit("2000".to_string(), amount(800), None),
+            // Rent paid straight from cash
+            TransactionBuilder::new("pay-1".to_string(), day(4, 2), "Rent".to_string())
+                .debit("6000".to_string(), amount(200), None)
+                .credit("1000".to_string(), amount(200), None),
+        ];
+        for builder in transactions {
+            ledger
+                .record_transaction(builder.build().unwrap())
+                .await
+                .unwrap();
+        }
+
+        let april = ledger
+            .generate_income_statement_on_basis(day(4, 1), day(4, 30), ReportingBasis::Cash)
+            .await
+            .unwrap();
+        assert_eq!(april.total_revenue, amount(1300));
+        assert_eq!(april.total_expenses, amount(200));
+        assert_eq!(april.net_income, amount(1100));
+
+        let march = ledger
+            .generate_cash_basis_income_statement(day(3, 1), day(3, 31))
+            .await
+            .unwrap();
+        assert_eq!(march.net_income, amount(0));
+
+        let accrual = ledger
+            .generate_income_statement_on_basis(day(3, 1), day(4, 30), ReportingBasis::Accrual)
+            .await
+            .unwrap();
+        assert_eq!(accrual.total_revenue, amount(1600));
+    }
+}
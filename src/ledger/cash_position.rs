@@ -0,0 +1,265 @@
+//! Cash position for a dashboard
+//!
+//! One call gathers what a treasury widget shows: the balance of every
+//! designated cash and bank account, bank lines still sitting in suspense,
+//! and post-dated cheques and payments that will move cash later. Post-dated
+//! items are those recorded with a date after the position date, plus any
+//! pending cheques of a [`ChequeRegister`] added with
+//! [`CashPosition::with_pending_cheques`].
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::{ChequeDetails, ChequeRegister, Ledger};
+use crate::reconciliation::SuspenseItem;
+use crate::traits::*;
+use crate::types::*;
+
+/// Balance of one cash or bank account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CashAccountPosition {
+    pub account_id: String,
+    pub account_name: String,
+    pub balance: BigDecimal,
+}
+
+/// A cheque or payment that will move cash after the position date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PostDatedItem {
+    pub transaction_id: String,
+    pub account_id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Cheque number, when the item is a cheque
+    pub cheque_number: Option<String>,
+    /// Positive for money coming in, negative for money going out
+    pub amount: BigDecimal,
+    /// Whether the item is already in the ledger or held in a register
+    pub recorded: bool,
+}
+
+/// Cash and bank balances with the items that will change them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CashPosition {
+    pub as_of: NaiveDate,
+    /// Designated cash and bank accounts, ordered by account ID
+    pub accounts: Vec<CashAccountPosition>,
+    pub total_balance: BigDecimal,
+    /// Bank lines posted to suspense and not yet classified
+    pub unreconciled: Vec<SuspenseItem>,
+    /// Net of the unreconciled items, positive for money received
+    pub unreconciled_total: BigDecimal,
+    /// Post-dated items, ordered by date
+    pub post_dated: Vec<PostDatedItem>,
+    pub post_dated_receipts: BigDecimal,
+    pub post_dated_payments: BigDecimal,
+    /// Balance once every post-dated item has cleared
+    pub projected_balance: BigDecimal,
+}
+
+impl CashPosition {
+    /// Add the pending cheques of a register that move cash through the
+    /// position's accounts
+    pub fn with_pending_cheques(mut self, register: &ChequeRegister) -> Self {
+        for cheque in register.pending() {
+            for entry in &cheque.transaction.entries {
+                if self
+                    .accounts
+                    .iter()
+                    .any(|a| a.account_id == entry.account_id)
+                {
+                    self.post_dated.push(PostDatedItem {
+                        transaction_id: cheque.transaction.id.clone(),
+                        account_id: entry.account_id.clone(),
+                        date: cheque.details.cheque_date,
+                        description: cheque.transaction.description.clone(),
+                        cheque_number: Some(cheque.details.number.clone()),
+                        amount: AccountType::Asset.balance_effect(&entry.entry_type, &entry.amount),
+                        recorded: false,
+                    });
+                }
+            }
+        }
+        self.refresh_totals();
+        self
+    }
+
+    fn refresh_totals(&mut self) {
+        self.post_dated.sort_by(|a, b| {
+            a.date
+                .cmp(&b.date)
+                .then_with(|| a.transaction_id.cmp(&b.transaction_id))
+        });
+        self.post_dated_receipts = BigDecimal::zero();
+        self.post_dated_payments = BigDecimal::zero();
+        for item in &self.post_dated {
+            if item.amount > BigDecimal::zero() {
+                self.post_dated_receipts += &item.amount;
+            } else {
+                self.post_dated_payments -= &item.amount;
+            }
+        }
+        self.projected_balance =
+            &self.total_balance + &self.post_dated_receipts - &self.post_dated_payments;
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Cash and bank balances at the end of `as_of`, with unreconciled and
+    /// post-dated items
+    ///
+    /// Accounts are those marked with
+    /// [`designate_cash_account`](Ledger::designate_cash_account).
+    pub async fn get_cash_position(&self, as_of: NaiveDate) -> LedgerResult<CashPosition> {
+        let mut position = CashPosition {
+            as_of,
+            accounts: Vec::new(),
+            total_balance: BigDecimal::zero(),
+            unreconciled: Vec::new(),
+            unreconciled_total: BigDecimal::zero(),
+            post_dated: Vec::new(),
+            post_dated_receipts: BigDecimal::zero(),
+            post_dated_payments: BigDecimal::zero(),
+            projected_balance: BigDecimal::zero(),
+        };
+
+        let after = as_of.succ_opt().unwrap_or(NaiveDate::MAX);
+        for account in self.list_cash_accounts().await? {
+            let balance = self.get_account_balance(&account.id, Some(as_of)).await?;
+            position.total_balance += &balance;
+            for transaction in self
+                .get_account_transactions(&account.id, Some(after), None)
+                .await?
+            {
+                let amount: BigDecimal = transaction
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.account_id == account.id)
+                    .map(|entry| {
+                        AccountType::Asset.balance_effect(&entry.entry_type, &entry.amount)
+                    })
+                    .sum();
+                position.post_dated.push(PostDatedItem {
+                    account_id: account.id.clone(),
+                    date: transaction.date,
+                    description: transaction.description.clone(),
+                    cheque_number: ChequeDetails::from_transaction(&transaction)
+                        .map(|cheque| cheque.number),
+                    amount,
+                    recorded: true,
+                    transaction_id: transaction.id,
+                });
+            }
+            position.accounts.push(CashAccountPosition {
+                account_id: account.id,
+                account_name: account.name,
+                balance,
+            });
+        }
+
+        if self.suspense_account().is_some() {
+            position.unreconciled = self
+                .list_suspense_items()
+                .await?
+                .into_iter()
+                .filter(|item| item.date <= as_of)
+                .collect();
+            position.unreconciled_total = position.unreconciled.iter().map(|i| &i.amount).sum();
+        }
+        position.refresh_totals();
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::reconciliation::BankStatementLine;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_cash_position() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "1010".to_string(),
+                "Petty Cash".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger
+            .create_account(
+                "9999".to_string(),
+                "Suspense".to_string(),
+                AccountType::Liability,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger.set_suspense_account(Some("9999".to_string()));
+        ledger.designate_cash_account("1000").await.unwrap();
+        ledger.designate_cash_account("1010").await.unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 10, d).unwrap();
+
+        for (id, day, debit, credit, amount) in [
+            ("T1", 1, "1000", "3000", 50000),
+            ("T2", 2, "1010", "1000", 2000),
+            ("T3", 20, "6000", "1000", 8000),
+        ] {
+            let builder = TransactionBuilder::new(id.to_string(), date(day), id.to_string())
+                .debit(debit.to_string(), BigDecimal::from(amount), None)
+                .credit(credit.to_string(), BigDecimal::from(amount), None);
+            ledger
+                .record_transaction(builder.build().unwrap())
+                .await
+                .unwrap();
+        }
+        ledger
+            .import_bank_lines_to_suspense(
+                "1000",
+                &[BankStatementLine::new(
+                    date(5),
+                    "NEFT UNKNOWN".to_string(),
+                    BigDecimal::from(750),
+                    None,
+                )],
+            )
+            .await
+            .unwrap();
+
+        let mut register = ChequeRegister::new();
+        register
+            .register(
+                TransactionBuilder::new("PDC-1".to_string(), date(25), "Customer PDC".to_string())
+                    .debit("1000".to_string(), BigDecimal::from(3000), None)
+                    .credit("1200".to_string(), BigDecimal::from(3000), None)
+                    .build()
+                    .unwrap(),
+                ChequeDetails::new("884412".to_string(), date(25), None),
+            )
+            .unwrap();
+
+        let position = ledger
+            .get_cash_position(date(10))
+            .await
+            .unwrap()
+            .with_pending_cheques(&register);
+        assert_eq!(position.accounts.len(), 2);
+        assert_eq!(position.accounts[1].balance, BigDecimal::from(2000));
+        assert_eq!(position.total_balance, BigDecimal::from(50750));
+        assert_eq!(position.unreconciled_total, BigDecimal::from(750));
+        assert_eq!(position.post_dated.len(), 2);
+        assert!(position.post_dated[0].recorded);
+        assert_eq!(position.post_dated_payments, BigDecimal::from(8000));
+        assert_eq!(position.post_dated_receipts, BigDecimal::from(3000));
+        assert_eq!(position.projected_balance, BigDecimal::from(45750));
+    }
+}
@@ -0,0 +1,454 @@
+//! Cheque handling: cheque details on receipts and payments, a register for
+//! post-dated cheques and bounced cheque reversal
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ledger::{patterns, Ledger};
+use crate::traits::*;
+use crate::types::*;
+
+const CHEQUE_NUMBER_KEY: &str = "cheque_number";
+const CHEQUE_DATE_KEY: &str = "cheque_date";
+pub(crate) const CHEQUE_BANK_KEY: &str = "cheque_bank";
+const CHEQUE_STATUS_KEY: &str = "cheque_status";
+
+/// Status of a cheque
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ChequeStatus {
+    /// Post-dated cheque held in the register, not yet in the ledger
+    Pending,
+    /// Deposited or issued and recorded in the ledger
+    Presented,
+    /// Dishonoured by the bank and reversed
+    Bounced,
+    /// Cancelled before presentation
+    Cancelled,
+}
+
+impl ChequeStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ChequeStatus::Pending => "pending",
+            ChequeStatus::Presented => "presented",
+            ChequeStatus::Bounced => "bounced",
+            ChequeStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ChequeStatus::Pending),
+            "presented" => Some(ChequeStatus::Presented),
+            "bounced" => Some(ChequeStatus::Bounced),
+            "cancelled" => Some(ChequeStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// Cheque details carried on a receipt or payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChequeDetails {
+    pub number: String,
+    /// Date written on the cheque
+    pub cheque_date: NaiveDate,
+    /// Drawee bank, if known
+    pub bank: Option<String>,
+    pub status: ChequeStatus,
+}
+
+impl ChequeDetails {
+    /// Create cheque details
+    pub fn new(number: String, cheque_date: NaiveDate, bank: Option<String>) -> Self {
+        Self {
+            number,
+            cheque_date,
+            bank,
+            status: ChequeStatus::Presented,
+        }
+    }
+
+    /// Store the cheque details in a transaction's metadata
+    pub fn apply_to(&self, transaction: &mut Transaction) {
+        let metadata = &mut transaction.metadata;
+        metadata.insert(CHEQUE_NUMBER_KEY.to_string(), self.number.as_str().into());
+        metadata.insert(CHEQUE_DATE_KEY.to_string(), self.cheque_date.into());
+        match &self.bank {
+            Some(bank) => metadata.insert(CHEQUE_BANK_KEY.to_string(), bank.as_str().into()),
+            None => metadata.remove(CHEQUE_BANK_KEY),
+        };
+        metadata.insert(CHEQUE_STATUS_KEY.to_string(), self.status.as_str().into());
+    }
+
+    /// Read cheque details back from a transaction's metadata
+    pub fn from_transaction(transaction: &Transaction) -> Option<Self> {
+        let metadata = &transaction.metadata;
+        Some(Self {
+            number: metadata.get(CHEQUE_NUMBER_KEY)?.as_str()?.to_string(),
+            cheque_date: metadata.get(CHEQUE_DATE_KEY)?.as_date()?,
+            bank: metadata
+                .get(CHEQUE_BANK_KEY)
+                .and_then(|value| value.as_str())
+                .map(str::to_string),
+            status: ChequeStatus::parse(metadata.get(CHEQUE_STATUS_KEY)?.as_str()?)?,
+        })
+    }
+}
+
+/// A post-dated cheque waiting for its date before it hits the ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PendingCheque {
+    pub details: ChequeDetails,
+    /// Receipt or payment to post when the cheque matures
+    pub transaction: Transaction,
+}
+
+/// Register of post-dated cheques received or issued
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChequeRegister {
+    /// Pending cheques keyed by transaction ID
+    cheques: BTreeMap<String, PendingCheque>,
+}
+
+impl ChequeRegister {
+    /// Create an empty register
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hold a post-dated cheque until its date
+    ///
+    /// The transaction is validated now and will be dated on the cheque date
+    /// when it is posted.
+    pub fn register(
+        &mut self,
+        mut transaction: Transaction,
+        mut details: ChequeDetails,
+    ) -> LedgerResult<()> {
+        transaction.validate()?;
+        if self.cheques.contains_key(&transaction.id) {
+            return Err(LedgerError::Validation(format!(
+                "Cheque transaction '{}' is already registered",
+                transaction.id
+            )));
+        }
+
+        details.status = ChequeStatus::Pending;
+        transaction.date = details.cheque_date;
+        details.apply_to(&mut transaction);
+        self.cheques.insert(
+            transaction.id.clone(),
+            PendingCheque {
+                details,
+                transaction,
+            },
+        );
+        Ok(())
+    }
+
+    /// Cancel a pending cheque before it matures
+    pub fn cancel(&mut self, transaction_id: &str) -> LedgerResult<PendingCheque> {
+        let mut cheque = self
+            .cheques
+            .remove(transaction_id)
+            .ok_or_else(|| LedgerError::TransactionNotFound(transaction_id.to_string()))?;
+        cheque.details.status = ChequeStatus::Cancelled;
+        cheque.details.apply_to(&mut cheque.transaction);
+        Ok(cheque)
+    }
+
+    /// All pending cheques, ordered by transaction ID
+    pub fn pending(&self) -> impl Iterator<Item = &PendingCheque> {
+        self.cheques.values()
+    }
+
+    /// Pending cheques dated on or before `as_of`
+    pub fn matured(&self, as_of: NaiveDate) -> Vec<&PendingCheque> {
+        let mut matured: Vec<&PendingCheque> = self
+            .cheques
+            .values()
+            .filter(|c| c.details.cheque_date <= as_of)
+            .collect();
+        matured.sort_by_key(|c| c.details.cheque_date);
+        matured
+    }
+}
+
+/// Bank charges levied when a cheque bounces
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BounceCharges {
+    pub amount: BigDecimal,
+    /// Bank account the charges were debited from
+    pub bank_account_id: String,
+    /// Bank charges expense, or the party's account when they are recoverable
+    pub charges_account_id: String,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Post every registered cheque that has matured by `as_of`
+    ///
+    /// Matured cheques leave the register; returns the transactions that
+    /// posted, leaving out any held for approval.
+    pub async fn post_matured_cheques(
+        &mut self,
+        register: &mut ChequeRegister,
+        as_of: NaiveDate,
+    ) -> LedgerResult<Vec<Transaction>> {
+        let matured: Vec<String> = register
+            .matured(as_of)
+            .into_iter()
+            .map(|c| c.transaction.id.clone())
+            .collect();
+
+        let mut posted = Vec::new();
+        for transaction_id in matured {
+            let Some(mut cheque) = register.cheques.remove(&transaction_id) else {
+                continue;
+            };
+            cheque.details.status = ChequeStatus::Presented;
+            cheque.details.apply_to(&mut cheque.transaction);
+
+            match self.record_transaction(cheque.transaction.clone()).await {
+                Ok(transaction) => {
+                    if transaction.is_posted() {
+                        posted.push(transaction);
+                    }
+                }
+                Err(error) => {
+                    // Keep the cheque pending so it can be retried
+                    cheque.details.status = ChequeStatus::Pending;
+                    cheque.details.apply_to(&mut cheque.transaction);
+                    register.cheques.insert(transaction_id, cheque);
+                    return Err(error);
+                }
+            }
+        }
+
+        Ok(posted)
+    }
+
+    /// Reverse a receipt or payment whose cheque bounced, optionally booking
+    /// the bank's charges
+    ///
+    /// The original transaction is marked as bounced and the reversal is
+    /// returned.
+    pub async fn bounce_cheque(
+        &mut self,
+        transaction_id: &str,
+        date: NaiveDate,
+        charges: Option<BounceCharges>,
+    ) -> LedgerResult<Transaction> {
+        let mut original = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        let mut details = ChequeDetails::from_transaction(&original).ok_or_else(|| {
+            LedgerError::Validation(format!(
+                "Transaction '{}' has no cheque details",
+                transaction_id
+            ))
+        })?;
+        if details.status != ChequeStatus::Presented {
+            return Err(LedgerError::Validation(format!(
+                "Cheque {} cannot bounce from status {:?}",
+                details.number, details.status
+            )));
+        }
+
+        let mut reversal = patterns::create_reversal(
+            &original,
+            format!("{}-bounce", original.id),
+            date,
+            format!("Cheque {} bounced", details.number),
+        )?;
+        if let Some(charges) = charges.filter(|c| !c.amount.is_zero()) {
+            reversal.add_entry(Entry::debit(
+                charges.charges_account_id,
+                charges.amount.clone(),
+                Some("Cheque bounce charges".to_string()),
+            ));
+            reversal.add_entry(Entry::credit(
+                charges.bank_account_id,
+                charges.amount,
+                Some("Cheque bounce charges".to_string()),
+            ));
+        }
+
+        // Mark the cheque first so a failed update leaves nothing posted.
+        // Only the metadata changes, so the original is not revalidated or
+        // reposted and may sit in a locked period.
+        self.authorize(LedgerOperation::EditTransaction {
+            transaction: &original,
+        })?;
+        let presented = original.metadata.clone();
+        details.status = ChequeStatus::Bounced;
+        details.apply_to(&mut original);
+        original
+            .metadata
+            .insert("reversed_by".to_string(), reversal.id.clone().into());
+        self.transaction_manager
+            .update_metadata(&original.id, original.metadata)
+            .await?;
+        if let Err(error) = self.record_transaction(reversal.clone()).await {
+            // Leave the cheque presented so the bounce can be retried
+            self.transaction_manager
+                .update_metadata(transaction_id, presented)
+                .await?;
+            return Err(error);
+        }
+
+        Ok(reversal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+
+    async fn ledger() -> Ledger<MemoryStorage> {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        for (id, account_type) in [
+            ("bank", AccountType::Asset),
+            ("ar", AccountType::Asset),
+            ("charges", AccountType::Expense),
+            ("sales", AccountType::Income),
+        ] {
+            ledger
+                .create_account(id.to_string(), id.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        ledger
+    }
+
+    fn receipt(id: &str, amount: i32) -> Transaction {
+        patterns::create_sales_transaction(
+            id.to_string(),
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            "Cheque receipt".to_string(),
+            "bank".to_string(),
+            "ar".to_string(),
+            BigDecimal::from(amount),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_post_dated_cheque_posts_on_maturity() {
+        let mut ledger = ledger().await;
+        let mut register = ChequeRegister::new();
+        let cheque_date = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
+
+        register
+            .register(
+                receipt("pdc1", 5000),
+                ChequeDetails::new("000123".to_string(), cheque_date, None),
+            )
+            .unwrap();
+
+        let early = ledger
+            .post_matured_cheques(&mut register, NaiveDate::from_ymd_opt(2024, 7, 10).unwrap())
+            .await
+            .unwrap();
+        assert!(early.is_empty());
+        assert_eq!(register.pending().count(), 1);
+
+        let posted = ledger
+            .post_matured_cheques(&mut register, cheque_date)
+            .await
+            .unwrap();
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].date, cheque_date);
+        assert_eq!(register.pending().count(), 0);
+        let details = ChequeDetails::from_transaction(&posted[0]).unwrap();
+        assert_eq!(details.status, ChequeStatus::Presented);
+
+        // A cheque held for approval leaves the register but is not posted
+        ledger.set_approval_threshold(Some(BigDecimal::from(1000)));
+        register
+            .register(
+                receipt("pdc2", 5000),
+                ChequeDetails::new("000124".to_string(), cheque_date, None),
+            )
+            .unwrap();
+        let posted = ledger
+            .post_matured_cheques(&mut register, cheque_date)
+            .await
+            .unwrap();
+        assert!(posted.is_empty());
+        assert_eq!(register.pending().count(), 0);
+        let held = ledger.get_transaction("pdc2").await.unwrap().unwrap();
+        assert_eq!(held.status, TransactionStatus::PendingApproval);
+    }
+
+    #[tokio::test]
+    async fn test_bounce_cheque_reverses_receipt_with_charges() {
+        let mut ledger = ledger().await;
+        let mut transaction = receipt("chq1", 5000);
+        ChequeDetails::new(
+            "000456".to_string(),
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            Some("SBI".to_string()),
+        )
+        .apply_to(&mut transaction);
+        ledger.record_transaction(transaction).await.unwrap();
+        // The receipt's period is closed by the time the bank reports the bounce
+        ledger
+            .lock_period(NaiveDate::from_ymd_opt(2024, 7, 1).unwrap())
+            .unwrap();
+
+        // A reversal that cannot be posted leaves the cheque as it was
+        let bounce_date = NaiveDate::from_ymd_opt(2024, 7, 4).unwrap();
+        let unknown_charges = BounceCharges {
+            amount: BigDecimal::from(300),
+            bank_account_id: "bank".to_string(),
+            charges_account_id: "missing".to_string(),
+        };
+        assert!(ledger
+            .bounce_cheque("chq1", bounce_date, Some(unknown_charges))
+            .await
+            .is_err());
+        let original = ledger.get_transaction("chq1").await.unwrap().unwrap();
+        let details = ChequeDetails::from_transaction(&original).unwrap();
+        assert_eq!(details.status, ChequeStatus::Presented);
+        assert!(!original.metadata.contains_key("reversed_by"));
+
+        ledger
+            .bounce_cheque(
+                "chq1",
+                NaiveDate::from_ymd_opt(2024, 7, 4).unwrap(),
+                Some(BounceCharges {
+                    amount: BigDecimal::from(300),
+                    bank_account_id: "bank".to_string(),
+                    charges_account_id: "charges".to_string(),
+                }),
+            )
+            .await
+            .unwrap();
+
+        let bank = ledger.get_account_balance("bank", None).await.unwrap();
+        let charges = ledger.get_account_balance("charges", None).await.unwrap();
+        assert_eq!(bank, BigDecimal::from(-300));
+        assert_eq!(charges, BigDecimal::from(300));
+
+        let original = ledger.get_transaction("chq1").await.unwrap().unwrap();
+        let details = ChequeDetails::from_transaction(&original).unwrap();
+        assert_eq!(details.status, ChequeStatus::Bounced);
+        assert_eq!(original.metadata["reversed_by"], "chq1-bounce");
+
+        // A bounced cheque cannot bounce again
+        assert!(ledger
+            .bounce_cheque("chq1", NaiveDate::from_ymd_opt(2024, 7, 5).unwrap(), None)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Closing entries and the trial balances either side of them
+//!
+//! At the end of a year (or month, for management accounts) the income and
+//! expense accounts are closed into retained earnings by a closing entry.
+//! The working papers need the trial balance both before the closing entry,
+//! with every adjustment in (the adjusted trial balance), and after it, with
+//! only balance sheet accounts left (the post-closing trial balance).
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key marking a closing entry
+pub const CLOSING_ENTRY_KEY: &str = "closing_entry";
+
+fn is_profit_and_loss(account_type: &AccountType) -> bool {
+    matches!(account_type, AccountType::Income | AccountType::Expense)
+}
+
+/// Whether a transaction is a closing entry posted by
+/// [`Ledger::post_closing_entries`]
+pub(crate) fn is_closing_entry(transaction: &Transaction) -> bool {
+    transaction
+        .metadata
+        .get(CLOSING_ENTRY_KEY)
+        .and_then(MetaValue::as_bool)
+        .unwrap_or(false)
+}
+
+/// Debit and credit totals of each line of a trial balance
+fn totals_of(trial_balance: &TrialBalance) -> HashMap<String, AccountTotals> {
+    trial_balance
+        .balances
+        .iter()
+        .map(|(id, line)| {
+            let totals = AccountTotals {
+                debits: line.debit_balance.clone().unwrap_or_default(),
+                credits: line.credit_balance.clone().unwrap_or_default(),
+            };
+            (id.clone(), totals)
+        })
+        .collect()
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Close every income and expense account into `retained_earnings_account_id`
+    ///
+    /// Posts one adjustment dated `date` that brings each income and expense
+    /// balance to zero and carries the net profit or loss to retained
+    /// earnings, marked with [`CLOSING_ENTRY_KEY`] and, with a
+    /// [signer](Ledger::set_signer) set, signed under
+    /// [`SIGNATURE_KEY`](crate::ledger::SIGNATURE_KEY). Fails with
+    /// [`LedgerError::Validation`] when there is nothing to close.
+    pub async fn post_closing_entries(
+        &mut self,
+        id: String,
+        date: NaiveDate,
+        retained_earnings_account_id: &str,
+    ) -> LedgerResult<Transaction> {
+        let retained_earnings = self
+            .account_manager
+            .get_account_required(retained_earnings_account_id)
+            .await?;
+        if retained_earnings.account_type != AccountType::Equity {
+            return Err(LedgerError::Validation(format!(
+                "Retained earnings account {} must be an equity account",
+                retained_earnings_account_id
+            )));
+        }
+
+        let mut lines: Vec<AccountBalance> = self
+            .get_trial_balance(date)
+            .await?
+            .balances
+            .into_values()
+            .filter(|line| is_profit_and_loss(&line.account.account_type))
+            .filter(|line| !line.signed_balance().is_zero())
+            .collect();
+        if lines.is_empty() {
+            return Err(LedgerError::Validation(format!(
+                "No income or expense balances to close as of {}",
+                date
+            )));
+        }
+        lines.sort_by(|a, b| a.account.id.cmp(&b.account.id));
+
+        let mut builder = TransactionBuilder::new(id, date, "Closing entries".to_string())
+            .kind(TransactionKind::Adjustment)
+            .metadata(CLOSING_ENTRY_KEY.to_string(), true);
+        let mut net_income = BigDecimal::zero();
+        for line in &lines {
+            let account_id = line.account.id.clone();
+            if let Some(debit) = &line.debit_balance {
+                builder = builder.credit(account_id, debit.clone(), None);
+                net_income -= debit;
+            } else if let Some(credit) = &line.credit_balance {
+                builder = builder.debit(account_id, credit.clone(), None);
+                net_income += credit;
+            }
+        }
+        let description = Some("Net result for the period".to_string());
+        if net_income.is_positive() {
+            builder = builder.credit(
+                retained_earnings_account_id.to_string(),
+                net_income,
+                description,
+            );
+        } else if net_income.is_negative() {
+            builder = builder.debit(
+                retained_earnings_account_id.to_string(),
+                net_income.abs(),
+                description,
+            );
+        }
+
+        let mut transaction = builder.build()?;
+        self.sign_closing_entry(&mut transaction)?;
+        self.record_transaction(transaction).await
+    }
+
+    /// Trial balance after all adjustments but before closing
+    ///
+    /// Closing entries dated `as_of_date` are left out, so income and expense
+    /// accounts show the period's results even once the period is closed.
+    pub async fn get_adjusted_trial_balance(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<TrialBalance> {
+        let trial_balance = self.get_trial_balance(as_of_date).await?;
+        let closing_entries: Vec<Transaction> = self
+            .get_transactions(Some(as_of_date), Some(as_of_date))
+            .await?
+            .into_iter()
+            .filter(is_closing_entry)
+            .collect();
+        if closing_entries.is_empty() {
+            return Ok(trial_balance);
+        }
+
+        let mut totals = totals_of(&trial_balance);
+        for entry in closing_entries.iter().flat_map(|t| &t.entries) {
+            totals
+                .entry(entry.account_id.clone())
+                .or_default()
+                .subtract(&entry.entry_type, &entry.amount);
+        }
+        let accounts = trial_balance
+            .balances
+            .into_values()
+            .map(|line| line.account)
+            .collect();
+        Ok(TrialBalance::from_totals(accounts, &totals, as_of_date))
+    }
+
+    /// Trial balance after closing: income and expense accounts at zero and
+    /// their net carried to `retained_earnings_account_id`
+    ///
+    /// Built from the adjusted trial balance, so it is the same whether or
+    /// not the closing entries have been posted yet.
+    pub async fn get_post_closing_trial_balance(
+        &self,
+        as_of_date: NaiveDate,
+        retained_earnings_account_id: &str,
+    ) -> LedgerResult<TrialBalance> {
+        let adjusted = self.get_adjusted_trial_balance(as_of_date).await?;
+        if !adjusted.balances.contains_key(retained_earnings_account_id) {
+            return Err(LedgerError::AccountNotFound(
+                retained_earnings_account_id.to_string(),
+            ));
+        }
+
+        let mut totals = totals_of(&adjusted);
+        let mut net_income = BigDecimal::zero();
+        for line in adjusted.balances.values() {
+            if is_profit_and_loss(&line.account.account_type) {
+                net_income += line.credit_balance.clone().unwrap_or_default();
+                net_income -= line.debit_balance.clone().unwrap_or_default();
+                totals.remove(&line.account.id);
+            }
+        }
+        let retained_earnings = totals
+            .entry(retained_earnings_account_id.to_string())
+            .or_default();
+        if net_income.is_negative() {
+            retained_earnings.add(&EntryType::Debit, &net_income.abs());
+        } else {
+            retained_earnings.add(&EntryType::Credit, &net_income);
+        }
+
+        let accounts = adjusted
+            .balances
+            .into_values()
+            .map(|line| line.account)
+            .collect();
+        Ok(TrialBalance::from_totals(accounts, &totals, as_of_date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::MemoryStorage;
+    use std::collections::BTreeMap;
+
+    fn signed_balances(trial_balance: &TrialBalance) -> BTreeMap<String, BigDecimal> {
+        trial_balance
+            .balances
+            .iter()
+            .map(|(id, line)| (id.clone(), line.signed_balance()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_trial_balances_around_closing() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let year_end = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+        let sale = TransactionBuilder::new(
+            "sale".to_string(),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+            "Sale".to_string(),
+        )
+        .debit("1000".to_string(), BigDecimal::from(5000), None)
+        .credit("4000".to_string(), BigDecimal::from(5000), None)
+        .build()
+        .unwrap();
+        ledger.record_transaction(sale).await.unwrap();
+        ledger
+            .record_transaction(
+                patterns::create_expense_payment(
+                    "rent".to_string(),
+                    year_end,
+                    "Rent".to_string(),
+                    "6000".to_string(),
+                    "1000".to_string(),
+                    BigDecimal::from(1200),
+                )
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let adjusted = signed_balances(&ledger.get_adjusted_trial_balance(year_end).await.unwrap());
+        let post_closing = ledger
+            .get_post_closing_trial_balance(year_end, "3200")
+            .await
+            .unwrap();
+        assert!(post_closing.is_balanced);
+        assert!(post_closing.balances["4000"].balance_amount().is_zero());
+        assert_eq!(
+            post_closing.balances["3200"].credit_balance,
+            Some(BigDecimal::from(3800))
+        );
+
+        ledger
+            .post_closing_entries("close-fy25".to_string(), year_end, "3200")
+            .await
+            .unwrap();
+        let post_closing = signed_balances(&post_closing);
+        assert_eq!(
+            signed_balances(&ledger.get_adjusted_trial_balance(year_end).await.unwrap()),
+            adjusted
+        );
+        assert_eq!(
+            signed_balances(&ledger.get_trial_balance(year_end).await.unwrap()),
+            post_closing
+        );
+        assert_eq!(
+            signed_balances(
+                &ledger
+                    .get_post_closing_trial_balance(year_end, "3200")
+                    .await
+                    .unwrap()
+            ),
+            post_closing
+        );
+        assert!(ledger
+            .post_closing_entries("again".to_string(), year_end, "3200")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Ledger-wide configuration
+//!
+//! [`LedgerConfig`] gathers the settings that hold for the whole book:
+//! currency, fiscal year, rounding, the business's GST registration and the
+//! accounts the ledger posts to on its own. Pass it to
+//! [`Ledger::with_config`]; [`Ledger::new`] uses [`LedgerConfig::default`].
+//! The individual setters such as [`Ledger::set_suspense_account`] still
+//! work afterwards, and [`Ledger::config`] reads the current values back.
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::{DefaultAccounts, Ledger};
+use crate::tax::GstRegistration;
+use crate::traits::*;
+use crate::types::*;
+
+/// Base currency of a ledger created without one
+pub const DEFAULT_BASE_CURRENCY: &str = "INR";
+/// First month of the fiscal year of a ledger created without one (April,
+/// as in the Indian financial year)
+pub const DEFAULT_FISCAL_YEAR_START_MONTH: u32 = 4;
+
+/// Decimal places of an ISO 4217 currency's minor unit: 0 for currencies
+/// such as JPY and KRW, 3 for the dinars and rials that divide into
+/// thousandths, and 2 for the rest
+pub fn currency_minor_units(currency: &str) -> i64 {
+    match currency {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+pub(crate) fn default_base_currency() -> String {
+    DEFAULT_BASE_CURRENCY.to_string()
+}
+
+pub(crate) fn default_fiscal_year_start_month() -> u32 {
+    DEFAULT_FISCAL_YEAR_START_MONTH
+}
+
+/// Settings that hold for the whole ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LedgerConfig {
+    /// ISO 4217 code of the currency amounts are kept in
+    #[serde(default = "default_base_currency")]
+    pub base_currency: String,
+    /// Month (1–12) the fiscal year starts on the first of
+    #[serde(default = "default_fiscal_year_start_month")]
+    pub fiscal_year_start_month: u32,
+    /// Precision and rounding of amounts
+    #[serde(default)]
+    pub amount_policy: Option<AmountPolicy>,
+    /// Registration invoices are issued under; with one set,
+    /// [`Ledger::issue_invoice`] charges IGST or CGST and SGST by the
+    /// invoice's place of supply
+    #[serde(default)]
+    pub gst_registration: Option<GstRegistration>,
+    /// Equity account profits are closed into
+    #[serde(default)]
+    pub retained_earnings_account_id: Option<String>,
+    /// Round-off account and tolerance for small rounding differences
+    #[serde(default)]
+    pub round_off_policy: Option<RoundOffPolicy>,
+    /// Account holding unclassified amounts
+    #[serde(default)]
+    pub suspense_account_id: Option<String>,
+    /// Let child accounts differ in type from their parent
+    #[serde(default)]
+    pub allow_mixed_account_types: bool,
+    /// Most levels and children per account in the chart
+    #[serde(default)]
+    pub hierarchy_limits: HierarchyLimits,
+    /// Accounts posting helpers use unless given others
+    #[serde(default)]
+    pub default_accounts: DefaultAccounts,
+}
+
+impl Default for LedgerConfig {
+    /// INR with an April fiscal year and nothing else configured
+    fn default() -> Self {
+        Self {
+            base_currency: default_base_currency(),
+            fiscal_year_start_month: DEFAULT_FISCAL_YEAR_START_MONTH,
+            amount_policy: None,
+            gst_registration: None,
+            retained_earnings_account_id: None,
+            round_off_policy: None,
+            suspense_account_id: None,
+            allow_mixed_account_types: false,
+            hierarchy_limits: HierarchyLimits::default(),
+            default_accounts: DefaultAccounts::default(),
+        }
+    }
+}
+
+impl LedgerConfig {
+    /// Check the currency code, fiscal year start and GSTIN
+    pub fn validate(&self) -> LedgerResult<()> {
+        let currency = &self.base_currency;
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(LedgerError::Validation(format!(
+                "Base currency {:?} is not an ISO 4217 code",
+                currency
+            )));
+        }
+        if !(1..=12).contains(&self.fiscal_year_start_month) {
+            return Err(LedgerError::Validation(format!(
+                "Fiscal year cannot start in month {}",
+                self.fiscal_year_start_month
+            )));
+        }
+        if let Some(registration) = &self.gst_registration {
+            registration
+                .validate()
+                .map_err(|e| LedgerError::Validation(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Create a ledger with the given storage backend and configuration
+    pub fn with_config(storage: S, config: LedgerConfig) -> LedgerResult<Self> {
+        let mut ledger = Self::new(storage);
+        ledger.apply_config(config)?;
+        Ok(ledger)
+    }
+
+    /// Replace every configured setting
+    pub fn apply_config(&mut self, config: LedgerConfig) -> LedgerResult<()> {
+        config.validate()?;
+        self.base_currency = config.base_currency;
+        self.fiscal_year_start_month = config.fiscal_year_start_month;
+        self.gst_registration = config.gst_registration;
+        self.retained_earnings_account_id = config.retained_earnings_account_id;
+        self.suspense_account_id = config.suspense_account_id;
+        self.transaction_manager
+            .set_amount_policy(config.amount_policy);
+        self.transaction_manager
+            .set_round_off_policy(config.round_off_policy);
+        self.account_manager
+            .set_allow_mixed_types(config.allow_mixed_account_types);
+        self.account_manager
+            .set_hierarchy_limits(config.hierarchy_limits);
+        self.default_accounts = config.default_accounts;
+        self.clear_report_cache();
+        Ok(())
+    }
+
+    /// The settings currently in effect
+    pub fn config(&self) -> LedgerConfig {
+        LedgerConfig {
+            base_currency: self.base_currency.clone(),
+            fiscal_year_start_month: self.fiscal_year_start_month,
+            amount_policy: self.amount_policy().cloned(),
+            gst_registration: self.gst_registration.clone(),
+            retained_earnings_account_id: self.retained_earnings_account_id.clone(),
+            round_off_policy: self.round_off_policy().cloned(),
+            suspense_account_id: self.suspense_account_id.clone(),
+            allow_mixed_account_types: self.allows_mixed_account_types(),
+            hierarchy_limits: self.hierarchy_limits(),
+            default_accounts: self.default_accounts.clone(),
+        }
+    }
+
+    /// ISO 4217 code of the ledger's currency
+    pub fn base_currency(&self) -> &str {
+        &self.base_currency
+    }
+
+    /// Policy helpers round computed amounts with: the configured
+    /// [amount policy](Ledger::set_amount_policy), or else the base
+    /// currency's minor units rounded half up
+    pub fn currency_amount_policy(&self) -> AmountPolicy {
+        self.amount_policy().cloned().unwrap_or_else(|| {
+            AmountPolicy::new(
+                currency_minor_units(&self.base_currency),
+                AmountRounding::HalfUp,
+            )
+        })
+    }
+
+    /// The business's GST registration, if configured
+    pub fn gst_registration(&self) -> Option<&GstRegistration> {
+        self.gst_registration.as_ref()
+    }
+
+    /// First and last day of the fiscal year containing `date`
+    ///
+    /// Fails when the year reaches past the dates the calendar can represent.
+    pub fn fiscal_year(&self, date: NaiveDate) -> LedgerResult<(NaiveDate, NaiveDate)> {
+        let year = if date.month() >= self.fiscal_year_start_month {
+            date.year()
+        } else {
+            date.year() - 1
+        };
+        NaiveDate::from_ymd_opt(year, self.fiscal_year_start_month, 1)
+            .and_then(|start| {
+                let end = start
+                    .checked_add_months(Months::new(12))?
+                    .checked_sub_days(Days::new(1))?;
+                Some((start, end))
+            })
+            .ok_or_else(|| {
+                LedgerError::Validation(format!(
+                    "The fiscal year containing {} is out of range",
+                    date
+                ))
+            })
+    }
+
+    /// Close the fiscal year containing `date` into the configured retained
+    /// earnings account, dating the closing entry on the year's last day
+    ///
+    /// See [`Ledger::post_closing_entries`].
+    pub async fn close_fiscal_year(
+        &mut self,
+        id: String,
+        date: NaiveDate,
+    ) -> LedgerResult<Transaction> {
+        let retained_earnings_account_id = self
+            .retained_earnings_account()
+            .map(str::to_string)
+            .ok_or_else(|| {
+                LedgerError::Validation("No retained earnings account is configured".to_string())
+            })?;
+        let (_, year_end) = self.fiscal_year(date)?;
+        self.post_closing_entries(id, year_end, &retained_earnings_account_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::MemoryStorage;
+    use bigdecimal::BigDecimal;
+
+    #[tokio::test]
+    async fn test_ledger_from_config() {
+        let config = LedgerConfig {
+            fiscal_year_start_month: 1,
+            base_currency: "inr".to_string(),
+            ..LedgerConfig::default()
+        };
+        assert!(Ledger::with_config(MemoryStorage::new(), config).is_err());
+
+        let config = LedgerConfig {
+            amount_policy: Some(AmountPolicy::default()),
+            gst_registration: Some(
+                GstRegistration::new("29abcde1234f1z5".to_string(), "Acme Pvt Ltd".to_string())
+                    .unwrap(),
+            ),
+            retained_earnings_account_id: Some("3200".to_string()),
+            ..LedgerConfig::default()
+        };
+        let mut ledger = Ledger::with_config(MemoryStorage::new(), config.clone()).unwrap();
+        assert_eq!(ledger.config(), config);
+        assert_eq!(ledger.base_currency(), "INR");
+        assert_eq!(ledger.gst_registration().unwrap().state_code(), "29");
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(
+            ledger.fiscal_year(date(2025, 2, 14)).unwrap(),
+            (date(2024, 4, 1), date(2025, 3, 31))
+        );
+        assert!(ledger.fiscal_year(NaiveDate::MAX).is_err());
+        assert_eq!(ledger.currency_amount_policy(), AmountPolicy::default());
+
+        let mut tampered = config.clone();
+        tampered.gst_registration.as_mut().unwrap().gstin = "not-a-gstin".to_string();
+        assert!(tampered.validate().is_err());
+        let yen = LedgerConfig {
+            base_currency: "JPY".to_string(),
+            ..LedgerConfig::default()
+        };
+        let yen_ledger = Ledger::with_config(MemoryStorage::new(), yen).unwrap();
+        assert_eq!(yen_ledger.currency_amount_policy().max_scale, 0);
+
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let sale = patterns::create_sales_transaction(
+            "S1".to_string(),
+            date(2024, 9, 1),
+            "Sale".to_string(),
+            "1000".to_string(),
+            "4000".to_string(),
+            BigDecimal::from(250),
+        )
+        .unwrap();
+        ledger.record_transaction(sale).await.unwrap();
+        let closing = ledger
+            .close_fiscal_year("CL-FY25".to_string(), date(2024, 9, 1))
+            .await
+            .unwrap();
+        assert_eq!(closing.date, date(2025, 3, 31));
+        assert_eq!(
+            ledger.get_account_balance("3200", None).await.unwrap(),
+            BigDecimal::from(250)
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Control accounts and their reconciliation to sub-ledger balances
+//!
+//! A control account (accounts receivable, accounts payable, GST payable)
+//! carries one total in the general ledger while its detail lives in a
+//! sub-ledger. Postings to a control account are tagged with an entry
+//! dimension (the party or tax head), and the sub-ledger balance of each
+//! dimension value must add up to the control account balance.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Entry dimension identifying the customer or supplier
+pub const PARTY_DIMENSION: &str = "party";
+/// Entry dimension identifying the tax head (e.g. CGST, SGST, IGST)
+pub const TAX_HEAD_DIMENSION: &str = "tax_head";
+
+/// Account metadata key holding a control account's sub-ledger dimension
+const CONTROL_DIMENSION_KEY: &str = "control_dimension";
+
+/// Result of reconciling a control account to its sub-ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ControlAccountReconciliation {
+    pub account_id: String,
+    /// Dimension the sub-ledger is keyed by
+    pub dimension: String,
+    pub as_of_date: NaiveDate,
+    pub control_balance: BigDecimal,
+    /// Balance per dimension value
+    pub subledger_balances: BTreeMap<String, BigDecimal>,
+    pub subledger_total: BigDecimal,
+    /// Transactions posting to the control account without the dimension
+    pub unassigned_transactions: Vec<String>,
+    /// Control balance minus the sub-ledger total
+    pub difference: BigDecimal,
+    pub is_reconciled: bool,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Designate an account as a control account whose sub-ledger is keyed by
+    /// the given entry dimension (e.g. [`PARTY_DIMENSION`])
+    pub async fn designate_control_account(
+        &mut self,
+        account_id: &str,
+        dimension: &str,
+    ) -> LedgerResult<Account> {
+        let mut account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        account
+            .metadata
+            .insert(CONTROL_DIMENSION_KEY.to_string(), dimension.into());
+        account.updated_at = self.clock.now();
+        self.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Sub-ledger dimension of a control account, if it is one
+    pub async fn control_dimension(&self, account_id: &str) -> LedgerResult<Option<String>> {
+        let account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        Ok(account
+            .metadata
+            .get(CONTROL_DIMENSION_KEY)
+            .and_then(|value| value.as_str())
+            .map(str::to_string))
+    }
+
+    /// All designated control accounts
+    pub async fn list_control_accounts(&self) -> LedgerResult<Vec<Account>> {
+        let mut accounts: Vec<Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .filter(|a| a.metadata.contains_key(CONTROL_DIMENSION_KEY))
+            .collect();
+        accounts.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(accounts)
+    }
+
+    /// Check that the sub-ledger balances of a control account add up to its
+    /// balance as of a date
+    pub async fn reconcile_control_account(
+        &self,
+        account_id: &str,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<ControlAccountReconciliation> {
+        let account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        let dimension = account
+            .metadata
+            .get(CONTROL_DIMENSION_KEY)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                LedgerError::Validation(format!(
+                    "Account '{}' is not a control account",
+                    account_id
+                ))
+            })?;
+
+        let control_balance = self
+            .get_account_balance(account_id, Some(as_of_date))
+            .await?;
+
+        let mut transactions = self
+            .get_account_transactions(account_id, None, Some(as_of_date))
+            .await?;
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+        let mut subledger_balances: BTreeMap<String, BigDecimal> = BTreeMap::new();
+        let mut unassigned_transactions = Vec::new();
+        for transaction in &transactions {
+            for entry in transaction
+                .entries
+                .iter()
+                .filter(|e| e.account_id == account_id)
+            {
+                match entry.dimensions.get(&dimension) {
+                    Some(value) => {
+                        *subledger_balances
+                            .entry(value.clone())
+                            .or_insert_with(BigDecimal::zero) += account
+                            .account_type
+                            .balance_effect(&entry.entry_type, &entry.amount);
+                    }
+                    None => {
+                        if !unassigned_transactions.contains(&transaction.id) {
+                            unassigned_transactions.push(transaction.id.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let subledger_total: BigDecimal = subledger_balances.values().sum();
+        let difference = &control_balance - &subledger_total;
+        let is_reconciled = difference.is_zero() && unassigned_transactions.is_empty();
+
+        Ok(ControlAccountReconciliation {
+            account_id: account_id.to_string(),
+            dimension,
+            as_of_date,
+            control_balance,
+            subledger_balances,
+            subledger_total,
+            unassigned_transactions,
+            difference,
+            is_reconciled,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_reconcile_control_account_by_party() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        for (id, account_type) in [("ar", AccountType::Asset), ("sales", AccountType::Income)] {
+            ledger
+                .create_account(id.to_string(), id.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        ledger
+            .designate_control_account("ar", PARTY_DIMENSION)
+            .await
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        for (id, party, amount) in [("inv1", "acme", 1000), ("inv2", "globex", 500)] {
+            let transaction = TransactionBuilder::new(id.to_string(), date, "Invoice".to_string())
+                .entry(
+                    Entry::debit("ar".to_string(), BigDecimal::from(amount), None)
+                        .with_dimension(PARTY_DIMENSION.to_string(), party.to_string()),
+                )
+                .credit("sales".to_string(), BigDecimal::from(amount), None)
+                .build()
+                .unwrap();
+            ledger.record_transaction(transaction).await.unwrap();
+        }
+
+        let report = ledger.reconcile_control_account("ar", date).await.unwrap();
+        assert!(report.is_reconciled);
+        assert_eq!(report.subledger_balances["acme"], BigDecimal::from(1000));
+        assert_eq!(report.subledger_total, BigDecimal::from(1500));
+
+        // A posting without a party breaks the reconciliation
+        let untagged = TransactionBuilder::new("inv3".to_string(), date, "Untagged".to_string())
+            .debit("ar".to_string(), BigDecimal::from(200), None)
+            .credit("sales".to_string(), BigDecimal::from(200), None)
+            .build()
+            .unwrap();
+        ledger.record_transaction(untagged).await.unwrap();
+
+        let report = ledger.reconcile_control_account("ar", date).await.unwrap();
+        assert!(!report.is_reconciled);
+        assert_eq!(report.difference, BigDecimal::from(200));
+        assert_eq!(report.unassigned_transactions, vec!["inv3".to_string()]);
+
+        assert!(ledger
+            .reconcile_control_account("sales", date)
+            .await
+            .is_err());
+    }
+}
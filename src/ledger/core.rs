@@ -1,18 +1,53 @@
 //! Main ledger orchestrator that coordinates accounts and transactions
 
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Zero};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
 
-use crate::ledger::{AccountManager, TransactionManager};
+use crate::ledger::{
+    default_base_currency, default_fiscal_year_start_month, AccountManager, BudgetEnforcement,
+    BudgetLimit, CachedReport, DefaultAccounts, Fund, NetIncomePresentation, PostingRules, Project,
+    ReportCache, ReportKey, ReportKind, SimulationResult, TransactionManager, TransactionTemplate,
+    BUDGET_EXCEEDED_KEY,
+};
+use crate::receivables::PaymentTerms;
+use crate::tax::GstRegistration;
 use crate::traits::*;
 use crate::types::*;
+use crate::utils::UuidV7Generator;
 
 /// Main ledger system that orchestrates all accounting operations
 pub struct Ledger<S: LedgerStorage> {
-    account_manager: AccountManager<S>,
-    transaction_manager: TransactionManager<S>,
+    pub(crate) account_manager: AccountManager<S>,
+    pub(crate) transaction_manager: TransactionManager<S>,
+    pub(crate) suspense_account_id: Option<String>,
+    pub(crate) retained_earnings_account_id: Option<String>,
+    pub(crate) net_income_presentation: NetIncomePresentation,
+    pub(crate) authorization_policy: Option<Box<dyn AuthorizationPolicy>>,
+    pub(crate) actor: Option<Actor>,
+    pub(crate) observers: Vec<Arc<dyn LedgerObserver>>,
+    pub(crate) report_cache: Option<ReportCache>,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) id_generator: Arc<dyn IdGenerator>,
+    /// Whether `id_generator` was chosen by the caller rather than being the
+    /// default UUIDv7 generator that follows `clock`
+    pub(crate) custom_id_generator: bool,
+    pub(crate) business_timezone: BusinessTimezone,
+    pub(crate) templates: BTreeMap<String, TransactionTemplate>,
+    pub(crate) template_store: Option<Box<dyn TemplateStore>>,
+    pub(crate) party_payment_terms: BTreeMap<String, PaymentTerms>,
+    pub(crate) projects: BTreeMap<String, Project>,
+    pub(crate) funds: BTreeMap<String, Fund>,
+    pub(crate) budget_limits: Vec<BudgetLimit>,
+    pub(crate) budget_enforcement: BudgetEnforcement,
+    pub(crate) signer: Option<Arc<dyn Signer>>,
+    pub(crate) base_currency: String,
+    pub(crate) fiscal_year_start_month: u32,
+    pub(crate) gst_registration: Option<GstRegistration>,
+    pub(crate) posting_rules: Option<PostingRules>,
+    pub(crate) default_accounts: DefaultAccounts,
 }
 
 impl<S: LedgerStorage + Clone> Ledger<S> {
@@ -21,6 +56,30 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         Self {
             account_manager: AccountManager::new(storage.clone()),
             transaction_manager: TransactionManager::new(storage),
+            suspense_account_id: None,
+            retained_earnings_account_id: None,
+            net_income_presentation: NetIncomePresentation::default(),
+            authorization_policy: None,
+            actor: None,
+            observers: Vec::new(),
+            report_cache: None,
+            clock: Arc::new(SystemClock),
+            id_generator: Arc::new(UuidV7Generator::new()),
+            custom_id_generator: false,
+            business_timezone: BusinessTimezone::default(),
+            templates: BTreeMap::new(),
+            template_store: None,
+            party_payment_terms: BTreeMap::new(),
+            projects: BTreeMap::new(),
+            funds: BTreeMap::new(),
+            budget_limits: Vec::new(),
+            budget_enforcement: BudgetEnforcement::default(),
+            signer: None,
+            base_currency: default_base_currency(),
+            fiscal_year_start_month: default_fiscal_year_start_month(),
+            gst_registration: None,
+            posting_rules: None,
+            default_accounts: DefaultAccounts::default(),
         }
     }
 
@@ -33,6 +92,135 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         Self {
             account_manager: AccountManager::with_validator(storage.clone(), account_validator),
             transaction_manager: TransactionManager::with_validator(storage, transaction_validator),
+            suspense_account_id: None,
+            retained_earnings_account_id: None,
+            net_income_presentation: NetIncomePresentation::default(),
+            authorization_policy: None,
+            actor: None,
+            observers: Vec::new(),
+            report_cache: None,
+            clock: Arc::new(SystemClock),
+            id_generator: Arc::new(UuidV7Generator::new()),
+            custom_id_generator: false,
+            business_timezone: BusinessTimezone::default(),
+            templates: BTreeMap::new(),
+            template_store: None,
+            party_payment_terms: BTreeMap::new(),
+            projects: BTreeMap::new(),
+            funds: BTreeMap::new(),
+            budget_limits: Vec::new(),
+            budget_enforcement: BudgetEnforcement::default(),
+            signer: None,
+            base_currency: default_base_currency(),
+            fiscal_year_start_month: default_fiscal_year_start_month(),
+            gst_registration: None,
+            posting_rules: None,
+            default_accounts: DefaultAccounts::default(),
+        }
+    }
+
+    /// Consult the given policy before every state-changing operation
+    pub fn set_authorization_policy(&mut self, policy: Box<dyn AuthorizationPolicy>) {
+        self.authorization_policy = Some(policy);
+    }
+
+    /// Set the actor whose operations are authorized from now on
+    pub fn set_actor(&mut self, actor: Option<Actor>) {
+        self.actor = actor;
+    }
+
+    /// Get the current actor, if any
+    pub fn actor(&self) -> Option<&Actor> {
+        self.actor.as_ref()
+    }
+
+    /// Take created/updated timestamps from `clock` instead of the system time
+    ///
+    /// The default id generator stamps its ids from the same clock.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.account_manager.set_clock(clock.clone());
+        self.transaction_manager.set_clock(clock.clone());
+        if !self.custom_id_generator {
+            self.id_generator = Arc::new(UuidV7Generator::with_clock(clock.clone()));
+        }
+        self.clock = clock;
+    }
+
+    /// Allow child accounts of a different type than their parent (say, an
+    /// expense account under current assets) for unusual charts
+    pub fn set_allow_mixed_account_types(&mut self, allow: bool) {
+        self.account_manager.set_allow_mixed_types(allow);
+    }
+
+    /// Whether child accounts may differ in type from their parent
+    pub fn allows_mixed_account_types(&self) -> bool {
+        self.account_manager.allows_mixed_types()
+    }
+
+    /// Limit how deep and wide the chart of accounts may grow
+    pub fn set_hierarchy_limits(&mut self, limits: HierarchyLimits) {
+        self.account_manager.set_hierarchy_limits(limits);
+    }
+
+    /// Limits on the shape of the chart of accounts
+    pub fn hierarchy_limits(&self) -> HierarchyLimits {
+        self.account_manager.hierarchy_limits()
+    }
+
+    /// The clock used for timestamps
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Give transactions recorded without an id one from `generator`
+    /// instead of a UUIDv7
+    pub fn set_id_generator(&mut self, generator: Arc<dyn IdGenerator>) {
+        self.id_generator = generator;
+        self.custom_id_generator = true;
+    }
+
+    /// The generator used for transactions recorded without an id
+    pub fn id_generator(&self) -> &dyn IdGenerator {
+        self.id_generator.as_ref()
+    }
+
+    /// A fresh transaction id from the ledger's id generator
+    pub fn next_transaction_id(&self) -> String {
+        self.id_generator.next_id()
+    }
+
+    /// Show timestamps in `timezone`; stored timestamps stay in UTC
+    pub fn set_business_timezone(&mut self, timezone: BusinessTimezone) {
+        self.business_timezone = timezone;
+    }
+
+    /// Timezone in which the business reads timestamps
+    pub fn business_timezone(&self) -> BusinessTimezone {
+        self.business_timezone
+    }
+
+    /// Business day of the current time
+    pub fn business_today(&self) -> NaiveDate {
+        self.business_timezone.local_date(&self.clock.now())
+    }
+
+    /// Register an observer notified of every stored change
+    pub fn add_observer(&mut self, observer: Arc<dyn LedgerObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Announce a stored change to the observers
+    pub(crate) fn notify(&self, event: LedgerEvent<'_>) {
+        for observer in &self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Check an operation against the authorization policy, if one is set
+    pub(crate) fn authorize(&self, operation: LedgerOperation<'_>) -> LedgerResult<()> {
+        match &self.authorization_policy {
+            Some(policy) => policy.authorize(self.actor.as_ref(), &operation),
+            None => Ok(()),
         }
     }
 
@@ -45,9 +233,45 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         account_type: AccountType,
         parent_id: Option<String>,
     ) -> LedgerResult<Account> {
-        self.account_manager
+        self.authorize(LedgerOperation::CreateAccount { account_id: &id })?;
+        let account = self
+            .account_manager
             .create_account(id, name, account_type, parent_id)
-            .await
+            .await?;
+        self.notify(LedgerEvent::AccountChanged {
+            account_id: &account.id,
+        });
+        Ok(account)
+    }
+
+    /// Create an account from a fully populated `Account`, including its
+    /// metadata
+    pub async fn add_account(&mut self, account: Account) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::CreateAccount {
+            account_id: &account.id,
+        })?;
+        let account = self.account_manager.add_account(account).await?;
+        self.notify(LedgerEvent::AccountChanged {
+            account_id: &account.id,
+        });
+        Ok(account)
+    }
+
+    /// Create a batch of accounts in one go, parents resolved within the
+    /// batch; see [`AccountManager::create_accounts`]
+    pub async fn create_accounts(&mut self, batch: Vec<NewAccount>) -> LedgerResult<Vec<Account>> {
+        for new in &batch {
+            self.authorize(LedgerOperation::CreateAccount {
+                account_id: &new.id,
+            })?;
+        }
+        let accounts = self.account_manager.create_accounts(batch).await?;
+        for account in &accounts {
+            self.notify(LedgerEvent::AccountChanged {
+                account_id: &account.id,
+            });
+        }
+        Ok(accounts)
     }
 
     /// Get an account by ID
@@ -55,11 +279,56 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         self.account_manager.get_account(account_id).await
     }
 
+    /// Find an account by code, name or alias, ignoring case and accepting
+    /// an unambiguous prefix
+    pub async fn find_account(&self, by: &AccountLookup) -> LedgerResult<Option<Account>> {
+        self.account_manager.find_account(by).await
+    }
+
+    /// Let an account be found by `alias` as well as its code and name
+    pub async fn add_account_alias(
+        &mut self,
+        account_id: &str,
+        alias: &str,
+    ) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::EditAccount { account_id })?;
+        let account = self.account_manager.add_alias(account_id, alias).await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(account)
+    }
+
+    /// Stop an account being found by `alias`
+    pub async fn remove_account_alias(
+        &mut self,
+        account_id: &str,
+        alias: &str,
+    ) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::EditAccount { account_id })?;
+        let account = self.account_manager.remove_alias(account_id, alias).await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(account)
+    }
+
     /// List all accounts
     pub async fn list_accounts(&self) -> LedgerResult<Vec<Account>> {
         self.account_manager.list_accounts().await
     }
 
+    /// List accounts that have not been archived
+    pub async fn list_active_accounts(&self) -> LedgerResult<Vec<Account>> {
+        self.account_manager.list_active_accounts().await
+    }
+
+    /// Accounts from the top-level ancestor of an account down to the account
+    pub async fn get_account_path(&self, account_id: &str) -> LedgerResult<Vec<Account>> {
+        self.account_manager.account_path(account_id).await
+    }
+
+    /// Get the chart of accounts as a tree with rolled-up balances
+    pub async fn get_account_tree(&self) -> LedgerResult<Vec<AccountNode>> {
+        Ok(AccountNode::build_tree(self.list_accounts().await?))
+    }
+
     /// List accounts by type
     pub async fn list_accounts_by_type(
         &self,
@@ -72,22 +341,343 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
 
     /// Update an account
     pub async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
-        self.account_manager.update_account(account).await
+        self.authorize(LedgerOperation::EditAccount {
+            account_id: &account.id,
+        })?;
+        self.account_manager.update_account(account).await?;
+        self.notify(LedgerEvent::AccountChanged {
+            account_id: &account.id,
+        });
+        Ok(())
     }
 
     /// Delete an account
     pub async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
-        self.account_manager.delete_account(account_id).await
+        self.authorize(LedgerOperation::DeleteAccount { account_id })?;
+        self.account_manager.delete_account(account_id).await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(())
+    }
+
+    /// Archive an account: it keeps its history but accepts no new postings
+    pub async fn archive_account(&mut self, account_id: &str) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::EditAccount { account_id })?;
+        let account = self.account_manager.archive_account(account_id).await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(account)
+    }
+
+    /// Reopen an archived account for postings
+    pub async fn restore_account(&mut self, account_id: &str) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::EditAccount { account_id })?;
+        let account = self.account_manager.restore_account(account_id).await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(account)
+    }
+
+    /// Move an account under a new parent, or to the top level with `None`
+    pub async fn move_account(
+        &mut self,
+        account_id: &str,
+        new_parent_id: Option<&str>,
+    ) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::EditAccount { account_id })?;
+        let account = self
+            .account_manager
+            .move_account(account_id, new_parent_id)
+            .await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(account)
+    }
+
+    /// Flag whether an account's balance may go below zero
+    ///
+    /// Postings that would drive a non-negative account below zero on or after
+    /// their date fail with [`LedgerError::InsufficientBalance`].
+    pub async fn set_non_negative(
+        &mut self,
+        account_id: &str,
+        non_negative: bool,
+    ) -> LedgerResult<Account> {
+        self.authorize(LedgerOperation::EditAccount { account_id })?;
+        let account = self
+            .account_manager
+            .set_non_negative(account_id, non_negative)
+            .await?;
+        self.notify(LedgerEvent::AccountChanged { account_id });
+        Ok(account)
     }
 
     // Transaction operations
     /// Record a new transaction
-    pub async fn record_transaction(&mut self, transaction: Transaction) -> LedgerResult<()> {
-        self.transaction_manager
+    ///
+    /// Transactions above the approval threshold are held as
+    /// [`TransactionStatus::PendingApproval`] instead of being posted. With a
+    /// round-off policy set, small imbalances are first posted to the
+    /// round-off account. A transaction with an empty id, such as one built
+    /// by a [`patterns`](crate::ledger::patterns) helper given
+    /// `String::new()`, is given one from the ledger's id generator.
+    ///
+    /// Returns the transaction as stored, with its id and any adjustments.
+    pub async fn record_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> LedgerResult<Transaction> {
+        self.post_transaction(transaction, false).await
+    }
+
+    /// Record a new transaction, skipping budget limits when
+    /// `budget_override` is set by an authorized override
+    pub(crate) async fn post_transaction(
+        &mut self,
+        mut transaction: Transaction,
+        budget_override: bool,
+    ) -> LedgerResult<Transaction> {
+        if transaction.id.is_empty() {
+            transaction.id = self.id_generator.next_id();
+        }
+        self.prepare_for_posting(&mut transaction, budget_override)
+            .await?;
+        self.authorize(LedgerOperation::PostTransaction {
+            transaction: &transaction,
+        })?;
+        if self.transaction_manager.requires_approval(&transaction) {
+            let maker = self.actor.as_ref().map(|a| a.id.as_str());
+            return self
+                .transaction_manager
+                .submit_for_approval(transaction, maker)
+                .await;
+        }
+        let posted = self
+            .transaction_manager
             .record_transaction(transaction)
+            .await?;
+        self.notify(LedgerEvent::TransactionPosted(&posted));
+        Ok(posted)
+    }
+
+    /// Checks and adjustments every transaction gets before it is posted,
+    /// whether directly, from a draft, by an edit or on approval
+    ///
+    /// Small imbalances are posted to the round-off account, then fund
+    /// restrictions, budget limits (unless `budget_override` is set) and
+    /// posting rules are applied. Budget and posting-rule warnings are noted
+    /// on the transaction.
+    pub(crate) async fn prepare_for_posting(
+        &self,
+        transaction: &mut Transaction,
+        budget_override: bool,
+    ) -> LedgerResult<()> {
+        if let Some(policy) = self.transaction_manager.round_off_policy() {
+            policy.balance(transaction);
+        }
+        if !self.funds.is_empty() {
+            self.check_fund_restrictions(transaction).await?;
+        }
+        if !budget_override {
+            self.enforce_budget_limits(transaction).await?;
+        }
+        if self.posting_rules.is_some() {
+            self.enforce_posting_rules(transaction).await?;
+        }
+        Ok(())
+    }
+
+    /// Note the outcome of a pre-posting check on a transaction, once: a
+    /// transaction prepared again after an edit keeps a single copy
+    pub(crate) fn add_check_note(&self, transaction: &mut Transaction, text: String) {
+        if transaction.notes.iter().any(|note| note.text == text) {
+            return;
+        }
+        transaction.notes.push(TransactionNote {
+            text,
+            author: self.actor.as_ref().map(|actor| actor.id.clone()),
+            created_at: self.clock.now(),
+        });
+    }
+
+    /// Reject postings whose amounts carry more decimal places than `policy`
+    /// allows; `None` accepts any precision
+    pub fn set_amount_policy(&mut self, policy: Option<AmountPolicy>) {
+        self.transaction_manager.set_amount_policy(policy);
+    }
+
+    /// The amount policy postings are checked against, if any
+    pub fn amount_policy(&self) -> Option<&AmountPolicy> {
+        self.transaction_manager.amount_policy()
+    }
+
+    /// Post differences of up to `policy.tolerance_per_line` per entry to the
+    /// round-off account instead of rejecting the transaction as unbalanced
+    pub fn set_round_off_policy(&mut self, policy: Option<RoundOffPolicy>) {
+        self.transaction_manager.set_round_off_policy(policy);
+    }
+
+    /// The round-off policy applied to recorded transactions, if any
+    pub fn round_off_policy(&self) -> Option<&RoundOffPolicy> {
+        self.transaction_manager.round_off_policy()
+    }
+
+    /// Require approval for transactions whose total exceeds the threshold;
+    /// `None` posts everything directly
+    pub fn set_approval_threshold(&mut self, threshold: Option<BigDecimal>) {
+        self.transaction_manager.set_approval_threshold(threshold);
+    }
+
+    /// List transactions awaiting approval within a date range
+    pub async fn list_pending_approval(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.transaction_manager
+            .list_pending_approval(start_date, end_date)
+            .await
+    }
+
+    /// Approve a pending transaction as the current actor and post it
+    pub async fn approve_transaction(
+        &mut self,
+        transaction_id: &str,
+        reason: Option<String>,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        let operation = LedgerOperation::ApproveTransaction {
+            transaction: &transaction,
+        };
+        self.authorize(operation)?;
+        // Adjustments were made on submission; check again in case the ledger
+        // changed while the transaction waited. A budget breach already
+        // flagged or overridden stays accepted.
+        if transaction.status == TransactionStatus::PendingApproval {
+            let accepted_breach = transaction.metadata.contains_key(BUDGET_EXCEEDED_KEY);
+            self.prepare_for_posting(&mut transaction.clone(), accepted_breach)
+                .await?;
+        }
+        let approver = self
+            .actor
+            .as_ref()
+            .ok_or_else(|| LedgerError::unauthorized(None, &operation))?
+            .id
+            .clone();
+        let approved = self
+            .transaction_manager
+            .approve_transaction(transaction_id, &approver, reason)
+            .await?;
+        self.notify(LedgerEvent::TransactionPosted(&approved));
+        Ok(approved)
+    }
+
+    /// Reject a pending transaction as the current actor
+    pub async fn reject_transaction(
+        &mut self,
+        transaction_id: &str,
+        reason: String,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        let operation = LedgerOperation::RejectTransaction {
+            transaction: &transaction,
+        };
+        self.authorize(operation)?;
+        let approver = self
+            .actor
+            .as_ref()
+            .ok_or_else(|| LedgerError::unauthorized(None, &operation))?
+            .id
+            .clone();
+        self.transaction_manager
+            .reject_transaction(transaction_id, &approver, reason)
             .await
     }
 
+    /// Run full validation on a transaction and return the projected balance
+    /// changes per affected account, without persisting anything
+    ///
+    /// The transaction is prepared as [`Ledger::record_transaction`] would,
+    /// so round-off lines are included and restricted funds, blocking
+    /// budgets and error posting rules fail the simulation.
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> LedgerResult<SimulationResult> {
+        let mut prepared = transaction.clone();
+        self.prepare_for_posting(&mut prepared, false).await?;
+        self.transaction_manager
+            .simulate_transaction(&prepared)
+            .await
+    }
+
+    /// Save a transaction as a draft that does not affect balances
+    ///
+    /// Like [`record_transaction`](Ledger::record_transaction), an empty id
+    /// is filled from the ledger's id generator; the draft is returned as
+    /// stored.
+    pub async fn save_draft(&mut self, mut transaction: Transaction) -> LedgerResult<Transaction> {
+        if transaction.id.is_empty() {
+            transaction.id = self.id_generator.next_id();
+        }
+        self.authorize(LedgerOperation::SaveDraft {
+            transaction: &transaction,
+        })?;
+        let draft = self.transaction_manager.save_draft(transaction).await?;
+        self.notify(LedgerEvent::DraftSaved(&draft));
+        Ok(draft)
+    }
+
+    /// List draft transactions within a date range
+    pub async fn list_drafts(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.transaction_manager
+            .list_drafts(start_date, end_date)
+            .await
+    }
+
+    /// Post a previously saved draft
+    ///
+    /// Like [`Ledger::record_transaction`], a draft above the approval
+    /// threshold is held as [`TransactionStatus::PendingApproval`] instead.
+    pub async fn post_draft(&mut self, transaction_id: &str) -> LedgerResult<Transaction> {
+        let mut draft = self
+            .transaction_manager
+            .get_draft_required(transaction_id)
+            .await?;
+        self.prepare_for_posting(&mut draft, false).await?;
+        self.authorize(LedgerOperation::PostTransaction {
+            transaction: &draft,
+        })?;
+        let maker = self.actor.as_ref().map(|a| a.id.as_str());
+        let posted = self
+            .transaction_manager
+            .post_loaded_draft(draft, maker)
+            .await?;
+        if posted.is_posted() {
+            self.notify(LedgerEvent::TransactionPosted(&posted));
+        }
+        Ok(posted)
+    }
+
+    /// Discard a draft without touching balances
+    pub async fn discard_draft(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        self.authorize(LedgerOperation::VoidTransaction { transaction_id })?;
+        let draft = self.observed_transaction(transaction_id).await?;
+        self.transaction_manager
+            .discard_draft(transaction_id)
+            .await?;
+        if let Some(draft) = draft {
+            self.notify(LedgerEvent::DraftDiscarded(&draft));
+        }
+        Ok(())
+    }
+
     /// Get a transaction by ID
     pub async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
         self.transaction_manager
@@ -118,18 +708,176 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
             .await
     }
 
+    /// List individual entries matching a filter
+    pub async fn get_entries(&self, filter: &EntryFilter) -> LedgerResult<Vec<EntryRow>> {
+        self.transaction_manager.get_entries(filter).await
+    }
+
+    /// Find an entry by its id
+    pub async fn find_entry(&self, entry_id: &str) -> LedgerResult<Option<EntryRow>> {
+        self.transaction_manager.find_entry(entry_id).await
+    }
+
+    /// Get posted transactions of one voucher type within a date range
+    pub async fn get_transactions_by_kind(
+        &self,
+        kind: TransactionKind,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        let mut transactions: Vec<Transaction> = self
+            .get_transactions(start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.kind == kind)
+            .collect();
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        Ok(transactions)
+    }
+
+    /// Generate the daybook for one voucher type (sales book, cash book, ...)
+    pub async fn generate_daybook(
+        &self,
+        kind: TransactionKind,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<Daybook> {
+        let transactions = self
+            .get_transactions_by_kind(kind, Some(start_date), Some(end_date))
+            .await?;
+        let total_amount = transactions.iter().map(|t| t.total_debits()).sum();
+
+        Ok(Daybook {
+            kind,
+            start_date,
+            end_date,
+            transactions,
+            total_amount,
+        })
+    }
+
     /// Update a transaction
+    ///
+    /// A posted transaction is prepared again as
+    /// [`Ledger::record_transaction`] would before the edit is stored.
     pub async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        self.authorize(LedgerOperation::EditTransaction { transaction })?;
+        let mut updated = transaction.clone();
+        if updated.is_posted() {
+            let accepted_breach = updated.metadata.contains_key(BUDGET_EXCEEDED_KEY);
+            self.prepare_for_posting(&mut updated, accepted_breach)
+                .await?;
+        }
+        let old = self.observed_transaction(&updated.id).await?;
         self.transaction_manager
-            .update_transaction(transaction)
+            .update_transaction(&updated)
+            .await?;
+        if let Some(old) = old {
+            self.notify(LedgerEvent::TransactionUpdated {
+                old: &old,
+                new: &updated,
+            });
+        }
+        Ok(())
+    }
+
+    /// Load a transaction before a change only when observers will need it
+    async fn observed_transaction(
+        &self,
+        transaction_id: &str,
+    ) -> LedgerResult<Option<Transaction>> {
+        if self.observers.is_empty() {
+            return Ok(None);
+        }
+        self.transaction_manager
+            .get_transaction(transaction_id)
+            .await
+    }
+
+    /// Append a timestamped note to a transaction, authored by the current
+    /// actor
+    pub async fn add_note(
+        &mut self,
+        transaction_id: &str,
+        text: String,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        self.authorize(LedgerOperation::EditTransaction {
+            transaction: &transaction,
+        })?;
+        let author = self.actor.as_ref().map(|actor| actor.id.clone());
+        self.transaction_manager
+            .add_note(transaction_id, text, author)
+            .await
+    }
+
+    /// Transactions that directly correct a transaction
+    pub async fn get_corrections(&self, transaction_id: &str) -> LedgerResult<Vec<Transaction>> {
+        self.transaction_manager
+            .get_corrections(transaction_id)
+            .await
+    }
+
+    /// The original transaction and every correction in its chain
+    pub async fn get_correction_chain(
+        &self,
+        transaction_id: &str,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.transaction_manager
+            .get_correction_chain(transaction_id)
+            .await
+    }
+
+    /// Link a supporting document to a transaction
+    pub async fn add_attachment(
+        &mut self,
+        transaction_id: &str,
+        attachment: Attachment,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        self.authorize(LedgerOperation::EditTransaction {
+            transaction: &transaction,
+        })?;
+        self.transaction_manager
+            .add_attachment(transaction_id, attachment)
+            .await
+    }
+
+    /// Unlink a supporting document from a transaction
+    pub async fn remove_attachment(
+        &mut self,
+        transaction_id: &str,
+        attachment_id: &str,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        self.authorize(LedgerOperation::EditTransaction {
+            transaction: &transaction,
+        })?;
+        self.transaction_manager
+            .remove_attachment(transaction_id, attachment_id)
             .await
     }
 
     /// Delete a transaction
     pub async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        self.authorize(LedgerOperation::VoidTransaction { transaction_id })?;
+        let voided = self.observed_transaction(transaction_id).await?;
         self.transaction_manager
             .delete_transaction(transaction_id)
-            .await
+            .await?;
+        if let Some(voided) = voided {
+            self.notify(LedgerEvent::TransactionVoided(&voided));
+        }
+        Ok(())
     }
 
     // Balance and reporting operations
@@ -146,10 +894,26 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
 
     /// Get trial balance as of a specific date
     pub async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
-        self.account_manager
-            .storage
-            .get_trial_balance(as_of_date)
-            .await
+        let key = ReportKey {
+            kind: ReportKind::TrialBalance,
+            start_date: None,
+            end_date: as_of_date,
+            scope: ReportScope::Posted,
+        };
+        if let Some(CachedReport::TrialBalance(report)) = self.cached_report(&key) {
+            return Ok(report);
+        }
+        let storage = &self.account_manager.storage;
+        let report = match storage.reporting() {
+            Some(reporting) => {
+                let accounts = storage.list_accounts(None).await?;
+                let totals = reporting.get_account_totals(None, as_of_date).await?;
+                TrialBalance::from_totals(accounts, &totals, as_of_date)
+            }
+            None => storage.get_trial_balance(as_of_date).await?,
+        };
+        self.cache_report(key, CachedReport::TrialBalance(report.clone()));
+        Ok(report)
     }
 
     /// Get account balances grouped by type
@@ -157,10 +921,65 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         &self,
         as_of_date: NaiveDate,
     ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
-        self.account_manager
-            .storage
-            .get_account_balances_by_type(as_of_date)
-            .await
+        if self.account_manager.storage.reporting().is_none() {
+            return self
+                .account_manager
+                .storage
+                .get_account_balances_by_type(as_of_date)
+                .await;
+        }
+        let mut result: HashMap<AccountType, Vec<AccountBalance>> = HashMap::new();
+        for line in self
+            .get_trial_balance(as_of_date)
+            .await?
+            .balances
+            .into_values()
+        {
+            result
+                .entry(line.account.account_type.clone())
+                .or_default()
+                .push(line);
+        }
+        Ok(result)
+    }
+
+    /// Get account balances grouped by type, optionally including drafts
+    pub async fn get_account_balances_by_type_with_scope(
+        &self,
+        as_of_date: NaiveDate,
+        scope: ReportScope,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        let mut balances = self.get_account_balances_by_type(as_of_date).await?;
+        if scope == ReportScope::Posted {
+            return Ok(balances);
+        }
+
+        // Overlay the entries of drafts dated up to the report date
+        let drafts = self.list_drafts(None, Some(as_of_date)).await?;
+        let mut draft_entries: HashMap<&str, Vec<&Entry>> = HashMap::new();
+        for entry in drafts.iter().flat_map(|draft| &draft.entries) {
+            draft_entries
+                .entry(entry.account_id.as_str())
+                .or_default()
+                .push(entry);
+        }
+
+        for line in balances.values_mut().flatten() {
+            if let Some(entries) = draft_entries.get(line.account.id.as_str()) {
+                let effect: BigDecimal = entries
+                    .iter()
+                    .map(|e| {
+                        line.account
+                            .account_type
+                            .balance_effect(&e.entry_type, &e.amount)
+                    })
+                    .sum();
+                let balance = line.signed_balance() + effect;
+                *line = AccountBalance::from_balance(line.account.clone(), balance);
+            }
+        }
+
+        Ok(balances)
     }
 
     /// Generate a balance sheet as of a specific date
@@ -168,7 +987,29 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         &self,
         as_of_date: NaiveDate,
     ) -> LedgerResult<BalanceSheet> {
-        let balances = self.get_account_balances_by_type(as_of_date).await?;
+        self.generate_balance_sheet_with_scope(as_of_date, ReportScope::Posted)
+            .await
+    }
+
+    /// Generate a balance sheet, optionally previewing the effect of drafts
+    pub async fn generate_balance_sheet_with_scope(
+        &self,
+        as_of_date: NaiveDate,
+        scope: ReportScope,
+    ) -> LedgerResult<BalanceSheet> {
+        let key = ReportKey {
+            kind: ReportKind::BalanceSheet,
+            start_date: None,
+            end_date: as_of_date,
+            scope,
+        };
+        if let Some(CachedReport::BalanceSheet(report)) = self.cached_report(&key) {
+            return Ok(report);
+        }
+
+        let balances = self
+            .get_account_balances_by_type_with_scope(as_of_date, scope)
+            .await?;
 
         let assets = balances
             .get(&AccountType::Asset)
@@ -193,50 +1034,55 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
             .cloned()
             .unwrap_or_default();
 
-        let total_income: BigDecimal = income_accounts.iter().map(|ab| ab.balance_amount()).sum();
+        // Totals use signed balances so contra lines (sales returns,
+        // drawings) reduce their section
+        let total_income: BigDecimal = income_accounts.iter().map(|ab| ab.signed_balance()).sum();
         let total_expenses: BigDecimal =
-            expense_accounts.iter().map(|ab| ab.balance_amount()).sum();
+            expense_accounts.iter().map(|ab| ab.signed_balance()).sum();
         let net_income = &total_income - &total_expenses;
 
-        // Add net income to equity as retained earnings (if non-zero)
-        if net_income != BigDecimal::from(0) {
-            let retained_earnings = AccountBalance {
-                account: Account::new(
-                    "net_income".to_string(),
-                    "Net Income".to_string(),
-                    AccountType::Equity,
-                    None,
-                ),
-                debit_balance: if net_income < BigDecimal::from(0) {
-                    Some(net_income.abs())
-                } else {
-                    None
-                },
-                credit_balance: if net_income > BigDecimal::from(0) {
-                    Some(net_income)
-                } else {
-                    None
-                },
-            };
-            equity.push(retained_earnings);
-        }
+        // Roll net income not yet closed into the retained earnings account,
+        // or report it as a computed line when asked to or when there is no
+        // such account
+        let retained_earnings = match self.net_income_presentation {
+            NetIncomePresentation::RollIntoAccount => self
+                .retained_earnings_account_id
+                .as_deref()
+                .and_then(|id| equity.iter_mut().find(|line| line.account.id == id)),
+            NetIncomePresentation::SeparateLine => None,
+        };
+        let current_earnings = match retained_earnings {
+            Some(line) => {
+                let balance = line.signed_balance() + &net_income;
+                *line = AccountBalance::from_balance(line.account.clone(), balance);
+                None
+            }
+            None => Some(net_income),
+        };
 
-        let total_assets: BigDecimal = assets.iter().map(|ab| ab.balance_amount()).sum();
-        let total_liabilities: BigDecimal = liabilities.iter().map(|ab| ab.balance_amount()).sum();
-        let total_equity: BigDecimal = equity.iter().map(|ab| ab.balance_amount()).sum();
+        let total_assets: BigDecimal = assets.iter().map(|ab| ab.signed_balance()).sum();
+        let total_liabilities: BigDecimal = liabilities.iter().map(|ab| ab.signed_balance()).sum();
+        let total_equity: BigDecimal = equity
+            .iter()
+            .map(|ab| ab.signed_balance())
+            .sum::<BigDecimal>()
+            + current_earnings.clone().unwrap_or_default();
 
         let is_balanced = total_assets == (&total_liabilities + &total_equity);
 
-        Ok(BalanceSheet {
+        let report = BalanceSheet {
             as_of_date,
             assets,
             liabilities,
             equity,
+            current_earnings,
             total_assets,
             total_liabilities,
             total_equity,
             is_balanced,
-        })
+        };
+        self.cache_report(key, CachedReport::BalanceSheet(report.clone()));
+        Ok(report)
     }
 
     /// Generate an income statement for a date range
@@ -245,7 +1091,30 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         start_date: NaiveDate,
         end_date: NaiveDate,
     ) -> LedgerResult<IncomeStatement> {
-        let balances = self.get_account_balances_by_type(end_date).await?;
+        self.generate_income_statement_with_scope(start_date, end_date, ReportScope::Posted)
+            .await
+    }
+
+    /// Generate an income statement, optionally previewing the effect of drafts
+    pub async fn generate_income_statement_with_scope(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        scope: ReportScope,
+    ) -> LedgerResult<IncomeStatement> {
+        let key = ReportKey {
+            kind: ReportKind::IncomeStatement,
+            start_date: Some(start_date),
+            end_date,
+            scope,
+        };
+        if let Some(CachedReport::IncomeStatement(report)) = self.cached_report(&key) {
+            return Ok(report);
+        }
+
+        let balances = self
+            .get_account_balances_by_type_with_scope(end_date, scope)
+            .await?;
 
         let revenue = balances
             .get(&AccountType::Income)
@@ -260,7 +1129,7 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
         let total_expenses: BigDecimal = expenses.iter().map(|ab| ab.balance_amount()).sum();
         let net_income = &total_revenue - &total_expenses;
 
-        Ok(IncomeStatement {
+        let report = IncomeStatement {
             start_date,
             end_date,
             revenue,
@@ -268,7 +1137,9 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
             total_revenue,
             total_expenses,
             net_income,
-        })
+        };
+        self.cache_report(key, CachedReport::IncomeStatement(report.clone()));
+        Ok(report)
     }
 
     /// Create a basic cash flow statement
@@ -345,7 +1216,34 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
     pub async fn setup_standard_chart_of_accounts(
         &mut self,
     ) -> LedgerResult<HashMap<String, Account>> {
-        crate::ledger::account::utils::create_standard_chart(&mut self.account_manager).await
+        self.authorize(LedgerOperation::SetupChartOfAccounts)?;
+        let accounts =
+            crate::ledger::account::utils::create_standard_chart(&mut self.account_manager).await?;
+        if self.retained_earnings_account_id.is_none() {
+            self.retained_earnings_account_id =
+                accounts.get("retained_earnings").map(|a| a.id.clone());
+        }
+        self.default_accounts.fill_from_standard_chart(&accounts);
+        Ok(accounts)
+    }
+
+    /// Lock all dates up to and including `through` against posting changes
+    pub fn lock_period(&mut self, through: NaiveDate) -> LedgerResult<()> {
+        self.authorize(LedgerOperation::LockPeriod { through })?;
+        self.transaction_manager.lock_period(through);
+        Ok(())
+    }
+
+    /// Remove the current period lock
+    pub fn unlock_period(&mut self) -> LedgerResult<()> {
+        self.authorize(LedgerOperation::UnlockPeriod)?;
+        self.transaction_manager.unlock_period();
+        Ok(())
+    }
+
+    /// Get the current period lock, if any
+    pub fn period_lock(&self) -> Option<&PeriodLock> {
+        self.transaction_manager.period_lock()
     }
 
     /// Validate the integrity of the ledger
@@ -360,10 +1258,10 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
 
         // Check if trial balance is balanced
         if !trial_balance.is_balanced {
-            issues.push(format!(
-                "Trial balance is not balanced: debits = {}, credits = {}",
-                trial_balance.total_debits, trial_balance.total_credits
-            ));
+            issues.push(IntegrityIssue::TrialBalanceUnbalanced {
+                debits: trial_balance.total_debits.clone(),
+                credits: trial_balance.total_credits.clone(),
+            });
         }
 
         let total_liabilities_equity =
@@ -371,13 +1269,23 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
 
         // Check if balance sheet is balanced
         if !balance_sheet.is_balanced {
-            issues.push(format!(
-                "Balance sheet is not balanced: assets = {}, liabilities + equity = {}",
-                balance_sheet.total_assets, total_liabilities_equity
-            ));
+            issues.push(IntegrityIssue::BalanceSheetUnbalanced {
+                assets: balance_sheet.total_assets.clone(),
+                liabilities_and_equity: total_liabilities_equity.clone(),
+            });
         }
 
-        // Additional checks could be added here
+        let accounts: HashMap<String, Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|account| (account.id.clone(), account))
+            .collect();
+        let transactions = self.get_transactions(None, None).await?;
+
+        check_account_hierarchy(&accounts, &mut issues);
+        check_transactions(&accounts, &transactions, self.period_lock(), &mut issues);
+        check_cached_balances(&accounts, &transactions, &mut issues);
 
         Ok(LedgerIntegrityReport {
             as_of_date,
@@ -391,12 +1299,245 @@ impl<S: LedgerStorage + Clone> Ledger<S> {
     }
 }
 
+/// Detect orphaned parent references and circular account hierarchies
+fn check_account_hierarchy(accounts: &HashMap<String, Account>, issues: &mut Vec<IntegrityIssue>) {
+    let mut reported_cycles: HashSet<Vec<String>> = HashSet::new();
+
+    let mut ids: Vec<&String> = accounts.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let account = &accounts[id];
+        if let Some(parent_id) = &account.parent_id {
+            if !accounts.contains_key(parent_id) {
+                issues.push(IntegrityIssue::OrphanedParent {
+                    account_id: account.id.clone(),
+                    parent_id: parent_id.clone(),
+                });
+            }
+        }
+
+        // Walk up the parent chain until it ends or revisits an account
+        let mut chain: Vec<String> = Vec::new();
+        let mut current = Some(id.clone());
+        while let Some(current_id) = current {
+            if let Some(position) = chain.iter().position(|c| *c == current_id) {
+                let mut cycle = chain[position..].to_vec();
+                cycle.sort();
+                if reported_cycles.insert(cycle.clone()) {
+                    issues.push(IntegrityIssue::CircularHierarchy { account_ids: cycle });
+                }
+                break;
+            }
+            current = accounts
+                .get(&current_id)
+                .and_then(|account| account.parent_id.clone());
+            chain.push(current_id);
+        }
+    }
+}
+
+/// Detect unbalanced transactions, missing account references and postings
+/// inside the locked period
+fn check_transactions(
+    accounts: &HashMap<String, Account>,
+    transactions: &[Transaction],
+    period_lock: Option<&PeriodLock>,
+    issues: &mut Vec<IntegrityIssue>,
+) {
+    let mut transactions: Vec<&Transaction> = transactions.iter().collect();
+    transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+    for transaction in transactions {
+        if !transaction.is_balanced() {
+            issues.push(IntegrityIssue::UnbalancedTransaction {
+                transaction_id: transaction.id.clone(),
+                debits: transaction.total_debits(),
+                credits: transaction.total_credits(),
+            });
+        }
+
+        for entry in &transaction.entries {
+            if !accounts.contains_key(&entry.account_id) {
+                issues.push(IntegrityIssue::MissingAccountReference {
+                    transaction_id: transaction.id.clone(),
+                    account_id: entry.account_id.clone(),
+                });
+            }
+        }
+
+        // Transactions legitimately posted before the lock are fine; anything
+        // written after the lock was applied bypassed it
+        if let Some(lock) = period_lock {
+            if lock.covers(transaction.date) && transaction.updated_at > lock.locked_at {
+                issues.push(IntegrityIssue::LockedPeriodTransaction {
+                    transaction_id: transaction.id.clone(),
+                    date: transaction.date,
+                    locked_through: lock.locked_through,
+                });
+            }
+        }
+    }
+}
+
+/// Detect accounts whose stored balance differs from a full journal replay
+fn check_cached_balances(
+    accounts: &HashMap<String, Account>,
+    transactions: &[Transaction],
+    issues: &mut Vec<IntegrityIssue>,
+) {
+    let mut computed: HashMap<&str, BigDecimal> = HashMap::new();
+    for transaction in transactions.iter().filter(|t| t.is_posted()) {
+        for entry in &transaction.entries {
+            if let Some(account) = accounts.get(&entry.account_id) {
+                *computed
+                    .entry(account.id.as_str())
+                    .or_insert_with(BigDecimal::zero) += account
+                    .account_type
+                    .balance_effect(&entry.entry_type, &entry.amount);
+            }
+        }
+    }
+
+    let mut ids: Vec<&String> = accounts.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let account = &accounts[id];
+        let computed_balance = computed
+            .remove(account.id.as_str())
+            .unwrap_or_else(BigDecimal::zero);
+        if computed_balance != account.balance {
+            issues.push(IntegrityIssue::BalanceMismatch {
+                account_id: account.id.clone(),
+                stored: account.balance.clone(),
+                computed: computed_balance,
+            });
+        }
+    }
+}
+
+/// Individual problem found by [`Ledger::validate_integrity`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum IntegrityIssue {
+    /// Trial balance debits and credits differ
+    TrialBalanceUnbalanced {
+        debits: BigDecimal,
+        credits: BigDecimal,
+    },
+    /// Assets differ from liabilities plus equity
+    BalanceSheetUnbalanced {
+        assets: BigDecimal,
+        liabilities_and_equity: BigDecimal,
+    },
+    /// A stored transaction whose debits and credits differ
+    UnbalancedTransaction {
+        transaction_id: String,
+        debits: BigDecimal,
+        credits: BigDecimal,
+    },
+    /// A transaction entry references an account that does not exist
+    MissingAccountReference {
+        transaction_id: String,
+        account_id: String,
+    },
+    /// An account's parent does not exist
+    OrphanedParent {
+        account_id: String,
+        parent_id: String,
+    },
+    /// Accounts whose parent chain loops back on itself
+    CircularHierarchy { account_ids: Vec<String> },
+    /// A transaction inside the locked period was written after the lock
+    LockedPeriodTransaction {
+        transaction_id: String,
+        date: NaiveDate,
+        locked_through: NaiveDate,
+    },
+    /// Stored account balance differs from the balance computed from its entries
+    BalanceMismatch {
+        account_id: String,
+        stored: BigDecimal,
+        computed: BigDecimal,
+    },
+}
+
+impl std::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityIssue::TrialBalanceUnbalanced { debits, credits } => write!(
+                f,
+                "Trial balance is not balanced: debits = {}, credits = {}",
+                debits, credits
+            ),
+            IntegrityIssue::BalanceSheetUnbalanced {
+                assets,
+                liabilities_and_equity,
+            } => write!(
+                f,
+                "Balance sheet is not balanced: assets = {}, liabilities + equity = {}",
+                assets, liabilities_and_equity
+            ),
+            IntegrityIssue::UnbalancedTransaction {
+                transaction_id,
+                debits,
+                credits,
+            } => write!(
+                f,
+                "Transaction '{}' is not balanced: debits = {}, credits = {}",
+                transaction_id, debits, credits
+            ),
+            IntegrityIssue::MissingAccountReference {
+                transaction_id,
+                account_id,
+            } => write!(
+                f,
+                "Transaction '{}' references missing account '{}'",
+                transaction_id, account_id
+            ),
+            IntegrityIssue::OrphanedParent {
+                account_id,
+                parent_id,
+            } => write!(
+                f,
+                "Account '{}' has missing parent '{}'",
+                account_id, parent_id
+            ),
+            IntegrityIssue::CircularHierarchy { account_ids } => write!(
+                f,
+                "Circular account hierarchy: {}",
+                account_ids.join(" -> ")
+            ),
+            IntegrityIssue::LockedPeriodTransaction {
+                transaction_id,
+                date,
+                locked_through,
+            } => write!(
+                f,
+                "Transaction '{}' dated {} was written after the period was locked through {}",
+                transaction_id, date, locked_through
+            ),
+            IntegrityIssue::BalanceMismatch {
+                account_id,
+                stored,
+                computed,
+            } => write!(
+                f,
+                "Account '{}' stored balance {} does not match computed balance {}",
+                account_id, stored, computed
+            ),
+        }
+    }
+}
+
 /// Report on ledger integrity and validation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LedgerIntegrityReport {
     pub as_of_date: NaiveDate,
     pub is_valid: bool,
-    pub issues: Vec<String>,
+    pub issues: Vec<IntegrityIssue>,
     pub trial_balance_total_debits: BigDecimal,
     pub trial_balance_total_credits: BigDecimal,
     pub balance_sheet_total_assets: BigDecimal,
@@ -469,4 +1610,330 @@ mod tests {
 
         assert_eq!(balance_sheet.total_assets, BigDecimal::from(1000));
     }
+
+    #[tokio::test]
+    async fn test_integrity_detects_storage_corruption() {
+        let storage = MemoryStorage::new();
+        let mut raw = storage.clone();
+        let mut ledger = Ledger::new(storage);
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        ledger
+            .create_account(
+                "cash".to_string(),
+                "Cash".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger
+            .create_account(
+                "capital".to_string(),
+                "Capital".to_string(),
+                AccountType::Equity,
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Accounts pointing at each other, and one pointing nowhere
+        let mut a = Account::new("a".to_string(), "A".to_string(), AccountType::Asset, None);
+        a.parent_id = Some("b".to_string());
+        let mut b = Account::new("b".to_string(), "B".to_string(), AccountType::Asset, None);
+        b.parent_id = Some("a".to_string());
+        let mut orphan = Account::new(
+            "orphan".to_string(),
+            "Orphan".to_string(),
+            AccountType::Asset,
+            Some("missing".to_string()),
+        );
+        orphan.balance = BigDecimal::from(5);
+        raw.save_account(&a).await.unwrap();
+        raw.save_account(&b).await.unwrap();
+        raw.save_account(&orphan).await.unwrap();
+
+        // An unbalanced transaction referencing an unknown account, written
+        // straight to storage after the period was locked
+        ledger.lock_period(date).unwrap();
+        let mut bad = Transaction::new("bad".to_string(), date, "Bad".to_string(), None);
+        bad.add_entry(Entry::debit("cash".to_string(), BigDecimal::from(10), None));
+        bad.add_entry(Entry::credit(
+            "ghost".to_string(),
+            BigDecimal::from(4),
+            None,
+        ));
+        raw.save_transaction(&bad).await.unwrap();
+
+        let report = ledger.validate_integrity(date).await.unwrap();
+        assert!(!report.is_valid);
+
+        let has = |f: &dyn Fn(&IntegrityIssue) -> bool| report.issues.iter().any(f);
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::UnbalancedTransaction { .. }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::MissingAccountReference { account_id, .. } if account_id == "ghost"
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::OrphanedParent { account_id, .. } if account_id == "orphan"
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::CircularHierarchy { account_ids } if account_ids == &["a", "b"]
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::LockedPeriodTransaction { .. }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::BalanceMismatch { account_id, .. } if account_id == "cash"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_locked_period_rejects_postings() {
+        let storage = MemoryStorage::new();
+        let mut ledger = Ledger::new(storage);
+        ledger
+            .create_account(
+                "cash".to_string(),
+                "Cash".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger
+            .create_account(
+                "sales".to_string(),
+                "Sales".to_string(),
+                AccountType::Income,
+                None,
+            )
+            .await
+            .unwrap();
+
+        ledger
+            .lock_period(chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())
+            .unwrap();
+
+        let sale = |id: &str, day: u32| {
+            crate::ledger::transaction::patterns::create_sales_transaction(
+                id.to_string(),
+                chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+                "Sale".to_string(),
+                "cash".to_string(),
+                "sales".to_string(),
+                BigDecimal::from(100),
+            )
+            .unwrap()
+        };
+
+        assert!(matches!(
+            ledger.record_transaction(sale("t1", 15)).await,
+            Err(LedgerError::PeriodLocked(_))
+        ));
+
+        ledger.unlock_period().unwrap();
+        ledger.record_transaction(sale("t1", 15)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_authorization_policy_guards_operations() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.set_authorization_policy(Box::new(
+            RoleBasedPolicy::new()
+                .require("post_transaction", "accountant")
+                .require("lock_period", "controller"),
+        ));
+        for (id, account_type) in [("cash", AccountType::Asset), ("sales", AccountType::Income)] {
+            ledger
+                .create_account(id.to_string(), id.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let sale = crate::ledger::transaction::patterns::create_sales_transaction(
+            "t1".to_string(),
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            "Sale".to_string(),
+            "cash".to_string(),
+            "sales".to_string(),
+            BigDecimal::from(100),
+        )
+        .unwrap();
+
+        let error = ledger.record_transaction(sale.clone()).await.unwrap_err();
+        assert_eq!(error.code(), "unauthorized");
+
+        ledger.set_actor(Some(Actor::new(
+            "asha".to_string(),
+            vec!["accountant".to_string()],
+        )));
+        ledger.record_transaction(sale).await.unwrap();
+        assert!(matches!(
+            ledger.lock_period(chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()),
+            Err(LedgerError::Unauthorized { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_amount_policy_rejects_long_decimal_tails() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let policy = AmountPolicy::default();
+        ledger.set_amount_policy(Some(policy.clone()));
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let share = BigDecimal::from(1000) / BigDecimal::from(3);
+        let split = |id: &str| {
+            crate::ledger::TransactionBuilder::new(id.to_string(), date, "Split".to_string())
+                .debit("6000".to_string(), share.clone(), None)
+                .credit("1000".to_string(), share.clone(), None)
+        };
+
+        assert!(matches!(
+            ledger
+                .record_transaction(split("raw").build().unwrap())
+                .await,
+            Err(LedgerError::ExcessPrecision { max_scale: 2, .. })
+        ));
+        ledger
+            .record_transaction(split("rounded").amount_policy(&policy).build().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            ledger.get_account_balance("6000", None).await.unwrap(),
+            "333.33".parse::<BigDecimal>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_round_off_balances_paisa_differences() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("2200", "GST Payable", AccountType::Liability),
+            ("6900", "Round Off", AccountType::Expense),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let amount = |s: &str| s.parse::<BigDecimal>().unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let invoice = |id: &str, total: &str| {
+            let mut transaction =
+                Transaction::new(id.to_string(), date, "Imported invoice".to_string(), None);
+            transaction.add_entry(Entry::debit("1200".to_string(), amount(total), None));
+            transaction.add_entry(Entry::credit("4000".to_string(), amount("847.46"), None));
+            transaction.add_entry(Entry::credit("2200".to_string(), amount("152.53"), None));
+            transaction
+        };
+
+        assert!(matches!(
+            ledger.record_transaction(invoice("inv-1", "1000")).await,
+            Err(LedgerError::Unbalanced { .. })
+        ));
+        ledger.set_round_off_policy(Some(RoundOffPolicy::new(
+            "6900".to_string(),
+            amount("0.01"),
+        )));
+        ledger
+            .record_transaction(invoice("inv-1", "1000"))
+            .await
+            .unwrap();
+        assert_eq!(
+            ledger.get_account_balance("6900", None).await.unwrap(),
+            amount("-0.01")
+        );
+        assert!(matches!(
+            ledger.record_transaction(invoice("inv-2", "1000.10")).await,
+            Err(LedgerError::Unbalanced { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_allocation_remainder_keeps_transaction_balanced() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let amount = |s: &str| s.parse::<BigDecimal>().unwrap();
+        let policy = AmountPolicy::default();
+        assert_eq!(
+            policy
+                .allocate(&amount("1000"), &[amount("60"), amount("30"), amount("10")])
+                .unwrap(),
+            vec![amount("600"), amount("300"), amount("100")]
+        );
+        assert_eq!(
+            policy
+                .allocate(&amount("-0.05"), &[amount("1"), amount("1"), amount("1")])
+                .unwrap(),
+            vec![amount("-0.02"), amount("-0.02"), amount("-0.01")]
+        );
+        assert!(policy
+            .allocate(&amount("10"), &[BigDecimal::zero()])
+            .is_err());
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let departments = [
+            ("6000".to_string(), amount("1")),
+            ("6100".to_string(), amount("1")),
+            ("5000".to_string(), amount("1")),
+        ];
+        let rent =
+            crate::ledger::TransactionBuilder::new("rent".to_string(), date, "Rent".to_string())
+                .allocate_debit(&amount("100"), &departments, None)
+                .credit("1000".to_string(), amount("100"), None)
+                .build()
+                .unwrap();
+        ledger.record_transaction(rent).await.unwrap();
+        assert_eq!(
+            ledger.get_account_balance("6000", None).await.unwrap(),
+            amount("33.34")
+        );
+        assert_eq!(
+            ledger.get_account_balance("5000", None).await.unwrap(),
+            amount("33.33")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generated_ids_are_returned_and_follow_the_clock() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let now = chrono::TimeZone::with_ymd_and_hms(&chrono::Utc, 2024, 6, 1, 9, 0, 0).unwrap();
+        ledger.set_clock(Arc::new(FixedClock::new(now)));
+        let rent = || {
+            crate::ledger::transaction::patterns::create_expense_payment(
+                String::new(),
+                NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+                "Rent".to_string(),
+                "6000".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(100),
+            )
+            .unwrap()
+        };
+
+        let recorded = ledger.record_transaction(rent()).await.unwrap();
+        let uuid = uuid::Uuid::parse_str(&recorded.id).unwrap();
+        let (seconds, _) = uuid.get_timestamp().unwrap().to_unix();
+        assert_eq!(seconds, now.timestamp() as u64);
+        assert!(ledger
+            .get_transaction(&recorded.id)
+            .await
+            .unwrap()
+            .is_some());
+
+        // Drafts saved without an id do not overwrite each other
+        let first = ledger.save_draft(rent()).await.unwrap();
+        let second = ledger.save_draft(rent()).await.unwrap();
+        assert!(first.id < second.id);
+        assert_eq!(ledger.list_drafts(None, None).await.unwrap().len(), 2);
+    }
 }
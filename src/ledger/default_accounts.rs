@@ -0,0 +1,442 @@
+//! Accounts the ledger's helpers post to unless told otherwise
+//!
+//! [`DefaultAccounts`] names the account filling each common role (sales
+//! revenue, receivables, payables, cash, the GST output and input accounts).
+//! Configure it once with [`Ledger::set_default_accounts`] or
+//! [`LedgerConfig`](crate::ledger::LedgerConfig) and helpers such as
+//! [`Ledger::post_gst_invoice`], [`Ledger::issue_invoice_with_defaults`] and
+//! [`Ledger::default_payment_run`] take only what differs per call.
+//! [`Ledger::setup_standard_chart_of_accounts`] fills the roles the standard
+//! chart has accounts for.
+//!
+//! The round-off, suspense and retained earnings accounts are ledger
+//! settings of their own: see [`Ledger::set_round_off_policy`],
+//! [`Ledger::set_suspense_account`] and
+//! [`Ledger::set_retained_earnings_account`].
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::ledger::{patterns, GstAccounts, GstPurchaseParams, GstSaleParams, Ledger};
+use crate::payables::{Bill, PaymentRun, PurchaseAccounts};
+use crate::receivables::{Invoice, SalesAccounts};
+use crate::tax::GstCalculation;
+use crate::traits::*;
+use crate::types::*;
+
+/// The account filling each common posting role; `None` where the chart has
+/// none
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DefaultAccounts {
+    /// Revenue account sales are credited to
+    #[serde(default)]
+    pub sales_revenue: Option<String>,
+    /// Asset account customers' balances are kept in
+    #[serde(default)]
+    pub receivables: Option<String>,
+    /// Liability account suppliers' balances are kept in
+    #[serde(default)]
+    pub payables: Option<String>,
+    /// Cash or bank account payments are made from and received into
+    #[serde(default)]
+    pub cash: Option<String>,
+    /// CGST, SGST and IGST collected on sales
+    #[serde(default)]
+    pub gst_output: Option<GstAccounts>,
+    /// CGST, SGST and IGST credit on purchases
+    #[serde(default)]
+    pub gst_input: Option<GstAccounts>,
+}
+
+fn required<'a, T>(account: &'a Option<T>, role: &str) -> LedgerResult<&'a T> {
+    account.as_ref().ok_or_else(|| {
+        LedgerError::Validation(format!("No default {} account is configured", role))
+    })
+}
+
+impl DefaultAccounts {
+    /// Fill unset roles from the accounts created by
+    /// [`create_standard_chart`](crate::ledger::account::utils::create_standard_chart)
+    pub(crate) fn fill_from_standard_chart(&mut self, accounts: &HashMap<String, Account>) {
+        for (role, key) in [
+            (&mut self.sales_revenue, "sales_revenue"),
+            (&mut self.receivables, "accounts_receivable"),
+            (&mut self.payables, "accounts_payable"),
+            (&mut self.cash, "cash"),
+        ] {
+            if role.is_none() {
+                *role = accounts.get(key).map(|account| account.id.clone());
+            }
+        }
+    }
+
+    /// Accounts a sales invoice posts to
+    pub fn sales_accounts(&self) -> LedgerResult<SalesAccounts> {
+        Ok(SalesAccounts {
+            receivables_account_id: required(&self.receivables, "receivables")?.clone(),
+            revenue_account_id: required(&self.sales_revenue, "sales revenue")?.clone(),
+            output_tax: required(&self.gst_output, "GST output")?.clone(),
+        })
+    }
+
+    /// Accounts a supplier bill for `expense_account_id` posts to
+    pub fn purchase_accounts(&self, expense_account_id: String) -> LedgerResult<PurchaseAccounts> {
+        Ok(PurchaseAccounts {
+            payables_account_id: required(&self.payables, "payables")?.clone(),
+            expense_account_id,
+            input_tax: required(&self.gst_input, "GST input")?.clone(),
+        })
+    }
+
+    /// A cash sale (debit cash, credit sales revenue)
+    pub fn cash_sale(
+        &self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        patterns::create_sales_transaction(
+            id,
+            date,
+            description,
+            required(&self.cash, "cash")?.clone(),
+            required(&self.sales_revenue, "sales revenue")?.clone(),
+            amount,
+        )
+    }
+
+    /// A sale on credit (debit receivables, credit sales revenue)
+    pub fn credit_sale(
+        &self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        patterns::create_sales_transaction(
+            id,
+            date,
+            description,
+            required(&self.receivables, "receivables")?.clone(),
+            required(&self.sales_revenue, "sales revenue")?.clone(),
+            amount,
+        )
+    }
+
+    /// An expense paid in cash (debit the expense, credit cash)
+    pub fn expense_payment(
+        &self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        expense_account_id: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        patterns::create_expense_payment(
+            id,
+            date,
+            description,
+            expense_account_id,
+            required(&self.cash, "cash")?.clone(),
+            amount,
+        )
+    }
+
+    /// Parameters for a GST sale on credit to sales revenue
+    pub fn gst_sale(
+        &self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        calculation: GstCalculation,
+    ) -> LedgerResult<GstSaleParams> {
+        let accounts = self.sales_accounts()?;
+        Ok(GstSaleParams {
+            id,
+            date,
+            description,
+            receivables_account_id: accounts.receivables_account_id,
+            revenue_account_id: accounts.revenue_account_id,
+            output_accounts: accounts.output_tax,
+            calculation,
+            cess_amount: BigDecimal::from(0),
+        })
+    }
+
+    /// Parameters for a GST purchase of `expense_account_id` on credit
+    pub fn gst_purchase(
+        &self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        expense_account_id: String,
+        calculation: GstCalculation,
+    ) -> LedgerResult<GstPurchaseParams> {
+        let accounts = self.purchase_accounts(expense_account_id)?;
+        Ok(GstPurchaseParams {
+            id,
+            date,
+            description,
+            expense_account_id: accounts.expense_account_id,
+            cash_or_payables_account_id: accounts.payables_account_id,
+            input_accounts: accounts.input_tax,
+            calculation,
+            cess_amount: BigDecimal::from(0),
+        })
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Set the accounts helpers post to by default
+    pub fn set_default_accounts(&mut self, accounts: DefaultAccounts) {
+        self.default_accounts = accounts;
+    }
+
+    /// The accounts helpers post to by default
+    pub fn default_accounts(&self) -> &DefaultAccounts {
+        &self.default_accounts
+    }
+
+    /// Post a GST sale on credit to the default receivables, sales revenue
+    /// and GST output accounts
+    pub async fn post_gst_invoice(
+        &mut self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        calculation: GstCalculation,
+    ) -> LedgerResult<Transaction> {
+        let params = self
+            .default_accounts
+            .gst_sale(id, date, description, calculation)?;
+        let transaction = patterns::create_gst_sale(params)?;
+        self.record_transaction(transaction).await
+    }
+
+    /// Post a GST purchase of `expense_account_id` on credit to the default
+    /// payables and GST input accounts
+    pub async fn post_gst_bill(
+        &mut self,
+        id: String,
+        date: NaiveDate,
+        description: String,
+        expense_account_id: String,
+        calculation: GstCalculation,
+    ) -> LedgerResult<Transaction> {
+        let params = self.default_accounts.gst_purchase(
+            id,
+            date,
+            description,
+            expense_account_id,
+            calculation,
+        )?;
+        let transaction = patterns::create_gst_purchase(params)?;
+        self.record_transaction(transaction).await
+    }
+
+    /// Issue an invoice to the default receivables, sales revenue and GST
+    /// output accounts
+    ///
+    /// See [`Ledger::issue_invoice`].
+    pub async fn issue_invoice_with_defaults(
+        &mut self,
+        invoice: &mut Invoice,
+    ) -> LedgerResult<Transaction> {
+        let accounts = self.default_accounts.sales_accounts()?;
+        self.issue_invoice(invoice, &accounts).await
+    }
+
+    /// Post a bill for `expense_account_id` to the default payables and GST
+    /// input accounts
+    ///
+    /// See [`Ledger::post_bill`].
+    pub async fn post_bill_with_defaults(
+        &mut self,
+        bill: &mut Bill,
+        expense_account_id: String,
+    ) -> LedgerResult<Transaction> {
+        let accounts = self
+            .default_accounts
+            .purchase_accounts(expense_account_id)?;
+        self.post_bill(bill, &accounts).await
+    }
+
+    /// A payment run paying from the default cash account against the
+    /// default payables account, rounding to the ledger's
+    /// [currency policy](Ledger::currency_amount_policy)
+    pub fn default_payment_run(&self, id: String, date: NaiveDate) -> LedgerResult<PaymentRun> {
+        let mut run = PaymentRun::new(
+            id,
+            date,
+            required(&self.default_accounts.cash, "cash")?.clone(),
+            required(&self.default_accounts.payables, "payables")?.clone(),
+        );
+        run.amount_policy = self.currency_amount_policy();
+        Ok(run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payables::BillStatus;
+    use crate::receivables::PaymentTerms;
+    use crate::tax::{GstInvoice, GstLineItem, GstRate};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_helpers_post_to_default_accounts() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name) in [
+            ("2310", "CGST Output"),
+            ("2320", "SGST Output"),
+            ("2330", "IGST Output"),
+        ] {
+            ledger
+                .create_account(
+                    id.to_string(),
+                    name.to_string(),
+                    AccountType::Liability,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let calculation =
+            GstCalculation::calculate(BigDecimal::from(1000), GstRate::intra_state(18.into()))
+                .unwrap();
+
+        // GST output accounts are not in the standard chart
+        assert!(ledger
+            .post_gst_invoice(
+                "INV-0".to_string(),
+                date,
+                "Sale".to_string(),
+                calculation.clone()
+            )
+            .await
+            .is_err());
+
+        let mut defaults = ledger.default_accounts().clone();
+        assert_eq!(defaults.receivables.as_deref(), Some("1200"));
+        defaults.gst_output = Some(GstAccounts {
+            cgst_account_id: "2310".to_string(),
+            sgst_account_id: "2320".to_string(),
+            igst_account_id: "2330".to_string(),
+            cess_account_id: None,
+        });
+        ledger.set_default_accounts(defaults);
+
+        ledger
+            .post_gst_invoice("INV-1".to_string(), date, "Sale".to_string(), calculation)
+            .await
+            .unwrap();
+        let cash_sale = ledger
+            .default_accounts()
+            .cash_sale(
+                "CS-1".to_string(),
+                date,
+                "Counter sale".to_string(),
+                BigDecimal::from(50),
+            )
+            .unwrap();
+        ledger.record_transaction(cash_sale).await.unwrap();
+
+        let balance = |id: &'static str| {
+            let ledger = &ledger;
+            async move { ledger.get_account_balance(id, None).await.unwrap() }
+        };
+        assert_eq!(balance("1200").await, BigDecimal::from(1180));
+        assert_eq!(balance("4000").await, BigDecimal::from(1050));
+        assert_eq!(balance("2310").await, BigDecimal::from(90));
+        assert_eq!(balance("1000").await, BigDecimal::from(50));
+    }
+
+    #[tokio::test]
+    async fn test_documents_post_to_default_accounts() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("2310", "CGST Output", AccountType::Liability),
+            ("2320", "SGST Output", AccountType::Liability),
+            ("1410", "CGST Input", AccountType::Asset),
+            ("1420", "SGST Input", AccountType::Asset),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let mut defaults = ledger.default_accounts().clone();
+        defaults.gst_output = Some(GstAccounts {
+            cgst_account_id: "2310".to_string(),
+            sgst_account_id: "2320".to_string(),
+            igst_account_id: "2330".to_string(),
+            cess_account_id: None,
+        });
+        defaults.gst_input = Some(GstAccounts {
+            cgst_account_id: "1410".to_string(),
+            sgst_account_id: "1420".to_string(),
+            igst_account_id: "1430".to_string(),
+            cess_account_id: None,
+        });
+        ledger.set_default_accounts(defaults);
+        assert_eq!(ledger.retained_earnings_account(), Some("3200"));
+
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let gst = |amount: i32| {
+            GstInvoice::new(vec![GstLineItem::new(
+                "Services".to_string(),
+                BigDecimal::from(1),
+                BigDecimal::from(amount),
+                GstRate::intra_state(BigDecimal::from(18)),
+            )
+            .unwrap()])
+        };
+        let mut invoice = Invoice::new(
+            "INV-1".to_string(),
+            "acme".to_string(),
+            date,
+            gst(1000),
+            None,
+        )
+        .unwrap();
+        ledger
+            .issue_invoice_with_defaults(&mut invoice)
+            .await
+            .unwrap();
+        let mut bills = vec![Bill::new(
+            "B1".to_string(),
+            "fixit".to_string(),
+            "F-77".to_string(),
+            date,
+            gst(500),
+            Some(PaymentTerms::DueOnReceipt),
+        )
+        .unwrap()];
+        ledger
+            .post_bill_with_defaults(&mut bills[0], "6100".to_string())
+            .await
+            .unwrap();
+        let run = ledger
+            .default_payment_run("RUN-1".to_string(), date)
+            .unwrap();
+        ledger.execute_payment_run(&run, &mut bills).await.unwrap();
+
+        let balance = |id: &'static str| {
+            let ledger = &ledger;
+            async move { ledger.get_account_balance(id, None).await.unwrap() }
+        };
+        assert_eq!(balance("1200").await, BigDecimal::from(1180));
+        assert_eq!(balance("1410").await, BigDecimal::from(45));
+        assert_eq!(balance("1000").await, BigDecimal::from(-590));
+        assert_eq!(balance("2000").await, BigDecimal::from(0));
+        assert_eq!(bills[0].status, BillStatus::Paid);
+    }
+}
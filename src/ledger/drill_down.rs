@@ -0,0 +1,163 @@
+//! Drill-down from report lines to the transactions behind them
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// One posting behind a balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DrillDownLine {
+    pub transaction_id: String,
+    pub entry_id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub reference: Option<String>,
+    pub entry_type: EntryType,
+    pub amount: BigDecimal,
+    /// Balance after this posting, in the account's normal direction
+    pub running_balance: BigDecimal,
+}
+
+/// Postings that make up an account's movement over a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DrillDown {
+    pub account_id: String,
+    /// First day of the period; `None` drills from the beginning
+    pub start_date: Option<NaiveDate>,
+    pub end_date: NaiveDate,
+    pub opening_balance: BigDecimal,
+    pub lines: Vec<DrillDownLine>,
+    pub closing_balance: BigDecimal,
+}
+
+impl DrillDown {
+    /// Ids of the underlying transactions, in posting order
+    pub fn transaction_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for line in &self.lines {
+            if !ids.contains(&line.transaction_id.as_str()) {
+                ids.push(&line.transaction_id);
+            }
+        }
+        ids
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// List the posted transactions behind a report line
+    ///
+    /// Report lines hold balances accumulated through the report's end date,
+    /// income statement lines included, so pass `None` as the start date to
+    /// list everything behind the figure.
+    pub async fn drill_down(
+        &self,
+        line: &AccountBalance,
+        start_date: Option<NaiveDate>,
+        end_date: NaiveDate,
+    ) -> LedgerResult<DrillDown> {
+        self.drill_down_account(&line.account.id, start_date, end_date)
+            .await
+    }
+
+    /// List the posted transactions that moved an account over a period
+    pub async fn drill_down_account(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: NaiveDate,
+    ) -> LedgerResult<DrillDown> {
+        let account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        let rows = self
+            .get_entries(&EntryFilter {
+                account_id: Some(account_id.to_string()),
+                end_date: Some(end_date),
+                ..EntryFilter::default()
+            })
+            .await?;
+
+        let mut opening_balance = BigDecimal::zero();
+        let mut running_balance = BigDecimal::zero();
+        let mut lines = Vec::new();
+        for row in rows {
+            let effect = account
+                .account_type
+                .balance_effect(&row.entry.entry_type, &row.entry.amount);
+            running_balance += effect;
+            if start_date.is_some_and(|start| row.date < start) {
+                opening_balance = running_balance.clone();
+                continue;
+            }
+            lines.push(DrillDownLine {
+                transaction_id: row.transaction_id,
+                entry_id: row.entry.id,
+                date: row.date,
+                description: row.entry.description.unwrap_or(row.transaction_description),
+                reference: row.reference,
+                entry_type: row.entry.entry_type,
+                amount: row.entry.amount,
+                running_balance: running_balance.clone(),
+            });
+        }
+
+        Ok(DrillDown {
+            account_id: account_id.to_string(),
+            start_date,
+            end_date,
+            opening_balance,
+            lines,
+            closing_balance: running_balance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_drill_down_from_income_statement_line() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for month in 1..=3 {
+            let rent = patterns::create_expense_payment(
+                format!("rent-{}", month),
+                NaiveDate::from_ymd_opt(2024, month, 5).unwrap(),
+                format!("Rent for month {}", month),
+                "6000".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(8000),
+            )
+            .unwrap();
+            ledger.record_transaction(rent).await.unwrap();
+        }
+
+        let start = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let statement = ledger.generate_income_statement(start, end).await.unwrap();
+        let rent_line = statement
+            .expenses
+            .iter()
+            .find(|line| line.account.id == "6000")
+            .unwrap();
+
+        let drill = ledger
+            .drill_down(rent_line, Some(start), end)
+            .await
+            .unwrap();
+        assert_eq!(drill.transaction_ids(), vec!["rent-2", "rent-3"]);
+        assert_eq!(drill.opening_balance, BigDecimal::from(8000));
+        assert_eq!(drill.lines[1].running_balance, BigDecimal::from(24000));
+        assert_eq!(drill.closing_balance, rent_line.balance_amount());
+    }
+}
@@ -0,0 +1,473 @@
+//! Multi-entity support: one ledger per company within a single application
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ledger::{patterns, IntercompanyParams, Ledger};
+use crate::traits::*;
+use crate::types::*;
+
+/// A legal entity (company) keeping its own books
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Entity {
+    pub id: String,
+    pub name: String,
+    /// Optional tax registration, e.g. the entity's GSTIN
+    pub tax_id: Option<String>,
+}
+
+impl Entity {
+    /// Create a new entity
+    pub fn new(id: String, name: String) -> Self {
+        Self {
+            id,
+            name,
+            tax_id: None,
+        }
+    }
+}
+
+/// Entry dimension naming the other entity on intercompany postings
+pub const COUNTERPARTY_ENTITY_DIMENSION: &str = "counterparty_entity";
+
+/// Intercompany positions between two entities
+///
+/// Positions are debit-positive: a due-from is positive and a due-to is
+/// negative, so matched balances sum to zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IntercompanyBalance {
+    pub entity_id: String,
+    pub counterparty_id: String,
+    /// Entity's net position against the counterparty
+    pub position: BigDecimal,
+    /// Counterparty's net position against the entity
+    pub counterparty_position: BigDecimal,
+    /// Sum of both positions; zero when matched
+    pub difference: BigDecimal,
+}
+
+impl IntercompanyBalance {
+    pub fn is_matched(&self) -> bool {
+        self.difference.is_zero()
+    }
+}
+
+/// Intercompany balances across all entities in a ledger set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct IntercompanyReport {
+    pub as_of_date: NaiveDate,
+    pub balances: Vec<IntercompanyBalance>,
+    /// Intercompany transactions (entity ID, transaction ID) whose mirror is
+    /// missing from the counterparty
+    pub unmatched_transactions: Vec<(String, String)>,
+}
+
+impl IntercompanyReport {
+    /// Balances that do not net to zero
+    pub fn unmatched_balances(&self) -> Vec<&IntercompanyBalance> {
+        self.balances.iter().filter(|b| !b.is_matched()).collect()
+    }
+}
+
+/// A set of ledgers, one per entity
+///
+/// Each entity's ledger has its own storage; entities never see each other's
+/// accounts or transactions.
+pub struct LedgerSet<S: LedgerStorage> {
+    entities: BTreeMap<String, (Entity, Ledger<S>)>,
+}
+
+impl<S: LedgerStorage + Clone> Default for LedgerSet<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: LedgerStorage + Clone> LedgerSet<S> {
+    /// Create an empty ledger set
+    pub fn new() -> Self {
+        Self {
+            entities: BTreeMap::new(),
+        }
+    }
+
+    /// Register an entity with the storage for its books
+    pub fn add_entity(&mut self, entity: Entity, storage: S) -> LedgerResult<&mut Ledger<S>> {
+        self.add_entity_ledger(entity, Ledger::new(storage))
+    }
+
+    /// Register an entity with an already configured ledger
+    pub fn add_entity_ledger(
+        &mut self,
+        entity: Entity,
+        ledger: Ledger<S>,
+    ) -> LedgerResult<&mut Ledger<S>> {
+        if self.entities.contains_key(&entity.id) {
+            return Err(LedgerError::Validation(format!(
+                "Entity '{}' already exists",
+                entity.id
+            )));
+        }
+        let id = entity.id.clone();
+        let (_, ledger) = self.entities.entry(id).or_insert((entity, ledger));
+        Ok(ledger)
+    }
+
+    /// Remove an entity and return its ledger
+    pub fn remove_entity(&mut self, entity_id: &str) -> LedgerResult<(Entity, Ledger<S>)> {
+        self.entities
+            .remove(entity_id)
+            .ok_or_else(|| LedgerError::EntityNotFound(entity_id.to_string()))
+    }
+
+    /// Get an entity by ID
+    pub fn entity(&self, entity_id: &str) -> LedgerResult<&Entity> {
+        self.entities
+            .get(entity_id)
+            .map(|(entity, _)| entity)
+            .ok_or_else(|| LedgerError::EntityNotFound(entity_id.to_string()))
+    }
+
+    /// All entities, ordered by ID
+    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.values().map(|(entity, _)| entity)
+    }
+
+    /// The ledger of an entity
+    pub fn ledger(&self, entity_id: &str) -> LedgerResult<&Ledger<S>> {
+        self.entities
+            .get(entity_id)
+            .map(|(_, ledger)| ledger)
+            .ok_or_else(|| LedgerError::EntityNotFound(entity_id.to_string()))
+    }
+
+    /// The ledger of an entity, for posting
+    pub fn ledger_mut(&mut self, entity_id: &str) -> LedgerResult<&mut Ledger<S>> {
+        self.entities
+            .get_mut(entity_id)
+            .map(|(_, ledger)| ledger)
+            .ok_or_else(|| LedgerError::EntityNotFound(entity_id.to_string()))
+    }
+}
+
+impl<S: LedgerStorage + Clone> LedgerSet<S> {
+    /// Post a mirrored intercompany pair built from `params`
+    pub async fn record_intercompany(
+        &mut self,
+        params: IntercompanyParams,
+    ) -> LedgerResult<(Transaction, Transaction)> {
+        let source_entity_id = params.source_entity_id.clone();
+        let target_entity_id = params.target_entity_id.clone();
+        let (source, target) = patterns::create_intercompany_pair(params)?;
+        self.record_intercompany_pair(&source_entity_id, source, &target_entity_id, target)
+            .await
+    }
+
+    /// Verify and post an intercompany pair
+    ///
+    /// The source transaction is rolled back if the target cannot be posted,
+    /// so either both sides are recorded or neither is.
+    pub async fn record_intercompany_pair(
+        &mut self,
+        source_entity_id: &str,
+        source: Transaction,
+        target_entity_id: &str,
+        target: Transaction,
+    ) -> LedgerResult<(Transaction, Transaction)> {
+        verify_intercompany_pair(source_entity_id, &source, target_entity_id, &target)?;
+        self.ledger(target_entity_id)?;
+
+        let source = self
+            .ledger_mut(source_entity_id)?
+            .record_transaction(source)
+            .await?;
+        let target = match self
+            .ledger_mut(target_entity_id)?
+            .record_transaction(target)
+            .await
+        {
+            Ok(target) => target,
+            Err(error) => {
+                self.ledger_mut(source_entity_id)?
+                    .delete_transaction(&source.id)
+                    .await?;
+                return Err(error);
+            }
+        };
+
+        Ok((source, target))
+    }
+
+    /// Intercompany positions between every pair of entities as of a date
+    pub async fn intercompany_report(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<IntercompanyReport> {
+        // (entity, counterparty) -> position, and intercompany refs per pair
+        let mut positions: BTreeMap<(String, String), BigDecimal> = BTreeMap::new();
+        let mut refs: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+
+        for (entity_id, (_, ledger)) in &self.entities {
+            for transaction in ledger.get_transactions(None, Some(as_of_date)).await? {
+                for entry in &transaction.entries {
+                    let Some(counterparty) = entry.dimensions.get(COUNTERPARTY_ENTITY_DIMENSION)
+                    else {
+                        continue;
+                    };
+                    let key = (entity_id.clone(), counterparty.clone());
+                    let signed = match entry.entry_type {
+                        EntryType::Debit => entry.amount.clone(),
+                        EntryType::Credit => -entry.amount.clone(),
+                    };
+                    *positions
+                        .entry(key.clone())
+                        .or_insert_with(BigDecimal::zero) += signed;
+                    refs.entry(key).or_default().insert(
+                        transaction
+                            .metadata
+                            .get("intercompany_ref")
+                            .and_then(|value| value.as_str())
+                            .unwrap_or(&transaction.id)
+                            .to_string(),
+                    );
+                }
+            }
+        }
+
+        let mut balances = Vec::new();
+        let mut pairs: BTreeSet<(String, String)> = BTreeSet::new();
+        for (entity_id, counterparty_id) in positions.keys() {
+            let pair = if entity_id <= counterparty_id {
+                (entity_id.clone(), counterparty_id.clone())
+            } else {
+                (counterparty_id.clone(), entity_id.clone())
+            };
+            pairs.insert(pair);
+        }
+        for (entity_id, counterparty_id) in pairs {
+            let position = positions
+                .get(&(entity_id.clone(), counterparty_id.clone()))
+                .cloned()
+                .unwrap_or_else(BigDecimal::zero);
+            let counterparty_position = positions
+                .get(&(counterparty_id.clone(), entity_id.clone()))
+                .cloned()
+                .unwrap_or_else(BigDecimal::zero);
+            balances.push(IntercompanyBalance {
+                difference: &position + &counterparty_position,
+                entity_id,
+                counterparty_id,
+                position,
+                counterparty_position,
+            });
+        }
+
+        let empty = BTreeSet::new();
+        let mut unmatched_transactions = Vec::new();
+        for ((entity_id, counterparty_id), entity_refs) in &refs {
+            let mirrored = refs
+                .get(&(counterparty_id.clone(), entity_id.clone()))
+                .unwrap_or(&empty);
+            for reference in entity_refs.difference(mirrored) {
+                unmatched_transactions.push((entity_id.clone(), reference.clone()));
+            }
+        }
+
+        Ok(IntercompanyReport {
+            as_of_date,
+            balances,
+            unmatched_transactions,
+        })
+    }
+}
+
+/// Check that two transactions mirror each other as an intercompany pair
+///
+/// The source's intercompany entries must name the target entity, the
+/// target's must name the source, and the two sides must net to zero.
+pub fn verify_intercompany_pair(
+    source_entity_id: &str,
+    source: &Transaction,
+    target_entity_id: &str,
+    target: &Transaction,
+) -> LedgerResult<()> {
+    if source_entity_id == target_entity_id {
+        return Err(LedgerError::Validation(
+            "Intercompany transactions need two different entities".to_string(),
+        ));
+    }
+
+    let position = |transaction: &Transaction, counterparty: &str| -> LedgerResult<BigDecimal> {
+        let mut total = BigDecimal::zero();
+        let mut found = false;
+        for entry in &transaction.entries {
+            match entry.dimensions.get(COUNTERPARTY_ENTITY_DIMENSION) {
+                Some(value) if value == counterparty => {
+                    found = true;
+                    match entry.entry_type {
+                        EntryType::Debit => total += &entry.amount,
+                        EntryType::Credit => total -= &entry.amount,
+                    }
+                }
+                Some(value) => {
+                    return Err(LedgerError::Validation(format!(
+                        "Transaction '{}' names unexpected counterparty '{}'",
+                        transaction.id, value
+                    )))
+                }
+                None => {}
+            }
+        }
+        if !found {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' has no intercompany entry for '{}'",
+                transaction.id, counterparty
+            )));
+        }
+        Ok(total)
+    };
+
+    let source_position = position(source, target_entity_id)?;
+    let target_position = position(target, source_entity_id)?;
+    if !(&source_position + &target_position).is_zero() {
+        return Err(LedgerError::Validation(format!(
+            "Intercompany sides do not mirror: {} in {} vs {} in {}",
+            source_position, source_entity_id, target_position, target_entity_id
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_entities_keep_separate_books() {
+        let mut set = LedgerSet::new();
+        for id in ["acme-in", "acme-us"] {
+            set.add_entity(
+                Entity::new(id.to_string(), id.to_string()),
+                MemoryStorage::new(),
+            )
+            .unwrap();
+        }
+        assert!(set
+            .add_entity(
+                Entity::new("acme-in".to_string(), "Duplicate".to_string()),
+                MemoryStorage::new()
+            )
+            .is_err());
+
+        set.ledger_mut("acme-in")
+            .unwrap()
+            .create_account(
+                "1000".to_string(),
+                "Cash".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(set
+            .ledger("acme-in")
+            .unwrap()
+            .get_account("1000")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(set
+            .ledger("acme-us")
+            .unwrap()
+            .get_account("1000")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            set.ledger("acme-uk"),
+            Err(LedgerError::EntityNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_intercompany_pairing_and_report() {
+        let mut set = LedgerSet::new();
+        for id in ["parent", "sub"] {
+            let ledger = set
+                .add_entity(
+                    Entity::new(id.to_string(), id.to_string()),
+                    MemoryStorage::new(),
+                )
+                .unwrap();
+            for (account, account_type) in [
+                ("due_from", AccountType::Asset),
+                ("due_to", AccountType::Liability),
+                ("income", AccountType::Income),
+                ("expense", AccountType::Expense),
+            ] {
+                ledger
+                    .create_account(account.to_string(), account.to_string(), account_type, None)
+                    .await
+                    .unwrap();
+            }
+        }
+        let date = NaiveDate::from_ymd_opt(2024, 9, 30).unwrap();
+        let params = |id: &str| IntercompanyParams {
+            id: id.to_string(),
+            date,
+            description: "Management fee".to_string(),
+            source_entity_id: "parent".to_string(),
+            target_entity_id: "sub".to_string(),
+            amount: BigDecimal::from(25000),
+            source_due_from_account_id: "due_from".to_string(),
+            source_counter_account_id: "income".to_string(),
+            target_due_to_account_id: "due_to".to_string(),
+            target_counter_account_id: "expense".to_string(),
+        };
+
+        set.record_intercompany(params("ic1")).await.unwrap();
+        let report = set.intercompany_report(date).await.unwrap();
+        assert_eq!(report.balances.len(), 1);
+        assert_eq!(report.balances[0].position, BigDecimal::from(25000));
+        assert!(report.unmatched_balances().is_empty());
+        assert!(report.unmatched_transactions.is_empty());
+
+        // A one-sided posting shows up as unmatched
+        let (source, _) = patterns::create_intercompany_pair(params("ic2")).unwrap();
+        set.ledger_mut("parent")
+            .unwrap()
+            .record_transaction(source)
+            .await
+            .unwrap();
+        let report = set.intercompany_report(date).await.unwrap();
+        assert_eq!(report.unmatched_balances().len(), 1);
+        assert_eq!(
+            report.unmatched_transactions,
+            vec![("parent".to_string(), "ic2".to_string())]
+        );
+
+        // Mismatched sides are rejected before anything is posted
+        let (source, mut target) = patterns::create_intercompany_pair(params("ic3")).unwrap();
+        for entry in target.entries.iter_mut() {
+            entry.amount = BigDecimal::from(100);
+        }
+        assert!(set
+            .record_intercompany_pair("parent", source, "sub", target)
+            .await
+            .is_err());
+        assert!(set
+            .ledger("parent")
+            .unwrap()
+            .get_transaction("ic3")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
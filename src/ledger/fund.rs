@@ -0,0 +1,448 @@
+//! Fund accounting for non-profits
+//!
+//! Grants and donations often come with restrictions on what they may be
+//! spent on. Each entry is tagged with the [`Fund`] it belongs to through
+//! [`FUND_DIMENSION`]; entries without the dimension belong to the
+//! unrestricted [`GENERAL_FUND`]. A restricted fund can list the expense
+//! accounts it may be spent on, and postings charging any other expense
+//! account to it are rejected.
+//!
+//! The statement of activities follows the usual non-profit presentation:
+//! revenue is split by restriction, all expenses are shown as unrestricted,
+//! and spending from restricted funds appears as net assets released from
+//! restriction.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Entry dimension identifying the fund an entry belongs to
+pub const FUND_DIMENSION: &str = "fund";
+/// Fund of entries without the fund dimension
+pub const GENERAL_FUND: &str = "general";
+
+/// Whether a fund's use is restricted by its donor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FundRestriction {
+    #[default]
+    Unrestricted,
+    Restricted,
+}
+
+/// A grant, donation or pool of money tracked on its own
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Fund {
+    pub id: String,
+    pub name: String,
+    pub restriction: FundRestriction,
+    /// Expense accounts a restricted fund may be spent on; empty allows any
+    #[serde(default)]
+    pub allowed_expense_accounts: BTreeSet<String>,
+}
+
+impl Fund {
+    /// Create an unrestricted fund
+    pub fn unrestricted(id: String, name: String) -> Self {
+        Self {
+            id,
+            name,
+            restriction: FundRestriction::Unrestricted,
+            allowed_expense_accounts: BTreeSet::new(),
+        }
+    }
+
+    /// Create a restricted fund that may be spent on the given expense accounts
+    pub fn restricted(id: String, name: String, allowed_expense_accounts: &[&str]) -> Self {
+        Self {
+            restriction: FundRestriction::Restricted,
+            allowed_expense_accounts: allowed_expense_accounts
+                .iter()
+                .map(|id| id.to_string())
+                .collect(),
+            ..Self::unrestricted(id, name)
+        }
+    }
+
+    /// Tag an entry as belonging to the fund
+    pub fn tag(&self, entry: Entry) -> Entry {
+        entry.with_dimension(FUND_DIMENSION.to_string(), self.id.clone())
+    }
+
+    /// Whether the fund may be spent on an expense account
+    pub fn allows_expense(&self, account_id: &str) -> bool {
+        self.restriction == FundRestriction::Unrestricted
+            || self.allowed_expense_accounts.is_empty()
+            || self.allowed_expense_accounts.contains(account_id)
+    }
+}
+
+/// Fund an entry belongs to
+pub fn entry_fund(entry: &Entry) -> &str {
+    entry
+        .dimensions
+        .get(FUND_DIMENSION)
+        .map(String::as_str)
+        .unwrap_or(GENERAL_FUND)
+}
+
+/// Trial balance of one fund's entries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FundTrialBalance {
+    pub fund_id: String,
+    pub as_of_date: NaiveDate,
+    /// Accounts with entries in the fund, ordered by account ID
+    pub lines: Vec<AccountBalance>,
+    pub total_debits: BigDecimal,
+    pub total_credits: BigDecimal,
+    /// Whether every transaction kept the fund in balance
+    pub is_balanced: bool,
+}
+
+/// One revenue or expense account in the statement of activities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ActivityLine {
+    pub account_id: String,
+    pub account_name: String,
+    pub unrestricted: BigDecimal,
+    pub restricted: BigDecimal,
+    pub total: BigDecimal,
+}
+
+/// Changes in net assets by restriction for a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StatementOfActivities {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub revenue: Vec<ActivityLine>,
+    /// Expenses, all presented as unrestricted
+    pub expenses: Vec<ActivityLine>,
+    /// Restricted funds spent, moved from restricted to unrestricted
+    pub released_from_restriction: BigDecimal,
+    pub change_in_unrestricted: BigDecimal,
+    pub change_in_restricted: BigDecimal,
+    pub change_in_net_assets: BigDecimal,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Save a fund, replacing any with the same ID
+    pub async fn save_fund(&mut self, fund: Fund) -> LedgerResult<()> {
+        if fund.id == GENERAL_FUND {
+            return Err(LedgerError::Validation(format!(
+                "Fund ID {} is reserved for untagged entries",
+                GENERAL_FUND
+            )));
+        }
+        for account_id in &fund.allowed_expense_accounts {
+            self.account_manager
+                .get_account_required(account_id)
+                .await?;
+        }
+        self.funds.insert(fund.id.clone(), fund);
+        Ok(())
+    }
+
+    /// Get a fund by ID
+    pub fn get_fund(&self, fund_id: &str) -> Option<&Fund> {
+        self.funds.get(fund_id)
+    }
+
+    /// All funds, ordered by ID
+    pub fn list_funds(&self) -> Vec<&Fund> {
+        self.funds.values().collect()
+    }
+
+    /// Reject entries, in any book, tagged with an unknown fund or charging a
+    /// restricted fund to an expense account it may not be spent on
+    pub(crate) async fn check_fund_restrictions(
+        &self,
+        transaction: &Transaction,
+    ) -> LedgerResult<()> {
+        for entry in transaction.all_entries() {
+            let Some(fund_id) = entry.dimensions.get(FUND_DIMENSION) else {
+                continue;
+            };
+            let fund = self.funds.get(fund_id).ok_or_else(|| {
+                LedgerError::Validation(format!(
+                    "Transaction {} is tagged with unknown fund {}",
+                    transaction.id, fund_id
+                ))
+            })?;
+            if fund.allows_expense(&entry.account_id) {
+                continue;
+            }
+            let account = self
+                .account_manager
+                .get_account_required(&entry.account_id)
+                .await?;
+            if account.account_type == AccountType::Expense {
+                return Err(LedgerError::Validation(format!(
+                    "Restricted fund {} may not be spent on account {}",
+                    fund.id, entry.account_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_restricted_fund(&self, fund_id: &str) -> bool {
+        self.funds
+            .get(fund_id)
+            .is_some_and(|fund| fund.restriction == FundRestriction::Restricted)
+    }
+
+    /// Trial balance of the entries belonging to one fund
+    pub async fn generate_fund_trial_balance(
+        &self,
+        fund_id: &str,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<FundTrialBalance> {
+        let filter = EntryFilter {
+            end_date: Some(as_of_date),
+            ..Default::default()
+        };
+        let mut balances: BTreeMap<String, BigDecimal> = BTreeMap::new();
+        let mut unbalanced = BTreeMap::<String, BigDecimal>::new();
+        let accounts: HashMap<String, Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|account| (account.id.clone(), account))
+            .collect();
+        for row in self.get_entries(&filter).await? {
+            if entry_fund(&row.entry) != fund_id {
+                continue;
+            }
+            let Some(account) = accounts.get(&row.entry.account_id) else {
+                continue;
+            };
+            *balances.entry(account.id.clone()).or_default() += account
+                .account_type
+                .balance_effect(&row.entry.entry_type, &row.entry.amount);
+            let signed = match row.entry.entry_type {
+                EntryType::Debit => row.entry.amount.clone(),
+                EntryType::Credit => -row.entry.amount.clone(),
+            };
+            *unbalanced.entry(row.transaction_id).or_default() += signed;
+        }
+
+        let mut trial_balance = FundTrialBalance {
+            fund_id: fund_id.to_string(),
+            as_of_date,
+            lines: Vec::new(),
+            total_debits: BigDecimal::zero(),
+            total_credits: BigDecimal::zero(),
+            is_balanced: unbalanced.values().all(|net| net.is_zero()),
+        };
+        for (account_id, balance) in balances {
+            let line = AccountBalance::from_balance(accounts[&account_id].clone(), balance);
+            trial_balance.total_debits += line.debit_balance.clone().unwrap_or_default();
+            trial_balance.total_credits += line.credit_balance.clone().unwrap_or_default();
+            trial_balance.lines.push(line);
+        }
+        Ok(trial_balance)
+    }
+
+    /// Statement of activities for a period
+    pub async fn generate_statement_of_activities(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<StatementOfActivities> {
+        let accounts: HashMap<String, Account> = self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|account| (account.id.clone(), account))
+            .collect();
+        let filter = EntryFilter {
+            start_date: Some(start_date),
+            end_date: Some(end_date),
+            ..Default::default()
+        };
+        // (unrestricted, restricted) per account
+        let mut revenue: BTreeMap<String, (BigDecimal, BigDecimal)> = BTreeMap::new();
+        let mut expenses: BTreeMap<String, BigDecimal> = BTreeMap::new();
+        let mut released = BigDecimal::zero();
+        for row in self.get_entries(&filter).await? {
+            let Some(account) = accounts.get(&row.entry.account_id) else {
+                continue;
+            };
+            let amount = account
+                .account_type
+                .balance_effect(&row.entry.entry_type, &row.entry.amount);
+            let restricted = self.is_restricted_fund(entry_fund(&row.entry));
+            match account.account_type {
+                AccountType::Income => {
+                    let line = revenue.entry(account.id.clone()).or_default();
+                    if restricted {
+                        line.1 += amount;
+                    } else {
+                        line.0 += amount;
+                    }
+                }
+                AccountType::Expense => {
+                    if restricted {
+                        released += &amount;
+                    }
+                    *expenses.entry(account.id.clone()).or_default() += amount;
+                }
+                _ => {}
+            }
+        }
+
+        let revenue: Vec<ActivityLine> = revenue
+            .into_iter()
+            .map(|(account_id, (unrestricted, restricted))| ActivityLine {
+                account_name: accounts[&account_id].name.clone(),
+                account_id,
+                total: &unrestricted + &restricted,
+                unrestricted,
+                restricted,
+            })
+            .collect();
+        let expenses: Vec<ActivityLine> = expenses
+            .into_iter()
+            .map(|(account_id, amount)| ActivityLine {
+                account_name: accounts[&account_id].name.clone(),
+                account_id,
+                unrestricted: amount.clone(),
+                restricted: BigDecimal::zero(),
+                total: amount,
+            })
+            .collect();
+        let revenue_unrestricted: BigDecimal = revenue.iter().map(|l| &l.unrestricted).sum();
+        let revenue_restricted: BigDecimal = revenue.iter().map(|l| &l.restricted).sum();
+        let total_expenses: BigDecimal = expenses.iter().map(|l| &l.total).sum();
+        let change_in_unrestricted = revenue_unrestricted + &released - total_expenses;
+        let change_in_restricted = revenue_restricted - &released;
+        Ok(StatementOfActivities {
+            start_date,
+            end_date,
+            revenue,
+            expenses,
+            released_from_restriction: released,
+            change_in_net_assets: &change_in_unrestricted + &change_in_restricted,
+            change_in_unrestricted,
+            change_in_restricted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_restricted_fund_spending_and_activities() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "6300".to_string(),
+                "Program Supplies".to_string(),
+                AccountType::Expense,
+                None,
+            )
+            .await
+            .unwrap();
+        let grant = Fund::restricted(
+            "literacy".to_string(),
+            "Literacy grant".to_string(),
+            &["6300"],
+        );
+        ledger.save_fund(grant.clone()).await.unwrap();
+        assert!(ledger
+            .save_fund(Fund::unrestricted(
+                "general".to_string(),
+                "General".to_string()
+            ))
+            .await
+            .is_err());
+        let date = |d| NaiveDate::from_ymd_opt(2024, 7, d).unwrap();
+
+        let donation = TransactionBuilder::new("T1".to_string(), date(1), "Grant".to_string())
+            .entry(grant.tag(Entry::debit(
+                "1000".to_string(),
+                BigDecimal::from(10000),
+                None,
+            )))
+            .entry(grant.tag(Entry::credit(
+                "4000".to_string(),
+                BigDecimal::from(10000),
+                None,
+            )))
+            .build()
+            .unwrap();
+        ledger.record_transaction(donation).await.unwrap();
+        let general = TransactionBuilder::new("T2".to_string(), date(2), "Gala".to_string())
+            .debit("1000".to_string(), BigDecimal::from(3000), None)
+            .credit("4000".to_string(), BigDecimal::from(3000), None)
+            .build()
+            .unwrap();
+        ledger.record_transaction(general).await.unwrap();
+
+        // Rent is not an allowed use of the grant
+        let spend = |id: &str, account: &str| {
+            TransactionBuilder::new(id.to_string(), date(10), "Spend".to_string())
+                .entry(grant.tag(Entry::debit(
+                    account.to_string(),
+                    BigDecimal::from(4000),
+                    None,
+                )))
+                .entry(grant.tag(Entry::credit(
+                    "1000".to_string(),
+                    BigDecimal::from(4000),
+                    None,
+                )))
+                .build()
+                .unwrap()
+        };
+        assert!(ledger
+            .record_transaction(spend("T3", "6000"))
+            .await
+            .is_err());
+        // Nor can the restriction be dodged through a draft or another book
+        ledger.save_draft(spend("D1", "6000")).await.unwrap();
+        assert!(ledger.post_draft("D1").await.is_err());
+        let mut in_tax_book = spend("T5", "6300");
+        in_tax_book.set_book_entries("tax", spend("T5", "6000").entries);
+        assert!(ledger.record_transaction(in_tax_book).await.is_err());
+        ledger
+            .record_transaction(spend("T4", "6300"))
+            .await
+            .unwrap();
+        assert!(ledger
+            .update_transaction(&spend("T4", "6000"))
+            .await
+            .is_err());
+
+        let trial_balance = ledger
+            .generate_fund_trial_balance("literacy", date(31))
+            .await
+            .unwrap();
+        assert!(trial_balance.is_balanced);
+        assert_eq!(trial_balance.total_debits, BigDecimal::from(10000));
+        assert_eq!(trial_balance.lines.len(), 3);
+
+        let activities = ledger
+            .generate_statement_of_activities(date(1), date(31))
+            .await
+            .unwrap();
+        assert_eq!(activities.released_from_restriction, BigDecimal::from(4000));
+        assert_eq!(activities.change_in_unrestricted, BigDecimal::from(3000));
+        assert_eq!(activities.change_in_restricted, BigDecimal::from(6000));
+        assert_eq!(activities.revenue[0].restricted, BigDecimal::from(10000));
+    }
+}
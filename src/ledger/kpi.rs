@@ -0,0 +1,346 @@
+//! KPI time series for charting
+//!
+//! Each [`Kpi`] is derived from the books with one fixed formula, so every
+//! app charting it shows the same numbers:
+//!
+//! | KPI | Formula |
+//! |---|---|
+//! | revenue | income posted in the period |
+//! | gross margin | (revenue − cost of sales) ÷ revenue, as a percentage |
+//! | burn rate | net decrease of the cash accounts over the period |
+//! | DSO | receivables at period end ÷ revenue × days in the period |
+//! | DPO | payables at period end ÷ cost of sales × days in the period |
+//!
+//! Cash accounts are those marked with
+//! [`designate_cash_account`](Ledger::designate_cash_account), cost of sales
+//! accounts those marked with
+//! [`designate_cost_of_sales_account`](Ledger::designate_cost_of_sales_account),
+//! and receivables and payables the asset and liability control accounts
+//! keyed by [`PARTY_DIMENSION`]. Ratios with a zero denominator have no
+//! value.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use crate::ledger::closing::is_closing_entry;
+use crate::ledger::{Granularity, Ledger, PARTY_DIMENSION};
+use crate::traits::*;
+use crate::types::*;
+
+/// Account metadata key marking a cost of sales account
+const COST_OF_SALES_KEY: &str = "cost_of_sales";
+
+/// A key performance indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Kpi {
+    Revenue,
+    GrossMargin,
+    BurnRate,
+    /// Days sales outstanding
+    Dso,
+    /// Days payables outstanding
+    Dpo,
+}
+
+/// Value of a KPI for one period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KpiPoint {
+    pub start_date: NaiveDate,
+    /// Last day of the period, clipped to the end of the range
+    pub end_date: NaiveDate,
+    /// `None` when the formula's denominator is zero
+    pub value: Option<BigDecimal>,
+}
+
+/// One KPI across the periods of a range
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KpiSeries {
+    pub kpi: Kpi,
+    pub points: Vec<KpiPoint>,
+}
+
+/// Ledger figures the KPIs of one period are computed from
+struct PeriodFigures {
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    revenue: BigDecimal,
+    cost_of_sales: BigDecimal,
+    cash_change: BigDecimal,
+    receivables: BigDecimal,
+    payables: BigDecimal,
+}
+
+impl PeriodFigures {
+    fn days(&self) -> BigDecimal {
+        BigDecimal::from((self.end_date - self.start_date).num_days() + 1)
+    }
+
+    fn value(&self, kpi: Kpi) -> Option<BigDecimal> {
+        let ratio = |numerator: &BigDecimal, denominator: &BigDecimal, scale: BigDecimal| {
+            (!denominator.is_zero()).then(|| (numerator * scale / denominator).round(2))
+        };
+        match kpi {
+            Kpi::Revenue => Some(self.revenue.clone()),
+            Kpi::GrossMargin => ratio(
+                &(&self.revenue - &self.cost_of_sales),
+                &self.revenue,
+                BigDecimal::from(100),
+            ),
+            Kpi::BurnRate => Some(-self.cash_change.clone()),
+            Kpi::Dso => ratio(&self.receivables, &self.revenue, self.days()),
+            Kpi::Dpo => ratio(&self.payables, &self.cost_of_sales, self.days()),
+        }
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Mark an expense account as cost of sales for gross margin and DPO
+    pub async fn designate_cost_of_sales_account(
+        &mut self,
+        account_id: &str,
+    ) -> LedgerResult<Account> {
+        let mut account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        if account.account_type != AccountType::Expense {
+            return Err(LedgerError::Validation(format!(
+                "Cost of sales account {} must be an expense account",
+                account_id
+            )));
+        }
+        account
+            .metadata
+            .insert(COST_OF_SALES_KEY.to_string(), true.into());
+        account.updated_at = self.clock.now();
+        self.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Series of each KPI per day, week or month of a date range
+    pub async fn compute_kpi_series(
+        &self,
+        kpis: &[Kpi],
+        period: RangeInclusive<NaiveDate>,
+        granularity: Granularity,
+    ) -> LedgerResult<Vec<KpiSeries>> {
+        let (start_date, end_date) = (*period.start(), *period.end());
+        if start_date > end_date {
+            return Err(LedgerError::Validation(
+                "Start date must not be after end date".to_string(),
+            ));
+        }
+
+        let accounts = self.list_accounts().await?;
+        let types: HashMap<&str, AccountType> = accounts
+            .iter()
+            .map(|a| (a.id.as_str(), a.account_type.clone()))
+            .collect();
+        let flagged = |key: &str| -> HashSet<&str> {
+            accounts
+                .iter()
+                .filter(|a| a.metadata.get(key).and_then(MetaValue::as_bool) == Some(true))
+                .map(|a| a.id.as_str())
+                .collect()
+        };
+        let cost_of_sales = flagged(COST_OF_SALES_KEY);
+        let cash: HashSet<String> = self
+            .list_cash_accounts()
+            .await?
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        let mut receivables = Vec::new();
+        let mut payables = Vec::new();
+        for account in self.list_control_accounts().await? {
+            if self.control_dimension(&account.id).await?.as_deref() != Some(PARTY_DIMENSION) {
+                continue;
+            }
+            match account.account_type {
+                AccountType::Asset => receivables.push(account.id),
+                AccountType::Liability => payables.push(account.id),
+                _ => {}
+            }
+        }
+
+        let rows = self
+            .get_entries(&EntryFilter {
+                start_date: Some(start_date),
+                end_date: Some(end_date),
+                ..EntryFilter::default()
+            })
+            .await?;
+        // Closing entries move the year's profit to equity; counting them
+        // would zero out the income and expense totals
+        let closing: HashSet<String> = self
+            .get_transactions(Some(start_date), Some(end_date))
+            .await?
+            .into_iter()
+            .filter(is_closing_entry)
+            .map(|t| t.id)
+            .collect();
+
+        let mut figures = Vec::new();
+        let mut period_start = start_date;
+        while period_start <= end_date {
+            let period_end = granularity.period_end(period_start).min(end_date);
+            let mut period = PeriodFigures {
+                start_date: period_start,
+                end_date: period_end,
+                revenue: BigDecimal::zero(),
+                cost_of_sales: BigDecimal::zero(),
+                cash_change: BigDecimal::zero(),
+                receivables: BigDecimal::zero(),
+                payables: BigDecimal::zero(),
+            };
+            for row in rows
+                .iter()
+                .filter(|row| row.date >= period_start && row.date <= period_end)
+                .filter(|row| !closing.contains(&row.transaction_id))
+            {
+                let entry = &row.entry;
+                let Some(account_type) = types.get(entry.account_id.as_str()) else {
+                    continue;
+                };
+                let amount = account_type.balance_effect(&entry.entry_type, &entry.amount);
+                if *account_type == AccountType::Income {
+                    period.revenue += &amount;
+                }
+                if cost_of_sales.contains(entry.account_id.as_str()) {
+                    period.cost_of_sales += &amount;
+                }
+                if cash.contains(&entry.account_id) {
+                    period.cash_change += &amount;
+                }
+            }
+            for account_id in &receivables {
+                period.receivables += self
+                    .get_account_balance(account_id, Some(period_end))
+                    .await?;
+            }
+            for account_id in &payables {
+                period.payables += self
+                    .get_account_balance(account_id, Some(period_end))
+                    .await?;
+            }
+            figures.push(period);
+            match period_end.succ_opt() {
+                Some(next) => period_start = next,
+                None => break,
+            }
+        }
+
+        Ok(kpis
+            .iter()
+            .map(|&kpi| KpiSeries {
+                kpi,
+                points: figures
+                    .iter()
+                    .map(|period| KpiPoint {
+                        start_date: period.start_date,
+                        end_date: period.end_date,
+                        value: period.value(kpi),
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_monthly_kpi_series() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger.designate_cash_account("1000").await.unwrap();
+        ledger
+            .designate_cost_of_sales_account("5000")
+            .await
+            .unwrap();
+        ledger
+            .designate_control_account("1200", PARTY_DIMENSION)
+            .await
+            .unwrap();
+        ledger
+            .designate_control_account("2000", PARTY_DIMENSION)
+            .await
+            .unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        let party =
+            |entry: Entry| entry.with_dimension(PARTY_DIMENSION.to_string(), "p".to_string());
+        let transactions = [
+            // January: 31,000 of sales, 15,500 still owed by customers
+            TransactionBuilder::new("S1".to_string(), date(1, 10), "Sales".to_string())
+                .entry(party(Entry::debit(
+                    "1200".to_string(),
+                    BigDecimal::from(15500),
+                    None,
+                )))
+                .debit("1000".to_string(), BigDecimal::from(15500), None)
+                .credit("4000".to_string(), BigDecimal::from(31000), None),
+            // Stock bought on credit and sold
+            TransactionBuilder::new("P1".to_string(), date(1, 12), "Stock".to_string())
+                .debit("5000".to_string(), BigDecimal::from(12400), None)
+                .entry(party(Entry::credit(
+                    "2000".to_string(),
+                    BigDecimal::from(12400),
+                    None,
+                ))),
+            // February: rent only
+            TransactionBuilder::new("R1".to_string(), date(2, 1), "Rent".to_string())
+                .debit("6000".to_string(), BigDecimal::from(9000), None)
+                .credit("1000".to_string(), BigDecimal::from(9000), None),
+        ];
+        for builder in transactions {
+            ledger
+                .record_transaction(builder.build().unwrap())
+                .await
+                .unwrap();
+        }
+        ledger
+            .post_closing_entries("CL".to_string(), date(2, 29), "3200")
+            .await
+            .unwrap();
+
+        let series = ledger
+            .compute_kpi_series(
+                &[
+                    Kpi::Revenue,
+                    Kpi::GrossMargin,
+                    Kpi::BurnRate,
+                    Kpi::Dso,
+                    Kpi::Dpo,
+                ],
+                date(1, 1)..=date(2, 29),
+                Granularity::Monthly,
+            )
+            .await
+            .unwrap();
+        let values = |kpi: usize| -> Vec<Option<BigDecimal>> {
+            series[kpi].points.iter().map(|p| p.value.clone()).collect()
+        };
+        assert_eq!(
+            values(0),
+            vec![Some(BigDecimal::from(31000)), Some(BigDecimal::zero())]
+        );
+        assert_eq!(values(1), vec![Some(BigDecimal::from(60)), None]);
+        assert_eq!(
+            values(2),
+            vec![Some(BigDecimal::from(-15500)), Some(BigDecimal::from(9000))]
+        );
+        assert_eq!(values(3), vec![Some(BigDecimal::from(155) / 10), None]);
+        assert_eq!(values(4), vec![Some(BigDecimal::from(31)), None]);
+    }
+}
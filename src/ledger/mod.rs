@@ -1,9 +1,70 @@
 //! Ledger module containing account management and transaction processing
 
 pub mod account;
+pub mod anonymize;
+pub mod archive;
+pub mod audit;
+pub mod backup;
+pub mod balance_history;
+pub mod balance_snapshot;
+pub mod books;
+pub mod budget_limit;
+pub mod builder;
+pub mod cash_basis;
+pub mod cash_position;
+pub mod cheque;
+pub mod closing;
+pub mod config;
+pub mod control;
 pub mod core;
+pub mod default_accounts;
+pub mod drill_down;
+pub mod entity;
+pub mod fund;
+pub mod kpi;
+pub mod payroll;
+pub mod period_close;
+pub mod posting_rules;
+pub mod project;
+pub mod report_cache;
+pub mod retained_earnings;
+pub mod revenue_recognition;
+pub mod segment;
+pub mod signing;
+pub mod suspense;
+pub mod tags;
+pub mod templates;
 pub mod transaction;
 
 pub use account::*;
+pub use archive::*;
+pub use audit::*;
+pub use backup::*;
+pub use balance_history::*;
+pub use balance_snapshot::*;
+pub use budget_limit::*;
+pub use builder::*;
+pub use cash_basis::*;
+pub use cash_position::*;
+pub use cheque::*;
+pub use closing::*;
+pub use config::*;
+pub use control::*;
 pub use core::*;
+pub use default_accounts::*;
+pub use drill_down::*;
+pub use entity::*;
+pub use fund::*;
+pub use kpi::*;
+pub use payroll::*;
+pub use period_close::*;
+pub use posting_rules::*;
+pub use project::*;
+pub use report_cache::*;
+pub use retained_earnings::*;
+pub use revenue_recognition::*;
+pub use segment::*;
+pub use signing::*;
+pub use tags::*;
+pub use templates::*;
 pub use transaction::*;
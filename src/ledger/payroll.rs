@@ -0,0 +1,218 @@
+//! Payroll journal patterns with Indian statutory components
+//!
+//! A monthly payroll run is booked as a single compound journal: gross
+//! salary and the employer's statutory contributions are expensed, while
+//! employee deductions (PF, ESI, professional tax, TDS) and employer
+//! contributions are credited to their payable accounts. The remainder is
+//! net pay owed to employees.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::TransactionBuilder;
+use crate::types::*;
+
+/// Totals for one payroll run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PayrollRunSummary {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Gross salary before any deductions
+    pub gross_salary: BigDecimal,
+    /// Employee's provident fund contribution (deducted from salary)
+    pub employee_pf: BigDecimal,
+    /// Employer's provident fund contribution (additional cost)
+    pub employer_pf: BigDecimal,
+    /// Employee's ESI contribution (deducted from salary)
+    pub employee_esi: BigDecimal,
+    /// Employer's ESI contribution (additional cost)
+    pub employer_esi: BigDecimal,
+    /// Professional tax deducted
+    pub professional_tax: BigDecimal,
+    /// Income tax deducted at source on salary (section 192)
+    pub tds: BigDecimal,
+}
+
+impl PayrollRunSummary {
+    /// Total deducted from employees' gross salary
+    pub fn total_deductions(&self) -> BigDecimal {
+        &self.employee_pf + &self.employee_esi + &self.professional_tax + &self.tds
+    }
+
+    /// Net pay owed to employees
+    pub fn net_pay(&self) -> BigDecimal {
+        &self.gross_salary - self.total_deductions()
+    }
+
+    /// Total cost to the employer (gross plus employer contributions)
+    pub fn employer_cost(&self) -> BigDecimal {
+        &self.gross_salary + &self.employer_pf + &self.employer_esi
+    }
+}
+
+/// Accounts used when posting payroll
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PayrollAccounts {
+    pub salary_expense_account_id: String,
+    pub employer_pf_expense_account_id: String,
+    pub employer_esi_expense_account_id: String,
+    pub pf_payable_account_id: String,
+    pub esi_payable_account_id: String,
+    pub professional_tax_payable_account_id: String,
+    pub tds_payable_account_id: String,
+    /// Net salary payable to employees
+    pub salary_payable_account_id: String,
+}
+
+/// Create the compound payroll journal for a payroll run
+///
+/// Zero components are omitted, so runs without ESI or professional tax
+/// produce no empty lines.
+pub fn create_payroll_journal(
+    summary: &PayrollRunSummary,
+    accounts: &PayrollAccounts,
+) -> LedgerResult<Transaction> {
+    let net_pay = summary.net_pay();
+    if !net_pay.is_positive() {
+        return Err(LedgerError::Validation(format!(
+            "Payroll deductions {} leave no net pay from gross salary {}",
+            summary.total_deductions(),
+            summary.gross_salary
+        )));
+    }
+
+    let debits = [
+        (
+            &accounts.salary_expense_account_id,
+            summary.gross_salary.clone(),
+            "Gross salary",
+        ),
+        (
+            &accounts.employer_pf_expense_account_id,
+            summary.employer_pf.clone(),
+            "Employer PF contribution",
+        ),
+        (
+            &accounts.employer_esi_expense_account_id,
+            summary.employer_esi.clone(),
+            "Employer ESI contribution",
+        ),
+    ];
+    let credits = [
+        (
+            &accounts.pf_payable_account_id,
+            &summary.employee_pf + &summary.employer_pf,
+            "PF payable",
+        ),
+        (
+            &accounts.esi_payable_account_id,
+            &summary.employee_esi + &summary.employer_esi,
+            "ESI payable",
+        ),
+        (
+            &accounts.professional_tax_payable_account_id,
+            summary.professional_tax.clone(),
+            "Professional tax payable",
+        ),
+        (
+            &accounts.tds_payable_account_id,
+            summary.tds.clone(),
+            "TDS on salary payable",
+        ),
+        (
+            &accounts.salary_payable_account_id,
+            net_pay,
+            "Net salary payable",
+        ),
+    ];
+
+    let mut builder = TransactionBuilder::new(
+        summary.id.clone(),
+        summary.date,
+        summary.description.clone(),
+    )
+    .kind(TransactionKind::Journal)
+    .metadata("source".to_string(), "payroll".to_string());
+
+    for (account_id, amount, description) in debits {
+        if !amount.is_zero() {
+            builder = builder.debit(account_id.clone(), amount, Some(description.to_string()));
+        }
+    }
+    for (account_id, amount, description) in credits {
+        if !amount.is_zero() {
+            builder = builder.credit(account_id.clone(), amount, Some(description.to_string()));
+        }
+    }
+
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts() -> PayrollAccounts {
+        PayrollAccounts {
+            salary_expense_account_id: "salary".to_string(),
+            employer_pf_expense_account_id: "pf_expense".to_string(),
+            employer_esi_expense_account_id: "esi_expense".to_string(),
+            pf_payable_account_id: "pf_payable".to_string(),
+            esi_payable_account_id: "esi_payable".to_string(),
+            professional_tax_payable_account_id: "pt_payable".to_string(),
+            tds_payable_account_id: "tds_payable".to_string(),
+            salary_payable_account_id: "salary_payable".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_payroll_journal() {
+        let summary = PayrollRunSummary {
+            id: "payroll-2024-04".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(),
+            description: "Payroll for April 2024".to_string(),
+            gross_salary: BigDecimal::from(100000),
+            employee_pf: BigDecimal::from(7200),
+            employer_pf: BigDecimal::from(7200),
+            employee_esi: BigDecimal::from(0),
+            employer_esi: BigDecimal::from(0),
+            professional_tax: BigDecimal::from(200),
+            tds: BigDecimal::from(5000),
+        };
+
+        let journal = create_payroll_journal(&summary, &accounts()).unwrap();
+
+        assert_eq!(summary.net_pay(), BigDecimal::from(87600));
+        assert_eq!(journal.total_debits(), summary.employer_cost());
+        // No ESI lines when ESI does not apply
+        assert!(!journal.entries.iter().any(|e| e.account_id.contains("esi")));
+        let pf = journal
+            .entries
+            .iter()
+            .find(|e| e.account_id == "pf_payable")
+            .unwrap();
+        assert_eq!(pf.amount, BigDecimal::from(14400));
+    }
+
+    #[test]
+    fn test_payroll_rejects_deductions_exceeding_gross() {
+        let summary = PayrollRunSummary {
+            id: "bad".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 4, 30).unwrap(),
+            description: "Bad payroll".to_string(),
+            gross_salary: BigDecimal::from(1000),
+            employee_pf: BigDecimal::from(0),
+            employer_pf: BigDecimal::from(0),
+            employee_esi: BigDecimal::from(0),
+            employer_esi: BigDecimal::from(0),
+            professional_tax: BigDecimal::from(200),
+            tds: BigDecimal::from(900),
+        };
+
+        assert!(create_payroll_journal(&summary, &accounts()).is_err());
+    }
+}
@@ -0,0 +1,574 @@
+//! Period-close checklist of the usual adjusting entries
+//!
+//! Before a month or year is closed the books normally need depreciation
+//! charged, last period's accruals reversed, prepaid expenses amortized,
+//! imported bank lines classified and the suspense account cleared.
+//! [`Ledger::period_close_checklist`] looks for each of these and reports
+//! what is still open, along with the adjusting entries already posted in
+//! the period.
+//!
+//! Adjusting entries posted through [`Ledger::record_adjusting_entry`] are
+//! tagged with the check they address under [`CLOSE_CHECK_KEY`]. Accruals
+//! that should be reversed next period carry [`ACCRUAL_KEY`] set to `true`;
+//! a reversal is any transaction that [corrects](Transaction::corrects) one.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key naming the close check an adjusting entry
+/// addresses
+pub const CLOSE_CHECK_KEY: &str = "close_check";
+/// Transaction metadata key marking an accrual to be reversed next period
+pub const ACCRUAL_KEY: &str = "accrual";
+
+/// A typical period-end adjustment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CloseCheck {
+    /// Depreciation charged on every fixed asset account with a balance
+    Depreciation,
+    /// Accruals from earlier periods reversed
+    AccrualReversal,
+    /// Prepaid expenses amortized
+    PrepaidAmortization,
+    /// Imported bank lines moved out of suspense to their proper accounts
+    BankReconciliation,
+    /// Suspense account cleared to zero
+    SuspenseBalance,
+}
+
+impl CloseCheck {
+    /// Every check, in the order the checklist lists them
+    pub const ALL: [CloseCheck; 5] = [
+        CloseCheck::Depreciation,
+        CloseCheck::AccrualReversal,
+        CloseCheck::PrepaidAmortization,
+        CloseCheck::BankReconciliation,
+        CloseCheck::SuspenseBalance,
+    ];
+
+    /// Name stored under [`CLOSE_CHECK_KEY`]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CloseCheck::Depreciation => "depreciation",
+            CloseCheck::AccrualReversal => "accrual_reversal",
+            CloseCheck::PrepaidAmortization => "prepaid_amortization",
+            CloseCheck::BankReconciliation => "bank_reconciliation",
+            CloseCheck::SuspenseBalance => "suspense_balance",
+        }
+    }
+
+    /// Parse a stored check name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|check| check.as_str() == name)
+    }
+}
+
+/// Where a checklist item stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum CloseItemStatus {
+    /// Adjustments are still needed
+    Open,
+    /// Nothing left to adjust
+    Done,
+    /// The ledger has nothing this check applies to
+    NotApplicable,
+}
+
+/// One line of the period-close checklist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CloseChecklistItem {
+    pub check: CloseCheck,
+    pub status: CloseItemStatus,
+    /// What is outstanding, for display
+    pub detail: String,
+    /// Accounts still needing an adjustment
+    pub account_ids: Vec<String>,
+    /// Transactions still needing attention
+    pub transaction_ids: Vec<String>,
+    /// Balance or total still outstanding
+    pub amount: BigDecimal,
+}
+
+/// An adjusting entry posted in the period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PostedAdjustment {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Check the entry was recorded against, if any
+    pub check: Option<CloseCheck>,
+    pub amount: BigDecimal,
+}
+
+/// Accounts the checklist inspects
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CloseChecklistConfig {
+    /// Fixed asset accounts that are depreciated every period
+    pub depreciable_accounts: Vec<String>,
+    /// Prepaid expense accounts that are amortized every period
+    pub prepaid_accounts: Vec<String>,
+}
+
+/// Period-close checklist with the adjustments posted so far
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeriodCloseChecklist {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub items: Vec<CloseChecklistItem>,
+    /// Adjustment vouchers dated in the period, oldest first
+    pub adjustments: Vec<PostedAdjustment>,
+}
+
+impl PeriodCloseChecklist {
+    /// Items still needing adjustments
+    pub fn open_items(&self) -> Vec<&CloseChecklistItem> {
+        self.items
+            .iter()
+            .filter(|item| item.status == CloseItemStatus::Open)
+            .collect()
+    }
+
+    /// Whether nothing is left open
+    pub fn is_ready_to_close(&self) -> bool {
+        self.open_items().is_empty()
+    }
+
+    /// The item for a check
+    pub fn item(&self, check: CloseCheck) -> Option<&CloseChecklistItem> {
+        self.items.iter().find(|item| item.check == check)
+    }
+}
+
+impl CloseChecklistItem {
+    fn new(check: CloseCheck, status: CloseItemStatus, detail: String) -> Self {
+        Self {
+            check,
+            status,
+            detail,
+            account_ids: Vec::new(),
+            transaction_ids: Vec::new(),
+            amount: BigDecimal::zero(),
+        }
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Post an adjusting entry against a close check
+    ///
+    /// The transaction is posted as an [`TransactionKind::Adjustment`] and
+    /// tagged with the check under [`CLOSE_CHECK_KEY`].
+    pub async fn record_adjusting_entry(
+        &mut self,
+        check: CloseCheck,
+        mut transaction: Transaction,
+    ) -> LedgerResult<Transaction> {
+        transaction.kind = TransactionKind::Adjustment;
+        transaction
+            .metadata
+            .insert(CLOSE_CHECK_KEY.to_string(), check.as_str().into());
+        self.record_transaction(transaction).await
+    }
+
+    /// Build the close checklist for a period
+    pub async fn period_close_checklist(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        config: &CloseChecklistConfig,
+    ) -> LedgerResult<PeriodCloseChecklist> {
+        let in_period = self
+            .get_transactions(Some(start_date), Some(end_date))
+            .await?;
+
+        let items = vec![
+            self.amortization_item(
+                CloseCheck::Depreciation,
+                &config.depreciable_accounts,
+                &in_period,
+                end_date,
+            )
+            .await?,
+            self.accrual_reversal_item(start_date, end_date).await?,
+            self.amortization_item(
+                CloseCheck::PrepaidAmortization,
+                &config.prepaid_accounts,
+                &in_period,
+                end_date,
+            )
+            .await?,
+            self.bank_reconciliation_item(end_date).await?,
+            self.suspense_balance_item(end_date).await?,
+        ];
+
+        let mut adjustments: Vec<PostedAdjustment> = in_period
+            .iter()
+            .filter(|t| t.kind == TransactionKind::Adjustment)
+            .map(|t| PostedAdjustment {
+                transaction_id: t.id.clone(),
+                date: t.date,
+                description: t.description.clone(),
+                check: t
+                    .metadata
+                    .get(CLOSE_CHECK_KEY)
+                    .and_then(MetaValue::as_str)
+                    .and_then(CloseCheck::parse),
+                amount: t.total_debits(),
+            })
+            .collect();
+        adjustments.sort_by(|a, b| {
+            a.date
+                .cmp(&b.date)
+                .then_with(|| a.transaction_id.cmp(&b.transaction_id))
+        });
+
+        Ok(PeriodCloseChecklist {
+            start_date,
+            end_date,
+            items,
+            adjustments,
+        })
+    }
+
+    /// Depreciation or prepaid amortization: every listed account with a
+    /// balance needs a credit from an adjustment dated in the period
+    async fn amortization_item(
+        &self,
+        check: CloseCheck,
+        account_ids: &[String],
+        in_period: &[Transaction],
+        end_date: NaiveDate,
+    ) -> LedgerResult<CloseChecklistItem> {
+        let mut pending = Vec::new();
+        let mut outstanding = BigDecimal::zero();
+        let mut with_balance = 0;
+        for account_id in account_ids {
+            let balance = self.get_account_balance(account_id, Some(end_date)).await?;
+            if balance.is_zero() {
+                continue;
+            }
+            with_balance += 1;
+            let adjusted = in_period.iter().any(|t| {
+                t.kind == TransactionKind::Adjustment
+                    && t.entries
+                        .iter()
+                        .any(|e| &e.account_id == account_id && e.entry_type == EntryType::Credit)
+            });
+            if !adjusted {
+                pending.push(account_id.clone());
+                outstanding += balance;
+            }
+        }
+
+        let (what, verb) = match check {
+            CloseCheck::Depreciation => ("Depreciation", "depreciate"),
+            _ => ("Amortization", "amortize"),
+        };
+        let mut item = if with_balance == 0 {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::NotApplicable,
+                format!("No accounts with a balance to {}", verb),
+            )
+        } else if pending.is_empty() {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Done,
+                format!("{} posted for {} account(s)", what, with_balance),
+            )
+        } else {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Open,
+                format!("{} not posted for {} account(s)", what, pending.len()),
+            )
+        };
+        item.account_ids = pending;
+        item.amount = outstanding;
+        Ok(item)
+    }
+
+    async fn accrual_reversal_item(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<CloseChecklistItem> {
+        let check = CloseCheck::AccrualReversal;
+        let Some(before_start) = start_date.pred_opt() else {
+            return Ok(CloseChecklistItem::new(
+                check,
+                CloseItemStatus::NotApplicable,
+                "No earlier periods".to_string(),
+            ));
+        };
+        let accruals: Vec<Transaction> = self
+            .get_transactions(None, Some(before_start))
+            .await?
+            .into_iter()
+            .filter(|t| {
+                t.metadata
+                    .get(ACCRUAL_KEY)
+                    .and_then(MetaValue::as_bool)
+                    .unwrap_or(false)
+            })
+            .collect();
+        if accruals.is_empty() {
+            return Ok(CloseChecklistItem::new(
+                check,
+                CloseItemStatus::NotApplicable,
+                "No accruals from earlier periods".to_string(),
+            ));
+        }
+
+        let reversed: HashSet<String> = self
+            .get_transactions(None, Some(end_date))
+            .await?
+            .into_iter()
+            .filter_map(|t| t.corrects)
+            .collect();
+        let unreversed: Vec<&Transaction> = accruals
+            .iter()
+            .filter(|t| !reversed.contains(&t.id))
+            .collect();
+
+        let mut item = if unreversed.is_empty() {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Done,
+                format!("All {} accrual(s) reversed", accruals.len()),
+            )
+        } else {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Open,
+                format!("{} accrual(s) not reversed", unreversed.len()),
+            )
+        };
+        item.amount = unreversed.iter().map(|t| t.total_debits()).sum();
+        item.transaction_ids = unreversed.into_iter().map(|t| t.id.clone()).collect();
+        Ok(item)
+    }
+
+    async fn bank_reconciliation_item(
+        &self,
+        end_date: NaiveDate,
+    ) -> LedgerResult<CloseChecklistItem> {
+        let check = CloseCheck::BankReconciliation;
+        if self.suspense_account().is_none() {
+            return Ok(CloseChecklistItem::new(
+                check,
+                CloseItemStatus::NotApplicable,
+                "No suspense account configured for bank imports".to_string(),
+            ));
+        }
+
+        let mut unclassified = Vec::new();
+        let mut outstanding = BigDecimal::zero();
+        for suspense_item in self.list_suspense_items().await? {
+            if suspense_item.date > end_date {
+                continue;
+            }
+            let from_bank = self
+                .get_transaction(&suspense_item.transaction_id)
+                .await?
+                .and_then(|t| t.metadata.get("source").cloned())
+                .is_some_and(|source| source.as_str() == Some("bank_import"));
+            if from_bank {
+                outstanding += suspense_item.amount.abs();
+                unclassified.push(suspense_item.transaction_id);
+            }
+        }
+
+        let mut item = if unclassified.is_empty() {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Done,
+                "All imported bank lines classified".to_string(),
+            )
+        } else {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Open,
+                format!("{} imported bank line(s) in suspense", unclassified.len()),
+            )
+        };
+        item.transaction_ids = unclassified;
+        item.amount = outstanding;
+        Ok(item)
+    }
+
+    async fn suspense_balance_item(&self, end_date: NaiveDate) -> LedgerResult<CloseChecklistItem> {
+        let check = CloseCheck::SuspenseBalance;
+        let Some(suspense_account_id) = self.suspense_account().map(str::to_string) else {
+            return Ok(CloseChecklistItem::new(
+                check,
+                CloseItemStatus::NotApplicable,
+                "No suspense account configured".to_string(),
+            ));
+        };
+        let balance = self
+            .get_account_balance(&suspense_account_id, Some(end_date))
+            .await?;
+        let mut item = if balance.is_zero() {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Done,
+                "Suspense account is clear".to_string(),
+            )
+        } else {
+            CloseChecklistItem::new(
+                check,
+                CloseItemStatus::Open,
+                format!("Suspense account balance is {}", balance),
+            )
+        };
+        item.account_ids = vec![suspense_account_id];
+        item.amount = balance;
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, TransactionBuilder};
+    use crate::reconciliation::BankStatementLine;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_close_checklist_tracks_adjustments() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("1500", "Plant and Machinery", AccountType::Asset),
+            ("1400", "Prepaid Insurance", AccountType::Asset),
+            ("1999", "Suspense", AccountType::Liability),
+            ("2300", "Accrued Expenses", AccountType::Liability),
+            ("6800", "Depreciation", AccountType::Expense),
+            ("6810", "Insurance", AccountType::Expense),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        ledger.set_suspense_account(Some("1999".to_string()));
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let amount = BigDecimal::from;
+
+        let setup = [
+            TransactionBuilder::new("machine".to_string(), day(3, 1), "Machine".to_string())
+                .debit("1500".to_string(), amount(12000), None)
+                .credit("1000".to_string(), amount(12000), None),
+            TransactionBuilder::new("policy".to_string(), day(3, 1), "Policy".to_string())
+                .debit("1400".to_string(), amount(1200), None)
+                .credit("1000".to_string(), amount(1200), None),
+            TransactionBuilder::new("accrual".to_string(), day(3, 31), "Audit fee".to_string())
+                .kind(TransactionKind::Adjustment)
+                .debit("6810".to_string(), amount(500), None)
+                .credit("2300".to_string(), amount(500), None)
+                .metadata(ACCRUAL_KEY.to_string(), true),
+        ];
+        for builder in setup {
+            ledger
+                .record_transaction(builder.build().unwrap())
+                .await
+                .unwrap();
+        }
+        ledger
+            .import_bank_lines_to_suspense(
+                "1000",
+                &[BankStatementLine::new(
+                    day(4, 10),
+                    "CHARGES".to_string(),
+                    amount(-30),
+                    None,
+                )],
+            )
+            .await
+            .unwrap();
+
+        let config = CloseChecklistConfig {
+            depreciable_accounts: vec!["1500".to_string()],
+            prepaid_accounts: vec!["1400".to_string()],
+        };
+        let checklist = ledger
+            .period_close_checklist(day(4, 1), day(4, 30), &config)
+            .await
+            .unwrap();
+        assert_eq!(checklist.open_items().len(), 5);
+        assert_eq!(
+            checklist
+                .item(CloseCheck::AccrualReversal)
+                .unwrap()
+                .transaction_ids,
+            vec!["accrual".to_string()]
+        );
+        assert_eq!(
+            checklist.item(CloseCheck::SuspenseBalance).unwrap().amount,
+            amount(-30)
+        );
+
+        ledger
+            .record_adjusting_entry(
+                CloseCheck::Depreciation,
+                TransactionBuilder::new("dep".to_string(), day(4, 30), "Depreciation".to_string())
+                    .debit("6800".to_string(), amount(100), None)
+                    .credit("1500".to_string(), amount(100), None)
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        ledger
+            .record_adjusting_entry(
+                CloseCheck::PrepaidAmortization,
+                TransactionBuilder::new("amort".to_string(), day(4, 30), "Insurance".to_string())
+                    .debit("6810".to_string(), amount(100), None)
+                    .credit("1400".to_string(), amount(100), None)
+                    .build()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let accrual = ledger.get_transaction("accrual").await.unwrap().unwrap();
+        let reversal = patterns::create_reversal(
+            &accrual,
+            "accrual-rev".to_string(),
+            day(4, 1),
+            "Reverse audit fee".to_string(),
+        )
+        .unwrap();
+        ledger
+            .record_adjusting_entry(CloseCheck::AccrualReversal, reversal)
+            .await
+            .unwrap();
+        let bank_line = &ledger.list_suspense_items().await.unwrap()[0];
+        ledger
+            .recode_suspense_item(&bank_line.transaction_id.clone(), "6810")
+            .await
+            .unwrap();
+
+        let checklist = ledger
+            .period_close_checklist(day(4, 1), day(4, 30), &config)
+            .await
+            .unwrap();
+        assert!(checklist.is_ready_to_close());
+        assert_eq!(checklist.adjustments.len(), 3);
+        assert_eq!(
+            checklist.adjustments[0].check,
+            Some(CloseCheck::AccrualReversal)
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Account-type sanity checks on postings
+//!
+//! Some entries are legal double entry but almost always a data-entry
+//! mistake: crediting an expense account or debiting revenue outside a
+//! reversal. With [`PostingRules`] set on the ledger, each recorded
+//! transaction is checked against them. A rule at [`RuleSeverity::Error`]
+//! rejects the transaction; at [`RuleSeverity::Warn`] the transaction is
+//! posted with a note and [`POSTING_WARNING_KEY`] set, for review.
+//!
+//! Reversals (transactions that [correct](Transaction::corrects) another,
+//! or carry [`REVERSAL_KEY`] like credit notes and refunds) and closing
+//! entries are never checked, nor are transactions of an exempt kind.
+
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::{Ledger, CLOSING_ENTRY_KEY};
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key marking a posting that broke a warning rule
+pub const POSTING_WARNING_KEY: &str = "posting_warning";
+/// Transaction metadata key marking a document that reverses earlier
+/// postings without correcting one transaction, such as a credit note
+pub const REVERSAL_KEY: &str = "reversal";
+
+/// A sanity check on the direction of an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PostingRule {
+    /// An expense account is credited
+    ExpenseCredited,
+    /// An income account is debited
+    IncomeDebited,
+}
+
+impl PostingRule {
+    fn describe(&self) -> &'static str {
+        match self {
+            PostingRule::ExpenseCredited => "expense account credited",
+            PostingRule::IncomeDebited => "income account debited",
+        }
+    }
+}
+
+/// What happens when a rule is broken
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RuleSeverity {
+    /// The rule is not checked
+    Off,
+    /// Post it, noting the problem on the transaction
+    #[default]
+    Warn,
+    /// Reject the transaction
+    Error,
+}
+
+/// An entry that broke a posting rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PostingViolation {
+    /// Rule that was broken
+    pub rule: PostingRule,
+    /// Severity the rule is enforced at
+    pub severity: RuleSeverity,
+    /// Account of the offending entry
+    pub account_id: String,
+    /// Amount of the offending entry
+    pub amount: BigDecimal,
+}
+
+impl PostingViolation {
+    fn describe(&self) -> String {
+        format!(
+            "{} ({} {})",
+            self.rule.describe(),
+            self.account_id,
+            self.amount
+        )
+    }
+}
+
+/// Severity of each posting rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PostingRules {
+    /// Severity of [`PostingRule::ExpenseCredited`]
+    pub expense_credited: RuleSeverity,
+    /// Severity of [`PostingRule::IncomeDebited`]
+    pub income_debited: RuleSeverity,
+    /// Voucher types never checked, such as period-end adjustments
+    #[serde(default)]
+    pub exempt_kinds: Vec<TransactionKind>,
+}
+
+impl Default for PostingRules {
+    /// Warn on every rule; adjustments are exempt
+    fn default() -> Self {
+        Self {
+            expense_credited: RuleSeverity::Warn,
+            income_debited: RuleSeverity::Warn,
+            exempt_kinds: vec![TransactionKind::Adjustment],
+        }
+    }
+}
+
+impl PostingRules {
+    /// Set the severity of one rule
+    pub fn with(mut self, rule: PostingRule, severity: RuleSeverity) -> Self {
+        match rule {
+            PostingRule::ExpenseCredited => self.expense_credited = severity,
+            PostingRule::IncomeDebited => self.income_debited = severity,
+        }
+        self
+    }
+
+    /// Severity of a rule
+    pub fn severity(&self, rule: PostingRule) -> RuleSeverity {
+        match rule {
+            PostingRule::ExpenseCredited => self.expense_credited,
+            PostingRule::IncomeDebited => self.income_debited,
+        }
+    }
+
+    /// Whether a transaction is exempt from every rule
+    pub fn is_exempt(&self, transaction: &Transaction) -> bool {
+        let flagged = |key| {
+            transaction
+                .metadata
+                .get(key)
+                .and_then(MetaValue::as_bool)
+                .unwrap_or(false)
+        };
+        transaction.corrects.is_some()
+            || self.exempt_kinds.contains(&transaction.kind)
+            || flagged(REVERSAL_KEY)
+            || flagged(CLOSING_ENTRY_KEY)
+    }
+
+    /// Rule broken by an entry to an account of the given type, if any
+    pub fn check_entry(
+        &self,
+        account_type: &AccountType,
+        entry: &Entry,
+    ) -> Option<PostingViolation> {
+        let rule = match (account_type, &entry.entry_type) {
+            (AccountType::Expense, EntryType::Credit) => PostingRule::ExpenseCredited,
+            (AccountType::Income, EntryType::Debit) => PostingRule::IncomeDebited,
+            _ => return None,
+        };
+        let severity = self.severity(rule);
+        (severity != RuleSeverity::Off).then(|| PostingViolation {
+            rule,
+            severity,
+            account_id: entry.account_id.clone(),
+            amount: entry.amount.clone(),
+        })
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Check recorded transactions against `rules`, or stop checking
+    pub fn set_posting_rules(&mut self, rules: Option<PostingRules>) {
+        self.posting_rules = rules;
+    }
+
+    /// The posting rules in force, if any
+    pub fn posting_rules(&self) -> Option<&PostingRules> {
+        self.posting_rules.as_ref()
+    }
+
+    /// Rules a transaction would break
+    pub async fn check_posting_rules(
+        &self,
+        transaction: &Transaction,
+    ) -> LedgerResult<Vec<PostingViolation>> {
+        let Some(rules) = &self.posting_rules else {
+            return Ok(Vec::new());
+        };
+        if rules.is_exempt(transaction) {
+            return Ok(Vec::new());
+        }
+        let mut violations = Vec::new();
+        for entry in transaction.all_entries() {
+            // Unknown accounts are left for reference validation to report
+            let Some(account) = self.get_account(&entry.account_id).await? else {
+                continue;
+            };
+            violations.extend(rules.check_entry(&account.account_type, entry));
+        }
+        Ok(violations)
+    }
+
+    /// Reject a transaction breaking an error rule, or note warnings on it
+    pub(crate) async fn enforce_posting_rules(
+        &self,
+        transaction: &mut Transaction,
+    ) -> LedgerResult<()> {
+        let violations = self.check_posting_rules(transaction).await?;
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let errors: Vec<String> = violations
+            .iter()
+            .filter(|v| v.severity == RuleSeverity::Error)
+            .map(PostingViolation::describe)
+            .collect();
+        if !errors.is_empty() {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' breaks posting rules: {}",
+                transaction.id,
+                errors.join("; ")
+            )));
+        }
+        let warnings: Vec<String> = violations.iter().map(PostingViolation::describe).collect();
+        transaction
+            .metadata
+            .insert(POSTING_WARNING_KEY.to_string(), true.into());
+        self.add_check_note(
+            transaction,
+            format!("Posting check: {}", warnings.join("; ")),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_posting_rules_warn_and_reject() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger.set_posting_rules(Some(
+            PostingRules::default().with(PostingRule::IncomeDebited, RuleSeverity::Error),
+        ));
+        let date = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let transfer = |id: &str, debit: &str, credit: &str| {
+            TransactionBuilder::new(id.to_string(), date, "Entry".to_string())
+                .debit(debit.to_string(), BigDecimal::from(100), None)
+                .credit(credit.to_string(), BigDecimal::from(100), None)
+        };
+
+        // Rent refunded into cash credits an expense: posted with a warning
+        ledger
+            .record_transaction(transfer("W1", "1000", "6000").build().unwrap())
+            .await
+            .unwrap();
+        let warned = ledger.get_transaction("W1").await.unwrap().unwrap();
+        assert_eq!(
+            warned.metadata.get(POSTING_WARNING_KEY),
+            Some(&MetaValue::Bool(true))
+        );
+        assert!(warned.notes[0].text.contains("expense account credited"));
+
+        // Debiting revenue is an error
+        let result = ledger
+            .record_transaction(transfer("E1", "4000", "1000").build().unwrap())
+            .await;
+        assert!(matches!(result, Err(LedgerError::Validation(_))));
+        assert!(ledger.get_transaction("E1").await.unwrap().is_none());
+
+        // Adjustments and normal postings pass untouched
+        let adjustment = transfer("A1", "4000", "1000")
+            .kind(TransactionKind::Adjustment)
+            .build()
+            .unwrap();
+        ledger.record_transaction(adjustment).await.unwrap();
+        ledger
+            .record_transaction(transfer("N1", "1000", "4000").build().unwrap())
+            .await
+            .unwrap();
+        let normal = ledger.get_transaction("N1").await.unwrap().unwrap();
+        assert!(normal.notes.is_empty());
+    }
+}
@@ -0,0 +1,428 @@
+//! Projects and job costing
+//!
+//! A [`Project`] is a job run for a customer, with a budget. Costs and
+//! revenue are charged to it by tagging entries with [`PROJECT_DIMENSION`];
+//! cost entries can also be marked billable to the customer or not with
+//! [`BILLABLE_DIMENSION`]. Project reports total the tagged entries, so
+//! project accounting needs no accounts of its own.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Entry dimension identifying the project an entry is charged to
+pub const PROJECT_DIMENSION: &str = "project";
+/// Entry dimension marking a project cost as billable to the customer
+pub const BILLABLE_DIMENSION: &str = "billable";
+
+/// Where a project is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ProjectStatus {
+    #[default]
+    Active,
+    Completed,
+    Cancelled,
+}
+
+/// Planned revenue and cost of a project
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProjectBudget {
+    pub revenue: BigDecimal,
+    pub cost: BigDecimal,
+    /// Cost budget per expense account, within `cost`
+    #[serde(default)]
+    pub cost_by_account: BTreeMap<String, BigDecimal>,
+}
+
+/// A job run for a customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub customer_id: Option<String>,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    pub status: ProjectStatus,
+    #[serde(default)]
+    pub budget: ProjectBudget,
+    /// Asset account costs are held in until billed
+    pub wip_account_id: Option<String>,
+}
+
+impl Project {
+    /// Create an active project with no budget
+    pub fn new(id: String, name: String, start_date: NaiveDate) -> Self {
+        Self {
+            id,
+            name,
+            customer_id: None,
+            start_date,
+            end_date: None,
+            status: ProjectStatus::Active,
+            budget: ProjectBudget::default(),
+            wip_account_id: None,
+        }
+    }
+
+    /// Set the customer the project is run for
+    pub fn customer(mut self, customer_id: String) -> Self {
+        self.customer_id = Some(customer_id);
+        self
+    }
+
+    /// Set the account costs are held in until billed
+    pub fn wip_account(mut self, account_id: String) -> Self {
+        self.wip_account_id = Some(account_id);
+        self
+    }
+
+    /// Charge an entry to the project
+    pub fn charge(&self, entry: Entry) -> Entry {
+        entry.with_dimension(PROJECT_DIMENSION.to_string(), self.id.clone())
+    }
+
+    /// Charge a cost entry to the project, marking whether it is billable
+    pub fn charge_cost(&self, entry: Entry, billable: bool) -> Entry {
+        self.charge(entry)
+            .with_dimension(BILLABLE_DIMENSION.to_string(), billable.to_string())
+    }
+}
+
+/// Whether a project entry is marked billable
+pub fn is_billable(entry: &Entry) -> bool {
+    entry
+        .dimensions
+        .get(BILLABLE_DIMENSION)
+        .is_some_and(|value| value == "true")
+}
+
+/// Costs charged to a project in a period
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProjectCosts {
+    pub billable: BigDecimal,
+    pub non_billable: BigDecimal,
+    /// Net cost per expense account
+    pub by_account: BTreeMap<String, BigDecimal>,
+}
+
+impl ProjectCosts {
+    /// Billable and non-billable costs together
+    pub fn total(&self) -> BigDecimal {
+        &self.billable + &self.non_billable
+    }
+}
+
+/// One project's results for a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProjectProfitabilityLine {
+    pub project_id: String,
+    pub project_name: String,
+    pub revenue: BigDecimal,
+    pub costs: ProjectCosts,
+    /// Revenue less costs
+    pub profit: BigDecimal,
+    /// Profit as a percentage of revenue; `None` without revenue
+    pub margin_percent: Option<BigDecimal>,
+    /// Balance held in the project's WIP account at the period end
+    pub wip: BigDecimal,
+    pub budget: ProjectBudget,
+    /// Cost budget left; negative when overspent
+    pub cost_budget_remaining: BigDecimal,
+}
+
+/// Results per project for a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProjectProfitabilityReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// One line per project, ordered by project ID
+    pub lines: Vec<ProjectProfitabilityLine>,
+    pub total_revenue: BigDecimal,
+    pub total_cost: BigDecimal,
+    pub total_profit: BigDecimal,
+}
+
+impl ProjectProfitabilityReport {
+    /// Line for a project
+    pub fn line(&self, project_id: &str) -> Option<&ProjectProfitabilityLine> {
+        self.lines.iter().find(|line| line.project_id == project_id)
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Save a project, replacing any with the same ID
+    ///
+    /// The WIP account, if any, must exist.
+    pub async fn save_project(&mut self, project: Project) -> LedgerResult<()> {
+        if let Some(account_id) = &project.wip_account_id {
+            self.account_manager
+                .get_account_required(account_id)
+                .await?;
+        }
+        self.projects.insert(project.id.clone(), project);
+        Ok(())
+    }
+
+    /// Get a project by ID
+    pub fn get_project(&self, project_id: &str) -> Option<&Project> {
+        self.projects.get(project_id)
+    }
+
+    /// All projects, ordered by ID
+    pub fn list_projects(&self) -> Vec<&Project> {
+        self.projects.values().collect()
+    }
+
+    /// Replace a project's budget
+    pub fn set_project_budget(
+        &mut self,
+        project_id: &str,
+        budget: ProjectBudget,
+    ) -> LedgerResult<()> {
+        self.project_required(project_id)?.budget = budget;
+        Ok(())
+    }
+
+    /// Change a project's status
+    pub fn set_project_status(
+        &mut self,
+        project_id: &str,
+        status: ProjectStatus,
+    ) -> LedgerResult<()> {
+        self.project_required(project_id)?.status = status;
+        Ok(())
+    }
+
+    fn project_required(&mut self, project_id: &str) -> LedgerResult<&mut Project> {
+        self.projects
+            .get_mut(project_id)
+            .ok_or_else(|| LedgerError::Validation(format!("Project not found: {}", project_id)))
+    }
+
+    /// Costs charged to a project between two dates
+    pub async fn get_project_costs(
+        &self,
+        project_id: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<ProjectCosts> {
+        let accounts = self.account_types().await?;
+        let (_, costs) = self
+            .project_results(project_id, start_date, end_date, &accounts)
+            .await?;
+        Ok(costs)
+    }
+
+    /// Revenue, cost, profit and WIP per project between two dates
+    pub async fn generate_project_profitability(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<ProjectProfitabilityReport> {
+        let accounts = self.account_types().await?;
+        let mut report = ProjectProfitabilityReport {
+            start_date,
+            end_date,
+            lines: Vec::new(),
+            total_revenue: BigDecimal::zero(),
+            total_cost: BigDecimal::zero(),
+            total_profit: BigDecimal::zero(),
+        };
+        for project in self.projects.values() {
+            let (revenue, costs) = self
+                .project_results(&project.id, start_date, end_date, &accounts)
+                .await?;
+            let wip = match &project.wip_account_id {
+                Some(account_id) => {
+                    let filter = EntryFilter {
+                        account_id: Some(account_id.clone()),
+                        end_date: Some(end_date),
+                        dimension: Some((PROJECT_DIMENSION.to_string(), project.id.clone())),
+                        ..Default::default()
+                    };
+                    self.get_entries(&filter)
+                        .await?
+                        .iter()
+                        .map(|row| {
+                            AccountType::Asset
+                                .balance_effect(&row.entry.entry_type, &row.entry.amount)
+                        })
+                        .sum()
+                }
+                None => BigDecimal::zero(),
+            };
+            let cost = costs.total();
+            let profit = &revenue - &cost;
+            let margin_percent =
+                (!revenue.is_zero()).then(|| (&profit * BigDecimal::from(100) / &revenue).round(2));
+            report.total_revenue += &revenue;
+            report.total_cost += &cost;
+            report.total_profit += &profit;
+            report.lines.push(ProjectProfitabilityLine {
+                project_id: project.id.clone(),
+                project_name: project.name.clone(),
+                revenue,
+                cost_budget_remaining: &project.budget.cost - &cost,
+                costs,
+                profit,
+                margin_percent,
+                wip,
+                budget: project.budget.clone(),
+            });
+        }
+        Ok(report)
+    }
+
+    pub(crate) async fn account_types(&self) -> LedgerResult<HashMap<String, AccountType>> {
+        Ok(self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|account| (account.id, account.account_type))
+            .collect())
+    }
+
+    /// Revenue and costs charged to a project
+    pub(crate) async fn project_results(
+        &self,
+        project_id: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        accounts: &HashMap<String, AccountType>,
+    ) -> LedgerResult<(BigDecimal, ProjectCosts)> {
+        let filter = EntryFilter {
+            start_date: Some(start_date),
+            end_date: Some(end_date),
+            dimension: Some((PROJECT_DIMENSION.to_string(), project_id.to_string())),
+            ..Default::default()
+        };
+        let mut revenue = BigDecimal::zero();
+        let mut costs = ProjectCosts::default();
+        for row in self.get_entries(&filter).await? {
+            let entry = &row.entry;
+            match accounts.get(&entry.account_id) {
+                Some(AccountType::Income) => {
+                    revenue += AccountType::Income.balance_effect(&entry.entry_type, &entry.amount);
+                }
+                Some(AccountType::Expense) => {
+                    let amount =
+                        AccountType::Expense.balance_effect(&entry.entry_type, &entry.amount);
+                    *costs
+                        .by_account
+                        .entry(entry.account_id.clone())
+                        .or_default() += &amount;
+                    if is_billable(entry) {
+                        costs.billable += amount;
+                    } else {
+                        costs.non_billable += amount;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok((revenue, costs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_project_profitability() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "1350".to_string(),
+                "Work in Progress".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+        let fitout = Project::new("P-1".to_string(), "Office fit-out".to_string(), date(1))
+            .customer("acme".to_string())
+            .wip_account("1350".to_string());
+        ledger.save_project(fitout.clone()).await.unwrap();
+        ledger
+            .set_project_budget(
+                "P-1",
+                ProjectBudget {
+                    revenue: BigDecimal::from(10000),
+                    cost: BigDecimal::from(6000),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert!(ledger
+            .set_project_budget("P-9", ProjectBudget::default())
+            .is_err());
+
+        let costs = TransactionBuilder::new("T1".to_string(), date(5), "Materials".to_string())
+            .entry(fitout.charge_cost(
+                Entry::debit("5000".to_string(), BigDecimal::from(4000), None),
+                true,
+            ))
+            .entry(fitout.charge_cost(
+                Entry::debit("6000".to_string(), BigDecimal::from(2500), None),
+                false,
+            ))
+            .credit("1000".to_string(), BigDecimal::from(6500), None)
+            .build()
+            .unwrap();
+        ledger.record_transaction(costs).await.unwrap();
+        let billing = TransactionBuilder::new("T2".to_string(), date(20), "Stage 1".to_string())
+            .debit("1200".to_string(), BigDecimal::from(8000), None)
+            .entry(fitout.charge(Entry::credit(
+                "4000".to_string(),
+                BigDecimal::from(8000),
+                None,
+            )))
+            .build()
+            .unwrap();
+        ledger.record_transaction(billing).await.unwrap();
+        let wip = TransactionBuilder::new("T3".to_string(), date(25), "Labour held".to_string())
+            .entry(fitout.charge(Entry::debit(
+                "1350".to_string(),
+                BigDecimal::from(700),
+                None,
+            )))
+            .credit("1000".to_string(), BigDecimal::from(700), None)
+            .build()
+            .unwrap();
+        ledger.record_transaction(wip).await.unwrap();
+
+        let costs = ledger
+            .get_project_costs("P-1", date(1), date(30))
+            .await
+            .unwrap();
+        assert_eq!(costs.billable, BigDecimal::from(4000));
+        assert_eq!(costs.non_billable, BigDecimal::from(2500));
+
+        let report = ledger
+            .generate_project_profitability(date(1), date(30))
+            .await
+            .unwrap();
+        let line = report.line("P-1").unwrap();
+        assert_eq!(line.revenue, BigDecimal::from(8000));
+        assert_eq!(line.profit, BigDecimal::from(1500));
+        assert_eq!(line.margin_percent, Some(BigDecimal::from(1875) / 100));
+        assert_eq!(line.wip, BigDecimal::from(700));
+        assert_eq!(line.cost_budget_remaining, BigDecimal::from(-500));
+    }
+}
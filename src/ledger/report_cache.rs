@@ -0,0 +1,214 @@
+//! Memoization of generated reports
+//!
+//! A [`ReportCache`] attached to a ledger stores trial balances, balance
+//! sheets and income statements by report type, period and scope. The ledger
+//! registers it as an observer, so any posting, edit or void dated on or
+//! before a cached report's end date drops that report. Any account change,
+//! and any setting that shapes reports, drops them all.
+
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Kind of a cached report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportKind {
+    TrialBalance,
+    BalanceSheet,
+    IncomeStatement,
+}
+
+/// Cache key: report type, period and scope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportKey {
+    pub kind: ReportKind,
+    /// Start of the period for period reports
+    pub start_date: Option<NaiveDate>,
+    pub end_date: NaiveDate,
+    pub scope: ReportScope,
+}
+
+/// A cached report
+#[derive(Debug, Clone, PartialEq)]
+pub enum CachedReport {
+    TrialBalance(TrialBalance),
+    BalanceSheet(BalanceSheet),
+    IncomeStatement(IncomeStatement),
+}
+
+/// Shared store of generated reports; clones share the same entries
+#[derive(Debug, Clone, Default)]
+pub struct ReportCache {
+    reports: Arc<RwLock<HashMap<ReportKey, CachedReport>>>,
+}
+
+impl ReportCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached report for a key, if present
+    pub fn get(&self, key: &ReportKey) -> Option<CachedReport> {
+        self.reports.read().unwrap().get(key).cloned()
+    }
+
+    /// Store a report
+    pub fn insert(&self, key: ReportKey, report: CachedReport) {
+        self.reports.write().unwrap().insert(key, report);
+    }
+
+    /// Number of cached reports
+    pub fn len(&self) -> usize {
+        self.reports.read().unwrap().len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every cached report
+    pub fn clear(&self) {
+        self.reports.write().unwrap().clear();
+    }
+
+    /// Drop reports that a change dated `date` can affect; draft-only changes
+    /// only affect reports that include drafts
+    pub fn invalidate(&self, date: NaiveDate, drafts_only: bool) {
+        self.reports.write().unwrap().retain(|key, _| {
+            key.end_date < date || (drafts_only && key.scope == ReportScope::Posted)
+        });
+    }
+}
+
+impl LedgerObserver for ReportCache {
+    fn on_event(&self, event: &LedgerEvent<'_>) {
+        match event {
+            LedgerEvent::TransactionPosted(transaction)
+            | LedgerEvent::TransactionVoided(transaction) => {
+                self.invalidate(transaction.date, !transaction.is_posted())
+            }
+            LedgerEvent::TransactionUpdated { old, new } => {
+                let drafts_only = !old.is_posted() && !new.is_posted();
+                self.invalidate(old.date.min(new.date), drafts_only);
+            }
+            LedgerEvent::DraftSaved(transaction) | LedgerEvent::DraftDiscarded(transaction) => {
+                self.invalidate(transaction.date, true)
+            }
+            LedgerEvent::AccountChanged { .. }
+            | LedgerEvent::PeriodArchived { .. }
+            | LedgerEvent::SnapshotImported => self.clear(),
+        }
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Memoize reports in `cache` until a change invalidates them
+    pub fn set_report_cache(&mut self, cache: ReportCache) {
+        self.add_observer(Arc::new(cache.clone()));
+        self.report_cache = Some(cache);
+    }
+
+    /// The attached report cache, if any
+    pub fn report_cache(&self) -> Option<&ReportCache> {
+        self.report_cache.as_ref()
+    }
+
+    pub(crate) fn cached_report(&self, key: &ReportKey) -> Option<CachedReport> {
+        self.report_cache.as_ref()?.get(key)
+    }
+
+    pub(crate) fn cache_report(&self, key: ReportKey, report: CachedReport) {
+        if let Some(cache) = &self.report_cache {
+            cache.insert(key, report);
+        }
+    }
+
+    /// Drop every cached report after a setting that shapes reports changes
+    pub(crate) fn clear_report_cache(&self) {
+        if let Some(cache) = &self.report_cache {
+            cache.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, NetIncomePresentation};
+    use crate::utils::MemoryStorage;
+    use bigdecimal::BigDecimal;
+
+    #[tokio::test]
+    async fn test_reports_are_cached_until_a_covered_posting() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let cache = ReportCache::new();
+        ledger.set_report_cache(cache.clone());
+        let march = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let rent = |id: &str, date: NaiveDate| {
+            patterns::create_expense_payment(
+                id.to_string(),
+                date,
+                "Rent".to_string(),
+                "6000".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(100),
+            )
+            .unwrap()
+        };
+
+        ledger.record_transaction(rent("r1", march)).await.unwrap();
+        let first = ledger.generate_balance_sheet(march).await.unwrap();
+        ledger.get_trial_balance(march).await.unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(ledger.generate_balance_sheet(march).await.unwrap(), first);
+
+        // Postings after the report date and drafts leave posted reports alone
+        ledger
+            .record_transaction(rent("r2", march.succ_opt().unwrap()))
+            .await
+            .unwrap();
+        ledger.save_draft(rent("d1", march)).await.unwrap();
+        assert_eq!(cache.len(), 2);
+
+        ledger.record_transaction(rent("r3", march)).await.unwrap();
+        assert!(cache.is_empty());
+        let second = ledger.generate_balance_sheet(march).await.unwrap();
+        assert_ne!(second.total_assets, first.total_assets);
+    }
+
+    #[tokio::test]
+    async fn test_presentation_change_drops_cached_reports() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let cache = ReportCache::new();
+        ledger.set_report_cache(cache.clone());
+        let march = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let sale = patterns::create_sales_transaction(
+            "s1".to_string(),
+            march,
+            "Sale".to_string(),
+            "1000".to_string(),
+            "4000".to_string(),
+            BigDecimal::from(500),
+        )
+        .unwrap();
+        ledger.record_transaction(sale).await.unwrap();
+
+        let rolled = ledger.generate_balance_sheet(march).await.unwrap();
+        assert_eq!(rolled.current_earnings, None);
+        ledger.set_net_income_presentation(NetIncomePresentation::SeparateLine);
+        assert!(cache.is_empty());
+        let separate = ledger.generate_balance_sheet(march).await.unwrap();
+        assert_eq!(separate.current_earnings, Some(BigDecimal::from(500)));
+
+        ledger.set_retained_earnings_account(None);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,305 @@
+//! Retained earnings account and the statement of retained earnings
+//!
+//! Retained earnings here are the balance of the retained earnings account,
+//! plus profit not yet closed into it, less dividends and drawings not yet
+//! closed into it. The statement explains the change in that figure over a
+//! period: net income in, distributions out, and any other postings made to
+//! the retained earnings account directly (prior-period adjustments, for
+//! example). Closing entries only move amounts between these accounts, so
+//! they leave every line of the statement unchanged.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::ledger::closing::is_closing_entry;
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Account metadata key marking a dividends or drawings account
+const DISTRIBUTION_KEY: &str = "distribution";
+
+/// How the balance sheet shows net income not yet closed to equity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum NetIncomePresentation {
+    /// Add it to the retained earnings account's line, falling back to
+    /// [`BalanceSheet::current_earnings`] when no account is configured
+    #[default]
+    RollIntoAccount,
+    /// Always report it in [`BalanceSheet::current_earnings`]
+    SeparateLine,
+}
+
+/// Statement of retained earnings for a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RetainedEarningsStatement {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub retained_earnings_account_id: String,
+    /// Retained earnings at the close of the day before `start_date`
+    pub opening_balance: BigDecimal,
+    pub net_income: BigDecimal,
+    /// Dividends and drawings for the period, as a positive amount
+    pub distributions: BigDecimal,
+    /// Other postings made directly to the retained earnings account
+    pub other_adjustments: BigDecimal,
+    pub closing_balance: BigDecimal,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Set the equity account profits are closed into, such as Retained
+    /// Earnings or Current Year Earnings
+    ///
+    /// [`Ledger::setup_standard_chart_of_accounts`] sets this to the standard
+    /// chart's retained earnings account.
+    pub fn set_retained_earnings_account(&mut self, account_id: Option<String>) {
+        self.retained_earnings_account_id = account_id;
+        self.clear_report_cache();
+    }
+
+    /// Get the configured retained earnings account
+    pub fn retained_earnings_account(&self) -> Option<&str> {
+        self.retained_earnings_account_id.as_deref()
+    }
+
+    /// Choose how the balance sheet shows net income not yet closed
+    pub fn set_net_income_presentation(&mut self, presentation: NetIncomePresentation) {
+        self.net_income_presentation = presentation;
+        self.clear_report_cache();
+    }
+
+    /// How the balance sheet shows net income not yet closed
+    pub fn net_income_presentation(&self) -> NetIncomePresentation {
+        self.net_income_presentation
+    }
+
+    /// Mark an equity account as holding dividends or owner's drawings
+    pub async fn designate_distribution_account(
+        &mut self,
+        account_id: &str,
+    ) -> LedgerResult<Account> {
+        let mut account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        if account.account_type != AccountType::Equity {
+            return Err(LedgerError::Validation(format!(
+                "Distribution account {} must be an equity account",
+                account_id
+            )));
+        }
+        account
+            .metadata
+            .insert(DISTRIBUTION_KEY.to_string(), true.into());
+        account.updated_at = self.clock.now();
+        self.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Generate the statement of retained earnings for a period
+    ///
+    /// Fails with [`LedgerError::Validation`] when no retained earnings
+    /// account is configured.
+    pub async fn generate_retained_earnings_statement(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<RetainedEarningsStatement> {
+        let retained_earnings_account_id = self
+            .retained_earnings_account()
+            .map(str::to_string)
+            .ok_or_else(|| {
+                LedgerError::Validation("No retained earnings account configured".to_string())
+            })?;
+        let accounts = self.list_accounts().await?;
+        let distribution_accounts: HashSet<&str> = accounts
+            .iter()
+            .filter(|a| {
+                a.metadata
+                    .get(DISTRIBUTION_KEY)
+                    .and_then(MetaValue::as_bool)
+                    .unwrap_or(false)
+            })
+            .map(|a| a.id.as_str())
+            .collect();
+        let account_type = |id: &str| {
+            accounts
+                .iter()
+                .find(|a| a.id == id)
+                .map(|a| a.account_type.clone())
+                .ok_or_else(|| LedgerError::AccountNotFound(id.to_string()))
+        };
+        account_type(&retained_earnings_account_id)?;
+
+        let opening_balance = match start_date.pred_opt() {
+            Some(day_before) => {
+                let trial_balance = self.get_trial_balance(day_before).await?;
+                trial_balance
+                    .balances
+                    .values()
+                    .map(|line| match line.account.account_type {
+                        AccountType::Income => line.signed_balance(),
+                        AccountType::Expense => -line.signed_balance(),
+                        AccountType::Equity
+                            if line.account.id == retained_earnings_account_id
+                                || distribution_accounts.contains(line.account.id.as_str()) =>
+                        {
+                            line.signed_balance()
+                        }
+                        _ => BigDecimal::zero(),
+                    })
+                    .sum()
+            }
+            None => BigDecimal::zero(),
+        };
+
+        let mut net_income = BigDecimal::zero();
+        let mut distributions = BigDecimal::zero();
+        let mut other_adjustments = BigDecimal::zero();
+        for transaction in self
+            .get_transactions(Some(start_date), Some(end_date))
+            .await?
+        {
+            if is_closing_entry(&transaction) {
+                continue;
+            }
+            for entry in &transaction.entries {
+                let entry_account_type = account_type(&entry.account_id)?;
+                let effect = entry_account_type.balance_effect(&entry.entry_type, &entry.amount);
+                match entry_account_type {
+                    AccountType::Income => net_income += effect,
+                    AccountType::Expense => net_income -= effect,
+                    AccountType::Equity
+                        if distribution_accounts.contains(entry.account_id.as_str()) =>
+                    {
+                        distributions -= effect
+                    }
+                    AccountType::Equity if entry.account_id == retained_earnings_account_id => {
+                        other_adjustments += effect
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let closing_balance = &opening_balance + &net_income - &distributions + &other_adjustments;
+        Ok(RetainedEarningsStatement {
+            start_date,
+            end_date,
+            retained_earnings_account_id,
+            opening_balance,
+            net_income,
+            distributions,
+            other_adjustments,
+            closing_balance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, TransactionBuilder};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_retained_earnings_statement_across_closing() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        assert_eq!(ledger.retained_earnings_account(), Some("3200"));
+        ledger
+            .create_account(
+                "3300".to_string(),
+                "Dividends".to_string(),
+                AccountType::Equity,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger.designate_distribution_account("3300").await.unwrap();
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        let sale = |id: &str, date, amount| {
+            TransactionBuilder::new(id.to_string(), date, "Sale".to_string())
+                .debit("1000".to_string(), BigDecimal::from(amount), None)
+                .credit("4000".to_string(), BigDecimal::from(amount), None)
+                .build()
+                .unwrap()
+        };
+        ledger
+            .record_transaction(sale("fy24", day(2024, 1, 15), 3000))
+            .await
+            .unwrap();
+        ledger
+            .post_closing_entries("close-fy24".to_string(), day(2024, 3, 31), "3200")
+            .await
+            .unwrap();
+        ledger
+            .record_transaction(sale("fy25", day(2024, 6, 1), 5000))
+            .await
+            .unwrap();
+        ledger
+            .record_transaction(
+                patterns::create_expense_payment(
+                    "rent".to_string(),
+                    day(2024, 6, 2),
+                    "Rent".to_string(),
+                    "6000".to_string(),
+                    "1000".to_string(),
+                    BigDecimal::from(1200),
+                )
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+        let dividend = TransactionBuilder::new(
+            "dividend".to_string(),
+            day(2024, 9, 30),
+            "Interim dividend".to_string(),
+        )
+        .debit("3300".to_string(), BigDecimal::from(800), None)
+        .credit("1000".to_string(), BigDecimal::from(800), None)
+        .build()
+        .unwrap();
+        ledger.record_transaction(dividend).await.unwrap();
+
+        let statement = ledger
+            .generate_retained_earnings_statement(day(2024, 4, 1), day(2025, 3, 31))
+            .await
+            .unwrap();
+        assert_eq!(statement.opening_balance, BigDecimal::from(3000));
+        assert_eq!(statement.net_income, BigDecimal::from(3800));
+        assert_eq!(statement.distributions, BigDecimal::from(800));
+        assert_eq!(statement.closing_balance, BigDecimal::from(6000));
+
+        // The balance sheet carries unclosed profit in the real account
+        let balance_sheet = ledger
+            .generate_balance_sheet(day(2025, 3, 31))
+            .await
+            .unwrap();
+        assert!(balance_sheet.is_balanced);
+        assert!(balance_sheet
+            .equity
+            .iter()
+            .all(|line| line.account.id != "net_income"));
+        let retained = balance_sheet
+            .equity
+            .iter()
+            .find(|line| line.account.id == "3200")
+            .unwrap();
+        assert_eq!(retained.signed_balance(), BigDecimal::from(6800));
+        assert_eq!(balance_sheet.current_earnings, None);
+
+        ledger.set_net_income_presentation(NetIncomePresentation::SeparateLine);
+        let balance_sheet = ledger
+            .generate_balance_sheet(day(2025, 3, 31))
+            .await
+            .unwrap();
+        assert!(balance_sheet.is_balanced);
+        assert_eq!(balance_sheet.current_earnings, Some(BigDecimal::from(3800)));
+    }
+}
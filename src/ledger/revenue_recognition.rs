@@ -0,0 +1,406 @@
+//! Revenue recognition for long-running projects
+//!
+//! Revenue on a contract is earned as the work progresses, which rarely
+//! matches when it is billed. A [`RevenueSchedule`] works out the revenue
+//! earned on a [`Project`](crate::ledger::Project) by a date, either by
+//! percentage of completion (costs incurred against the cost budget) or by
+//! milestones reached, and compares it with the project revenue already
+//! booked by invoices. The difference is held on the balance sheet: earned
+//! but not billed as unbilled revenue (a contract asset), billed ahead of
+//! the work as deferred revenue (a contract liability).
+//!
+//! Each period's journal moves both balances to where they should be on the
+//! period end, so running a period twice posts nothing the second time.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::{Ledger, TransactionBuilder, PROJECT_DIMENSION};
+use crate::traits::*;
+use crate::types::*;
+
+/// A stage of a contract whose completion earns a fixed amount of revenue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Milestone {
+    pub id: String,
+    pub description: String,
+    pub amount: BigDecimal,
+    /// Day the milestone was reached, if it has been
+    pub completed_on: Option<NaiveDate>,
+}
+
+/// How revenue is earned over a contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RecognitionMethod {
+    /// In proportion to project costs incurred against the cost budget,
+    /// applied to the revenue budget
+    PercentageOfCompletion,
+    /// As each milestone is reached
+    Milestones(Vec<Milestone>),
+}
+
+/// Accounts revenue recognition posts to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RecognitionAccounts {
+    pub revenue_account_id: String,
+    /// Contract asset for revenue earned but not yet billed
+    pub unbilled_account_id: String,
+    /// Contract liability for billing ahead of revenue earned
+    pub deferred_account_id: String,
+}
+
+/// How a project's revenue is recognised
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RevenueSchedule {
+    pub id: String,
+    pub project_id: String,
+    pub method: RecognitionMethod,
+    pub accounts: RecognitionAccounts,
+}
+
+/// Where a contract stands on a date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ContractPosition {
+    pub project_id: String,
+    pub as_of: NaiveDate,
+    /// Share of the work done, from 0 to 1
+    pub progress: BigDecimal,
+    /// Revenue earned to date
+    pub earned: BigDecimal,
+    /// Revenue billed to date
+    pub billed: BigDecimal,
+    /// Unbilled revenue the contract asset should hold
+    pub unbilled: BigDecimal,
+    /// Deferred revenue the contract liability should hold
+    pub deferred: BigDecimal,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Revenue earned and billed on a schedule's project by `as_of`
+    pub async fn contract_position(
+        &self,
+        schedule: &RevenueSchedule,
+        as_of: NaiveDate,
+    ) -> LedgerResult<ContractPosition> {
+        let project = self.get_project(&schedule.project_id).ok_or_else(|| {
+            LedgerError::Validation(format!("Project not found: {}", schedule.project_id))
+        })?;
+        let accounts = self.account_types().await?;
+        let (revenue, costs) = self
+            .project_results(&project.id, project.start_date, as_of, &accounts)
+            .await?;
+
+        let (progress, earned) = match &schedule.method {
+            RecognitionMethod::PercentageOfCompletion => {
+                if !project.budget.cost.is_positive() {
+                    return Err(LedgerError::Validation(format!(
+                        "Project {} needs a cost budget for percentage of completion",
+                        project.id
+                    )));
+                }
+                let progress = (costs.total() / &project.budget.cost)
+                    .min(BigDecimal::from(1))
+                    .max(BigDecimal::zero());
+                let earned = (&project.budget.revenue * &progress).round(2);
+                (progress, earned)
+            }
+            RecognitionMethod::Milestones(milestones) => {
+                let total: BigDecimal = milestones.iter().map(|m| &m.amount).sum();
+                let earned: BigDecimal = milestones
+                    .iter()
+                    .filter(|m| m.completed_on.is_some_and(|date| date <= as_of))
+                    .map(|m| &m.amount)
+                    .sum();
+                let progress = if total.is_zero() {
+                    BigDecimal::zero()
+                } else {
+                    &earned / &total
+                };
+                (progress, earned)
+            }
+        };
+
+        // Project revenue booked so far includes earlier recognition entries;
+        // taking them out leaves what was billed
+        let unbilled = self
+            .project_balance(&schedule.accounts.unbilled_account_id, &project.id, as_of)
+            .await?;
+        let deferred = self
+            .project_balance(&schedule.accounts.deferred_account_id, &project.id, as_of)
+            .await?;
+        let billed = revenue - (unbilled - deferred);
+        let difference = &earned - &billed;
+        Ok(ContractPosition {
+            project_id: project.id.clone(),
+            as_of,
+            progress,
+            unbilled: difference.clone().max(BigDecimal::zero()),
+            deferred: (-difference).max(BigDecimal::zero()),
+            earned,
+            billed,
+        })
+    }
+
+    /// Post the recognition journal for the period ending `as_of`
+    ///
+    /// Returns `None` when the contract balances are already where they
+    /// should be.
+    pub async fn recognize_contract_revenue(
+        &mut self,
+        schedule: &RevenueSchedule,
+        as_of: NaiveDate,
+    ) -> LedgerResult<Option<Transaction>> {
+        let position = self.contract_position(schedule, as_of).await?;
+        let accounts = &schedule.accounts;
+        let unbilled_change = &position.unbilled
+            - self
+                .project_balance(&accounts.unbilled_account_id, &position.project_id, as_of)
+                .await?;
+        let deferred_change = &position.deferred
+            - self
+                .project_balance(&accounts.deferred_account_id, &position.project_id, as_of)
+                .await?;
+        if unbilled_change.is_zero() && deferred_change.is_zero() {
+            return Ok(None);
+        }
+
+        let line = |account_id: &String, amount: &BigDecimal, debit: bool, description: &str| {
+            let entry_type = if debit == amount.is_positive() {
+                EntryType::Debit
+            } else {
+                EntryType::Credit
+            };
+            Entry::new(
+                account_id.clone(),
+                entry_type,
+                amount.abs(),
+                Some(description.to_string()),
+            )
+            .with_dimension(PROJECT_DIMENSION.to_string(), position.project_id.clone())
+        };
+        let revenue_change = &unbilled_change - &deferred_change;
+        let mut builder = TransactionBuilder::new(
+            format!("{}-{}", schedule.id, as_of),
+            as_of,
+            format!("Revenue recognition for project {}", position.project_id),
+        )
+        .kind(TransactionKind::Adjustment)
+        .reference(schedule.id.clone());
+        for (account_id, amount, debit, description) in [
+            (
+                &accounts.unbilled_account_id,
+                &unbilled_change,
+                true,
+                "Unbilled revenue",
+            ),
+            (
+                &accounts.deferred_account_id,
+                &deferred_change,
+                false,
+                "Deferred revenue",
+            ),
+            (
+                &accounts.revenue_account_id,
+                &revenue_change,
+                false,
+                "Revenue recognised",
+            ),
+        ] {
+            if !amount.is_zero() {
+                builder = builder.entry(line(account_id, amount, debit, description));
+            }
+        }
+        let transaction = builder.build()?;
+        let transaction = self.record_transaction(transaction).await?;
+        Ok(Some(transaction))
+    }
+
+    /// Post the recognition journals for a run of period ends, in order
+    pub async fn run_revenue_schedule(
+        &mut self,
+        schedule: &RevenueSchedule,
+        period_ends: &[NaiveDate],
+    ) -> LedgerResult<Vec<Transaction>> {
+        let mut period_ends = period_ends.to_vec();
+        period_ends.sort();
+        let mut posted = Vec::new();
+        for as_of in period_ends {
+            if let Some(transaction) = self.recognize_contract_revenue(schedule, as_of).await? {
+                posted.push(transaction);
+            }
+        }
+        Ok(posted)
+    }
+
+    /// Balance of a project's entries in an account, in the account's
+    /// normal direction
+    async fn project_balance(
+        &self,
+        account_id: &str,
+        project_id: &str,
+        as_of: NaiveDate,
+    ) -> LedgerResult<BigDecimal> {
+        let account_type = self
+            .account_manager
+            .get_account_required(account_id)
+            .await?
+            .account_type;
+        let filter = EntryFilter {
+            account_id: Some(account_id.to_string()),
+            end_date: Some(as_of),
+            dimension: Some((PROJECT_DIMENSION.to_string(), project_id.to_string())),
+            ..Default::default()
+        };
+        Ok(self
+            .get_entries(&filter)
+            .await?
+            .iter()
+            .map(|row| account_type.balance_effect(&row.entry.entry_type, &row.entry.amount))
+            .sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{Project, ProjectBudget};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_percentage_of_completion_and_milestones() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("1260", "Unbilled Revenue", AccountType::Asset),
+            ("2460", "Deferred Revenue", AccountType::Liability),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let mut bridge = Project::new("P-1".to_string(), "Footbridge".to_string(), date(1, 1));
+        bridge.budget = ProjectBudget {
+            revenue: BigDecimal::from(100000),
+            cost: BigDecimal::from(80000),
+            ..Default::default()
+        };
+        ledger.save_project(bridge.clone()).await.unwrap();
+        let accounts = RecognitionAccounts {
+            revenue_account_id: "4000".to_string(),
+            unbilled_account_id: "1260".to_string(),
+            deferred_account_id: "2460".to_string(),
+        };
+        let schedule = RevenueSchedule {
+            id: "RR-1".to_string(),
+            project_id: "P-1".to_string(),
+            method: RecognitionMethod::PercentageOfCompletion,
+            accounts: accounts.clone(),
+        };
+
+        let post = |id: &str, date, debit: &str, credit: &str, amount: i32| {
+            TransactionBuilder::new(id.to_string(), date, id.to_string())
+                .entry(bridge.charge(Entry::debit(
+                    debit.to_string(),
+                    BigDecimal::from(amount),
+                    None,
+                )))
+                .entry(bridge.charge(Entry::credit(
+                    credit.to_string(),
+                    BigDecimal::from(amount),
+                    None,
+                )))
+                .build()
+                .unwrap()
+        };
+        // January: a quarter of the costs, nothing billed
+        ledger
+            .record_transaction(post("C1", date(1, 20), "5000", "1000", 20000))
+            .await
+            .unwrap();
+        let journals = ledger
+            .run_revenue_schedule(&schedule, &[date(1, 31), date(1, 31)])
+            .await
+            .unwrap();
+        assert_eq!(journals.len(), 1);
+        assert_eq!(
+            ledger.get_account_balance("1260", None).await.unwrap(),
+            BigDecimal::from(25000)
+        );
+
+        // February: billed 60,000 against half the work
+        ledger
+            .record_transaction(post("C2", date(2, 10), "5000", "1000", 20000))
+            .await
+            .unwrap();
+        ledger
+            .record_transaction(post("B1", date(2, 15), "1200", "4000", 60000))
+            .await
+            .unwrap();
+        ledger
+            .recognize_contract_revenue(&schedule, date(2, 29))
+            .await
+            .unwrap();
+        let position = ledger
+            .contract_position(&schedule, date(2, 29))
+            .await
+            .unwrap();
+        assert_eq!(position.earned, BigDecimal::from(50000));
+        assert_eq!(position.billed, BigDecimal::from(60000));
+        assert!(ledger
+            .get_account_balance("1260", None)
+            .await
+            .unwrap()
+            .is_zero());
+        assert_eq!(
+            ledger.get_account_balance("2460", None).await.unwrap(),
+            BigDecimal::from(10000)
+        );
+        assert_eq!(
+            ledger.get_account_balance("4000", None).await.unwrap(),
+            BigDecimal::from(50000)
+        );
+
+        // Milestones on a second project
+        ledger
+            .save_project(Project::new(
+                "P-2".to_string(),
+                "Survey".to_string(),
+                date(1, 1),
+            ))
+            .await
+            .unwrap();
+        let milestones = RevenueSchedule {
+            id: "RR-2".to_string(),
+            project_id: "P-2".to_string(),
+            method: RecognitionMethod::Milestones(vec![
+                Milestone {
+                    id: "M1".to_string(),
+                    description: "Site survey".to_string(),
+                    amount: BigDecimal::from(4000),
+                    completed_on: Some(date(3, 5)),
+                },
+                Milestone {
+                    id: "M2".to_string(),
+                    description: "Report".to_string(),
+                    amount: BigDecimal::from(6000),
+                    completed_on: None,
+                },
+            ]),
+            accounts,
+        };
+        let position = ledger
+            .contract_position(&milestones, date(3, 31))
+            .await
+            .unwrap();
+        assert_eq!(position.progress, BigDecimal::from(4) / 10);
+        assert_eq!(position.unbilled, BigDecimal::from(4000));
+    }
+}
@@ -0,0 +1,327 @@
+//! Balance sheet by segment (branch, division or business line)
+//!
+//! Accounts are assigned to a segment through account metadata; sub-accounts
+//! inherit the segment of their nearest marked ancestor, so marking a
+//! branch's group account is enough. Accounts that hold balances between
+//! segments (inter-branch current accounts) can be marked as intersegment:
+//! they appear in their own segment's column and are cancelled in the
+//! eliminations column, so the total column shows the business as a whole.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Account metadata key naming the account's segment
+const SEGMENT_KEY: &str = "segment";
+/// Account metadata key marking a balance held with another segment
+const INTERSEGMENT_KEY: &str = "intersegment";
+/// Column for accounts with no segment
+pub const UNALLOCATED_SEGMENT: &str = "unallocated";
+
+/// Value of `key` on the account or its nearest ancestor that has it
+fn inherited<'a>(
+    accounts: &'a HashMap<String, Account>,
+    account_id: &str,
+    key: &str,
+) -> Option<&'a MetaValue> {
+    let mut current = accounts.get(account_id);
+    // Bounded by the number of accounts in case of a cycle
+    for _ in 0..accounts.len() {
+        let account = current?;
+        if let Some(value) = account.metadata.get(key) {
+            return Some(value);
+        }
+        current = account.parent_id.as_ref().and_then(|id| accounts.get(id));
+    }
+    None
+}
+
+/// One account's balance across the segment columns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SegmentBalanceLine {
+    pub account_id: String,
+    pub account_name: String,
+    pub account_type: AccountType,
+    /// Segment column the balance falls in
+    pub segment: String,
+    /// Balance in the account's normal direction
+    pub balance: BigDecimal,
+    /// Amount cancelled in the eliminations column
+    pub elimination: BigDecimal,
+    /// Balance after eliminations
+    pub total: BigDecimal,
+}
+
+/// Section totals of one column
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SegmentColumn {
+    pub total_assets: BigDecimal,
+    pub total_liabilities: BigDecimal,
+    /// Net income not yet closed, included in `total_equity`
+    pub current_earnings: BigDecimal,
+    pub total_equity: BigDecimal,
+}
+
+impl SegmentColumn {
+    fn add(&mut self, account_type: &AccountType, balance: &BigDecimal) {
+        match account_type {
+            AccountType::Asset => self.total_assets += balance,
+            AccountType::Liability => self.total_liabilities += balance,
+            AccountType::Equity => self.total_equity += balance,
+            AccountType::Income => {
+                self.current_earnings += balance;
+                self.total_equity += balance;
+            }
+            AccountType::Expense => {
+                self.current_earnings -= balance;
+                self.total_equity -= balance;
+            }
+        }
+    }
+
+    /// Whether assets equal liabilities plus equity within the column
+    pub fn is_balanced(&self) -> bool {
+        self.total_assets == &self.total_liabilities + &self.total_equity
+    }
+}
+
+/// Balance sheet with a column per segment, an eliminations column and a
+/// total column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SegmentBalanceSheet {
+    pub as_of_date: NaiveDate,
+    /// Balance sheet accounts, ordered by account ID
+    pub lines: Vec<SegmentBalanceLine>,
+    /// Totals per segment, including [`UNALLOCATED_SEGMENT`] when any
+    /// account has no segment
+    pub segments: BTreeMap<String, SegmentColumn>,
+    pub eliminations: SegmentColumn,
+    pub total: SegmentColumn,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Assign an account, and the sub-accounts under it, to a segment
+    ///
+    /// `intersegment` marks balances held with other segments, which are
+    /// eliminated in the total column.
+    pub async fn set_account_segment(
+        &mut self,
+        account_id: &str,
+        segment: &str,
+        intersegment: bool,
+    ) -> LedgerResult<Account> {
+        let mut account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        if segment.trim().is_empty() {
+            return Err(LedgerError::Validation(
+                "Segment name cannot be empty".to_string(),
+            ));
+        }
+        account
+            .metadata
+            .insert(SEGMENT_KEY.to_string(), segment.into());
+        account
+            .metadata
+            .insert(INTERSEGMENT_KEY.to_string(), intersegment.into());
+        account.updated_at = self.clock.now();
+        self.update_account(&account).await?;
+        Ok(account)
+    }
+
+    /// Segment an account falls in: its own or its nearest marked ancestor's
+    pub async fn account_segment(&self, account_id: &str) -> LedgerResult<Option<String>> {
+        let accounts = self.accounts_by_id().await?;
+        if !accounts.contains_key(account_id) {
+            return Err(LedgerError::AccountNotFound(account_id.to_string()));
+        }
+        Ok(inherited(&accounts, account_id, SEGMENT_KEY)
+            .and_then(MetaValue::as_str)
+            .map(str::to_string))
+    }
+
+    async fn accounts_by_id(&self) -> LedgerResult<HashMap<String, Account>> {
+        Ok(self
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|a| (a.id.clone(), a))
+            .collect())
+    }
+
+    /// Generate a balance sheet with a column per segment
+    ///
+    /// Income and expense balances are carried to each column's current
+    /// earnings, so a column balances whenever the segment's postings do.
+    pub async fn generate_balance_sheet_by_segment(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<SegmentBalanceSheet> {
+        let accounts = self.accounts_by_id().await?;
+
+        let mut lines = Vec::new();
+        let mut segments: BTreeMap<String, SegmentColumn> = BTreeMap::new();
+        let mut eliminations = SegmentColumn::default();
+        let mut total = SegmentColumn::default();
+        let mut balances: Vec<AccountBalance> = self
+            .get_account_balances_by_type(as_of_date)
+            .await?
+            .into_values()
+            .flatten()
+            .collect();
+        balances.sort_by(|a, b| a.account.id.cmp(&b.account.id));
+
+        for line in balances {
+            let account = &line.account;
+            let balance = line.signed_balance();
+            let segment = inherited(&accounts, &account.id, SEGMENT_KEY)
+                .and_then(MetaValue::as_str)
+                .unwrap_or(UNALLOCATED_SEGMENT)
+                .to_string();
+            let intersegment = inherited(&accounts, &account.id, INTERSEGMENT_KEY)
+                .and_then(MetaValue::as_bool)
+                .unwrap_or(false);
+            let elimination = if intersegment {
+                -balance.clone()
+            } else {
+                BigDecimal::zero()
+            };
+
+            segments
+                .entry(segment.clone())
+                .or_default()
+                .add(&account.account_type, &balance);
+            eliminations.add(&account.account_type, &elimination);
+            let line_total = &balance + &elimination;
+            total.add(&account.account_type, &line_total);
+
+            if matches!(
+                account.account_type,
+                AccountType::Asset | AccountType::Liability | AccountType::Equity
+            ) {
+                lines.push(SegmentBalanceLine {
+                    account_id: account.id.clone(),
+                    account_name: account.name.clone(),
+                    account_type: account.account_type.clone(),
+                    segment,
+                    balance,
+                    elimination,
+                    total: line_total,
+                });
+            }
+        }
+
+        Ok(SegmentBalanceSheet {
+            as_of_date,
+            lines,
+            segments,
+            eliminations,
+            total,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_balance_sheet_by_branch() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        for (id, parent, account_type) in [
+            ("mumbai", None, AccountType::Asset),
+            ("mumbai-bank", Some("mumbai"), AccountType::Asset),
+            ("mumbai-due-from-pune", Some("mumbai"), AccountType::Asset),
+            ("pune-bank", None, AccountType::Asset),
+            ("pune-due-to-mumbai", None, AccountType::Liability),
+            ("pune-sales", None, AccountType::Income),
+            ("capital", None, AccountType::Equity),
+        ] {
+            ledger
+                .create_account(
+                    id.to_string(),
+                    id.to_string(),
+                    account_type,
+                    parent.map(str::to_string),
+                )
+                .await
+                .unwrap();
+        }
+        ledger
+            .set_account_segment("mumbai", "Mumbai", false)
+            .await
+            .unwrap();
+        ledger
+            .set_account_segment("mumbai-due-from-pune", "Mumbai", true)
+            .await
+            .unwrap();
+        for id in ["pune-bank", "pune-sales"] {
+            ledger.set_account_segment(id, "Pune", false).await.unwrap();
+        }
+        ledger
+            .set_account_segment("pune-due-to-mumbai", "Pune", true)
+            .await
+            .unwrap();
+        assert_eq!(
+            ledger.account_segment("mumbai-bank").await.unwrap(),
+            Some("Mumbai".to_string())
+        );
+
+        let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        let amount = BigDecimal::from;
+        for builder in [
+            TransactionBuilder::new("capital".to_string(), date, "Capital".to_string())
+                .debit("mumbai-bank".to_string(), amount(10000), None)
+                .credit("capital".to_string(), amount(10000), None),
+            // Mumbai funds Pune's account
+            TransactionBuilder::new("fund-pune".to_string(), date, "Branch funding".to_string())
+                .debit("mumbai-due-from-pune".to_string(), amount(3000), None)
+                .credit("mumbai-bank".to_string(), amount(3000), None)
+                .debit("pune-bank".to_string(), amount(3000), None)
+                .credit("pune-due-to-mumbai".to_string(), amount(3000), None),
+            TransactionBuilder::new("pune-sale".to_string(), date, "Sale".to_string())
+                .debit("pune-bank".to_string(), amount(500), None)
+                .credit("pune-sales".to_string(), amount(500), None),
+        ] {
+            ledger
+                .record_transaction(builder.build().unwrap())
+                .await
+                .unwrap();
+        }
+
+        let sheet = ledger
+            .generate_balance_sheet_by_segment(date)
+            .await
+            .unwrap();
+        let pune = &sheet.segments["Pune"];
+        assert_eq!(pune.total_assets, amount(3500));
+        assert_eq!(pune.current_earnings, amount(500));
+        assert!(pune.is_balanced());
+        assert_eq!(sheet.segments["Mumbai"].total_assets, amount(10000));
+        assert_eq!(
+            sheet.segments[UNALLOCATED_SEGMENT].total_equity,
+            amount(10000)
+        );
+
+        assert_eq!(sheet.eliminations.total_assets, amount(-3000));
+        assert_eq!(sheet.total.total_assets, amount(10500));
+        assert!(sheet.total.is_balanced());
+        let due_from = sheet
+            .lines
+            .iter()
+            .find(|l| l.account_id == "mumbai-due-from-pune")
+            .unwrap();
+        assert!(due_from.total.is_zero());
+    }
+}
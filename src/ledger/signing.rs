@@ -0,0 +1,316 @@
+//! Digital signatures on finalized documents
+//!
+//! With a [`Signer`] set, issuing an invoice and posting closing entries
+//! compute a canonical digest of the finalized document and sign it. The
+//! [`DocumentSignature`] is kept with the document: on the invoice itself,
+//! and under [`SIGNATURE_KEY`] in the closing entry's metadata. Verifying
+//! recomputes the digest, so any later change to the signed content is
+//! detected even before the signature itself is checked.
+//!
+//! The digest covers what the document states, not its lifecycle: payments
+//! and credit notes against an invoice leave its signature valid.
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::ledger::Ledger;
+use crate::receivables::Invoice;
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key holding the signature of a closing entry
+pub const SIGNATURE_KEY: &str = "signature";
+
+/// Signature over a document's canonical digest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DocumentSignature {
+    /// Key reported by [`Signer::key_id`]
+    pub key_id: String,
+    /// `sha256:` followed by the hex digest of the signed content
+    pub digest: String,
+    /// Signature over `digest`, as produced by [`Signer::sign`]
+    pub signature: String,
+    /// When the document was signed, by the ledger clock
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub signed_at: DateTime<Utc>,
+}
+
+fn canonical_digest(content: &serde_json::Value) -> LedgerResult<String> {
+    let bytes = serde_json::to_vec(content)
+        .map_err(|e| LedgerError::storage_with_source("Cannot encode document", false, e))?;
+    let digest = Sha256::digest(&bytes);
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("sha256:{}", hex))
+}
+
+/// Normalized text of an amount, so `1000.00` and `1000` digest alike
+fn amount(value: &BigDecimal) -> String {
+    value.normalized().to_string()
+}
+
+/// Canonical digest of what an invoice bills
+///
+/// Covers the parties, dates, terms, line items and tax, with amounts
+/// normalized; status, attachments and amounts paid, credited or refunded
+/// are left out.
+pub fn invoice_digest(invoice: &Invoice) -> LedgerResult<String> {
+    let lines: Vec<serde_json::Value> = invoice
+        .gst
+        .line_items
+        .iter()
+        .map(|item| {
+            let calculation = &item.gst_calculation;
+            json!({
+                "description": item.description,
+                "quantity": amount(&item.quantity),
+                "unit_price": amount(&item.unit_price),
+                "base_amount": amount(&calculation.base_amount),
+                "rates": [
+                    amount(&calculation.gst_rate.cgst_rate),
+                    amount(&calculation.gst_rate.sgst_rate),
+                    amount(&calculation.gst_rate.igst_rate),
+                ],
+                "cgst_amount": amount(&calculation.cgst_amount),
+                "sgst_amount": amount(&calculation.sgst_amount),
+                "igst_amount": amount(&calculation.igst_amount),
+                "total_amount": amount(&calculation.total_amount),
+            })
+        })
+        .collect();
+    canonical_digest(&json!({
+        "document": "invoice",
+        "id": invoice.id,
+        "customer_id": invoice.customer_id,
+        "date": invoice.date,
+        "due_date": invoice.due_date,
+        "payment_terms": invoice.payment_terms,
+        "place_of_supply": invoice.place_of_supply,
+        "lines": lines,
+        "grand_total": amount(&invoice.gst.grand_total),
+        "cess_amount": amount(&invoice.cess_amount),
+        "quotation_id": invoice.quotation_id,
+        "transaction_id": invoice.transaction_id,
+    }))
+}
+
+/// Canonical digest of a closing entry's lines
+///
+/// Amounts are normalized, so storage backends that change the scale of a
+/// decimal do not invalidate the signature.
+pub fn closing_entry_digest(transaction: &Transaction) -> LedgerResult<String> {
+    let entries: Vec<serde_json::Value> = transaction
+        .entries
+        .iter()
+        .map(|entry| {
+            json!({
+                "account_id": entry.account_id,
+                "entry_type": entry.entry_type,
+                "amount": amount(&entry.amount),
+                "description": entry.description,
+            })
+        })
+        .collect();
+    canonical_digest(&json!({
+        "document": "closing_entry",
+        "id": transaction.id,
+        "date": transaction.date,
+        "description": transaction.description,
+        "entries": entries,
+    }))
+}
+
+/// Signature stored on a closing entry, if any
+pub fn closing_signature(transaction: &Transaction) -> Option<DocumentSignature> {
+    let value = serde_json::to_value(transaction.metadata.get(SIGNATURE_KEY)?).ok()?;
+    serde_json::from_value(value).ok()
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Sign issued invoices and closing entries with `signer` from now on
+    pub fn set_signer(&mut self, signer: Option<Arc<dyn Signer>>) {
+        self.signer = signer;
+    }
+
+    /// Sign a digest with the configured signer, if any
+    pub(crate) fn sign_digest(&self, digest: String) -> LedgerResult<Option<DocumentSignature>> {
+        let Some(signer) = &self.signer else {
+            return Ok(None);
+        };
+        Ok(Some(DocumentSignature {
+            key_id: signer.key_id(),
+            signature: signer.sign(&digest)?,
+            digest,
+            signed_at: self.clock.now(),
+        }))
+    }
+
+    /// Attach a signature of a closing entry to its metadata
+    pub(crate) fn sign_closing_entry(&self, transaction: &mut Transaction) -> LedgerResult<()> {
+        let Some(signature) = self.sign_digest(closing_entry_digest(transaction)?)? else {
+            return Ok(());
+        };
+        let value = serde_json::to_value(&signature)
+            .and_then(serde_json::from_value::<MetaValue>)
+            .map_err(|e| LedgerError::storage_with_source("Cannot encode signature", false, e))?;
+        transaction
+            .metadata
+            .insert(SIGNATURE_KEY.to_string(), value);
+        Ok(())
+    }
+
+    /// Check a signature against a document's current digest
+    fn verify_signature(
+        &self,
+        signature: Option<&DocumentSignature>,
+        digest: String,
+    ) -> LedgerResult<bool> {
+        let signer = self.signer.as_ref().ok_or_else(|| {
+            LedgerError::Validation("No signer is configured to verify with".to_string())
+        })?;
+        match signature {
+            Some(signature) if signature.digest == digest => {
+                signer.verify(&signature.key_id, &digest, &signature.signature)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Whether an invoice carries a valid signature of its current content
+    ///
+    /// Unsigned invoices and invoices changed since signing are not valid.
+    /// Fails with [`LedgerError::Validation`] when no signer is configured.
+    pub fn verify_invoice_signature(&self, invoice: &Invoice) -> LedgerResult<bool> {
+        self.verify_signature(invoice.signature.as_ref(), invoice_digest(invoice)?)
+    }
+
+    /// Whether a closing entry carries a valid signature of its current lines
+    ///
+    /// Fails with [`LedgerError::Validation`] when no signer is configured.
+    pub fn verify_closing_signature(&self, transaction: &Transaction) -> LedgerResult<bool> {
+        self.verify_signature(
+            closing_signature(transaction).as_ref(),
+            closing_entry_digest(transaction)?,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{GstAccounts, TransactionBuilder};
+    use crate::receivables::SalesAccounts;
+    use crate::tax::{GstInvoice, GstLineItem, GstRate};
+    use crate::utils::MemoryStorage;
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+
+    /// Keyed hash standing in for a real signature scheme
+    struct KeyedHashSigner(&'static str);
+
+    impl Signer for KeyedHashSigner {
+        fn key_id(&self) -> String {
+            "test-key".to_string()
+        }
+
+        fn sign(&self, digest: &str) -> LedgerResult<String> {
+            canonical_digest(&json!([self.0, digest]))
+        }
+
+        fn verify(&self, key_id: &str, digest: &str, signature: &str) -> LedgerResult<bool> {
+            Ok(key_id == "test-key" && self.sign(digest)? == signature)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signed_invoice_and_closing_entry() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name) in [("2310", "CGST Output"), ("2320", "SGST Output")] {
+            ledger
+                .create_account(
+                    id.to_string(),
+                    name.to_string(),
+                    AccountType::Liability,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        ledger.set_signer(Some(Arc::new(KeyedHashSigner("secret"))));
+        let accounts = SalesAccounts {
+            receivables_account_id: "1200".to_string(),
+            revenue_account_id: "4000".to_string(),
+            output_tax: GstAccounts {
+                cgst_account_id: "2310".to_string(),
+                sgst_account_id: "2320".to_string(),
+                igst_account_id: "2330".to_string(),
+                cess_account_id: None,
+            },
+        };
+        let line = GstLineItem::new(
+            "Consulting".to_string(),
+            BigDecimal::from(1),
+            BigDecimal::from(1000),
+            GstRate::intra_state(BigDecimal::from(18)),
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let mut invoice = Invoice::new(
+            "INV-9".to_string(),
+            "acme".to_string(),
+            date,
+            GstInvoice::new(vec![line]),
+            None,
+        )
+        .unwrap();
+        ledger.issue_invoice(&mut invoice, &accounts).await.unwrap();
+        let signature = invoice.signature.clone().unwrap();
+        assert_eq!(signature.digest, invoice_digest(&invoice).unwrap());
+        assert!(ledger.verify_invoice_signature(&invoice).unwrap());
+
+        // Payments do not touch the signed content; edits do
+        invoice.record_payment(&BigDecimal::from(500)).unwrap();
+        assert!(ledger.verify_invoice_signature(&invoice).unwrap());
+        // Neither do attachments or a backend rescaling the amounts
+        let mut rescaled = invoice.clone();
+        rescaled.gst.grand_total = rescaled.gst.grand_total.with_scale(4);
+        rescaled.gst.line_items[0].unit_price = BigDecimal::from_str("1000.00").unwrap();
+        rescaled.gst.attach(Attachment::new(
+            "A1".to_string(),
+            "copy.pdf".to_string(),
+            "application/pdf".to_string(),
+            "blob://copy".to_string(),
+            "sha256:00".to_string(),
+        ));
+        assert!(ledger.verify_invoice_signature(&rescaled).unwrap());
+        let mut altered = invoice.clone();
+        altered.customer_id = "someone-else".to_string();
+        assert!(!ledger.verify_invoice_signature(&altered).unwrap());
+
+        let closing = ledger
+            .post_closing_entries("CL-2024".to_string(), date, "3200")
+            .await
+            .unwrap();
+        let stored = ledger.get_transaction(&closing.id).await.unwrap().unwrap();
+        assert!(closing_signature(&stored).is_some());
+        assert!(ledger.verify_closing_signature(&stored).unwrap());
+
+        ledger.set_signer(Some(Arc::new(KeyedHashSigner("forged"))));
+        assert!(!ledger.verify_closing_signature(&stored).unwrap());
+        ledger.set_signer(None);
+        assert!(ledger.verify_closing_signature(&stored).is_err());
+
+        let unsigned = TransactionBuilder::new("T".to_string(), date, "Plain".to_string())
+            .debit("1000".to_string(), BigDecimal::from(1), None)
+            .credit("3000".to_string(), BigDecimal::from(1), None)
+            .build()
+            .unwrap();
+        assert!(closing_signature(&unsigned).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+//! Suspense account handling for bank lines that cannot be classified on import
+
+use bigdecimal::{BigDecimal, Zero};
+
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::reconciliation::{BankStatementLine, SuspenseItem};
+use crate::traits::*;
+use crate::types::*;
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Set the account that unclassified imported amounts are posted against
+    pub fn set_suspense_account(&mut self, account_id: Option<String>) {
+        self.suspense_account_id = account_id;
+    }
+
+    /// Get the configured suspense account
+    pub fn suspense_account(&self) -> Option<&str> {
+        self.suspense_account_id.as_deref()
+    }
+
+    fn suspense_account_required(&self) -> LedgerResult<String> {
+        self.suspense_account()
+            .map(str::to_string)
+            .ok_or_else(|| LedgerError::Validation("No suspense account configured".to_string()))
+    }
+
+    /// Import one-sided bank lines, posting each against the suspense account
+    ///
+    /// Deposits debit the bank and credit suspense; withdrawals debit suspense
+    /// and credit the bank. Zero-amount lines are skipped. Returns the posted
+    /// transactions in statement order.
+    pub async fn import_bank_lines_to_suspense(
+        &mut self,
+        bank_account_id: &str,
+        lines: &[BankStatementLine],
+    ) -> LedgerResult<Vec<Transaction>> {
+        let suspense_account_id = self.suspense_account_required()?;
+        self.account_manager
+            .get_account_required(bank_account_id)
+            .await?;
+        self.account_manager
+            .get_account_required(&suspense_account_id)
+            .await?;
+
+        let mut posted = Vec::new();
+        for line in lines.iter().filter(|line| !line.amount.is_zero()) {
+            let amount = line.amount.abs();
+            let (debit_account_id, credit_account_id, kind) = if line.is_deposit() {
+                (
+                    bank_account_id.to_string(),
+                    suspense_account_id.clone(),
+                    TransactionKind::Receipt,
+                )
+            } else {
+                (
+                    suspense_account_id.clone(),
+                    bank_account_id.to_string(),
+                    TransactionKind::Payment,
+                )
+            };
+
+            let mut builder = TransactionBuilder::new(
+                format!("import-{}", self.next_transaction_id()),
+                line.date,
+                line.description.clone(),
+            )
+            .kind(kind)
+            .debit(debit_account_id, amount.clone(), None)
+            .credit(credit_account_id, amount, None)
+            .metadata("source".to_string(), "bank_import".to_string());
+            if let Some(reference) = &line.reference {
+                builder = builder.reference(reference.clone());
+            }
+            for (key, value) in &line.metadata {
+                builder = builder.metadata(key.clone(), value.clone());
+            }
+
+            let transaction = builder.build()?;
+            let transaction = self.record_transaction(transaction).await?;
+            posted.push(transaction);
+        }
+
+        Ok(posted)
+    }
+
+    /// List posted amounts still sitting in the suspense account
+    pub async fn list_suspense_items(&self) -> LedgerResult<Vec<SuspenseItem>> {
+        let suspense_account_id = self.suspense_account_required()?;
+        let mut transactions = self
+            .get_account_transactions(&suspense_account_id, None, None)
+            .await?;
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(transactions
+            .into_iter()
+            .map(|transaction| {
+                let amount: BigDecimal = transaction
+                    .entries
+                    .iter()
+                    .filter(|e| e.account_id == suspense_account_id)
+                    .map(|e| match e.entry_type {
+                        EntryType::Credit => e.amount.clone(),
+                        EntryType::Debit => -e.amount.clone(),
+                    })
+                    .sum();
+                SuspenseItem {
+                    transaction_id: transaction.id,
+                    date: transaction.date,
+                    description: transaction.description,
+                    reference: transaction.reference,
+                    amount,
+                }
+            })
+            .collect())
+    }
+
+    /// Move a suspense item to its proper account once it has been classified
+    ///
+    /// The suspense leg of the transaction is re-pointed at `account_id` and
+    /// balances are adjusted; the original suspense account is kept in the
+    /// `recoded_from` metadata key.
+    pub async fn recode_suspense_item(
+        &mut self,
+        transaction_id: &str,
+        account_id: &str,
+    ) -> LedgerResult<Transaction> {
+        let suspense_account_id = self.suspense_account_required()?;
+        self.account_manager
+            .get_account_required(account_id)
+            .await?;
+
+        let mut transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        if !transaction
+            .entries
+            .iter()
+            .any(|e| e.account_id == suspense_account_id)
+        {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' has no entry in the suspense account",
+                transaction_id
+            )));
+        }
+
+        for entry in transaction
+            .entries
+            .iter_mut()
+            .filter(|e| e.account_id == suspense_account_id)
+        {
+            entry.account_id = account_id.to_string();
+        }
+        transaction
+            .metadata
+            .insert("recoded_from".to_string(), suspense_account_id.into());
+        transaction.updated_at = self.clock.now();
+
+        self.update_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_suspense_import_and_recode() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        for (id, account_type) in [
+            ("bank", AccountType::Asset),
+            ("suspense", AccountType::Liability),
+            ("meals", AccountType::Expense),
+            ("capital", AccountType::Equity),
+        ] {
+            ledger
+                .create_account(id.to_string(), id.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        ledger.set_suspense_account(Some("suspense".to_string()));
+
+        let date = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+        let lines = vec![
+            BankStatementLine::new(date, "NEFT IN".to_string(), BigDecimal::from(900), None),
+            BankStatementLine::new(
+                date,
+                "SWIGGY".to_string(),
+                BigDecimal::from(-250),
+                Some("UPI123".to_string()),
+            ),
+            BankStatementLine::new(date, "Zero".to_string(), BigDecimal::from(0), None),
+        ];
+
+        let posted = ledger
+            .import_bank_lines_to_suspense("bank", &lines)
+            .await
+            .unwrap();
+        assert_eq!(posted.len(), 2);
+
+        let items = ledger.list_suspense_items().await.unwrap();
+        assert_eq!(items.len(), 2);
+        let total: BigDecimal = items.iter().map(|i| &i.amount).sum();
+        assert_eq!(total, BigDecimal::from(650));
+
+        let swiggy = items.iter().find(|i| i.description == "SWIGGY").unwrap();
+        ledger
+            .recode_suspense_item(&swiggy.transaction_id, "meals")
+            .await
+            .unwrap();
+
+        assert_eq!(ledger.list_suspense_items().await.unwrap().len(), 1);
+        let meals = ledger.get_account_balance("meals", None).await.unwrap();
+        let suspense = ledger.get_account_balance("suspense", None).await.unwrap();
+        assert_eq!(meals, BigDecimal::from(250));
+        assert_eq!(suspense, BigDecimal::from(900));
+    }
+}
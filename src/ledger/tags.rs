@@ -0,0 +1,214 @@
+//! Transaction tags and tag-based reporting
+//!
+//! Tags are free-form labels such as `travel` or `client-acme` used for
+//! lightweight classification that does not warrant its own account or
+//! entry dimension. Unlike metadata they carry no value, and a transaction
+//! can carry any number of them.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Posted totals of one tag within a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TagSummaryLine {
+    pub tag: String,
+    pub transaction_count: usize,
+    pub total_debits: BigDecimal,
+    pub total_credits: BigDecimal,
+    /// Debits minus credits per account
+    pub net_by_account: BTreeMap<String, BigDecimal>,
+}
+
+/// Posted totals per tag for a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TagSummaryReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// One line per tag, ordered by tag
+    pub lines: Vec<TagSummaryLine>,
+}
+
+impl TagSummaryReport {
+    /// Line for a tag, if any posted transaction in the period carries it
+    pub fn line(&self, tag: &str) -> Option<&TagSummaryLine> {
+        let tag = normalize_tag(tag).ok()?;
+        self.lines.iter().find(|line| line.tag == tag)
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Tag a transaction
+    pub async fn add_tag(&mut self, transaction_id: &str, tag: &str) -> LedgerResult<Transaction> {
+        self.authorize_tag_edit(transaction_id).await?;
+        self.transaction_manager.add_tag(transaction_id, tag).await
+    }
+
+    /// Remove a tag from a transaction
+    pub async fn remove_tag(
+        &mut self,
+        transaction_id: &str,
+        tag: &str,
+    ) -> LedgerResult<Transaction> {
+        self.authorize_tag_edit(transaction_id).await?;
+        self.transaction_manager
+            .remove_tag(transaction_id, tag)
+            .await
+    }
+
+    /// All tags in use with the number of transactions carrying each
+    pub async fn list_tags(&self) -> LedgerResult<BTreeMap<String, usize>> {
+        self.transaction_manager.list_tags().await
+    }
+
+    /// Transactions of any status carrying a tag within a date range
+    pub async fn get_transactions_by_tag(
+        &self,
+        tag: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.transaction_manager
+            .get_transactions_by_tag(tag, start_date, end_date)
+            .await
+    }
+
+    /// Rename a tag everywhere; returns the number of transactions changed
+    pub async fn rename_tag(&mut self, from: &str, to: &str) -> LedgerResult<usize> {
+        for transaction in self.get_transactions_by_tag(from, None, None).await? {
+            self.authorize(LedgerOperation::EditTransaction {
+                transaction: &transaction,
+            })?;
+        }
+        self.transaction_manager.rename_tag(from, to).await
+    }
+
+    /// Remove a tag everywhere; returns the number of transactions changed
+    pub async fn delete_tag(&mut self, tag: &str) -> LedgerResult<usize> {
+        for transaction in self.get_transactions_by_tag(tag, None, None).await? {
+            self.authorize(LedgerOperation::EditTransaction {
+                transaction: &transaction,
+            })?;
+        }
+        self.transaction_manager.delete_tag(tag).await
+    }
+
+    /// Total posted debits and credits per tag within a period
+    pub async fn generate_tag_summary(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> LedgerResult<TagSummaryReport> {
+        let transactions = self
+            .get_transactions(Some(start_date), Some(end_date))
+            .await?;
+
+        let mut lines: BTreeMap<String, TagSummaryLine> = BTreeMap::new();
+        for transaction in &transactions {
+            for tag in &transaction.tags {
+                let line = lines.entry(tag.clone()).or_insert_with(|| TagSummaryLine {
+                    tag: tag.clone(),
+                    transaction_count: 0,
+                    total_debits: BigDecimal::zero(),
+                    total_credits: BigDecimal::zero(),
+                    net_by_account: BTreeMap::new(),
+                });
+                line.transaction_count += 1;
+                line.total_debits += transaction.total_debits();
+                line.total_credits += transaction.total_credits();
+                for entry in &transaction.entries {
+                    let net = line
+                        .net_by_account
+                        .entry(entry.account_id.clone())
+                        .or_insert_with(BigDecimal::zero);
+                    match entry.entry_type {
+                        EntryType::Debit => *net += &entry.amount,
+                        EntryType::Credit => *net -= &entry.amount,
+                    }
+                }
+            }
+        }
+
+        Ok(TagSummaryReport {
+            start_date,
+            end_date,
+            lines: lines.into_values().collect(),
+        })
+    }
+
+    async fn authorize_tag_edit(&self, transaction_id: &str) -> LedgerResult<()> {
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        self.authorize(LedgerOperation::EditTransaction {
+            transaction: &transaction,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, IntegrityIssue};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_tag_summary_per_tag() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        for (id, amount) in [("t1", 300), ("t2", 200), ("t3", 50)] {
+            let mut payment = patterns::create_expense_payment(
+                id.to_string(),
+                date,
+                "Taxi".to_string(),
+                "6100".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(amount),
+            )
+            .unwrap();
+            payment.add_tag("#Travel").unwrap();
+            if id == "t3" {
+                payment.add_tag("client-acme").unwrap();
+            }
+            ledger.record_transaction(payment).await.unwrap();
+        }
+
+        let report = ledger.generate_tag_summary(date, date).await.unwrap();
+        assert_eq!(report.lines.len(), 2);
+        let travel = report.line("travel").unwrap();
+        assert_eq!(travel.transaction_count, 3);
+        assert_eq!(travel.total_debits, BigDecimal::from(550));
+        assert_eq!(travel.net_by_account["1000"], BigDecimal::from(-550));
+        assert_eq!(
+            report.line("client-acme").unwrap().total_credits,
+            BigDecimal::from(50)
+        );
+
+        // Retagging a locked period does not count as editing it
+        ledger.lock_period(date).unwrap();
+        let before = ledger.get_transaction("t1").await.unwrap().unwrap();
+        assert_eq!(ledger.rename_tag("travel", "trips").await.unwrap(), 3);
+        ledger.remove_tag("t1", "trips").await.unwrap();
+        let tags = ledger.list_tags().await.unwrap();
+        assert_eq!(tags.get("trips"), Some(&2));
+        assert!(!tags.contains_key("travel"));
+        assert_eq!(ledger.delete_tag("client-acme").await.unwrap(), 1);
+        assert!(ledger.add_tag("t1", "two words").await.is_err());
+        let after = ledger.add_tag("t1", "audited").await.unwrap();
+        assert_eq!(after.updated_at, before.updated_at);
+        let report = ledger.validate_integrity(date).await.unwrap();
+        assert!(!report
+            .issues
+            .iter()
+            .any(|i| matches!(i, IntegrityIssue::LockedPeriodTransaction { .. })));
+    }
+}
@@ -0,0 +1,483 @@
+//! Transaction templates for vouchers posted again and again by hand
+//!
+//! Unlike a recurring schedule, a template is posted only when the
+//! bookkeeper asks, with the figures that vary filled in each time: monthly
+//! rent is fixed, the electricity recharge on the same bill is not, and the
+//! bank line pays whatever the two add up to. Templates are kept on the
+//! ledger and travel with its snapshots; set a [`TemplateStore`] to have
+//! them persisted as they change.
+
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key naming the template a transaction was posted from
+pub const TEMPLATE_KEY: &str = "template";
+
+/// Account of a template line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TemplateAccount {
+    Fixed(String),
+    /// Supplied at posting time under this parameter name
+    Param(String),
+}
+
+/// Amount of a template line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TemplateAmount {
+    Fixed(BigDecimal),
+    /// Supplied at posting time under this parameter name
+    Param(String),
+    /// Whatever balances the other lines; at most one line may use it
+    Balance,
+}
+
+/// One entry of a template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TemplateLine {
+    pub entry_type: EntryType,
+    pub account: TemplateAccount,
+    pub amount: TemplateAmount,
+    pub description: Option<String>,
+}
+
+/// A reusable voucher with placeholders for the parts that vary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TransactionTemplate {
+    pub id: String,
+    pub name: String,
+    /// Description of transactions posted from the template, unless the
+    /// posting overrides it
+    pub description: String,
+    pub kind: TransactionKind,
+    pub tags: BTreeSet<String>,
+    pub lines: Vec<TemplateLine>,
+}
+
+impl TransactionTemplate {
+    /// Create an empty template
+    pub fn new(id: String, name: String, description: String) -> Self {
+        Self {
+            id,
+            name,
+            description,
+            kind: TransactionKind::default(),
+            tags: BTreeSet::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    /// Add a debit line
+    pub fn debit(mut self, account: TemplateAccount, amount: TemplateAmount) -> Self {
+        self.lines.push(TemplateLine {
+            entry_type: EntryType::Debit,
+            account,
+            amount,
+            description: None,
+        });
+        self
+    }
+
+    /// Add a credit line
+    pub fn credit(mut self, account: TemplateAccount, amount: TemplateAmount) -> Self {
+        self.lines.push(TemplateLine {
+            entry_type: EntryType::Credit,
+            account,
+            amount,
+            description: None,
+        });
+        self
+    }
+
+    /// Set the voucher type of transactions posted from the template
+    pub fn kind(mut self, kind: TransactionKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Tag every transaction posted from the template
+    pub fn tag(mut self, tag: &str) -> LedgerResult<Self> {
+        self.tags.insert(normalize_tag(tag)?);
+        Ok(self)
+    }
+
+    /// Check the template's shape: at least two lines and at most one
+    /// balancing line
+    pub fn validate(&self) -> LedgerResult<()> {
+        if self.lines.len() < 2 {
+            return Err(LedgerError::Validation(format!(
+                "Template {} needs at least two lines",
+                self.id
+            )));
+        }
+        let balancing = self
+            .lines
+            .iter()
+            .filter(|line| line.amount == TemplateAmount::Balance)
+            .count();
+        if balancing > 1 {
+            return Err(LedgerError::Validation(format!(
+                "Template {} has more than one balancing line",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fill in the placeholders and build the transaction
+    pub fn instantiate(&self, params: &TemplateParams) -> LedgerResult<Transaction> {
+        self.validate()?;
+        let missing = |kind: &str, name: &str| {
+            LedgerError::Validation(format!(
+                "Template {} needs {} parameter {}",
+                self.id, kind, name
+            ))
+        };
+
+        let mut resolved = Vec::new();
+        let mut difference = BigDecimal::zero();
+        for line in &self.lines {
+            let account_id = match &line.account {
+                TemplateAccount::Fixed(id) => id.clone(),
+                TemplateAccount::Param(name) => params
+                    .accounts
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| missing("account", name))?,
+            };
+            let amount = match &line.amount {
+                TemplateAmount::Fixed(amount) => Some(amount.clone()),
+                TemplateAmount::Param(name) => Some(
+                    params
+                        .amounts
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| missing("amount", name))?,
+                ),
+                TemplateAmount::Balance => None,
+            };
+            if let Some(amount) = &amount {
+                match line.entry_type {
+                    EntryType::Debit => difference += amount,
+                    EntryType::Credit => difference -= amount,
+                }
+            }
+            resolved.push((line, account_id, amount));
+        }
+
+        let description = params
+            .description
+            .clone()
+            .unwrap_or_else(|| self.description.clone());
+        let mut builder =
+            TransactionBuilder::new(params.transaction_id.clone(), params.date, description)
+                .kind(self.kind)
+                .metadata(TEMPLATE_KEY.to_string(), self.id.as_str());
+        if let Some(reference) = &params.reference {
+            builder = builder.reference(reference.clone());
+        }
+        for (line, account_id, amount) in resolved {
+            // The balancing line takes the difference on its own side
+            let amount = amount.unwrap_or_else(|| match line.entry_type {
+                EntryType::Debit => -difference.clone(),
+                EntryType::Credit => difference.clone(),
+            });
+            // Optional lines left at zero are dropped
+            if amount.is_zero() {
+                continue;
+            }
+            builder = builder.entry(Entry::new(
+                account_id,
+                line.entry_type.clone(),
+                amount,
+                line.description.clone(),
+            ));
+        }
+
+        let mut transaction = builder.build()?;
+        transaction.tags = self.tags.clone();
+        Ok(transaction)
+    }
+}
+
+/// Values for a template's placeholders
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TemplateParams {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    /// Replaces the template's description
+    pub description: Option<String>,
+    pub reference: Option<String>,
+    pub amounts: HashMap<String, BigDecimal>,
+    pub accounts: HashMap<String, String>,
+}
+
+impl TemplateParams {
+    pub fn new(transaction_id: String, date: NaiveDate) -> Self {
+        Self {
+            transaction_id,
+            date,
+            description: None,
+            reference: None,
+            amounts: HashMap::new(),
+            accounts: HashMap::new(),
+        }
+    }
+
+    /// Supply an amount placeholder
+    pub fn amount(mut self, name: &str, amount: BigDecimal) -> Self {
+        self.amounts.insert(name.to_string(), amount);
+        self
+    }
+
+    /// Supply an account placeholder
+    pub fn account(mut self, name: &str, account_id: &str) -> Self {
+        self.accounts
+            .insert(name.to_string(), account_id.to_string());
+        self
+    }
+
+    /// Override the template's description
+    pub fn description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Set the reference of the posted transaction
+    pub fn reference(mut self, reference: String) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+}
+
+/// [`TemplateStore`] keeping templates in memory; clones share the same
+/// templates
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTemplateStore {
+    templates: Arc<RwLock<BTreeMap<String, TransactionTemplate>>>,
+}
+
+impl MemoryTemplateStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TemplateStore for MemoryTemplateStore {
+    async fn load_templates(&self) -> LedgerResult<Vec<TransactionTemplate>> {
+        let templates = self
+            .templates
+            .read()
+            .map_err(|_| LedgerError::storage("Template store lock poisoned"))?;
+        Ok(templates.values().cloned().collect())
+    }
+
+    async fn save_template(&mut self, template: &TransactionTemplate) -> LedgerResult<()> {
+        self.templates
+            .write()
+            .map_err(|_| LedgerError::storage("Template store lock poisoned"))?
+            .insert(template.id.clone(), template.clone());
+        Ok(())
+    }
+
+    async fn delete_template(&mut self, template_id: &str) -> LedgerResult<()> {
+        self.templates
+            .write()
+            .map_err(|_| LedgerError::storage("Template store lock poisoned"))?
+            .remove(template_id);
+        Ok(())
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Persist templates in `store` from now on
+    ///
+    /// The templates already in the store replace those on the ledger, so a
+    /// rebuilt ledger picks up where the last one left off.
+    pub async fn set_template_store(&mut self, store: Box<dyn TemplateStore>) -> LedgerResult<()> {
+        self.templates = store
+            .load_templates()
+            .await?
+            .into_iter()
+            .map(|template| (template.id.clone(), template))
+            .collect();
+        self.template_store = Some(store);
+        Ok(())
+    }
+
+    /// Replace every template, in the store too when one is set
+    pub(crate) async fn replace_templates(
+        &mut self,
+        templates: Vec<TransactionTemplate>,
+    ) -> LedgerResult<()> {
+        let templates: BTreeMap<String, TransactionTemplate> = templates
+            .into_iter()
+            .map(|template| (template.id.clone(), template))
+            .collect();
+        if let Some(store) = &mut self.template_store {
+            for template_id in self.templates.keys() {
+                if !templates.contains_key(template_id) {
+                    store.delete_template(template_id).await?;
+                }
+            }
+            for template in templates.values() {
+                store.save_template(template).await?;
+            }
+        }
+        self.templates = templates;
+        Ok(())
+    }
+
+    /// Save a template, replacing any with the same ID
+    ///
+    /// Fixed accounts must exist.
+    pub async fn save_template(&mut self, template: TransactionTemplate) -> LedgerResult<()> {
+        template.validate()?;
+        for line in &template.lines {
+            if let TemplateAccount::Fixed(account_id) = &line.account {
+                self.account_manager
+                    .get_account_required(account_id)
+                    .await?;
+            }
+        }
+        if let Some(store) = &mut self.template_store {
+            store.save_template(&template).await?;
+        }
+        self.templates.insert(template.id.clone(), template);
+        Ok(())
+    }
+
+    /// Get a template by ID
+    pub fn get_template(&self, template_id: &str) -> Option<&TransactionTemplate> {
+        self.templates.get(template_id)
+    }
+
+    /// All saved templates, ordered by ID
+    pub fn list_templates(&self) -> Vec<&TransactionTemplate> {
+        self.templates.values().collect()
+    }
+
+    /// Delete a template; returns it if it existed
+    pub async fn delete_template(
+        &mut self,
+        template_id: &str,
+    ) -> LedgerResult<Option<TransactionTemplate>> {
+        if let Some(store) = &mut self.template_store {
+            store.delete_template(template_id).await?;
+        }
+        Ok(self.templates.remove(template_id))
+    }
+
+    /// Build a transaction from a saved template and record it
+    pub async fn create_from_template(
+        &mut self,
+        template_id: &str,
+        params: TemplateParams,
+    ) -> LedgerResult<Transaction> {
+        let template = self.templates.get(template_id).ok_or_else(|| {
+            LedgerError::Validation(format!("Template not found: {}", template_id))
+        })?;
+        let transaction = template.instantiate(&params)?;
+        self.record_transaction(transaction).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_monthly_rent_with_variable_electricity() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let template = TransactionTemplate::new(
+            "rent".to_string(),
+            "Monthly rent".to_string(),
+            "Office rent and electricity".to_string(),
+        )
+        .kind(TransactionKind::Payment)
+        .debit(
+            TemplateAccount::Fixed("6000".to_string()),
+            TemplateAmount::Fixed(BigDecimal::from(25000)),
+        )
+        .debit(
+            TemplateAccount::Fixed("6100".to_string()),
+            TemplateAmount::Param("electricity".to_string()),
+        )
+        .credit(
+            TemplateAccount::Param("paid_from".to_string()),
+            TemplateAmount::Balance,
+        )
+        .tag("premises")
+        .unwrap();
+        ledger.save_template(template).await.unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 5, 5).unwrap();
+        let params = TemplateParams::new("rent-may".to_string(), date)
+            .amount("electricity", BigDecimal::from(3140))
+            .account("paid_from", "1000");
+        let transaction = ledger.create_from_template("rent", params).await.unwrap();
+        assert!(transaction.has_tag("premises"));
+        assert_eq!(transaction.kind, TransactionKind::Payment);
+        assert_eq!(
+            ledger.get_account_balance("1000", None).await.unwrap(),
+            BigDecimal::from(-28140)
+        );
+
+        let incomplete = TemplateParams::new("rent-jun".to_string(), date);
+        assert!(ledger
+            .create_from_template("rent", incomplete)
+            .await
+            .is_err());
+        assert!(ledger.get_transaction("rent-jun").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_templates_persist_in_store() {
+        let storage = MemoryStorage::new();
+        let store = MemoryTemplateStore::new();
+        let mut ledger = Ledger::new(storage.clone());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .set_template_store(Box::new(store.clone()))
+            .await
+            .unwrap();
+        for id in ["rent", "fees"] {
+            let template = TransactionTemplate::new(id.to_string(), id.to_string(), id.to_string())
+                .debit(
+                    TemplateAccount::Fixed("6000".to_string()),
+                    TemplateAmount::Fixed(BigDecimal::from(100)),
+                )
+                .credit(
+                    TemplateAccount::Fixed("1000".to_string()),
+                    TemplateAmount::Balance,
+                );
+            ledger.save_template(template).await.unwrap();
+        }
+        assert!(ledger.delete_template("fees").await.unwrap().is_some());
+
+        let mut reloaded = Ledger::new(storage);
+        reloaded.set_template_store(Box::new(store)).await.unwrap();
+        let ids: Vec<_> = reloaded
+            .list_templates()
+            .iter()
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(ids, ["rent"]);
+    }
+}
@@ -1,8 +1,12 @@
 //! Transaction processing and management
 
-use bigdecimal::BigDecimal;
-use chrono::NaiveDate;
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
 
+use crate::tax::GstCalculation;
 use crate::traits::*;
 use crate::types::*;
 
@@ -30,10 +34,138 @@ pub struct BillPaymentWithGstParams {
     pub gst_amount: BigDecimal,
 }
 
+/// Ledger accounts for the separate GST components on one side, output
+/// (collected on sales) or input (credit on purchases), as GSTR returns
+/// report them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GstAccounts {
+    pub cgst_account_id: String,
+    pub sgst_account_id: String,
+    pub igst_account_id: String,
+    /// Compensation cess account; needed only when cess is charged
+    pub cess_account_id: Option<String>,
+}
+
+/// Parameters for a sale with GST split into its components
+pub struct GstSaleParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub receivables_account_id: String,
+    pub revenue_account_id: String,
+    pub output_accounts: GstAccounts,
+    /// Tax breakdown of the sale, already rounded for posting
+    pub calculation: GstCalculation,
+    /// Compensation cess charged on top of GST
+    pub cess_amount: BigDecimal,
+}
+
+/// Parameters for a purchase with GST split into its components
+pub struct GstPurchaseParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub expense_account_id: String,
+    pub cash_or_payables_account_id: String,
+    pub input_accounts: GstAccounts,
+    /// Tax breakdown of the bill, already rounded for posting
+    pub calculation: GstCalculation,
+    /// Compensation cess paid on top of GST
+    pub cess_amount: BigDecimal,
+}
+
+/// Parameters for a transfer between two bank (or cash) accounts
+pub struct BankTransferParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub from_bank_account_id: String,
+    pub to_bank_account_id: String,
+    pub amount: BigDecimal,
+    /// Funds-in-transit account for two-step transfers; `None` posts directly
+    pub in_transit_account_id: Option<String>,
+}
+
+/// Parameters for writing off a small residual balance on a party account
+pub struct SmallBalanceWriteOffParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Receivable or payable account carrying the residual
+    pub party_account_id: String,
+    /// Account absorbing the write-off (sundry expense or sundry income)
+    pub write_off_account_id: String,
+    /// Residual in the party account's debit direction: positive when the
+    /// party still owes us, negative when we hold an overpayment
+    pub residual: BigDecimal,
+    /// Largest absolute residual that may be written off
+    pub threshold: BigDecimal,
+}
+
+/// Parameters for settling a receivable or payable with a cash discount
+pub struct SettlementDiscountParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub cash_account_id: String,
+    /// Receivable (discount allowed) or payable (discount received) account
+    pub party_account_id: String,
+    /// Discount allowed (expense) or discount received (income) account
+    pub discount_account_id: String,
+    /// Full amount of the invoice or bill being settled
+    pub settled_amount: BigDecimal,
+    /// Discount granted on settlement
+    pub discount: BigDecimal,
+}
+
+/// Parameters for charging interest on an overdue receivable
+pub struct OverdueInterestChargeParams {
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub receivables_account_id: String,
+    pub interest_income_account_id: String,
+    pub gst_payable_account_id: String,
+    /// Interest charged, before GST
+    pub interest: BigDecimal,
+    /// GST rate percentage levied on the interest (e.g. 18)
+    pub gst_rate: BigDecimal,
+    /// Rounding for the GST, usually the ledger's
+    /// [`currency_amount_policy`](crate::ledger::Ledger::currency_amount_policy)
+    pub amount_policy: AmountPolicy,
+}
+
+/// Parameters for a pair of mirrored intercompany transactions
+pub struct IntercompanyParams {
+    /// Shared ID used for the transaction in both entities
+    pub id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Entity recording the amount due from the other
+    pub source_entity_id: String,
+    /// Entity recording the amount due to the source
+    pub target_entity_id: String,
+    pub amount: BigDecimal,
+    /// Source entity's due-from account (debited)
+    pub source_due_from_account_id: String,
+    /// Source entity's offsetting account (credited), e.g. income or bank
+    pub source_counter_account_id: String,
+    /// Target entity's due-to account (credited)
+    pub target_due_to_account_id: String,
+    /// Target entity's offsetting account (debited), e.g. expense or bank
+    pub target_counter_account_id: String,
+}
+
 /// Transaction manager for handling transaction operations
 pub struct TransactionManager<S: LedgerStorage> {
     storage: S,
     validator: Box<dyn TransactionValidator>,
+    period_lock: Option<PeriodLock>,
+    approval_threshold: Option<BigDecimal>,
+    amount_policy: Option<AmountPolicy>,
+    round_off_policy: Option<RoundOffPolicy>,
+    clock: Arc<dyn Clock>,
 }
 
 impl<S: LedgerStorage> TransactionManager<S> {
@@ -42,44 +174,554 @@ impl<S: LedgerStorage> TransactionManager<S> {
         Self {
             storage,
             validator: Box::new(DefaultTransactionValidator),
+            period_lock: None,
+            approval_threshold: None,
+            amount_policy: None,
+            round_off_policy: None,
+            clock: Arc::new(SystemClock),
         }
     }
 
     /// Create a new transaction manager with custom validator
     pub fn with_validator(storage: S, validator: Box<dyn TransactionValidator>) -> Self {
-        Self { storage, validator }
+        Self {
+            storage,
+            validator,
+            period_lock: None,
+            approval_threshold: None,
+            amount_policy: None,
+            round_off_policy: None,
+            clock: Arc::new(SystemClock),
+        }
     }
 
-    /// Record a new transaction
-    pub async fn record_transaction(&mut self, mut transaction: Transaction) -> LedgerResult<()> {
+    /// Use `clock` for transaction and account timestamps
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Lock all dates up to and including `through` against posting changes
+    pub fn lock_period(&mut self, through: NaiveDate) {
+        self.period_lock = Some(PeriodLock::new(through, self.clock.now()));
+    }
+
+    /// Remove the current period lock
+    pub fn unlock_period(&mut self) {
+        self.period_lock = None;
+    }
+
+    /// Get the current period lock, if any
+    pub fn period_lock(&self) -> Option<&PeriodLock> {
+        self.period_lock.as_ref()
+    }
+
+    /// Replace the period lock as is, keeping its original timestamp
+    pub(crate) fn restore_period_lock(&mut self, lock: Option<PeriodLock>) {
+        self.period_lock = lock;
+    }
+
+    /// Ensure a date is not inside the locked period
+    fn ensure_unlocked(&self, date: NaiveDate) -> LedgerResult<()> {
+        match &self.period_lock {
+            Some(lock) if lock.covers(date) => Err(LedgerError::PeriodLocked(lock.locked_through)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Ensure no account flagged as non-negative would drop below zero on or
+    /// after the transaction date once `transaction` is in place
+    async fn check_balance_constraints(&self, transaction: &Transaction) -> LedgerResult<()> {
+        self.check_replacement_constraints(Some(transaction), None)
+            .await
+    }
+
+    /// Ensure no account flagged as non-negative would drop below zero once
+    /// the posted `previous` version of a transaction is replaced by
+    /// `transaction`; `None` for `transaction` checks a deletion
+    ///
+    /// Every account the previous version touched is checked, since taking
+    /// away a deposit can leave a later withdrawal short.
+    async fn check_replacement_constraints(
+        &self,
+        transaction: Option<&Transaction>,
+        previous: Option<&Transaction>,
+    ) -> LedgerResult<()> {
+        let Some(replaced) = transaction.or(previous) else {
+            return Ok(());
+        };
+        let from_date = transaction
+            .into_iter()
+            .chain(previous)
+            .map(|t| t.date)
+            .min()
+            .unwrap_or(replaced.date);
+        let mut account_ids: Vec<&String> = transaction
+            .into_iter()
+            .chain(previous)
+            .flat_map(|t| t.entries.iter().map(|e| &e.account_id))
+            .collect();
+        account_ids.sort();
+        account_ids.dedup();
+
+        for account_id in account_ids {
+            let account = match self.storage.get_account(account_id).await? {
+                Some(account) if account.non_negative => account,
+                _ => continue,
+            };
+            let effect_on = |t: &Transaction| -> BigDecimal {
+                t.entries
+                    .iter()
+                    .filter(|e| &e.account_id == account_id)
+                    .map(|e| {
+                        account
+                            .account_type
+                            .balance_effect(&e.entry_type, &e.amount)
+                    })
+                    .sum()
+            };
+
+            // Changes that only increase the balance, without moving it to
+            // another date, can never cause a shortfall
+            let effect = transaction.map(effect_on).unwrap_or_default()
+                - previous.map(effect_on).unwrap_or_default();
+            let same_date = previous.is_none_or(|p| transaction.is_some_and(|t| t.date == p.date));
+            if !effect.is_negative() && same_date {
+                continue;
+            }
+
+            // Replay the account with this transaction replacing any stored version
+            let history = self
+                .storage
+                .get_account_transactions(account_id, None, None)
+                .await?;
+            let mut movements: BTreeMap<NaiveDate, BigDecimal> = BTreeMap::new();
+            for txn in history
+                .iter()
+                .filter(|t| t.is_posted() && t.id != replaced.id)
+                .chain(transaction)
+            {
+                for entry in txn.entries.iter().filter(|e| &e.account_id == account_id) {
+                    *movements.entry(txn.date).or_insert_with(BigDecimal::zero) += account
+                        .account_type
+                        .balance_effect(&entry.entry_type, &entry.amount);
+                }
+            }
+
+            let mut running = BigDecimal::zero();
+            let mut lowest = BigDecimal::zero();
+            for (date, movement) in movements {
+                running += movement;
+                if date >= from_date && running < lowest {
+                    lowest = running.clone();
+                }
+            }
+
+            if lowest.is_negative() {
+                return Err(LedgerError::InsufficientBalance {
+                    account_id: account_id.clone(),
+                    shortfall: lowest.abs(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Run every check a new transaction must pass before it is posted
+    async fn validate_for_posting(&self, transaction: &Transaction) -> LedgerResult<()> {
         // Validate the transaction
-        self.validator.validate_transaction(&transaction)?;
-        self.validator.validate_account_references(&transaction)?;
+        self.validator.validate_transaction(transaction)?;
+        self.validator.validate_account_references(transaction)?;
+        self.ensure_unlocked(transaction.date)?;
+        self.ensure_correction_target(transaction).await?;
 
-        // Verify all referenced accounts exist
-        for entry in &transaction.entries {
-            if self.storage.get_account(&entry.account_id).await?.is_none() {
-                return Err(LedgerError::AccountNotFound(entry.account_id.clone()));
+        // Verify all referenced accounts, in every book, exist and are open
+        // for postings
+        for entry in transaction.all_entries() {
+            if let Some(policy) = &self.amount_policy {
+                policy.check_entry(entry)?;
+            }
+            if !self
+                .get_account_required(&entry.account_id)
+                .await?
+                .is_active()
+            {
+                return Err(LedgerError::AccountArchived(entry.account_id.clone()));
             }
         }
 
-        // Update the transaction timestamp
-        transaction.updated_at = chrono::Utc::now().naive_utc();
+        self.check_balance_constraints(transaction).await
+    }
+
+    /// Record a new transaction
+    pub async fn record_transaction(
+        &mut self,
+        mut transaction: Transaction,
+    ) -> LedgerResult<Transaction> {
+        transaction.status = TransactionStatus::Posted;
+        transaction.assign_entry_ids();
+        self.validate_for_posting(&transaction).await?;
+
+        // Stamp the transaction as created now
+        transaction.created_at = self.clock.now();
+        transaction.updated_at = transaction.created_at;
 
         // Save the transaction
         self.storage.save_transaction(&transaction).await?;
 
         // Update account balances
+        self.apply_entries(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Apply a transaction's entries to the stored account balances
+    async fn apply_entries(&mut self, transaction: &Transaction) -> LedgerResult<()> {
         for entry in &transaction.entries {
             if let Some(mut account) = self.storage.get_account(&entry.account_id).await? {
                 account.apply_entry(entry.entry_type.clone(), &entry.amount);
+                account.updated_at = self.clock.now();
                 self.storage.update_account(&account).await?;
             }
         }
+        Ok(())
+    }
 
+    /// Reverse a transaction's entries on the stored account balances
+    async fn reverse_entries(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        for entry in &transaction.entries {
+            if let Some(mut account) = self.storage.get_account(&entry.account_id).await? {
+                // Reverse the entry by applying the opposite
+                let reverse_type = match entry.entry_type {
+                    EntryType::Debit => EntryType::Credit,
+                    EntryType::Credit => EntryType::Debit,
+                };
+                account.apply_entry(reverse_type, &entry.amount);
+                account.updated_at = self.clock.now();
+                self.storage.update_account(&account).await?;
+            }
+        }
         Ok(())
     }
 
+    /// Save a transaction as a draft that does not affect balances
+    ///
+    /// Drafts are validated like postings apart from balance constraints,
+    /// which are checked when the draft is posted.
+    pub async fn save_draft(&mut self, mut transaction: Transaction) -> LedgerResult<Transaction> {
+        transaction.status = TransactionStatus::Draft;
+        self.save_unposted(transaction).await
+    }
+
+    /// Validate and save a transaction that does not affect balances yet
+    async fn save_unposted(&mut self, mut transaction: Transaction) -> LedgerResult<Transaction> {
+        transaction.assign_entry_ids();
+        self.validator.validate_transaction(&transaction)?;
+        self.validator.validate_account_references(&transaction)?;
+        self.ensure_correction_target(&transaction).await?;
+        for entry in &transaction.entries {
+            if !self
+                .get_account_required(&entry.account_id)
+                .await?
+                .is_active()
+            {
+                return Err(LedgerError::AccountArchived(entry.account_id.clone()));
+            }
+        }
+
+        transaction.created_at = self.clock.now();
+        transaction.updated_at = transaction.created_at;
+        self.storage.save_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Require checker approval for transactions whose total exceeds the
+    /// threshold; `None` disables approval
+    pub fn set_approval_threshold(&mut self, threshold: Option<BigDecimal>) {
+        self.approval_threshold = threshold;
+    }
+
+    /// Get the current approval threshold, if any
+    pub fn approval_threshold(&self) -> Option<&BigDecimal> {
+        self.approval_threshold.as_ref()
+    }
+
+    /// Reject postings with amounts more precise than `policy` allows;
+    /// `None` accepts any precision
+    pub fn set_amount_policy(&mut self, policy: Option<AmountPolicy>) {
+        self.amount_policy = policy;
+    }
+
+    /// Get the current amount policy, if any
+    pub fn amount_policy(&self) -> Option<&AmountPolicy> {
+        self.amount_policy.as_ref()
+    }
+
+    /// Balance small rounding differences with `policy` before posting;
+    /// `None` rejects every unbalanced transaction
+    pub fn set_round_off_policy(&mut self, policy: Option<RoundOffPolicy>) {
+        self.round_off_policy = policy;
+    }
+
+    /// Get the current round-off policy, if any
+    pub fn round_off_policy(&self) -> Option<&RoundOffPolicy> {
+        self.round_off_policy.as_ref()
+    }
+
+    /// Check whether a transaction needs approval before it can be posted
+    pub fn requires_approval(&self, transaction: &Transaction) -> bool {
+        self.approval_threshold
+            .as_ref()
+            .is_some_and(|threshold| &transaction.total_debits() > threshold)
+    }
+
+    /// Save a transaction awaiting approval; it does not affect balances
+    /// until approved
+    pub async fn submit_for_approval(
+        &mut self,
+        mut transaction: Transaction,
+        submitted_by: Option<&str>,
+    ) -> LedgerResult<Transaction> {
+        self.ensure_unlocked(transaction.date)?;
+        Self::mark_pending(&mut transaction, submitted_by);
+        self.save_unposted(transaction).await
+    }
+
+    /// Hold a transaction for approval, recording its maker
+    fn mark_pending(transaction: &mut Transaction, submitted_by: Option<&str>) {
+        transaction.status = TransactionStatus::PendingApproval;
+        if let Some(maker) = submitted_by {
+            transaction
+                .metadata
+                .insert("submitted_by".to_string(), maker.into());
+        }
+    }
+
+    /// List transactions awaiting approval within a date range
+    pub async fn list_pending_approval(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        Ok(self
+            .storage
+            .get_transactions(start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.status == TransactionStatus::PendingApproval)
+            .collect())
+    }
+
+    /// Approve a pending transaction and post it
+    ///
+    /// The approver and reason are recorded on the transaction. The maker of a
+    /// transaction cannot approve it, and a transaction submitted without a
+    /// maker cannot be approved at all; reject it and submit it again.
+    pub async fn approve_transaction(
+        &mut self,
+        transaction_id: &str,
+        approver: &str,
+        reason: Option<String>,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_pending_required(transaction_id, approver).await?;
+        if !transaction.metadata.contains_key("submitted_by") {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' has no recorded maker and cannot be approved",
+                transaction_id
+            )));
+        }
+        transaction.status = TransactionStatus::Posted;
+        self.validate_for_posting(&transaction).await?;
+
+        let now = self.clock.now();
+        transaction
+            .metadata
+            .insert("approved_by".to_string(), approver.into());
+        transaction
+            .metadata
+            .insert("approved_at".to_string(), now.to_rfc3339().into());
+        if let Some(reason) = reason {
+            transaction
+                .metadata
+                .insert("approval_reason".to_string(), reason.into());
+        }
+        transaction.updated_at = now;
+        self.storage.update_transaction(&transaction).await?;
+        self.apply_entries(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Reject a pending transaction; it stays in storage for the audit trail
+    pub async fn reject_transaction(
+        &mut self,
+        transaction_id: &str,
+        approver: &str,
+        reason: String,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_pending_required(transaction_id, approver).await?;
+        transaction.status = TransactionStatus::Rejected;
+
+        let now = self.clock.now();
+        transaction
+            .metadata
+            .insert("rejected_by".to_string(), approver.into());
+        transaction
+            .metadata
+            .insert("rejected_at".to_string(), now.to_rfc3339().into());
+        transaction
+            .metadata
+            .insert("rejection_reason".to_string(), reason.into());
+        transaction.updated_at = now;
+        self.storage.update_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+
+    async fn get_pending_required(
+        &self,
+        transaction_id: &str,
+        approver: &str,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self.get_transaction_required(transaction_id).await?;
+        if transaction.status != TransactionStatus::PendingApproval {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' is not pending approval",
+                transaction_id
+            )));
+        }
+        if transaction
+            .metadata
+            .get("submitted_by")
+            .is_some_and(|maker| maker == approver)
+        {
+            return Err(LedgerError::Validation(format!(
+                "'{}' cannot review their own transaction '{}'",
+                approver, transaction_id
+            )));
+        }
+        Ok(transaction)
+    }
+
+    /// List draft transactions within a date range
+    pub async fn list_drafts(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        Ok(self
+            .storage
+            .get_transactions(start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.status == TransactionStatus::Draft)
+            .collect())
+    }
+
+    /// Post a previously saved draft so it affects balances
+    ///
+    /// A draft above the approval threshold is held for approval instead,
+    /// with `submitted_by` as its maker; check the returned status.
+    pub async fn post_draft(
+        &mut self,
+        transaction_id: &str,
+        submitted_by: Option<&str>,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self.get_draft_required(transaction_id).await?;
+        self.post_loaded_draft(transaction, submitted_by).await
+    }
+
+    /// Post a draft already loaded from storage, keeping any changes made to
+    /// it since
+    pub(crate) async fn post_loaded_draft(
+        &mut self,
+        mut transaction: Transaction,
+        submitted_by: Option<&str>,
+    ) -> LedgerResult<Transaction> {
+        if self.requires_approval(&transaction) {
+            self.ensure_unlocked(transaction.date)?;
+            Self::mark_pending(&mut transaction, submitted_by);
+            transaction.updated_at = self.clock.now();
+            self.storage.update_transaction(&transaction).await?;
+            return Ok(transaction);
+        }
+        transaction.status = TransactionStatus::Posted;
+        self.validate_for_posting(&transaction).await?;
+
+        transaction.updated_at = self.clock.now();
+        self.storage.update_transaction(&transaction).await?;
+        self.apply_entries(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Discard a draft without touching balances
+    pub async fn discard_draft(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        self.get_draft_required(transaction_id).await?;
+        self.storage.delete_transaction(transaction_id).await
+    }
+
+    pub(crate) async fn get_draft_required(
+        &self,
+        transaction_id: &str,
+    ) -> LedgerResult<Transaction> {
+        let transaction = self.get_transaction_required(transaction_id).await?;
+        if transaction.status != TransactionStatus::Draft {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' is not a draft",
+                transaction_id
+            )));
+        }
+        Ok(transaction)
+    }
+
+    /// Validate a transaction and project its effect on account balances
+    /// without persisting anything
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> LedgerResult<SimulationResult> {
+        self.validate_for_posting(transaction).await?;
+
+        let mut changes: Vec<BalanceChange> = Vec::new();
+        for entry in &transaction.entries {
+            let position = match changes
+                .iter()
+                .position(|c| c.account_id == entry.account_id)
+            {
+                Some(position) => position,
+                None => {
+                    let account = self.get_account_required(&entry.account_id).await?;
+                    changes.push(BalanceChange {
+                        account_id: account.id,
+                        account_name: account.name,
+                        account_type: account.account_type,
+                        balance_before: account.balance.clone(),
+                        change: BigDecimal::zero(),
+                        balance_after: account.balance,
+                    });
+                    changes.len() - 1
+                }
+            };
+
+            let change = &mut changes[position];
+            let effect = change
+                .account_type
+                .balance_effect(&entry.entry_type, &entry.amount);
+            change.change += &effect;
+            change.balance_after += effect;
+        }
+
+        Ok(SimulationResult {
+            transaction_id: transaction.id.clone(),
+            date: transaction.date,
+            changes,
+        })
+    }
+
+    async fn get_account_required(&self, account_id: &str) -> LedgerResult<Account> {
+        self.storage
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))
+    }
+
     /// Get a transaction by ID
     pub async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
         self.storage.get_transaction(transaction_id).await
@@ -96,77 +738,416 @@ impl<S: LedgerStorage> TransactionManager<S> {
             .ok_or_else(|| LedgerError::TransactionNotFound(transaction_id.to_string()))
     }
 
-    /// Get transactions for a specific account
+    /// Get posted transactions for a specific account
     pub async fn get_account_transactions(
         &self,
         account_id: &str,
         start_date: Option<NaiveDate>,
         end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        Ok(self
+            .storage
+            .get_account_transactions(account_id, start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.is_posted())
+            .collect())
+    }
+
+    /// Get transactions of every status for a specific account
+    pub async fn get_all_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
     ) -> LedgerResult<Vec<Transaction>> {
         self.storage
             .get_account_transactions(account_id, start_date, end_date)
             .await
     }
 
-    /// Get all transactions within a date range
-    pub async fn get_transactions(
+    /// Get all posted transactions within a date range
+    pub async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        Ok(self
+            .storage
+            .get_transactions(start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.is_posted())
+            .collect())
+    }
+
+    /// Get transactions of every status within a date range
+    pub async fn get_all_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.storage.get_transactions(start_date, end_date).await
+    }
+
+    /// Link a supporting document to a transaction
+    ///
+    /// Attachments do not change the books, so this is allowed for posted
+    /// transactions and in locked periods, and `updated_at` is left alone.
+    pub async fn add_attachment(
+        &mut self,
+        transaction_id: &str,
+        attachment: Attachment,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_transaction_required(transaction_id).await?;
+        if transaction
+            .attachments
+            .iter()
+            .any(|a| a.id == attachment.id)
+        {
+            return Err(LedgerError::Validation(format!(
+                "Attachment '{}' is already linked to transaction '{}'",
+                attachment.id, transaction_id
+            )));
+        }
+        transaction.attachments.push(attachment);
+        self.storage.update_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Unlink a supporting document from a transaction
+    ///
+    /// The transaction is revalidated, so a validator requiring attachments
+    /// blocks removing the last one. Like adding one, this leaves
+    /// `updated_at` alone and is allowed in locked periods.
+    pub async fn remove_attachment(
+        &mut self,
+        transaction_id: &str,
+        attachment_id: &str,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_transaction_required(transaction_id).await?;
+        let count = transaction.attachments.len();
+        transaction.attachments.retain(|a| a.id != attachment_id);
+        if transaction.attachments.len() == count {
+            return Err(LedgerError::Validation(format!(
+                "Attachment '{}' is not linked to transaction '{}'",
+                attachment_id, transaction_id
+            )));
+        }
+        self.validator.validate_transaction(&transaction)?;
+        self.storage.update_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Reject a correction link to a missing transaction or to itself
+    async fn ensure_correction_target(&self, transaction: &Transaction) -> LedgerResult<()> {
+        match &transaction.corrects {
+            Some(target) if target == &transaction.id => Err(LedgerError::Validation(format!(
+                "Transaction '{}' cannot correct itself",
+                transaction.id
+            ))),
+            Some(target) => self.get_transaction_required(target).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Append a timestamped note to a transaction
+    ///
+    /// Notes do not change the books, so this is allowed for posted
+    /// transactions and in locked periods. The note carries its own
+    /// timestamp and the transaction's `updated_at` is left alone, so an
+    /// annotated transaction in a locked period is not reported as edited.
+    pub async fn add_note(
+        &mut self,
+        transaction_id: &str,
+        text: String,
+        author: Option<String>,
+    ) -> LedgerResult<Transaction> {
+        if text.trim().is_empty() {
+            return Err(LedgerError::Validation(
+                "Note text cannot be empty".to_string(),
+            ));
+        }
+        let mut transaction = self.get_transaction_required(transaction_id).await?;
+        transaction.notes.push(TransactionNote {
+            text,
+            author,
+            created_at: self.clock.now(),
+        });
+        self.storage.update_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Replace a transaction's metadata, leaving its entries and status as
+    /// they are
+    ///
+    /// Nothing is revalidated or reposted, so this also works for posted
+    /// transactions in locked periods.
+    pub(crate) async fn update_metadata(
+        &mut self,
+        transaction_id: &str,
+        metadata: Metadata,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_transaction_required(transaction_id).await?;
+        transaction.metadata = metadata;
+        self.storage.update_transaction(&transaction).await?;
+        Ok(transaction)
+    }
+
+    /// Transactions of any status that directly correct a transaction,
+    /// ordered by date
+    pub async fn get_corrections(&self, transaction_id: &str) -> LedgerResult<Vec<Transaction>> {
+        let mut corrections: Vec<Transaction> = self
+            .storage
+            .get_transactions(None, None)
+            .await?
+            .into_iter()
+            .filter(|t| t.corrects.as_deref() == Some(transaction_id))
+            .collect();
+        corrections.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        Ok(corrections)
+    }
+
+    /// The whole correction chain a transaction belongs to: the original
+    /// transaction first, followed by every correction of it and of its
+    /// corrections, breadth first
+    pub async fn get_correction_chain(
+        &self,
+        transaction_id: &str,
+    ) -> LedgerResult<Vec<Transaction>> {
+        let all: HashMap<String, Transaction> = self
+            .storage
+            .get_transactions(None, None)
+            .await?
+            .into_iter()
+            .map(|t| (t.id.clone(), t))
+            .collect();
+        let mut root = all
+            .get(transaction_id)
+            .ok_or_else(|| LedgerError::TransactionNotFound(transaction_id.to_string()))?;
+        let mut visited = HashSet::from([root.id.clone()]);
+        while let Some(parent) = root.corrects.as_ref().and_then(|id| all.get(id)) {
+            if !visited.insert(parent.id.clone()) {
+                break;
+            }
+            root = parent;
+        }
+
+        let mut corrections: HashMap<&str, Vec<&Transaction>> = HashMap::new();
+        for transaction in all.values() {
+            if let Some(target) = &transaction.corrects {
+                corrections
+                    .entry(target.as_str())
+                    .or_default()
+                    .push(transaction);
+            }
+        }
+        for children in corrections.values_mut() {
+            children.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        }
+
+        let mut chain = vec![root.clone()];
+        let mut seen = HashSet::from([root.id.clone()]);
+        let mut index = 0;
+        while index < chain.len() {
+            let id = chain[index].id.clone();
+            for child in corrections.get(id.as_str()).into_iter().flatten() {
+                if seen.insert(child.id.clone()) {
+                    chain.push((*child).clone());
+                }
+            }
+            index += 1;
+        }
+        Ok(chain)
+    }
+
+    /// Tag a transaction
+    ///
+    /// Tags do not change the books, so this is allowed in locked periods
+    /// and leaves `updated_at` alone; the same goes for the other tag edits.
+    pub async fn add_tag(&mut self, transaction_id: &str, tag: &str) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_transaction_required(transaction_id).await?;
+        if transaction.add_tag(tag)? {
+            self.storage.update_transaction(&transaction).await?;
+        }
+        Ok(transaction)
+    }
+
+    /// Remove a tag from a transaction
+    pub async fn remove_tag(
+        &mut self,
+        transaction_id: &str,
+        tag: &str,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = self.get_transaction_required(transaction_id).await?;
+        if transaction.remove_tag(tag)? {
+            self.storage.update_transaction(&transaction).await?;
+        }
+        Ok(transaction)
+    }
+
+    /// All tags in use with the number of transactions carrying each
+    pub async fn list_tags(&self) -> LedgerResult<BTreeMap<String, usize>> {
+        let mut tags = BTreeMap::new();
+        for transaction in self.storage.get_transactions(None, None).await? {
+            for tag in transaction.tags {
+                *tags.entry(tag).or_insert(0) += 1;
+            }
+        }
+        Ok(tags)
+    }
+
+    /// Transactions of any status carrying a tag within a date range
+    pub async fn get_transactions_by_tag(
         &self,
+        tag: &str,
         start_date: Option<NaiveDate>,
         end_date: Option<NaiveDate>,
     ) -> LedgerResult<Vec<Transaction>> {
-        self.storage.get_transactions(start_date, end_date).await
+        let tag = normalize_tag(tag)?;
+        let mut transactions: Vec<Transaction> = self
+            .storage
+            .get_transactions(start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.tags.contains(&tag))
+            .collect();
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        Ok(transactions)
+    }
+
+    /// Rename a tag on every transaction carrying it; returns the number of
+    /// transactions changed
+    pub async fn rename_tag(&mut self, from: &str, to: &str) -> LedgerResult<usize> {
+        let to = normalize_tag(to)?;
+        let transactions = self.get_transactions_by_tag(from, None, None).await?;
+        for mut transaction in transactions.iter().cloned() {
+            transaction.remove_tag(from)?;
+            transaction.tags.insert(to.clone());
+            self.storage.update_transaction(&transaction).await?;
+        }
+        Ok(transactions.len())
+    }
+
+    /// Remove a tag from every transaction carrying it; returns the number of
+    /// transactions changed
+    pub async fn delete_tag(&mut self, tag: &str) -> LedgerResult<usize> {
+        let transactions = self.get_transactions_by_tag(tag, None, None).await?;
+        for mut transaction in transactions.iter().cloned() {
+            transaction.remove_tag(tag)?;
+            self.storage.update_transaction(&transaction).await?;
+        }
+        Ok(transactions.len())
+    }
+
+    /// List individual entries matching a filter
+    pub async fn get_entries(&self, filter: &EntryFilter) -> LedgerResult<Vec<EntryRow>> {
+        self.storage.get_entries(filter).await
+    }
+
+    /// Find an entry by its id, whatever the status of its transaction
+    pub async fn find_entry(&self, entry_id: &str) -> LedgerResult<Option<EntryRow>> {
+        let filter = EntryFilter {
+            include_unposted: true,
+            ..EntryFilter::default()
+        };
+        Ok(self
+            .storage
+            .get_entries(&filter)
+            .await?
+            .into_iter()
+            .find(|row| row.entry.id == entry_id))
     }
 
     /// Update a transaction (requires reversing old entries and applying new ones)
+    ///
+    /// The status cannot be changed here: drafts are posted with
+    /// [`post_draft`](Self::post_draft) and pending transactions go through
+    /// approval or rejection.
     pub async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
         // Get the existing transaction
         let old_transaction = self.get_transaction_required(&transaction.id).await?;
+        if transaction.status != old_transaction.status {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' cannot change status from {:?} to {:?} by an update",
+                transaction.id, old_transaction.status, transaction.status
+            )));
+        }
 
         // Validate the new transaction
         self.validator.validate_transaction(transaction)?;
         self.validator.validate_account_references(transaction)?;
+        self.ensure_unlocked(old_transaction.date)?;
+        self.ensure_unlocked(transaction.date)?;
+        self.ensure_archived_entries_unchanged(&old_transaction, transaction)
+            .await?;
+        self.check_replacement_constraints(
+            Some(transaction).filter(|t| t.is_posted()),
+            Some(&old_transaction).filter(|t| t.is_posted()),
+        )
+        .await?;
 
-        // Reverse the effects of the old transaction
-        for entry in &old_transaction.entries {
-            if let Some(mut account) = self.storage.get_account(&entry.account_id).await? {
-                // Reverse the entry by applying the opposite
-                let reverse_type = match entry.entry_type {
-                    EntryType::Debit => EntryType::Credit,
-                    EntryType::Credit => EntryType::Debit,
-                };
-                account.apply_entry(reverse_type, &entry.amount);
-                self.storage.update_account(&account).await?;
-            }
+        // Reverse the effects of the old transaction, then apply the new one
+        if old_transaction.is_posted() {
+            self.reverse_entries(&old_transaction).await?;
+        }
+        if transaction.is_posted() {
+            self.apply_entries(transaction).await?;
         }
 
-        // Apply the effects of the new transaction
+        // Update the transaction in storage
+        let mut transaction = transaction.clone();
+        transaction.assign_entry_ids();
+        self.storage.update_transaction(&transaction).await
+    }
+
+    /// Reject edits that would add or change postings on archived accounts
+    async fn ensure_archived_entries_unchanged(
+        &self,
+        old_transaction: &Transaction,
+        transaction: &Transaction,
+    ) -> LedgerResult<()> {
+        let mut checked: Vec<&str> = Vec::new();
         for entry in &transaction.entries {
-            if let Some(mut account) = self.storage.get_account(&entry.account_id).await? {
-                account.apply_entry(entry.entry_type.clone(), &entry.amount);
-                self.storage.update_account(&account).await?;
+            if checked.contains(&entry.account_id.as_str()) {
+                continue;
+            }
+            checked.push(&entry.account_id);
+            if self
+                .get_account_required(&entry.account_id)
+                .await?
+                .is_active()
+            {
+                continue;
             }
-        }
 
-        // Update the transaction in storage
-        self.storage.update_transaction(transaction).await
+            let entries_on = |t: &Transaction| -> Vec<(EntryType, BigDecimal)> {
+                t.entries
+                    .iter()
+                    .filter(|e| e.account_id == entry.account_id)
+                    .map(|e| (e.entry_type.clone(), e.amount.clone()))
+                    .collect()
+            };
+            if entries_on(old_transaction) != entries_on(transaction)
+                || old_transaction.is_posted() != transaction.is_posted()
+            {
+                return Err(LedgerError::AccountArchived(entry.account_id.clone()));
+            }
+        }
+        Ok(())
     }
 
     /// Delete a transaction (reverses its effects on account balances)
     pub async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
         // Get the transaction to be deleted
         let transaction = self.get_transaction_required(transaction_id).await?;
+        self.ensure_unlocked(transaction.date)?;
 
         // Reverse the effects on account balances
-        for entry in &transaction.entries {
-            if let Some(mut account) = self.storage.get_account(&entry.account_id).await? {
-                // Reverse the entry by applying the opposite
-                let reverse_type = match entry.entry_type {
-                    EntryType::Debit => EntryType::Credit,
-                    EntryType::Credit => EntryType::Debit,
-                };
-                account.apply_entry(reverse_type, &entry.amount);
-                self.storage.update_account(&account).await?;
-            }
+        if transaction.is_posted() {
+            self.check_replacement_constraints(None, Some(&transaction))
+                .await?;
+            self.reverse_entries(&transaction).await?;
         }
 
         // Delete the transaction from storage
@@ -174,10 +1155,38 @@ impl<S: LedgerStorage> TransactionManager<S> {
     }
 }
 
+/// Projected outcome of posting a transaction, produced without persisting it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SimulationResult {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    /// One change per affected account, in order of first appearance
+    pub changes: Vec<BalanceChange>,
+}
+
+/// Projected balance change for a single account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BalanceChange {
+    pub account_id: String,
+    pub account_name: String,
+    pub account_type: AccountType,
+    pub balance_before: BigDecimal,
+    /// Net signed change in the account's normal balance direction
+    pub change: BigDecimal,
+    pub balance_after: BigDecimal,
+}
+
 /// Transaction builder for creating complex transactions
 #[derive(Debug)]
 pub struct TransactionBuilder {
     transaction: Transaction,
+    stamped_at: Option<DateTime<Utc>>,
+    amount_policy: Option<AmountPolicy>,
+    round_off_policy: Option<RoundOffPolicy>,
+    /// First error met while adding entries, reported by `build`
+    error: Option<LedgerError>,
 }
 
 impl TransactionBuilder {
@@ -185,7 +1194,38 @@ impl TransactionBuilder {
     pub fn new(id: String, date: NaiveDate, description: String) -> Self {
         Self {
             transaction: Transaction::new(id, date, description, None),
+            stamped_at: None,
+            amount_policy: None,
+            round_off_policy: None,
+            error: None,
+        }
+    }
+
+    /// Take the created and updated timestamps from `clock`
+    pub fn clock(mut self, clock: &dyn Clock) -> Self {
+        self.stamped_at = Some(clock.now());
+        self
+    }
+
+    /// Take the id from `generator` unless one was given to `new`
+    pub fn id_generator(mut self, generator: &dyn IdGenerator) -> Self {
+        if self.transaction.id.is_empty() {
+            self.transaction.id = generator.next_id();
         }
+        self
+    }
+
+    /// Round every entry amount with `policy` when the transaction is built
+    pub fn amount_policy(mut self, policy: &AmountPolicy) -> Self {
+        self.amount_policy = Some(policy.clone());
+        self
+    }
+
+    /// Post a small imbalance to the round-off account when the transaction
+    /// is built instead of rejecting it
+    pub fn round_off(mut self, policy: &RoundOffPolicy) -> Self {
+        self.round_off_policy = Some(policy.clone());
+        self
     }
 
     /// Set the reference for the transaction
@@ -194,9 +1234,27 @@ impl TransactionBuilder {
         self
     }
 
+    /// Set the voucher type of the transaction
+    pub fn kind(mut self, kind: TransactionKind) -> Self {
+        self.transaction.kind = kind;
+        self
+    }
+
     /// Add metadata to the transaction
-    pub fn metadata(mut self, key: String, value: String) -> Self {
-        self.transaction.metadata.insert(key, value);
+    pub fn metadata(mut self, key: String, value: impl Into<MetaValue>) -> Self {
+        self.transaction.metadata.insert(key, value.into());
+        self
+    }
+
+    /// Mark the transaction as correcting another one
+    pub fn corrects(mut self, transaction_id: String) -> Self {
+        self.transaction.corrects = Some(transaction_id);
+        self
+    }
+
+    /// Link a supporting document to the transaction
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.transaction.attachments.push(attachment);
         self
     }
 
@@ -230,9 +1288,78 @@ impl TransactionBuilder {
         self
     }
 
+    /// Debit an amount split across accounts in proportion to their weights
+    ///
+    /// See [`AmountPolicy::allocate`]; shares are cut to the precision of
+    /// the builder's amount policy if one was set before this call, or to
+    /// two decimal places otherwise.
+    pub fn allocate_debit(
+        self,
+        amount: &BigDecimal,
+        shares: &[(String, BigDecimal)],
+        description: Option<String>,
+    ) -> Self {
+        self.allocate(EntryType::Debit, amount, shares, description)
+    }
+
+    /// Credit an amount split across accounts in proportion to their weights
+    ///
+    /// See [`TransactionBuilder::allocate_debit`].
+    pub fn allocate_credit(
+        self,
+        amount: &BigDecimal,
+        shares: &[(String, BigDecimal)],
+        description: Option<String>,
+    ) -> Self {
+        self.allocate(EntryType::Credit, amount, shares, description)
+    }
+
+    fn allocate(
+        mut self,
+        entry_type: EntryType,
+        amount: &BigDecimal,
+        shares: &[(String, BigDecimal)],
+        description: Option<String>,
+    ) -> Self {
+        let policy = self.amount_policy.clone().unwrap_or_default();
+        let weights: Vec<BigDecimal> = shares.iter().map(|(_, weight)| weight.clone()).collect();
+        match policy.allocate(amount, &weights) {
+            Ok(amounts) => {
+                for ((account_id, _), amount) in shares.iter().zip(amounts) {
+                    if amount.is_zero() {
+                        continue;
+                    }
+                    self.transaction.add_entry(Entry::new(
+                        account_id.clone(),
+                        entry_type.clone(),
+                        amount,
+                        description.clone(),
+                    ));
+                }
+            }
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+
     /// Build the transaction
-    pub fn build(self) -> LedgerResult<Transaction> {
+    pub fn build(mut self) -> LedgerResult<Transaction> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if let Some(policy) = &self.amount_policy {
+            policy.round_transaction(&mut self.transaction);
+        }
+        if let Some(policy) = &self.round_off_policy {
+            policy.balance(&mut self.transaction);
+        }
         self.transaction.validate()?;
+        if let Some(now) = self.stamped_at {
+            self.transaction.created_at = now;
+            self.transaction.updated_at = now;
+        }
         Ok(self.transaction)
     }
 }
@@ -251,6 +1378,7 @@ pub mod patterns {
         amount: BigDecimal,
     ) -> LedgerResult<Transaction> {
         TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Payment)
             .debit(expense_account_id, amount.clone(), None)
             .credit(cash_account_id, amount, None)
             .build()
@@ -266,6 +1394,7 @@ pub mod patterns {
         amount: BigDecimal,
     ) -> LedgerResult<Transaction> {
         TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Sales)
             .debit(cash_or_receivables_account_id, amount.clone(), None)
             .credit(revenue_account_id, amount, None)
             .build()
@@ -281,6 +1410,7 @@ pub mod patterns {
         amount: BigDecimal,
     ) -> LedgerResult<Transaction> {
         TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Purchase)
             .debit(asset_account_id, amount.clone(), None)
             .credit(cash_or_payables_account_id, amount, None)
             .build()
@@ -291,6 +1421,7 @@ pub mod patterns {
         let total_amount = &params.base_amount + &params.gst_amount;
 
         TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Sales)
             .debit(
                 params.receivables_account_id,
                 total_amount,
@@ -316,6 +1447,7 @@ pub mod patterns {
         let total_amount = &params.base_amount + &params.gst_amount;
 
         TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Purchase)
             .debit(
                 params.expense_account_id,
                 params.base_amount,
@@ -334,6 +1466,103 @@ pub mod patterns {
             .build()
     }
 
+    /// GST components (CGST, SGST, IGST and cess) with their accounts,
+    /// skipping those that are zero
+    pub(crate) fn gst_legs(
+        accounts: &GstAccounts,
+        [cgst, sgst, igst]: [&BigDecimal; 3],
+        cess_amount: &BigDecimal,
+        side: &str,
+    ) -> LedgerResult<Vec<(String, BigDecimal, String)>> {
+        let mut legs = Vec::new();
+        for (name, account_id, amount) in [
+            ("CGST", &accounts.cgst_account_id, cgst),
+            ("SGST", &accounts.sgst_account_id, sgst),
+            ("IGST", &accounts.igst_account_id, igst),
+        ] {
+            if !amount.is_zero() {
+                legs.push((account_id.clone(), amount.clone(), format!("{name} {side}")));
+            }
+        }
+        if !cess_amount.is_zero() {
+            let account_id = accounts.cess_account_id.clone().ok_or_else(|| {
+                LedgerError::Validation("Cess charged without a cess account".to_string())
+            })?;
+            legs.push((account_id, cess_amount.clone(), format!("Cess {side}")));
+        }
+        Ok(legs)
+    }
+
+    /// Create a sale crediting each GST component to its own output account
+    /// (debit receivable, credit revenue, CGST, SGST, IGST and cess)
+    pub fn create_gst_sale(params: GstSaleParams) -> LedgerResult<Transaction> {
+        let calculation = &params.calculation;
+        let legs = gst_legs(
+            &params.output_accounts,
+            [
+                &calculation.cgst_amount,
+                &calculation.sgst_amount,
+                &calculation.igst_amount,
+            ],
+            &params.cess_amount,
+            "output",
+        )?;
+        let total_amount = &calculation.total_amount + &params.cess_amount;
+
+        let mut builder = TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Sales)
+            .debit(
+                params.receivables_account_id,
+                total_amount,
+                Some("Total including GST".to_string()),
+            )
+            .credit(
+                params.revenue_account_id,
+                calculation.base_amount.clone(),
+                Some("Revenue amount".to_string()),
+            );
+        for (account_id, amount, description) in legs {
+            builder = builder.credit(account_id, amount, Some(description));
+        }
+        builder.build()
+    }
+
+    /// Create a purchase debiting each GST component to its own input
+    /// account (debit expense, CGST, SGST, IGST and cess, credit cash or
+    /// payable)
+    pub fn create_gst_purchase(params: GstPurchaseParams) -> LedgerResult<Transaction> {
+        let calculation = &params.calculation;
+        let legs = gst_legs(
+            &params.input_accounts,
+            [
+                &calculation.cgst_amount,
+                &calculation.sgst_amount,
+                &calculation.igst_amount,
+            ],
+            &params.cess_amount,
+            "input",
+        )?;
+        let total_amount = &calculation.total_amount + &params.cess_amount;
+
+        let mut builder = TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Purchase)
+            .debit(
+                params.expense_account_id,
+                calculation.base_amount.clone(),
+                Some("Expense amount".to_string()),
+            );
+        for (account_id, amount, description) in legs {
+            builder = builder.debit(account_id, amount, Some(description));
+        }
+        builder
+            .credit(
+                params.cash_or_payables_account_id,
+                total_amount,
+                Some("Total payment".to_string()),
+            )
+            .build()
+    }
+
     /// Create a loan transaction
     pub fn create_loan_received(
         id: String,
@@ -344,6 +1573,7 @@ pub mod patterns {
         amount: BigDecimal,
     ) -> LedgerResult<Transaction> {
         TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Receipt)
             .debit(
                 cash_account_id,
                 amount.clone(),
@@ -367,6 +1597,7 @@ pub mod patterns {
         amount: BigDecimal,
     ) -> LedgerResult<Transaction> {
         TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Receipt)
             .debit(
                 cash_account_id,
                 amount.clone(),
@@ -379,4 +1610,311 @@ pub mod patterns {
             )
             .build()
     }
+
+    /// Create a bank transfer (contra entry)
+    ///
+    /// Without an in-transit account the money moves directly between the two
+    /// banks. With one, the sending bank is credited against funds in transit
+    /// and [`create_transfer_clearing`] completes the transfer once the
+    /// receiving bank confirms it.
+    pub fn create_bank_transfer(params: BankTransferParams) -> LedgerResult<Transaction> {
+        let debit_account_id = params
+            .in_transit_account_id
+            .unwrap_or(params.to_bank_account_id);
+
+        TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Contra)
+            .debit(
+                debit_account_id,
+                params.amount.clone(),
+                Some("Transfer in".to_string()),
+            )
+            .credit(
+                params.from_bank_account_id,
+                params.amount,
+                Some("Transfer out".to_string()),
+            )
+            .build()
+    }
+
+    /// Clear funds in transit into the receiving bank once it confirms receipt
+    pub fn create_transfer_clearing(
+        id: String,
+        date: NaiveDate,
+        description: String,
+        in_transit_account_id: String,
+        to_bank_account_id: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Contra)
+            .debit(
+                to_bank_account_id,
+                amount.clone(),
+                Some("Transfer received".to_string()),
+            )
+            .credit(
+                in_transit_account_id,
+                amount,
+                Some("Funds in transit cleared".to_string()),
+            )
+            .build()
+    }
+
+    /// Write off an uncollectible receivable directly to bad debt expense
+    pub fn create_direct_write_off(
+        id: String,
+        date: NaiveDate,
+        description: String,
+        bad_debt_expense_account_id: String,
+        receivables_account_id: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        TransactionBuilder::new(id, date, description)
+            .debit(
+                bad_debt_expense_account_id,
+                amount.clone(),
+                Some("Bad debt written off".to_string()),
+            )
+            .credit(
+                receivables_account_id,
+                amount,
+                Some("Receivable written off".to_string()),
+            )
+            .build()
+    }
+
+    /// Provide for doubtful debts under the allowance method
+    pub fn create_bad_debt_provision(
+        id: String,
+        date: NaiveDate,
+        description: String,
+        bad_debt_expense_account_id: String,
+        allowance_account_id: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        TransactionBuilder::new(id, date, description)
+            .kind(TransactionKind::Adjustment)
+            .debit(
+                bad_debt_expense_account_id,
+                amount.clone(),
+                Some("Provision for doubtful debts".to_string()),
+            )
+            .credit(
+                allowance_account_id,
+                amount,
+                Some("Allowance for doubtful debts".to_string()),
+            )
+            .build()
+    }
+
+    /// Write off a receivable against an existing allowance for doubtful debts
+    pub fn create_allowance_write_off(
+        id: String,
+        date: NaiveDate,
+        description: String,
+        allowance_account_id: String,
+        receivables_account_id: String,
+        amount: BigDecimal,
+    ) -> LedgerResult<Transaction> {
+        TransactionBuilder::new(id, date, description)
+            .debit(
+                allowance_account_id,
+                amount.clone(),
+                Some("Allowance utilised".to_string()),
+            )
+            .credit(
+                receivables_account_id,
+                amount,
+                Some("Receivable written off".to_string()),
+            )
+            .build()
+    }
+
+    /// Write off a small residual on a party account if it is within the threshold
+    pub fn create_small_balance_write_off(
+        params: SmallBalanceWriteOffParams,
+    ) -> LedgerResult<Transaction> {
+        let amount = params.residual.abs();
+        if amount > params.threshold {
+            return Err(LedgerError::Validation(format!(
+                "Residual {} exceeds the write-off threshold {}",
+                amount, params.threshold
+            )));
+        }
+
+        let builder = TransactionBuilder::new(params.id, params.date, params.description);
+        let builder = if params.residual.is_positive() {
+            // Party owes a trifle: expense it and clear the debit balance
+            builder
+                .debit(params.write_off_account_id, amount.clone(), None)
+                .credit(params.party_account_id, amount, None)
+        } else {
+            // We hold a trifling overpayment: clear it to income
+            builder
+                .debit(params.party_account_id, amount.clone(), None)
+                .credit(params.write_off_account_id, amount, None)
+        };
+        builder.build()
+    }
+
+    /// Receive payment of a receivable net of a cash discount allowed
+    pub fn create_settlement_with_discount_allowed(
+        params: SettlementDiscountParams,
+    ) -> LedgerResult<Transaction> {
+        let received = &params.settled_amount - &params.discount;
+
+        TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Receipt)
+            .debit(
+                params.cash_account_id,
+                received,
+                Some("Amount received".to_string()),
+            )
+            .debit(
+                params.discount_account_id,
+                params.discount,
+                Some("Discount allowed".to_string()),
+            )
+            .credit(
+                params.party_account_id,
+                params.settled_amount,
+                Some("Receivable settled".to_string()),
+            )
+            .build()
+    }
+
+    /// Pay a payable net of a cash discount received
+    pub fn create_settlement_with_discount_received(
+        params: SettlementDiscountParams,
+    ) -> LedgerResult<Transaction> {
+        let paid = &params.settled_amount - &params.discount;
+
+        TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Payment)
+            .debit(
+                params.party_account_id,
+                params.settled_amount,
+                Some("Payable settled".to_string()),
+            )
+            .credit(
+                params.cash_account_id,
+                paid,
+                Some("Amount paid".to_string()),
+            )
+            .credit(
+                params.discount_account_id,
+                params.discount,
+                Some("Discount received".to_string()),
+            )
+            .build()
+    }
+
+    /// Create a transaction reversing every entry of `original`
+    pub fn create_reversal(
+        original: &Transaction,
+        id: String,
+        date: NaiveDate,
+        description: String,
+    ) -> LedgerResult<Transaction> {
+        let mut builder = TransactionBuilder::new(id, date, description)
+            .kind(original.kind)
+            .corrects(original.id.clone())
+            .metadata("reverses".to_string(), original.id.clone());
+        if let Some(reference) = &original.reference {
+            builder = builder.reference(reference.clone());
+        }
+        for entry in &original.entries {
+            let reversed_type = match entry.entry_type {
+                EntryType::Debit => EntryType::Credit,
+                EntryType::Credit => EntryType::Debit,
+            };
+            builder = builder.entry(Entry {
+                id: new_entry_id(),
+                entry_type: reversed_type,
+                ..entry.clone()
+            });
+        }
+        builder.build()
+    }
+
+    /// Charge interest on an overdue receivable together with the GST on it
+    /// (debit receivable, credit interest income and GST payable)
+    pub fn create_overdue_interest_charge(
+        params: OverdueInterestChargeParams,
+    ) -> LedgerResult<Transaction> {
+        let gst_amount = params
+            .amount_policy
+            .round(&(&params.interest * &params.gst_rate / BigDecimal::from(100)));
+        let total_amount = &params.interest + &gst_amount;
+
+        let mut builder = TransactionBuilder::new(params.id, params.date, params.description)
+            .kind(TransactionKind::Sales)
+            .debit(
+                params.receivables_account_id,
+                total_amount,
+                Some("Interest on overdue invoice".to_string()),
+            )
+            .credit(
+                params.interest_income_account_id,
+                params.interest,
+                Some("Interest income".to_string()),
+            );
+        if !gst_amount.is_zero() {
+            builder = builder.credit(
+                params.gst_payable_account_id,
+                gst_amount,
+                Some("GST on interest".to_string()),
+            );
+        }
+        builder.build()
+    }
+
+    /// Create the mirrored intercompany transactions: a due-from in the source
+    /// entity and a due-to in the target entity
+    ///
+    /// The intercompany entries carry the counterparty entity as a dimension
+    /// and both transactions share the same ID and `intercompany_ref`.
+    pub fn create_intercompany_pair(
+        params: IntercompanyParams,
+    ) -> LedgerResult<(Transaction, Transaction)> {
+        let counterparty = crate::ledger::COUNTERPARTY_ENTITY_DIMENSION.to_string();
+
+        let source =
+            TransactionBuilder::new(params.id.clone(), params.date, params.description.clone())
+                .metadata("intercompany_ref".to_string(), params.id.clone())
+                .entry(
+                    Entry::debit(
+                        params.source_due_from_account_id,
+                        params.amount.clone(),
+                        Some(format!("Due from {}", params.target_entity_id)),
+                    )
+                    .with_dimension(counterparty.clone(), params.target_entity_id.clone()),
+                )
+                .credit(
+                    params.source_counter_account_id,
+                    params.amount.clone(),
+                    None,
+                )
+                .build()?;
+
+        let target = TransactionBuilder::new(params.id.clone(), params.date, params.description)
+            .metadata("intercompany_ref".to_string(), params.id)
+            .debit(
+                params.target_counter_account_id,
+                params.amount.clone(),
+                None,
+            )
+            .entry(
+                Entry::credit(
+                    params.target_due_to_account_id,
+                    params.amount,
+                    Some(format!("Due to {}", params.source_entity_id)),
+                )
+                .with_dimension(counterparty, params.source_entity_id),
+            )
+            .build()?;
+
+        Ok((source, target))
+    }
 }
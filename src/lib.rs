@@ -8,6 +8,8 @@
 //! - **Double-entry bookkeeping**: Complete transaction validation and balance tracking
 //! - **Account management**: Support for Assets, Liabilities, Equity, Income, and Expense accounts
 //! - **GST calculations**: Indian GST compliance with CGST/SGST/IGST support
+//! - **Invoicing**: Sales invoices, quotations and supplier bills with payment terms
+//! - **Inventory**: Stock items with FIFO/weighted average costing and COGS postings
 //! - **Financial reporting**: Balance sheets, income statements, and trial balance generation
 //! - **Reconciliation**: Bank statement and payment gateway reconciliation
 //! - **Storage abstraction**: Database-agnostic design with trait-based storage
@@ -24,14 +26,25 @@
 //! // let mut ledger = Ledger::new(storage);
 //! ```
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod export;
+pub mod inventory;
 pub mod ledger;
+pub mod migrations;
+pub mod notifications;
+pub mod payables;
+pub mod receivables;
 pub mod reconciliation;
 pub mod tax;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod traits;
 pub mod types;
 pub mod utils;
 
 // Re-export commonly used types
+pub use inventory::*;
 pub use ledger::*;
 pub use tax::gst::*;
 pub use traits::*;
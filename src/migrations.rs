@@ -0,0 +1,311 @@
+//! Versioned serialization and schema migrations
+//!
+//! [`to_versioned`] writes a value as a JSON object carrying a
+//! `schema_version` field. [`from_versioned`] reads one back, first running
+//! the migrations that upgrade payloads written by earlier crate versions,
+//! so stored JSON keeps loading after the types change. Payloads without a
+//! `schema_version` predate versioning and are treated as version 0.
+//!
+//! Version 1 differs from version 0 in that:
+//!
+//! - amounts are decimal strings; version 0 payloads may hold JSON numbers,
+//!   which are converted through their shortest text form so `0.1` stays
+//!   `0.1` rather than picking up binary float noise
+//! - transactions always carry a `status`; those saved before statuses
+//!   existed were all posted and are marked so explicitly
+//!
+//! Trial balances are at version 2: their balances are listed in ordered
+//! `sections` by account type rather than as a map keyed by account ID.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::traits::{BalanceSheet, CashFlowStatement, IncomeStatement};
+use crate::types::*;
+
+/// Field holding the schema version of a serialized payload
+pub const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// Upgrades a payload object by one schema version
+pub type Migration = fn(&mut Map<String, Value>) -> LedgerResult<()>;
+
+/// A type serialized with a schema version
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Schema version written by this crate version
+    const SCHEMA_VERSION: u32;
+
+    /// Migrations in order; entry `n` upgrades version `n` to `n + 1`
+    fn migrations() -> &'static [Migration];
+}
+
+/// Serialize a value with its schema version
+pub fn to_versioned<T: Versioned>(value: &T) -> LedgerResult<Value> {
+    let mut payload =
+        serde_json::to_value(value).map_err(|e| LedgerError::InvalidPayload(e.to_string()))?;
+    let object = payload
+        .as_object_mut()
+        .ok_or_else(|| LedgerError::InvalidPayload("expected a JSON object".to_string()))?;
+    object.insert(SCHEMA_VERSION_FIELD.to_string(), T::SCHEMA_VERSION.into());
+    Ok(payload)
+}
+
+/// Upgrade a serialized payload to the current schema version of `T`
+///
+/// The returned payload carries the current version. Payloads from a newer
+/// crate version are rejected rather than guessed at.
+pub fn migrate<T: Versioned>(mut payload: Value) -> LedgerResult<Value> {
+    let object = payload
+        .as_object_mut()
+        .ok_or_else(|| LedgerError::InvalidPayload("expected a JSON object".to_string()))?;
+    let found = match object.get(SCHEMA_VERSION_FIELD) {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                LedgerError::InvalidPayload(format!("invalid {SCHEMA_VERSION_FIELD}: {version}"))
+            })?,
+    };
+    if found > T::SCHEMA_VERSION {
+        return Err(LedgerError::UnsupportedSchemaVersion {
+            found,
+            supported: T::SCHEMA_VERSION,
+        });
+    }
+    for migration in &T::migrations()[found as usize..T::SCHEMA_VERSION as usize] {
+        migration(object)?;
+    }
+    object.insert(SCHEMA_VERSION_FIELD.to_string(), T::SCHEMA_VERSION.into());
+    Ok(payload)
+}
+
+/// Deserialize a payload written by this or an earlier crate version
+pub fn from_versioned<T: Versioned>(payload: Value) -> LedgerResult<T> {
+    let mut payload = migrate::<T>(payload)?;
+    if let Some(object) = payload.as_object_mut() {
+        object.remove(SCHEMA_VERSION_FIELD);
+    }
+    serde_json::from_value(payload).map_err(|e| LedgerError::InvalidPayload(e.to_string()))
+}
+
+/// Fields holding amounts, at any depth
+const AMOUNT_FIELDS: &[&str] = &[
+    "balance",
+    "amount",
+    "debit_balance",
+    "credit_balance",
+    "total_debits",
+    "total_credits",
+    "total_assets",
+    "total_liabilities",
+    "total_equity",
+    "total_revenue",
+    "total_expenses",
+    "net_income",
+    "net_operating_cash_flow",
+    "net_investing_cash_flow",
+    "net_financing_cash_flow",
+    "net_cash_flow",
+];
+
+/// Free-form maps whose keys are user data, not schema fields
+const OPAQUE_FIELDS: &[&str] = &["metadata", "dimensions"];
+
+/// Rewrite numeric amounts as decimal strings
+fn amounts_to_strings(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, field) in object.iter_mut() {
+                if OPAQUE_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                if let Value::Number(number) = field {
+                    if AMOUNT_FIELDS.contains(&key.as_str()) {
+                        *field = Value::String(number.to_string());
+                        continue;
+                    }
+                }
+                amounts_to_strings(field);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(amounts_to_strings),
+        _ => {}
+    }
+}
+
+fn v0_decimal_amounts(payload: &mut Map<String, Value>) -> LedgerResult<()> {
+    let mut value = Value::Object(std::mem::take(payload));
+    amounts_to_strings(&mut value);
+    if let Value::Object(object) = value {
+        *payload = object;
+    }
+    Ok(())
+}
+
+fn v0_transaction(payload: &mut Map<String, Value>) -> LedgerResult<()> {
+    v0_decimal_amounts(payload)?;
+    payload
+        .entry("status")
+        .or_insert_with(|| Value::String("Posted".to_string()));
+    Ok(())
+}
+
+fn v1_trial_balance_sections(payload: &mut Map<String, Value>) -> LedgerResult<()> {
+    let balances = payload.remove("balances").unwrap_or_default();
+    let balances: HashMap<String, AccountBalance> =
+        serde_json::from_value(balances).map_err(|e| LedgerError::InvalidPayload(e.to_string()))?;
+    let sections = TrialBalance {
+        as_of_date: NaiveDate::default(),
+        balances,
+        total_debits: BigDecimal::zero(),
+        total_credits: BigDecimal::zero(),
+        is_balanced: true,
+    }
+    .sections();
+    let sections =
+        serde_json::to_value(sections).map_err(|e| LedgerError::InvalidPayload(e.to_string()))?;
+    payload.insert("sections".to_string(), sections);
+    Ok(())
+}
+
+impl Versioned for Account {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [Migration] {
+        &[v0_decimal_amounts]
+    }
+}
+
+impl Versioned for Transaction {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [Migration] {
+        &[v0_transaction]
+    }
+}
+
+impl Versioned for TrialBalance {
+    const SCHEMA_VERSION: u32 = 2;
+
+    fn migrations() -> &'static [Migration] {
+        &[v0_decimal_amounts, v1_trial_balance_sections]
+    }
+}
+
+impl Versioned for BalanceSheet {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [Migration] {
+        &[v0_decimal_amounts]
+    }
+}
+
+impl Versioned for IncomeStatement {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [Migration] {
+        &[v0_decimal_amounts]
+    }
+}
+
+impl Versioned for CashFlowStatement {
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn migrations() -> &'static [Migration] {
+        &[v0_decimal_amounts]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_unversioned_transaction_is_upgraded() {
+        let legacy = json!({
+            "id": "t1",
+            "date": "2024-01-05",
+            "description": "Opening capital",
+            "reference": null,
+            "metadata": {"amount": 7},
+            "entries": [
+                {"account_id": "1000", "entry_type": "Debit", "amount": 1000.1, "description": null},
+                {"account_id": "3000", "entry_type": "Credit", "amount": 1000.1, "description": null}
+            ],
+            "created_at": "2024-01-05T10:00:00",
+            "updated_at": "2024-01-05T10:00:00"
+        });
+        let transaction: Transaction = from_versioned(legacy).unwrap();
+        assert_eq!(transaction.status, TransactionStatus::Posted);
+        assert_eq!(
+            transaction.entries[0].amount,
+            BigDecimal::from_str("1000.1").unwrap()
+        );
+        assert_eq!(transaction.metadata["amount"], MetaValue::Integer(7));
+
+        let stored = to_versioned(&transaction).unwrap();
+        assert_eq!(stored[SCHEMA_VERSION_FIELD], 1);
+        assert_eq!(from_versioned::<Transaction>(stored).unwrap(), transaction);
+    }
+
+    #[test]
+    fn test_keyed_trial_balance_is_upgraded() {
+        let account = |id: &str, account_type| {
+            serde_json::to_value(Account::new(
+                id.to_string(),
+                id.to_string(),
+                account_type,
+                None,
+            ))
+            .unwrap()
+        };
+        let legacy = json!({
+            "schema_version": 1,
+            "as_of_date": "2024-03-31",
+            "balances": {
+                "4000": {"account": account("4000", AccountType::Income), "debit_balance": null, "credit_balance": "500"},
+                "1100": {"account": account("1100", AccountType::Asset), "debit_balance": "200", "credit_balance": null},
+                "1000": {"account": account("1000", AccountType::Asset), "debit_balance": "300", "credit_balance": null}
+            },
+            "total_debits": "500",
+            "total_credits": "500",
+            "is_balanced": true
+        });
+        let trial_balance: TrialBalance = from_versioned(legacy).unwrap();
+        let order: Vec<String> = trial_balance
+            .ordered_lines()
+            .into_iter()
+            .map(|line| line.account.id)
+            .collect();
+        assert_eq!(order, ["1000", "1100", "4000"]);
+
+        let stored = to_versioned(&trial_balance).unwrap();
+        assert_eq!(stored["sections"][0]["total_debits"], "500");
+        assert_eq!(stored["sections"][1]["lines"][0]["account"]["id"], "4000");
+        assert_eq!(
+            from_versioned::<TrialBalance>(stored).unwrap(),
+            trial_balance
+        );
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let account = Account::new(
+            "1000".to_string(),
+            "Cash".to_string(),
+            AccountType::Asset,
+            None,
+        );
+        let mut stored = to_versioned(&account).unwrap();
+        stored[SCHEMA_VERSION_FIELD] = json!(Account::SCHEMA_VERSION + 1);
+        let error = from_versioned::<Account>(stored).unwrap_err();
+        assert_eq!(error.code(), "unsupported_schema_version");
+    }
+}
@@ -0,0 +1,430 @@
+//! Due-date notifications for host apps
+//!
+//! [`DueEventScheduler`] turns open invoices, GST filing deadlines and
+//! recurring transactions into typed [`DueEvent`]s and hands them to a
+//! [`NotificationSink`], which a host app implements over email, SMS, Slack
+//! or whatever it uses. Run the scheduler once a day (or less often); each
+//! run emits the events that fell due since the previous one, so nothing is
+//! sent twice and a missed day is caught up on the next run.
+
+use bigdecimal::{BigDecimal, Signed};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::receivables::OpenInvoice;
+use crate::traits::*;
+use crate::types::*;
+
+/// A monthly GST return
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum GstReturnKind {
+    /// Outward supplies, due on the 11th of the following month
+    Gstr1,
+    /// Summary return with tax payment, due on the 20th of the following month
+    Gstr3b,
+}
+
+impl GstReturnKind {
+    /// Filing deadline of the return for the month starting `period_start`;
+    /// `None` past the end of the date range
+    pub fn due_date(&self, period_start: NaiveDate) -> Option<NaiveDate> {
+        let day = match self {
+            GstReturnKind::Gstr1 => 11,
+            GstReturnKind::Gstr3b => 20,
+        };
+        let next_month = first_of_month(period_start).checked_add_months(Months::new(1))?;
+        Some(next_month.with_day0(day - 1).unwrap_or(next_month))
+    }
+}
+
+/// How often a recurring transaction falls due
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Recurrence {
+    /// Same weekday each week
+    Weekly,
+    /// Same day each month, or the month's last day when it is shorter
+    Monthly,
+    /// Every three months, on the same day as a monthly recurrence
+    Quarterly,
+    /// Same date each year; 29 February falls on the 28th in other years
+    Yearly,
+}
+
+/// A transaction the business posts on a fixed schedule, such as rent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RecurringTransaction {
+    /// Unique identifier
+    pub id: String,
+    /// What the transaction is for, passed on in its events
+    pub description: String,
+    /// Date of the first occurrence
+    pub first_date: NaiveDate,
+    /// How often it falls due
+    pub recurrence: Recurrence,
+    /// No occurrences after this date
+    pub last_date: Option<NaiveDate>,
+}
+
+impl RecurringTransaction {
+    /// Create a recurring transaction with no last date
+    pub fn new(
+        id: String,
+        description: String,
+        first_date: NaiveDate,
+        recurrence: Recurrence,
+    ) -> Self {
+        Self {
+            id,
+            description,
+            first_date,
+            recurrence,
+            last_date: None,
+        }
+    }
+
+    /// Date of the occurrence after `count` earlier ones
+    fn occurrence(&self, count: u32) -> Option<NaiveDate> {
+        match self.recurrence {
+            Recurrence::Weekly => self
+                .first_date
+                .checked_add_days(Days::new(7 * u64::from(count))),
+            Recurrence::Monthly => self.first_date.checked_add_months(Months::new(count)),
+            Recurrence::Quarterly => self.first_date.checked_add_months(Months::new(3 * count)),
+            Recurrence::Yearly => self.first_date.checked_add_months(Months::new(12 * count)),
+        }
+    }
+
+    /// Occurrences falling between two dates, inclusive
+    pub fn occurrences_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let to = self.last_date.map_or(to, |last| last.min(to));
+        (0..)
+            .map_while(|count| self.occurrence(count))
+            .take_while(|date| *date <= to)
+            .filter(|date| *date >= from)
+            .collect()
+    }
+}
+
+/// Something a host app should tell the business about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DueEvent {
+    /// An unpaid invoice falls due tomorrow
+    InvoiceDueTomorrow {
+        invoice_id: String,
+        customer_id: String,
+        due_date: NaiveDate,
+        outstanding: BigDecimal,
+    },
+    /// An unpaid invoice has passed the overdue threshold
+    InvoiceOverdue {
+        invoice_id: String,
+        customer_id: String,
+        due_date: NaiveDate,
+        days_overdue: i64,
+        outstanding: BigDecimal,
+    },
+    /// A GST return's filing deadline is approaching
+    GstReturnDue {
+        kind: GstReturnKind,
+        /// First day of the month the return covers
+        period_start: NaiveDate,
+        due_date: NaiveDate,
+    },
+    /// A recurring transaction should be posted
+    RecurringTransactionDue {
+        recurring_id: String,
+        description: String,
+        due_date: NaiveDate,
+    },
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day0(0).unwrap_or(date)
+}
+
+/// Emits [`DueEvent`]s to a [`NotificationSink`] as they fall due
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DueEventScheduler {
+    /// Days past due after which an invoice is reported overdue
+    pub overdue_after_days: u64,
+    /// Days before a GST filing deadline to announce it
+    pub gst_notice_days: u64,
+    /// Returns whose deadlines are announced
+    pub gst_returns: Vec<GstReturnKind>,
+    recurring: Vec<RecurringTransaction>,
+    /// Last date events were emitted through
+    last_run: Option<NaiveDate>,
+}
+
+impl Default for DueEventScheduler {
+    /// Overdue after 30 days, GSTR-1 and GSTR-3B announced a week ahead
+    fn default() -> Self {
+        Self {
+            overdue_after_days: 30,
+            gst_notice_days: 7,
+            gst_returns: vec![GstReturnKind::Gstr1, GstReturnKind::Gstr3b],
+            recurring: Vec::new(),
+            last_run: None,
+        }
+    }
+}
+
+impl DueEventScheduler {
+    /// Announce occurrences of a recurring transaction, replacing any with
+    /// the same ID
+    pub fn add_recurring(&mut self, recurring: RecurringTransaction) {
+        self.recurring.retain(|r| r.id != recurring.id);
+        self.recurring.push(recurring);
+    }
+
+    /// Stop announcing a recurring transaction
+    pub fn remove_recurring(&mut self, recurring_id: &str) -> Option<RecurringTransaction> {
+        let index = self.recurring.iter().position(|r| r.id == recurring_id)?;
+        Some(self.recurring.remove(index))
+    }
+
+    /// Recurring transactions being announced
+    pub fn recurring(&self) -> &[RecurringTransaction] {
+        &self.recurring
+    }
+
+    /// Last date events were emitted through
+    pub fn last_run(&self) -> Option<NaiveDate> {
+        self.last_run
+    }
+
+    /// Events that fell due between two dates, inclusive, in date order
+    ///
+    /// An invoice is announced the day before it is due and again on the
+    /// first day it is more than [`overdue_after_days`](Self::overdue_after_days)
+    /// overdue; a GST deadline [`gst_notice_days`](Self::gst_notice_days)
+    /// before it; a recurring transaction on each occurrence.
+    pub fn events_between(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        invoices: &[OpenInvoice],
+    ) -> Vec<DueEvent> {
+        let in_window = |date: Option<NaiveDate>| date.is_some_and(|d| d >= from && d <= to);
+        let mut events: Vec<(NaiveDate, DueEvent)> = Vec::new();
+
+        for invoice in invoices.iter().filter(|i| i.outstanding.is_positive()) {
+            let day_before = invoice.due_date.pred_opt();
+            if in_window(day_before) {
+                events.push((
+                    day_before.unwrap_or(to),
+                    DueEvent::InvoiceDueTomorrow {
+                        invoice_id: invoice.invoice_id.clone(),
+                        customer_id: invoice.customer_id.clone(),
+                        due_date: invoice.due_date,
+                        outstanding: invoice.outstanding.clone(),
+                    },
+                ));
+            }
+            let overdue_from = invoice
+                .due_date
+                .checked_add_days(Days::new(self.overdue_after_days + 1));
+            if in_window(overdue_from) {
+                let overdue_from = overdue_from.unwrap_or(to);
+                events.push((
+                    overdue_from,
+                    DueEvent::InvoiceOverdue {
+                        invoice_id: invoice.invoice_id.clone(),
+                        customer_id: invoice.customer_id.clone(),
+                        due_date: invoice.due_date,
+                        days_overdue: invoice.days_overdue(overdue_from),
+                        outstanding: invoice.outstanding.clone(),
+                    },
+                ));
+            }
+        }
+
+        let mut next_period = Some(
+            first_of_month(from)
+                .checked_sub_months(Months::new(2))
+                .unwrap_or(first_of_month(NaiveDate::MIN)),
+        );
+        while let Some(period_start) = next_period.filter(|start| *start <= to) {
+            for kind in &self.gst_returns {
+                let Some(due_date) = kind.due_date(period_start) else {
+                    continue;
+                };
+                let notice = due_date.checked_sub_days(Days::new(self.gst_notice_days));
+                if in_window(notice) {
+                    events.push((
+                        notice.unwrap_or(due_date),
+                        DueEvent::GstReturnDue {
+                            kind: *kind,
+                            period_start,
+                            due_date,
+                        },
+                    ));
+                }
+            }
+            next_period = period_start.checked_add_months(Months::new(1));
+        }
+
+        for recurring in &self.recurring {
+            for due_date in recurring.occurrences_between(from, to) {
+                events.push((
+                    due_date,
+                    DueEvent::RecurringTransactionDue {
+                        recurring_id: recurring.id.clone(),
+                        description: recurring.description.clone(),
+                        due_date,
+                    },
+                ));
+            }
+        }
+
+        events.sort_by_key(|(date, _)| *date);
+        events.into_iter().map(|(_, event)| event).collect()
+    }
+
+    /// Events due since the last run, through `as_of`
+    ///
+    /// The first run covers `as_of` only.
+    pub fn pending_events(&self, as_of: NaiveDate, invoices: &[OpenInvoice]) -> Vec<DueEvent> {
+        let from = self
+            .last_run
+            .and_then(|last| last.succ_opt())
+            .unwrap_or(as_of);
+        if from > as_of {
+            return Vec::new();
+        }
+        self.events_between(from, as_of, invoices)
+    }
+
+    /// Send the [pending events](Self::pending_events) to `sink` and record
+    /// the run, returning how many were sent
+    ///
+    /// If the sink fails the run is not recorded, so the next run sends the
+    /// same events again; sinks should tolerate the occasional repeat.
+    pub async fn run(
+        &mut self,
+        as_of: NaiveDate,
+        invoices: &[OpenInvoice],
+        sink: &dyn NotificationSink,
+    ) -> LedgerResult<usize> {
+        let events = self.pending_events(as_of, invoices);
+        for event in &events {
+            sink.notify(event).await?;
+        }
+        if self.last_run.is_none_or(|last| last < as_of) {
+            self.last_run = Some(as_of);
+        }
+        Ok(events.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Outbox(Mutex<Vec<DueEvent>>);
+
+    #[async_trait]
+    impl NotificationSink for Outbox {
+        async fn notify(&self, event: &DueEvent) -> LedgerResult<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_emit_each_event_once() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let invoices = [
+            OpenInvoice::new(
+                "INV-1".to_string(),
+                "acme".to_string(),
+                date(6, 10),
+                date(7, 10),
+                BigDecimal::from(500),
+            ),
+            OpenInvoice::new(
+                "INV-2".to_string(),
+                "globex".to_string(),
+                date(5, 1),
+                date(6, 7),
+                BigDecimal::from(800),
+            ),
+        ];
+        let mut scheduler = DueEventScheduler::default();
+        scheduler.add_recurring(RecurringTransaction::new(
+            "rent".to_string(),
+            "Office rent".to_string(),
+            date(1, 9),
+            Recurrence::Monthly,
+        ));
+        let outbox = Outbox::default();
+
+        assert_eq!(
+            scheduler.run(date(7, 4), &invoices, &outbox).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            scheduler
+                .run(date(7, 10), &invoices, &outbox)
+                .await
+                .unwrap(),
+            3
+        );
+        assert_eq!(
+            scheduler
+                .run(date(7, 10), &invoices, &outbox)
+                .await
+                .unwrap(),
+            0
+        );
+        let sent = outbox.0.lock().unwrap().clone();
+        assert_eq!(
+            sent,
+            vec![
+                DueEvent::GstReturnDue {
+                    kind: GstReturnKind::Gstr1,
+                    period_start: date(6, 1),
+                    due_date: date(7, 11),
+                },
+                DueEvent::InvoiceOverdue {
+                    invoice_id: "INV-2".to_string(),
+                    customer_id: "globex".to_string(),
+                    due_date: date(6, 7),
+                    days_overdue: 31,
+                    outstanding: BigDecimal::from(800),
+                },
+                DueEvent::InvoiceDueTomorrow {
+                    invoice_id: "INV-1".to_string(),
+                    customer_id: "acme".to_string(),
+                    due_date: date(7, 10),
+                    outstanding: BigDecimal::from(500),
+                },
+                DueEvent::RecurringTransactionDue {
+                    recurring_id: "rent".to_string(),
+                    description: "Office rent".to_string(),
+                    due_date: date(7, 9),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_at_the_ends_of_the_date_range() {
+        let scheduler = DueEventScheduler::default();
+        assert!(scheduler
+            .events_between(NaiveDate::MIN, NaiveDate::MIN, &[])
+            .is_empty());
+        assert!(scheduler
+            .events_between(NaiveDate::MAX, NaiveDate::MAX, &[])
+            .is_empty());
+        assert_eq!(GstReturnKind::Gstr3b.due_date(NaiveDate::MAX), None);
+    }
+}
@@ -0,0 +1,394 @@
+//! Supplier bills
+//!
+//! A [`Bill`] is the purchase-side counterpart of a sales
+//! [`Invoice`](crate::receivables::Invoice): the supplier's invoice as
+//! received, with its GST line items, the input tax credit (ITC) that can be
+//! claimed on it and its due date. A bill can name the purchase order and
+//! goods receipt it relates to, so it can be matched against both before it
+//! is posted and paid.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ledger::transaction::patterns::gst_legs;
+use crate::ledger::{GstAccounts, Ledger, TransactionBuilder, PARTY_DIMENSION};
+use crate::receivables::PaymentTerms;
+use crate::tax::GstInvoice;
+use crate::traits::*;
+use crate::types::*;
+
+/// Where a bill is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BillStatus {
+    /// Entered but not yet posted
+    #[default]
+    Draft,
+    /// Recorded, but its transaction is awaiting approval
+    PendingApproval,
+    /// Posted and awaiting payment
+    Posted,
+    PartiallyPaid,
+    Paid,
+    Cancelled,
+}
+
+/// How far a bill has been matched to its purchase documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum BillMatch {
+    /// Neither a purchase order nor a goods receipt is referenced
+    Unmatched,
+    /// Only one of the purchase order and the goods receipt is referenced
+    TwoWay,
+    /// Both the purchase order and the goods receipt are referenced
+    ThreeWay,
+}
+
+/// Accounts a supplier bill posts to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PurchaseAccounts {
+    pub payables_account_id: String,
+    pub expense_account_id: String,
+    pub input_tax: GstAccounts,
+}
+
+/// A supplier's invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Bill {
+    pub id: String,
+    pub vendor_id: String,
+    /// Invoice number printed by the supplier
+    pub vendor_invoice_number: String,
+    pub date: NaiveDate,
+    pub due_date: NaiveDate,
+    /// Terms agreed for this bill; the supplier's apply when `None`
+    pub payment_terms: Option<PaymentTerms>,
+    /// Line items and GST; round with [`GstInvoice::rounded`] before posting
+    pub gst: GstInvoice,
+    #[serde(default)]
+    pub cess_amount: BigDecimal,
+    /// Whether the GST can be claimed as input tax credit; when not, it is
+    /// part of the cost
+    pub itc_eligible: bool,
+    pub status: BillStatus,
+    #[serde(default)]
+    pub amount_paid: BigDecimal,
+    pub purchase_order_id: Option<String>,
+    pub goods_receipt_id: Option<String>,
+    /// Transaction the bill was posted as
+    pub transaction_id: Option<String>,
+}
+
+impl Bill {
+    /// Create a draft bill with input tax credit claimable
+    ///
+    /// Fails if the payment terms put the due date out of range.
+    pub fn new(
+        id: String,
+        vendor_id: String,
+        vendor_invoice_number: String,
+        date: NaiveDate,
+        gst: GstInvoice,
+        payment_terms: Option<PaymentTerms>,
+    ) -> LedgerResult<Self> {
+        Ok(Self {
+            id,
+            vendor_id,
+            vendor_invoice_number,
+            date,
+            due_date: payment_terms.unwrap_or_default().due_date(date)?,
+            payment_terms,
+            gst,
+            cess_amount: BigDecimal::zero(),
+            itc_eligible: true,
+            status: BillStatus::Draft,
+            amount_paid: BigDecimal::zero(),
+            purchase_order_id: None,
+            goods_receipt_id: None,
+            transaction_id: None,
+        })
+    }
+
+    /// Reference the purchase order and goods receipt the bill is for
+    pub fn link(
+        mut self,
+        purchase_order_id: Option<String>,
+        goods_receipt_id: Option<String>,
+    ) -> Self {
+        self.purchase_order_id = purchase_order_id;
+        self.goods_receipt_id = goods_receipt_id;
+        self
+    }
+
+    /// How far the bill is matched to its purchase documents
+    pub fn match_status(&self) -> BillMatch {
+        match (&self.purchase_order_id, &self.goods_receipt_id) {
+            (Some(_), Some(_)) => BillMatch::ThreeWay,
+            (None, None) => BillMatch::Unmatched,
+            _ => BillMatch::TwoWay,
+        }
+    }
+
+    /// Amount payable to the supplier, including GST and cess
+    pub fn total(&self) -> BigDecimal {
+        &self.gst.grand_total + &self.cess_amount
+    }
+
+    /// Amount still to be paid
+    pub fn outstanding(&self) -> BigDecimal {
+        self.total() - &self.amount_paid
+    }
+
+    /// Input tax credit claimable on the bill
+    pub fn itc_amount(&self) -> BigDecimal {
+        if self.itc_eligible {
+            &self.gst.total_gst + &self.cess_amount
+        } else {
+            BigDecimal::zero()
+        }
+    }
+
+    /// Whether the bill has been posted and not fully paid
+    pub fn is_open(&self) -> bool {
+        matches!(self.status, BillStatus::Posted | BillStatus::PartiallyPaid)
+    }
+
+    /// Build the purchase voucher for the bill (debit the expense and each
+    /// claimable GST component, credit the supplier's payable)
+    pub fn posting(&self, accounts: &PurchaseAccounts) -> LedgerResult<Transaction> {
+        let (cost, legs) = if self.itc_eligible {
+            let legs = gst_legs(
+                &accounts.input_tax,
+                [
+                    &self.gst.total_cgst,
+                    &self.gst.total_sgst,
+                    &self.gst.total_igst,
+                ],
+                &self.cess_amount,
+                "input",
+            )?;
+            (self.gst.total_before_gst.clone(), legs)
+        } else {
+            (self.total(), Vec::new())
+        };
+
+        let mut builder = TransactionBuilder::new(
+            self.id.clone(),
+            self.date,
+            format!(
+                "Bill {} from {}",
+                self.vendor_invoice_number, self.vendor_id
+            ),
+        )
+        .kind(TransactionKind::Purchase)
+        .reference(self.vendor_invoice_number.clone())
+        .debit(
+            accounts.expense_account_id.clone(),
+            cost,
+            Some("Expense amount".to_string()),
+        );
+        for (account_id, amount, description) in legs {
+            builder = builder.debit(account_id, amount, Some(description));
+        }
+        builder
+            .entry(
+                Entry::credit(
+                    accounts.payables_account_id.clone(),
+                    self.total(),
+                    Some("Total payable".to_string()),
+                )
+                .with_dimension(PARTY_DIMENSION.to_string(), self.vendor_id.clone()),
+            )
+            .build()
+    }
+
+    /// Apply a payment against the bill
+    pub fn record_payment(&mut self, amount: &BigDecimal) -> LedgerResult<()> {
+        if !self.is_open() {
+            return Err(LedgerError::Validation(format!(
+                "Bill {} is not open for payment",
+                self.id
+            )));
+        }
+        if !amount.is_positive() || amount > &self.outstanding() {
+            return Err(LedgerError::Validation(format!(
+                "Payment of {} does not fit the {} outstanding on bill {}",
+                amount,
+                self.outstanding(),
+                self.id
+            )));
+        }
+        self.amount_paid += amount;
+        self.status = if self.outstanding().is_zero() {
+            BillStatus::Paid
+        } else {
+            BillStatus::PartiallyPaid
+        };
+        Ok(())
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Post a draft bill
+    ///
+    /// The due date is recalculated from the bill's terms, or the supplier's
+    /// agreed terms when the bill has none. If the transaction is held for
+    /// approval the bill is left pending; see
+    /// [`Ledger::refresh_bill_approval`].
+    pub async fn post_bill(
+        &mut self,
+        bill: &mut Bill,
+        accounts: &PurchaseAccounts,
+    ) -> LedgerResult<Transaction> {
+        if bill.status != BillStatus::Draft {
+            return Err(LedgerError::Validation(format!(
+                "Bill {} has already been posted",
+                bill.id
+            )));
+        }
+        let transaction = bill.posting(accounts)?;
+        let transaction = self.record_transaction(transaction).await?;
+        bill.due_date = self.invoice_due_date(&bill.vendor_id, bill.date, bill.payment_terms)?;
+        bill.status = if transaction.is_posted() {
+            BillStatus::Posted
+        } else {
+            BillStatus::PendingApproval
+        };
+        bill.transaction_id = Some(transaction.id.clone());
+        Ok(transaction)
+    }
+
+    /// Settle a bill held for approval once its transaction is decided
+    ///
+    /// The bill is posted when the transaction is approved and cancelled when
+    /// it is rejected; while still pending it is left unchanged.
+    pub async fn refresh_bill_approval(&self, bill: &mut Bill) -> LedgerResult<()> {
+        let Some(transaction_id) = &bill.transaction_id else {
+            return Ok(());
+        };
+        if bill.status != BillStatus::PendingApproval {
+            return Ok(());
+        }
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        match transaction.status {
+            TransactionStatus::Posted => bill.status = BillStatus::Posted,
+            TransactionStatus::Rejected => bill.status = BillStatus::Cancelled,
+            TransactionStatus::Draft | TransactionStatus::PendingApproval => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tax::{GstLineItem, GstRate};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_post_bill_with_and_without_itc() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "1430".to_string(),
+                "IGST Input".to_string(),
+                AccountType::Asset,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger.set_party_payment_terms("steelco", Some(PaymentTerms::EndOfMonth(30)));
+        let accounts = PurchaseAccounts {
+            payables_account_id: "2000".to_string(),
+            expense_account_id: "5000".to_string(),
+            input_tax: GstAccounts {
+                cgst_account_id: "1410".to_string(),
+                sgst_account_id: "1420".to_string(),
+                igst_account_id: "1430".to_string(),
+                cess_account_id: None,
+            },
+        };
+        let gst = || {
+            GstInvoice::new(vec![GstLineItem::new(
+                "Steel sheets".to_string(),
+                BigDecimal::from(10),
+                BigDecimal::from(100),
+                GstRate::inter_state(BigDecimal::from(18)),
+            )
+            .unwrap()])
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 4, 10).unwrap();
+
+        let mut bill = Bill::new(
+            "BILL-1".to_string(),
+            "steelco".to_string(),
+            "SC/889".to_string(),
+            date,
+            gst(),
+            None,
+        )
+        .unwrap()
+        .link(Some("PO-3".to_string()), Some("GRN-5".to_string()));
+        assert_eq!(bill.match_status(), BillMatch::ThreeWay);
+        ledger.post_bill(&mut bill, &accounts).await.unwrap();
+        assert_eq!(bill.status, BillStatus::Posted);
+        assert_eq!(bill.due_date, NaiveDate::from_ymd_opt(2024, 5, 30).unwrap());
+        assert_eq!(bill.itc_amount(), BigDecimal::from(180));
+        assert_eq!(
+            ledger.get_account_balance("1430", None).await.unwrap(),
+            BigDecimal::from(180)
+        );
+
+        let mut blocked = Bill::new(
+            "BILL-2".to_string(),
+            "steelco".to_string(),
+            "SC/890".to_string(),
+            date,
+            gst(),
+            Some(PaymentTerms::NET_15),
+        )
+        .unwrap();
+        blocked.itc_eligible = false;
+        ledger.post_bill(&mut blocked, &accounts).await.unwrap();
+        assert_eq!(
+            ledger.get_account_balance("5000", None).await.unwrap(),
+            BigDecimal::from(2180)
+        );
+        assert_eq!(
+            ledger.get_account_balance("2000", None).await.unwrap(),
+            BigDecimal::from(2360)
+        );
+
+        blocked.record_payment(&BigDecimal::from(1180)).unwrap();
+        assert_eq!(blocked.status, BillStatus::Paid);
+
+        // A bill held for approval is not open until it is approved
+        ledger.set_approval_threshold(Some(BigDecimal::from(1000)));
+        ledger.set_actor(Some(Actor::new("maker".to_string(), vec![])));
+        let mut held = Bill::new(
+            "BILL-3".to_string(),
+            "steelco".to_string(),
+            "SC/891".to_string(),
+            date,
+            gst(),
+            None,
+        )
+        .unwrap();
+        let transaction = ledger.post_bill(&mut held, &accounts).await.unwrap();
+        assert_eq!(transaction.status, TransactionStatus::PendingApproval);
+        assert_eq!(held.status, BillStatus::PendingApproval);
+        assert!(held.record_payment(&BigDecimal::from(100)).is_err());
+        ledger.set_actor(Some(Actor::new("checker".to_string(), vec![])));
+        ledger.approve_transaction("BILL-3", None).await.unwrap();
+        ledger.refresh_bill_approval(&mut held).await.unwrap();
+        assert_eq!(held.status, BillStatus::Posted);
+    }
+}
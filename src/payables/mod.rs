@@ -0,0 +1,7 @@
+//! Payables module: supplier bills and their payment
+
+pub mod bill;
+pub mod payment_run;
+
+pub use bill::*;
+pub use payment_run::*;
@@ -0,0 +1,469 @@
+//! Vendor payment runs
+//!
+//! A payment run pays a batch of open bills at once: it selects the bills
+//! due by a date (optionally for some suppliers only), deducts TDS for
+//! suppliers it applies to, posts one payment journal per supplier, marks
+//! the bills paid and produces a CSV file for upload to the bank.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{Bill, BillStatus};
+use crate::export::write_field;
+use crate::ledger::{Ledger, TransactionBuilder, PARTY_DIMENSION};
+use crate::traits::*;
+use crate::types::*;
+
+/// Header line of a bank payment file
+pub const BANK_PAYMENT_CSV_HEADER: [&str; 6] = [
+    "vendor_id",
+    "beneficiary_name",
+    "account_number",
+    "ifsc",
+    "amount",
+    "reference",
+];
+
+/// Where a supplier is paid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VendorBankDetails {
+    pub beneficiary_name: String,
+    pub account_number: String,
+    pub ifsc: String,
+}
+
+/// Selection and posting rules of a payment run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PaymentRun {
+    pub id: String,
+    /// Payment date
+    pub date: NaiveDate,
+    /// Bills due on or before this date are paid
+    pub due_by: NaiveDate,
+    /// Suppliers to pay; all when empty
+    pub vendor_ids: BTreeSet<String>,
+    pub bank_account_id: String,
+    pub payables_account_id: String,
+    /// Account TDS deducted is credited to
+    pub tds_payable_account_id: Option<String>,
+    /// TDS rate percentage per supplier, levied on the taxable value
+    pub tds_rates: HashMap<String, BigDecimal>,
+    pub amount_policy: AmountPolicy,
+}
+
+/// One supplier's share of a payment run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VendorPayment {
+    pub vendor_id: String,
+    pub bill_ids: Vec<String>,
+    /// Amount settled on the bills
+    pub gross: BigDecimal,
+    pub tds: BigDecimal,
+    /// Amount paid out of the bank
+    pub net: BigDecimal,
+    pub transaction: Transaction,
+}
+
+/// Result of a payment run, ordered by supplier
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PaymentRunResult {
+    pub run_id: String,
+    pub payments: Vec<VendorPayment>,
+    pub total_paid: BigDecimal,
+}
+
+impl PaymentRunResult {
+    /// Bank upload file with one row per supplier
+    ///
+    /// Fails if bank details are missing for a supplier being paid.
+    pub fn bank_file_csv(
+        &self,
+        bank_details: &HashMap<String, VendorBankDetails>,
+    ) -> LedgerResult<String> {
+        let mut out = BANK_PAYMENT_CSV_HEADER.join(",");
+        out.push('\n');
+        for payment in &self.payments {
+            let details = bank_details.get(&payment.vendor_id).ok_or_else(|| {
+                LedgerError::Validation(format!("No bank details for vendor {}", payment.vendor_id))
+            })?;
+            let amount = payment.net.to_plain_string();
+            let fields = [
+                payment.vendor_id.as_str(),
+                details.beneficiary_name.as_str(),
+                details.account_number.as_str(),
+                details.ifsc.as_str(),
+                amount.as_str(),
+                payment.transaction.id.as_str(),
+            ];
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_field(&mut out, field);
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+impl PaymentRun {
+    pub fn new(
+        id: String,
+        date: NaiveDate,
+        bank_account_id: String,
+        payables_account_id: String,
+    ) -> Self {
+        Self {
+            id,
+            date,
+            due_by: date,
+            vendor_ids: BTreeSet::new(),
+            bank_account_id,
+            payables_account_id,
+            tds_payable_account_id: None,
+            tds_rates: HashMap::new(),
+            amount_policy: AmountPolicy::default(),
+        }
+    }
+
+    /// Pay bills due on or before `date` instead of the payment date
+    pub fn due_by(mut self, date: NaiveDate) -> Self {
+        self.due_by = date;
+        self
+    }
+
+    /// Pay only this supplier's bills; may be called for several suppliers
+    pub fn vendor(mut self, vendor_id: &str) -> Self {
+        self.vendor_ids.insert(vendor_id.to_string());
+        self
+    }
+
+    /// Deduct TDS from a supplier's payments at `rate` percent
+    pub fn tds(mut self, vendor_id: &str, rate: BigDecimal) -> Self {
+        self.tds_rates.insert(vendor_id.to_string(), rate);
+        self
+    }
+
+    /// Credit TDS deducted to this account
+    pub fn tds_account(mut self, account_id: String) -> Self {
+        self.tds_payable_account_id = Some(account_id);
+        self
+    }
+
+    /// Whether a bill is paid by this run
+    pub fn selects(&self, bill: &Bill) -> bool {
+        bill.is_open()
+            && bill.due_date <= self.due_by
+            && (self.vendor_ids.is_empty() || self.vendor_ids.contains(&bill.vendor_id))
+    }
+
+    /// Work out the payments without posting anything
+    ///
+    /// TDS is deducted on the taxable value of bills paid for the first
+    /// time; a bill already partly paid had its TDS deducted then.
+    pub fn prepare(&self, bills: &[Bill]) -> LedgerResult<PaymentRunResult> {
+        let mut by_vendor: BTreeMap<&str, Vec<&Bill>> = BTreeMap::new();
+        for bill in bills.iter().filter(|bill| self.selects(bill)) {
+            by_vendor.entry(&bill.vendor_id).or_default().push(bill);
+        }
+
+        let hundred = BigDecimal::from(100);
+        let mut payments = Vec::new();
+        let mut total_paid = BigDecimal::zero();
+        for (vendor_id, bills) in by_vendor {
+            let gross: BigDecimal = bills.iter().map(|bill| bill.outstanding()).sum();
+            let tds = match self.tds_rates.get(vendor_id) {
+                Some(rate) => {
+                    let taxable: BigDecimal = bills
+                        .iter()
+                        .filter(|bill| bill.status == BillStatus::Posted)
+                        .map(|bill| &bill.gst.total_before_gst)
+                        .sum();
+                    self.amount_policy.round(&(taxable * rate / &hundred))
+                }
+                None => BigDecimal::zero(),
+            };
+            let net = &gross - &tds;
+
+            let mut builder = TransactionBuilder::new(
+                format!("{}-{}", self.id, vendor_id),
+                self.date,
+                format!("Payment run {} to {}", self.id, vendor_id),
+            )
+            .kind(TransactionKind::Payment)
+            .reference(self.id.clone())
+            .entry(
+                Entry::debit(
+                    self.payables_account_id.clone(),
+                    gross.clone(),
+                    Some("Bills settled".to_string()),
+                )
+                .with_dimension(PARTY_DIMENSION.to_string(), vendor_id.to_string()),
+            )
+            .credit(
+                self.bank_account_id.clone(),
+                net.clone(),
+                Some("Bank payment".to_string()),
+            );
+            if !tds.is_zero() {
+                let account_id = self.tds_payable_account_id.clone().ok_or_else(|| {
+                    LedgerError::Validation(format!(
+                        "TDS deducted for vendor {} without a TDS payable account",
+                        vendor_id
+                    ))
+                })?;
+                builder = builder.credit(account_id, tds.clone(), Some("TDS deducted".to_string()));
+            }
+
+            total_paid += &net;
+            payments.push(VendorPayment {
+                vendor_id: vendor_id.to_string(),
+                bill_ids: bills.iter().map(|bill| bill.id.clone()).collect(),
+                gross,
+                tds,
+                net,
+                transaction: builder.build()?,
+            });
+        }
+
+        Ok(PaymentRunResult {
+            run_id: self.id.clone(),
+            payments,
+            total_paid,
+        })
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Post a payment run and mark the bills it pays as paid
+    ///
+    /// Either every supplier's payment is recorded or none is: if one is
+    /// refused, those already recorded are removed again and no bill is
+    /// changed. A payment held for approval leaves its bills open; the
+    /// returned payments carry the transactions as recorded, so the caller
+    /// can settle those bills once the payment is approved.
+    pub async fn execute_payment_run(
+        &mut self,
+        run: &PaymentRun,
+        bills: &mut [Bill],
+    ) -> LedgerResult<PaymentRunResult> {
+        let mut result = run.prepare(bills)?;
+
+        let mut recorded: Vec<String> = Vec::new();
+        for payment in &mut result.payments {
+            match self.record_transaction(payment.transaction.clone()).await {
+                Ok(transaction) => payment.transaction = transaction,
+                Err(error) => {
+                    // Not the authorized delete: running payments must not
+                    // need the right to void transactions
+                    for transaction_id in recorded.iter().rev() {
+                        self.transaction_manager
+                            .delete_transaction(transaction_id)
+                            .await?;
+                    }
+                    return Err(error);
+                }
+            }
+            recorded.push(payment.transaction.id.clone());
+        }
+
+        let mut paid: BTreeSet<&str> = BTreeSet::new();
+        for payment in &result.payments {
+            if payment.transaction.is_posted() {
+                paid.extend(payment.bill_ids.iter().map(String::as_str));
+            }
+        }
+        for bill in bills
+            .iter_mut()
+            .filter(|bill| paid.contains(bill.id.as_str()))
+        {
+            let outstanding = bill.outstanding();
+            bill.record_payment(&outstanding)?;
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::GstAccounts;
+    use crate::payables::PurchaseAccounts;
+    use crate::receivables::PaymentTerms;
+    use crate::tax::{GstInvoice, GstLineItem, GstRate};
+    use crate::utils::MemoryStorage;
+
+    fn may(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    /// Post B1 and B2 for fixit and B3 and B4 for cleanco; only B4 is not
+    /// due by 20 May
+    async fn post_bills(ledger: &mut Ledger<MemoryStorage>) -> Vec<Bill> {
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("1430", "IGST Input", AccountType::Asset),
+            ("2400", "TDS Payable", AccountType::Liability),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let accounts = PurchaseAccounts {
+            payables_account_id: "2000".to_string(),
+            expense_account_id: "6100".to_string(),
+            input_tax: GstAccounts {
+                cgst_account_id: "1410".to_string(),
+                sgst_account_id: "1420".to_string(),
+                igst_account_id: "1430".to_string(),
+                cess_account_id: None,
+            },
+        };
+        let mut bills = Vec::new();
+        for (id, vendor, amount, terms) in [
+            ("B1", "fixit", 1000, PaymentTerms::DueOnReceipt),
+            ("B2", "fixit", 2000, PaymentTerms::NET_15),
+            ("B3", "cleanco", 500, PaymentTerms::DueOnReceipt),
+            ("B4", "cleanco", 700, PaymentTerms::NET_45),
+        ] {
+            let gst = GstInvoice::new(vec![GstLineItem::new(
+                "Services".to_string(),
+                BigDecimal::from(1),
+                BigDecimal::from(amount),
+                GstRate::inter_state(BigDecimal::from(18)),
+            )
+            .unwrap()]);
+            let mut bill = Bill::new(
+                id.to_string(),
+                vendor.to_string(),
+                id.to_string(),
+                may(1),
+                gst,
+                Some(terms),
+            )
+            .unwrap();
+            ledger.post_bill(&mut bill, &accounts).await.unwrap();
+            bills.push(bill);
+        }
+        bills
+    }
+
+    fn payment_run() -> PaymentRun {
+        PaymentRun::new(
+            "RUN-5".to_string(),
+            may(20),
+            "1000".to_string(),
+            "2000".to_string(),
+        )
+        .tds("fixit", BigDecimal::from(2))
+        .tds_account("2400".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_payment_run_pays_due_bills_per_vendor() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        let mut bills = post_bills(&mut ledger).await;
+        let run = payment_run();
+        let result = ledger.execute_payment_run(&run, &mut bills).await.unwrap();
+
+        let vendors: Vec<_> = result
+            .payments
+            .iter()
+            .map(|p| p.vendor_id.as_str())
+            .collect();
+        assert_eq!(vendors, ["cleanco", "fixit"]);
+        let fixit = &result.payments[1];
+        assert_eq!(fixit.gross, BigDecimal::from(3540));
+        assert_eq!(fixit.tds, BigDecimal::from(60));
+        assert_eq!(result.total_paid, BigDecimal::from(590 + 3480));
+        assert_eq!(
+            ledger.get_account_balance("2400", None).await.unwrap(),
+            BigDecimal::from(60)
+        );
+        assert_eq!(
+            ledger.get_account_balance("2000", None).await.unwrap(),
+            BigDecimal::from(826)
+        );
+        let statuses: Vec<_> = bills.iter().map(|bill| bill.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                BillStatus::Paid,
+                BillStatus::Paid,
+                BillStatus::Paid,
+                BillStatus::Posted
+            ]
+        );
+
+        let mut bank_details = HashMap::new();
+        bank_details.insert(
+            "cleanco".to_string(),
+            VendorBankDetails {
+                beneficiary_name: "Clean Co, Pune".to_string(),
+                account_number: "000111".to_string(),
+                ifsc: "HDFC0000001".to_string(),
+            },
+        );
+        assert!(result.bank_file_csv(&bank_details).is_err());
+        bank_details.insert(
+            "fixit".to_string(),
+            VendorBankDetails {
+                beneficiary_name: "Fixit".to_string(),
+                account_number: "000222".to_string(),
+                ifsc: "ICIC0000002".to_string(),
+            },
+        );
+        let file = result.bank_file_csv(&bank_details).unwrap();
+        assert_eq!(
+            file.lines().nth(1).unwrap(),
+            "cleanco,\"Clean Co, Pune\",000111,HDFC0000001,590,RUN-5-cleanco"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_held_payment_leaves_bills_open() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        let mut bills = post_bills(&mut ledger).await;
+        // fixit's payment of 3480 needs approval, cleanco's 590 does not
+        ledger.set_approval_threshold(Some(BigDecimal::from(1000)));
+        let result = ledger
+            .execute_payment_run(&payment_run(), &mut bills)
+            .await
+            .unwrap();
+
+        let statuses: Vec<_> = result
+            .payments
+            .iter()
+            .map(|p| p.transaction.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                TransactionStatus::Posted,
+                TransactionStatus::PendingApproval
+            ]
+        );
+        let statuses: Vec<_> = bills.iter().map(|bill| bill.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                BillStatus::Posted,
+                BillStatus::Posted,
+                BillStatus::Paid,
+                BillStatus::Posted
+            ]
+        );
+        assert_eq!(
+            ledger.get_account_balance("2400", None).await.unwrap(),
+            BigDecimal::zero()
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Dunning: payment reminder schedules for overdue invoices
+
+use bigdecimal::{BigDecimal, Signed};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::OpenInvoice;
+
+/// One escalation step of a reminder schedule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReminderLevel {
+    /// Escalation level, starting at 1
+    pub level: u32,
+    /// Days past due at which this level becomes due
+    pub days_overdue: i64,
+    /// Label for templates, e.g. "Friendly reminder" or "Final notice"
+    pub label: String,
+}
+
+impl ReminderLevel {
+    pub fn new(level: u32, days_overdue: i64, label: String) -> Self {
+        Self {
+            level,
+            days_overdue,
+            label,
+        }
+    }
+}
+
+/// A reminder that is due to be sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DueReminder {
+    pub invoice_id: String,
+    pub customer_id: String,
+    pub due_date: NaiveDate,
+    pub days_overdue: i64,
+    pub level: u32,
+    pub label: String,
+    pub amount_due: BigDecimal,
+}
+
+/// Reminder schedule with escalating levels
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DunningSchedule {
+    levels: Vec<ReminderLevel>,
+}
+
+impl Default for DunningSchedule {
+    /// Reminders at 7, 14 and 30 days overdue
+    fn default() -> Self {
+        Self::new(vec![
+            ReminderLevel::new(1, 7, "Friendly reminder".to_string()),
+            ReminderLevel::new(2, 14, "Second reminder".to_string()),
+            ReminderLevel::new(3, 30, "Final notice".to_string()),
+        ])
+    }
+}
+
+impl DunningSchedule {
+    /// Create a schedule from its levels
+    pub fn new(mut levels: Vec<ReminderLevel>) -> Self {
+        levels.sort_by_key(|l| l.days_overdue);
+        Self { levels }
+    }
+
+    /// Levels ordered by days overdue
+    pub fn levels(&self) -> &[ReminderLevel] {
+        &self.levels
+    }
+
+    /// Highest level reached by an invoice that many days overdue
+    pub fn level_for(&self, days_overdue: i64) -> Option<&ReminderLevel> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|l| days_overdue >= l.days_overdue)
+    }
+
+    /// Reminders due as of a date
+    ///
+    /// `sent_levels` maps invoice IDs to the highest level already sent, so
+    /// an invoice only produces a reminder when it escalates past it. Results
+    /// are ordered by customer, then due date.
+    pub fn due_reminders(
+        &self,
+        invoices: &[OpenInvoice],
+        as_of: NaiveDate,
+        sent_levels: &HashMap<String, u32>,
+    ) -> Vec<DueReminder> {
+        let mut reminders: Vec<DueReminder> = invoices
+            .iter()
+            .filter(|invoice| invoice.outstanding.is_positive())
+            .filter_map(|invoice| {
+                let days_overdue = invoice.days_overdue(as_of);
+                let level = self.level_for(days_overdue)?;
+                let already_sent = sent_levels.get(&invoice.invoice_id).copied().unwrap_or(0);
+                if level.level <= already_sent {
+                    return None;
+                }
+                Some(DueReminder {
+                    invoice_id: invoice.invoice_id.clone(),
+                    customer_id: invoice.customer_id.clone(),
+                    due_date: invoice.due_date,
+                    days_overdue,
+                    level: level.level,
+                    label: level.label.clone(),
+                    amount_due: invoice.outstanding.clone(),
+                })
+            })
+            .collect();
+        reminders.sort_by(|a, b| {
+            a.customer_id
+                .cmp(&b.customer_id)
+                .then(a.due_date.cmp(&b.due_date))
+                .then_with(|| a.invoice_id.cmp(&b.invoice_id))
+        });
+        reminders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_due_reminders_escalate() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let invoice = |id: &str, days: i64| {
+            let due = as_of - chrono::Duration::days(days);
+            OpenInvoice::new(
+                id.to_string(),
+                "acme".to_string(),
+                due - chrono::Duration::days(30),
+                due,
+                BigDecimal::from(1000),
+            )
+        };
+        let invoices = vec![
+            invoice("inv1", 3),
+            invoice("inv2", 10),
+            invoice("inv3", 20),
+            invoice("inv4", 45),
+        ];
+        let schedule = DunningSchedule::default();
+
+        let reminders = schedule.due_reminders(&invoices, as_of, &HashMap::new());
+        let levels: Vec<(String, u32)> = reminders
+            .iter()
+            .map(|r| (r.invoice_id.clone(), r.level))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![
+                ("inv4".to_string(), 3),
+                ("inv3".to_string(), 2),
+                ("inv2".to_string(), 1),
+            ]
+        );
+
+        // Already-sent levels are not repeated
+        let sent = HashMap::from([("inv3".to_string(), 2), ("inv4".to_string(), 2)]);
+        let reminders = schedule.due_reminders(&invoices, as_of, &sent);
+        assert_eq!(reminders.len(), 2);
+        assert_eq!(reminders[0].invoice_id, "inv4");
+        assert_eq!(reminders[0].label, "Final notice");
+    }
+}
@@ -0,0 +1,291 @@
+//! Interest on overdue invoices
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::OpenInvoice;
+use crate::types::AmountPolicy;
+
+/// How overdue interest accrues
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum InterestMethod {
+    /// Interest on the outstanding principal only
+    Simple,
+    /// Interest compounded the given number of times per year
+    Compound { periods_per_year: u32 },
+}
+
+/// Interest terms agreed with the customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InterestTerms {
+    /// Annual interest rate percentage (e.g. 18 for 18% p.a.)
+    pub annual_rate: BigDecimal,
+    pub method: InterestMethod,
+    /// Days after the due date before interest is charged; once exceeded,
+    /// interest runs from the due date
+    pub grace_days: i64,
+}
+
+impl InterestTerms {
+    /// Simple interest at an annual rate
+    pub fn simple(annual_rate: BigDecimal) -> Self {
+        Self {
+            annual_rate,
+            method: InterestMethod::Simple,
+            grace_days: 0,
+        }
+    }
+
+    /// Compound interest at an annual rate
+    pub fn compound(annual_rate: BigDecimal, periods_per_year: u32) -> Self {
+        Self {
+            annual_rate,
+            method: InterestMethod::Compound { periods_per_year },
+            grace_days: 0,
+        }
+    }
+
+    /// Set the grace period
+    pub fn with_grace_days(mut self, grace_days: i64) -> Self {
+        self.grace_days = grace_days;
+        self
+    }
+}
+
+/// Interest due on a single overdue invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OverdueInterest {
+    pub invoice_id: String,
+    pub customer_id: String,
+    pub due_date: NaiveDate,
+    pub days_overdue: i64,
+    pub principal: BigDecimal,
+    pub interest: BigDecimal,
+}
+
+/// Overdue invoices and interest for one customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CustomerOverdueInterest {
+    pub customer_id: String,
+    pub invoices: Vec<OverdueInterest>,
+    pub total_overdue: BigDecimal,
+    pub total_interest: BigDecimal,
+}
+
+/// Overdue interest report grouped by customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OverdueInterestReport {
+    pub as_of_date: NaiveDate,
+    pub customers: Vec<CustomerOverdueInterest>,
+    pub total_interest: BigDecimal,
+}
+
+/// Calculator for interest on overdue invoices
+#[derive(Debug, Clone)]
+pub struct OverdueInterestCalculator {
+    terms: InterestTerms,
+    amount_policy: AmountPolicy,
+}
+
+impl OverdueInterestCalculator {
+    /// Create a calculator for the given terms, rounding interest to paise
+    pub fn new(terms: InterestTerms) -> Self {
+        Self {
+            terms,
+            amount_policy: AmountPolicy::default(),
+        }
+    }
+
+    /// Round interest with the ledger's amount policy instead
+    pub fn with_amount_policy(mut self, policy: AmountPolicy) -> Self {
+        self.amount_policy = policy;
+        self
+    }
+
+    /// Interest due on an invoice as of a date, or `None` if it is not
+    /// chargeable (not overdue, within grace, or nothing outstanding)
+    pub fn calculate(&self, invoice: &OpenInvoice, as_of: NaiveDate) -> Option<OverdueInterest> {
+        let days_overdue = invoice.days_overdue(as_of);
+        if days_overdue == 0
+            || days_overdue <= self.terms.grace_days
+            || !invoice.outstanding.is_positive()
+        {
+            return None;
+        }
+
+        let interest = self
+            .amount_policy
+            .round(&self.interest_for(&invoice.outstanding, days_overdue));
+
+        Some(OverdueInterest {
+            invoice_id: invoice.invoice_id.clone(),
+            customer_id: invoice.customer_id.clone(),
+            due_date: invoice.due_date,
+            days_overdue,
+            principal: invoice.outstanding.clone(),
+            interest,
+        })
+    }
+
+    /// Overdue interest on a set of open invoices, grouped by customer
+    pub fn report(&self, invoices: &[OpenInvoice], as_of: NaiveDate) -> OverdueInterestReport {
+        let mut by_customer: BTreeMap<String, Vec<OverdueInterest>> = BTreeMap::new();
+        for invoice in invoices {
+            if let Some(interest) = self.calculate(invoice, as_of) {
+                by_customer
+                    .entry(interest.customer_id.clone())
+                    .or_default()
+                    .push(interest);
+            }
+        }
+
+        let customers: Vec<CustomerOverdueInterest> = by_customer
+            .into_iter()
+            .map(|(customer_id, mut invoices)| {
+                invoices.sort_by_key(|i| i.due_date);
+                CustomerOverdueInterest {
+                    customer_id,
+                    total_overdue: invoices.iter().map(|i| &i.principal).sum(),
+                    total_interest: invoices.iter().map(|i| &i.interest).sum(),
+                    invoices,
+                }
+            })
+            .collect();
+        let total_interest = customers.iter().map(|c| &c.total_interest).sum();
+
+        OverdueInterestReport {
+            as_of_date: as_of,
+            customers,
+            total_interest,
+        }
+    }
+
+    fn interest_for(&self, principal: &BigDecimal, days: i64) -> BigDecimal {
+        let rate = &self.terms.annual_rate / BigDecimal::from(100);
+        let days_in_year = BigDecimal::from(365);
+
+        match self.terms.method {
+            InterestMethod::Simple => principal * &rate * BigDecimal::from(days) / days_in_year,
+            InterestMethod::Compound { periods_per_year } => {
+                if periods_per_year == 0 {
+                    return principal * &rate * BigDecimal::from(days) / days_in_year;
+                }
+                // A period is 365 / periods_per_year days. Compound whole
+                // periods, then accrue the period rate on the compounded
+                // amount for the fraction of a period left over.
+                let period_rate = &rate / BigDecimal::from(periods_per_year);
+                let elapsed = days * i64::from(periods_per_year);
+                let total_periods = elapsed / 365;
+                let remaining = BigDecimal::from(elapsed % 365) / days_in_year;
+
+                let mut amount = principal.clone();
+                for _ in 0..total_periods {
+                    amount = &amount + &amount * &period_rate;
+                }
+                amount = &amount + &amount * &period_rate * remaining;
+
+                let interest = amount - principal;
+                if interest.is_negative() {
+                    BigDecimal::zero()
+                } else {
+                    interest
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(id: &str, customer: &str, due: NaiveDate, amount: i32) -> OpenInvoice {
+        OpenInvoice::new(
+            id.to_string(),
+            customer.to_string(),
+            due - chrono::Duration::days(30),
+            due,
+            BigDecimal::from(amount),
+        )
+    }
+
+    #[test]
+    fn test_simple_interest_with_grace() {
+        let due = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let calculator = OverdueInterestCalculator::new(
+            InterestTerms::simple(BigDecimal::from(18)).with_grace_days(7),
+        );
+
+        // Within grace: nothing charged
+        assert!(calculator
+            .calculate(
+                &invoice("inv1", "acme", due, 100000),
+                due + chrono::Duration::days(5)
+            )
+            .is_none());
+
+        // 73 days at 18% p.a. on 100,000 = 3,600
+        let interest = calculator
+            .calculate(
+                &invoice("inv1", "acme", due, 100000),
+                due + chrono::Duration::days(73),
+            )
+            .unwrap();
+        assert_eq!(interest.days_overdue, 73);
+        assert_eq!(interest.interest, BigDecimal::from(3600));
+    }
+
+    #[test]
+    fn test_compound_interest_worked_examples() {
+        let due = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let interest = |periods_per_year, days| {
+            OverdueInterestCalculator::new(InterestTerms::compound(
+                BigDecimal::from(12),
+                periods_per_year,
+            ))
+            .calculate(
+                &invoice("inv1", "acme", due, 10000),
+                due + chrono::Duration::days(days),
+            )
+            .unwrap()
+            .interest
+        };
+        let amount = |s: &str| s.parse::<BigDecimal>().unwrap();
+
+        // A full year monthly: 10,000 x (1.01^12 - 1) = 1,268.2503...
+        assert_eq!(interest(12, 365), amount("1268.25"));
+        // 45 days monthly: one period (10,100), then 175/365 of a period
+        // at 1% on 10,100 = 48.4246...
+        assert_eq!(interest(12, 45), amount("148.42"));
+        // 100 days quarterly: one period (10,300), then 35/365 of a period
+        // at 3% on 10,300 = 29.6301...
+        assert_eq!(interest(4, 100), amount("329.63"));
+    }
+
+    #[test]
+    fn test_report_groups_by_customer() {
+        let due = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let as_of = due + chrono::Duration::days(73);
+        let calculator =
+            OverdueInterestCalculator::new(InterestTerms::simple(BigDecimal::from(18)));
+        let report = calculator.report(
+            &[
+                invoice("inv1", "acme", due, 10000),
+                invoice("inv2", "acme", due, 5000),
+                invoice("inv3", "globex", as_of, 7000),
+            ],
+            as_of,
+        );
+
+        assert_eq!(report.customers.len(), 1);
+        assert_eq!(report.customers[0].total_overdue, BigDecimal::from(15000));
+        assert_eq!(report.total_interest, BigDecimal::from(540));
+    }
+}
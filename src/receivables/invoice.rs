@@ -0,0 +1,471 @@
+//! Sales invoices
+//!
+//! An [`Invoice`] carries the GST line items billed to a customer, its
+//! payment terms and how much has been paid. It reaches the ledger only when
+//! issued, as one sales voucher crediting revenue and each GST component to
+//! its own output account.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{OpenInvoice, PaymentTerms};
+use crate::ledger::transaction::patterns::gst_legs;
+use crate::ledger::{
+    invoice_digest, DocumentSignature, GstAccounts, Ledger, TransactionBuilder, PARTY_DIMENSION,
+};
+use crate::tax::GstInvoice;
+use crate::traits::*;
+use crate::types::*;
+
+/// Where an invoice is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum InvoiceStatus {
+    /// Not yet posted; can still be edited
+    #[default]
+    Draft,
+    /// Recorded, but its transaction is awaiting approval
+    PendingApproval,
+    /// Posted and awaiting payment
+    Issued,
+    PartiallyPaid,
+    Paid,
+    /// Credited in full by credit notes before any payment stayed on it
+    Credited,
+    /// Credited in full and the payments refunded
+    Refunded,
+    Cancelled,
+}
+
+/// Accounts a sales invoice posts to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SalesAccounts {
+    pub receivables_account_id: String,
+    pub revenue_account_id: String,
+    pub output_tax: GstAccounts,
+}
+
+/// A customer invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Invoice {
+    pub id: String,
+    pub customer_id: String,
+    pub date: NaiveDate,
+    pub due_date: NaiveDate,
+    /// Terms agreed for this invoice; the customer's apply when `None`
+    pub payment_terms: Option<PaymentTerms>,
+    /// Line items and GST; round with [`GstInvoice::rounded`] before issuing
+    pub gst: GstInvoice,
+    /// State code of the place of supply; with a
+    /// [GST registration](Ledger::gst_registration) configured, decides
+    /// whether the invoice is issued with IGST or CGST and SGST
+    #[serde(default)]
+    pub place_of_supply: Option<String>,
+    /// Compensation cess charged on top of GST
+    #[serde(default)]
+    pub cess_amount: BigDecimal,
+    pub status: InvoiceStatus,
+    #[serde(default)]
+    pub amount_paid: BigDecimal,
+    /// Reduction by credit notes
+    #[serde(default)]
+    pub amount_credited: BigDecimal,
+    /// Payments returned to the customer
+    #[serde(default)]
+    pub amount_refunded: BigDecimal,
+    /// Quotation or proforma invoice the invoice was converted from
+    pub quotation_id: Option<String>,
+    /// Transaction the invoice was posted as
+    pub transaction_id: Option<String>,
+    /// Signature taken when the invoice was issued, if a signer is set
+    #[serde(default)]
+    pub signature: Option<DocumentSignature>,
+}
+
+impl Invoice {
+    /// Create a draft invoice
+    ///
+    /// Fails if the payment terms put the due date out of range.
+    pub fn new(
+        id: String,
+        customer_id: String,
+        date: NaiveDate,
+        gst: GstInvoice,
+        payment_terms: Option<PaymentTerms>,
+    ) -> LedgerResult<Self> {
+        Ok(Self {
+            id,
+            customer_id,
+            date,
+            due_date: payment_terms.unwrap_or_default().due_date(date)?,
+            payment_terms,
+            gst,
+            place_of_supply: None,
+            cess_amount: BigDecimal::zero(),
+            status: InvoiceStatus::Draft,
+            amount_paid: BigDecimal::zero(),
+            amount_credited: BigDecimal::zero(),
+            amount_refunded: BigDecimal::zero(),
+            quotation_id: None,
+            transaction_id: None,
+            signature: None,
+        })
+    }
+
+    /// Amount billed, including GST and cess
+    pub fn total(&self) -> BigDecimal {
+        &self.gst.grand_total + &self.cess_amount
+    }
+
+    /// Amount still to be paid; negative when the customer is owed money
+    pub fn outstanding(&self) -> BigDecimal {
+        self.total() - &self.amount_credited - &self.amount_paid
+    }
+
+    /// Whether the invoice has been posted and not fully settled
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self.status,
+            InvoiceStatus::Issued | InvoiceStatus::PartiallyPaid
+        )
+    }
+
+    /// The invoice as dunning and overdue interest see it, while open
+    pub fn to_open_invoice(&self) -> Option<OpenInvoice> {
+        self.is_open().then(|| OpenInvoice {
+            payment_terms: self.payment_terms,
+            ..OpenInvoice::new(
+                self.id.clone(),
+                self.customer_id.clone(),
+                self.date,
+                self.due_date,
+                self.outstanding(),
+            )
+        })
+    }
+
+    /// Build the sales voucher for the invoice (debit the customer's
+    /// receivable, credit revenue and each GST component)
+    pub fn posting(&self, accounts: &SalesAccounts) -> LedgerResult<Transaction> {
+        let legs = gst_legs(
+            &accounts.output_tax,
+            [
+                &self.gst.total_cgst,
+                &self.gst.total_sgst,
+                &self.gst.total_igst,
+            ],
+            &self.cess_amount,
+            "output",
+        )?;
+        let mut builder = TransactionBuilder::new(
+            self.id.clone(),
+            self.date,
+            format!("Invoice {} to {}", self.id, self.customer_id),
+        )
+        .kind(TransactionKind::Sales)
+        .entry(
+            Entry::debit(
+                accounts.receivables_account_id.clone(),
+                self.total(),
+                Some("Total including GST".to_string()),
+            )
+            .with_dimension(PARTY_DIMENSION.to_string(), self.customer_id.clone()),
+        )
+        .credit(
+            accounts.revenue_account_id.clone(),
+            self.gst.total_before_gst.clone(),
+            Some("Revenue amount".to_string()),
+        );
+        for (account_id, amount, description) in legs {
+            builder = builder.credit(account_id, amount, Some(description));
+        }
+        builder.build()
+    }
+
+    /// Apply a payment against the invoice
+    pub fn record_payment(&mut self, amount: &BigDecimal) -> LedgerResult<()> {
+        if !self.is_open() {
+            return Err(LedgerError::Validation(format!(
+                "Invoice {} is not open for payment",
+                self.id
+            )));
+        }
+        if !amount.is_positive() || amount > &self.outstanding() {
+            return Err(LedgerError::Validation(format!(
+                "Payment of {} does not fit the {} outstanding on invoice {}",
+                amount,
+                self.outstanding(),
+                self.id
+            )));
+        }
+        self.amount_paid += amount;
+        self.refresh_status();
+        Ok(())
+    }
+
+    /// Derive the status of an issued invoice from its amounts
+    pub(crate) fn refresh_status(&mut self) {
+        let fully_credited = self.amount_credited >= self.total();
+        self.status = if fully_credited && self.amount_paid.is_zero() {
+            if self.amount_refunded.is_zero() {
+                InvoiceStatus::Credited
+            } else {
+                InvoiceStatus::Refunded
+            }
+        } else if !self.outstanding().is_positive() {
+            InvoiceStatus::Paid
+        } else if self.amount_paid.is_positive() {
+            InvoiceStatus::PartiallyPaid
+        } else {
+            InvoiceStatus::Issued
+        };
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Post a draft invoice and mark it issued
+    ///
+    /// The due date is recalculated from the invoice's terms, or the
+    /// customer's agreed terms when the invoice has none. When both the
+    /// ledger's GST registration and the invoice's place of supply are known,
+    /// lines are re-split into IGST for an inter-state supply or CGST and
+    /// SGST for an intra-state one. With a
+    /// [signer](Ledger::set_signer) set, the issued invoice is signed before
+    /// anything is posted. If the transaction is held for approval the
+    /// invoice is left pending; see [`Ledger::refresh_invoice_approval`].
+    pub async fn issue_invoice(
+        &mut self,
+        invoice: &mut Invoice,
+        accounts: &SalesAccounts,
+    ) -> LedgerResult<Transaction> {
+        if invoice.status != InvoiceStatus::Draft {
+            return Err(LedgerError::Validation(format!(
+                "Invoice {} has already been issued",
+                invoice.id
+            )));
+        }
+        let mut issued = invoice.clone();
+        if let (Some(registration), Some(place_of_supply)) =
+            (&self.gst_registration, &invoice.place_of_supply)
+        {
+            issued.gst = invoice
+                .gst
+                .with_supply(
+                    registration.is_inter_state(place_of_supply),
+                    &self.currency_amount_policy(),
+                )
+                .map_err(|e| LedgerError::Validation(e.to_string()))?;
+        }
+        let transaction = issued.posting(accounts)?;
+        issued.due_date =
+            self.invoice_due_date(&invoice.customer_id, invoice.date, invoice.payment_terms)?;
+        issued.transaction_id = Some(transaction.id.clone());
+        issued.signature = self.sign_digest(invoice_digest(&issued)?)?;
+        let transaction = self.record_transaction(transaction).await?;
+        issued.status = if transaction.is_posted() {
+            InvoiceStatus::Issued
+        } else {
+            InvoiceStatus::PendingApproval
+        };
+        *invoice = issued;
+        Ok(transaction)
+    }
+
+    /// Settle an invoice held for approval once its transaction is decided
+    ///
+    /// The invoice is issued when the transaction is approved and cancelled
+    /// when it is rejected; while still pending it is left unchanged.
+    pub async fn refresh_invoice_approval(&self, invoice: &mut Invoice) -> LedgerResult<()> {
+        let Some(transaction_id) = &invoice.transaction_id else {
+            return Ok(());
+        };
+        if invoice.status != InvoiceStatus::PendingApproval {
+            return Ok(());
+        }
+        let transaction = self
+            .transaction_manager
+            .get_transaction_required(transaction_id)
+            .await?;
+        match transaction.status {
+            TransactionStatus::Posted => invoice.status = InvoiceStatus::Issued,
+            TransactionStatus::Rejected => invoice.status = InvoiceStatus::Cancelled,
+            TransactionStatus::Draft | TransactionStatus::PendingApproval => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::LedgerConfig;
+    use crate::tax::{GstLineItem, GstRate, GstRegistration};
+    use crate::utils::MemoryStorage;
+
+    async fn sales_ledger() -> (Ledger<MemoryStorage>, SalesAccounts) {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name) in [("2310", "CGST Output"), ("2320", "SGST Output")] {
+            ledger
+                .create_account(
+                    id.to_string(),
+                    name.to_string(),
+                    AccountType::Liability,
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+        ledger.set_party_payment_terms("acme", Some(PaymentTerms::NET_30));
+        let accounts = SalesAccounts {
+            receivables_account_id: "1200".to_string(),
+            revenue_account_id: "4000".to_string(),
+            output_tax: GstAccounts {
+                cgst_account_id: "2310".to_string(),
+                sgst_account_id: "2320".to_string(),
+                igst_account_id: "2330".to_string(),
+                cess_account_id: None,
+            },
+        };
+        (ledger, accounts)
+    }
+
+    fn consulting_invoice(id: &str) -> Invoice {
+        let line = GstLineItem::new(
+            "Consulting".to_string(),
+            BigDecimal::from(2),
+            BigDecimal::from(500),
+            GstRate::intra_state(BigDecimal::from(18)),
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        Invoice::new(
+            id.to_string(),
+            "acme".to_string(),
+            date,
+            GstInvoice::new(vec![line]),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_issue_and_settle_invoice() {
+        let (mut ledger, accounts) = sales_ledger().await;
+        let mut invoice = consulting_invoice("INV-1");
+        assert!(invoice.to_open_invoice().is_none());
+
+        ledger.issue_invoice(&mut invoice, &accounts).await.unwrap();
+        assert_eq!(
+            invoice.due_date,
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()
+        );
+        assert_eq!(
+            ledger.get_account_balance("2310", None).await.unwrap(),
+            BigDecimal::from(90)
+        );
+        assert!(ledger.issue_invoice(&mut invoice, &accounts).await.is_err());
+
+        invoice.record_payment(&BigDecimal::from(1000)).unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::PartiallyPaid);
+        assert_eq!(
+            invoice.to_open_invoice().unwrap().outstanding,
+            BigDecimal::from(180)
+        );
+        assert!(invoice.record_payment(&BigDecimal::from(200)).is_err());
+        invoice.record_payment(&BigDecimal::from(180)).unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Paid);
+    }
+
+    #[tokio::test]
+    async fn test_invoice_tax_split_by_place_of_supply() {
+        let (mut ledger, accounts) = sales_ledger().await;
+        ledger
+            .create_account(
+                "2330".to_string(),
+                "IGST Output".to_string(),
+                AccountType::Liability,
+                None,
+            )
+            .await
+            .unwrap();
+        let registration =
+            GstRegistration::new("29ABCDE1234F1Z5".to_string(), "Acme Pvt Ltd".to_string())
+                .unwrap();
+        ledger
+            .apply_config(LedgerConfig {
+                gst_registration: Some(registration),
+                ..ledger.config()
+            })
+            .unwrap();
+
+        let mut local = consulting_invoice("INV-1");
+        local.place_of_supply = Some("29".to_string());
+        ledger.issue_invoice(&mut local, &accounts).await.unwrap();
+        let mut interstate = consulting_invoice("INV-2");
+        interstate.place_of_supply = Some("27".to_string());
+        ledger
+            .issue_invoice(&mut interstate, &accounts)
+            .await
+            .unwrap();
+
+        assert_eq!(local.gst.total_cgst, BigDecimal::from(90));
+        assert_eq!(interstate.gst.total_igst, BigDecimal::from(180));
+        assert_eq!(interstate.total(), local.total());
+        assert_eq!(
+            ledger.get_account_balance("2330", None).await.unwrap(),
+            BigDecimal::from(180)
+        );
+        assert_eq!(
+            ledger.get_account_balance("2310", None).await.unwrap(),
+            BigDecimal::from(90)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invoice_held_for_approval() {
+        let (mut ledger, accounts) = sales_ledger().await;
+        ledger.set_approval_threshold(Some(BigDecimal::from(1000)));
+        ledger.set_actor(Some(Actor::new("maker".to_string(), vec![])));
+        let mut approved = consulting_invoice("INV-1");
+        let mut rejected = consulting_invoice("INV-2");
+
+        let held = ledger
+            .issue_invoice(&mut approved, &accounts)
+            .await
+            .unwrap();
+        assert_eq!(held.status, TransactionStatus::PendingApproval);
+        assert_eq!(approved.status, InvoiceStatus::PendingApproval);
+        assert!(approved.to_open_invoice().is_none());
+        assert!(approved.record_payment(&BigDecimal::from(100)).is_err());
+        ledger
+            .issue_invoice(&mut rejected, &accounts)
+            .await
+            .unwrap();
+
+        ledger.set_actor(Some(Actor::new("checker".to_string(), vec![])));
+        ledger
+            .refresh_invoice_approval(&mut approved)
+            .await
+            .unwrap();
+        assert_eq!(approved.status, InvoiceStatus::PendingApproval);
+        ledger.approve_transaction("INV-1", None).await.unwrap();
+        ledger
+            .reject_transaction("INV-2", "Duplicate".to_string())
+            .await
+            .unwrap();
+        ledger
+            .refresh_invoice_approval(&mut approved)
+            .await
+            .unwrap();
+        ledger
+            .refresh_invoice_approval(&mut rejected)
+            .await
+            .unwrap();
+        assert_eq!(approved.status, InvoiceStatus::Issued);
+        assert_eq!(rejected.status, InvoiceStatus::Cancelled);
+    }
+}
@@ -0,0 +1,64 @@
+//! Receivables module: invoices and quotations, receipts, credit notes and
+//! refunds, gift vouchers, open invoices, overdue interest and payment reminders
+
+pub mod dunning;
+pub mod interest;
+pub mod invoice;
+pub mod quotation;
+pub mod receipt;
+pub mod refund;
+pub mod terms;
+pub mod voucher;
+
+pub use dunning::*;
+pub use interest::*;
+pub use invoice::*;
+pub use quotation::*;
+pub use receipt::*;
+pub use refund::*;
+pub use terms::*;
+pub use voucher::*;
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// An unpaid or partly paid customer invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct OpenInvoice {
+    pub invoice_id: String,
+    pub customer_id: String,
+    pub invoice_date: NaiveDate,
+    pub due_date: NaiveDate,
+    /// Amount still outstanding
+    pub outstanding: BigDecimal,
+    /// Terms the due date was set by
+    #[serde(default)]
+    pub payment_terms: Option<PaymentTerms>,
+}
+
+impl OpenInvoice {
+    /// Create a new open invoice
+    pub fn new(
+        invoice_id: String,
+        customer_id: String,
+        invoice_date: NaiveDate,
+        due_date: NaiveDate,
+        outstanding: BigDecimal,
+    ) -> Self {
+        Self {
+            invoice_id,
+            customer_id,
+            invoice_date,
+            due_date,
+            outstanding,
+            payment_terms: None,
+        }
+    }
+
+    /// Days past the due date as of a date (zero if not yet due)
+    pub fn days_overdue(&self, as_of: NaiveDate) -> i64 {
+        (as_of - self.due_date).num_days().max(0)
+    }
+}
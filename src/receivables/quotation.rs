@@ -0,0 +1,207 @@
+//! Quotations and proforma invoices
+//!
+//! Both are offers: they carry the same GST line items as an [`Invoice`]
+//! but never post to the ledger. Once the customer accepts, the document is
+//! converted into a draft invoice that carries the items, customer and terms
+//! forward and records which quotation it came from.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{Invoice, PaymentTerms};
+use crate::tax::GstInvoice;
+use crate::types::*;
+
+/// Kind of offer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum QuotationKind {
+    #[default]
+    Quotation,
+    /// Sent ahead of supply, typically to collect an advance
+    Proforma,
+}
+
+/// Where a quotation is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum QuotationStatus {
+    #[default]
+    Open,
+    /// Converted into an invoice
+    Converted,
+    Cancelled,
+}
+
+/// A quotation or proforma invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Quotation {
+    pub id: String,
+    pub kind: QuotationKind,
+    pub customer_id: String,
+    pub date: NaiveDate,
+    /// Last day the offer can be accepted
+    pub valid_until: Option<NaiveDate>,
+    pub payment_terms: Option<PaymentTerms>,
+    pub gst: GstInvoice,
+    #[serde(default)]
+    pub cess_amount: BigDecimal,
+    pub status: QuotationStatus,
+    /// Invoice the quotation was converted into
+    pub invoice_id: Option<String>,
+}
+
+/// A proforma invoice is a [`Quotation`] of kind [`QuotationKind::Proforma`]
+pub type ProformaInvoice = Quotation;
+
+impl Quotation {
+    /// Create an open quotation
+    pub fn new(id: String, customer_id: String, date: NaiveDate, gst: GstInvoice) -> Self {
+        Self {
+            id,
+            kind: QuotationKind::Quotation,
+            customer_id,
+            date,
+            valid_until: None,
+            payment_terms: None,
+            gst,
+            cess_amount: BigDecimal::zero(),
+            status: QuotationStatus::Open,
+            invoice_id: None,
+        }
+    }
+
+    /// Create an open proforma invoice
+    pub fn proforma(id: String, customer_id: String, date: NaiveDate, gst: GstInvoice) -> Self {
+        Self {
+            kind: QuotationKind::Proforma,
+            ..Self::new(id, customer_id, date, gst)
+        }
+    }
+
+    /// Set the last day the offer can be accepted
+    pub fn valid_until(mut self, date: NaiveDate) -> Self {
+        self.valid_until = Some(date);
+        self
+    }
+
+    /// Set the payment terms offered
+    pub fn payment_terms(mut self, terms: PaymentTerms) -> Self {
+        self.payment_terms = Some(terms);
+        self
+    }
+
+    /// Amount offered, including GST and cess
+    pub fn total(&self) -> BigDecimal {
+        &self.gst.grand_total + &self.cess_amount
+    }
+
+    /// Whether the offer has lapsed by `date`
+    pub fn is_expired(&self, date: NaiveDate) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| date > valid_until)
+    }
+
+    /// Withdraw the offer
+    pub fn cancel(&mut self) -> LedgerResult<()> {
+        if self.status == QuotationStatus::Converted {
+            return Err(LedgerError::Validation(format!(
+                "Quotation {} has already been invoiced as {}",
+                self.id,
+                self.invoice_id.as_deref().unwrap_or_default()
+            )));
+        }
+        self.status = QuotationStatus::Cancelled;
+        Ok(())
+    }
+
+    /// Turn the accepted offer into a draft invoice dated `date`
+    ///
+    /// The invoice still has to be issued to post it.
+    pub fn convert_to_invoice(
+        &mut self,
+        invoice_id: String,
+        date: NaiveDate,
+    ) -> LedgerResult<Invoice> {
+        match self.status {
+            QuotationStatus::Open => {}
+            QuotationStatus::Converted => {
+                return Err(LedgerError::Validation(format!(
+                    "Quotation {} has already been invoiced as {}",
+                    self.id,
+                    self.invoice_id.as_deref().unwrap_or_default()
+                )))
+            }
+            QuotationStatus::Cancelled => {
+                return Err(LedgerError::Validation(format!(
+                    "Quotation {} has been cancelled",
+                    self.id
+                )))
+            }
+        }
+        if self.is_expired(date) {
+            return Err(LedgerError::Validation(format!(
+                "Quotation {} expired on {}",
+                self.id,
+                self.valid_until.unwrap_or(self.date)
+            )));
+        }
+
+        let mut invoice = Invoice::new(
+            invoice_id,
+            self.customer_id.clone(),
+            date,
+            self.gst.clone(),
+            self.payment_terms,
+        )?;
+        invoice.cess_amount = self.cess_amount.clone();
+        invoice.quotation_id = Some(self.id.clone());
+        self.status = QuotationStatus::Converted;
+        self.invoice_id = Some(invoice.id.clone());
+        Ok(invoice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receivables::InvoiceStatus;
+    use crate::tax::{GstLineItem, GstRate};
+
+    #[test]
+    fn test_convert_proforma_to_invoice() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 4, d).unwrap();
+        let line = GstLineItem::new(
+            "Annual support".to_string(),
+            BigDecimal::from(1),
+            BigDecimal::from(12000),
+            GstRate::inter_state(BigDecimal::from(18)),
+        )
+        .unwrap();
+        let mut proforma = ProformaInvoice::proforma(
+            "PF-7".to_string(),
+            "acme".to_string(),
+            date(1),
+            GstInvoice::new(vec![line]),
+        )
+        .valid_until(date(15))
+        .payment_terms(PaymentTerms::NET_15);
+        assert!(proforma.is_expired(date(16)));
+
+        let invoice = proforma
+            .convert_to_invoice("INV-9".to_string(), date(10))
+            .unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Draft);
+        assert_eq!(invoice.quotation_id.as_deref(), Some("PF-7"));
+        assert_eq!(invoice.total(), proforma.total());
+        assert_eq!(invoice.due_date, date(25));
+        assert_eq!(proforma.status, QuotationStatus::Converted);
+        assert_eq!(proforma.invoice_id.as_deref(), Some("INV-9"));
+        assert!(proforma
+            .convert_to_invoice("INV-10".to_string(), date(10))
+            .is_err());
+        assert!(proforma.cancel().is_err());
+    }
+}
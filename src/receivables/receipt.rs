@@ -0,0 +1,432 @@
+//! Customer receipts, their allocation to invoices and customer advances
+//!
+//! A receipt settles the customer's open invoices, earliest due first.
+//! Whatever is left over is booked as an advance, a liability to the
+//! customer kept per customer through the party dimension, and can be
+//! applied to later invoices. Where GST is payable on advances, the tax on
+//! the advance is paid when it is received and taken back when the advance
+//! is applied to an invoice, which charges the full GST itself.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::Invoice;
+use crate::ledger::transaction::patterns::gst_legs;
+use crate::ledger::{GstAccounts, Ledger, TransactionBuilder, PARTY_DIMENSION};
+use crate::tax::{GstCalculation, GstRate};
+use crate::traits::*;
+use crate::types::*;
+
+/// GST payable on advances received
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AdvanceGst {
+    /// Rate of the supply the advance is for; advances include the tax
+    pub rate: GstRate,
+    pub output_tax: GstAccounts,
+    /// Asset account holding the tax paid on advances until they are applied
+    pub adjustment_account_id: String,
+}
+
+/// Accounts receipts and advances post to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReceiptAccounts {
+    pub bank_account_id: String,
+    pub receivables_account_id: String,
+    /// Liability account for customer advances
+    pub advances_account_id: String,
+    /// Set to pay GST on advances when they are received
+    pub advance_gst: Option<AdvanceGst>,
+}
+
+/// Money received from a customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Receipt {
+    pub id: String,
+    pub customer_id: String,
+    pub date: NaiveDate,
+    pub amount: BigDecimal,
+}
+
+/// Part of a receipt or advance applied to one invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InvoiceAllocation {
+    pub invoice_id: String,
+    pub amount: BigDecimal,
+}
+
+/// How a receipt was applied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReceiptAllocation {
+    pub receipt_id: String,
+    /// Invoices settled, earliest due first
+    pub allocations: Vec<InvoiceAllocation>,
+    /// Surplus booked as a customer advance
+    pub advance: BigDecimal,
+    /// GST paid on the advance
+    pub advance_gst: BigDecimal,
+    pub transaction: Transaction,
+}
+
+impl AdvanceGst {
+    /// Tax included in an advance, rounded with `policy`
+    fn tax_on(&self, advance: &BigDecimal, policy: &AmountPolicy) -> LedgerResult<GstCalculation> {
+        GstCalculation::reverse_calculate(advance.clone(), self.rate.clone())
+            .map(|calculation| calculation.rounded(policy))
+            .map_err(|e| LedgerError::Validation(e.to_string()))
+    }
+
+    /// Entries moving the tax on an advance between the output tax accounts
+    /// and the adjustment account: paid out when `receiving`, taken back
+    /// otherwise
+    fn entries(&self, calculation: &GstCalculation, receiving: bool) -> LedgerResult<Vec<Entry>> {
+        let legs = gst_legs(
+            &self.output_tax,
+            [
+                &calculation.cgst_amount,
+                &calculation.sgst_amount,
+                &calculation.igst_amount,
+            ],
+            &BigDecimal::zero(),
+            "on advance",
+        )?;
+        let (tax_side, adjustment_side) = if receiving {
+            (EntryType::Credit, EntryType::Debit)
+        } else {
+            (EntryType::Debit, EntryType::Credit)
+        };
+        let mut entries: Vec<Entry> = legs
+            .into_iter()
+            .map(|(account_id, amount, description)| {
+                Entry::new(account_id, tax_side.clone(), amount, Some(description))
+            })
+            .collect();
+        if !calculation.total_gst_amount.is_zero() {
+            entries.push(Entry::new(
+                self.adjustment_account_id.clone(),
+                adjustment_side,
+                calculation.total_gst_amount.clone(),
+                Some("GST on advance adjustable".to_string()),
+            ));
+        }
+        Ok(entries)
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Record a receipt, settling the customer's open invoices earliest due
+    /// first and booking any surplus as an advance
+    ///
+    /// Invoices of other customers in `invoices` are left alone. A receipt
+    /// held for approval leaves the invoices open until it is posted.
+    pub async fn record_receipt(
+        &mut self,
+        receipt: &Receipt,
+        invoices: &mut [Invoice],
+        accounts: &ReceiptAccounts,
+    ) -> LedgerResult<ReceiptAllocation> {
+        if !receipt.amount.is_positive() {
+            return Err(LedgerError::Validation(format!(
+                "Receipt {} must be for a positive amount",
+                receipt.id
+            )));
+        }
+        let mut open: Vec<&mut Invoice> = invoices
+            .iter_mut()
+            .filter(|invoice| invoice.customer_id == receipt.customer_id && invoice.is_open())
+            .collect();
+        open.sort_by(|a, b| a.due_date.cmp(&b.due_date).then_with(|| a.id.cmp(&b.id)));
+
+        let mut remaining = receipt.amount.clone();
+        let mut allocations = Vec::new();
+        for invoice in &open {
+            if remaining.is_zero() {
+                break;
+            }
+            let amount = invoice.outstanding().min(remaining.clone());
+            remaining -= &amount;
+            allocations.push(InvoiceAllocation {
+                invoice_id: invoice.id.clone(),
+                amount,
+            });
+        }
+        let advance = remaining;
+        let allocated = &receipt.amount - &advance;
+
+        let party = |entry: Entry| {
+            entry.with_dimension(PARTY_DIMENSION.to_string(), receipt.customer_id.clone())
+        };
+        let mut builder = TransactionBuilder::new(
+            receipt.id.clone(),
+            receipt.date,
+            format!("Receipt from {}", receipt.customer_id),
+        )
+        .kind(TransactionKind::Receipt)
+        .debit(
+            accounts.bank_account_id.clone(),
+            receipt.amount.clone(),
+            Some("Amount received".to_string()),
+        );
+        if !allocated.is_zero() {
+            builder = builder.entry(party(Entry::credit(
+                accounts.receivables_account_id.clone(),
+                allocated,
+                Some("Invoices settled".to_string()),
+            )));
+        }
+        let mut advance_gst = BigDecimal::zero();
+        if !advance.is_zero() {
+            builder = builder.entry(party(Entry::credit(
+                accounts.advances_account_id.clone(),
+                advance.clone(),
+                Some("Customer advance".to_string()),
+            )));
+            if let Some(gst) = &accounts.advance_gst {
+                let calculation = gst.tax_on(&advance, &self.currency_amount_policy())?;
+                for entry in gst.entries(&calculation, true)? {
+                    builder = builder.entry(entry);
+                }
+                advance_gst = calculation.total_gst_amount;
+            }
+        }
+        let transaction = builder.build()?;
+        let transaction = self.record_transaction(transaction).await?;
+
+        if transaction.is_posted() {
+            for (invoice, allocation) in open.into_iter().zip(&allocations) {
+                invoice.record_payment(&allocation.amount)?;
+            }
+        }
+        Ok(ReceiptAllocation {
+            receipt_id: receipt.id.clone(),
+            allocations,
+            advance,
+            advance_gst,
+            transaction,
+        })
+    }
+
+    /// Advance held for a customer and not yet applied
+    pub async fn customer_advance(
+        &self,
+        customer_id: &str,
+        accounts: &ReceiptAccounts,
+    ) -> LedgerResult<BigDecimal> {
+        let rows = self
+            .get_entries(&EntryFilter {
+                account_id: Some(accounts.advances_account_id.clone()),
+                dimension: Some((PARTY_DIMENSION.to_string(), customer_id.to_string())),
+                ..EntryFilter::default()
+            })
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| match row.entry.entry_type {
+                EntryType::Credit => row.entry.amount.clone(),
+                EntryType::Debit => -row.entry.amount.clone(),
+            })
+            .sum())
+    }
+
+    /// Apply a customer's advance to an open invoice, as far as both go
+    ///
+    /// Returns `None` when the customer has no advance or the invoice
+    /// nothing outstanding. While the journal is held for approval the
+    /// invoice is left open.
+    pub async fn apply_advance(
+        &mut self,
+        transaction_id: String,
+        date: NaiveDate,
+        invoice: &mut Invoice,
+        accounts: &ReceiptAccounts,
+    ) -> LedgerResult<Option<InvoiceAllocation>> {
+        if !invoice.is_open() {
+            return Ok(None);
+        }
+        let available = self
+            .customer_advance(&invoice.customer_id, accounts)
+            .await?;
+        let amount = available.min(invoice.outstanding());
+        if !amount.is_positive() {
+            return Ok(None);
+        }
+
+        let party = |entry: Entry| {
+            entry.with_dimension(PARTY_DIMENSION.to_string(), invoice.customer_id.clone())
+        };
+        let mut builder = TransactionBuilder::new(
+            transaction_id,
+            date,
+            format!("Advance applied to invoice {}", invoice.id),
+        )
+        .kind(TransactionKind::Journal)
+        .reference(invoice.id.clone())
+        .entry(party(Entry::debit(
+            accounts.advances_account_id.clone(),
+            amount.clone(),
+            Some("Customer advance applied".to_string()),
+        )))
+        .entry(party(Entry::credit(
+            accounts.receivables_account_id.clone(),
+            amount.clone(),
+            Some("Invoice settled".to_string()),
+        )));
+        if let Some(gst) = &accounts.advance_gst {
+            let calculation = gst.tax_on(&amount, &self.currency_amount_policy())?;
+            for entry in gst.entries(&calculation, false)? {
+                builder = builder.entry(entry);
+            }
+        }
+        let transaction = self.record_transaction(builder.build()?).await?;
+
+        if transaction.is_posted() {
+            invoice.record_payment(&amount)?;
+        }
+        Ok(Some(InvoiceAllocation {
+            invoice_id: invoice.id.clone(),
+            amount,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receivables::{InvoiceStatus, SalesAccounts};
+    use crate::tax::{GstInvoice, GstLineItem};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_surplus_receipt_becomes_advance() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("2330", "IGST Output", AccountType::Liability),
+            ("2500", "Customer Advances", AccountType::Liability),
+            ("1450", "GST on Advances", AccountType::Asset),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let output_tax = GstAccounts {
+            cgst_account_id: "2310".to_string(),
+            sgst_account_id: "2320".to_string(),
+            igst_account_id: "2330".to_string(),
+            cess_account_id: None,
+        };
+        let sales = SalesAccounts {
+            receivables_account_id: "1200".to_string(),
+            revenue_account_id: "4100".to_string(),
+            output_tax: output_tax.clone(),
+        };
+        let rate = GstRate::inter_state(BigDecimal::from(18));
+        let accounts = ReceiptAccounts {
+            bank_account_id: "1000".to_string(),
+            receivables_account_id: "1200".to_string(),
+            advances_account_id: "2500".to_string(),
+            advance_gst: Some(AdvanceGst {
+                rate: rate.clone(),
+                output_tax,
+                adjustment_account_id: "1450".to_string(),
+            }),
+        };
+        let date = |d| NaiveDate::from_ymd_opt(2024, 7, d).unwrap();
+        let invoice = |id: &str, d| {
+            Invoice::new(
+                id.to_string(),
+                "acme".to_string(),
+                date(d),
+                GstInvoice::new(vec![GstLineItem::new(
+                    "Retainer".to_string(),
+                    BigDecimal::from(1),
+                    BigDecimal::from(1000),
+                    rate.clone(),
+                )
+                .unwrap()]),
+                None,
+            )
+            .unwrap()
+        };
+
+        let mut invoices = vec![invoice("INV-1", 1)];
+        ledger
+            .issue_invoice(&mut invoices[0], &sales)
+            .await
+            .unwrap();
+        let receipt = Receipt {
+            id: "RCPT-1".to_string(),
+            customer_id: "acme".to_string(),
+            date: date(5),
+            amount: BigDecimal::from(1770),
+        };
+        let allocation = ledger
+            .record_receipt(&receipt, &mut invoices, &accounts)
+            .await
+            .unwrap();
+        assert_eq!(allocation.allocations[0].amount, BigDecimal::from(1180));
+        assert_eq!(allocation.advance, BigDecimal::from(590));
+        assert_eq!(allocation.advance_gst, BigDecimal::from(90));
+        assert_eq!(invoices[0].status, InvoiceStatus::Paid);
+        assert_eq!(
+            ledger.customer_advance("acme", &accounts).await.unwrap(),
+            BigDecimal::from(590)
+        );
+        assert_eq!(
+            ledger.get_account_balance("2330", None).await.unwrap(),
+            BigDecimal::from(270)
+        );
+
+        let mut next = invoice("INV-2", 20);
+        ledger.issue_invoice(&mut next, &sales).await.unwrap();
+        let applied = ledger
+            .apply_advance("ADV-1".to_string(), date(20), &mut next, &accounts)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(applied.amount, BigDecimal::from(590));
+        assert_eq!(next.status, InvoiceStatus::PartiallyPaid);
+        assert!(ledger
+            .customer_advance("acme", &accounts)
+            .await
+            .unwrap()
+            .is_zero());
+        // Tax on the advance is taken back; the invoice carries the full tax
+        assert!(ledger
+            .get_account_balance("1450", None)
+            .await
+            .unwrap()
+            .is_zero());
+        assert_eq!(
+            ledger.get_account_balance("2330", None).await.unwrap(),
+            BigDecimal::from(360)
+        );
+
+        // A receipt held for approval leaves the invoice open
+        let mut held = vec![invoice("INV-3", 25)];
+        ledger.issue_invoice(&mut held[0], &sales).await.unwrap();
+        ledger.set_approval_threshold(Some(BigDecimal::from(1000)));
+        let receipt = Receipt {
+            id: "RCPT-2".to_string(),
+            customer_id: "acme".to_string(),
+            date: date(28),
+            amount: BigDecimal::from(1180),
+        };
+        let allocation = ledger
+            .record_receipt(&receipt, &mut held, &accounts)
+            .await
+            .unwrap();
+        assert_eq!(
+            allocation.transaction.status,
+            TransactionStatus::PendingApproval
+        );
+        assert_eq!(held[0].status, InvoiceStatus::Issued);
+        assert_eq!(held[0].outstanding(), BigDecimal::from(1180));
+    }
+}
@@ -0,0 +1,411 @@
+//! Credit notes and customer refunds
+//!
+//! Goods returned or a price reduced after invoicing are credited with a
+//! [`CreditNote`], which reverses the revenue and the output GST on the
+//! credited lines. Money then goes back to the customer with a [`Refund`],
+//! which undoes the allocation of their payment to the invoice and is paid
+//! out of the bank or through the payment gateway the customer paid with.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{Invoice, InvoiceStatus, SalesAccounts};
+use crate::ledger::transaction::patterns::gst_legs;
+use crate::ledger::{Ledger, TransactionBuilder, PARTY_DIMENSION, REVERSAL_KEY};
+use crate::tax::GstInvoice;
+use crate::traits::*;
+use crate::types::*;
+
+/// A reduction of an issued invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CreditNote {
+    pub id: String,
+    pub invoice_id: String,
+    pub customer_id: String,
+    pub date: NaiveDate,
+    /// Lines credited, at the invoice's rates
+    pub gst: GstInvoice,
+    #[serde(default)]
+    pub cess_amount: BigDecimal,
+}
+
+impl CreditNote {
+    /// Credit lines of an invoice
+    pub fn new(id: String, invoice: &Invoice, date: NaiveDate, gst: GstInvoice) -> Self {
+        Self {
+            id,
+            invoice_id: invoice.id.clone(),
+            customer_id: invoice.customer_id.clone(),
+            date,
+            gst,
+            cess_amount: BigDecimal::zero(),
+        }
+    }
+
+    /// Credit an invoice in full
+    pub fn full(id: String, invoice: &Invoice, date: NaiveDate) -> Self {
+        Self {
+            cess_amount: invoice.cess_amount.clone(),
+            ..Self::new(id, invoice, date, invoice.gst.clone())
+        }
+    }
+
+    /// Amount credited, including GST and cess
+    pub fn total(&self) -> BigDecimal {
+        &self.gst.grand_total + &self.cess_amount
+    }
+
+    /// Build the credit note voucher (debit revenue and each GST component,
+    /// credit the customer's receivable)
+    pub fn posting(&self, accounts: &SalesAccounts) -> LedgerResult<Transaction> {
+        let legs = gst_legs(
+            &accounts.output_tax,
+            [
+                &self.gst.total_cgst,
+                &self.gst.total_sgst,
+                &self.gst.total_igst,
+            ],
+            &self.cess_amount,
+            "output reversed",
+        )?;
+        let mut builder = TransactionBuilder::new(
+            self.id.clone(),
+            self.date,
+            format!(
+                "Credit note {} against invoice {}",
+                self.id, self.invoice_id
+            ),
+        )
+        .kind(TransactionKind::Sales)
+        .reference(self.invoice_id.clone())
+        .metadata(REVERSAL_KEY.to_string(), true)
+        .debit(
+            accounts.revenue_account_id.clone(),
+            self.gst.total_before_gst.clone(),
+            Some("Revenue reversed".to_string()),
+        );
+        for (account_id, amount, description) in legs {
+            builder = builder.debit(account_id, amount, Some(description));
+        }
+        builder
+            .entry(
+                Entry::credit(
+                    accounts.receivables_account_id.clone(),
+                    self.total(),
+                    Some("Credit to customer".to_string()),
+                )
+                .with_dimension(PARTY_DIMENSION.to_string(), self.customer_id.clone()),
+            )
+            .build()
+    }
+}
+
+/// How a refund reaches the customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RefundChannel {
+    /// Paid from a bank account
+    Bank { bank_account_id: String },
+    /// Reversed through the payment gateway, which may charge a fee
+    Gateway {
+        clearing_account_id: String,
+        fee: BigDecimal,
+        fee_account_id: String,
+    },
+}
+
+/// Money returned to a customer against an invoice they paid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Refund {
+    pub id: String,
+    pub invoice_id: String,
+    pub customer_id: String,
+    pub date: NaiveDate,
+    pub amount: BigDecimal,
+    pub channel: RefundChannel,
+}
+
+impl Refund {
+    /// Build the refund voucher (debit the customer's receivable, credit
+    /// the bank or gateway clearing account)
+    pub fn posting(&self, receivables_account_id: &str) -> LedgerResult<Transaction> {
+        let builder = TransactionBuilder::new(
+            self.id.clone(),
+            self.date,
+            format!(
+                "Refund to {} for invoice {}",
+                self.customer_id, self.invoice_id
+            ),
+        )
+        .kind(TransactionKind::Payment)
+        .reference(self.invoice_id.clone())
+        .metadata(REVERSAL_KEY.to_string(), true)
+        .entry(
+            Entry::debit(
+                receivables_account_id.to_string(),
+                self.amount.clone(),
+                Some("Payment refunded".to_string()),
+            )
+            .with_dimension(PARTY_DIMENSION.to_string(), self.customer_id.clone()),
+        );
+        let builder = match &self.channel {
+            RefundChannel::Bank { bank_account_id } => builder.credit(
+                bank_account_id.clone(),
+                self.amount.clone(),
+                Some("Bank refund".to_string()),
+            ),
+            RefundChannel::Gateway {
+                clearing_account_id,
+                fee,
+                fee_account_id,
+            } => {
+                let builder = builder.credit(
+                    clearing_account_id.clone(),
+                    &self.amount + fee,
+                    Some("Gateway refund".to_string()),
+                );
+                if fee.is_zero() {
+                    builder
+                } else {
+                    builder.debit(
+                        fee_account_id.clone(),
+                        fee.clone(),
+                        Some("Gateway refund fee".to_string()),
+                    )
+                }
+            }
+        };
+        builder.build()
+    }
+}
+
+fn check_invoice(invoice: &Invoice, invoice_id: &str, customer_id: &str) -> LedgerResult<()> {
+    if invoice.id != invoice_id || invoice.customer_id != customer_id {
+        return Err(LedgerError::Validation(format!(
+            "Document is for invoice {} of {}, not {} of {}",
+            invoice_id, customer_id, invoice.id, invoice.customer_id
+        )));
+    }
+    if matches!(
+        invoice.status,
+        InvoiceStatus::Draft | InvoiceStatus::PendingApproval | InvoiceStatus::Cancelled
+    ) {
+        return Err(LedgerError::Validation(format!(
+            "Invoice {} has not been issued",
+            invoice.id
+        )));
+    }
+    Ok(())
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Post a credit note and reduce the invoice by it
+    ///
+    /// An invoice cannot be credited for more than it was issued for.
+    pub async fn issue_credit_note(
+        &mut self,
+        note: &CreditNote,
+        invoice: &mut Invoice,
+        accounts: &SalesAccounts,
+    ) -> LedgerResult<Transaction> {
+        check_invoice(invoice, &note.invoice_id, &note.customer_id)?;
+        let creditable = invoice.total() - &invoice.amount_credited;
+        if !note.total().is_positive() || note.total() > creditable {
+            return Err(LedgerError::Validation(format!(
+                "Credit note {} for {} exceeds the {} left to credit on invoice {}",
+                note.id,
+                note.total(),
+                creditable,
+                invoice.id
+            )));
+        }
+        let transaction = note.posting(accounts)?;
+        let transaction = self.record_transaction(transaction).await?;
+        invoice.amount_credited += note.total();
+        invoice.refresh_status();
+        Ok(transaction)
+    }
+
+    /// Refund part or all of what a customer paid on an invoice
+    ///
+    /// The refunded amount is taken off the invoice's payments, so unless a
+    /// credit note has reduced the invoice, it falls due again.
+    pub async fn refund_payment(
+        &mut self,
+        refund: &Refund,
+        invoice: &mut Invoice,
+        receivables_account_id: &str,
+    ) -> LedgerResult<Transaction> {
+        check_invoice(invoice, &refund.invoice_id, &refund.customer_id)?;
+        if !refund.amount.is_positive() || refund.amount > invoice.amount_paid {
+            return Err(LedgerError::Validation(format!(
+                "Refund {} of {} exceeds the {} paid on invoice {}",
+                refund.id, refund.amount, invoice.amount_paid, invoice.id
+            )));
+        }
+        let transaction = refund.posting(receivables_account_id)?;
+        let transaction = self.record_transaction(transaction).await?;
+        invoice.amount_paid -= &refund.amount;
+        invoice.amount_refunded += &refund.amount;
+        invoice.refresh_status();
+        Ok(transaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{GstAccounts, PostingRule, PostingRules, RuleSeverity};
+    use crate::receivables::{Receipt, ReceiptAccounts};
+    use crate::tax::{GstLineItem, GstRate};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_credit_note_and_gateway_refund() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        // Credit notes debit revenue, which is fine for a reversal
+        ledger.set_posting_rules(Some(
+            PostingRules::default()
+                .with(PostingRule::IncomeDebited, RuleSeverity::Error)
+                .with(PostingRule::ExpenseCredited, RuleSeverity::Error),
+        ));
+        for (id, name, account_type) in [
+            ("2310", "CGST Output", AccountType::Liability),
+            ("2320", "SGST Output", AccountType::Liability),
+            ("1050", "Gateway Clearing", AccountType::Asset),
+            ("6200", "Gateway Fees", AccountType::Expense),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let accounts = SalesAccounts {
+            receivables_account_id: "1200".to_string(),
+            revenue_account_id: "4000".to_string(),
+            output_tax: GstAccounts {
+                cgst_account_id: "2310".to_string(),
+                sgst_account_id: "2320".to_string(),
+                igst_account_id: "2330".to_string(),
+                cess_account_id: None,
+            },
+        };
+        let line = |quantity| {
+            GstLineItem::new(
+                "Headphones".to_string(),
+                BigDecimal::from(quantity),
+                BigDecimal::from(500),
+                GstRate::intra_state(BigDecimal::from(18)),
+            )
+            .unwrap()
+        };
+        let date = |d| NaiveDate::from_ymd_opt(2024, 8, d).unwrap();
+        let mut invoice = Invoice::new(
+            "INV-1".to_string(),
+            "asha".to_string(),
+            date(1),
+            GstInvoice::new(vec![line(2)]),
+            None,
+        )
+        .unwrap();
+        ledger.issue_invoice(&mut invoice, &accounts).await.unwrap();
+        let receipt = Receipt {
+            id: "RCPT-1".to_string(),
+            customer_id: "asha".to_string(),
+            date: date(2),
+            amount: BigDecimal::from(1180),
+        };
+        let receipt_accounts = ReceiptAccounts {
+            bank_account_id: "1000".to_string(),
+            receivables_account_id: "1200".to_string(),
+            advances_account_id: "2400".to_string(),
+            advance_gst: None,
+        };
+        ledger
+            .record_receipt(
+                &receipt,
+                std::slice::from_mut(&mut invoice),
+                &receipt_accounts,
+            )
+            .await
+            .unwrap();
+
+        // One of the two pairs is returned
+        let note = CreditNote::new(
+            "CN-1".to_string(),
+            &invoice,
+            date(5),
+            GstInvoice::new(vec![line(1)]),
+        );
+        ledger
+            .issue_credit_note(&note, &mut invoice, &accounts)
+            .await
+            .unwrap();
+        assert_eq!(invoice.outstanding(), BigDecimal::from(-590));
+        assert_eq!(
+            ledger.get_account_balance("2310", None).await.unwrap(),
+            BigDecimal::from(45)
+        );
+
+        let refund = Refund {
+            id: "RF-1".to_string(),
+            invoice_id: "INV-1".to_string(),
+            customer_id: "asha".to_string(),
+            date: date(6),
+            amount: BigDecimal::from(590),
+            channel: RefundChannel::Gateway {
+                clearing_account_id: "1050".to_string(),
+                fee: BigDecimal::from(5),
+                fee_account_id: "6200".to_string(),
+            },
+        };
+        ledger
+            .refund_payment(&refund, &mut invoice, "1200")
+            .await
+            .unwrap();
+        assert!(invoice.outstanding().is_zero());
+        assert_eq!(invoice.status, InvoiceStatus::Paid);
+        assert_eq!(
+            ledger.get_account_balance("1050", None).await.unwrap(),
+            BigDecimal::from(-595)
+        );
+
+        // The rest is returned too
+        let rest = CreditNote::new(
+            "CN-2".to_string(),
+            &invoice,
+            date(9),
+            GstInvoice::new(vec![line(1)]),
+        );
+        ledger
+            .issue_credit_note(&rest, &mut invoice, &accounts)
+            .await
+            .unwrap();
+        assert!(ledger
+            .issue_credit_note(&rest, &mut invoice, &accounts)
+            .await
+            .is_err());
+        let refund = Refund {
+            id: "RF-2".to_string(),
+            date: date(10),
+            channel: RefundChannel::Bank {
+                bank_account_id: "1000".to_string(),
+            },
+            ..refund
+        };
+        ledger
+            .refund_payment(&refund, &mut invoice, "1200")
+            .await
+            .unwrap();
+        assert_eq!(invoice.status, InvoiceStatus::Refunded);
+        assert!(ledger
+            .get_account_balance("1200", None)
+            .await
+            .unwrap()
+            .is_zero());
+    }
+}
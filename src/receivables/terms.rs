@@ -0,0 +1,210 @@
+//! Payment terms and due dates
+//!
+//! Terms are agreed per customer or supplier and can be overridden on a
+//! single invoice. The due date they give is what dunning and overdue
+//! interest run on.
+
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::OpenInvoice;
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// When an invoice falls due
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PaymentTerms {
+    /// Due on the invoice date
+    #[default]
+    DueOnReceipt,
+    /// Due this many days after the invoice date
+    Net(u32),
+    /// Due this many days after the end of the invoice month
+    EndOfMonth(u32),
+    /// Due on a fixed day of a later month, e.g. the 10th of the following
+    /// month; the day is clamped to the length of the month
+    DayOfMonth { day: u32, months_after: u32 },
+}
+
+impl PaymentTerms {
+    pub const NET_15: Self = Self::Net(15);
+    pub const NET_30: Self = Self::Net(30);
+    pub const NET_45: Self = Self::Net(45);
+
+    /// Due date of an invoice dated `invoice_date`
+    ///
+    /// Fails if the due date is past the last date chrono can represent.
+    pub fn due_date(&self, invoice_date: NaiveDate) -> LedgerResult<NaiveDate> {
+        let due_date = match *self {
+            PaymentTerms::DueOnReceipt => Some(invoice_date),
+            PaymentTerms::Net(days) => invoice_date.checked_add_days(Days::new(u64::from(days))),
+            PaymentTerms::EndOfMonth(days) => month_end(month_start(invoice_date))
+                .and_then(|end| end.checked_add_days(Days::new(u64::from(days)))),
+            PaymentTerms::DayOfMonth { day, months_after } => month_start(invoice_date)
+                .checked_add_months(Months::new(months_after))
+                .and_then(|start| {
+                    let last_day = month_end(start)?.day();
+                    start.checked_add_days(Days::new(u64::from(day.clamp(1, last_day) - 1)))
+                }),
+        };
+        due_date.ok_or_else(|| {
+            LedgerError::Validation(format!(
+                "Terms {self} put the due date of an invoice dated {invoice_date} out of range"
+            ))
+        })
+    }
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn month_end(month_start: NaiveDate) -> Option<NaiveDate> {
+    month_start
+        .checked_add_months(Months::new(1))?
+        .checked_sub_days(Days::new(1))
+}
+
+impl fmt::Display for PaymentTerms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentTerms::DueOnReceipt => write!(f, "Due on receipt"),
+            PaymentTerms::Net(days) => write!(f, "Net {days}"),
+            PaymentTerms::EndOfMonth(days) => write!(f, "EOM+{days}"),
+            PaymentTerms::DayOfMonth { day, months_after } => {
+                write!(f, "Day {day} of month +{months_after}")
+            }
+        }
+    }
+}
+
+impl OpenInvoice {
+    /// Create an open invoice falling due under the given terms
+    pub fn with_terms(
+        invoice_id: String,
+        customer_id: String,
+        invoice_date: NaiveDate,
+        terms: PaymentTerms,
+        outstanding: BigDecimal,
+    ) -> LedgerResult<Self> {
+        Ok(Self {
+            payment_terms: Some(terms),
+            ..Self::new(
+                invoice_id,
+                customer_id,
+                invoice_date,
+                terms.due_date(invoice_date)?,
+                outstanding,
+            )
+        })
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Agree payment terms with a customer or supplier
+    ///
+    /// Parties are identified as in the [`PARTY_DIMENSION`] of control
+    /// account postings.
+    ///
+    /// [`PARTY_DIMENSION`]: crate::ledger::PARTY_DIMENSION
+    pub fn set_party_payment_terms(&mut self, party_id: &str, terms: Option<PaymentTerms>) {
+        match terms {
+            Some(terms) => {
+                self.party_payment_terms.insert(party_id.to_string(), terms);
+            }
+            None => {
+                self.party_payment_terms.remove(party_id);
+            }
+        }
+    }
+
+    /// Payment terms agreed with a party
+    pub fn party_payment_terms(&self, party_id: &str) -> Option<PaymentTerms> {
+        self.party_payment_terms.get(party_id).copied()
+    }
+
+    /// Due date of a party's invoice: the invoice's own terms if given,
+    /// otherwise the party's, otherwise due on receipt
+    pub fn invoice_due_date(
+        &self,
+        party_id: &str,
+        invoice_date: NaiveDate,
+        invoice_terms: Option<PaymentTerms>,
+    ) -> LedgerResult<NaiveDate> {
+        invoice_terms
+            .or_else(|| self.party_payment_terms(party_id))
+            .unwrap_or_default()
+            .due_date(invoice_date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+
+    #[test]
+    fn test_due_dates() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let invoiced = date(2024, 1, 31);
+        assert_eq!(
+            PaymentTerms::NET_30.due_date(invoiced).unwrap(),
+            date(2024, 3, 1)
+        );
+        assert_eq!(
+            PaymentTerms::EndOfMonth(15)
+                .due_date(date(2024, 2, 3))
+                .unwrap(),
+            date(2024, 3, 15)
+        );
+        assert_eq!(
+            PaymentTerms::DayOfMonth {
+                day: 31,
+                months_after: 1
+            }
+            .due_date(invoiced)
+            .unwrap(),
+            date(2024, 2, 29)
+        );
+        assert_eq!(PaymentTerms::EndOfMonth(15).to_string(), "EOM+15");
+
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.set_party_payment_terms("acme", Some(PaymentTerms::NET_45));
+        assert_eq!(
+            ledger.invoice_due_date("acme", invoiced, None).unwrap(),
+            date(2024, 3, 16)
+        );
+        assert_eq!(
+            ledger
+                .invoice_due_date("acme", invoiced, Some(PaymentTerms::NET_15))
+                .unwrap(),
+            date(2024, 2, 15)
+        );
+        assert_eq!(
+            ledger.invoice_due_date("walk-in", invoiced, None).unwrap(),
+            invoiced
+        );
+
+        let invoice = OpenInvoice::with_terms(
+            "inv-1".to_string(),
+            "acme".to_string(),
+            invoiced,
+            PaymentTerms::NET_15,
+            BigDecimal::from(100),
+        )
+        .unwrap();
+        assert_eq!(invoice.days_overdue(date(2024, 2, 20)), 5);
+
+        assert!(matches!(
+            PaymentTerms::Net(u32::MAX).due_date(NaiveDate::MAX),
+            Err(LedgerError::Validation(_))
+        ));
+        assert!(PaymentTerms::EndOfMonth(0)
+            .due_date(NaiveDate::MAX)
+            .is_err());
+    }
+}
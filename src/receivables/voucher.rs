@@ -0,0 +1,446 @@
+//! Gift vouchers and store credit
+//!
+//! A [`GiftVoucher`] is money held for a future sale: issuing it books a
+//! liability, redeeming it against an invoice consumes that liability in
+//! place of a payment, and whatever is left once it expires is breakage,
+//! released to income. Store credit given in place of a refund is the same
+//! thing, issued to a named customer.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::Invoice;
+use crate::ledger::{Ledger, TransactionBuilder, PARTY_DIMENSION};
+use crate::traits::*;
+use crate::types::*;
+
+/// Entry dimension identifying the gift voucher a liability entry belongs to
+pub const VOUCHER_DIMENSION: &str = "voucher";
+
+/// Where a gift voucher is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum VoucherStatus {
+    /// Not yet issued
+    #[default]
+    Draft,
+    /// Issued with a balance left to redeem
+    Active,
+    /// Redeemed in full
+    Redeemed,
+    /// Expired with the unused balance recognised as breakage
+    Expired,
+}
+
+/// Accounts gift vouchers post to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct VoucherAccounts {
+    /// Liability for balances not yet redeemed
+    pub liability_account_id: String,
+    /// Income for balances that expire unused
+    pub breakage_account_id: String,
+}
+
+/// A gift voucher or store credit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GiftVoucher {
+    pub id: String,
+    /// Customer the credit belongs to; `None` for a bearer voucher
+    pub customer_id: Option<String>,
+    pub issue_date: NaiveDate,
+    /// Last day the voucher can be redeemed; `None` if it never expires
+    pub expiry_date: Option<NaiveDate>,
+    pub face_value: BigDecimal,
+    #[serde(default)]
+    pub amount_redeemed: BigDecimal,
+    /// Unused balance released to income on expiry
+    #[serde(default)]
+    pub amount_expired: BigDecimal,
+    pub status: VoucherStatus,
+}
+
+impl GiftVoucher {
+    /// Create a bearer gift voucher
+    pub fn new(id: String, issue_date: NaiveDate, face_value: BigDecimal) -> Self {
+        Self {
+            id,
+            customer_id: None,
+            issue_date,
+            expiry_date: None,
+            face_value,
+            amount_redeemed: BigDecimal::zero(),
+            amount_expired: BigDecimal::zero(),
+            status: VoucherStatus::Draft,
+        }
+    }
+
+    /// Create store credit for a customer
+    pub fn store_credit(
+        id: String,
+        customer_id: String,
+        issue_date: NaiveDate,
+        amount: BigDecimal,
+    ) -> Self {
+        Self {
+            customer_id: Some(customer_id),
+            ..Self::new(id, issue_date, amount)
+        }
+    }
+
+    /// Set the last day the voucher can be redeemed
+    pub fn expires(mut self, expiry_date: NaiveDate) -> Self {
+        self.expiry_date = Some(expiry_date);
+        self
+    }
+
+    /// Balance left to redeem
+    pub fn balance(&self) -> BigDecimal {
+        &self.face_value - &self.amount_redeemed - &self.amount_expired
+    }
+
+    /// Whether the voucher has passed its expiry date on `date`
+    pub fn is_expired(&self, date: NaiveDate) -> bool {
+        self.expiry_date.is_some_and(|expiry| date > expiry)
+    }
+
+    fn liability(&self, entry: Entry) -> Entry {
+        let entry = entry.with_dimension(VOUCHER_DIMENSION.to_string(), self.id.clone());
+        match &self.customer_id {
+            Some(customer_id) => {
+                entry.with_dimension(PARTY_DIMENSION.to_string(), customer_id.clone())
+            }
+            None => entry,
+        }
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Issue a voucher, booking its face value as a liability
+    ///
+    /// The debit goes to `funding_account_id`: the bank or till for a voucher
+    /// sold, or the receivable for store credit given in place of a refund.
+    /// The voucher becomes active only once the journal is posted; while it
+    /// is held for approval the voucher stays a draft.
+    pub async fn issue_voucher(
+        &mut self,
+        voucher: &mut GiftVoucher,
+        funding_account_id: &str,
+        accounts: &VoucherAccounts,
+    ) -> LedgerResult<Transaction> {
+        if voucher.status != VoucherStatus::Draft {
+            return Err(LedgerError::Validation(format!(
+                "Voucher {} has already been issued",
+                voucher.id
+            )));
+        }
+        if !voucher.face_value.is_positive() {
+            return Err(LedgerError::Validation(format!(
+                "Voucher {} must have a positive face value",
+                voucher.id
+            )));
+        }
+
+        let mut funding = Entry::debit(
+            funding_account_id.to_string(),
+            voucher.face_value.clone(),
+            Some("Voucher consideration".to_string()),
+        );
+        if let Some(customer_id) = &voucher.customer_id {
+            funding = funding.with_dimension(PARTY_DIMENSION.to_string(), customer_id.clone());
+        }
+        let transaction = TransactionBuilder::new(
+            voucher.id.clone(),
+            voucher.issue_date,
+            format!("Gift voucher {} issued", voucher.id),
+        )
+        .kind(TransactionKind::Journal)
+        .entry(funding)
+        .entry(voucher.liability(Entry::credit(
+            accounts.liability_account_id.clone(),
+            voucher.face_value.clone(),
+            Some("Voucher liability".to_string()),
+        )))
+        .build()?;
+        let transaction = self.record_transaction(transaction).await?;
+        if transaction.is_posted() {
+            voucher.status = VoucherStatus::Active;
+        }
+        Ok(transaction)
+    }
+
+    /// Redeem a voucher against an open invoice, as far as both go
+    ///
+    /// Store credit can only be redeemed against its own customer's invoices.
+    /// Returns the amount redeemed, which is zero while the journal is held
+    /// for approval; the voucher and invoice are then left unchanged.
+    pub async fn redeem_voucher(
+        &mut self,
+        transaction_id: String,
+        date: NaiveDate,
+        voucher: &mut GiftVoucher,
+        invoice: &mut Invoice,
+        receivables_account_id: &str,
+        accounts: &VoucherAccounts,
+    ) -> LedgerResult<BigDecimal> {
+        if voucher.status != VoucherStatus::Active || voucher.is_expired(date) {
+            return Err(LedgerError::Validation(format!(
+                "Voucher {} cannot be redeemed on {}",
+                voucher.id, date
+            )));
+        }
+        if voucher
+            .customer_id
+            .as_ref()
+            .is_some_and(|customer_id| customer_id != &invoice.customer_id)
+        {
+            return Err(LedgerError::Validation(format!(
+                "Voucher {} belongs to another customer than invoice {}",
+                voucher.id, invoice.id
+            )));
+        }
+        if !invoice.is_open() {
+            return Err(LedgerError::Validation(format!(
+                "Invoice {} is not open for payment",
+                invoice.id
+            )));
+        }
+        let amount = voucher.balance().min(invoice.outstanding());
+
+        let transaction = TransactionBuilder::new(
+            transaction_id,
+            date,
+            format!("Voucher {} redeemed on invoice {}", voucher.id, invoice.id),
+        )
+        .kind(TransactionKind::Journal)
+        .reference(invoice.id.clone())
+        .entry(voucher.liability(Entry::debit(
+            accounts.liability_account_id.clone(),
+            amount.clone(),
+            Some("Voucher redeemed".to_string()),
+        )))
+        .entry(
+            Entry::credit(
+                receivables_account_id.to_string(),
+                amount.clone(),
+                Some("Invoice settled".to_string()),
+            )
+            .with_dimension(PARTY_DIMENSION.to_string(), invoice.customer_id.clone()),
+        )
+        .build()?;
+        let transaction = self.record_transaction(transaction).await?;
+        if !transaction.is_posted() {
+            return Ok(BigDecimal::zero());
+        }
+
+        invoice.record_payment(&amount)?;
+        voucher.amount_redeemed += &amount;
+        if voucher.balance().is_zero() {
+            voucher.status = VoucherStatus::Redeemed;
+        }
+        Ok(amount)
+    }
+
+    /// Release the unused balance of vouchers expired by `as_of` to income
+    ///
+    /// All breakage is recognised in one journal, with a line per voucher.
+    /// Returns `None` when no voucher had expired with a balance. While the
+    /// journal is held for approval the vouchers stay active.
+    pub async fn recognize_breakage(
+        &mut self,
+        transaction_id: String,
+        as_of: NaiveDate,
+        vouchers: &mut [GiftVoucher],
+        accounts: &VoucherAccounts,
+    ) -> LedgerResult<Option<Transaction>> {
+        let expired: Vec<usize> = vouchers
+            .iter()
+            .enumerate()
+            .filter(|(_, voucher)| {
+                voucher.status == VoucherStatus::Active
+                    && voucher.is_expired(as_of)
+                    && voucher.balance().is_positive()
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if expired.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder =
+            TransactionBuilder::new(transaction_id, as_of, "Gift voucher breakage".to_string())
+                .kind(TransactionKind::Adjustment);
+        let mut total = BigDecimal::zero();
+        for &index in &expired {
+            let voucher = &vouchers[index];
+            total += voucher.balance();
+            builder = builder.entry(voucher.liability(Entry::debit(
+                accounts.liability_account_id.clone(),
+                voucher.balance(),
+                Some(format!("Voucher {} expired", voucher.id)),
+            )));
+        }
+        let transaction = builder
+            .credit(
+                accounts.breakage_account_id.clone(),
+                total,
+                Some("Breakage income".to_string()),
+            )
+            .build()?;
+        let transaction = self.record_transaction(transaction).await?;
+        if !transaction.is_posted() {
+            return Ok(Some(transaction));
+        }
+
+        for index in expired {
+            let voucher = &mut vouchers[index];
+            voucher.amount_expired = voucher.balance();
+            voucher.status = VoucherStatus::Expired;
+        }
+        Ok(Some(transaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::GstAccounts;
+    use crate::receivables::{InvoiceStatus, SalesAccounts};
+    use crate::tax::{GstInvoice, GstLineItem, GstRate};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_voucher_issue_redeem_and_breakage() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name, account_type) in [
+            ("2310", "CGST Output", AccountType::Liability),
+            ("2320", "SGST Output", AccountType::Liability),
+            ("2450", "Gift Vouchers", AccountType::Liability),
+            ("4500", "Breakage Income", AccountType::Income),
+        ] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), account_type, None)
+                .await
+                .unwrap();
+        }
+        let accounts = VoucherAccounts {
+            liability_account_id: "2450".to_string(),
+            breakage_account_id: "4500".to_string(),
+        };
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        let mut sold = GiftVoucher::new("GV-1".to_string(), date(1, 10), BigDecimal::from(1000))
+            .expires(date(6, 30));
+        let mut credit = GiftVoucher::store_credit(
+            "SC-1".to_string(),
+            "ravi".to_string(),
+            date(2, 1),
+            BigDecimal::from(300),
+        );
+        ledger
+            .issue_voucher(&mut sold, "1000", &accounts)
+            .await
+            .unwrap();
+        ledger
+            .issue_voucher(&mut credit, "1200", &accounts)
+            .await
+            .unwrap();
+        assert_eq!(
+            ledger.get_account_balance("2450", None).await.unwrap(),
+            BigDecimal::from(1300)
+        );
+
+        let mut invoice = Invoice::new(
+            "INV-7".to_string(),
+            "meera".to_string(),
+            date(3, 1),
+            GstInvoice::new(vec![GstLineItem::new(
+                "Lamp".to_string(),
+                BigDecimal::from(1),
+                BigDecimal::from(500),
+                GstRate::intra_state(BigDecimal::from(18)),
+            )
+            .unwrap()]),
+            None,
+        )
+        .unwrap();
+        let sales = SalesAccounts {
+            receivables_account_id: "1200".to_string(),
+            revenue_account_id: "4000".to_string(),
+            output_tax: GstAccounts {
+                cgst_account_id: "2310".to_string(),
+                sgst_account_id: "2320".to_string(),
+                igst_account_id: "2330".to_string(),
+                cess_account_id: None,
+            },
+        };
+        ledger.issue_invoice(&mut invoice, &sales).await.unwrap();
+
+        // Ravi's store credit cannot settle Meera's invoice
+        assert!(ledger
+            .redeem_voucher(
+                "RD-0".to_string(),
+                date(3, 2),
+                &mut credit,
+                &mut invoice,
+                "1200",
+                &accounts
+            )
+            .await
+            .is_err());
+        let redeemed = ledger
+            .redeem_voucher(
+                "RD-1".to_string(),
+                date(3, 2),
+                &mut sold,
+                &mut invoice,
+                "1200",
+                &accounts,
+            )
+            .await
+            .unwrap();
+        assert_eq!(redeemed, BigDecimal::from(590));
+        assert_eq!(invoice.status, InvoiceStatus::Paid);
+        assert_eq!(sold.balance(), BigDecimal::from(410));
+
+        assert!(ledger
+            .recognize_breakage(
+                "BRK-1".to_string(),
+                date(6, 30),
+                &mut [sold.clone()],
+                &accounts
+            )
+            .await
+            .unwrap()
+            .is_none());
+        let mut vouchers = [sold, credit];
+        ledger
+            .recognize_breakage("BRK-1".to_string(), date(7, 1), &mut vouchers, &accounts)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vouchers[0].status, VoucherStatus::Expired);
+        assert_eq!(vouchers[1].status, VoucherStatus::Active);
+        assert_eq!(
+            ledger.get_account_balance("4500", None).await.unwrap(),
+            BigDecimal::from(410)
+        );
+        assert_eq!(
+            ledger.get_account_balance("2450", None).await.unwrap(),
+            BigDecimal::from(300)
+        );
+
+        // A voucher whose journal is held for approval is not yet active
+        ledger.set_approval_threshold(Some(BigDecimal::from(100)));
+        let mut held = GiftVoucher::new("GV-2".to_string(), date(7, 2), BigDecimal::from(500));
+        let transaction = ledger
+            .issue_voucher(&mut held, "1000", &accounts)
+            .await
+            .unwrap();
+        assert_eq!(transaction.status, TransactionStatus::PendingApproval);
+        assert_eq!(held.status, VoucherStatus::Draft);
+    }
+}
@@ -0,0 +1,199 @@
+//! Pulling bank statement lines from a [`BankFeedProvider`]
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Transaction metadata key identifying the feed line a transaction came from
+pub const BANK_FEED_KEY: &str = "bank_feed_key";
+
+/// Outcome of one bank feed sync
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BankFeedSync {
+    /// Lines returned by the provider
+    pub fetched: usize,
+    /// Lines skipped because an earlier sync imported them
+    pub already_imported: usize,
+    /// Suspense transactions posted for new lines
+    pub imported: Vec<Transaction>,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Fetch lines from a bank feed and post the new ones to suspense
+    ///
+    /// Narrations are parsed for payment details first (see
+    /// [`BankStatementLine::enrich_from_narration`]). Each line is keyed by
+    /// feed account, date, amount and reference (or narration), numbering
+    /// repeats within a day, so overlapping syncs never post a line twice,
+    /// even while an earlier import is still held for approval.
+    /// New lines go through [`Ledger::import_bank_lines_to_suspense`] against
+    /// `bank_account_id`.
+    ///
+    /// [`BankStatementLine::enrich_from_narration`]: crate::reconciliation::BankStatementLine::enrich_from_narration
+    pub async fn sync_bank_feed(
+        &mut self,
+        provider: &dyn BankFeedProvider,
+        feed_account: &str,
+        bank_account_id: &str,
+        since: NaiveDate,
+    ) -> LedgerResult<BankFeedSync> {
+        let lines = provider.fetch_statement_lines(feed_account, since).await?;
+        let fetched = lines.len();
+
+        let imported_keys: HashSet<String> = self
+            .transaction_manager
+            .get_all_account_transactions(bank_account_id, Some(since), None)
+            .await?
+            .iter()
+            .filter_map(|t| t.metadata.get(BANK_FEED_KEY))
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect();
+
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        let mut already_imported = 0;
+        let mut new_lines = Vec::new();
+        for mut line in lines.into_iter().filter(|line| line.date >= since) {
+            line.enrich_from_narration();
+            let identity = format!(
+                "{}:{}:{}:{}",
+                feed_account,
+                line.date,
+                line.amount.normalized(),
+                line.reference.as_deref().unwrap_or(&line.description)
+            );
+            let occurrence = occurrences.entry(identity.clone()).or_default();
+            *occurrence += 1;
+            let key = format!("{identity}#{occurrence}");
+            if imported_keys.contains(&key) {
+                already_imported += 1;
+                continue;
+            }
+            line.metadata.insert(BANK_FEED_KEY.to_string(), key);
+            new_lines.push(line);
+        }
+
+        let imported = self
+            .import_bank_lines_to_suspense(bank_account_id, &new_lines)
+            .await?;
+        Ok(BankFeedSync {
+            fetched,
+            already_imported,
+            imported,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reconciliation::BankStatementLine;
+    use crate::utils::MemoryStorage;
+    use async_trait::async_trait;
+    use bigdecimal::BigDecimal;
+    use std::sync::Mutex;
+
+    struct StaticFeed(Mutex<Vec<BankStatementLine>>);
+
+    #[async_trait]
+    impl BankFeedProvider for StaticFeed {
+        async fn fetch_statement_lines(
+            &self,
+            account: &str,
+            since: NaiveDate,
+        ) -> LedgerResult<Vec<BankStatementLine>> {
+            assert_eq!(account, "aa-link-1");
+            let lines = self.0.lock().unwrap();
+            Ok(lines.iter().filter(|l| l.date >= since).cloned().collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_bank_feed_skips_imported_lines() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "1999".to_string(),
+                "Suspense".to_string(),
+                AccountType::Liability,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger.set_suspense_account(Some("1999".to_string()));
+
+        let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        let fee =
+            || BankStatementLine::new(date, "ATM FEE".to_string(), BigDecimal::from(-20), None);
+        let feed = StaticFeed(Mutex::new(vec![
+            BankStatementLine::new(
+                date,
+                "UPI/CR/412345678901/MEERA/meera@ybl".to_string(),
+                BigDecimal::from(750),
+                None,
+            ),
+            fee(),
+            fee(),
+        ]));
+
+        let first = ledger
+            .sync_bank_feed(&feed, "aa-link-1", "1000", date)
+            .await
+            .unwrap();
+        assert_eq!(first.imported.len(), 3);
+        assert_eq!(first.imported[0].reference.as_deref(), Some("412345678901"));
+
+        feed.0.lock().unwrap().push(fee());
+        let second = ledger
+            .sync_bank_feed(&feed, "aa-link-1", "1000", date)
+            .await
+            .unwrap();
+        assert_eq!(second.fetched, 4);
+        assert_eq!(second.already_imported, 3);
+        assert_eq!(second.imported.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_bank_feed_skips_lines_held_for_approval() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        ledger
+            .create_account(
+                "1999".to_string(),
+                "Suspense".to_string(),
+                AccountType::Liability,
+                None,
+            )
+            .await
+            .unwrap();
+        ledger.set_suspense_account(Some("1999".to_string()));
+        ledger.set_approval_threshold(Some(BigDecimal::from(500)));
+
+        let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        let feed = StaticFeed(Mutex::new(vec![BankStatementLine::new(
+            date,
+            "NEFT FROM ACME".to_string(),
+            BigDecimal::from(750),
+            None,
+        )]));
+
+        let first = ledger
+            .sync_bank_feed(&feed, "aa-link-1", "1000", date)
+            .await
+            .unwrap();
+        assert_eq!(first.imported.len(), 1);
+        assert_eq!(first.imported[0].status, TransactionStatus::PendingApproval);
+
+        let second = ledger
+            .sync_bank_feed(&feed, "aa-link-1", "1000", date)
+            .await
+            .unwrap();
+        assert_eq!(second.already_imported, 1);
+        assert!(second.imported.is_empty());
+    }
+}
@@ -0,0 +1,335 @@
+//! Bank statement import types and parsers
+
+use bigdecimal::{BigDecimal, Signed};
+use chrono::NaiveDate;
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::types::{LedgerError, LedgerResult};
+
+/// A single line from a bank statement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BankStatementLine {
+    /// Value date of the line
+    pub date: NaiveDate,
+    /// Narration as printed by the bank
+    pub description: String,
+    /// Signed amount: positive for deposits, negative for withdrawals
+    pub amount: BigDecimal,
+    /// Bank reference (cheque number, UTR, etc.)
+    pub reference: Option<String>,
+    /// Additional structured information extracted from the statement
+    pub metadata: HashMap<String, String>,
+}
+
+impl BankStatementLine {
+    /// Create a new statement line
+    pub fn new(
+        date: NaiveDate,
+        description: String,
+        amount: BigDecimal,
+        reference: Option<String>,
+    ) -> Self {
+        Self {
+            date,
+            description,
+            amount,
+            reference,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Whether the line is money coming into the account
+    pub fn is_deposit(&self) -> bool {
+        self.amount.is_positive()
+    }
+}
+
+/// An imported amount parked in the suspense account awaiting classification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SuspenseItem {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    pub description: String,
+    pub reference: Option<String>,
+    /// Signed amount: positive when the suspense account was credited
+    /// (money received), negative when it was debited (money paid)
+    pub amount: BigDecimal,
+}
+
+/// One account statement from an ISO 20022 camt.053 file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CamtStatement {
+    /// Statement identification assigned by the bank
+    pub id: String,
+    /// IBAN, or the bank's own account identifier
+    pub account: Option<String>,
+    /// Account currency
+    pub currency: Option<String>,
+    /// Opening booked balance, signed like the lines
+    pub opening_balance: Option<BigDecimal>,
+    /// Closing booked balance, signed like the lines
+    pub closing_balance: Option<BigDecimal>,
+    /// Booked entries in statement order
+    pub lines: Vec<BankStatementLine>,
+}
+
+/// Parse an ISO 20022 camt.053 (bank to customer statement) document
+///
+/// Works with any `camt.053.001.xx` version. Each booked `Ntry` becomes a
+/// [`BankStatementLine`]; pending and informational entries are skipped.
+/// Lines carry the value date (the booking date when there is none), the
+/// remittance information as description and the bank's reference, falling
+/// back to the end-to-end id. The booking date, currency, end-to-end id,
+/// bank transaction code and counterparty name are kept in `metadata` when
+/// present.
+pub fn parse_camt053(xml: &str) -> LedgerResult<Vec<CamtStatement>> {
+    let document = roxmltree::Document::parse(xml)
+        .map_err(|e| LedgerError::InvalidPayload(format!("camt.053: {e}")))?;
+    let report = child(document.root_element(), "BkToCstmrStmt").ok_or_else(|| {
+        LedgerError::InvalidPayload("camt.053: missing BkToCstmrStmt".to_string())
+    })?;
+    children(report, "Stmt").map(parse_statement).collect()
+}
+
+fn parse_statement(statement: Node) -> LedgerResult<CamtStatement> {
+    let account = child(statement, "Acct");
+    let mut parsed = CamtStatement {
+        id: text(statement, &["Id"]).unwrap_or_default(),
+        account: account
+            .and_then(|a| text(a, &["Id", "IBAN"]).or_else(|| text(a, &["Id", "Othr", "Id"]))),
+        currency: account.and_then(|a| text(a, &["Ccy"])),
+        opening_balance: None,
+        closing_balance: None,
+        lines: Vec::new(),
+    };
+    for balance in children(statement, "Bal") {
+        let code = text(balance, &["Tp", "CdOrPrtry", "Cd"]);
+        let amount = signed_amount(balance)?;
+        match code.as_deref() {
+            Some("OPBD") | Some("PRCD") => parsed.opening_balance = Some(amount),
+            Some("CLBD") => parsed.closing_balance = Some(amount),
+            _ => {}
+        }
+    }
+    for entry in children(statement, "Ntry") {
+        let status = text(entry, &["Sts", "Cd"]).or_else(|| text(entry, &["Sts"]));
+        if status.as_deref().is_some_and(|s| s != "BOOK") {
+            continue;
+        }
+        parsed.lines.push(parse_entry(entry)?);
+    }
+    Ok(parsed)
+}
+
+fn parse_entry(entry: Node) -> LedgerResult<BankStatementLine> {
+    let amount = signed_amount(entry)?;
+    let booking_date = date(entry, "BookgDt");
+    let line_date = date(entry, "ValDt")
+        .or(booking_date)
+        .ok_or_else(|| LedgerError::InvalidPayload("camt.053: entry without date".to_string()))?;
+    let details = child(entry, "NtryDtls").and_then(|d| child(d, "TxDtls"));
+
+    let remittance: Vec<String> = details
+        .and_then(|d| child(d, "RmtInf"))
+        .map(|r| {
+            children(r, "Ustrd")
+                .filter_map(|u| u.text().map(|t| t.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let description = if !remittance.is_empty() {
+        remittance.join(" ")
+    } else {
+        details
+            .and_then(|d| text(d, &["AddtlTxInf"]))
+            .or_else(|| text(entry, &["AddtlNtryInf"]))
+            .unwrap_or_default()
+    };
+
+    let end_to_end_id = details
+        .and_then(|d| text(d, &["Refs", "EndToEndId"]))
+        .filter(|id| id != "NOTPROVIDED");
+    let reference = text(entry, &["AcctSvcrRef"])
+        .or_else(|| details.and_then(|d| text(d, &["Refs", "AcctSvcrRef"])))
+        .or_else(|| end_to_end_id.clone());
+
+    let mut line = BankStatementLine::new(line_date, description, amount.clone(), reference);
+    if let Some(currency) = child(entry, "Amt").and_then(|a| a.attribute("Ccy")) {
+        line.metadata
+            .insert("currency".to_string(), currency.to_string());
+    }
+    if let Some(booking_date) = booking_date {
+        line.metadata
+            .insert("booking_date".to_string(), booking_date.to_string());
+    }
+    if let Some(end_to_end_id) = end_to_end_id {
+        line.metadata
+            .insert("end_to_end_id".to_string(), end_to_end_id);
+    }
+    if let Some(code) = bank_transaction_code(entry) {
+        line.metadata
+            .insert("bank_transaction_code".to_string(), code);
+    }
+    // The other side: who paid us, or whom we paid
+    let party = if amount.is_positive() { "Dbtr" } else { "Cdtr" };
+    let counterparty = details.and_then(|d| {
+        text(d, &["RltdPties", party, "Nm"]).or_else(|| text(d, &["RltdPties", party, "Pty", "Nm"]))
+    });
+    if let Some(counterparty) = counterparty {
+        line.metadata
+            .insert("counterparty".to_string(), counterparty);
+    }
+    Ok(line)
+}
+
+/// `Domain/Family/SubFamily` code, or the proprietary code
+fn bank_transaction_code(entry: Node) -> Option<String> {
+    let code = child(entry, "BkTxCd")?;
+    if let Some(domain) = child(code, "Domn") {
+        let parts: Vec<String> = [
+            text(domain, &["Cd"]),
+            text(domain, &["Fmly", "Cd"]),
+            text(domain, &["Fmly", "SubFmlyCd"]),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        return Some(parts.join("/"));
+    }
+    text(code, &["Prtry", "Cd"])
+}
+
+/// `Amt`, negated when `CdtDbtInd` is `DBIT`
+fn signed_amount(node: Node) -> LedgerResult<BigDecimal> {
+    let raw = text(node, &["Amt"])
+        .ok_or_else(|| LedgerError::InvalidPayload("camt.053: missing Amt".to_string()))?;
+    let amount = BigDecimal::from_str(&raw)
+        .map_err(|_| LedgerError::InvalidPayload(format!("camt.053: invalid amount '{raw}'")))?;
+    match text(node, &["CdtDbtInd"]).as_deref() {
+        Some("DBIT") => Ok(-amount),
+        Some("CRDT") => Ok(amount),
+        other => Err(LedgerError::InvalidPayload(format!(
+            "camt.053: invalid CdtDbtInd {other:?}"
+        ))),
+    }
+}
+
+/// Date of a `Dt` or `DtTm` child
+fn date(node: Node, name: &'static str) -> Option<NaiveDate> {
+    let node = child(node, name)?;
+    let value = text(node, &["Dt"]).or_else(|| text(node, &["DtTm"]))?;
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+/// Trimmed text at a path of element names below `node`
+fn text(node: Node, path: &[&'static str]) -> Option<String> {
+    let mut current = node;
+    for name in path {
+        current = child(current, name)?;
+    }
+    current
+        .text()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG-1</MsgId><CreDtTm>2024-04-02T06:00:00</CreDtTm></GrpHdr>
+    <Stmt>
+      <Id>STMT-2024-04-01</Id>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1000.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-04-01</Dt></Dt>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">1130.50</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-04-01</Dt></Dt>
+      </Bal>
+      <Ntry>
+        <Amt Ccy="EUR">250.50</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2024-04-01</Dt></BookgDt>
+        <ValDt><Dt>2024-03-31</Dt></ValDt>
+        <AcctSvcrRef>BANKREF-1</AcctSvcrRef>
+        <BkTxCd><Domn><Cd>PMNT</Cd><Fmly><Cd>RCDT</Cd><SubFmlyCd>ESCT</SubFmlyCd></Fmly></Domn></BkTxCd>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>INV-1042</EndToEndId></Refs>
+          <RltdPties><Dbtr><Pty><Nm>Acme &amp; Sons</Nm></Pty></Dbtr></RltdPties>
+          <RmtInf><Ustrd>Invoice 1042</Ustrd><Ustrd>April</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">120.00</Amt><CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><DtTm>2024-04-01T10:15:00+02:00</DtTm></BookgDt>
+        <AddtlNtryInf>Card fee</AddtlNtryInf>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">75.00</Amt><CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>PDNG</Cd></Sts>
+        <BookgDt><Dt>2024-04-01</Dt></BookgDt>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn test_parse_camt053_statement() {
+        let statements = parse_camt053(STATEMENT).unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.id, "STMT-2024-04-01");
+        assert_eq!(statement.account.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(
+            statement.closing_balance,
+            Some(BigDecimal::from_str("1130.50").unwrap())
+        );
+        assert_eq!(statement.lines.len(), 2);
+
+        let receipt = &statement.lines[0];
+        assert_eq!(receipt.date, NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        assert_eq!(receipt.amount, BigDecimal::from_str("250.50").unwrap());
+        assert_eq!(receipt.description, "Invoice 1042 April");
+        assert_eq!(receipt.reference.as_deref(), Some("BANKREF-1"));
+        assert_eq!(receipt.metadata["end_to_end_id"], "INV-1042");
+        assert_eq!(receipt.metadata["bank_transaction_code"], "PMNT/RCDT/ESCT");
+        assert_eq!(receipt.metadata["counterparty"], "Acme & Sons");
+
+        let fee = &statement.lines[1];
+        assert_eq!(fee.amount, BigDecimal::from(-120));
+        assert_eq!(fee.date, NaiveDate::from_ymd_opt(2024, 4, 1).unwrap());
+        assert_eq!(fee.description, "Card fee");
+        assert!(!fee.is_deposit());
+
+        let error = parse_camt053("<Document/>").unwrap_err();
+        assert_eq!(error.code(), "invalid_payload");
+    }
+}
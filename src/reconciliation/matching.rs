@@ -0,0 +1,296 @@
+//! Ranked, explained match suggestions for bank lines
+//!
+//! Each candidate transaction earns points for what it has in common with
+//! the bank line, and every point comes with a [`MatchReason`] a UI can show:
+//!
+//! | Reason | Score |
+//! |---|---|
+//! | exact amount | 0.5 |
+//! | amount within the engine's tolerance | 0.3 |
+//! | same date | 0.3 |
+//! | date within the tolerance | 0.3, less 0.1 per day apart, at least 0.1 |
+//! | reference match | 0.2 |
+//!
+//! Scores are rounded to two places and capped at 1. Only transactions moving money in the line's
+//! direction through the bank account are considered.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::{BankStatementLine, ReconciliationEngine};
+use crate::ledger::Ledger;
+use crate::traits::*;
+use crate::types::*;
+
+/// Why a transaction was suggested for a bank line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum MatchReason {
+    ExactAmount,
+    /// Amounts differ by `difference`, within the tolerance
+    AmountWithinTolerance {
+        difference: BigDecimal,
+    },
+    SameDate,
+    /// Dated `days` apart
+    DateWithin {
+        days: u32,
+    },
+    /// The reference, or one containing the other, appears on both sides
+    ReferenceMatch {
+        reference: String,
+    },
+}
+
+impl MatchReason {
+    /// Score this reason contributes
+    pub fn score(&self) -> f64 {
+        match self {
+            MatchReason::ExactAmount => 0.5,
+            MatchReason::AmountWithinTolerance { .. } => 0.3,
+            MatchReason::SameDate => 0.3,
+            MatchReason::DateWithin { days } => (0.3 - 0.1 * f64::from(*days)).max(0.1),
+            MatchReason::ReferenceMatch { .. } => 0.2,
+        }
+    }
+}
+
+impl fmt::Display for MatchReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchReason::ExactAmount => write!(f, "exact amount"),
+            MatchReason::AmountWithinTolerance { difference } => {
+                write!(f, "amount differs by {difference}")
+            }
+            MatchReason::SameDate => write!(f, "same date"),
+            MatchReason::DateWithin { days: 1 } => write!(f, "date within 1 day"),
+            MatchReason::DateWithin { days } => write!(f, "date within {days} days"),
+            MatchReason::ReferenceMatch { reference } => {
+                write!(f, "reference '{reference}' matches")
+            }
+        }
+    }
+}
+
+/// A ledger transaction that may correspond to a bank line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MatchSuggestion {
+    pub transaction_id: String,
+    /// From 0 to 1; higher is a better match
+    pub score: f64,
+    /// What the line and the transaction have in common
+    pub reasons: Vec<MatchReason>,
+}
+
+/// Net movement of a transaction through an account: positive when the
+/// account was debited, as a deposit shows on the bank side
+fn bank_movement(transaction: &Transaction, bank_account_id: &str) -> BigDecimal {
+    transaction
+        .entries
+        .iter()
+        .filter(|e| e.account_id == bank_account_id)
+        .map(|e| match e.entry_type {
+            EntryType::Debit => e.amount.clone(),
+            EntryType::Credit => -e.amount.clone(),
+        })
+        .sum()
+}
+
+/// Reference shared by the line and the transaction, if any
+fn shared_reference(line: &BankStatementLine, transaction: &Transaction) -> Option<String> {
+    let reference = transaction
+        .reference
+        .as_deref()
+        .filter(|r| !r.trim().is_empty())?;
+    let wanted = reference.to_lowercase();
+    if let Some(line_reference) = &line.reference {
+        let found = line_reference.to_lowercase();
+        if !found.is_empty() && (found.contains(&wanted) || wanted.contains(&found)) {
+            return Some(reference.to_string());
+        }
+    }
+    line.description
+        .to_lowercase()
+        .contains(&wanted)
+        .then(|| reference.to_string())
+}
+
+impl ReconciliationEngine {
+    /// Score one candidate transaction against a bank line
+    ///
+    /// Returns `None` when the transaction does not touch the bank account in
+    /// the line's direction, is outside the date or amount tolerance, or
+    /// scores below `min_score`.
+    pub fn score_match(
+        &self,
+        line: &BankStatementLine,
+        transaction: &Transaction,
+        bank_account_id: &str,
+    ) -> Option<MatchSuggestion> {
+        let movement = bank_movement(transaction, bank_account_id);
+        if movement.is_zero() || movement.is_positive() != line.amount.is_positive() {
+            return None;
+        }
+        let mut reasons = Vec::new();
+
+        let difference = (&movement - &line.amount).abs();
+        if difference.is_zero() {
+            reasons.push(MatchReason::ExactAmount);
+        } else if difference <= self.amount_tolerance {
+            reasons.push(MatchReason::AmountWithinTolerance { difference });
+        } else {
+            return None;
+        }
+
+        let days = (transaction.date - line.date).num_days().unsigned_abs();
+        match u32::try_from(days) {
+            Ok(0) => reasons.push(MatchReason::SameDate),
+            Ok(days) if days <= self.date_tolerance_days => {
+                reasons.push(MatchReason::DateWithin { days })
+            }
+            _ => return None,
+        }
+
+        if let Some(reference) = shared_reference(line, transaction) {
+            reasons.push(MatchReason::ReferenceMatch { reference });
+        }
+
+        // Rounded so equal evidence gives equal scores whatever the order
+        let total: f64 = reasons.iter().map(MatchReason::score).sum();
+        let score = ((total * 100.0).round() / 100.0).min(1.0);
+        (score >= self.min_score).then(|| MatchSuggestion {
+            transaction_id: transaction.id.clone(),
+            score,
+            reasons,
+        })
+    }
+
+    /// Rank candidate transactions for a bank line, best first
+    ///
+    /// Ties are broken by closeness in date, then transaction id, so the
+    /// order is stable.
+    pub fn suggest_matches(
+        &self,
+        line: &BankStatementLine,
+        candidates: &[Transaction],
+        bank_account_id: &str,
+    ) -> Vec<MatchSuggestion> {
+        let mut ranked: Vec<(MatchSuggestion, i64)> = candidates
+            .iter()
+            .filter_map(|transaction| {
+                self.score_match(line, transaction, bank_account_id)
+                    .map(|s| (s, (transaction.date - line.date).num_days().abs()))
+            })
+            .collect();
+        ranked.sort_by(|(a, a_days), (b, b_days)| {
+            b.score
+                .total_cmp(&a.score)
+                .then(a_days.cmp(b_days))
+                .then_with(|| a.transaction_id.cmp(&b.transaction_id))
+        });
+        ranked
+            .into_iter()
+            .map(|(suggestion, _)| suggestion)
+            .collect()
+    }
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Suggest posted transactions on `bank_account_id` that a bank line may
+    /// correspond to, best first
+    pub async fn suggest_matches(
+        &self,
+        engine: &ReconciliationEngine,
+        bank_account_id: &str,
+        line: &BankStatementLine,
+    ) -> LedgerResult<Vec<MatchSuggestion>> {
+        let window = Days::new(u64::from(engine.date_tolerance_days));
+        let start = line.date.checked_sub_days(window).unwrap_or(NaiveDate::MIN);
+        let end = line.date.checked_add_days(window).unwrap_or(NaiveDate::MAX);
+        let candidates = self
+            .get_account_transactions(bank_account_id, Some(start), Some(end))
+            .await?;
+        Ok(engine.suggest_matches(line, &candidates, bank_account_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::TransactionBuilder;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_suggestions_are_ranked_and_explained() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 4, d).unwrap();
+        for (id, date, amount, reference) in [
+            ("rent-a", day(3), 1200, Some("CHQ-000451")),
+            ("rent-b", day(4), 1200, None),
+            ("rent-c", day(9), 1200, None),
+            ("rent-d", day(4), 1100, None),
+        ] {
+            let mut builder = TransactionBuilder::new(id.to_string(), date, "Rent".to_string())
+                .debit("6000".to_string(), BigDecimal::from(amount), None)
+                .credit("1000".to_string(), BigDecimal::from(amount), None);
+            if let Some(reference) = reference {
+                builder = builder.reference(reference.to_string());
+            }
+            ledger
+                .record_transaction(builder.build().unwrap())
+                .await
+                .unwrap();
+        }
+
+        let line = BankStatementLine::new(
+            day(4),
+            "CHQ PAID 000451".to_string(),
+            BigDecimal::from(-1200),
+            Some("CHQ-000451".to_string()),
+        );
+        let engine = ReconciliationEngine::new();
+        let suggestions = ledger
+            .suggest_matches(&engine, "1000", &line)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = suggestions
+            .iter()
+            .map(|s| s.transaction_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["rent-a", "rent-b"]);
+
+        assert_eq!(suggestions[0].score, 0.9);
+        assert_eq!(
+            suggestions[0].reasons,
+            vec![
+                MatchReason::ExactAmount,
+                MatchReason::DateWithin { days: 1 },
+                MatchReason::ReferenceMatch {
+                    reference: "CHQ-000451".to_string()
+                },
+            ]
+        );
+        assert_eq!(suggestions[0].reasons[1].to_string(), "date within 1 day");
+        assert_eq!(suggestions[1].score, 0.8);
+
+        // A bank charge deducted from the payment still matches when allowed
+        let mut lenient = ReconciliationEngine::new();
+        lenient.amount_tolerance = BigDecimal::from(100);
+        let suggestions = lenient.suggest_matches(
+            &line,
+            &ledger.get_transactions(None, None).await.unwrap(),
+            "1000",
+        );
+        assert_eq!(suggestions.len(), 3);
+        assert_eq!(
+            suggestions[2].reasons[0],
+            MatchReason::AmountWithinTolerance {
+                difference: BigDecimal::from(100)
+            }
+        );
+    }
+}
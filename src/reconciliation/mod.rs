@@ -1,12 +1,36 @@
 //! Reconciliation module for bank statements and payment gateways
 //!
-//! This module will contain the reconciliation engine implementation
-//! based on the detailed specification in the ideas folder.
+//! Statement lines come in through the parsers in [`import`] or a
+//! [`BankFeedProvider`](crate::traits::BankFeedProvider); the
+//! [`ReconciliationEngine`] ranks the ledger transactions each line may
+//! correspond to, and [`rules`] categorize the lines nothing matched.
 
-// TODO: Implement reconciliation engine as per reconciliation-implementation.md
-// This is a placeholder for future implementation
+pub mod feed;
+pub mod import;
+pub mod matching;
+pub mod narration;
+pub mod rules;
 
-pub struct ReconciliationEngine;
+pub use feed::*;
+pub use import::*;
+pub use matching::*;
+pub use narration::*;
+pub use rules::*;
+
+use bigdecimal::BigDecimal;
+
+/// Settings for matching bank lines to ledger transactions
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconciliationEngine {
+    /// Transactions dated more than this many days from the line are not
+    /// considered
+    pub date_tolerance_days: u32,
+    /// Largest amount difference still treated as a near match, for example
+    /// a bank charge deducted from a receipt; zero requires exact amounts
+    pub amount_tolerance: BigDecimal,
+    /// Suggestions scoring below this are dropped
+    pub min_score: f64,
+}
 
 impl Default for ReconciliationEngine {
     fn default() -> Self {
@@ -15,7 +39,12 @@ impl Default for ReconciliationEngine {
 }
 
 impl ReconciliationEngine {
+    /// Match within two days, on exact amounts, keeping scores of 0.5 and up
     pub fn new() -> Self {
-        Self
+        Self {
+            date_tolerance_days: 2,
+            amount_tolerance: BigDecimal::from(0),
+            min_score: 0.5,
+        }
     }
 }
@@ -0,0 +1,256 @@
+//! Structured details from Indian bank narrations
+//!
+//! Banks print UPI, IMPS, NEFT and RTGS credits and debits as one narration
+//! string whose layout differs from bank to bank, for example:
+//!
+//! ```text
+//! UPI/CR/412345678901/RAMESH KUMAR/SBIN/ramesh@oksbi/Payment
+//! UPI-RAMESH KUMAR-ramesh@okicici-ICIC0000123-412345678901-lunch
+//! NEFT CR-HDFC0000123-ACME PVT LTD-HDFCN52024040112
+//! RTGS/UTIBR52024040100012345/ACME PVT LTD
+//! ```
+//!
+//! [`parse_narration`] splits the narration on the usual separators and picks
+//! out the parts by shape rather than position, so it copes with most
+//! layouts: 12-digit retrieval reference numbers, `name@handle` VPAs, IFSC
+//! codes, 16- and 22-character UTRs, and the first name-like part as the
+//! counterparty.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::BankStatementLine;
+
+/// Payment rail a bank line came through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum PaymentChannel {
+    Upi,
+    Imps,
+    Neft,
+    Rtgs,
+}
+
+impl PaymentChannel {
+    /// Name as printed in narrations
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentChannel::Upi => "UPI",
+            PaymentChannel::Imps => "IMPS",
+            PaymentChannel::Neft => "NEFT",
+            PaymentChannel::Rtgs => "RTGS",
+        }
+    }
+
+    fn from_word(word: &str) -> Option<Self> {
+        match word.to_ascii_uppercase().as_str() {
+            "UPI" => Some(PaymentChannel::Upi),
+            "IMPS" => Some(PaymentChannel::Imps),
+            "NEFT" => Some(PaymentChannel::Neft),
+            "RTGS" => Some(PaymentChannel::Rtgs),
+            _ => None,
+        }
+    }
+}
+
+/// Details found in a narration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NarrationDetails {
+    pub channel: Option<PaymentChannel>,
+    /// 12-digit retrieval reference number of a UPI or IMPS payment
+    pub reference_number: Option<String>,
+    /// Unique transaction reference of a NEFT or RTGS transfer
+    pub utr: Option<String>,
+    /// UPI virtual payment address, e.g. `ramesh@oksbi`
+    pub vpa: Option<String>,
+    /// IFSC of the other party's branch
+    pub ifsc: Option<String>,
+    /// Name of the payer on credits, or the payee on debits
+    pub counterparty: Option<String>,
+}
+
+/// Direction and sub-type markers that are not names
+const MARKERS: &[&str] = &[
+    "CR", "DR", "IN", "OUT", "P2A", "P2M", "P2P", "INB", "MOB", "BY", "TO", "TRANSFER",
+];
+
+fn is_reference_number(part: &str) -> bool {
+    part.len() == 12 && part.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_vpa(part: &str) -> bool {
+    match part.split_once('@') {
+        Some((user, handle)) => {
+            !user.is_empty()
+                && !handle.is_empty()
+                && !part.contains(char::is_whitespace)
+                && handle
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'.')
+        }
+        None => false,
+    }
+}
+
+/// Four letters, a zero, then six letters or digits
+fn is_ifsc(part: &str) -> bool {
+    let bytes = part.as_bytes();
+    bytes.len() == 11
+        && bytes[..4].iter().all(u8::is_ascii_uppercase)
+        && bytes[4] == b'0'
+        && bytes[5..].iter().all(u8::is_ascii_alphanumeric)
+}
+
+/// 16 characters for NEFT, 22 for RTGS, mostly digits
+fn is_utr(part: &str) -> bool {
+    let digits = part.bytes().filter(u8::is_ascii_digit).count();
+    matches!(part.len(), 16 | 22)
+        && part
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b.is_ascii_uppercase())
+        && digits >= 10
+}
+
+fn is_name(part: &str) -> bool {
+    part.chars().filter(|c| c.is_alphabetic()).count() >= 2
+        && part
+            .chars()
+            .all(|c| c.is_alphabetic() || c == ' ' || c == '.' || c == '&')
+        && !part.split_whitespace().all(|word| {
+            MARKERS.contains(&word.to_ascii_uppercase().as_str())
+                || PaymentChannel::from_word(word).is_some()
+        })
+}
+
+/// Extract payment details from a bank narration
+pub fn parse_narration(narration: &str) -> NarrationDetails {
+    let mut details = NarrationDetails::default();
+    let parts = narration
+        .split(['/', '-', '*', ':', '|'])
+        .map(str::trim)
+        .filter(|part| !part.is_empty());
+    for part in parts {
+        let first_word = part.split_whitespace().next().unwrap_or_default();
+        if details.channel.is_none() {
+            if let Some(channel) = PaymentChannel::from_word(first_word) {
+                details.channel = Some(channel);
+                continue;
+            }
+        }
+        if details.vpa.is_none() && is_vpa(part) {
+            details.vpa = Some(part.to_string());
+        } else if details.reference_number.is_none() && is_reference_number(part) {
+            details.reference_number = Some(part.to_string());
+        } else if details.ifsc.is_none() && is_ifsc(part) {
+            details.ifsc = Some(part.to_string());
+        } else if details.utr.is_none() && is_utr(part) {
+            details.utr = Some(part.to_string());
+        } else if details.counterparty.is_none() && is_name(part) {
+            details.counterparty = Some(part.to_string());
+        }
+    }
+    details
+}
+
+impl NarrationDetails {
+    /// Whether nothing was found
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The payment's own reference: the UTR, or else the reference number
+    pub fn payment_reference(&self) -> Option<&str> {
+        self.utr.as_deref().or(self.reference_number.as_deref())
+    }
+
+    /// Add the details to statement-line metadata, keeping existing values
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        let fields = [
+            (
+                "payment_channel",
+                self.channel.map(|c| c.as_str().to_string()),
+            ),
+            ("reference_number", self.reference_number.clone()),
+            ("utr", self.utr.clone()),
+            ("vpa", self.vpa.clone()),
+            ("ifsc", self.ifsc.clone()),
+            ("counterparty", self.counterparty.clone()),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                metadata.entry(key.to_string()).or_insert(value);
+            }
+        }
+    }
+}
+
+impl BankStatementLine {
+    /// Parse the narration into metadata, and use the payment reference as
+    /// the line's reference when the bank gave none
+    pub fn enrich_from_narration(&mut self) -> NarrationDetails {
+        let details = parse_narration(&self.description);
+        details.apply_to(&mut self.metadata);
+        if self.reference.is_none() {
+            self.reference = details.payment_reference().map(str::to_string);
+        }
+        details
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_common_narrations() {
+        let sbi = parse_narration("UPI/CR/412345678901/RAMESH KUMAR/SBIN/ramesh@oksbi/Payment");
+        assert_eq!(sbi.channel, Some(PaymentChannel::Upi));
+        assert_eq!(sbi.reference_number.as_deref(), Some("412345678901"));
+        assert_eq!(sbi.vpa.as_deref(), Some("ramesh@oksbi"));
+        assert_eq!(sbi.counterparty.as_deref(), Some("RAMESH KUMAR"));
+
+        let hdfc =
+            parse_narration("UPI-RAMESH KUMAR-ramesh.k@okicici-ICIC0000123-412345678901-lunch");
+        assert_eq!(hdfc.counterparty.as_deref(), Some("RAMESH KUMAR"));
+        assert_eq!(hdfc.vpa.as_deref(), Some("ramesh.k@okicici"));
+        assert_eq!(hdfc.ifsc.as_deref(), Some("ICIC0000123"));
+        assert_eq!(hdfc.reference_number.as_deref(), Some("412345678901"));
+
+        let neft = parse_narration("NEFT CR-HDFC0000123-ACME PVT LTD-HDFCN52024040112");
+        assert_eq!(neft.channel, Some(PaymentChannel::Neft));
+        assert_eq!(neft.ifsc.as_deref(), Some("HDFC0000123"));
+        assert_eq!(neft.counterparty.as_deref(), Some("ACME PVT LTD"));
+        assert_eq!(neft.utr.as_deref(), Some("HDFCN52024040112"));
+
+        let rtgs = parse_narration("RTGS/UTIBR52024040100012345/ACME PVT LTD");
+        assert_eq!(rtgs.channel, Some(PaymentChannel::Rtgs));
+        assert_eq!(rtgs.utr.as_deref(), Some("UTIBR52024040100012345"));
+
+        let sbi_transfer = parse_narration("BY TRANSFER-UPI/CR/412345678901/MEERA/ybl");
+        assert_eq!(sbi_transfer.channel, Some(PaymentChannel::Upi));
+        assert_eq!(sbi_transfer.counterparty.as_deref(), Some("MEERA"));
+
+        assert!(parse_narration("ATM WDL 0042").is_empty());
+    }
+
+    #[test]
+    fn test_enrich_statement_line() {
+        let mut line = BankStatementLine::new(
+            NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+            "NEFT*HDFC0000001*N123241234567890*ACME PVT LTD".to_string(),
+            BigDecimal::from(5000),
+            None,
+        );
+        line.metadata.insert(
+            "counterparty".to_string(),
+            "Acme Private Limited".to_string(),
+        );
+        line.enrich_from_narration();
+        assert_eq!(line.reference.as_deref(), Some("N123241234567890"));
+        assert_eq!(line.metadata["payment_channel"], "NEFT");
+        assert_eq!(line.metadata["counterparty"], "Acme Private Limited");
+    }
+}
@@ -0,0 +1,536 @@
+//! Rules that categorize bank lines into complete journal entries
+//!
+//! A [`CategorizationRule`] says, for example, "narration contains `SWIGGY`
+//! → post to Meals, tag `food`". [`RuleSet::propose`] turns a bank line into
+//! a [`ProposedEntry`] from the best matching rule, with a confidence score.
+//! [`Ledger::categorize_bank_lines`] posts confident proposals straight away
+//! and parks the rest in a [`ReviewQueue`] for a person to approve.
+
+use bigdecimal::{BigDecimal, Signed, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::BankStatementLine;
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::traits::*;
+use crate::types::*;
+
+/// Test applied to a bank line; text tests ignore case
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RuleCondition {
+    NarrationContains(String),
+    ReferenceContains(String),
+    /// A statement-line metadata value, such as a `vpa` found by narration
+    /// parsing, equals the given text
+    MetadataEquals {
+        key: String,
+        value: String,
+    },
+    /// The unsigned amount lies within the bounds, inclusive
+    AmountBetween {
+        min: Option<BigDecimal>,
+        max: Option<BigDecimal>,
+    },
+    Deposit,
+    Withdrawal,
+}
+
+impl RuleCondition {
+    /// Whether the line passes this test
+    pub fn matches(&self, line: &BankStatementLine) -> bool {
+        let contains =
+            |text: &str, needle: &str| text.to_lowercase().contains(&needle.to_lowercase());
+        match self {
+            RuleCondition::NarrationContains(needle) => contains(&line.description, needle),
+            RuleCondition::ReferenceContains(needle) => line
+                .reference
+                .as_deref()
+                .is_some_and(|reference| contains(reference, needle)),
+            RuleCondition::MetadataEquals { key, value } => line
+                .metadata
+                .get(key)
+                .is_some_and(|v| v.eq_ignore_ascii_case(value)),
+            RuleCondition::AmountBetween { min, max } => {
+                let amount = line.amount.abs();
+                min.as_ref().is_none_or(|min| amount >= *min)
+                    && max.as_ref().is_none_or(|max| amount <= *max)
+            }
+            RuleCondition::Deposit => line.is_deposit(),
+            RuleCondition::Withdrawal => line.amount.is_negative(),
+        }
+    }
+}
+
+/// A user-defined categorization rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CategorizationRule {
+    pub id: String,
+    pub name: String,
+    /// All must match
+    pub conditions: Vec<RuleCondition>,
+    /// Account posted against the bank
+    pub account_id: String,
+    /// Tags added to the proposed transaction
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Voucher type; receipts and payments by default
+    #[serde(default)]
+    pub kind: Option<TransactionKind>,
+    /// How sure a match makes us, from 0 to 1
+    pub confidence: f64,
+}
+
+impl CategorizationRule {
+    /// A rule with confidence 0.9 and no conditions yet
+    pub fn new(id: String, name: String, account_id: String) -> Self {
+        Self {
+            id,
+            name,
+            conditions: Vec::new(),
+            account_id,
+            tags: Vec::new(),
+            kind: None,
+            confidence: 0.9,
+        }
+    }
+
+    /// Add a condition
+    pub fn when(mut self, condition: RuleCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Add a tag to proposed transactions
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Set the confidence of a match
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Whether the rule applies to the line; rules without conditions never do
+    pub fn matches(&self, line: &BankStatementLine) -> bool {
+        !self.conditions.is_empty() && self.conditions.iter().all(|c| c.matches(line))
+    }
+}
+
+/// A journal entry proposed for a bank line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProposedEntry {
+    pub line: BankStatementLine,
+    pub rule_id: String,
+    /// Confidence from 0 to 1
+    pub confidence: f64,
+    /// Other rules that matched but post to a different account
+    pub conflicting_rules: Vec<String>,
+    /// Balanced entry between the bank and the rule's account
+    pub transaction: Transaction,
+}
+
+/// Ordered collection of categorization rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RuleSet {
+    /// Earlier rules win ties in confidence
+    pub rules: Vec<CategorizationRule>,
+    /// Proposals at or above this confidence are posted without review
+    pub auto_post_threshold: f64,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            auto_post_threshold: 0.95,
+        }
+    }
+}
+
+impl RuleSet {
+    /// An empty rule set with the default auto-post threshold of 0.95
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule after the existing ones
+    pub fn add_rule(&mut self, rule: CategorizationRule) {
+        self.rules.push(rule);
+    }
+
+    /// Propose an entry posting `line` against `bank_account_id`
+    ///
+    /// The most confident matching rule wins. When other matching rules
+    /// point at different accounts the proposal is ambiguous and its
+    /// confidence is halved, which sends it to review. The entry's id is
+    /// taken from `ids`.
+    pub fn propose(
+        &self,
+        bank_account_id: &str,
+        line: &BankStatementLine,
+        ids: &dyn IdGenerator,
+    ) -> LedgerResult<Option<ProposedEntry>> {
+        if line.amount.is_zero() {
+            return Ok(None);
+        }
+        let matched: Vec<&CategorizationRule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(line))
+            .collect();
+        let Some(best) = matched.iter().copied().reduce(|best, rule| {
+            if rule.confidence > best.confidence {
+                rule
+            } else {
+                best
+            }
+        }) else {
+            return Ok(None);
+        };
+        let conflicting_rules: Vec<String> = matched
+            .iter()
+            .filter(|rule| rule.account_id != best.account_id)
+            .map(|rule| rule.id.clone())
+            .collect();
+        let confidence = if conflicting_rules.is_empty() {
+            best.confidence
+        } else {
+            best.confidence / 2.0
+        };
+
+        let amount = line.amount.abs();
+        let (debit, credit, default_kind) = if line.is_deposit() {
+            (
+                bank_account_id,
+                best.account_id.as_str(),
+                TransactionKind::Receipt,
+            )
+        } else {
+            (
+                best.account_id.as_str(),
+                bank_account_id,
+                TransactionKind::Payment,
+            )
+        };
+        let mut builder = TransactionBuilder::new(
+            format!("rule-{}", ids.next_id()),
+            line.date,
+            line.description.clone(),
+        )
+        .kind(best.kind.unwrap_or(default_kind))
+        .debit(debit.to_string(), amount.clone(), None)
+        .credit(credit.to_string(), amount, None)
+        .metadata("source".to_string(), "bank_rule".to_string())
+        .metadata("rule_id".to_string(), best.id.clone());
+        if let Some(reference) = &line.reference {
+            builder = builder.reference(reference.clone());
+        }
+        for (key, value) in &line.metadata {
+            builder = builder.metadata(key.clone(), value.clone());
+        }
+        let mut transaction = builder.build()?;
+        for tag in &best.tags {
+            transaction.add_tag(tag)?;
+        }
+
+        Ok(Some(ProposedEntry {
+            line: line.clone(),
+            rule_id: best.id.clone(),
+            confidence,
+            conflicting_rules,
+            transaction,
+        }))
+    }
+}
+
+/// Proposals waiting for a person to approve or reject them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReviewQueue {
+    /// Keyed by proposed transaction id
+    proposals: BTreeMap<String, ProposedEntry>,
+}
+
+impl ReviewQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a proposal
+    pub fn push(&mut self, proposal: ProposedEntry) {
+        self.proposals
+            .insert(proposal.transaction.id.clone(), proposal);
+    }
+
+    /// Look up a proposal by its transaction id
+    pub fn get(&self, proposal_id: &str) -> Option<&ProposedEntry> {
+        self.proposals.get(proposal_id)
+    }
+
+    /// Pending proposals, least confident first
+    pub fn pending(&self) -> Vec<&ProposedEntry> {
+        let mut pending: Vec<&ProposedEntry> = self.proposals.values().collect();
+        pending.sort_by(|a, b| a.confidence.total_cmp(&b.confidence));
+        pending
+    }
+
+    pub fn len(&self) -> usize {
+        self.proposals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proposals.is_empty()
+    }
+
+    /// Drop a proposal, returning it so its line can go elsewhere (for
+    /// example to suspense)
+    pub fn reject(&mut self, proposal_id: &str) -> Option<ProposedEntry> {
+        self.proposals.remove(proposal_id)
+    }
+}
+
+/// Outcome of categorizing a batch of bank lines
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CategorizationOutcome {
+    /// Confident proposals that were recorded and posted
+    pub posted: Vec<Transaction>,
+    /// Confident proposals that were recorded but are held for approval
+    #[serde(default)]
+    pub pending_approval: Vec<Transaction>,
+    /// Ids of proposals added to the review queue
+    pub queued: Vec<String>,
+    /// Lines no rule matched
+    pub unmatched: Vec<BankStatementLine>,
+    /// Lines whose proposal could not be made or recorded; the others are
+    /// unaffected, so only these need another attempt
+    pub failed: Vec<CategorizationFailure>,
+}
+
+/// A bank line that could not be categorized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CategorizationFailure {
+    /// The line as given
+    pub line: BankStatementLine,
+    /// Why the proposal was refused
+    pub message: String,
+}
+
+impl<S: LedgerStorage + Clone> Ledger<S> {
+    /// Apply categorization rules to bank lines that matched nothing else
+    ///
+    /// Proposals at or above the rule set's auto-post threshold are
+    /// recorded; the rest are added to `queue`. Lines without a matching
+    /// rule are returned untouched, ready for
+    /// [`Ledger::import_bank_lines_to_suspense`]. A line that fails is
+    /// reported in [`CategorizationOutcome::failed`] and the batch carries
+    /// on, so what was recorded is always returned.
+    ///
+    /// Recorded proposals over the ledger's approval threshold land in
+    /// [`CategorizationOutcome::pending_approval`] rather than `posted`.
+    pub async fn categorize_bank_lines(
+        &mut self,
+        rules: &RuleSet,
+        queue: &mut ReviewQueue,
+        bank_account_id: &str,
+        lines: &[BankStatementLine],
+    ) -> LedgerResult<CategorizationOutcome> {
+        self.account_manager
+            .get_account_required(bank_account_id)
+            .await?;
+        let mut outcome = CategorizationOutcome::default();
+        for line in lines {
+            let fail = |error: LedgerError| CategorizationFailure {
+                line: line.clone(),
+                message: error.to_string(),
+            };
+            match rules.propose(bank_account_id, line, self.id_generator()) {
+                Ok(Some(proposal)) if proposal.confidence >= rules.auto_post_threshold => {
+                    match self.record_transaction(proposal.transaction).await {
+                        Ok(recorded) if recorded.is_posted() => outcome.posted.push(recorded),
+                        Ok(recorded) => outcome.pending_approval.push(recorded),
+                        Err(error) => outcome.failed.push(fail(error)),
+                    }
+                }
+                Ok(Some(proposal)) => {
+                    outcome.queued.push(proposal.transaction.id.clone());
+                    queue.push(proposal);
+                }
+                Ok(None) => outcome.unmatched.push(line.clone()),
+                Err(error) => outcome.failed.push(fail(error)),
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Record a queued proposal, optionally posting to a different account
+    /// than the rule chose
+    pub async fn approve_proposal(
+        &mut self,
+        queue: &mut ReviewQueue,
+        proposal_id: &str,
+        account_override: Option<&str>,
+    ) -> LedgerResult<Transaction> {
+        let proposal = queue.get(proposal_id).ok_or_else(|| {
+            LedgerError::Validation(format!("No proposal '{}' in the review queue", proposal_id))
+        })?;
+        let mut transaction = proposal.transaction.clone();
+        if let Some(account_id) = account_override {
+            let rule_account_id = category_account(&transaction, proposal)?;
+            for entry in transaction
+                .entries
+                .iter_mut()
+                .filter(|e| e.account_id == rule_account_id)
+            {
+                entry.account_id = account_id.to_string();
+            }
+            transaction
+                .metadata
+                .insert("recoded_from".to_string(), rule_account_id.into());
+        }
+        let transaction = self.record_transaction(transaction).await?;
+        queue.reject(proposal_id);
+        Ok(transaction)
+    }
+}
+
+/// The non-bank account of a proposal: the leg on the side opposite the
+/// line's direction
+fn category_account(transaction: &Transaction, proposal: &ProposedEntry) -> LedgerResult<String> {
+    let side = if proposal.line.is_deposit() {
+        EntryType::Credit
+    } else {
+        EntryType::Debit
+    };
+    transaction
+        .entries
+        .iter()
+        .find(|e| e.entry_type == side)
+        .map(|e| e.account_id.clone())
+        .ok_or_else(|| LedgerError::Validation("Proposal has no category entry".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_rules_post_confident_lines_and_queue_the_rest() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        for (id, name) in [("6810", "Meals"), ("6820", "Travel")] {
+            ledger
+                .create_account(id.to_string(), name.to_string(), AccountType::Expense, None)
+                .await
+                .unwrap();
+        }
+
+        let mut rules = RuleSet::new();
+        rules.add_rule(
+            CategorizationRule::new(
+                "swiggy".to_string(),
+                "Food delivery".to_string(),
+                "6810".to_string(),
+            )
+            .when(RuleCondition::NarrationContains("swiggy".to_string()))
+            .when(RuleCondition::Withdrawal)
+            .tag("food")
+            .confidence(0.99),
+        );
+        rules.add_rule(
+            CategorizationRule::new("uber".to_string(), "Cabs".to_string(), "6820".to_string())
+                .when(RuleCondition::NarrationContains("uber".to_string())),
+        );
+        rules.add_rule(
+            CategorizationRule::new(
+                "eats".to_string(),
+                "Uber Eats".to_string(),
+                "6810".to_string(),
+            )
+            .when(RuleCondition::NarrationContains("uber eats".to_string()))
+            .confidence(0.97),
+        );
+        rules.add_rule(
+            CategorizationRule::new(
+                "phone".to_string(),
+                "Phone bill".to_string(),
+                "6999".to_string(),
+            )
+            .when(RuleCondition::NarrationContains("airtel".to_string()))
+            .confidence(0.99),
+        );
+
+        let date = NaiveDate::from_ymd_opt(2024, 4, 3).unwrap();
+        let line = |description: &str, amount: i64| {
+            BankStatementLine::new(
+                date,
+                description.to_string(),
+                BigDecimal::from(amount),
+                None,
+            )
+        };
+        let lines = vec![
+            line("UPI/DR/412345678901/SWIGGY/swiggy@icici", -450),
+            line("AIRTEL POSTPAID", -999),
+            line("UBER EATS ORDER 88", -300),
+            line("NEFT IN ACME", 10_000),
+        ];
+
+        let mut queue = ReviewQueue::new();
+        let outcome = ledger
+            .categorize_bank_lines(&rules, &mut queue, "1000", &lines)
+            .await
+            .unwrap();
+        assert_eq!(outcome.posted.len(), 1);
+        assert!(outcome.posted[0].has_tag("food"));
+        assert!(outcome.pending_approval.is_empty());
+        assert_eq!(outcome.unmatched.len(), 1);
+        // The phone rule points at a missing account; the rest still ran
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].line, lines[1]);
+
+        // Two rules matched with different accounts, so it needs a person
+        let proposal = queue.get(&outcome.queued[0]).unwrap();
+        assert_eq!(proposal.rule_id, "eats");
+        assert_eq!(proposal.conflicting_rules, vec!["uber".to_string()]);
+        assert!(proposal.confidence < rules.auto_post_threshold);
+
+        let id = outcome.queued[0].clone();
+        let approved = ledger
+            .approve_proposal(&mut queue, &id, Some("6820"))
+            .await
+            .unwrap();
+        assert!(queue.is_empty());
+        assert!(approved
+            .entries
+            .iter()
+            .any(|e| e.account_id == "6820" && e.entry_type == EntryType::Debit));
+        assert_eq!(
+            ledger.get_account_balance("6820", None).await.unwrap(),
+            BigDecimal::from(300)
+        );
+
+        // A confident proposal over the approval threshold is recorded but
+        // not reported as posted
+        ledger.set_approval_threshold(Some(BigDecimal::from(100)));
+        let outcome = ledger
+            .categorize_bank_lines(&rules, &mut queue, "1000", &lines[..1])
+            .await
+            .unwrap();
+        assert!(outcome.posted.is_empty());
+        assert_eq!(outcome.pending_approval.len(), 1);
+        assert_eq!(
+            outcome.pending_approval[0].status,
+            TransactionStatus::PendingApproval
+        );
+    }
+}
@@ -1,11 +1,14 @@
 //! GST (Goods and Services Tax) calculation engine for Indian tax compliance
 
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Signed, Zero};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::types::{AmountPolicy, Attachment};
+
 /// GST rate structure for Indian taxation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GstRate {
     /// Total GST rate percentage (e.g., 18.0 for 18%)
     pub total_rate: BigDecimal,
@@ -51,15 +54,15 @@ impl GstRate {
         }
 
         // For intra-state transactions, CGST and SGST should be equal
-        if self.igst_rate == BigDecimal::from(0) && self.cgst_rate != self.sgst_rate {
+        if self.igst_rate.is_zero() && self.cgst_rate != self.sgst_rate {
             return Err(GstError::InvalidRate(
                 "CGST and SGST rates must be equal for intra-state transactions".to_string(),
             ));
         }
 
         // For inter-state transactions, only IGST should be non-zero
-        if self.igst_rate > BigDecimal::from(0)
-            && (self.cgst_rate > BigDecimal::from(0) || self.sgst_rate > BigDecimal::from(0))
+        if self.igst_rate.is_positive()
+            && (self.cgst_rate.is_positive() || self.sgst_rate.is_positive())
         {
             return Err(GstError::InvalidRate(
                 "Only IGST should be applicable for inter-state transactions".to_string(),
@@ -72,6 +75,7 @@ impl GstRate {
 
 /// Detailed GST calculation breakdown
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GstCalculation {
     /// Base amount (before GST)
     pub base_amount: BigDecimal,
@@ -124,10 +128,33 @@ impl GstCalculation {
 
         Self::calculate(base_amount, gst_rate)
     }
+
+    /// Round each tax component to the policy's precision, ready to post
+    ///
+    /// Totals are re-added from the rounded components so the postings
+    /// balance.
+    pub fn rounded(&self, policy: &AmountPolicy) -> Self {
+        let base_amount = policy.round(&self.base_amount);
+        let cgst_amount = policy.round(&self.cgst_amount);
+        let sgst_amount = policy.round(&self.sgst_amount);
+        let igst_amount = policy.round(&self.igst_amount);
+        let total_gst_amount = &cgst_amount + &sgst_amount + &igst_amount;
+        let total_amount = &base_amount + &total_gst_amount;
+        Self {
+            base_amount,
+            gst_rate: self.gst_rate.clone(),
+            cgst_amount,
+            sgst_amount,
+            igst_amount,
+            total_gst_amount,
+            total_amount,
+        }
+    }
 }
 
 /// Standard GST rates for different categories of goods and services
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum GstCategory {
     /// Essential items (food, medicines, etc.) - 0%
     Essential,
@@ -275,6 +302,7 @@ impl GstCalculator {
 
 /// Invoice line item with GST calculation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GstLineItem {
     /// Item description
     pub description: String,
@@ -315,6 +343,7 @@ impl GstLineItem {
 
 /// Complete GST invoice calculation
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GstInvoice {
     /// Invoice line items
     pub line_items: Vec<GstLineItem>,
@@ -330,6 +359,9 @@ pub struct GstInvoice {
     pub total_gst: BigDecimal,
     /// Grand total including GST
     pub grand_total: BigDecimal,
+    /// Supporting documents such as the signed invoice copy
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
 }
 
 impl GstInvoice {
@@ -366,9 +398,72 @@ impl GstInvoice {
             total_igst,
             total_gst,
             grand_total,
+            attachments: Vec::new(),
+        }
+    }
+
+    /// Round every line to the policy's precision and re-add the totals
+    pub fn rounded(&self, policy: &AmountPolicy) -> Self {
+        let line_items = self
+            .line_items
+            .iter()
+            .map(|item| {
+                let gst_calculation = item.gst_calculation.rounded(policy);
+                GstLineItem {
+                    line_total_before_gst: gst_calculation.base_amount.clone(),
+                    line_total_with_gst: gst_calculation.total_amount.clone(),
+                    gst_calculation,
+                    ..item.clone()
+                }
+            })
+            .collect();
+        Self {
+            attachments: self.attachments.clone(),
+            ..Self::new(line_items)
         }
     }
 
+    /// The invoice with every taxed line split as an inter-state (IGST) or
+    /// intra-state (CGST and SGST) supply
+    ///
+    /// Lines already split that way are kept as they are; the others are
+    /// recalculated at the same total rate and rounded with `policy`.
+    pub fn with_supply(&self, inter_state: bool, policy: &AmountPolicy) -> Result<Self, GstError> {
+        let line_items = self
+            .line_items
+            .iter()
+            .map(|item| {
+                let rate = &item.gst_calculation.gst_rate;
+                if rate.total_rate.is_zero() || rate.igst_rate.is_zero() != inter_state {
+                    return Ok(item.clone());
+                }
+                let rate = if inter_state {
+                    GstRate::inter_state(rate.total_rate.clone())
+                } else {
+                    GstRate::intra_state(rate.total_rate.clone())
+                };
+                let gst_calculation =
+                    GstCalculation::calculate(item.line_total_before_gst.clone(), rate)?
+                        .rounded(policy);
+                Ok(GstLineItem {
+                    line_total_before_gst: gst_calculation.base_amount.clone(),
+                    line_total_with_gst: gst_calculation.total_amount.clone(),
+                    gst_calculation,
+                    ..item.clone()
+                })
+            })
+            .collect::<Result<Vec<_>, GstError>>()?;
+        Ok(Self {
+            attachments: self.attachments.clone(),
+            ..Self::new(line_items)
+        })
+    }
+
+    /// Link a supporting document to the invoice
+    pub fn attach(&mut self, attachment: Attachment) {
+        self.attachments.push(attachment);
+    }
+
     /// Add a line item to the invoice
     pub fn add_line_item(&mut self, line_item: GstLineItem) {
         self.line_items.push(line_item);
@@ -387,6 +482,68 @@ impl GstInvoice {
     }
 }
 
+/// The business's own GST registration
+///
+/// Deserializing checks the GSTIN as [`GstRegistration::new`] does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GstRegistration {
+    /// 15-character GST identification number
+    pub gstin: String,
+    /// Name the business is registered under
+    pub legal_name: String,
+}
+
+impl<'de> Deserialize<'de> for GstRegistration {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Fields {
+            gstin: String,
+            legal_name: String,
+        }
+        let fields = Fields::deserialize(deserializer)?;
+        Self::new(fields.gstin, fields.legal_name).map_err(serde::de::Error::custom)
+    }
+}
+
+impl GstRegistration {
+    /// Create a registration, checking the GSTIN's shape
+    pub fn new(gstin: String, legal_name: String) -> Result<Self, GstError> {
+        let registration = Self {
+            gstin: gstin.trim().to_ascii_uppercase(),
+            legal_name,
+        };
+        registration.validate()?;
+        Ok(registration)
+    }
+
+    /// Check the GSTIN is 15 uppercase letters and digits starting with the
+    /// two-digit state code
+    pub fn validate(&self) -> Result<(), GstError> {
+        let gstin = &self.gstin;
+        let valid = gstin.len() == 15
+            && gstin
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+            && gstin[..2].chars().all(|c| c.is_ascii_digit());
+        if !valid {
+            return Err(GstError::InvalidGstin(gstin.clone()));
+        }
+        Ok(())
+    }
+
+    /// Two-digit code of the state the business is registered in
+    pub fn state_code(&self) -> &str {
+        self.gstin.get(..2).unwrap_or_default()
+    }
+
+    /// Whether a supply to `place_of_supply` (a state code) is inter-state,
+    /// and so charged IGST rather than CGST and SGST
+    pub fn is_inter_state(&self, place_of_supply: &str) -> bool {
+        self.state_code() != place_of_supply
+    }
+}
+
 /// GST-related errors
 #[derive(Debug, thiserror::Error)]
 pub enum GstError {
@@ -396,6 +553,8 @@ pub enum GstError {
     ProductNotFound(String),
     #[error("Calculation error: {0}")]
     Calculation(String),
+    #[error("Invalid GSTIN: {0}")]
+    InvalidGstin(String),
 }
 
 #[cfg(test)]
@@ -487,4 +646,58 @@ mod tests {
         assert_eq!(invoice.total_gst, BigDecimal::from(234)); // 18% of 1300
         assert_eq!(invoice.grand_total, BigDecimal::from(1534));
     }
+
+    #[test]
+    fn test_gst_rounded_to_policy() {
+        let gst_rate = GstRate::intra_state(BigDecimal::from(18));
+        let calculation =
+            GstCalculation::reverse_calculate(BigDecimal::from(1000), gst_rate).unwrap();
+        assert!(calculation.base_amount.fractional_digit_count() > 2);
+
+        let rounded = calculation.rounded(&AmountPolicy::default());
+        assert_eq!(rounded.base_amount, "847.46".parse::<BigDecimal>().unwrap());
+        assert_eq!(rounded.cgst_amount, "76.27".parse::<BigDecimal>().unwrap());
+        assert_eq!(
+            rounded.total_amount,
+            "1000.00".parse::<BigDecimal>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_gst_invoice_split_by_supply() {
+        let line = GstLineItem::new(
+            "Product A".to_string(),
+            BigDecimal::from(3),
+            "33.33".parse().unwrap(),
+            GstRate::intra_state(BigDecimal::from(18)),
+        )
+        .unwrap();
+        let invoice = GstInvoice::new(vec![line]);
+
+        let inter = invoice.with_supply(true, &AmountPolicy::default()).unwrap();
+        assert!(inter.total_cgst.is_zero() && inter.total_sgst.is_zero());
+        assert_eq!(inter.total_igst, "18.00".parse::<BigDecimal>().unwrap());
+        assert_eq!(inter.total_before_gst, invoice.total_before_gst);
+        assert_eq!(
+            invoice
+                .with_supply(false, &AmountPolicy::default())
+                .unwrap(),
+            invoice
+        );
+    }
+
+    #[test]
+    fn test_gst_registration_checked_on_deserialize() {
+        let registration: GstRegistration =
+            serde_json::from_str(r#"{"gstin": "29abcde1234f1z5", "legal_name": "Acme"}"#).unwrap();
+        assert_eq!(registration.gstin, "29ABCDE1234F1Z5");
+        assert!(serde_json::from_str::<GstRegistration>(
+            r#"{"gstin": "not-a-gstin", "legal_name": "Acme"}"#
+        )
+        .is_err());
+
+        let mut tampered = registration;
+        tampered.gstin = "X9ABCDE1234F1Z5".to_string();
+        assert!(tampered.validate().is_err());
+    }
 }
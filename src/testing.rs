@@ -0,0 +1,450 @@
+//! Invariant checks for storage backends
+//!
+//! Enabled with the `testing` feature. [`TransactionGenerator`] produces
+//! random but reproducible balanced transactions from a seed, and
+//! [`InvariantHarness`] drives a [`Ledger`] over any [`LedgerStorage`]
+//! through a sequence of recordings and deletions, checking after every step
+//! that the books still hold together:
+//!
+//! - the trial balance balances
+//! - every account's stored balance, current and as of any date, equals a
+//!   replay of the posted journal
+//! - an account's transactions are exactly the journal's transactions
+//!   touching it
+//!
+//! A backend passing the harness behaves like [`MemoryStorage`] as far as
+//! the ledger can tell.
+//!
+//! ```rust
+//! use accounting_core::testing::InvariantHarness;
+//! use accounting_core::utils::MemoryStorage;
+//!
+//! # async fn check() {
+//! let mut harness = InvariantHarness::new(MemoryStorage::new(), 42).await.unwrap();
+//! harness.run(200).await.unwrap();
+//! # }
+//! ```
+//!
+//! [`MemoryStorage`]: crate::utils::MemoryStorage
+
+use bigdecimal::BigDecimal;
+use chrono::{Days, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::ledger::{Ledger, TransactionBuilder};
+use crate::traits::*;
+use crate::types::*;
+
+/// Random balanced transactions over a fixed set of accounts
+///
+/// The same seed and accounts always give the same sequence, so a failing
+/// run can be replayed.
+#[derive(Debug, Clone)]
+pub struct TransactionGenerator {
+    state: u64,
+    account_ids: Vec<String>,
+    /// Dates are drawn from this day and the 364 after it
+    pub start_date: NaiveDate,
+    /// Most entries in one transaction; at least 2
+    pub max_entries: usize,
+    /// Largest transaction total, in minor units (paise)
+    pub max_total_minor: u64,
+}
+
+impl TransactionGenerator {
+    /// A generator posting to `account_ids`, which must name at least two
+    /// accounts
+    pub fn new(seed: u64, account_ids: Vec<String>) -> Self {
+        Self {
+            state: seed,
+            account_ids,
+            start_date: NaiveDate::from_ymd_opt(2024, 4, 1).unwrap_or_default(),
+            max_entries: 4,
+            max_total_minor: 10_000_000,
+        }
+    }
+
+    /// Next raw value (SplitMix64)
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `low..=high`
+    ///
+    /// Fails if `low` is above `high` or the range spans every `u64`.
+    pub fn between(&mut self, low: u64, high: u64) -> LedgerResult<u64> {
+        let span = high
+            .checked_sub(low)
+            .and_then(|width| width.checked_add(1))
+            .ok_or_else(|| {
+                LedgerError::Validation(format!("Cannot draw a value in {}..={}", low, high))
+            })?;
+        Ok(low + self.next_u64() % span)
+    }
+
+    /// A date within the generator's year
+    ///
+    /// Fails if the year runs past the last date chrono can represent.
+    pub fn date(&mut self) -> LedgerResult<NaiveDate> {
+        let offset = self.between(0, 364)?;
+        self.start_date
+            .checked_add_days(Days::new(offset))
+            .ok_or_else(|| {
+                LedgerError::Validation(format!(
+                    "{} days after {} is out of range",
+                    offset, self.start_date
+                ))
+            })
+    }
+
+    /// Split `total` into `parts` positive amounts
+    fn split(&mut self, total: u64, parts: usize) -> LedgerResult<Vec<u64>> {
+        let mut cuts: BTreeSet<u64> = BTreeSet::new();
+        while cuts.len() < parts - 1 {
+            cuts.insert(self.between(1, total - 1)?);
+        }
+        let mut amounts = Vec::with_capacity(parts);
+        let mut previous = 0;
+        for cut in cuts.into_iter().chain([total]) {
+            amounts.push(cut - previous);
+            previous = cut;
+        }
+        Ok(amounts)
+    }
+
+    /// A posted transaction whose debits equal its credits, with at least
+    /// one of each, on distinct accounts
+    ///
+    /// Fails if the generator has fewer than two accounts.
+    pub fn balanced_transaction(&mut self, id: String) -> LedgerResult<Transaction> {
+        if self.account_ids.len() < 2 {
+            return Err(LedgerError::Validation(format!(
+                "Generating a transaction needs at least two accounts, found {}",
+                self.account_ids.len()
+            )));
+        }
+        let max_entries = self.max_entries.min(self.account_ids.len()).max(2);
+        let entries = self.between(2, max_entries as u64)? as usize;
+        let debits = self.between(1, entries as u64 - 1)? as usize;
+        let total = self.between(entries as u64, self.max_total_minor.max(entries as u64))?;
+
+        let mut accounts = self.account_ids.clone();
+        let mut legs = Vec::with_capacity(entries);
+        for _ in 0..entries {
+            let index = self.between(0, accounts.len() as u64 - 1)? as usize;
+            legs.push(accounts.swap_remove(index));
+        }
+        let amount = |minor: u64| BigDecimal::new(minor.into(), 2);
+
+        let date = self.date()?;
+        let mut builder = TransactionBuilder::new(id.clone(), date, format!("Generated {}", id));
+        for (account_id, minor) in legs[..debits].iter().zip(self.split(total, debits)?) {
+            builder = builder.debit(account_id.clone(), amount(minor), None);
+        }
+        for (account_id, minor) in legs[debits..]
+            .iter()
+            .zip(self.split(total, entries - debits)?)
+        {
+            builder = builder.credit(account_id.clone(), amount(minor), None);
+        }
+        builder.build()
+    }
+}
+
+/// A step the harness applies to the ledger
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// Record this transaction
+    Record(Box<Transaction>),
+    /// Delete the transaction with this ID
+    Delete(String),
+}
+
+/// A broken invariant, with what is needed to replay the run
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("invariant broken after step {step} (seed {seed}): {description}")]
+pub struct InvariantViolation {
+    /// Seed the generator was started from
+    pub seed: u64,
+    /// Operations applied before the check failed
+    pub step: usize,
+    /// The invariant that broke, or the operation that failed
+    pub description: String,
+}
+
+/// Drives a ledger over a storage backend and checks the books after every
+/// operation
+pub struct InvariantHarness<S: LedgerStorage + Clone> {
+    ledger: Ledger<S>,
+    generator: TransactionGenerator,
+    seed: u64,
+    step: usize,
+    recorded: Vec<String>,
+}
+
+impl<S: LedgerStorage + Clone> InvariantHarness<S> {
+    /// Set up the standard chart of accounts on `storage`, which should be
+    /// empty, and seed the generator
+    pub async fn new(storage: S, seed: u64) -> LedgerResult<Self> {
+        let mut ledger = Ledger::new(storage);
+        ledger.setup_standard_chart_of_accounts().await?;
+        let mut account_ids: Vec<String> = ledger
+            .list_accounts()
+            .await?
+            .into_iter()
+            .map(|account| account.id)
+            .collect();
+        account_ids.sort();
+        Ok(Self {
+            ledger,
+            generator: TransactionGenerator::new(seed, account_ids),
+            seed,
+            step: 0,
+            recorded: Vec::new(),
+        })
+    }
+
+    /// The ledger under test
+    pub fn ledger(&self) -> &Ledger<S> {
+        &self.ledger
+    }
+
+    /// The transaction generator, e.g. to change its date range
+    pub fn generator_mut(&mut self) -> &mut TransactionGenerator {
+        &mut self.generator
+    }
+
+    /// Next operation: usually a new transaction, sometimes deleting one
+    pub fn next_operation(&mut self) -> LedgerResult<Operation> {
+        if !self.recorded.is_empty() && self.generator.between(0, 4)? == 0 {
+            let index = self.generator.between(0, self.recorded.len() as u64 - 1)? as usize;
+            return Ok(Operation::Delete(self.recorded[index].clone()));
+        }
+        let id = format!("GEN-{:06}", self.step + 1);
+        self.generator
+            .balanced_transaction(id)
+            .map(|transaction| Operation::Record(Box::new(transaction)))
+    }
+
+    /// Apply `steps` generated operations, checking after each
+    pub async fn run(&mut self, steps: usize) -> Result<(), InvariantViolation> {
+        for _ in 0..steps {
+            let operation = self.next_operation().map_err(|e| self.violation(e))?;
+            self.apply(operation).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply the given operations in order, checking after each
+    pub async fn run_operations(
+        &mut self,
+        operations: impl IntoIterator<Item = Operation>,
+    ) -> Result<(), InvariantViolation> {
+        for operation in operations {
+            self.apply(operation).await?;
+        }
+        Ok(())
+    }
+
+    async fn apply(&mut self, operation: Operation) -> Result<(), InvariantViolation> {
+        self.step += 1;
+        let result = match operation {
+            Operation::Record(transaction) => self
+                .ledger
+                .record_transaction(*transaction)
+                .await
+                .map(|recorded| self.recorded.push(recorded.id)),
+            Operation::Delete(id) => self
+                .ledger
+                .delete_transaction(&id)
+                .await
+                .map(|()| self.recorded.retain(|recorded| recorded != &id)),
+        };
+        result.map_err(|e| self.violation(format!("operation failed: {}", e)))?;
+        self.check().await
+    }
+
+    /// Check every invariant against the current state of storage
+    pub async fn check(&self) -> Result<(), InvariantViolation> {
+        check_invariants(&self.ledger.account_manager.storage)
+            .await
+            .map_err(|description| self.violation(description))
+    }
+
+    fn violation(&self, description: impl ToString) -> InvariantViolation {
+        InvariantViolation {
+            seed: self.seed,
+            step: self.step,
+            description: description.to_string(),
+        }
+    }
+}
+
+/// Check a backend's accounts and journal against each other, describing the
+/// first inconsistency found
+pub async fn check_invariants<S: LedgerStorage>(storage: &S) -> Result<(), String> {
+    let storage_error = |e: LedgerError| format!("storage error: {}", e);
+    let accounts = storage.list_accounts(None).await.map_err(storage_error)?;
+    let journal = storage
+        .get_transactions(None, None)
+        .await
+        .map_err(storage_error)?;
+
+    // Replay the posted journal into running balances per account and date
+    let types: BTreeMap<&str, &AccountType> = accounts
+        .iter()
+        .map(|account| (account.id.as_str(), &account.account_type))
+        .collect();
+    let mut movements: BTreeMap<&str, BTreeMap<NaiveDate, BigDecimal>> = BTreeMap::new();
+    let mut touching: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for transaction in &journal {
+        for entry in &transaction.entries {
+            touching
+                .entry(entry.account_id.as_str())
+                .or_default()
+                .insert(transaction.id.as_str());
+            if !transaction.is_posted() {
+                continue;
+            }
+            let account_type = types.get(entry.account_id.as_str()).ok_or_else(|| {
+                format!(
+                    "{} posts to unknown account {}",
+                    transaction.id, entry.account_id
+                )
+            })?;
+            *movements
+                .entry(entry.account_id.as_str())
+                .or_default()
+                .entry(transaction.date)
+                .or_default() += account_type.balance_effect(&entry.entry_type, &entry.amount);
+        }
+    }
+
+    let last_date = journal
+        .iter()
+        .map(|transaction| transaction.date)
+        .max()
+        .unwrap_or_default();
+    let trial_balance = storage
+        .get_trial_balance(last_date)
+        .await
+        .map_err(storage_error)?;
+    if !trial_balance.is_balanced || trial_balance.total_debits != trial_balance.total_credits {
+        return Err(format!(
+            "trial balance as of {} does not balance: debits {}, credits {}",
+            last_date, trial_balance.total_debits, trial_balance.total_credits
+        ));
+    }
+
+    let zero = BigDecimal::from(0);
+    let empty = BTreeMap::new();
+    for account in &accounts {
+        let id = account.id.as_str();
+        let by_date = movements.get(id).unwrap_or(&empty);
+        let replayed: BigDecimal = by_date.values().sum();
+        if account.balance != replayed {
+            return Err(format!(
+                "account {} holds {} but its journal replays to {}",
+                id, account.balance, replayed
+            ));
+        }
+        let current = storage
+            .get_account_balance(id, None)
+            .await
+            .map_err(storage_error)?;
+        if current != replayed {
+            return Err(format!(
+                "current balance of {} is {} but its journal replays to {}",
+                id, current, replayed
+            ));
+        }
+
+        // Every date with a movement, checked as of the day itself
+        let mut running = zero.clone();
+        for (date, movement) in by_date {
+            running += movement;
+            let as_of = storage
+                .get_account_balance(id, Some(*date))
+                .await
+                .map_err(storage_error)?;
+            if as_of != running {
+                return Err(format!(
+                    "balance of {} as of {} is {} but its journal replays to {}",
+                    id, date, as_of, running
+                ));
+            }
+        }
+
+        let listed: BTreeSet<String> = storage
+            .get_account_transactions(id, None, None)
+            .await
+            .map_err(storage_error)?
+            .into_iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        let expected: BTreeSet<String> = touching
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|id| id.to_string())
+            .collect();
+        if listed != expected {
+            return Err(format!(
+                "transactions listed for {} differ from the journal: {:?} vs {:?}",
+                id, listed, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_memory_storage_keeps_invariants() {
+        let mut harness = InvariantHarness::new(MemoryStorage::new(), 7)
+            .await
+            .unwrap();
+        harness.run(150).await.unwrap();
+        assert!(!harness.recorded.is_empty());
+
+        // Same seed, same transactions
+        let ids = vec!["1000".to_string(), "4000".to_string(), "6000".to_string()];
+        let mut first = TransactionGenerator::new(3, ids.clone());
+        let mut second = TransactionGenerator::new(3, ids);
+        let legs = |transaction: Transaction| -> Vec<(String, EntryType, BigDecimal)> {
+            assert!(transaction.is_balanced());
+            transaction
+                .entries
+                .into_iter()
+                .map(|e| (e.account_id, e.entry_type, e.amount))
+                .collect()
+        };
+        assert_eq!(
+            legs(first.balanced_transaction("T".to_string()).unwrap()),
+            legs(second.balanced_transaction("T".to_string()).unwrap())
+        );
+
+        // Bad ranges and too few accounts are errors, not panics
+        assert!(first.between(0, u64::MAX).is_err());
+        assert!(first.between(5, 4).is_err());
+        let mut lonely = TransactionGenerator::new(3, vec!["1000".to_string()]);
+        assert!(lonely.balanced_transaction("T".to_string()).is_err());
+
+        // A balance edited behind the ledger's back is caught
+        let storage = &mut harness.ledger.account_manager.storage;
+        let mut cash = storage.get_account("1000").await.unwrap().unwrap();
+        cash.balance += BigDecimal::from(1);
+        storage.update_account(&cash).await.unwrap();
+        let violation = harness.check().await.unwrap_err();
+        assert_eq!(violation.seed, 7);
+        assert!(violation.description.contains("1000"));
+    }
+}
@@ -2,10 +2,15 @@
 
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
+use crate::ledger::{PeriodArchive, TransactionTemplate};
+use crate::notifications::DueEvent;
+use crate::reconciliation::BankStatementLine;
 use crate::types::*;
 
 /// Storage abstraction for the ledger system
@@ -29,9 +34,59 @@ pub trait LedgerStorage: Send + Sync {
     /// Delete an account (if no transactions reference it)
     async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()>;
 
+    /// Save several new accounts, parents before children, as one unit of
+    /// work
+    ///
+    /// The default saves them one at a time and deletes the ones already
+    /// saved if a save fails. Backends with transactions should override it
+    /// to commit the batch atomically.
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        for (saved, account) in accounts.iter().enumerate() {
+            if let Err(err) = self.save_account(account).await {
+                for account in accounts[..saved].iter().rev() {
+                    let _ = self.delete_account(&account.id).await;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
     /// Save a transaction to storage
     async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()>;
 
+    /// Delete several transactions and save one in their place, as one unit
+    /// of work
+    ///
+    /// The default deletes them one at a time, then saves the replacement,
+    /// and saves back the ones already deleted if a step fails. Backends
+    /// with transactions should override it to commit the change
+    /// atomically.
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        let mut deleted = 0;
+        let mut result = Ok(());
+        for transaction in removed {
+            if let Err(err) = self.delete_transaction(&transaction.id).await {
+                result = Err(err);
+                break;
+            }
+            deleted += 1;
+        }
+        if result.is_ok() {
+            result = self.save_transaction(replacement).await;
+        }
+        if result.is_err() {
+            for transaction in removed[..deleted].iter().rev() {
+                let _ = self.save_transaction(transaction).await;
+            }
+        }
+        result
+    }
+
     /// Get a transaction by ID
     async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>>;
 
@@ -71,6 +126,86 @@ pub trait LedgerStorage: Send + Sync {
         &self,
         as_of_date: NaiveDate,
     ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>>;
+
+    /// Pre-aggregated reporting tables, if the backend maintains them
+    fn reporting(&self) -> Option<&dyn ReportingStorage> {
+        None
+    }
+
+    /// List individual entries matching a filter, ordered by date and
+    /// transaction
+    async fn get_entries(&self, filter: &EntryFilter) -> LedgerResult<Vec<EntryRow>> {
+        let mut transactions = self
+            .get_transactions(filter.start_date, filter.end_date)
+            .await?;
+        transactions.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.id.cmp(&b.id)));
+        Ok(transactions
+            .iter()
+            .flat_map(|t| {
+                t.entries
+                    .iter()
+                    .filter(|e| filter.matches(t, e))
+                    .map(|e| EntryRow::new(t, e))
+            })
+            .collect())
+    }
+}
+
+/// Extension for backends that keep pre-aggregated daily per-account totals
+/// of posted entries, updated as transactions are posted, edited and voided
+///
+/// The ledger uses these totals instead of scanning transactions when
+/// [`LedgerStorage::reporting`] returns the backend.
+#[async_trait]
+pub trait ReportingStorage: Send + Sync {
+    /// Daily totals of an account within a date range, in date order; days
+    /// without postings are omitted
+    async fn get_daily_totals(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: NaiveDate,
+    ) -> LedgerResult<Vec<DailyAccountTotal>>;
+
+    /// Totals per account within a date range
+    async fn get_account_totals(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: NaiveDate,
+    ) -> LedgerResult<HashMap<String, AccountTotals>>;
+}
+
+/// Storage that can partition its data per tenant
+///
+/// A scoped handle only ever reads and writes the given tenant's data, so a
+/// ledger built on it cannot see or modify another tenant's books.
+pub trait TenantStorage: LedgerStorage + Sized {
+    /// Get a storage handle confined to a tenant
+    fn for_tenant(&self, tenant: &TenantId) -> LedgerResult<Self>;
+
+    /// Tenant this handle is scoped to, if any
+    fn tenant(&self) -> Option<&TenantId>;
+}
+
+/// Storage for the content of attachments referenced by transactions and
+/// invoices
+#[async_trait]
+pub trait AttachmentStorage: Send + Sync {
+    /// Store an attachment's content under its id
+    async fn save_attachment(
+        &mut self,
+        attachment: &Attachment,
+        content: &[u8],
+    ) -> LedgerResult<()>;
+
+    /// Get an attachment reference by id
+    async fn get_attachment(&self, attachment_id: &str) -> LedgerResult<Option<Attachment>>;
+
+    /// Get the stored content of an attachment
+    async fn get_attachment_content(&self, attachment_id: &str) -> LedgerResult<Option<Vec<u8>>>;
+
+    /// Delete an attachment and its content
+    async fn delete_attachment(&mut self, attachment_id: &str) -> LedgerResult<()>;
 }
 
 /// Trait for implementing custom account validation rules
@@ -78,8 +213,12 @@ pub trait AccountValidator: Send + Sync {
     /// Validate an account before saving
     fn validate_account(&self, account: &Account) -> LedgerResult<()>;
 
-    /// Validate account deletion (e.g., check for existing transactions)
-    fn validate_account_deletion(&self, account_id: &str) -> LedgerResult<()>;
+    /// Validate account deletion given what still refers to the account
+    fn validate_account_deletion(
+        &self,
+        account: &Account,
+        usage: &AccountUsage,
+    ) -> LedgerResult<()>;
 }
 
 /// Trait for implementing custom transaction validation rules
@@ -111,9 +250,20 @@ impl AccountValidator for DefaultAccountValidator {
         Ok(())
     }
 
-    fn validate_account_deletion(&self, _account_id: &str) -> LedgerResult<()> {
-        // Basic implementation - in a real system you'd check for existing transactions
-        Ok(())
+    /// Refuse to delete an account with entries, a balance or children
+    fn validate_account_deletion(
+        &self,
+        account: &Account,
+        usage: &AccountUsage,
+    ) -> LedgerResult<()> {
+        let blockers = usage.blockers();
+        if blockers.is_empty() {
+            return Ok(());
+        }
+        Err(LedgerError::AccountInUse {
+            account_id: account.id.clone(),
+            blockers,
+        })
     }
 }
 
@@ -131,6 +281,220 @@ impl TransactionValidator for DefaultTransactionValidator {
     }
 }
 
+/// Source of the current time for created/updated timestamps
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    /// Current UTC time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Current UTC date
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
+
+/// Clock reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Clock that only moves when told to, for tests and deterministic replay;
+/// clones share the same time
+#[derive(Debug, Clone)]
+pub struct FixedClock {
+    now: Arc<RwLock<DateTime<Utc>>>,
+}
+
+impl FixedClock {
+    /// Create a clock stopped at `now`
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Arc::new(RwLock::new(now)),
+        }
+    }
+
+    /// Move the clock to `now`
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.write().unwrap() = now;
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: TimeDelta) {
+        *self.now.write().unwrap() += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read().unwrap()
+    }
+}
+
+/// Source of ids for transactions created without one
+///
+/// Implementations must be safe to call from concurrent writers; see
+/// [`crate::utils::id_generator`] for the bundled strategies.
+pub trait IdGenerator: Send + Sync + std::fmt::Debug {
+    /// A new id, never returned before by this generator
+    fn next_id(&self) -> String;
+}
+
+/// Receives [`LedgerEvent`]s after the ledger has stored a change
+pub trait LedgerObserver: Send + Sync {
+    /// Called once per change, after it has been stored
+    fn on_event(&self, event: &LedgerEvent<'_>);
+}
+
+/// Asynchronous delay used between storage retries
+///
+/// Implement it with your runtime's timer, e.g. `tokio::time::sleep`.
+#[async_trait]
+pub trait Sleeper: Send + Sync {
+    /// Wait for `duration` without blocking the executor
+    async fn sleep(&self, duration: Duration);
+}
+
+/// Source of bank statement lines, such as an Account Aggregator FIU
+/// integration or a bank data API
+///
+/// Used by [`Ledger::sync_bank_feed`](crate::ledger::Ledger::sync_bank_feed)
+/// to pull new lines straight into reconciliation.
+#[async_trait]
+pub trait BankFeedProvider: Send + Sync {
+    /// Lines of the provider's `account` booked on or after `since`, in
+    /// statement order
+    ///
+    /// Returning lines that were fetched before is fine; the ledger skips
+    /// lines it has already imported.
+    async fn fetch_statement_lines(
+        &self,
+        account: &str,
+        since: NaiveDate,
+    ) -> LedgerResult<Vec<BankStatementLine>>;
+}
+
+/// Encryption of individual field values, used by
+/// [`EncryptedStorage`](crate::utils::EncryptedStorage) to keep sensitive
+/// text unreadable at rest
+///
+/// `field` names the field being protected, such as `description` or
+/// `metadata.counterparty`; implementations may bind it to the ciphertext so
+/// a value cannot be moved to another field unnoticed.
+pub trait FieldCipher: Send + Sync {
+    /// Encrypt a plaintext value
+    fn encrypt(&self, field: &str, plaintext: &str) -> LedgerResult<String>;
+
+    /// Decrypt a value produced by [`encrypt`](FieldCipher::encrypt)
+    fn decrypt(&self, field: &str, ciphertext: &str) -> LedgerResult<String>;
+}
+
+/// Digital signature over finalized documents such as issued invoices and
+/// closing entries, set with
+/// [`Ledger::set_signer`](crate::ledger::Ledger::set_signer)
+///
+/// `digest` is the document's canonical digest, `sha256:` followed by the
+/// hex SHA-256 of its canonical JSON form. The signature format is up to the
+/// implementation; the ledger only stores it and hands it back to
+/// [`verify`](Signer::verify).
+pub trait Signer: Send + Sync {
+    /// Identifier of the signing key, stored with each signature
+    fn key_id(&self) -> String;
+
+    /// Sign a document digest
+    fn sign(&self, digest: &str) -> LedgerResult<String>;
+
+    /// Whether `signature` is a valid signature of `digest` by `key_id`
+    fn verify(&self, key_id: &str, digest: &str, signature: &str) -> LedgerResult<bool>;
+}
+
+/// Delivery of [`DueEvent`]s raised by
+/// [`DueEventScheduler`](crate::notifications::DueEventScheduler), such as by
+/// email, SMS or a chat webhook
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    /// Deliver one event
+    async fn notify(&self, event: &DueEvent) -> LedgerResult<()>;
+}
+
+/// Destination for transactions removed by
+/// [`Ledger::archive_period`](crate::ledger::Ledger::archive_period), such as
+/// a file or another storage backend
+///
+/// The archive is written before anything leaves the ledger, so an error
+/// here leaves the books untouched.
+#[async_trait]
+pub trait ArchiveSink: Send {
+    /// Persist an archive
+    async fn write_archive(&mut self, archive: &PeriodArchive) -> LedgerResult<()>;
+}
+
+/// Persistence for transaction templates, set with
+/// [`Ledger::set_template_store`](crate::ledger::Ledger::set_template_store)
+///
+/// Saving and deleting a template write through to the store, so templates
+/// survive the ledger being rebuilt.
+#[async_trait]
+pub trait TemplateStore: Send + Sync {
+    /// Every stored template
+    async fn load_templates(&self) -> LedgerResult<Vec<TransactionTemplate>>;
+
+    /// Insert a template, replacing any with the same ID
+    async fn save_template(&mut self, template: &TransactionTemplate) -> LedgerResult<()>;
+
+    /// Remove a template; an unknown ID is not an error
+    async fn delete_template(&mut self, template_id: &str) -> LedgerResult<()>;
+}
+
+/// Policy consulted by the ledger before every state-changing operation
+///
+/// Return [`LedgerError::Unauthorized`] (see [`LedgerError::unauthorized`])
+/// to refuse an operation.
+pub trait AuthorizationPolicy: Send + Sync {
+    /// Decide whether `actor` may perform `operation`
+    fn authorize(&self, actor: Option<&Actor>, operation: &LedgerOperation<'_>)
+        -> LedgerResult<()>;
+}
+
+/// Policy requiring a role per operation name; operations without a
+/// requirement are allowed
+#[derive(Debug, Clone, Default)]
+pub struct RoleBasedPolicy {
+    required_roles: HashMap<String, String>,
+}
+
+impl RoleBasedPolicy {
+    /// Create a policy with no requirements
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require `role` for the operation named `operation` (see
+    /// [`LedgerOperation::name`])
+    pub fn require(mut self, operation: &str, role: &str) -> Self {
+        self.required_roles
+            .insert(operation.to_string(), role.to_string());
+        self
+    }
+}
+
+impl AuthorizationPolicy for RoleBasedPolicy {
+    fn authorize(
+        &self,
+        actor: Option<&Actor>,
+        operation: &LedgerOperation<'_>,
+    ) -> LedgerResult<()> {
+        match self.required_roles.get(operation.name()) {
+            None => Ok(()),
+            Some(role) if actor.is_some_and(|a| a.has_role(role)) => Ok(()),
+            Some(_) => Err(LedgerError::unauthorized(actor, operation)),
+        }
+    }
+}
+
 /// Trait for implementing custom chart of accounts structures
 #[async_trait]
 pub trait ChartOfAccounts: Send + Sync {
@@ -145,6 +509,88 @@ pub trait ChartOfAccounts: Send + Sync {
 
     /// Get the full path to an account (for hierarchical display)
     async fn get_account_path(&self, account_id: &str) -> LedgerResult<Vec<Account>>;
+
+    /// Move an account under a new parent, or to the top level with `None`
+    ///
+    /// The new parent must have the same account type (unless mixed types
+    /// are allowed) and must not be the account itself or one of its
+    /// descendants.
+    async fn move_account(
+        &mut self,
+        account_id: &str,
+        new_parent_id: Option<&str>,
+    ) -> LedgerResult<Account>;
+
+    /// Get the chart as a tree of top-level accounts with nested children
+    async fn get_tree(&self) -> LedgerResult<Vec<AccountNode>> {
+        Ok(AccountNode::build_tree(self.get_chart().await?))
+    }
+}
+
+/// An account with its children and the balance rolled up from them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AccountNode {
+    pub account: Account,
+    pub children: Vec<AccountNode>,
+    /// The account's own balance plus the rolled-up balances of its children
+    pub rolled_up_balance: BigDecimal,
+}
+
+impl AccountNode {
+    /// Build the account tree from a flat list of accounts
+    ///
+    /// Accounts whose parent is missing become top-level nodes; accounts
+    /// caught in a parent cycle cannot be reached and are left out. Siblings
+    /// are ordered by account ID.
+    pub fn build_tree(accounts: Vec<Account>) -> Vec<AccountNode> {
+        let ids: std::collections::HashSet<String> =
+            accounts.iter().map(|a| a.id.clone()).collect();
+        let mut children_of: HashMap<Option<String>, Vec<Account>> = HashMap::new();
+        for account in accounts {
+            let parent = account
+                .parent_id
+                .clone()
+                .filter(|parent_id| ids.contains(parent_id));
+            children_of.entry(parent).or_default().push(account);
+        }
+
+        fn build(
+            parent: Option<String>,
+            children_of: &mut HashMap<Option<String>, Vec<Account>>,
+        ) -> Vec<AccountNode> {
+            let mut accounts = children_of.remove(&parent).unwrap_or_default();
+            accounts.sort_by(|a, b| a.id.cmp(&b.id));
+            accounts
+                .into_iter()
+                .map(|account| {
+                    let children = build(Some(account.id.clone()), children_of);
+                    let rolled_up_balance = children
+                        .iter()
+                        .fold(account.balance.clone(), |total, child| {
+                            total + &child.rolled_up_balance
+                        });
+                    AccountNode {
+                        account,
+                        children,
+                        rolled_up_balance,
+                    }
+                })
+                .collect()
+        }
+
+        build(None, &mut children_of)
+    }
+
+    /// Find a node by account ID anywhere below this node (inclusive)
+    pub fn find(&self, account_id: &str) -> Option<&AccountNode> {
+        if self.account.id == account_id {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.find(account_id))
+    }
 }
 
 /// Trait for report generation
@@ -170,11 +616,16 @@ pub trait ReportGenerator: Send + Sync {
 
 /// Balance Sheet structure
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BalanceSheet {
     pub as_of_date: NaiveDate,
     pub assets: Vec<AccountBalance>,
     pub liabilities: Vec<AccountBalance>,
     pub equity: Vec<AccountBalance>,
+    /// Net income not yet closed, when shown as its own line rather than
+    /// rolled into the retained earnings account; included in `total_equity`
+    #[serde(default)]
+    pub current_earnings: Option<BigDecimal>,
     pub total_assets: BigDecimal,
     pub total_liabilities: BigDecimal,
     pub total_equity: BigDecimal,
@@ -183,6 +634,7 @@ pub struct BalanceSheet {
 
 /// Income Statement structure
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct IncomeStatement {
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
@@ -195,6 +647,7 @@ pub struct IncomeStatement {
 
 /// Cash Flow Statement structure
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CashFlowStatement {
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
@@ -209,7 +662,21 @@ pub struct CashFlowStatement {
 
 /// Cash Flow Item
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CashFlowItem {
     pub description: String,
     pub amount: BigDecimal,
 }
+
+/// Daybook listing all transactions of one voucher type in a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Daybook {
+    pub kind: TransactionKind,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Transactions ordered by date, then ID
+    pub transactions: Vec<Transaction>,
+    /// Sum of debits across all listed transactions
+    pub total_amount: BigDecimal,
+}
@@ -1,12 +1,194 @@
 //! Core types and data structures for the accounting system
 
-use bigdecimal::BigDecimal;
-use chrono::{NaiveDate, NaiveDateTime};
+use bigdecimal::{BigDecimal, RoundingMode, Signed, Zero};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::traits::{Clock, SystemClock};
+
+/// Structured metadata attached to accounts and transactions
+pub type Metadata = HashMap<String, MetaValue>;
+
+/// A metadata value
+///
+/// Serialized untagged, so plain JSON strings (the format used before typed
+/// metadata) deserialize as [`MetaValue::Text`]. Amounts and dates are stored
+/// as text in their canonical form and read back with [`MetaValue::as_decimal`]
+/// and [`MetaValue::as_date`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum MetaValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<MetaValue>),
+    Map(BTreeMap<String, MetaValue>),
+}
+
+impl MetaValue {
+    /// The value as a string slice, if it is text
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetaValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The value as a boolean, accepting `"true"`/`"false"` text
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            MetaValue::Bool(value) => Some(*value),
+            MetaValue::Text(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, accepting numeric text
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            MetaValue::Integer(value) => Some(*value),
+            MetaValue::Text(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value as an exact decimal amount
+    pub fn as_decimal(&self) -> Option<BigDecimal> {
+        match self {
+            MetaValue::Integer(value) => Some(BigDecimal::from(*value)),
+            MetaValue::Float(value) => value.to_string().parse().ok(),
+            MetaValue::Text(text) => text.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The value as a date (ISO 8601 text)
+    pub fn as_date(&self) -> Option<NaiveDate> {
+        self.as_str()?.parse().ok()
+    }
+
+    /// The value as a list
+    pub fn as_list(&self) -> Option<&[MetaValue]> {
+        match self {
+            MetaValue::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// The value as a nested map
+    pub fn as_map(&self) -> Option<&BTreeMap<String, MetaValue>> {
+        match self {
+            MetaValue::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MetaValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetaValue::Null => f.write_str("null"),
+            MetaValue::Bool(value) => write!(f, "{}", value),
+            MetaValue::Integer(value) => write!(f, "{}", value),
+            MetaValue::Float(value) => write!(f, "{}", value),
+            MetaValue::Text(text) => f.write_str(text),
+            MetaValue::List(values) => {
+                f.write_str("[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            MetaValue::Map(map) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in map.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl From<String> for MetaValue {
+    fn from(value: String) -> Self {
+        MetaValue::Text(value)
+    }
+}
+
+impl From<&str> for MetaValue {
+    fn from(value: &str) -> Self {
+        MetaValue::Text(value.to_string())
+    }
+}
+
+impl From<bool> for MetaValue {
+    fn from(value: bool) -> Self {
+        MetaValue::Bool(value)
+    }
+}
+
+impl From<i64> for MetaValue {
+    fn from(value: i64) -> Self {
+        MetaValue::Integer(value)
+    }
+}
+
+impl From<f64> for MetaValue {
+    fn from(value: f64) -> Self {
+        MetaValue::Float(value)
+    }
+}
+
+impl From<BigDecimal> for MetaValue {
+    fn from(value: BigDecimal) -> Self {
+        MetaValue::Text(value.to_string())
+    }
+}
+
+impl From<NaiveDate> for MetaValue {
+    fn from(value: NaiveDate) -> Self {
+        MetaValue::Text(value.to_string())
+    }
+}
+
+impl From<Vec<MetaValue>> for MetaValue {
+    fn from(values: Vec<MetaValue>) -> Self {
+        MetaValue::List(values)
+    }
+}
+
+impl From<BTreeMap<String, MetaValue>> for MetaValue {
+    fn from(map: BTreeMap<String, MetaValue>) -> Self {
+        MetaValue::Map(map)
+    }
+}
+
+impl PartialEq<str> for MetaValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for MetaValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
 
 /// Account types following standard accounting principles
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AccountType {
     /// Assets - what the business owns (Cash, Inventory, Equipment, etc.)
     Asset,
@@ -21,6 +203,15 @@ pub enum AccountType {
 }
 
 impl AccountType {
+    /// Every account type, in financial statement order
+    pub const ALL: [AccountType; 5] = [
+        AccountType::Asset,
+        AccountType::Liability,
+        AccountType::Equity,
+        AccountType::Income,
+        AccountType::Expense,
+    ];
+
     /// Returns the normal balance type for this account type
     /// Assets and Expenses normally have debit balances
     /// Liabilities, Equity, and Income normally have credit balances
@@ -30,10 +221,23 @@ impl AccountType {
             AccountType::Liability | AccountType::Equity | AccountType::Income => EntryType::Credit,
         }
     }
+
+    /// Signed effect of an entry on an account of this type
+    ///
+    /// Entries on the normal balance side increase the balance, entries on the
+    /// opposite side decrease it.
+    pub fn balance_effect(&self, entry_type: &EntryType, amount: &BigDecimal) -> BigDecimal {
+        if self.normal_balance() == *entry_type {
+            amount.clone()
+        } else {
+            -amount.clone()
+        }
+    }
 }
 
 /// Types of entries in double-entry bookkeeping
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum EntryType {
     /// Debit entry - increases Assets and Expenses, decreases Liabilities, Equity, and Income
     Debit,
@@ -43,6 +247,7 @@ pub enum EntryType {
 
 /// Core account structure
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Account {
     /// Unique identifier for the account
     pub id: String,
@@ -52,56 +257,96 @@ pub struct Account {
     pub account_type: AccountType,
     /// Optional parent account for hierarchical chart of accounts
     pub parent_id: Option<String>,
+    /// Other names the account can be looked up by, such as the label an
+    /// import file uses for it
+    #[serde(default)]
+    pub aliases: Vec<String>,
     /// Current balance of the account
     pub balance: BigDecimal,
+    /// Whether postings may drive the balance below zero (e.g. cash, inventory)
+    #[serde(default)]
+    pub non_negative: bool,
     /// Additional metadata
-    pub metadata: HashMap<String, String>,
+    pub metadata: Metadata,
+    /// When the account was archived; archived accounts accept no new postings
+    #[serde(default, with = "utc_timestamp::option")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<DateTime<Utc>>"))]
+    pub archived_at: Option<DateTime<Utc>>,
     /// When the account was created
-    pub created_at: NaiveDateTime,
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub created_at: DateTime<Utc>,
     /// When the account was last updated
-    pub updated_at: NaiveDateTime,
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub updated_at: DateTime<Utc>,
 }
 
 impl Account {
-    /// Create a new account
+    /// Create a new account, stamped by the system clock until a ledger
+    /// stores it with its own
     pub fn new(
         id: String,
         name: String,
         account_type: AccountType,
         parent_id: Option<String>,
     ) -> Self {
-        let now = chrono::Utc::now().naive_utc();
+        let now = SystemClock.now();
         Self {
             id,
             name,
             account_type,
             parent_id,
+            aliases: Vec::new(),
             balance: BigDecimal::from(0),
+            non_negative: false,
             metadata: HashMap::new(),
+            archived_at: None,
             created_at: now,
             updated_at: now,
         }
     }
 
+    /// Check whether the account is open for postings
+    pub fn is_active(&self) -> bool {
+        self.archived_at.is_none()
+    }
+
     /// Update the account balance based on an entry
     pub fn apply_entry(&mut self, entry_type: EntryType, amount: &BigDecimal) {
-        match (self.account_type.normal_balance(), entry_type) {
-            // Normal balance side increases
-            (EntryType::Debit, EntryType::Debit) | (EntryType::Credit, EntryType::Credit) => {
-                self.balance += amount;
-            }
-            // Opposite side decreases
-            (EntryType::Debit, EntryType::Credit) | (EntryType::Credit, EntryType::Debit) => {
-                self.balance -= amount;
-            }
-        }
-        self.updated_at = chrono::Utc::now().naive_utc();
+        self.balance += self.account_type.balance_effect(&entry_type, amount);
+    }
+}
+
+/// Identifier of a tenant in a multi-tenant deployment
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TenantId(pub String);
+
+impl TenantId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for TenantId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
     }
 }
 
 /// Individual entry within a transaction
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Entry {
+    /// Unique identifier for the line; entries saved before line ids existed
+    /// get one when their transaction is next saved
+    #[serde(default)]
+    pub id: String,
     /// Account being affected
     pub account_id: String,
     /// Type of entry (Debit or Credit)
@@ -110,6 +355,9 @@ pub struct Entry {
     pub amount: BigDecimal,
     /// Optional description for this specific entry
     pub description: Option<String>,
+    /// Analytical dimensions such as party or tax head
+    #[serde(default)]
+    pub dimensions: HashMap<String, String>,
 }
 
 impl Entry {
@@ -121,13 +369,21 @@ impl Entry {
         description: Option<String>,
     ) -> Self {
         Self {
+            id: new_entry_id(),
             account_id,
             entry_type,
             amount,
             description,
+            dimensions: HashMap::new(),
         }
     }
 
+    /// Tag the entry with a dimension value
+    pub fn with_dimension(mut self, key: String, value: String) -> Self {
+        self.dimensions.insert(key, value);
+        self
+    }
+
     /// Create a debit entry
     pub fn debit(account_id: String, amount: BigDecimal, description: Option<String>) -> Self {
         Self::new(account_id, EntryType::Debit, amount, description)
@@ -139,8 +395,45 @@ impl Entry {
     }
 }
 
+/// Voucher type of a transaction, as accountants classify journals
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TransactionKind {
+    /// Sales voucher - goods or services sold
+    Sales,
+    /// Purchase voucher - goods, services or assets bought
+    Purchase,
+    /// Receipt voucher - money received
+    Receipt,
+    /// Payment voucher - money paid out
+    Payment,
+    /// Contra voucher - transfers between cash and bank accounts
+    Contra,
+    /// General journal voucher
+    #[default]
+    Journal,
+    /// Adjusting entry, typically at period end
+    Adjustment,
+}
+
+/// Lifecycle status of a transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TransactionStatus {
+    /// Saved for review; does not affect balances
+    Draft,
+    /// Posted to the ledger and reflected in balances
+    #[default]
+    Posted,
+    /// Awaiting a checker's approval; does not affect balances
+    PendingApproval,
+    /// Refused by a checker; kept for the audit trail only
+    Rejected,
+}
+
 /// Complete transaction with multiple entries
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Transaction {
     /// Unique identifier for the transaction
     pub id: String,
@@ -152,30 +445,65 @@ pub struct Transaction {
     pub description: String,
     /// Optional reference number (invoice number, check number, etc.)
     pub reference: Option<String>,
+    /// Voucher type of the transaction
+    #[serde(default)]
+    pub kind: TransactionKind,
+    /// Lifecycle status; only posted transactions affect balances
+    #[serde(default)]
+    pub status: TransactionStatus,
     /// Additional metadata
-    pub metadata: HashMap<String, String>,
+    pub metadata: Metadata,
+    /// Supporting documents such as receipts and bills
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Free-form classification tags such as `travel` or `client-acme`
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Timestamped notes, oldest first
+    #[serde(default)]
+    pub notes: Vec<TransactionNote>,
+    /// Transaction this one corrects or reverses
+    #[serde(default)]
+    pub corrects: Option<String>,
+    /// Replacement entries in other books (e.g. a tax book with its own
+    /// depreciation), keyed by book name; every other book uses `entries`
+    #[serde(default)]
+    pub book_entries: BTreeMap<String, Vec<Entry>>,
     /// When the transaction was created
-    pub created_at: NaiveDateTime,
-    /// When the transaction was last updated
-    pub updated_at: NaiveDateTime,
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub created_at: DateTime<Utc>,
+    /// When the transaction was last updated; notes, attachments and tags
+    /// are annotations and do not count
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub updated_at: DateTime<Utc>,
 }
 
 impl Transaction {
-    /// Create a new transaction
+    /// Create a new transaction, stamped by the system clock until a ledger
+    /// records it with its own
     pub fn new(
         id: String,
         date: NaiveDate,
         description: String,
         reference: Option<String>,
     ) -> Self {
-        let now = chrono::Utc::now().naive_utc();
+        let now = SystemClock.now();
         Self {
             id,
             date,
             entries: Vec::new(),
             description,
             reference,
+            kind: TransactionKind::Journal,
+            status: TransactionStatus::Posted,
             metadata: HashMap::new(),
+            attachments: Vec::new(),
+            tags: BTreeSet::new(),
+            notes: Vec::new(),
+            corrects: None,
+            book_entries: BTreeMap::new(),
             created_at: now,
             updated_at: now,
         }
@@ -184,7 +512,65 @@ impl Transaction {
     /// Add an entry to the transaction
     pub fn add_entry(&mut self, entry: Entry) {
         self.entries.push(entry);
-        self.updated_at = chrono::Utc::now().naive_utc();
+    }
+
+    /// Give every entry without an id, or with an id already used by an
+    /// earlier line, a fresh one
+    pub fn assign_entry_ids(&mut self) {
+        let mut seen = std::collections::HashSet::new();
+        for entry in &mut self.entries {
+            if entry.id.is_empty() || !seen.insert(entry.id.clone()) {
+                entry.id = new_entry_id();
+                seen.insert(entry.id.clone());
+            }
+        }
+    }
+
+    /// Add a tag; returns whether it was newly added
+    pub fn add_tag(&mut self, tag: &str) -> LedgerResult<bool> {
+        Ok(self.tags.insert(normalize_tag(tag)?))
+    }
+
+    /// Remove a tag; returns whether it was present
+    pub fn remove_tag(&mut self, tag: &str) -> LedgerResult<bool> {
+        Ok(self.tags.remove(&normalize_tag(tag)?))
+    }
+
+    /// Whether the transaction carries a tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        normalize_tag(tag).is_ok_and(|tag| self.tags.contains(&tag))
+    }
+
+    /// Post different entries in the named book
+    pub fn set_book_entries(&mut self, book: &str, entries: Vec<Entry>) {
+        self.book_entries.insert(book.to_string(), entries);
+    }
+
+    /// Every entry in every book: the transaction's own, then each book's
+    /// replacements
+    pub fn all_entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .chain(self.book_entries.values().flatten())
+    }
+
+    /// Entries as posted in a book: its replacement entries if it has any,
+    /// otherwise the transaction's own
+    pub fn entries_for_book(&self, book: &str) -> &[Entry] {
+        self.book_entries
+            .get(book)
+            .map(Vec::as_slice)
+            .unwrap_or(&self.entries)
+    }
+
+    /// Find an entry by its id
+    pub fn find_entry(&self, entry_id: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.id == entry_id)
+    }
+
+    /// Whether the transaction is posted and therefore affects balances
+    pub fn is_posted(&self) -> bool {
+        self.status == TransactionStatus::Posted
     }
 
     /// Calculate total debits
@@ -210,35 +596,41 @@ impl Transaction {
         self.total_debits() == self.total_credits()
     }
 
-    /// Validate the transaction
+    /// Validate the transaction, including its entries in every other book
     pub fn validate(&self) -> Result<(), LedgerError> {
-        if self.entries.is_empty() {
-            return Err(LedgerError::InvalidTransaction(
-                "Transaction must have at least one entry".to_string(),
-            ));
+        Self::validate_entries(&self.entries)?;
+        for entries in self.book_entries.values() {
+            Self::validate_entries(entries)?;
         }
+        Ok(())
+    }
 
-        if self.entries.len() < 2 {
-            return Err(LedgerError::InvalidTransaction(
-                "Transaction must have at least two entries for double-entry bookkeeping"
-                    .to_string(),
-            ));
+    fn validate_entries(entries: &[Entry]) -> Result<(), LedgerError> {
+        if entries.len() < 2 {
+            return Err(LedgerError::InsufficientEntries {
+                count: entries.len(),
+            });
         }
 
-        if !self.is_balanced() {
-            return Err(LedgerError::InvalidTransaction(format!(
-                "Transaction is not balanced: debits = {}, credits = {}",
-                self.total_debits(),
-                self.total_credits()
-            )));
+        let total = |entry_type: EntryType| -> BigDecimal {
+            entries
+                .iter()
+                .filter(|e| e.entry_type == entry_type)
+                .map(|e| &e.amount)
+                .sum()
+        };
+        let (debits, credits) = (total(EntryType::Debit), total(EntryType::Credit));
+        if debits != credits {
+            return Err(LedgerError::Unbalanced { debits, credits });
         }
 
         // Check for zero or negative amounts
-        for entry in &self.entries {
-            if entry.amount <= BigDecimal::from(0) {
-                return Err(LedgerError::InvalidTransaction(
-                    "Entry amounts must be positive".to_string(),
-                ));
+        for entry in entries {
+            if !entry.amount.is_positive() {
+                return Err(LedgerError::NonPositiveAmount {
+                    account_id: entry.account_id.clone(),
+                    amount: entry.amount.clone(),
+                });
             }
         }
 
@@ -246,12 +638,392 @@ impl Transaction {
     }
 }
 
+/// Reference to a supporting document; the content itself lives in an
+/// [`AttachmentStorage`](crate::traits::AttachmentStorage)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Attachment {
+    pub id: String,
+    pub filename: String,
+    /// MIME type, e.g. `application/pdf`
+    pub mime: String,
+    /// Location of the content in the attachment store
+    pub storage_uri: String,
+    /// Checksum of the content as recorded by the uploader
+    pub checksum: String,
+}
+
+impl Attachment {
+    /// Create a new attachment reference
+    pub fn new(
+        id: String,
+        filename: String,
+        mime: String,
+        storage_uri: String,
+        checksum: String,
+    ) -> Self {
+        Self {
+            id,
+            filename,
+            mime,
+            storage_uri,
+            checksum,
+        }
+    }
+}
+
+/// A timestamped note on a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TransactionNote {
+    pub text: String,
+    /// Id of the actor who wrote the note, if known
+    pub author: Option<String>,
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub created_at: DateTime<Utc>,
+}
+
+/// Normalize a tag: surrounding whitespace and a leading `#` are dropped and
+/// the rest is lowercased, so `#Travel` and `travel` are the same tag
+pub fn normalize_tag(tag: &str) -> LedgerResult<String> {
+    let tag = tag.trim();
+    let tag = tag.strip_prefix('#').unwrap_or(tag).to_lowercase();
+    if tag.is_empty() || tag.chars().any(char::is_whitespace) {
+        return Err(LedgerError::Validation(format!(
+            "Invalid tag '{}': tags must be a single non-empty word",
+            tag
+        )));
+    }
+    Ok(tag)
+}
+
+/// Generate a new entry id
+pub fn new_entry_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Criteria for querying individual entries across transactions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntryFilter {
+    pub account_id: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub entry_type: Option<EntryType>,
+    /// Only entries tagged with this dimension key and value
+    pub dimension: Option<(String, String)>,
+    /// Only entries of transactions carrying this tag
+    pub tag: Option<String>,
+    /// Include entries of draft, pending and rejected transactions
+    pub include_unposted: bool,
+}
+
+impl EntryFilter {
+    /// Whether an entry of a transaction matches the filter
+    pub fn matches(&self, transaction: &Transaction, entry: &Entry) -> bool {
+        (self.include_unposted || transaction.is_posted())
+            && self.tag.as_ref().is_none_or(|tag| transaction.has_tag(tag))
+            && self.start_date.is_none_or(|d| transaction.date >= d)
+            && self.end_date.is_none_or(|d| transaction.date <= d)
+            && self
+                .account_id
+                .as_ref()
+                .is_none_or(|id| &entry.account_id == id)
+            && self
+                .entry_type
+                .as_ref()
+                .is_none_or(|t| &entry.entry_type == t)
+            && self
+                .dimension
+                .as_ref()
+                .is_none_or(|(key, value)| entry.dimensions.get(key) == Some(value))
+    }
+}
+
+/// An entry together with the transaction it belongs to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EntryRow {
+    pub transaction_id: String,
+    pub date: NaiveDate,
+    pub transaction_description: String,
+    pub reference: Option<String>,
+    pub kind: TransactionKind,
+    pub status: TransactionStatus,
+    pub entry: Entry,
+}
+
+impl EntryRow {
+    /// Build a row from a transaction and one of its entries
+    pub fn new(transaction: &Transaction, entry: &Entry) -> Self {
+        Self {
+            transaction_id: transaction.id.clone(),
+            date: transaction.date,
+            transaction_description: transaction.description.clone(),
+            reference: transaction.reference.clone(),
+            kind: transaction.kind,
+            status: transaction.status,
+            entry: entry.clone(),
+        }
+    }
+}
+
+/// How amounts are rounded to the allowed number of decimal places
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum AmountRounding {
+    /// Half away from zero: 0.125 → 0.13
+    #[default]
+    HalfUp,
+    /// Half to the even digit (banker's rounding): 0.125 → 0.12
+    HalfEven,
+    /// Toward zero
+    Down,
+    /// Away from zero
+    Up,
+}
+
+impl AmountRounding {
+    fn mode(self) -> RoundingMode {
+        match self {
+            AmountRounding::HalfUp => RoundingMode::HalfUp,
+            AmountRounding::HalfEven => RoundingMode::HalfEven,
+            AmountRounding::Down => RoundingMode::Down,
+            AmountRounding::Up => RoundingMode::Up,
+        }
+    }
+}
+
+/// Decimal precision allowed for amounts and how calculated amounts are
+/// rounded to it
+///
+/// Division leaves long decimal tails (`1000 / 3`, GST on odd amounts,
+/// interest for a number of days); the policy rounds them before they reach
+/// the ledger, and a ledger with a policy rejects entries that still carry
+/// more decimal places than allowed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AmountPolicy {
+    /// Maximum number of decimal places
+    pub max_scale: i64,
+    /// How calculated amounts are rounded to `max_scale`
+    pub rounding: AmountRounding,
+}
+
+impl Default for AmountPolicy {
+    /// Two decimal places, rounded half up
+    fn default() -> Self {
+        Self::new(2, AmountRounding::HalfUp)
+    }
+}
+
+impl AmountPolicy {
+    /// Create a policy allowing `max_scale` decimal places
+    pub fn new(max_scale: i64, rounding: AmountRounding) -> Self {
+        Self {
+            max_scale,
+            rounding,
+        }
+    }
+
+    /// Round an amount to the allowed precision
+    pub fn round(&self, amount: &BigDecimal) -> BigDecimal {
+        amount.with_scale_round(self.max_scale, self.rounding.mode())
+    }
+
+    /// Whether an amount fits the allowed precision; trailing zeros don't count
+    pub fn allows(&self, amount: &BigDecimal) -> bool {
+        amount.normalized().fractional_digit_count() <= self.max_scale
+    }
+
+    /// Reject an entry amount carrying more decimal places than allowed
+    pub fn check_entry(&self, entry: &Entry) -> LedgerResult<()> {
+        if self.allows(&entry.amount) {
+            return Ok(());
+        }
+        Err(LedgerError::ExcessPrecision {
+            account_id: entry.account_id.clone(),
+            amount: entry.amount.clone(),
+            max_scale: self.max_scale,
+        })
+    }
+
+    /// Round every entry of a transaction, in every book
+    ///
+    /// Entries are rounded one by one, so a transaction balanced before may
+    /// be off by the rounding difference afterwards.
+    pub fn round_transaction(&self, transaction: &mut Transaction) {
+        let book_entries = transaction.book_entries.values_mut().flatten();
+        for entry in transaction.entries.iter_mut().chain(book_entries) {
+            entry.amount = self.round(&entry.amount);
+        }
+    }
+
+    /// Split an amount in proportion to `weights`, at the allowed precision
+    ///
+    /// Percentages work as weights (`60, 30, 10`). Each share is first cut
+    /// down to the allowed precision; the units left over go one at a time
+    /// to the shares that lost the most, earlier shares winning ties, so the
+    /// shares always add up to the amount and the same input always splits
+    /// the same way. The amount itself is rounded with the policy first.
+    pub fn allocate(
+        &self,
+        amount: &BigDecimal,
+        weights: &[BigDecimal],
+    ) -> LedgerResult<Vec<BigDecimal>> {
+        if weights.iter().any(Signed::is_negative) {
+            return Err(LedgerError::Validation(
+                "Allocation weights cannot be negative".to_string(),
+            ));
+        }
+        let total_weight: BigDecimal = weights.iter().sum();
+        if total_weight.is_zero() {
+            return Err(LedgerError::Validation(
+                "Allocation needs at least one positive weight".to_string(),
+            ));
+        }
+
+        let amount = self.round(amount);
+        let total = amount.abs();
+        let unit = BigDecimal::new(1.into(), self.max_scale);
+        let mut shares = Vec::with_capacity(weights.len());
+        let mut remainders = Vec::with_capacity(weights.len());
+        let mut left = total.clone();
+        for weight in weights {
+            let exact = &total * weight / &total_weight;
+            let share = exact.with_scale_round(self.max_scale, RoundingMode::Down);
+            left -= &share;
+            remainders.push(exact - &share);
+            shares.push(share);
+        }
+
+        let mut order: Vec<usize> = (0..weights.len()).collect();
+        // Stable sort keeps earlier shares first among equal remainders
+        order.sort_by(|&a, &b| remainders[b].cmp(&remainders[a]));
+        for index in order {
+            if left < unit {
+                break;
+            }
+            shares[index] += &unit;
+            left -= &unit;
+        }
+
+        if amount.is_negative() {
+            shares = shares.into_iter().map(|share| -share).collect();
+        }
+        Ok(shares)
+    }
+}
+
+/// Posting of small rounding differences to a round-off account
+///
+/// Imported invoices often miss balancing by a paisa because each line was
+/// rounded on its own. Rather than rejecting them, a difference of at most
+/// `tolerance_per_line` for each entry is posted to the round-off account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RoundOffPolicy {
+    pub account_id: String,
+    pub tolerance_per_line: BigDecimal,
+}
+
+impl RoundOffPolicy {
+    pub fn new(account_id: String, tolerance_per_line: BigDecimal) -> Self {
+        Self {
+            account_id,
+            tolerance_per_line,
+        }
+    }
+
+    /// Balance the transaction, in every book, with a round-off entry when
+    /// the difference is within tolerance
+    ///
+    /// Returns the difference posted in the principal book (positive when
+    /// debits exceeded credits). Differences beyond tolerance are left for
+    /// validation to reject.
+    pub fn balance(&self, transaction: &mut Transaction) -> Option<BigDecimal> {
+        for entries in transaction.book_entries.values_mut() {
+            self.balance_entries(entries);
+        }
+        self.balance_entries(&mut transaction.entries)
+    }
+
+    fn balance_entries(&self, entries: &mut Vec<Entry>) -> Option<BigDecimal> {
+        let mut difference = BigDecimal::zero();
+        for entry in entries.iter() {
+            match entry.entry_type {
+                EntryType::Debit => difference += &entry.amount,
+                EntryType::Credit => difference -= &entry.amount,
+            }
+        }
+        let tolerance = &self.tolerance_per_line * BigDecimal::from(entries.len() as u64);
+        if difference.is_zero() || difference.abs() > tolerance {
+            return None;
+        }
+
+        let description = Some("Rounding difference".to_string());
+        let account_id = self.account_id.clone();
+        entries.push(if difference.is_positive() {
+            Entry::credit(account_id, difference.clone(), description)
+        } else {
+            Entry::debit(account_id, difference.abs(), description)
+        });
+        Some(difference)
+    }
+}
+
+/// Debit and credit totals of posted entries
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AccountTotals {
+    pub debits: BigDecimal,
+    pub credits: BigDecimal,
+}
+
+impl AccountTotals {
+    /// Add one entry
+    pub fn add(&mut self, entry_type: &EntryType, amount: &BigDecimal) {
+        match entry_type {
+            EntryType::Debit => self.debits += amount,
+            EntryType::Credit => self.credits += amount,
+        }
+    }
+
+    /// Remove one entry
+    pub fn subtract(&mut self, entry_type: &EntryType, amount: &BigDecimal) {
+        match entry_type {
+            EntryType::Debit => self.debits -= amount,
+            EntryType::Credit => self.credits -= amount,
+        }
+    }
+
+    /// Balance in the normal direction of an account type
+    pub fn balance(&self, account_type: &AccountType) -> BigDecimal {
+        account_type.balance_effect(&EntryType::Debit, &self.debits)
+            + account_type.balance_effect(&EntryType::Credit, &self.credits)
+    }
+}
+
+/// Posted totals of one account on one day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DailyAccountTotal {
+    pub account_id: String,
+    pub date: NaiveDate,
+    pub totals: AccountTotals,
+}
+
 /// Trial Balance - snapshot of all account balances at a point in time
+///
+/// Serialized in the grouped form of [`TrialBalance::sections`], so exported
+/// trial balances always list accounts in the same order.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "OrderedTrialBalance", from = "OrderedTrialBalance")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct TrialBalance {
     /// Date of the trial balance
     pub as_of_date: NaiveDate,
-    /// Account balances grouped by type
+    /// Account balances indexed by account ID
     pub balances: HashMap<String, AccountBalance>,
     /// Total debits across all accounts
     pub total_debits: BigDecimal,
@@ -261,8 +1033,132 @@ pub struct TrialBalance {
     pub is_balanced: bool,
 }
 
+impl TrialBalance {
+    /// Build a trial balance from posted totals per account
+    pub fn from_totals(
+        accounts: Vec<Account>,
+        totals: &HashMap<String, AccountTotals>,
+        as_of_date: NaiveDate,
+    ) -> Self {
+        let mut balances = HashMap::new();
+        let mut total_debits = BigDecimal::from(0);
+        let mut total_credits = BigDecimal::from(0);
+        for account in accounts {
+            let balance = totals
+                .get(&account.id)
+                .map(|t| t.balance(&account.account_type))
+                .unwrap_or_default();
+            let account_balance = AccountBalance::from_balance(account, balance);
+            if let Some(debit) = &account_balance.debit_balance {
+                total_debits += debit;
+            }
+            if let Some(credit) = &account_balance.credit_balance {
+                total_credits += credit;
+            }
+            balances.insert(account_balance.account.id.clone(), account_balance);
+        }
+        let is_balanced = total_debits == total_credits;
+        Self {
+            as_of_date,
+            balances,
+            total_debits,
+            total_credits,
+            is_balanced,
+        }
+    }
+
+    /// Balances grouped by account type in statement order, each group
+    /// sorted by account code, with subtotals
+    ///
+    /// Account types with no accounts are left out.
+    pub fn sections(&self) -> Vec<TrialBalanceSection> {
+        AccountType::ALL
+            .into_iter()
+            .filter_map(|account_type| {
+                let mut lines: Vec<AccountBalance> = self
+                    .balances
+                    .values()
+                    .filter(|line| line.account.account_type == account_type)
+                    .cloned()
+                    .collect();
+                if lines.is_empty() {
+                    return None;
+                }
+                lines.sort_by(|a, b| a.account.id.cmp(&b.account.id));
+                let total_debits = lines.iter().filter_map(|l| l.debit_balance.as_ref()).sum();
+                let total_credits = lines.iter().filter_map(|l| l.credit_balance.as_ref()).sum();
+                Some(TrialBalanceSection {
+                    account_type,
+                    lines,
+                    total_debits,
+                    total_credits,
+                })
+            })
+            .collect()
+    }
+
+    /// Every balance line in [`TrialBalance::sections`] order
+    pub fn ordered_lines(&self) -> Vec<AccountBalance> {
+        self.sections().into_iter().flat_map(|s| s.lines).collect()
+    }
+}
+
+/// Trial balance lines of one account type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TrialBalanceSection {
+    pub account_type: AccountType,
+    /// Lines sorted by account code
+    pub lines: Vec<AccountBalance>,
+    /// Subtotal of the debit column
+    pub total_debits: BigDecimal,
+    /// Subtotal of the credit column
+    pub total_credits: BigDecimal,
+}
+
+/// Serialized form of [`TrialBalance`]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+struct OrderedTrialBalance {
+    as_of_date: NaiveDate,
+    sections: Vec<TrialBalanceSection>,
+    total_debits: BigDecimal,
+    total_credits: BigDecimal,
+    is_balanced: bool,
+}
+
+impl From<TrialBalance> for OrderedTrialBalance {
+    fn from(trial_balance: TrialBalance) -> Self {
+        Self {
+            sections: trial_balance.sections(),
+            as_of_date: trial_balance.as_of_date,
+            total_debits: trial_balance.total_debits,
+            total_credits: trial_balance.total_credits,
+            is_balanced: trial_balance.is_balanced,
+        }
+    }
+}
+
+impl From<OrderedTrialBalance> for TrialBalance {
+    fn from(ordered: OrderedTrialBalance) -> Self {
+        Self {
+            as_of_date: ordered.as_of_date,
+            balances: ordered
+                .sections
+                .into_iter()
+                .flat_map(|s| s.lines)
+                .map(|line| (line.account.id.clone(), line))
+                .collect(),
+            total_debits: ordered.total_debits,
+            total_credits: ordered.total_credits,
+            is_balanced: ordered.is_balanced,
+        }
+    }
+}
+
 /// Account balance information for trial balance
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct AccountBalance {
     /// Account information
     pub account: Account,
@@ -273,6 +1169,34 @@ pub struct AccountBalance {
 }
 
 impl AccountBalance {
+    /// Build a balance line from a signed balance in the account's normal direction
+    ///
+    /// Negative balances are shown on the opposite side.
+    pub fn from_balance(account: Account, balance: BigDecimal) -> Self {
+        let on_normal_side = !balance.is_negative();
+        let amount = balance.abs();
+        let (debit_balance, credit_balance) =
+            match (account.account_type.normal_balance(), on_normal_side) {
+                (EntryType::Debit, true) | (EntryType::Credit, false) => (Some(amount), None),
+                (EntryType::Credit, true) | (EntryType::Debit, false) => (None, Some(amount)),
+            };
+        Self {
+            account,
+            debit_balance,
+            credit_balance,
+        }
+    }
+
+    /// Signed balance in the account's normal direction
+    pub fn signed_balance(&self) -> BigDecimal {
+        let debit = self.debit_balance.clone().unwrap_or_default();
+        let credit = self.credit_balance.clone().unwrap_or_default();
+        match self.account.account_type.normal_balance() {
+            EntryType::Debit => debit - credit,
+            EntryType::Credit => credit - debit,
+        }
+    }
+
     /// Get the balance amount regardless of debit/credit
     pub fn balance_amount(&self) -> BigDecimal {
         self.debit_balance
@@ -282,19 +1206,616 @@ impl AccountBalance {
     }
 }
 
+/// Which transactions a report includes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ReportScope {
+    /// Only posted transactions
+    #[default]
+    Posted,
+    /// Posted transactions plus drafts, for previewing the effect of pending entries
+    PostedAndDraft,
+}
+
+/// Serde support for UTC timestamps
+///
+/// Timestamps are written in RFC 3339 form with a `Z` suffix. Data stored
+/// before timestamps carried a timezone holds naive values such as
+/// `2024-01-05T10:30:00`; those are read as UTC.
+pub mod utc_timestamp {
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Utc(DateTime<Utc>),
+        Naive(NaiveDateTime),
+    }
+
+    impl From<Stored> for DateTime<Utc> {
+        fn from(stored: Stored) -> Self {
+            match stored {
+                Stored::Utc(timestamp) => timestamp,
+                Stored::Naive(naive) => naive.and_utc(),
+            }
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        timestamp: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        timestamp.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        Stored::deserialize(deserializer).map(Into::into)
+    }
+
+    /// The same for optional timestamps
+    pub mod option {
+        use super::Stored;
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            timestamp: &Option<DateTime<Utc>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            timestamp.serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime<Utc>>, D::Error> {
+            Option::<Stored>::deserialize(deserializer).map(|stored| stored.map(Into::into))
+        }
+    }
+}
+
+/// Timezone in which the business reads dates and times
+///
+/// Timestamps are stored in UTC; use this to show them, or to find the
+/// business day they fall on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BusinessTimezone {
+    offset: FixedOffset,
+}
+
+impl Default for BusinessTimezone {
+    fn default() -> Self {
+        Self::utc()
+    }
+}
+
+impl BusinessTimezone {
+    /// A timezone at a fixed offset from UTC
+    pub fn new(offset: FixedOffset) -> Self {
+        Self { offset }
+    }
+
+    /// Coordinated Universal Time
+    pub fn utc() -> Self {
+        Self::new(FixedOffset::east_opt(0).expect("zero offset is valid"))
+    }
+
+    /// Indian Standard Time (UTC+05:30)
+    pub fn india() -> Self {
+        Self::new(FixedOffset::east_opt(5 * 3600 + 30 * 60).expect("IST offset is valid"))
+    }
+
+    /// Offset from UTC
+    pub fn offset(&self) -> FixedOffset {
+        self.offset
+    }
+
+    /// A UTC timestamp as local business time
+    pub fn to_local(&self, timestamp: &DateTime<Utc>) -> DateTime<FixedOffset> {
+        timestamp.with_timezone(&self.offset)
+    }
+
+    /// Business day on which a UTC timestamp falls
+    pub fn local_date(&self, timestamp: &DateTime<Utc>) -> NaiveDate {
+        self.to_local(timestamp).date_naive()
+    }
+
+    /// Render a UTC timestamp in local business time with a `strftime` format
+    pub fn format(&self, timestamp: &DateTime<Utc>, format: &str) -> String {
+        self.to_local(timestamp).format(format).to_string()
+    }
+
+    /// A local business time as a UTC timestamp
+    pub fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        (local - self.offset).and_utc()
+    }
+}
+
+/// Lock that closes all dates up to and including `locked_through` for posting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeriodLock {
+    /// Last date of the locked period
+    pub locked_through: NaiveDate,
+    /// When the lock was applied
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub locked_at: DateTime<Utc>,
+}
+
+impl PeriodLock {
+    /// Create a new lock through the given date, applied at `locked_at`
+    pub fn new(locked_through: NaiveDate, locked_at: DateTime<Utc>) -> Self {
+        Self {
+            locked_through,
+            locked_at,
+        }
+    }
+
+    /// Check whether a date falls inside the locked period
+    pub fn covers(&self, date: NaiveDate) -> bool {
+        date <= self.locked_through
+    }
+}
+
+/// The user or service performing a ledger operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Actor {
+    pub id: String,
+    pub roles: Vec<String>,
+}
+
+impl Actor {
+    /// Create an actor with the given roles
+    pub fn new(id: String, roles: Vec<String>) -> Self {
+        Self { id, roles }
+    }
+
+    /// Check whether the actor holds a role
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+}
+
+/// A ledger operation submitted for authorization
+#[derive(Debug, Clone, Copy)]
+pub enum LedgerOperation<'a> {
+    CreateAccount {
+        account_id: &'a str,
+    },
+    EditAccount {
+        account_id: &'a str,
+    },
+    DeleteAccount {
+        account_id: &'a str,
+    },
+    /// Creating the standard chart of accounts in bulk
+    SetupChartOfAccounts,
+    SaveDraft {
+        transaction: &'a Transaction,
+    },
+    PostTransaction {
+        transaction: &'a Transaction,
+    },
+    EditTransaction {
+        transaction: &'a Transaction,
+    },
+    /// Deleting a posted transaction or discarding a draft
+    VoidTransaction {
+        transaction_id: &'a str,
+    },
+    ApproveTransaction {
+        transaction: &'a Transaction,
+    },
+    RejectTransaction {
+        transaction: &'a Transaction,
+    },
+    LockPeriod {
+        through: NaiveDate,
+    },
+    UnlockPeriod,
+    /// Loading a backup snapshot into an empty ledger
+    RestoreSnapshot,
+    /// Posting a transaction past a budget limit
+    OverrideBudget {
+        transaction: &'a Transaction,
+    },
+    /// Moving transactions dated before `before` out to an archive
+    ArchivePeriod {
+        before: NaiveDate,
+    },
+}
+
+/// Change to the books announced to [`LedgerObserver`](crate::traits::LedgerObserver)s
+/// after it has been stored
+#[derive(Debug, Clone, Copy)]
+pub enum LedgerEvent<'a> {
+    /// A transaction was posted, directly, from a draft or on approval
+    TransactionPosted(&'a Transaction),
+    /// A transaction was edited
+    TransactionUpdated {
+        old: &'a Transaction,
+        new: &'a Transaction,
+    },
+    /// A posted transaction was deleted
+    TransactionVoided(&'a Transaction),
+    /// A draft was saved
+    DraftSaved(&'a Transaction),
+    /// A draft was discarded
+    DraftDiscarded(&'a Transaction),
+    /// An account was created, edited, moved, archived or deleted
+    AccountChanged { account_id: &'a str },
+    /// Transactions dated before `before` were archived and replaced with
+    /// opening balances
+    PeriodArchived { before: NaiveDate },
+    /// The accounts, transactions and settings were loaded from a snapshot
+    SnapshotImported,
+}
+
+impl LedgerOperation<'_> {
+    /// Stable snake_case name of the operation, for policies and error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerOperation::CreateAccount { .. } => "create_account",
+            LedgerOperation::EditAccount { .. } => "edit_account",
+            LedgerOperation::DeleteAccount { .. } => "delete_account",
+            LedgerOperation::SetupChartOfAccounts => "setup_chart_of_accounts",
+            LedgerOperation::SaveDraft { .. } => "save_draft",
+            LedgerOperation::PostTransaction { .. } => "post_transaction",
+            LedgerOperation::EditTransaction { .. } => "edit_transaction",
+            LedgerOperation::VoidTransaction { .. } => "void_transaction",
+            LedgerOperation::ApproveTransaction { .. } => "approve_transaction",
+            LedgerOperation::RejectTransaction { .. } => "reject_transaction",
+            LedgerOperation::LockPeriod { .. } => "lock_period",
+            LedgerOperation::UnlockPeriod => "unlock_period",
+            LedgerOperation::RestoreSnapshot => "restore_snapshot",
+            LedgerOperation::OverrideBudget { .. } => "override_budget",
+            LedgerOperation::ArchivePeriod { .. } => "archive_period",
+        }
+    }
+}
+
+/// Reason an account cannot be deleted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DeletionBlocker {
+    /// Transactions of any status post to the account
+    HasEntries {
+        transaction_count: usize,
+    },
+    NonZeroBalance {
+        balance: BigDecimal,
+    },
+    HasChildren {
+        child_ids: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for DeletionBlocker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeletionBlocker::HasEntries { transaction_count } => {
+                write!(f, "{} transactions post to it", transaction_count)
+            }
+            DeletionBlocker::NonZeroBalance { balance } => write!(f, "balance is {}", balance),
+            DeletionBlocker::HasChildren { child_ids } => {
+                write!(f, "child accounts {}", child_ids.join(", "))
+            }
+        }
+    }
+}
+
+/// What refers to an account, gathered from storage before it is deleted
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AccountUsage {
+    /// Transactions of any status with an entry on the account, in any book
+    pub transaction_count: usize,
+    pub balance: BigDecimal,
+    /// Accounts whose parent is the account
+    pub child_ids: Vec<String>,
+}
+
+impl AccountUsage {
+    /// Everything that stands in the way of deleting the account
+    pub fn blockers(&self) -> Vec<DeletionBlocker> {
+        let mut blockers = Vec::new();
+        if self.transaction_count > 0 {
+            blockers.push(DeletionBlocker::HasEntries {
+                transaction_count: self.transaction_count,
+            });
+        }
+        if !self.balance.is_zero() {
+            blockers.push(DeletionBlocker::NonZeroBalance {
+                balance: self.balance.clone(),
+            });
+        }
+        if !self.child_ids.is_empty() {
+            blockers.push(DeletionBlocker::HasChildren {
+                child_ids: self.child_ids.clone(),
+            });
+        }
+        blockers
+    }
+}
+
+/// How [`Ledger::find_account`](crate::ledger::Ledger::find_account) matches
+/// accounts
+///
+/// Matching ignores case. An exact match wins; otherwise the text may be the
+/// start of a single account's code, name or alias.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum AccountLookup {
+    /// The account ID, such as `1000`
+    Code(String),
+    Name(String),
+    Alias(String),
+    /// Code, then name or alias
+    Any(String),
+}
+
+impl AccountLookup {
+    /// The text being looked up
+    pub fn text(&self) -> &str {
+        match self {
+            AccountLookup::Code(text)
+            | AccountLookup::Name(text)
+            | AccountLookup::Alias(text)
+            | AccountLookup::Any(text) => text,
+        }
+    }
+
+    /// The strings of an account this lookup compares against
+    pub fn keys<'a>(&self, account: &'a Account) -> Vec<&'a str> {
+        let aliases = account.aliases.iter().map(String::as_str);
+        match self {
+            AccountLookup::Code(_) => vec![account.id.as_str()],
+            AccountLookup::Name(_) => vec![account.name.as_str()],
+            AccountLookup::Alias(_) => aliases.collect(),
+            AccountLookup::Any(_) => [account.id.as_str(), account.name.as_str()]
+                .into_iter()
+                .chain(aliases)
+                .collect(),
+        }
+    }
+}
+
+/// An account to create in a batch with
+/// [`AccountManager::create_accounts`](crate::ledger::AccountManager::create_accounts)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NewAccount {
+    pub id: String,
+    pub name: String,
+    pub account_type: AccountType,
+    /// Parent account, either already stored or earlier or later in the batch
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub non_negative: bool,
+    #[serde(default)]
+    pub metadata: Metadata,
+}
+
+impl NewAccount {
+    /// An account with no flags or metadata
+    pub fn new(
+        id: String,
+        name: String,
+        account_type: AccountType,
+        parent_id: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            account_type,
+            parent_id,
+            non_negative: false,
+            metadata: Metadata::new(),
+        }
+    }
+
+    /// The account as stored, created at `now`
+    pub fn into_account(self, now: DateTime<Utc>) -> Account {
+        let mut account = Account::new(self.id, self.name, self.account_type, self.parent_id);
+        account.non_negative = self.non_negative;
+        account.metadata = self.metadata;
+        account.created_at = now;
+        account.updated_at = now;
+        account
+    }
+}
+
+/// Limits on the shape of the chart of accounts; `None` leaves a dimension
+/// unbounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HierarchyLimits {
+    /// Most levels from a top-level account down, counting both ends
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Most direct children of one account
+    #[serde(default)]
+    pub max_children: Option<usize>,
+}
+
+fn describe_blockers(blockers: &[DeletionBlocker]) -> String {
+    let described: Vec<String> = blockers.iter().map(ToString::to_string).collect();
+    described.join("; ")
+}
+
 /// Errors that can occur in the ledger system
+///
+/// Every variant carries a stable machine-readable [`code`](LedgerError::code)
+/// and a retryable/permanent classification, so callers never need to parse
+/// the display string.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum LedgerError {
-    #[error("Storage error: {0}")]
-    Storage(String),
+    #[error("Storage error: {message}")]
+    Storage {
+        message: String,
+        /// Whether retrying the same operation may succeed (timeouts, lost connections)
+        retryable: bool,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
     #[error("Invalid transaction: {0}")]
     InvalidTransaction(String),
+    #[error("Invalid transaction: at least two entries are required, found {count}")]
+    InsufficientEntries { count: usize },
+    #[error("Invalid transaction: not balanced: debits = {debits}, credits = {credits}")]
+    Unbalanced {
+        debits: BigDecimal,
+        credits: BigDecimal,
+    },
+    #[error(
+        "Invalid transaction: entry amount for account {account_id} must be positive, got {amount}"
+    )]
+    NonPositiveAmount {
+        account_id: String,
+        amount: BigDecimal,
+    },
     #[error("Account not found: {0}")]
     AccountNotFound(String),
+    #[error("Account already exists: {0}")]
+    DuplicateAccount(String),
+    #[error("Parent account not found: {0}")]
+    ParentAccountNotFound(String),
     #[error("Transaction not found: {0}")]
     TransactionNotFound(String),
     #[error("Validation error: {0}")]
     Validation(String),
+    #[error("Period is locked through {0}")]
+    PeriodLocked(NaiveDate),
+    #[error("Insufficient balance in account {account_id}: short by {shortfall}")]
+    InsufficientBalance {
+        account_id: String,
+        shortfall: BigDecimal,
+    },
+    #[error("Account hierarchy would be circular at: {0}")]
+    CircularHierarchy(String),
+    #[error("Account is archived: {0}")]
+    AccountArchived(String),
+    #[error("Entity not found: {0}")]
+    EntityNotFound(String),
+    #[error("{actor} is not authorized to {operation}")]
+    Unauthorized { actor: String, operation: String },
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("Posted transaction cannot be changed: {0}")]
+    ImmutableTransaction(String),
+    #[error("Invalid payload: {0}")]
+    InvalidPayload(String),
+    #[error("Unsupported schema version {found}; this version reads up to {supported}")]
+    UnsupportedSchemaVersion { found: u32, supported: u32 },
+    #[error("Amount {amount} for account {account_id} has more than {max_scale} decimal places")]
+    ExcessPrecision {
+        account_id: String,
+        amount: BigDecimal,
+        max_scale: i64,
+    },
+    #[error("Budget for {dimension} {value} would be exceeded by {excess}")]
+    BudgetExceeded {
+        dimension: String,
+        value: String,
+        excess: BigDecimal,
+    },
+    #[error("Account {account_id} cannot be deleted: {}", describe_blockers(.blockers))]
+    AccountInUse {
+        account_id: String,
+        blockers: Vec<DeletionBlocker>,
+    },
+}
+
+impl LedgerError {
+    /// Create a permanent storage error
+    pub fn storage(message: impl Into<String>) -> Self {
+        LedgerError::Storage {
+            message: message.into(),
+            retryable: false,
+            source: None,
+        }
+    }
+
+    /// Create a transient storage error that may succeed on retry
+    pub fn transient_storage(message: impl Into<String>) -> Self {
+        LedgerError::Storage {
+            message: message.into(),
+            retryable: true,
+            source: None,
+        }
+    }
+
+    /// Create a storage error wrapping the backend's underlying error
+    pub fn storage_with_source(
+        message: impl Into<String>,
+        retryable: bool,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        LedgerError::Storage {
+            message: message.into(),
+            retryable,
+            source: Some(Box::new(source)),
+        }
+    }
+
+    /// Create an authorization failure for an operation
+    pub fn unauthorized(actor: Option<&Actor>, operation: &LedgerOperation<'_>) -> Self {
+        LedgerError::Unauthorized {
+            actor: actor
+                .map(|a| a.id.clone())
+                .unwrap_or_else(|| "anonymous".to_string()),
+            operation: operation.name().to_string(),
+        }
+    }
+
+    /// Stable machine-readable code for this error
+    pub fn code(&self) -> &'static str {
+        match self {
+            LedgerError::Storage { .. } => "storage_error",
+            LedgerError::InvalidTransaction(_) => "invalid_transaction",
+            LedgerError::InsufficientEntries { .. } => "insufficient_entries",
+            LedgerError::Unbalanced { .. } => "unbalanced_transaction",
+            LedgerError::NonPositiveAmount { .. } => "non_positive_amount",
+            LedgerError::AccountNotFound(_) => "account_not_found",
+            LedgerError::DuplicateAccount(_) => "duplicate_account",
+            LedgerError::ParentAccountNotFound(_) => "parent_account_not_found",
+            LedgerError::TransactionNotFound(_) => "transaction_not_found",
+            LedgerError::Validation(_) => "validation_error",
+            LedgerError::PeriodLocked(_) => "period_locked",
+            LedgerError::InsufficientBalance { .. } => "insufficient_balance",
+            LedgerError::CircularHierarchy(_) => "circular_hierarchy",
+            LedgerError::AccountArchived(_) => "account_archived",
+            LedgerError::EntityNotFound(_) => "entity_not_found",
+            LedgerError::Unauthorized { .. } => "unauthorized",
+            LedgerError::InvalidSnapshot(_) => "invalid_snapshot",
+            LedgerError::ImmutableTransaction(_) => "immutable_transaction",
+            LedgerError::InvalidPayload(_) => "invalid_payload",
+            LedgerError::UnsupportedSchemaVersion { .. } => "unsupported_schema_version",
+            LedgerError::ExcessPrecision { .. } => "excess_precision",
+            LedgerError::BudgetExceeded { .. } => "budget_exceeded",
+            LedgerError::AccountInUse { .. } => "account_in_use",
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed
+    ///
+    /// Only transient storage failures are retryable; every other error is
+    /// permanent for the given input.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            LedgerError::Storage {
+                retryable: true,
+                ..
+            }
+        )
+    }
 }
 
 /// Result type for ledger operations
@@ -0,0 +1,300 @@
+//! Storage wrapper that maintains daily per-account totals
+//!
+//! [`AggregatingStorage`] delegates everything to an inner backend and keeps
+//! an in-memory table of posted debit and credit totals per account and day,
+//! updated on every transaction save, update and delete. It implements
+//! [`ReportingStorage`], so the ledger builds trial balances and financial
+//! statements from the table instead of by replaying transactions.
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+use crate::traits::*;
+use crate::types::*;
+
+type DailyTotals = Arc<RwLock<BTreeMap<(String, NaiveDate), AccountTotals>>>;
+
+/// Storage wrapper maintaining pre-aggregated daily totals
+#[derive(Debug, Clone)]
+pub struct AggregatingStorage<S: LedgerStorage> {
+    inner: S,
+    daily_totals: DailyTotals,
+}
+
+impl<S: LedgerStorage> AggregatingStorage<S> {
+    /// Wrap a backend, aggregating the transactions it already holds
+    pub async fn new(inner: S) -> LedgerResult<Self> {
+        let storage = Self {
+            inner,
+            daily_totals: Arc::new(RwLock::new(BTreeMap::new())),
+        };
+        for transaction in storage.inner.get_transactions(None, None).await? {
+            storage.apply(&transaction, false);
+        }
+        Ok(storage)
+    }
+
+    /// The wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Add (or, with `remove`, subtract) a posted transaction's entries
+    fn apply(&self, transaction: &Transaction, remove: bool) {
+        if !transaction.is_posted() {
+            return;
+        }
+        let mut table = self.daily_totals.write().unwrap();
+        for entry in &transaction.entries {
+            let totals = table
+                .entry((entry.account_id.clone(), transaction.date))
+                .or_default();
+            if remove {
+                totals.subtract(&entry.entry_type, &entry.amount);
+            } else {
+                totals.add(&entry.entry_type, &entry.amount);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: LedgerStorage> ReportingStorage for AggregatingStorage<S> {
+    async fn get_daily_totals(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: NaiveDate,
+    ) -> LedgerResult<Vec<DailyAccountTotal>> {
+        let start = (account_id.to_string(), start_date.unwrap_or(NaiveDate::MIN));
+        let end = (account_id.to_string(), end_date);
+        if start > end {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .daily_totals
+            .read()
+            .unwrap()
+            .range(start..=end)
+            .map(|((account_id, date), totals)| DailyAccountTotal {
+                account_id: account_id.clone(),
+                date: *date,
+                totals: totals.clone(),
+            })
+            .collect())
+    }
+
+    async fn get_account_totals(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: NaiveDate,
+    ) -> LedgerResult<HashMap<String, AccountTotals>> {
+        let mut result: HashMap<String, AccountTotals> = HashMap::new();
+        for ((account_id, date), totals) in self.daily_totals.read().unwrap().iter() {
+            if *date > end_date || start_date.is_some_and(|start| *date < start) {
+                continue;
+            }
+            let sum = result.entry(account_id.clone()).or_default();
+            sum.debits += &totals.debits;
+            sum.credits += &totals.credits;
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl<S: LedgerStorage> LedgerStorage for AggregatingStorage<S> {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.inner.save_account(account).await
+    }
+
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        self.inner.save_accounts(accounts).await
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        self.inner.get_account(account_id).await
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        self.inner.list_accounts(account_type).await
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.inner.update_account(account).await
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        self.inner.delete_account(account_id).await
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        let previous = self.inner.get_transaction(&transaction.id).await?;
+        self.inner.save_transaction(transaction).await?;
+        if let Some(previous) = previous {
+            self.apply(&previous, true);
+        }
+        self.apply(transaction, false);
+        Ok(())
+    }
+
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        let mut previous: Vec<Transaction> = Vec::new();
+        for id in removed
+            .iter()
+            .map(|transaction| transaction.id.as_str())
+            .chain([replacement.id.as_str()])
+        {
+            if previous.iter().any(|transaction| transaction.id == id) {
+                continue;
+            }
+            if let Some(transaction) = self.inner.get_transaction(id).await? {
+                previous.push(transaction);
+            }
+        }
+        self.inner
+            .replace_transactions(removed, replacement)
+            .await?;
+        for transaction in &previous {
+            self.apply(transaction, true);
+        }
+        self.apply(replacement, false);
+        Ok(())
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        self.inner.get_transaction(transaction_id).await
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.inner
+            .get_account_transactions(account_id, start_date, end_date)
+            .await
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.inner.get_transactions(start_date, end_date).await
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        let previous = self.inner.get_transaction(&transaction.id).await?;
+        self.inner.update_transaction(transaction).await?;
+        if let Some(previous) = previous {
+            self.apply(&previous, true);
+        }
+        self.apply(transaction, false);
+        Ok(())
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        let previous = self.inner.get_transaction(transaction_id).await?;
+        self.inner.delete_transaction(transaction_id).await?;
+        if let Some(previous) = previous {
+            self.apply(&previous, true);
+        }
+        Ok(())
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        let Some(as_of_date) = as_of_date else {
+            return self.inner.get_account_balance(account_id, None).await;
+        };
+        let account = self
+            .get_account(account_id)
+            .await?
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        let mut totals = AccountTotals::default();
+        for day in self.get_daily_totals(account_id, None, as_of_date).await? {
+            totals.debits += day.totals.debits;
+            totals.credits += day.totals.credits;
+        }
+        Ok(totals.balance(&account.account_type))
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        self.inner.get_trial_balance(as_of_date).await
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        self.inner.get_account_balances_by_type(as_of_date).await
+    }
+
+    fn reporting(&self) -> Option<&dyn ReportingStorage> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, Ledger};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_aggregates_follow_postings_edits_and_voids() {
+        let storage = AggregatingStorage::new(MemoryStorage::new()).await.unwrap();
+        let mut ledger = Ledger::new(storage.clone());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
+        let rent = |id: &str, amount: i32| {
+            patterns::create_expense_payment(
+                id.to_string(),
+                date,
+                "Rent".to_string(),
+                "6000".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(amount),
+            )
+            .unwrap()
+        };
+
+        ledger.record_transaction(rent("r1", 300)).await.unwrap();
+        ledger.record_transaction(rent("r2", 200)).await.unwrap();
+        ledger.save_draft(rent("d1", 999)).await.unwrap();
+        let mut edited = rent("r2", 250);
+        edited.created_at = ledger
+            .get_transaction("r2")
+            .await
+            .unwrap()
+            .unwrap()
+            .created_at;
+        ledger.update_transaction(&edited).await.unwrap();
+        ledger.delete_transaction("r1").await.unwrap();
+
+        let days = storage.get_daily_totals("6000", None, date).await.unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].totals.debits, BigDecimal::from(250));
+
+        let trial_balance = ledger.get_trial_balance(date).await.unwrap();
+        assert!(trial_balance.is_balanced);
+        assert_eq!(
+            trial_balance.balances["1000"].credit_balance,
+            Some(BigDecimal::from(250))
+        );
+        let statement = ledger.generate_income_statement(date, date).await.unwrap();
+        assert_eq!(statement.total_expenses, BigDecimal::from(250));
+    }
+}
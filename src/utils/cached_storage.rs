@@ -0,0 +1,369 @@
+//! Storage wrapper with an LRU read cache
+//!
+//! [`CachedStorage`] keeps the most recently used accounts and transactions
+//! in memory so repeated lookups during validation and posting do not go back
+//! to a slow backend. Writes go through to the inner backend first and then
+//! refresh or drop the cached copy, so reads never see stale data written
+//! through the same storage. Clones share one cache.
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::traits::*;
+use crate::types::*;
+
+/// Least-recently-used map from ids to values
+#[derive(Debug)]
+struct Lru<V> {
+    capacity: usize,
+    tick: u64,
+    values: HashMap<String, (V, u64)>,
+    /// Last use of each key, oldest first
+    order: BTreeMap<u64, String>,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            values: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn touch(&mut self, key: &str) -> u64 {
+        self.tick += 1;
+        if let Some((_, used)) = self.values.get_mut(key) {
+            self.order.remove(used);
+            *used = self.tick;
+        }
+        self.order.insert(self.tick, key.to_string());
+        self.tick
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        if !self.values.contains_key(key) {
+            return None;
+        }
+        self.touch(key);
+        self.values.get(key).map(|(value, _)| value.clone())
+    }
+
+    fn put(&mut self, key: &str, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let used = self.touch(key);
+        self.values.insert(key.to_string(), (value, used));
+        while self.values.len() > self.capacity {
+            match self.order.pop_first() {
+                Some((_, oldest)) => {
+                    self.values.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some((_, used)) = self.values.remove(key) {
+            self.order.remove(&used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.order.clear();
+    }
+}
+
+/// Cache hit and miss counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug)]
+struct CacheState {
+    accounts: Lru<Account>,
+    transactions: Lru<Transaction>,
+    stats: CacheStats,
+}
+
+/// Storage wrapper caching account and transaction lookups
+#[derive(Debug, Clone)]
+pub struct CachedStorage<S: LedgerStorage> {
+    inner: S,
+    cache: Arc<Mutex<CacheState>>,
+}
+
+impl<S: LedgerStorage> CachedStorage<S> {
+    /// Wrap a backend, keeping up to `capacity` accounts and `capacity`
+    /// transactions in memory
+    pub fn new(inner: S, capacity: usize) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(CacheState {
+                accounts: Lru::new(capacity),
+                transactions: Lru::new(capacity),
+                stats: CacheStats::default(),
+            })),
+        }
+    }
+
+    /// The wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Hit and miss counts since creation
+    pub fn stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats
+    }
+
+    /// Drop every cached record, e.g. after the backend was changed by
+    /// another process
+    pub fn invalidate_all(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.accounts.clear();
+        cache.transactions.clear();
+    }
+
+    fn cached_account(&self, account_id: &str) -> Option<Account> {
+        let mut cache = self.cache.lock().unwrap();
+        let account = cache.accounts.get(account_id);
+        match account {
+            Some(_) => cache.stats.hits += 1,
+            None => cache.stats.misses += 1,
+        }
+        account
+    }
+
+    fn cached_transaction(&self, transaction_id: &str) -> Option<Transaction> {
+        let mut cache = self.cache.lock().unwrap();
+        let transaction = cache.transactions.get(transaction_id);
+        match transaction {
+            Some(_) => cache.stats.hits += 1,
+            None => cache.stats.misses += 1,
+        }
+        transaction
+    }
+}
+
+#[async_trait]
+impl<S: LedgerStorage> LedgerStorage for CachedStorage<S> {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.inner.save_account(account).await?;
+        self.cache
+            .lock()
+            .unwrap()
+            .accounts
+            .put(&account.id, account.clone());
+        Ok(())
+    }
+
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        let result = self.inner.save_accounts(accounts).await;
+        let mut cache = self.cache.lock().unwrap();
+        for account in accounts {
+            match result {
+                Ok(()) => cache.accounts.put(&account.id, account.clone()),
+                Err(_) => cache.accounts.remove(&account.id),
+            }
+        }
+        result
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        if let Some(account) = self.cached_account(account_id) {
+            return Ok(Some(account));
+        }
+        let account = self.inner.get_account(account_id).await?;
+        if let Some(account) = &account {
+            self.cache
+                .lock()
+                .unwrap()
+                .accounts
+                .put(account_id, account.clone());
+        }
+        Ok(account)
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        self.inner.list_accounts(account_type).await
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        let result = self.inner.update_account(account).await;
+        let mut cache = self.cache.lock().unwrap();
+        match result {
+            Ok(()) => cache.accounts.put(&account.id, account.clone()),
+            Err(_) => cache.accounts.remove(&account.id),
+        }
+        result
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        let result = self.inner.delete_account(account_id).await;
+        self.cache.lock().unwrap().accounts.remove(account_id);
+        result
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        let result = self.inner.save_transaction(transaction).await;
+        let mut cache = self.cache.lock().unwrap();
+        match result {
+            Ok(()) => cache.transactions.put(&transaction.id, transaction.clone()),
+            Err(_) => cache.transactions.remove(&transaction.id),
+        }
+        result
+    }
+
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        let result = self.inner.replace_transactions(removed, replacement).await;
+        let mut cache = self.cache.lock().unwrap();
+        for transaction in removed {
+            cache.transactions.remove(&transaction.id);
+        }
+        match result {
+            Ok(()) => cache.transactions.put(&replacement.id, replacement.clone()),
+            Err(_) => cache.transactions.remove(&replacement.id),
+        }
+        result
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        if let Some(transaction) = self.cached_transaction(transaction_id) {
+            return Ok(Some(transaction));
+        }
+        let transaction = self.inner.get_transaction(transaction_id).await?;
+        if let Some(transaction) = &transaction {
+            self.cache
+                .lock()
+                .unwrap()
+                .transactions
+                .put(transaction_id, transaction.clone());
+        }
+        Ok(transaction)
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.inner
+            .get_account_transactions(account_id, start_date, end_date)
+            .await
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        self.inner.get_transactions(start_date, end_date).await
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        let result = self.inner.update_transaction(transaction).await;
+        let mut cache = self.cache.lock().unwrap();
+        match result {
+            Ok(()) => cache.transactions.put(&transaction.id, transaction.clone()),
+            Err(_) => cache.transactions.remove(&transaction.id),
+        }
+        result
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        let result = self.inner.delete_transaction(transaction_id).await;
+        self.cache
+            .lock()
+            .unwrap()
+            .transactions
+            .remove(transaction_id);
+        result
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        self.inner.get_account_balance(account_id, as_of_date).await
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        self.inner.get_trial_balance(as_of_date).await
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        self.inner.get_account_balances_by_type(as_of_date).await
+    }
+
+    fn reporting(&self) -> Option<&dyn ReportingStorage> {
+        self.inner.reporting()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, Ledger};
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_cache_serves_reads_and_follows_writes() {
+        let backend = MemoryStorage::new();
+        let storage = CachedStorage::new(backend.clone(), 2);
+        let mut ledger = Ledger::new(storage.clone());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let rent = patterns::create_expense_payment(
+            "rent".to_string(),
+            NaiveDate::from_ymd_opt(2024, 7, 1).unwrap(),
+            "Rent".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(400),
+        )
+        .unwrap();
+        ledger.record_transaction(rent).await.unwrap();
+        assert!(storage.stats().hits > 0);
+
+        // Written balances are visible through the cache and the backend
+        assert_eq!(
+            storage.get_account("6000").await.unwrap().unwrap().balance,
+            BigDecimal::from(400)
+        );
+        assert_eq!(
+            backend.get_account("6000").await.unwrap().unwrap().balance,
+            BigDecimal::from(400)
+        );
+
+        // Capacity bounds the cache; evicted records are reloaded
+        let before = storage.stats();
+        storage.get_account("1000").await.unwrap();
+        storage.get_account("6000").await.unwrap();
+        storage.get_account("3000").await.unwrap();
+        storage.get_account("1000").await.unwrap();
+        let after = storage.stats();
+        assert_eq!(after.hits - before.hits, 2);
+        assert_eq!(after.misses - before.misses, 2);
+
+        ledger.delete_transaction("rent").await.unwrap();
+        assert!(storage.get_transaction("rent").await.unwrap().is_none());
+    }
+}
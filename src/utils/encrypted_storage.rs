@@ -0,0 +1,451 @@
+//! Storage wrapper encrypting sensitive fields at rest
+//!
+//! [`EncryptedStorage`] passes designated text fields through a
+//! [`FieldCipher`] on the way into the inner backend and back out again, so
+//! the ledger and its reports only ever see plaintext while the backend only
+//! ever holds ciphertext. Amounts, dates, account IDs and balances stay in
+//! the clear, so the backend can still total and filter by them; filtering
+//! by an encrypted dimension happens after decryption.
+//!
+//! `KeystreamCipher`, built with the `testing` feature, is a self-contained
+//! reference cipher for tests. Production deployments should implement
+//! [`FieldCipher`] over a vetted AEAD or a key management service.
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use crate::ledger::PARTY_DIMENSION;
+use crate::traits::*;
+use crate::types::*;
+
+/// Fields of a transaction that [`EncryptedStorage`] encrypts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptedFields {
+    /// Transaction and entry descriptions, references and note text
+    pub descriptions: bool,
+    /// Entry dimensions whose values are encrypted, such as the party
+    pub dimensions: BTreeSet<String>,
+    /// Transaction metadata keys whose text values are encrypted
+    pub metadata_keys: BTreeSet<String>,
+}
+
+impl Default for EncryptedFields {
+    /// Descriptions, parties and bank counterparty names
+    fn default() -> Self {
+        Self {
+            descriptions: true,
+            dimensions: BTreeSet::from([PARTY_DIMENSION.to_string()]),
+            metadata_keys: BTreeSet::from(["counterparty".to_string()]),
+        }
+    }
+}
+
+/// Storage wrapper encrypting designated transaction fields
+#[derive(Clone)]
+pub struct EncryptedStorage<S: LedgerStorage> {
+    inner: S,
+    cipher: Arc<dyn FieldCipher>,
+    fields: EncryptedFields,
+}
+
+impl<S: LedgerStorage> EncryptedStorage<S> {
+    /// Wrap a backend, encrypting the [default fields](EncryptedFields::default)
+    pub fn new(inner: S, cipher: Arc<dyn FieldCipher>) -> Self {
+        Self::with_fields(inner, cipher, EncryptedFields::default())
+    }
+
+    /// Wrap a backend, encrypting the given fields
+    pub fn with_fields(inner: S, cipher: Arc<dyn FieldCipher>, fields: EncryptedFields) -> Self {
+        Self {
+            inner,
+            cipher,
+            fields,
+        }
+    }
+
+    /// The wrapped backend, holding ciphertext
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Apply `apply` to every designated field of a transaction
+    fn map_fields(
+        &self,
+        transaction: &Transaction,
+        apply: impl Fn(&str, &str) -> LedgerResult<String>,
+    ) -> LedgerResult<Transaction> {
+        let mut transaction = transaction.clone();
+        let apply_option = |field: &str, value: &mut Option<String>| -> LedgerResult<()> {
+            if let Some(value) = value {
+                *value = apply(field, value)?;
+            }
+            Ok(())
+        };
+        if self.fields.descriptions {
+            transaction.description = apply("description", &transaction.description)?;
+            apply_option("reference", &mut transaction.reference)?;
+            for note in &mut transaction.notes {
+                note.text = apply("note", &note.text)?;
+            }
+        }
+        // Book replacement entries carry the same descriptions and parties
+        let entries = transaction
+            .entries
+            .iter_mut()
+            .chain(transaction.book_entries.values_mut().flatten());
+        for entry in entries {
+            if self.fields.descriptions {
+                apply_option("entry.description", &mut entry.description)?;
+            }
+            for (key, value) in entry.dimensions.iter_mut() {
+                if self.fields.dimensions.contains(key) {
+                    *value = apply(&format!("dimension.{}", key), value)?;
+                }
+            }
+        }
+        for (key, value) in transaction.metadata.iter_mut() {
+            if let MetaValue::Text(text) = value {
+                if self.fields.metadata_keys.contains(key) {
+                    *text = apply(&format!("metadata.{}", key), text)?;
+                }
+            }
+        }
+        Ok(transaction)
+    }
+
+    fn encrypt(&self, transaction: &Transaction) -> LedgerResult<Transaction> {
+        self.map_fields(transaction, |field, value| {
+            self.cipher.encrypt(field, value)
+        })
+    }
+
+    fn decrypt(&self, transaction: &Transaction) -> LedgerResult<Transaction> {
+        self.map_fields(transaction, |field, value| {
+            self.cipher.decrypt(field, value)
+        })
+    }
+
+    fn decrypt_all(&self, transactions: Vec<Transaction>) -> LedgerResult<Vec<Transaction>> {
+        transactions.iter().map(|t| self.decrypt(t)).collect()
+    }
+}
+
+#[async_trait]
+impl<S: LedgerStorage> LedgerStorage for EncryptedStorage<S> {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.inner.save_account(account).await
+    }
+
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        self.inner.save_accounts(accounts).await
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        self.inner.get_account(account_id).await
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        self.inner.list_accounts(account_type).await
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        self.inner.update_account(account).await
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        self.inner.delete_account(account_id).await
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        let encrypted = self.encrypt(transaction)?;
+        self.inner.save_transaction(&encrypted).await
+    }
+
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        let removed = removed
+            .iter()
+            .map(|transaction| self.encrypt(transaction))
+            .collect::<LedgerResult<Vec<_>>>()?;
+        let replacement = self.encrypt(replacement)?;
+        self.inner
+            .replace_transactions(&removed, &replacement)
+            .await
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        match self.inner.get_transaction(transaction_id).await? {
+            Some(transaction) => Ok(Some(self.decrypt(&transaction)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        let transactions = self
+            .inner
+            .get_account_transactions(account_id, start_date, end_date)
+            .await?;
+        self.decrypt_all(transactions)
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        let transactions = self.inner.get_transactions(start_date, end_date).await?;
+        self.decrypt_all(transactions)
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        let encrypted = self.encrypt(transaction)?;
+        self.inner.update_transaction(&encrypted).await
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        self.inner.delete_transaction(transaction_id).await
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        self.inner.get_account_balance(account_id, as_of_date).await
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        self.inner.get_trial_balance(as_of_date).await
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        self.inner.get_account_balances_by_type(as_of_date).await
+    }
+
+    fn reporting(&self) -> Option<&dyn ReportingStorage> {
+        self.inner.reporting()
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+pub use keystream::KeystreamCipher;
+
+#[cfg(any(test, feature = "testing"))]
+mod keystream {
+    use sha2::{Digest, Sha256};
+
+    use crate::traits::FieldCipher;
+    use crate::types::{LedgerError, LedgerResult};
+
+    /// Prefix marking values written by [`KeystreamCipher`]
+    pub(super) const KEYSTREAM_PREFIX: &str = "ks1:";
+    const NONCE_LEN: usize = 16;
+    const TAG_LEN: usize = 16;
+
+    /// Reference [`FieldCipher`] built on SHA-256 in counter mode with a
+    /// SHA-256 tag binding the key, nonce, field and ciphertext
+    ///
+    /// Each value gets a random nonce, so equal plaintexts encrypt differently.
+    /// Meant for tests and examples; it has not been reviewed as production
+    /// cryptography.
+    #[derive(Clone)]
+    pub struct KeystreamCipher {
+        key: [u8; 32],
+    }
+
+    impl std::fmt::Debug for KeystreamCipher {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("KeystreamCipher { .. }")
+        }
+    }
+
+    impl KeystreamCipher {
+        /// Derive the cipher key from user-supplied key material
+        pub fn new(key: &[u8]) -> Self {
+            Self {
+                key: Sha256::digest(key).into(),
+            }
+        }
+
+        fn apply_keystream(&self, nonce: &[u8], data: &mut [u8]) {
+            for (counter, chunk) in data.chunks_mut(32).enumerate() {
+                let block = Sha256::new()
+                    .chain_update(self.key)
+                    .chain_update(nonce)
+                    .chain_update((counter as u64).to_be_bytes())
+                    .finalize();
+                for (byte, key) in chunk.iter_mut().zip(block.iter()) {
+                    *byte ^= key;
+                }
+            }
+        }
+
+        fn tag(&self, field: &str, nonce: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+            let digest = Sha256::new()
+                .chain_update(self.key)
+                .chain_update(b"tag")
+                .chain_update((field.len() as u64).to_be_bytes())
+                .chain_update(field.as_bytes())
+                .chain_update(nonce)
+                .chain_update(ciphertext)
+                .finalize();
+            let mut tag = [0u8; TAG_LEN];
+            tag.copy_from_slice(&digest[..TAG_LEN]);
+            tag
+        }
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn from_hex(text: &str) -> Option<Vec<u8>> {
+        if text.len() % 2 != 0 {
+            return None;
+        }
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    impl FieldCipher for KeystreamCipher {
+        fn encrypt(&self, field: &str, plaintext: &str) -> LedgerResult<String> {
+            let nonce = *uuid::Uuid::new_v4().as_bytes();
+            let mut data = plaintext.as_bytes().to_vec();
+            self.apply_keystream(&nonce, &mut data);
+            let tag = self.tag(field, &nonce, &data);
+            Ok(format!(
+                "{}{}{}{}",
+                KEYSTREAM_PREFIX,
+                to_hex(&nonce),
+                to_hex(&data),
+                to_hex(&tag)
+            ))
+        }
+
+        fn decrypt(&self, field: &str, ciphertext: &str) -> LedgerResult<String> {
+            let invalid = || LedgerError::storage(format!("Cannot decrypt {}", field));
+            let bytes = ciphertext
+                .strip_prefix(KEYSTREAM_PREFIX)
+                .and_then(from_hex)
+                .filter(|bytes| bytes.len() >= NONCE_LEN + TAG_LEN)
+                .ok_or_else(invalid)?;
+            let (nonce, rest) = bytes.split_at(NONCE_LEN);
+            let (data, tag) = rest.split_at(rest.len() - TAG_LEN);
+            // Compare every byte so the time taken does not reveal where the
+            // tags first differ
+            let expected = self.tag(field, nonce, data);
+            let difference = expected
+                .iter()
+                .zip(tag)
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+            if difference != 0 {
+                return Err(invalid());
+            }
+            let mut data = data.to_vec();
+            self.apply_keystream(nonce, &mut data);
+            String::from_utf8(data).map_err(|_| invalid())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::keystream::KEYSTREAM_PREFIX;
+    use super::*;
+    use crate::ledger::{Ledger, TransactionBuilder};
+    use crate::utils::MemoryStorage;
+
+    #[test]
+    fn test_keystream_cipher_round_trip_and_tamper() {
+        let cipher = KeystreamCipher::new(b"secret");
+        let text = "Payment to Acme — invoice 42";
+        let encrypted = cipher.encrypt("description", text).unwrap();
+        assert_ne!(encrypted, cipher.encrypt("description", text).unwrap());
+        assert_eq!(cipher.decrypt("description", &encrypted).unwrap(), text);
+        assert!(cipher.decrypt("reference", &encrypted).is_err());
+        assert!(KeystreamCipher::new(b"other")
+            .decrypt("description", &encrypted)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fields_are_encrypted_at_rest() {
+        let backend = MemoryStorage::new();
+        let storage = EncryptedStorage::new(
+            backend.clone(),
+            Arc::new(KeystreamCipher::new(b"ledger key")),
+        );
+        let mut ledger = Ledger::new(storage);
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let mut sale = TransactionBuilder::new(
+            "S1".to_string(),
+            NaiveDate::from_ymd_opt(2024, 5, 2).unwrap(),
+            "Sale to Acme Traders".to_string(),
+        )
+        .reference("INV-17".to_string())
+        .entry(
+            Entry::debit("1200".to_string(), BigDecimal::from(900), None)
+                .with_dimension(PARTY_DIMENSION.to_string(), "acme".to_string()),
+        )
+        .credit("4000".to_string(), BigDecimal::from(900), None)
+        .build()
+        .unwrap();
+        sale.set_book_entries(
+            "tax",
+            vec![
+                Entry::debit(
+                    "1200".to_string(),
+                    BigDecimal::from(900),
+                    Some("Acme receivable".to_string()),
+                )
+                .with_dimension(PARTY_DIMENSION.to_string(), "acme".to_string()),
+                Entry::credit("4000".to_string(), BigDecimal::from(900), None),
+            ],
+        );
+        ledger.record_transaction(sale).await.unwrap();
+
+        let stored = backend.get_transaction("S1").await.unwrap().unwrap();
+        assert!(stored.description.starts_with(KEYSTREAM_PREFIX));
+        assert!(stored.entries[0].dimensions[PARTY_DIMENSION].starts_with(KEYSTREAM_PREFIX));
+        assert_eq!(stored.entries[0].amount, BigDecimal::from(900));
+        let stored_tax = &stored.book_entries["tax"][0];
+        assert!(stored_tax
+            .description
+            .as_deref()
+            .unwrap()
+            .starts_with(KEYSTREAM_PREFIX));
+        assert!(stored_tax.dimensions[PARTY_DIMENSION].starts_with(KEYSTREAM_PREFIX));
+
+        let read = ledger.get_transaction("S1").await.unwrap().unwrap();
+        assert_eq!(read.description, "Sale to Acme Traders");
+        assert_eq!(read.reference.as_deref(), Some("INV-17"));
+        let read_tax = &read.book_entries["tax"][0];
+        assert_eq!(read_tax.description.as_deref(), Some("Acme receivable"));
+        assert_eq!(read_tax.dimensions[PARTY_DIMENSION], "acme");
+        let filter = EntryFilter {
+            dimension: Some((PARTY_DIMENSION.to_string(), "acme".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(ledger.get_entries(&filter).await.unwrap().len(), 1);
+        assert_eq!(
+            ledger.get_account_balance("1200", None).await.unwrap(),
+            BigDecimal::from(900)
+        );
+    }
+}
@@ -0,0 +1,508 @@
+//! Append-only, event-sourced storage
+//!
+//! [`EventSourcedStorage`] keeps an immutable journal of [`JournalEvent`]s and
+//! answers every query from projections built by replaying it. Account
+//! balances are never stored: the balance passed in with an account update is
+//! ignored and recomputed from the posted transactions in the journal.
+//!
+//! Posted transactions cannot be changed or removed. Annotations (tags,
+//! notes, attachments, metadata) may still be added as new events, but a
+//! change to a posted transaction's date, status or entries is refused with
+//! [`LedgerError::ImmutableTransaction`]; corrections are made by posting a
+//! reversal. Drafts and transactions awaiting approval can be revised and
+//! discarded freely, since they never affected a balance.
+
+use async_trait::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::traits::*;
+use crate::types::*;
+
+/// A change recorded in the journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum JournalChange {
+    AccountOpened(Account),
+    AccountUpdated(Account),
+    AccountClosed { account_id: String },
+    TransactionRecorded(Transaction),
+    TransactionRevised(Transaction),
+    TransactionDiscarded { transaction_id: String },
+}
+
+/// An entry in the append-only journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JournalEvent {
+    /// Position in the journal, starting at 1
+    pub sequence: u64,
+    /// When the change was appended
+    #[serde(with = "utc_timestamp")]
+    #[cfg_attr(feature = "schema", schemars(with = "DateTime<Utc>"))]
+    pub recorded_at: DateTime<Utc>,
+    pub change: JournalChange,
+}
+
+/// Current state derived from the journal
+#[derive(Debug, Clone, Default)]
+struct Projection {
+    accounts: HashMap<String, Account>,
+    transactions: HashMap<String, Transaction>,
+}
+
+impl Projection {
+    fn apply(&mut self, change: &JournalChange) {
+        match change {
+            JournalChange::AccountOpened(account) | JournalChange::AccountUpdated(account) => {
+                let balance = self
+                    .accounts
+                    .get(&account.id)
+                    .map(|existing| existing.balance.clone())
+                    .unwrap_or_else(|| self.posted_balance(account));
+                let mut account = account.clone();
+                account.balance = balance;
+                self.accounts.insert(account.id.clone(), account);
+            }
+            JournalChange::AccountClosed { account_id } => {
+                self.accounts.remove(account_id);
+            }
+            JournalChange::TransactionRecorded(transaction)
+            | JournalChange::TransactionRevised(transaction) => {
+                if let Some(previous) = self.transactions.remove(&transaction.id) {
+                    self.apply_postings(&previous, true);
+                }
+                self.apply_postings(transaction, false);
+                self.transactions
+                    .insert(transaction.id.clone(), transaction.clone());
+            }
+            JournalChange::TransactionDiscarded { transaction_id } => {
+                if let Some(previous) = self.transactions.remove(transaction_id) {
+                    self.apply_postings(&previous, true);
+                }
+            }
+        }
+    }
+
+    /// Add (or, with `remove`, subtract) a posted transaction's entries to
+    /// the projected balances
+    fn apply_postings(&mut self, transaction: &Transaction, remove: bool) {
+        if !transaction.is_posted() {
+            return;
+        }
+        for entry in &transaction.entries {
+            if let Some(account) = self.accounts.get_mut(&entry.account_id) {
+                let effect = account
+                    .account_type
+                    .balance_effect(&entry.entry_type, &entry.amount);
+                if remove {
+                    account.balance -= effect;
+                } else {
+                    account.balance += effect;
+                }
+            }
+        }
+    }
+
+    /// Balance of an account from the posted transactions already projected
+    fn posted_balance(&self, account: &Account) -> BigDecimal {
+        self.transactions
+            .values()
+            .filter(|t| t.is_posted())
+            .flat_map(|t| &t.entries)
+            .filter(|e| e.account_id == account.id)
+            .map(|e| {
+                account
+                    .account_type
+                    .balance_effect(&e.entry_type, &e.amount)
+            })
+            .sum()
+    }
+
+    fn posted_totals(&self, as_of_date: NaiveDate) -> HashMap<String, AccountTotals> {
+        let mut totals: HashMap<String, AccountTotals> = HashMap::new();
+        for transaction in self.transactions.values() {
+            if !transaction.is_posted() || transaction.date > as_of_date {
+                continue;
+            }
+            for entry in &transaction.entries {
+                totals
+                    .entry(entry.account_id.clone())
+                    .or_default()
+                    .add(&entry.entry_type, &entry.amount);
+            }
+        }
+        totals
+    }
+}
+
+/// Whether `revised` only annotates `original`, leaving its postings alone
+fn is_annotation(original: &Transaction, revised: &Transaction) -> bool {
+    original.date == revised.date
+        && original.status == revised.status
+        && original.entries == revised.entries
+}
+
+/// Storage backend whose state is a projection of an append-only journal
+#[derive(Debug, Clone)]
+pub struct EventSourcedStorage {
+    journal: Arc<RwLock<Vec<JournalEvent>>>,
+    projection: Arc<RwLock<Projection>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for EventSourcedStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventSourcedStorage {
+    /// Create storage with an empty journal
+    pub fn new() -> Self {
+        Self {
+            journal: Arc::default(),
+            projection: Arc::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Stamp journal events with `clock` instead of the system time
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Rebuild storage from a previously exported journal
+    ///
+    /// Events must be in sequence order starting at 1.
+    pub fn from_events(events: Vec<JournalEvent>) -> LedgerResult<Self> {
+        for (index, event) in events.iter().enumerate() {
+            if event.sequence != index as u64 + 1 {
+                return Err(LedgerError::Validation(format!(
+                    "Journal event {} is out of sequence, expected {}",
+                    event.sequence,
+                    index + 1
+                )));
+            }
+        }
+        let storage = Self {
+            journal: Arc::new(RwLock::new(events)),
+            projection: Arc::default(),
+            clock: Arc::new(SystemClock),
+        };
+        storage.rebuild_projections();
+        Ok(storage)
+    }
+
+    /// Every event in the journal, oldest first
+    pub fn events(&self) -> Vec<JournalEvent> {
+        self.journal.read().unwrap().clone()
+    }
+
+    /// Events recorded after the given sequence number
+    pub fn events_since(&self, sequence: u64) -> Vec<JournalEvent> {
+        self.journal
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|event| event.sequence > sequence)
+            .cloned()
+            .collect()
+    }
+
+    /// Discard the projections and replay the whole journal
+    pub fn rebuild_projections(&self) {
+        let mut projection = Projection::default();
+        for event in self.journal.read().unwrap().iter() {
+            projection.apply(&event.change);
+        }
+        *self.projection.write().unwrap() = projection;
+    }
+
+    fn append(&self, change: JournalChange) {
+        let mut journal = self.journal.write().unwrap();
+        self.projection.write().unwrap().apply(&change);
+        let sequence = journal.len() as u64 + 1;
+        journal.push(JournalEvent {
+            sequence,
+            recorded_at: self.clock.now(),
+            change,
+        });
+    }
+
+    fn projected_account(&self, account_id: &str) -> Option<Account> {
+        self.projection
+            .read()
+            .unwrap()
+            .accounts
+            .get(account_id)
+            .cloned()
+    }
+
+    fn projected_transaction(&self, transaction_id: &str) -> Option<Transaction> {
+        self.projection
+            .read()
+            .unwrap()
+            .transactions
+            .get(transaction_id)
+            .cloned()
+    }
+
+    /// Refuse a change that would rewrite a posted transaction
+    fn ensure_mutable(&self, transaction: &Transaction) -> LedgerResult<()> {
+        match self.projected_transaction(&transaction.id) {
+            Some(original) if original.is_posted() && !is_annotation(&original, transaction) => {
+                Err(LedgerError::ImmutableTransaction(transaction.id.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl LedgerStorage for EventSourcedStorage {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        match self.projected_account(&account.id) {
+            Some(_) => self.update_account(account).await,
+            None => {
+                self.append(JournalChange::AccountOpened(account.clone()));
+                Ok(())
+            }
+        }
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        Ok(self.projected_account(account_id))
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        Ok(self
+            .projection
+            .read()
+            .unwrap()
+            .accounts
+            .values()
+            .filter(|account| {
+                account_type
+                    .as_ref()
+                    .is_none_or(|t| &account.account_type == t)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        let existing = self
+            .projected_account(&account.id)
+            .ok_or_else(|| LedgerError::AccountNotFound(account.id.clone()))?;
+        // Balance writes are projections of the journal, not changes
+        let mut proposed = account.clone();
+        proposed.balance = existing.balance.clone();
+        proposed.updated_at = existing.updated_at;
+        if proposed != existing {
+            self.append(JournalChange::AccountUpdated(account.clone()));
+        }
+        Ok(())
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        if self.projected_account(account_id).is_none() {
+            return Err(LedgerError::AccountNotFound(account_id.to_string()));
+        }
+        self.append(JournalChange::AccountClosed {
+            account_id: account_id.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        self.ensure_mutable(transaction)?;
+        let change = if self.projected_transaction(&transaction.id).is_some() {
+            JournalChange::TransactionRevised(transaction.clone())
+        } else {
+            JournalChange::TransactionRecorded(transaction.clone())
+        };
+        self.append(change);
+        Ok(())
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        Ok(self.projected_transaction(transaction_id))
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        Ok(self
+            .get_transactions(start_date, end_date)
+            .await?
+            .into_iter()
+            .filter(|t| t.entries.iter().any(|e| e.account_id == account_id))
+            .collect())
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        Ok(self
+            .projection
+            .read()
+            .unwrap()
+            .transactions
+            .values()
+            .filter(|t| {
+                start_date.is_none_or(|start| t.date >= start)
+                    && end_date.is_none_or(|end| t.date <= end)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        if self.projected_transaction(&transaction.id).is_none() {
+            return Err(LedgerError::TransactionNotFound(transaction.id.clone()));
+        }
+        self.ensure_mutable(transaction)?;
+        self.append(JournalChange::TransactionRevised(transaction.clone()));
+        Ok(())
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        let transaction = self
+            .projected_transaction(transaction_id)
+            .ok_or_else(|| LedgerError::TransactionNotFound(transaction_id.to_string()))?;
+        if transaction.is_posted() {
+            return Err(LedgerError::ImmutableTransaction(
+                transaction_id.to_string(),
+            ));
+        }
+        self.append(JournalChange::TransactionDiscarded {
+            transaction_id: transaction_id.to_string(),
+        });
+        Ok(())
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        let account = self
+            .projected_account(account_id)
+            .ok_or_else(|| LedgerError::AccountNotFound(account_id.to_string()))?;
+        let Some(as_of_date) = as_of_date else {
+            return Ok(account.balance);
+        };
+        let totals = self.projection.read().unwrap().posted_totals(as_of_date);
+        Ok(totals
+            .get(account_id)
+            .map(|totals| totals.balance(&account.account_type))
+            .unwrap_or_else(BigDecimal::zero))
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        let accounts = self.list_accounts(None).await?;
+        let totals = self.projection.read().unwrap().posted_totals(as_of_date);
+        Ok(TrialBalance::from_totals(accounts, &totals, as_of_date))
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        let mut result: HashMap<AccountType, Vec<AccountBalance>> = HashMap::new();
+        for line in self
+            .get_trial_balance(as_of_date)
+            .await?
+            .balances
+            .into_values()
+        {
+            result
+                .entry(line.account.account_type.clone())
+                .or_default()
+                .push(line);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{patterns, Ledger};
+
+    #[tokio::test]
+    async fn test_posted_transactions_are_immutable_and_projections_rebuild() {
+        let storage = EventSourcedStorage::new();
+        let mut ledger = Ledger::new(storage.clone());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let rent = patterns::create_expense_payment(
+            "rent".to_string(),
+            date,
+            "Rent".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(1200),
+        )
+        .unwrap();
+        ledger.record_transaction(rent.clone()).await.unwrap();
+        let accounts_opened = storage.events().len() - 1;
+
+        // Balance updates from the ledger are not journaled
+        assert!(matches!(
+            storage.events().last().unwrap().change,
+            JournalChange::TransactionRecorded(_)
+        ));
+        assert_eq!(
+            ledger.get_account_balance("6000", None).await.unwrap(),
+            BigDecimal::from(1200)
+        );
+
+        let posted = ledger.get_transaction("rent").await.unwrap().unwrap();
+        let mut edited = posted.clone();
+        edited.entries[0].amount = BigDecimal::from(1500);
+        edited.entries[1].amount = BigDecimal::from(1500);
+        let error = ledger.update_transaction(&edited).await.unwrap_err();
+        assert_eq!(error.code(), "immutable_transaction");
+        assert!(ledger.delete_transaction("rent").await.is_err());
+        ledger.add_tag("rent", "facilities").await.unwrap();
+
+        let reversal = patterns::create_reversal(
+            &posted,
+            "rent-rev".to_string(),
+            date,
+            "Reverse rent".to_string(),
+        )
+        .unwrap();
+        ledger.record_transaction(reversal).await.unwrap();
+        assert_eq!(
+            ledger.get_account_balance("6000", None).await.unwrap(),
+            BigDecimal::zero()
+        );
+
+        let events = storage.events();
+        assert_eq!(events.len(), accounts_opened + 3);
+        assert_eq!(storage.events_since(accounts_opened as u64).len(), 3);
+        let replayed = EventSourcedStorage::from_events(events).unwrap();
+        assert_eq!(
+            replayed.get_trial_balance(date).await.unwrap(),
+            ledger.get_trial_balance(date).await.unwrap()
+        );
+        assert!(replayed
+            .get_transaction("rent")
+            .await
+            .unwrap()
+            .unwrap()
+            .has_tag("facilities"));
+    }
+}
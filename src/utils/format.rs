@@ -0,0 +1,184 @@
+//! Display formatting for amounts
+//!
+//! Amounts are kept as exact decimals everywhere in the crate; a
+//! [`Formatter`] only decides how they are shown: how many decimal places,
+//! how digits are grouped (`12,34,567.00` in India, `1,234,567.00`
+//! elsewhere), where the currency symbol goes and how negatives are marked.
+
+use bigdecimal::{BigDecimal, RoundingMode, Signed};
+use serde::{Deserialize, Serialize};
+
+/// How the integer digits of an amount are grouped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DigitGrouping {
+    /// Thousands, then lakhs and crores: `12,34,567`
+    #[default]
+    Indian,
+    /// Groups of three: `1,234,567`
+    Western,
+    /// No separators: `1234567`
+    None,
+}
+
+/// Where the currency symbol goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum SymbolPlacement {
+    /// `₹1,000.00`
+    #[default]
+    Before,
+    /// `1,000.00 €`, separated by a space
+    After,
+}
+
+/// How negative amounts are marked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum NegativeStyle {
+    /// `-₹1,000.00`
+    #[default]
+    MinusSign,
+    /// `(₹1,000.00)`, as in printed accounts
+    Parentheses,
+}
+
+/// Formats amounts for display
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Formatter {
+    /// Digits after the decimal point; amounts are rounded half up
+    pub decimal_places: u32,
+    pub grouping: DigitGrouping,
+    pub group_separator: char,
+    pub decimal_separator: char,
+    /// Currency symbol, or `None` for bare numbers
+    pub symbol: Option<String>,
+    pub symbol_placement: SymbolPlacement,
+    pub negative_style: NegativeStyle,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::indian()
+    }
+}
+
+impl Formatter {
+    /// Rupees with Indian digit grouping: `₹12,34,567.00`
+    pub fn indian() -> Self {
+        Self {
+            decimal_places: 2,
+            grouping: DigitGrouping::Indian,
+            group_separator: ',',
+            decimal_separator: '.',
+            symbol: Some("₹".to_string()),
+            symbol_placement: SymbolPlacement::Before,
+            negative_style: NegativeStyle::MinusSign,
+        }
+    }
+
+    /// Western digit grouping with the given symbol: `$1,234,567.00`
+    pub fn western(symbol: &str) -> Self {
+        Self {
+            grouping: DigitGrouping::Western,
+            symbol: Some(symbol.to_string()),
+            ..Self::indian()
+        }
+    }
+
+    /// The same format without a currency symbol, for table columns
+    pub fn without_symbol(&self) -> Self {
+        Self {
+            symbol: None,
+            ..self.clone()
+        }
+    }
+
+    /// Format an amount
+    pub fn format(&self, amount: &BigDecimal) -> String {
+        let rounded = amount.with_scale_round(i64::from(self.decimal_places), RoundingMode::HalfUp);
+        let digits = rounded.abs().to_plain_string();
+        let (integer, fraction) = digits.split_once('.').unwrap_or((&digits, ""));
+
+        let mut number = self.group(integer);
+        if self.decimal_places > 0 {
+            number.push(self.decimal_separator);
+            // Pad to the minor-unit digits, so zero prints as e.g. 0.00
+            number.push_str(&format!(
+                "{:0<width$}",
+                fraction,
+                width = self.decimal_places as usize
+            ));
+        }
+        let number = match (&self.symbol, self.symbol_placement) {
+            (Some(symbol), SymbolPlacement::Before) => format!("{symbol}{number}"),
+            (Some(symbol), SymbolPlacement::After) => format!("{number} {symbol}"),
+            (None, _) => number,
+        };
+
+        if !rounded.is_negative() {
+            return number;
+        }
+        match self.negative_style {
+            NegativeStyle::MinusSign => format!("-{number}"),
+            NegativeStyle::Parentheses => format!("({number})"),
+        }
+    }
+
+    fn group(&self, integer: &str) -> String {
+        let digits: Vec<char> = integer.chars().collect();
+        // Sizes of the digit groups from the right
+        let (first, rest) = match self.grouping {
+            DigitGrouping::Indian => (3, 2),
+            DigitGrouping::Western => (3, 3),
+            DigitGrouping::None => return integer.to_string(),
+        };
+
+        let mut groups = Vec::new();
+        let mut end = digits.len();
+        let mut size = first;
+        while end > size {
+            groups.push(digits[end - size..end].iter().collect::<String>());
+            end -= size;
+            size = rest;
+        }
+        groups.push(digits[..end].iter().collect());
+        groups.reverse();
+        groups.join(&self.group_separator.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_amount_formats() {
+        let amount = |s| BigDecimal::from_str(s).unwrap();
+        let indian = Formatter::indian();
+        assert_eq!(indian.format(&amount("1234567.5")), "₹12,34,567.50");
+        assert_eq!(indian.format(&amount("999")), "₹999.00");
+        assert_eq!(indian.format(&amount("0.005")), "₹0.01");
+        assert_eq!(indian.format(&amount("0")), "₹0.00");
+        assert_eq!(indian.format(&amount("-1000")), "-₹1,000.00");
+
+        let western = Formatter::western("$");
+        assert_eq!(western.format(&amount("1234567.5")), "$1,234,567.50");
+
+        let printed = Formatter {
+            decimal_places: 0,
+            symbol_placement: SymbolPlacement::After,
+            negative_style: NegativeStyle::Parentheses,
+            group_separator: '.',
+            decimal_separator: ',',
+            ..Formatter::western("€")
+        };
+        assert_eq!(printed.format(&amount("-1234567.49")), "(1.234.567 €)");
+        assert_eq!(
+            indian.without_symbol().format(&amount("100000")),
+            "1,00,000.00"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Translations of report labels and standard account names
+//!
+//! Rendered reports look their labels up by key in a [`Translations`] map
+//! instead of hard-coding English. English and Hindi bundles ship with the
+//! crate; other languages can be built with [`Translations::new`] and
+//! [`Translations::insert`], and any key a bundle lacks falls back to
+//! English.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::ledger::STANDARD_ACCOUNT_KEY;
+use crate::types::*;
+
+/// Labels used in rendered reports
+///
+/// Titles take `{date}`, `{start}` and `{end}` placeholders and section
+/// totals take `{section}`, so each language can order the words its own way.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Label {
+    TrialBalanceTitle,
+    BalanceSheetTitle,
+    IncomeStatementTitle,
+    Account,
+    Debit,
+    Credit,
+    Total,
+    SectionTotal,
+    Assets,
+    Liabilities,
+    Equity,
+    Revenue,
+    Expenses,
+    NetIncome,
+    CurrentEarnings,
+    AccountType(AccountType),
+}
+
+impl Label {
+    /// Key the label is stored under
+    pub fn key(&self) -> &'static str {
+        match self {
+            Label::TrialBalanceTitle => "trial_balance.title",
+            Label::BalanceSheetTitle => "balance_sheet.title",
+            Label::IncomeStatementTitle => "income_statement.title",
+            Label::Account => "report.account",
+            Label::Debit => "report.debit",
+            Label::Credit => "report.credit",
+            Label::Total => "report.total",
+            Label::SectionTotal => "report.section_total",
+            Label::Assets => "report.assets",
+            Label::Liabilities => "report.liabilities",
+            Label::Equity => "report.equity",
+            Label::Revenue => "report.revenue",
+            Label::Expenses => "report.expenses",
+            Label::NetIncome => "report.net_income",
+            Label::CurrentEarnings => "report.current_earnings",
+            Label::AccountType(AccountType::Asset) => "account_type.asset",
+            Label::AccountType(AccountType::Liability) => "account_type.liability",
+            Label::AccountType(AccountType::Equity) => "account_type.equity",
+            Label::AccountType(AccountType::Income) => "account_type.income",
+            Label::AccountType(AccountType::Expense) => "account_type.expense",
+        }
+    }
+}
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("trial_balance.title", "Trial Balance as of {date}"),
+    ("balance_sheet.title", "Balance Sheet as of {date}"),
+    (
+        "income_statement.title",
+        "Income Statement from {start} to {end}",
+    ),
+    ("report.account", "Account"),
+    ("report.debit", "Debit"),
+    ("report.credit", "Credit"),
+    ("report.total", "Total"),
+    ("report.section_total", "Total {section}"),
+    ("report.assets", "Assets"),
+    ("report.liabilities", "Liabilities"),
+    ("report.equity", "Equity"),
+    ("report.revenue", "Revenue"),
+    ("report.expenses", "Expenses"),
+    ("report.net_income", "Net Income"),
+    ("report.current_earnings", "Current Earnings"),
+    ("account_type.asset", "Asset"),
+    ("account_type.liability", "Liability"),
+    ("account_type.equity", "Equity"),
+    ("account_type.income", "Income"),
+    ("account_type.expense", "Expense"),
+    ("account.cash", "Cash"),
+    ("account.accounts_receivable", "Accounts Receivable"),
+    ("account.inventory", "Inventory"),
+    ("account.accounts_payable", "Accounts Payable"),
+    ("account.loans_payable", "Loans Payable"),
+    ("account.owners_equity", "Owner's Equity"),
+    ("account.retained_earnings", "Retained Earnings"),
+    ("account.sales_revenue", "Sales Revenue"),
+    ("account.service_revenue", "Service Revenue"),
+    ("account.cost_of_goods_sold", "Cost of Goods Sold"),
+    ("account.rent_expense", "Rent Expense"),
+    ("account.utilities_expense", "Utilities Expense"),
+];
+
+const HINDI: &[(&str, &str)] = &[
+    ("trial_balance.title", "{date} तक का तलपट"),
+    ("balance_sheet.title", "{date} को तुलन पत्र"),
+    ("income_statement.title", "{start} से {end} तक का आय विवरण"),
+    ("report.account", "खाता"),
+    ("report.debit", "नामे"),
+    ("report.credit", "जमा"),
+    ("report.total", "कुल"),
+    ("report.section_total", "कुल {section}"),
+    ("report.assets", "परिसंपत्तियाँ"),
+    ("report.liabilities", "देयताएँ"),
+    ("report.equity", "पूँजी"),
+    ("report.revenue", "राजस्व"),
+    ("report.expenses", "व्यय"),
+    ("report.net_income", "शुद्ध आय"),
+    ("report.current_earnings", "चालू अवधि की आय"),
+    ("account_type.asset", "परिसंपत्ति"),
+    ("account_type.liability", "देयता"),
+    ("account_type.equity", "पूँजी"),
+    ("account_type.income", "आय"),
+    ("account_type.expense", "व्यय"),
+    ("account.cash", "नकद"),
+    ("account.accounts_receivable", "प्राप्य खाते"),
+    ("account.inventory", "माल सूची"),
+    ("account.accounts_payable", "देय खाते"),
+    ("account.loans_payable", "देय ऋण"),
+    ("account.owners_equity", "स्वामी की पूँजी"),
+    ("account.retained_earnings", "प्रतिधारित आय"),
+    ("account.sales_revenue", "विक्रय राजस्व"),
+    ("account.service_revenue", "सेवा राजस्व"),
+    ("account.cost_of_goods_sold", "बेचे गए माल की लागत"),
+    ("account.rent_expense", "किराया व्यय"),
+    ("account.utilities_expense", "उपयोगिता व्यय"),
+];
+
+fn english(key: &str) -> Option<&'static str> {
+    ENGLISH
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, text)| *text)
+}
+
+/// Label texts for one language
+#[derive(Debug, Clone, PartialEq)]
+pub struct Translations {
+    /// Language tag, such as `en` or `hi`
+    pub locale: String,
+    labels: HashMap<String, String>,
+}
+
+impl Default for Translations {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+impl Translations {
+    /// An empty bundle; every lookup falls back to English
+    pub fn new(locale: &str) -> Self {
+        Self {
+            locale: locale.to_string(),
+            labels: HashMap::new(),
+        }
+    }
+
+    fn from_pairs(locale: &str, pairs: &[(&str, &str)]) -> Self {
+        let mut translations = Self::new(locale);
+        for (key, text) in pairs {
+            translations.insert(key, text);
+        }
+        translations
+    }
+
+    /// The English bundle
+    pub fn english() -> Self {
+        Self::from_pairs("en", ENGLISH)
+    }
+
+    /// The Hindi bundle
+    pub fn hindi() -> Self {
+        Self::from_pairs("hi", HINDI)
+    }
+
+    /// A shipped bundle by language tag (`en`, `hi`, `hi-IN`, ...)
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        match locale.split(['-', '_']).next()? {
+            "en" => Some(Self::english()),
+            "hi" => Some(Self::hindi()),
+            _ => None,
+        }
+    }
+
+    /// Add or replace the text of a key
+    pub fn insert(&mut self, key: &str, text: &str) {
+        self.labels.insert(key.to_string(), text.to_string());
+    }
+
+    /// Text of a key, falling back to English and then to the key itself
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.labels
+            .get(key)
+            .map(String::as_str)
+            .or_else(|| english(key))
+            .unwrap_or(key)
+    }
+
+    /// Text of a label
+    pub fn label(&self, label: Label) -> &str {
+        self.get(label.key())
+    }
+
+    /// Text of a label with `{name}` placeholders filled in
+    pub fn format(&self, label: Label, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.label(label).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), value)
+            })
+    }
+
+    /// Display name of an account
+    ///
+    /// Accounts created from the standard chart are shown under the
+    /// translated template name, unless they have been renamed since.
+    pub fn account_name<'a>(&self, account: &'a Account) -> Cow<'a, str> {
+        let Some(template) = account
+            .metadata
+            .get(STANDARD_ACCOUNT_KEY)
+            .and_then(MetaValue::as_str)
+        else {
+            return Cow::Borrowed(&account.name);
+        };
+        let key = format!("account.{template}");
+        match english(&key) {
+            Some(original) if original == account.name => Cow::Owned(self.get(&key).to_string()),
+            _ => Cow::Borrowed(&account.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundles_cover_every_key() {
+        let hindi = Translations::hindi();
+        for (key, _) in ENGLISH {
+            assert!(HINDI.iter().any(|(k, _)| k == key), "no Hindi for {key}");
+        }
+        assert_eq!(
+            hindi.format(Label::TrialBalanceTitle, &[("date", "2025-03-31")]),
+            "2025-03-31 तक का तलपट"
+        );
+        assert_eq!(
+            Translations::for_locale("hi-IN")
+                .unwrap()
+                .label(Label::Debit),
+            "नामे"
+        );
+
+        let mut marathi = Translations::new("mr");
+        marathi.insert(Label::Debit.key(), "नावे");
+        assert_eq!(marathi.label(Label::Debit), "नावे");
+        assert_eq!(marathi.label(Label::Credit), "Credit");
+    }
+}
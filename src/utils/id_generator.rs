@@ -0,0 +1,214 @@
+//! Id generation strategies for transactions created without an id
+//!
+//! - [`UuidV7Generator`]: RFC 9562 UUIDv7, time-ordered and unique across
+//!   processes; the ledger's default
+//! - [`UlidGenerator`]: 26-character ULIDs, time-ordered and monotonic
+//!   within a millisecond
+//! - [`SequenceIdGenerator`]: a prefixed counter such as `JV-000042`,
+//!   unique only within one generator
+//!
+//! The time-based generators take the time from a [`Clock`]; the ledger's
+//! default generator follows the ledger's clock. Ids from one generator
+//! sort in the order they were made, even under a [`FixedClock`], but
+//! their random bits differ between runs: use a [`SequenceIdGenerator`]
+//! when a replay must reproduce the same ids.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use uuid::{ContextV7, Timestamp, Uuid};
+
+use crate::traits::*;
+
+/// Generates UUIDv7 ids
+///
+/// Ids made in the same millisecond carry an increasing counter, so they
+/// stay strictly ordered; clones share the counter.
+#[derive(Debug, Clone)]
+pub struct UuidV7Generator {
+    clock: Arc<dyn Clock>,
+    context: Arc<Mutex<ContextV7>>,
+}
+
+impl UuidV7Generator {
+    /// Generator stamping ids with the system time
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Generator stamping ids with the time read from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            context: Arc::new(Mutex::new(ContextV7::new())),
+        }
+    }
+}
+
+impl Default for UuidV7Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for UuidV7Generator {
+    fn next_id(&self) -> String {
+        let now = self.clock.now();
+        let timestamp = Timestamp::from_unix(
+            self.context.as_ref(),
+            now.timestamp().max(0) as u64,
+            now.timestamp_subsec_nanos(),
+        );
+        Uuid::new_v7(timestamp).to_string()
+    }
+}
+
+/// Crockford base32 alphabet used by ULIDs
+const ULID_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Mask for the 80 random bits of a ULID
+const ULID_RANDOM_MASK: u128 = (1 << 80) - 1;
+
+/// Generates ULIDs
+///
+/// Ids made in the same millisecond increment the random part of the
+/// previous one, so they stay strictly ordered; a clock that moves
+/// backwards keeps the last millisecond seen.
+#[derive(Debug)]
+pub struct UlidGenerator {
+    clock: Arc<dyn Clock>,
+    /// Millisecond and random part of the last id
+    last: Mutex<(u64, u128)>,
+}
+
+impl UlidGenerator {
+    /// Generator stamping ids with the system time
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Generator stamping ids with the time read from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            last: Mutex::new((0, 0)),
+        }
+    }
+
+    fn random_bits() -> u128 {
+        // The last six bytes and first four bytes of a v4 uuid are random
+        let bytes = Uuid::new_v4().into_bytes();
+        let mut random = [0u8; 16];
+        random[6..12].copy_from_slice(&bytes[10..16]);
+        random[12..16].copy_from_slice(&bytes[0..4]);
+        u128::from_be_bytes(random)
+    }
+
+    fn encode(value: u128) -> String {
+        (0..26)
+            .rev()
+            .map(|i| ULID_ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
+            .collect()
+    }
+}
+
+impl Default for UlidGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for UlidGenerator {
+    fn next_id(&self) -> String {
+        let now = self.clock.now().timestamp_millis().max(0) as u64 & ((1 << 48) - 1);
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        let (millis, random) = if now > last.0 {
+            (now, Self::random_bits())
+        } else if last.1 < ULID_RANDOM_MASK {
+            (last.0, last.1 + 1)
+        } else {
+            // The random part is exhausted for this millisecond
+            (last.0 + 1, Self::random_bits())
+        };
+        *last = (millis, random);
+        Self::encode(((millis as u128) << 80) | random)
+    }
+}
+
+/// Generates ids from a prefixed counter, zero-padded to six digits
+///
+/// The counter lives in memory: start each process past the highest id
+/// already stored with [`SequenceIdGenerator::starting_at`], and prefer
+/// the time-based generators when several processes write to one ledger.
+#[derive(Debug)]
+pub struct SequenceIdGenerator {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl SequenceIdGenerator {
+    /// Generator counting from 1
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self::starting_at(prefix, 1)
+    }
+
+    /// Generator counting from `first`
+    pub fn starting_at(prefix: impl Into<String>, first: u64) -> Self {
+        Self {
+            prefix: prefix.into(),
+            next: AtomicU64::new(first),
+        }
+    }
+}
+
+impl IdGenerator for SequenceIdGenerator {
+    fn next_id(&self) -> String {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}{:06}", self.prefix, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_generators_produce_ordered_unique_ids() {
+        let clock = FixedClock::new(Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap());
+
+        let uuids = UuidV7Generator::with_clock(Arc::new(clock.clone()));
+        let first = Uuid::parse_str(&uuids.next_id()).unwrap();
+        assert_eq!(first.get_version_num(), 7);
+        clock.advance(chrono::TimeDelta::milliseconds(1));
+        let second = uuids.next_id();
+        assert!(first.to_string() < second);
+        // Within one millisecond the counter keeps them ordered
+        let ids: Vec<String> = (0..100).map(|_| uuids.next_id()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let ulids = UlidGenerator::with_clock(Arc::new(clock.clone()));
+        let ids: Vec<String> = (0..100).map(|_| ulids.next_id()).collect();
+        assert!(ids.iter().all(|id| id.len() == 26));
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        // The first ten characters encode the millisecond timestamp
+        assert!(ids.iter().all(|id| id[..10] == ids[0][..10]));
+        clock.advance(chrono::TimeDelta::milliseconds(1));
+        assert!(ulids.next_id()[..10] > ids[0][..10]);
+
+        let sequence = Arc::new(SequenceIdGenerator::starting_at("JV-", 41));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let sequence = sequence.clone();
+                std::thread::spawn(move || (0..25).map(|_| sequence.next_id()).collect::<Vec<_>>())
+            })
+            .collect();
+        let ids: HashSet<String> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 100);
+        assert!(ids.contains("JV-000041") && ids.contains("JV-000140"));
+    }
+}
@@ -0,0 +1,215 @@
+//! Storage wrapper emitting tracing spans and metrics
+//!
+//! [`InstrumentedStorage`] runs every storage call inside a `tracing` span
+//! named `ledger.storage` carrying the operation name, and records through
+//! the `metrics` facade:
+//!
+//! - `ledger_storage_operations_total` — counter per `operation`
+//! - `ledger_storage_errors_total` — counter per `operation` and error `code`
+//! - `ledger_storage_duration_seconds` — histogram per `operation`
+//!
+//! Install any `tracing` subscriber and `metrics` recorder to collect them.
+//! Available with the `telemetry` feature.
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::future::Future;
+use tracing::Instrument;
+use web_time::Instant;
+
+use crate::traits::*;
+use crate::types::*;
+
+/// Run a storage call inside a span and record its outcome
+async fn observe<T>(
+    operation: &'static str,
+    call: impl Future<Output = LedgerResult<T>>,
+) -> LedgerResult<T> {
+    let span = tracing::debug_span!("ledger.storage", operation);
+    let started = Instant::now();
+    let result = call.instrument(span).await;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    metrics::counter!("ledger_storage_operations_total", "operation" => operation).increment(1);
+    metrics::histogram!("ledger_storage_duration_seconds", "operation" => operation)
+        .record(elapsed);
+    if let Err(error) = &result {
+        metrics::counter!(
+            "ledger_storage_errors_total",
+            "operation" => operation,
+            "code" => error.code()
+        )
+        .increment(1);
+        tracing::warn!(operation, code = error.code(), %error, "storage operation failed");
+    }
+    result
+}
+
+/// Storage wrapper reporting spans, latencies and error rates
+#[derive(Debug, Clone)]
+pub struct InstrumentedStorage<S: LedgerStorage> {
+    inner: S,
+}
+
+impl<S: LedgerStorage> InstrumentedStorage<S> {
+    /// Wrap a backend
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<S: LedgerStorage> LedgerStorage for InstrumentedStorage<S> {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        observe("save_account", self.inner.save_account(account)).await
+    }
+
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        observe("save_accounts", self.inner.save_accounts(accounts)).await
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        observe("get_account", self.inner.get_account(account_id)).await
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        observe("list_accounts", self.inner.list_accounts(account_type)).await
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        observe("update_account", self.inner.update_account(account)).await
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        observe("delete_account", self.inner.delete_account(account_id)).await
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        observe("save_transaction", self.inner.save_transaction(transaction)).await
+    }
+
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        observe(
+            "replace_transactions",
+            self.inner.replace_transactions(removed, replacement),
+        )
+        .await
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        observe(
+            "get_transaction",
+            self.inner.get_transaction(transaction_id),
+        )
+        .await
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        observe(
+            "get_account_transactions",
+            self.inner
+                .get_account_transactions(account_id, start_date, end_date),
+        )
+        .await
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        observe(
+            "get_transactions",
+            self.inner.get_transactions(start_date, end_date),
+        )
+        .await
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        observe(
+            "update_transaction",
+            self.inner.update_transaction(transaction),
+        )
+        .await
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        observe(
+            "delete_transaction",
+            self.inner.delete_transaction(transaction_id),
+        )
+        .await
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        observe(
+            "get_account_balance",
+            self.inner.get_account_balance(account_id, as_of_date),
+        )
+        .await
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        observe(
+            "get_trial_balance",
+            self.inner.get_trial_balance(as_of_date),
+        )
+        .await
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        observe(
+            "get_account_balances_by_type",
+            self.inner.get_account_balances_by_type(as_of_date),
+        )
+        .await
+    }
+
+    fn reporting(&self) -> Option<&dyn ReportingStorage> {
+        self.inner.reporting()
+    }
+
+    async fn get_entries(&self, filter: &EntryFilter) -> LedgerResult<Vec<EntryRow>> {
+        observe("get_entries", self.inner.get_entries(filter)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::Ledger;
+    use crate::utils::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_instrumented_storage_is_transparent() {
+        let mut ledger = Ledger::new(InstrumentedStorage::new(MemoryStorage::new()));
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        assert!(ledger.get_account("1000").await.unwrap().is_some());
+
+        let error = ledger.delete_transaction("missing").await.unwrap_err();
+        assert_eq!(error.code(), "transaction_not_found");
+    }
+}
@@ -3,17 +3,141 @@
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use chrono::NaiveDate;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use crate::traits::*;
 use crate::types::*;
 
+type AccountMap = Arc<RwLock<HashMap<String, Account>>>;
+type TransactionMap = Arc<RwLock<TransactionStore>>;
+type AttachmentMap = Arc<RwLock<HashMap<String, (Attachment, Vec<u8>)>>>;
+
+// A panic while a lock is held poisons it. Each insert, replace or remove
+// below handles a whole value and its index entries, and cannot panic short
+// of running out of memory. Batch writes such as `save_accounts` loop over
+// their values, so a panic there could leave part of a batch saved, but
+// every stored value is still whole: take the data back rather than failing
+// every later call on the storage.
+
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Transactions by ID, indexed by date and by the accounts they post to so
+/// range and account queries skip unrelated transactions
+#[derive(Debug, Default)]
+struct TransactionStore {
+    by_id: HashMap<String, Transaction>,
+    by_date: BTreeMap<NaiveDate, BTreeSet<String>>,
+    by_account: HashMap<String, BTreeSet<String>>,
+}
+
+impl TransactionStore {
+    fn get(&self, id: &str) -> Option<&Transaction> {
+        self.by_id.get(id)
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.by_id.contains_key(id)
+    }
+
+    /// Store a transaction, replacing any with the same ID
+    fn insert(&mut self, transaction: Transaction) {
+        self.remove(&transaction.id);
+        self.by_date
+            .entry(transaction.date)
+            .or_default()
+            .insert(transaction.id.clone());
+        for entry in &transaction.entries {
+            self.by_account
+                .entry(entry.account_id.clone())
+                .or_default()
+                .insert(transaction.id.clone());
+        }
+        self.by_id.insert(transaction.id.clone(), transaction);
+    }
+
+    fn remove(&mut self, id: &str) -> Option<Transaction> {
+        let transaction = self.by_id.remove(id)?;
+        if let Some(ids) = self.by_date.get_mut(&transaction.date) {
+            ids.remove(id);
+            if ids.is_empty() {
+                self.by_date.remove(&transaction.date);
+            }
+        }
+        for entry in &transaction.entries {
+            if let Some(ids) = self.by_account.get_mut(&entry.account_id) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.by_account.remove(&entry.account_id);
+                }
+            }
+        }
+        Some(transaction)
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Transactions dated within the bounds, by date then ID
+    fn between(&self, start: Option<NaiveDate>, end: Option<NaiveDate>) -> Vec<Transaction> {
+        // BTreeMap::range panics on an inverted range
+        if matches!((start, end), (Some(start), Some(end)) if start > end) {
+            return Vec::new();
+        }
+        let range = (
+            start.map_or(Bound::Unbounded, Bound::Included),
+            end.map_or(Bound::Unbounded, Bound::Included),
+        );
+        self.by_date
+            .range(range)
+            .flat_map(|(_, ids)| ids)
+            .filter_map(|id| self.by_id.get(id))
+            .cloned()
+            .collect()
+    }
+
+    /// Transactions posting to an account and dated within the bounds, by
+    /// date then ID
+    fn for_account(
+        &self,
+        account_id: &str,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> Vec<Transaction> {
+        let Some(ids) = self.by_account.get(account_id) else {
+            return Vec::new();
+        };
+        let mut transactions: Vec<Transaction> = ids
+            .iter()
+            .filter_map(|id| self.by_id.get(id))
+            .filter(|txn| start.is_none_or(|start| txn.date >= start))
+            .filter(|txn| end.is_none_or(|end| txn.date <= end))
+            .cloned()
+            .collect();
+        transactions.sort_by(|a, b| (a.date, &a.id).cmp(&(b.date, &b.id)));
+        transactions
+    }
+}
+
 /// In-memory storage implementation for testing and development
+///
+/// Each tenant gets its own maps; handles returned by
+/// [`TenantStorage::for_tenant`] share the tenant registry but only touch
+/// their tenant's data.
 #[derive(Debug, Clone)]
 pub struct MemoryStorage {
-    accounts: Arc<RwLock<HashMap<String, Account>>>,
-    transactions: Arc<RwLock<HashMap<String, Transaction>>>,
+    accounts: AccountMap,
+    transactions: TransactionMap,
+    tenant: Option<TenantId>,
+    tenants: Arc<RwLock<HashMap<TenantId, (AccountMap, TransactionMap)>>>,
 }
 
 impl MemoryStorage {
@@ -21,14 +145,21 @@ impl MemoryStorage {
     pub fn new() -> Self {
         Self {
             accounts: Arc::new(RwLock::new(HashMap::new())),
-            transactions: Arc::new(RwLock::new(HashMap::new())),
+            transactions: Arc::new(RwLock::new(TransactionStore::default())),
+            tenant: None,
+            tenants: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    /// Clear all data (useful for testing)
+    /// Tenants that have been given storage, in no particular order
+    pub fn tenants(&self) -> Vec<TenantId> {
+        read(&self.tenants).keys().cloned().collect()
+    }
+
+    /// Clear all data in this handle's partition (useful for testing)
     pub fn clear(&self) {
-        self.accounts.write().unwrap().clear();
-        self.transactions.write().unwrap().clear();
+        write(&self.accounts).clear();
+        write(&self.transactions).clear();
     }
 }
 
@@ -38,22 +169,52 @@ impl Default for MemoryStorage {
     }
 }
 
+impl TenantStorage for MemoryStorage {
+    fn for_tenant(&self, tenant: &TenantId) -> LedgerResult<Self> {
+        let (accounts, transactions) = write(&self.tenants)
+            .entry(tenant.clone())
+            .or_insert_with(|| {
+                (
+                    Arc::new(RwLock::new(HashMap::new())),
+                    Arc::new(RwLock::new(TransactionStore::default())),
+                )
+            })
+            .clone();
+
+        Ok(Self {
+            accounts,
+            transactions,
+            tenant: Some(tenant.clone()),
+            tenants: self.tenants.clone(),
+        })
+    }
+
+    fn tenant(&self) -> Option<&TenantId> {
+        self.tenant.as_ref()
+    }
+}
+
 #[async_trait]
 impl LedgerStorage for MemoryStorage {
     async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
-        self.accounts
-            .write()
-            .unwrap()
-            .insert(account.id.clone(), account.clone());
+        write(&self.accounts).insert(account.id.clone(), account.clone());
+        Ok(())
+    }
+
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        let mut stored = write(&self.accounts);
+        for account in accounts {
+            stored.insert(account.id.clone(), account.clone());
+        }
         Ok(())
     }
 
     async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
-        Ok(self.accounts.read().unwrap().get(account_id).cloned())
+        Ok(read(&self.accounts).get(account_id).cloned())
     }
 
     async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
-        let accounts = self.accounts.read().unwrap();
+        let accounts = read(&self.accounts);
         let filtered: Vec<Account> = accounts
             .values()
             .filter(|account| {
@@ -67,19 +228,17 @@ impl LedgerStorage for MemoryStorage {
     }
 
     async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
-        if self.accounts.read().unwrap().contains_key(&account.id) {
-            self.accounts
-                .write()
-                .unwrap()
-                .insert(account.id.clone(), account.clone());
-            Ok(())
-        } else {
-            Err(LedgerError::AccountNotFound(account.id.clone()))
+        match write(&self.accounts).get_mut(&account.id) {
+            Some(stored) => {
+                *stored = account.clone();
+                Ok(())
+            }
+            None => Err(LedgerError::AccountNotFound(account.id.clone())),
         }
     }
 
     async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
-        if self.accounts.write().unwrap().remove(account_id).is_some() {
+        if write(&self.accounts).remove(account_id).is_some() {
             Ok(())
         } else {
             Err(LedgerError::AccountNotFound(account_id.to_string()))
@@ -87,20 +246,28 @@ impl LedgerStorage for MemoryStorage {
     }
 
     async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
-        self.transactions
-            .write()
-            .unwrap()
-            .insert(transaction.id.clone(), transaction.clone());
+        write(&self.transactions).insert(transaction.clone());
+        Ok(())
+    }
+
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        let mut stored = write(&self.transactions);
+        if let Some(missing) = removed.iter().find(|t| stored.get(&t.id).is_none()) {
+            return Err(LedgerError::TransactionNotFound(missing.id.clone()));
+        }
+        for transaction in removed {
+            stored.remove(&transaction.id);
+        }
+        stored.insert(replacement.clone());
         Ok(())
     }
 
     async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
-        Ok(self
-            .transactions
-            .read()
-            .unwrap()
-            .get(transaction_id)
-            .cloned())
+        Ok(read(&self.transactions).get(transaction_id).cloned())
     }
 
     async fn get_account_transactions(
@@ -109,36 +276,7 @@ impl LedgerStorage for MemoryStorage {
         start_date: Option<NaiveDate>,
         end_date: Option<NaiveDate>,
     ) -> LedgerResult<Vec<Transaction>> {
-        let transactions = self.transactions.read().unwrap();
-        let filtered: Vec<Transaction> = transactions
-            .values()
-            .filter(|txn| {
-                // Check if transaction affects the account
-                let affects_account = txn
-                    .entries
-                    .iter()
-                    .any(|entry| entry.account_id == account_id);
-                if !affects_account {
-                    return false;
-                }
-
-                // Check date range
-                if let Some(start) = start_date {
-                    if txn.date < start {
-                        return false;
-                    }
-                }
-                if let Some(end) = end_date {
-                    if txn.date > end {
-                        return false;
-                    }
-                }
-
-                true
-            })
-            .cloned()
-            .collect();
-        Ok(filtered)
+        Ok(read(&self.transactions).for_account(account_id, start_date, end_date))
     }
 
     async fn get_transactions(
@@ -146,38 +284,13 @@ impl LedgerStorage for MemoryStorage {
         start_date: Option<NaiveDate>,
         end_date: Option<NaiveDate>,
     ) -> LedgerResult<Vec<Transaction>> {
-        let transactions = self.transactions.read().unwrap();
-        let filtered: Vec<Transaction> = transactions
-            .values()
-            .filter(|txn| {
-                if let Some(start) = start_date {
-                    if txn.date < start {
-                        return false;
-                    }
-                }
-                if let Some(end) = end_date {
-                    if txn.date > end {
-                        return false;
-                    }
-                }
-                true
-            })
-            .cloned()
-            .collect();
-        Ok(filtered)
+        Ok(read(&self.transactions).between(start_date, end_date))
     }
 
     async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
-        if self
-            .transactions
-            .read()
-            .unwrap()
-            .contains_key(&transaction.id)
-        {
-            self.transactions
-                .write()
-                .unwrap()
-                .insert(transaction.id.clone(), transaction.clone());
+        let mut transactions = write(&self.transactions);
+        if transactions.contains(&transaction.id) {
+            transactions.insert(transaction.clone());
             Ok(())
         } else {
             Err(LedgerError::TransactionNotFound(transaction.id.clone()))
@@ -185,13 +298,7 @@ impl LedgerStorage for MemoryStorage {
     }
 
     async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
-        if self
-            .transactions
-            .write()
-            .unwrap()
-            .remove(transaction_id)
-            .is_some()
-        {
+        if write(&self.transactions).remove(transaction_id).is_some() {
             Ok(())
         } else {
             Err(LedgerError::TransactionNotFound(transaction_id.to_string()))
@@ -219,19 +326,12 @@ impl LedgerStorage for MemoryStorage {
             .get_account_transactions(account_id, None, as_of_date)
             .await?;
 
-        for transaction in transactions {
-            for entry in transaction.entries {
+        for transaction in transactions.iter().filter(|t| t.is_posted()) {
+            for entry in &transaction.entries {
                 if entry.account_id == account_id {
-                    match (account.account_type.normal_balance(), entry.entry_type) {
-                        (EntryType::Debit, EntryType::Debit)
-                        | (EntryType::Credit, EntryType::Credit) => {
-                            balance += entry.amount;
-                        }
-                        (EntryType::Debit, EntryType::Credit)
-                        | (EntryType::Credit, EntryType::Debit) => {
-                            balance -= entry.amount;
-                        }
-                    }
+                    balance += account
+                        .account_type
+                        .balance_effect(&entry.entry_type, &entry.amount);
                 }
             }
         }
@@ -250,42 +350,13 @@ impl LedgerStorage for MemoryStorage {
                 .get_account_balance(&account.id, Some(as_of_date))
                 .await?;
 
-            let account_balance = match account.account_type.normal_balance() {
-                EntryType::Debit => {
-                    if balance >= BigDecimal::from(0) {
-                        total_debits += &balance;
-                        AccountBalance {
-                            account: account.clone(),
-                            debit_balance: Some(balance),
-                            credit_balance: None,
-                        }
-                    } else {
-                        total_credits += balance.abs();
-                        AccountBalance {
-                            account: account.clone(),
-                            debit_balance: None,
-                            credit_balance: Some(balance.abs()),
-                        }
-                    }
-                }
-                EntryType::Credit => {
-                    if balance >= BigDecimal::from(0) {
-                        total_credits += &balance;
-                        AccountBalance {
-                            account: account.clone(),
-                            debit_balance: None,
-                            credit_balance: Some(balance),
-                        }
-                    } else {
-                        total_debits += balance.abs();
-                        AccountBalance {
-                            account: account.clone(),
-                            debit_balance: Some(balance.abs()),
-                            credit_balance: None,
-                        }
-                    }
-                }
-            };
+            let account_balance = AccountBalance::from_balance(account.clone(), balance);
+            if let Some(debit) = &account_balance.debit_balance {
+                total_debits += debit;
+            }
+            if let Some(credit) = &account_balance.credit_balance {
+                total_credits += credit;
+            }
 
             balances.insert(account.id.clone(), account_balance);
         }
@@ -319,3 +390,182 @@ impl LedgerStorage for MemoryStorage {
         Ok(result)
     }
 }
+
+/// In-memory attachment store for testing and development
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAttachmentStorage {
+    attachments: AttachmentMap,
+}
+
+impl MemoryAttachmentStorage {
+    /// Create an empty attachment store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AttachmentStorage for MemoryAttachmentStorage {
+    async fn save_attachment(
+        &mut self,
+        attachment: &Attachment,
+        content: &[u8],
+    ) -> LedgerResult<()> {
+        write(&self.attachments).insert(
+            attachment.id.clone(),
+            (attachment.clone(), content.to_vec()),
+        );
+        Ok(())
+    }
+
+    async fn get_attachment(&self, attachment_id: &str) -> LedgerResult<Option<Attachment>> {
+        Ok(read(&self.attachments)
+            .get(attachment_id)
+            .map(|(attachment, _)| attachment.clone()))
+    }
+
+    async fn get_attachment_content(&self, attachment_id: &str) -> LedgerResult<Option<Vec<u8>>> {
+        Ok(read(&self.attachments)
+            .get(attachment_id)
+            .map(|(_, content)| content.clone()))
+    }
+
+    async fn delete_attachment(&mut self, attachment_id: &str) -> LedgerResult<()> {
+        write(&self.attachments)
+            .remove(attachment_id)
+            .map(|_| ())
+            .ok_or_else(|| {
+                LedgerError::Validation(format!("Attachment '{}' not found", attachment_id))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tenant_partitions_are_isolated() {
+        let storage = MemoryStorage::new();
+        let mut tenant_a = storage.for_tenant(&TenantId::new("a")).unwrap();
+        let tenant_b = storage.for_tenant(&TenantId::new("b")).unwrap();
+
+        tenant_a
+            .save_account(&Account::new(
+                "1000".to_string(),
+                "Cash".to_string(),
+                AccountType::Asset,
+                None,
+            ))
+            .await
+            .unwrap();
+
+        assert!(tenant_a.get_account("1000").await.unwrap().is_some());
+        assert!(tenant_b.get_account("1000").await.unwrap().is_none());
+        assert!(storage.get_account("1000").await.unwrap().is_none());
+
+        // A second handle for the same tenant sees the same data
+        let tenant_a_again = storage.for_tenant(&TenantId::new("a")).unwrap();
+        assert!(tenant_a_again.get_account("1000").await.unwrap().is_some());
+        assert_eq!(tenant_a_again.tenant(), Some(&TenantId::new("a")));
+        assert_eq!(storage.tenants().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_indexes_follow_updates_and_deletes() {
+        let mut storage = MemoryStorage::new();
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let transfer = |id: &str, day, debit: &str| {
+            crate::ledger::TransactionBuilder::new(id.to_string(), date(day), id.to_string())
+                .debit(debit.to_string(), BigDecimal::from(10), None)
+                .credit("1000".to_string(), BigDecimal::from(10), None)
+                .build()
+                .unwrap()
+        };
+        for (id, day, debit) in [("T3", 3, "6000"), ("T1", 1, "6000"), ("T2", 2, "6100")] {
+            storage
+                .save_transaction(&transfer(id, day, debit))
+                .await
+                .unwrap();
+        }
+        let ids = |transactions: Vec<Transaction>| -> Vec<String> {
+            transactions.into_iter().map(|t| t.id).collect()
+        };
+
+        assert_eq!(
+            ids(storage.get_transactions(Some(date(2)), None).await.unwrap()),
+            vec!["T2", "T3"]
+        );
+        assert_eq!(
+            ids(storage
+                .get_account_transactions("6000", None, None)
+                .await
+                .unwrap()),
+            vec!["T1", "T3"]
+        );
+        assert!(storage
+            .get_transactions(Some(date(3)), Some(date(1)))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Moving T1 to another account and date re-indexes it
+        storage
+            .update_transaction(&transfer("T1", 4, "6100"))
+            .await
+            .unwrap();
+        storage.delete_transaction("T2").await.unwrap();
+        assert_eq!(
+            ids(storage
+                .get_account_transactions("6000", None, None)
+                .await
+                .unwrap()),
+            vec!["T3"]
+        );
+        assert_eq!(
+            ids(storage
+                .get_account_transactions("6100", None, None)
+                .await
+                .unwrap()),
+            vec!["T1"]
+        );
+        assert_eq!(
+            ids(storage.get_transactions(None, Some(date(2))).await.unwrap()),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            ids(storage
+                .get_account_transactions("1000", Some(date(3)), None)
+                .await
+                .unwrap()),
+            vec!["T3", "T1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_poisoned_lock_is_recovered() {
+        let mut storage = MemoryStorage::new();
+        let cash = Account::new(
+            "1000".to_string(),
+            "Cash".to_string(),
+            AccountType::Asset,
+            None,
+        );
+        storage.save_account(&cash).await.unwrap();
+
+        // A thread panicking while it writes poisons the lock
+        let accounts = storage.accounts.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = accounts.write().unwrap();
+            panic!("writer failed");
+        })
+        .join();
+        assert!(storage.accounts.is_poisoned());
+
+        assert!(storage.get_account("1000").await.unwrap().is_some());
+        let mut renamed = cash.clone();
+        renamed.name = "Cash in Hand".to_string();
+        storage.update_account(&renamed).await.unwrap();
+        assert_eq!(storage.list_accounts(None).await.unwrap(), vec![renamed]);
+    }
+}
@@ -0,0 +1,256 @@
+//! Metadata schemas for accounts
+//!
+//! A schema lists the metadata keys an account must or may carry, the type
+//! each value must have (or parse as, for text values), and optionally the
+//! values it may take. Schemas are registered per account type, optionally
+//! narrowed to a category given by the account's `category` metadata (e.g.
+//! bank accounts among assets).
+
+use std::collections::HashMap;
+
+use crate::traits::*;
+use crate::types::*;
+
+/// Metadata key that narrows an account type to a category
+pub const CATEGORY_METADATA_KEY: &str = "category";
+
+/// Type a metadata value must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataValueType {
+    Text,
+    Integer,
+    Decimal,
+    Boolean,
+    /// ISO 8601 date (YYYY-MM-DD)
+    Date,
+}
+
+impl MetadataValueType {
+    fn accepts(&self, value: &MetaValue) -> bool {
+        match self {
+            MetadataValueType::Text => value.as_str().is_some(),
+            MetadataValueType::Integer => value.as_i64().is_some(),
+            MetadataValueType::Decimal => value.as_decimal().is_some(),
+            MetadataValueType::Boolean => value.as_bool().is_some(),
+            MetadataValueType::Date => value.as_date().is_some(),
+        }
+    }
+}
+
+/// Rule for one metadata key
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataField {
+    pub key: String,
+    pub value_type: MetadataValueType,
+    pub required: bool,
+    /// Permitted values; `None` allows any value of the right type
+    pub allowed_values: Option<Vec<String>>,
+}
+
+/// Set of metadata rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataSchema {
+    fields: Vec<MetadataField>,
+}
+
+impl MetadataSchema {
+    /// Create an empty schema
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require a key with a value of the given type
+    pub fn required(mut self, key: &str, value_type: MetadataValueType) -> Self {
+        self.fields.push(MetadataField {
+            key: key.to_string(),
+            value_type,
+            required: true,
+            allowed_values: None,
+        });
+        self
+    }
+
+    /// Allow a key whose value, if present, has the given type
+    pub fn optional(mut self, key: &str, value_type: MetadataValueType) -> Self {
+        self.fields.push(MetadataField {
+            key: key.to_string(),
+            value_type,
+            required: false,
+            allowed_values: None,
+        });
+        self
+    }
+
+    /// Restrict a previously declared key to a set of values
+    pub fn allowed_values(mut self, key: &str, values: &[&str]) -> Self {
+        if let Some(field) = self.fields.iter_mut().find(|f| f.key == key) {
+            field.allowed_values = Some(values.iter().map(|v| v.to_string()).collect());
+        }
+        self
+    }
+
+    /// The schema's fields
+    pub fn fields(&self) -> &[MetadataField] {
+        &self.fields
+    }
+
+    /// Validate metadata against the schema
+    pub fn validate(&self, metadata: &Metadata) -> LedgerResult<()> {
+        for field in &self.fields {
+            let Some(value) = metadata.get(&field.key) else {
+                if field.required {
+                    return Err(LedgerError::Validation(format!(
+                        "Missing required metadata '{}'",
+                        field.key
+                    )));
+                }
+                continue;
+            };
+            if !field.value_type.accepts(value) {
+                return Err(LedgerError::Validation(format!(
+                    "Metadata '{}' must be {:?}, got '{}'",
+                    field.key, field.value_type, value
+                )));
+            }
+            if let Some(allowed) = &field.allowed_values {
+                if !allowed.contains(&value.to_string()) {
+                    return Err(LedgerError::Validation(format!(
+                        "Metadata '{}' must be one of {:?}, got '{}'",
+                        field.key, allowed, value
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Account validator that checks metadata against registered schemas after
+/// running an inner validator
+pub struct MetadataSchemaValidator {
+    inner: Box<dyn AccountValidator>,
+    schemas: HashMap<(AccountType, Option<String>), MetadataSchema>,
+}
+
+impl MetadataSchemaValidator {
+    /// Wrap an existing account validator
+    pub fn new(inner: Box<dyn AccountValidator>) -> Self {
+        Self {
+            inner,
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Register a schema for every account of a type
+    pub fn register(mut self, account_type: AccountType, schema: MetadataSchema) -> Self {
+        self.schemas.insert((account_type, None), schema);
+        self
+    }
+
+    /// Register a schema for accounts of a type whose `category` metadata
+    /// equals `category`
+    pub fn register_for_category(
+        mut self,
+        account_type: AccountType,
+        category: &str,
+        schema: MetadataSchema,
+    ) -> Self {
+        self.schemas
+            .insert((account_type, Some(category.to_string())), schema);
+        self
+    }
+}
+
+impl AccountValidator for MetadataSchemaValidator {
+    fn validate_account(&self, account: &Account) -> LedgerResult<()> {
+        self.inner.validate_account(account)?;
+
+        let mut applicable = vec![(account.account_type.clone(), None)];
+        if let Some(category) = account
+            .metadata
+            .get(CATEGORY_METADATA_KEY)
+            .and_then(|value| value.as_str())
+        {
+            applicable.push((account.account_type.clone(), Some(category.to_string())));
+        }
+        for key in &applicable {
+            if let Some(schema) = self.schemas.get(key) {
+                schema
+                    .validate(&account.metadata)
+                    .map_err(|error| match error {
+                        LedgerError::Validation(message) => LedgerError::Validation(format!(
+                            "Account '{}': {}",
+                            account.id, message
+                        )),
+                        other => other,
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_account_deletion(
+        &self,
+        account: &Account,
+        usage: &AccountUsage,
+    ) -> LedgerResult<()> {
+        self.inner.validate_account_deletion(account, usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bank_validator() -> MetadataSchemaValidator {
+        MetadataSchemaValidator::new(Box::new(DefaultAccountValidator)).register_for_category(
+            AccountType::Asset,
+            "bank",
+            MetadataSchema::new()
+                .required("ifsc", MetadataValueType::Text)
+                .required("account_number", MetadataValueType::Integer)
+                .optional("account_kind", MetadataValueType::Text)
+                .allowed_values("account_kind", &["current", "savings"]),
+        )
+    }
+
+    #[test]
+    fn test_bank_accounts_require_schema_metadata() {
+        let validator = bank_validator();
+        let mut account = Account::new(
+            "1010".to_string(),
+            "HDFC Current".to_string(),
+            AccountType::Asset,
+            None,
+        );
+
+        // Plain assets are unaffected
+        assert!(validator.validate_account(&account).is_ok());
+
+        account
+            .metadata
+            .insert("category".to_string(), "bank".into());
+        assert!(validator.validate_account(&account).is_err());
+
+        account
+            .metadata
+            .insert("ifsc".to_string(), "HDFC0000123".into());
+        account
+            .metadata
+            .insert("account_number".to_string(), "50100012345".into());
+        assert!(validator.validate_account(&account).is_ok());
+
+        account
+            .metadata
+            .insert("account_kind".to_string(), "overdraft".into());
+        assert!(validator.validate_account(&account).is_err());
+
+        account
+            .metadata
+            .insert("account_kind".to_string(), "current".into());
+        account
+            .metadata
+            .insert("account_number".to_string(), "ABC".into());
+        assert!(validator.validate_account(&account).is_err());
+    }
+}
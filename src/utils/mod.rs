@@ -1,7 +1,31 @@
 //! Utility modules
 
+pub mod aggregating_storage;
+pub mod cached_storage;
+pub mod encrypted_storage;
+pub mod event_sourced_storage;
+pub mod format;
+pub mod i18n;
+pub mod id_generator;
+#[cfg(feature = "telemetry")]
+pub mod instrumented_storage;
 pub mod memory_storage;
+pub mod metadata_schema;
+pub mod resilient_storage;
+pub mod text_report;
 pub mod validation;
 
+pub use aggregating_storage::*;
+pub use cached_storage::*;
+pub use encrypted_storage::*;
+pub use event_sourced_storage::*;
+pub use format::*;
+pub use i18n::*;
+pub use id_generator::*;
+#[cfg(feature = "telemetry")]
+pub use instrumented_storage::*;
 pub use memory_storage::*;
+pub use metadata_schema::*;
+pub use resilient_storage::*;
+pub use text_report::*;
 pub use validation::*;
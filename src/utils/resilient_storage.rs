@@ -0,0 +1,569 @@
+//! Storage wrapper with retries and a circuit breaker
+//!
+//! [`ResilientStorage`] retries calls that fail with a retryable error (see
+//! [`LedgerError::is_retryable`]) using exponential backoff with jitter, and
+//! stops calling a backend that keeps failing: after a run of consecutive
+//! retryable failures the circuit opens and calls fail immediately until the
+//! reset period has passed, when a single trial call decides whether it
+//! closes again; other calls fail immediately while the trial runs. Permanent errors are returned at once and do not count as
+//! failures.
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+
+use crate::traits::*;
+use crate::types::*;
+
+/// How failed calls are retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Backoff growth per attempt
+    pub multiplier: f64,
+    /// Fraction of each backoff, between 0 and 1, removed at random
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let growth = self.multiplier.powi(retry.saturating_sub(1) as i32);
+        let base = self.initial_backoff.mul_f64(growth).min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0) * random_fraction();
+        base.mul_f64(1.0 - jitter)
+    }
+}
+
+/// Uniform value in `[0, 1)` from the standard library's randomly keyed hasher
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// When the circuit opens and how long it stays open
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerPolicy {
+    /// Consecutive retryable failures that open the circuit
+    pub failure_threshold: u32,
+    /// Time the circuit stays open before a trial call is let through
+    pub reset_after: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            reset_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// State of the circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+    /// Calls fail immediately
+    Open,
+    /// A single trial call closes or reopens the circuit; other calls fail
+    /// immediately while it runs
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    policy: Option<CircuitBreakerPolicy>,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    half_open: bool,
+    /// Set by the one call let through while half open, until it finishes
+    trial_in_flight: AtomicBool,
+}
+
+impl Breaker {
+    fn state(&self) -> CircuitState {
+        match (&self.policy, self.opened_at) {
+            (Some(policy), Some(opened_at)) if opened_at.elapsed() < policy.reset_after => {
+                CircuitState::Open
+            }
+            (Some(_), Some(_)) => CircuitState::HalfOpen,
+            _ if self.half_open => CircuitState::HalfOpen,
+            _ => CircuitState::Closed,
+        }
+    }
+
+    /// Let a call through, returning whether it is the half-open trial
+    fn admit(&mut self) -> LedgerResult<bool> {
+        match self.state() {
+            CircuitState::Open => Err(LedgerError::transient_storage(
+                "Storage circuit is open after repeated failures",
+            )),
+            CircuitState::HalfOpen => {
+                if self
+                    .trial_in_flight
+                    .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                    .is_err()
+                {
+                    return Err(LedgerError::transient_storage(
+                        "Storage circuit is half-open and a trial call is in flight",
+                    ));
+                }
+                self.opened_at = None;
+                self.half_open = true;
+                Ok(true)
+            }
+            CircuitState::Closed => Ok(false),
+        }
+    }
+
+    fn record(&mut self, failed: bool, trial: bool) {
+        if trial {
+            self.trial_in_flight.store(false, Ordering::Release);
+        }
+        let Some(policy) = &self.policy else {
+            return;
+        };
+        if !failed {
+            self.consecutive_failures = 0;
+            self.half_open = false;
+            return;
+        }
+        self.consecutive_failures += 1;
+        if self.half_open || self.consecutive_failures >= policy.failure_threshold {
+            self.opened_at = Some(Instant::now());
+            self.consecutive_failures = 0;
+            self.half_open = false;
+        }
+    }
+}
+
+/// Storage wrapper retrying transient failures behind a circuit breaker
+#[derive(Clone)]
+pub struct ResilientStorage<S: LedgerStorage> {
+    inner: S,
+    retry: RetryPolicy,
+    sleeper: Arc<dyn Sleeper>,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl<S: LedgerStorage + std::fmt::Debug> std::fmt::Debug for ResilientStorage<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResilientStorage")
+            .field("inner", &self.inner)
+            .field("retry", &self.retry)
+            .field("circuit", &self.circuit_state())
+            .finish()
+    }
+}
+
+impl<S: LedgerStorage> ResilientStorage<S> {
+    /// Wrap a backend with the default retry policy and no circuit breaker,
+    /// waiting between attempts with `sleeper`
+    pub fn new(inner: S, sleeper: Arc<dyn Sleeper>) -> Self {
+        Self {
+            inner,
+            retry: RetryPolicy::default(),
+            sleeper,
+            breaker: Arc::new(Mutex::new(Breaker {
+                policy: None,
+                consecutive_failures: 0,
+                opened_at: None,
+                half_open: false,
+                trial_in_flight: AtomicBool::new(false),
+            })),
+        }
+    }
+
+    /// Use a different retry policy
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Open the circuit after repeated failures
+    pub fn with_circuit_breaker(self, policy: CircuitBreakerPolicy) -> Self {
+        self.breaker.lock().unwrap().policy = Some(policy);
+        self
+    }
+
+    /// The wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Current state of the circuit breaker
+    pub fn circuit_state(&self) -> CircuitState {
+        self.breaker.lock().unwrap().state()
+    }
+}
+
+/// Run a storage call under the retry and circuit-breaker policies
+///
+/// A macro rather than a function so the call expression, which may borrow
+/// the inner backend mutably, is rebuilt for every attempt.
+macro_rules! resilient {
+    ($storage:ident, $call:expr) => {{
+        let mut attempt = 1;
+        loop {
+            let trial = $storage.breaker.lock().unwrap().admit()?;
+            let result = $call.await;
+            let failed = matches!(&result, Err(error) if error.is_retryable());
+            $storage.breaker.lock().unwrap().record(failed, trial);
+            if failed && attempt < $storage.retry.max_attempts {
+                $storage.sleeper.sleep($storage.retry.backoff(attempt)).await;
+                attempt += 1;
+                continue;
+            }
+            break result;
+        }
+    }};
+}
+
+#[async_trait]
+impl<S: LedgerStorage> LedgerStorage for ResilientStorage<S> {
+    async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+        resilient!(self, self.inner.save_account(account))
+    }
+
+    async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+        resilient!(self, self.inner.save_accounts(accounts))
+    }
+
+    async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+        resilient!(self, self.inner.get_account(account_id))
+    }
+
+    async fn list_accounts(&self, account_type: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+        resilient!(self, self.inner.list_accounts(account_type.clone()))
+    }
+
+    async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+        resilient!(self, self.inner.update_account(account))
+    }
+
+    async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+        resilient!(self, self.inner.delete_account(account_id))
+    }
+
+    async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        resilient!(self, self.inner.save_transaction(transaction))
+    }
+
+    async fn replace_transactions(
+        &mut self,
+        removed: &[Transaction],
+        replacement: &Transaction,
+    ) -> LedgerResult<()> {
+        resilient!(self, self.inner.replace_transactions(removed, replacement))
+    }
+
+    async fn get_transaction(&self, transaction_id: &str) -> LedgerResult<Option<Transaction>> {
+        resilient!(self, self.inner.get_transaction(transaction_id))
+    }
+
+    async fn get_account_transactions(
+        &self,
+        account_id: &str,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        resilient!(
+            self,
+            self.inner
+                .get_account_transactions(account_id, start_date, end_date)
+        )
+    }
+
+    async fn get_transactions(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+    ) -> LedgerResult<Vec<Transaction>> {
+        resilient!(self, self.inner.get_transactions(start_date, end_date))
+    }
+
+    async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+        resilient!(self, self.inner.update_transaction(transaction))
+    }
+
+    async fn delete_transaction(&mut self, transaction_id: &str) -> LedgerResult<()> {
+        resilient!(self, self.inner.delete_transaction(transaction_id))
+    }
+
+    async fn get_account_balance(
+        &self,
+        account_id: &str,
+        as_of_date: Option<NaiveDate>,
+    ) -> LedgerResult<BigDecimal> {
+        resilient!(self, self.inner.get_account_balance(account_id, as_of_date))
+    }
+
+    async fn get_trial_balance(&self, as_of_date: NaiveDate) -> LedgerResult<TrialBalance> {
+        resilient!(self, self.inner.get_trial_balance(as_of_date))
+    }
+
+    async fn get_account_balances_by_type(
+        &self,
+        as_of_date: NaiveDate,
+    ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+        resilient!(self, self.inner.get_account_balances_by_type(as_of_date))
+    }
+
+    fn reporting(&self) -> Option<&dyn ReportingStorage> {
+        self.inner.reporting()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::patterns;
+    use crate::utils::{CachedStorage, MemoryStorage};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Backend whose account lookups fail transiently a set number of times
+    /// and which counts its atomic batch writes
+    #[derive(Debug, Clone)]
+    struct Flaky {
+        inner: MemoryStorage,
+        failures_left: Arc<AtomicU32>,
+        batch_writes: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl LedgerStorage for Flaky {
+        async fn save_account(&mut self, account: &Account) -> LedgerResult<()> {
+            self.inner.save_account(account).await
+        }
+        async fn save_accounts(&mut self, accounts: &[Account]) -> LedgerResult<()> {
+            self.batch_writes.fetch_add(1, Ordering::SeqCst);
+            self.inner.save_accounts(accounts).await
+        }
+        async fn replace_transactions(
+            &mut self,
+            removed: &[Transaction],
+            replacement: &Transaction,
+        ) -> LedgerResult<()> {
+            self.batch_writes.fetch_add(1, Ordering::SeqCst);
+            self.inner.replace_transactions(removed, replacement).await
+        }
+        async fn get_account(&self, account_id: &str) -> LedgerResult<Option<Account>> {
+            if self
+                .failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(LedgerError::transient_storage("connection reset"));
+            }
+            self.inner.get_account(account_id).await
+        }
+        async fn list_accounts(&self, t: Option<AccountType>) -> LedgerResult<Vec<Account>> {
+            self.inner.list_accounts(t).await
+        }
+        async fn update_account(&mut self, account: &Account) -> LedgerResult<()> {
+            self.inner.update_account(account).await
+        }
+        async fn delete_account(&mut self, account_id: &str) -> LedgerResult<()> {
+            self.inner.delete_account(account_id).await
+        }
+        async fn save_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+            self.inner.save_transaction(transaction).await
+        }
+        async fn get_transaction(&self, id: &str) -> LedgerResult<Option<Transaction>> {
+            self.inner.get_transaction(id).await
+        }
+        async fn get_account_transactions(
+            &self,
+            account_id: &str,
+            start: Option<NaiveDate>,
+            end: Option<NaiveDate>,
+        ) -> LedgerResult<Vec<Transaction>> {
+            self.inner
+                .get_account_transactions(account_id, start, end)
+                .await
+        }
+        async fn get_transactions(
+            &self,
+            start: Option<NaiveDate>,
+            end: Option<NaiveDate>,
+        ) -> LedgerResult<Vec<Transaction>> {
+            self.inner.get_transactions(start, end).await
+        }
+        async fn update_transaction(&mut self, transaction: &Transaction) -> LedgerResult<()> {
+            self.inner.update_transaction(transaction).await
+        }
+        async fn delete_transaction(&mut self, id: &str) -> LedgerResult<()> {
+            self.inner.delete_transaction(id).await
+        }
+        async fn get_account_balance(
+            &self,
+            account_id: &str,
+            as_of: Option<NaiveDate>,
+        ) -> LedgerResult<BigDecimal> {
+            self.inner.get_account_balance(account_id, as_of).await
+        }
+        async fn get_trial_balance(&self, as_of: NaiveDate) -> LedgerResult<TrialBalance> {
+            self.inner.get_trial_balance(as_of).await
+        }
+        async fn get_account_balances_by_type(
+            &self,
+            as_of: NaiveDate,
+        ) -> LedgerResult<HashMap<AccountType, Vec<AccountBalance>>> {
+            self.inner.get_account_balances_by_type(as_of).await
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSleeper {
+        delays: Mutex<Vec<Duration>>,
+    }
+
+    #[async_trait]
+    impl Sleeper for RecordingSleeper {
+        async fn sleep(&self, duration: Duration) {
+            self.delays.lock().unwrap().push(duration);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors_then_opens_circuit() {
+        let failures_left = Arc::new(AtomicU32::new(2));
+        let flaky = Flaky {
+            inner: MemoryStorage::new(),
+            failures_left: failures_left.clone(),
+            batch_writes: Arc::default(),
+        };
+        let sleeper = Arc::new(RecordingSleeper::default());
+        let storage = ResilientStorage::new(flaky, sleeper.clone())
+            .with_retry(RetryPolicy {
+                jitter: 0.0,
+                ..RetryPolicy::default()
+            })
+            .with_circuit_breaker(CircuitBreakerPolicy {
+                failure_threshold: 3,
+                reset_after: Duration::from_secs(60),
+            });
+
+        // Two blips are absorbed by the third attempt
+        assert!(storage.get_account("1000").await.unwrap().is_none());
+        assert_eq!(
+            *sleeper.delays.lock().unwrap(),
+            vec![Duration::from_millis(100), Duration::from_millis(200)]
+        );
+        assert_eq!(storage.circuit_state(), CircuitState::Closed);
+
+        // Three failures in a row open the circuit
+        failures_left.store(3, Ordering::SeqCst);
+        let error = storage.get_account("1000").await.unwrap_err();
+        assert!(error.is_retryable());
+        assert_eq!(storage.circuit_state(), CircuitState::Open);
+        assert!(storage.get_account("1000").await.is_err());
+        assert_eq!(failures_left.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_half_open_admits_a_single_trial() {
+        let mut breaker = Breaker {
+            policy: Some(CircuitBreakerPolicy {
+                failure_threshold: 1,
+                reset_after: Duration::ZERO,
+            }),
+            consecutive_failures: 0,
+            opened_at: None,
+            half_open: false,
+            trial_in_flight: AtomicBool::new(false),
+        };
+        breaker.record(true, false);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        // Callers arriving while the trial runs are turned away
+        assert!(breaker.admit().unwrap());
+        assert!(breaker.admit().unwrap_err().is_retryable());
+
+        // A failed trial reopens the circuit for the next single trial
+        breaker.record(true, true);
+        assert!(breaker.admit().unwrap());
+        assert!(breaker.admit().is_err());
+
+        // A successful one closes it for everyone
+        breaker.record(false, true);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(!breaker.admit().unwrap());
+        assert!(!breaker.admit().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_batch_writes_reach_the_backend_through_wrappers() {
+        let batch_writes = Arc::new(AtomicU32::new(0));
+        let flaky = Flaky {
+            inner: MemoryStorage::new(),
+            failures_left: Arc::default(),
+            batch_writes: batch_writes.clone(),
+        };
+        let resilient = ResilientStorage::new(flaky, Arc::new(RecordingSleeper::default()));
+        let mut storage = CachedStorage::new(resilient, 16);
+
+        let accounts = [
+            Account::new(
+                "1000".to_string(),
+                "Cash".to_string(),
+                AccountType::Asset,
+                None,
+            ),
+            Account::new(
+                "6000".to_string(),
+                "Rent".to_string(),
+                AccountType::Expense,
+                None,
+            ),
+        ];
+        storage.save_accounts(&accounts).await.unwrap();
+        assert_eq!(batch_writes.load(Ordering::SeqCst), 1);
+
+        let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        let rent = |id: &str, amount: u32| {
+            patterns::create_expense_payment(
+                id.to_string(),
+                date,
+                "Rent".to_string(),
+                "6000".to_string(),
+                "1000".to_string(),
+                BigDecimal::from(amount),
+            )
+            .unwrap()
+        };
+        let (first, second) = (rent("r1", 100), rent("r2", 50));
+        storage.save_transaction(&first).await.unwrap();
+        storage.save_transaction(&second).await.unwrap();
+        storage
+            .replace_transactions(&[first, second], &rent("r3", 150))
+            .await
+            .unwrap();
+        assert_eq!(batch_writes.load(Ordering::SeqCst), 2);
+        assert!(storage.get_transaction("r1").await.unwrap().is_none());
+        assert!(storage.get_transaction("r3").await.unwrap().is_some());
+    }
+}
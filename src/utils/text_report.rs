@@ -0,0 +1,265 @@
+//! Plain-text rendering of financial reports
+//!
+//! Produces fixed-width tables suitable for terminals, logs and plain-text
+//! email. Amounts are shown through a [`Formatter`] and labels and standard
+//! account names through [`Translations`].
+
+use bigdecimal::BigDecimal;
+use std::fmt::Write;
+
+use crate::traits::{BalanceSheet, IncomeStatement};
+use crate::types::*;
+use crate::utils::{Formatter, Label, Translations};
+
+/// Width of the account name column
+const NAME_WIDTH: usize = 36;
+/// Width of each amount column
+const AMOUNT_WIDTH: usize = 16;
+
+/// Renders reports as fixed-width text
+#[derive(Debug, Clone, Default)]
+pub struct TextReportRenderer {
+    pub formatter: Formatter,
+    pub translations: Translations,
+}
+
+impl TextReportRenderer {
+    pub fn new(formatter: Formatter) -> Self {
+        Self {
+            formatter,
+            translations: Translations::english(),
+        }
+    }
+
+    /// Render labels in another language
+    pub fn with_translations(mut self, translations: Translations) -> Self {
+        self.translations = translations;
+        self
+    }
+
+    fn label(&self, label: Label) -> &str {
+        self.translations.label(label)
+    }
+
+    fn amount(&self, amount: &BigDecimal) -> String {
+        self.formatter.format(amount)
+    }
+
+    fn optional_amount(&self, amount: &Option<BigDecimal>) -> String {
+        amount.as_ref().map(|a| self.amount(a)).unwrap_or_default()
+    }
+
+    fn account_label(&self, line: &AccountBalance) -> String {
+        format!(
+            "{} {}",
+            line.account.id,
+            self.translations.account_name(&line.account)
+        )
+    }
+
+    /// Trial balance grouped by account type, with subtotals
+    pub fn render_trial_balance(&self, trial_balance: &TrialBalance) -> String {
+        let mut out = String::new();
+        let date = trial_balance.as_of_date.to_string();
+        let _ = writeln!(
+            out,
+            "{}",
+            self.translations
+                .format(Label::TrialBalanceTitle, &[("date", &date)])
+        );
+        let _ = writeln!(
+            out,
+            "{:<NAME_WIDTH$}{:>AMOUNT_WIDTH$}{:>AMOUNT_WIDTH$}",
+            self.label(Label::Account),
+            self.label(Label::Debit),
+            self.label(Label::Credit)
+        );
+        for section in trial_balance.sections() {
+            let _ = writeln!(
+                out,
+                "{}",
+                self.label(Label::AccountType(section.account_type))
+            );
+            for line in &section.lines {
+                let _ = writeln!(
+                    out,
+                    "  {:<width$}{:>AMOUNT_WIDTH$}{:>AMOUNT_WIDTH$}",
+                    self.account_label(line),
+                    self.optional_amount(&line.debit_balance),
+                    self.optional_amount(&line.credit_balance),
+                    width = NAME_WIDTH - 2
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{:<NAME_WIDTH$}{:>AMOUNT_WIDTH$}{:>AMOUNT_WIDTH$}",
+                "",
+                self.amount(&section.total_debits),
+                self.amount(&section.total_credits)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{:<NAME_WIDTH$}{:>AMOUNT_WIDTH$}{:>AMOUNT_WIDTH$}",
+            self.label(Label::Total),
+            self.amount(&trial_balance.total_debits),
+            self.amount(&trial_balance.total_credits)
+        );
+        out
+    }
+
+    fn render_section(
+        &self,
+        out: &mut String,
+        heading: Label,
+        lines: &[AccountBalance],
+        extra_line: Option<(Label, &BigDecimal)>,
+        total: &BigDecimal,
+    ) {
+        let heading = self.label(heading);
+        let _ = writeln!(out, "{heading}");
+        let lines = lines
+            .iter()
+            .map(|line| (self.account_label(line), line.signed_balance()))
+            .chain(
+                extra_line.map(|(label, amount)| (self.label(label).to_string(), amount.clone())),
+            );
+        for (label, amount) in lines {
+            let _ = writeln!(
+                out,
+                "  {:<width$}{:>AMOUNT_WIDTH$}",
+                label,
+                self.amount(&amount),
+                width = NAME_WIDTH - 2
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{:<NAME_WIDTH$}{:>AMOUNT_WIDTH$}",
+            self.translations
+                .format(Label::SectionTotal, &[("section", heading)]),
+            self.amount(total)
+        );
+    }
+
+    /// Balance sheet with assets, liabilities and equity sections
+    pub fn render_balance_sheet(&self, balance_sheet: &BalanceSheet) -> String {
+        let mut out = String::new();
+        let date = balance_sheet.as_of_date.to_string();
+        let _ = writeln!(
+            out,
+            "{}",
+            self.translations
+                .format(Label::BalanceSheetTitle, &[("date", &date)])
+        );
+        self.render_section(
+            &mut out,
+            Label::Assets,
+            &balance_sheet.assets,
+            None,
+            &balance_sheet.total_assets,
+        );
+        self.render_section(
+            &mut out,
+            Label::Liabilities,
+            &balance_sheet.liabilities,
+            None,
+            &balance_sheet.total_liabilities,
+        );
+        self.render_section(
+            &mut out,
+            Label::Equity,
+            &balance_sheet.equity,
+            balance_sheet
+                .current_earnings
+                .as_ref()
+                .map(|amount| (Label::CurrentEarnings, amount)),
+            &balance_sheet.total_equity,
+        );
+        out
+    }
+
+    /// Income statement with revenue and expense sections
+    pub fn render_income_statement(&self, income_statement: &IncomeStatement) -> String {
+        let mut out = String::new();
+        let start = income_statement.start_date.to_string();
+        let end = income_statement.end_date.to_string();
+        let _ = writeln!(
+            out,
+            "{}",
+            self.translations.format(
+                Label::IncomeStatementTitle,
+                &[("start", &start), ("end", &end)]
+            )
+        );
+        self.render_section(
+            &mut out,
+            Label::Revenue,
+            &income_statement.revenue,
+            None,
+            &income_statement.total_revenue,
+        );
+        self.render_section(
+            &mut out,
+            Label::Expenses,
+            &income_statement.expenses,
+            None,
+            &income_statement.total_expenses,
+        );
+        let _ = writeln!(
+            out,
+            "{:<NAME_WIDTH$}{:>AMOUNT_WIDTH$}",
+            self.label(Label::NetIncome),
+            self.amount(&income_statement.net_income)
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{Ledger, TransactionBuilder};
+    use crate::utils::MemoryStorage;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_render_reports() {
+        let mut ledger = Ledger::new(MemoryStorage::new());
+        ledger.setup_standard_chart_of_accounts().await.unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let sale = TransactionBuilder::new("sale".to_string(), date, "Sale".to_string())
+            .debit("1000".to_string(), BigDecimal::from(1250000), None)
+            .credit("4000".to_string(), BigDecimal::from(1250000), None)
+            .build()
+            .unwrap();
+        ledger.record_transaction(sale).await.unwrap();
+
+        let renderer = TextReportRenderer::default();
+        let trial_balance =
+            renderer.render_trial_balance(&ledger.get_trial_balance(date).await.unwrap());
+        let cash = trial_balance.find("1000 Cash").unwrap();
+        let sales = trial_balance.find("4000 Sales Revenue").unwrap();
+        assert!(cash < sales);
+        assert!(trial_balance.contains("₹12,50,000.00"));
+
+        let balance_sheet =
+            renderer.render_balance_sheet(&ledger.generate_balance_sheet(date).await.unwrap());
+        assert!(balance_sheet.contains("Total Assets"));
+
+        let income_statement = renderer
+            .render_income_statement(&ledger.generate_income_statement(date, date).await.unwrap());
+        assert!(income_statement
+            .lines()
+            .last()
+            .unwrap()
+            .ends_with("₹12,50,000.00"));
+
+        let hindi = renderer.with_translations(Translations::hindi());
+        let trial_balance =
+            hindi.render_trial_balance(&ledger.get_trial_balance(date).await.unwrap());
+        assert!(trial_balance.starts_with("2024-03-31 तक का तलपट"));
+        assert!(trial_balance.contains("4000 विक्रय राजस्व"));
+        assert!(trial_balance.contains("\nपरिसंपत्ति\n"));
+    }
+}
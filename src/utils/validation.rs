@@ -2,11 +2,12 @@
 
 use crate::traits::*;
 use crate::types::*;
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Signed};
+use std::collections::HashSet;
 
 /// Validate that an amount is positive
 pub fn validate_positive_amount(amount: &BigDecimal) -> LedgerResult<()> {
-    if *amount <= BigDecimal::from(0) {
+    if !amount.is_positive() {
         Err(LedgerError::Validation(
             "Amount must be positive".to_string(),
         ))
@@ -116,6 +117,50 @@ impl TransactionValidator for EnhancedTransactionValidator {
     }
 }
 
+/// Transaction validator that requires posted transactions of selected
+/// voucher types (typically tax invoices) to carry at least one attachment,
+/// after running an inner validator
+pub struct AttachmentRequiredValidator {
+    inner: Box<dyn TransactionValidator>,
+    kinds: HashSet<TransactionKind>,
+}
+
+impl AttachmentRequiredValidator {
+    /// Wrap an existing transaction validator
+    pub fn new(inner: Box<dyn TransactionValidator>) -> Self {
+        Self {
+            inner,
+            kinds: HashSet::new(),
+        }
+    }
+
+    /// Require attachments on posted transactions of a voucher type
+    pub fn require_for(mut self, kind: TransactionKind) -> Self {
+        self.kinds.insert(kind);
+        self
+    }
+}
+
+impl TransactionValidator for AttachmentRequiredValidator {
+    fn validate_transaction(&self, transaction: &Transaction) -> LedgerResult<()> {
+        self.inner.validate_transaction(transaction)?;
+        if transaction.is_posted()
+            && self.kinds.contains(&transaction.kind)
+            && transaction.attachments.is_empty()
+        {
+            return Err(LedgerError::Validation(format!(
+                "Transaction '{}' requires at least one attachment",
+                transaction.id
+            )));
+        }
+        Ok(())
+    }
+
+    fn validate_account_references(&self, transaction: &Transaction) -> LedgerResult<()> {
+        self.inner.validate_account_references(transaction)
+    }
+}
+
 /// Enhanced account validator with detailed checks
 pub struct EnhancedAccountValidator;
 
@@ -128,9 +173,11 @@ impl AccountValidator for EnhancedAccountValidator {
         Ok(())
     }
 
-    fn validate_account_deletion(&self, _account_id: &str) -> LedgerResult<()> {
-        // This would typically check if account has any transactions
-        // For this basic implementation, we allow deletion
-        Ok(())
+    fn validate_account_deletion(
+        &self,
+        account: &Account,
+        usage: &AccountUsage,
+    ) -> LedgerResult<()> {
+        DefaultAccountValidator.validate_account_deletion(account, usage)
     }
 }
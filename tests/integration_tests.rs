@@ -2,12 +2,21 @@
 
 use accounting_core::{
     patterns,
-    utils::{EnhancedAccountValidator, EnhancedTransactionValidator, MemoryStorage},
-    AccountType, GstCalculator, GstCategory, GstInvoice, GstLineItem, Ledger, LedgerStorage,
-    TransactionBuilder,
+    receivables::{InterestTerms, OpenInvoice, OverdueInterestCalculator},
+    utils::{
+        AttachmentRequiredValidator, EnhancedAccountValidator, EnhancedTransactionValidator,
+        MemoryAttachmentStorage, MemoryStorage,
+    },
+    Account, AccountType, Actor, AllocationBasis, Attachment, AttachmentStorage, BalanceSnapshot,
+    BusinessTimezone, DeletionBlocker, EntryFilter, EntryType, FixedClock, GstAccounts,
+    GstCalculator, GstCategory, GstInvoice, GstLineItem, GstPurchaseParams, GstSaleParams,
+    IntegrityIssue, Inventory, InventoryItem, LandedCharge, LandedCostParams, Ledger, LedgerError,
+    LedgerStorage, MetaValue, NewAccount, PurchaseLine, ReportScope, SaleLine, TransactionBuilder,
+    TransactionKind, TransactionStatus, ValuationMethod,
 };
 use bigdecimal::BigDecimal;
-use chrono::NaiveDate;
+use chrono::{NaiveDate, TimeDelta};
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_complete_accounting_workflow() {
@@ -268,6 +277,61 @@ async fn test_account_hierarchy() {
     assert_eq!(asset_accounts.len(), 2);
 }
 
+#[tokio::test]
+async fn test_account_deletion_blocked_by_history_and_children() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    ledger
+        .create_account(
+            "petty_cash".to_string(),
+            "Petty Cash".to_string(),
+            AccountType::Asset,
+            Some("1000".to_string()),
+        )
+        .await
+        .unwrap();
+    let funding = patterns::create_sales_transaction(
+        "S1".to_string(),
+        NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+        "Cash sale".to_string(),
+        "1000".to_string(),
+        "4000".to_string(),
+        BigDecimal::from(75),
+    )
+    .unwrap();
+    ledger.record_transaction(funding).await.unwrap();
+
+    match ledger.delete_account("1000").await {
+        Err(LedgerError::AccountInUse {
+            account_id,
+            blockers,
+        }) => {
+            assert_eq!(account_id, "1000");
+            assert_eq!(
+                blockers,
+                vec![
+                    DeletionBlocker::HasEntries {
+                        transaction_count: 1
+                    },
+                    DeletionBlocker::NonZeroBalance {
+                        balance: BigDecimal::from(75)
+                    },
+                    DeletionBlocker::HasChildren {
+                        child_ids: vec!["petty_cash".to_string()]
+                    },
+                ]
+            );
+        }
+        other => panic!("expected AccountInUse, got {:?}", other),
+    }
+    assert!(ledger.get_account("1000").await.unwrap().is_some());
+
+    // Unused accounts can still be deleted
+    ledger.delete_account("petty_cash").await.unwrap();
+    ledger.delete_account("6100").await.unwrap();
+    assert!(ledger.get_account("6100").await.unwrap().is_none());
+}
+
 #[tokio::test]
 async fn test_date_range_filtering() {
     let storage = MemoryStorage::new();
@@ -414,3 +478,1365 @@ async fn test_memory_storage_operations() {
     assert!(retrieved_txn.is_some());
     assert_eq!(retrieved_txn.unwrap().description, "Test transaction");
 }
+
+#[tokio::test]
+async fn test_non_negative_balance_constraint() {
+    let storage = MemoryStorage::new();
+    let mut ledger = Ledger::new(storage);
+    let accounts = ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let cash = accounts["cash"].id.clone();
+    let rent = accounts["rent_expense"].id.clone();
+
+    ledger.set_non_negative(&cash, true).await.unwrap();
+
+    let investment = |month: u32, amount: i32| {
+        patterns::create_owner_investment(
+            "invest1".to_string(),
+            NaiveDate::from_ymd_opt(2024, month, 1).unwrap(),
+            "Initial investment".to_string(),
+            cash.clone(),
+            accounts["owners_equity"].id.clone(),
+            BigDecimal::from(amount),
+        )
+        .unwrap()
+    };
+    ledger
+        .record_transaction(investment(1, 1000))
+        .await
+        .unwrap();
+
+    let rent_payment = |id: &str, day: u32, amount: i32| {
+        patterns::create_expense_payment(
+            id.to_string(),
+            NaiveDate::from_ymd_opt(2024, 2, day).unwrap(),
+            "Rent".to_string(),
+            rent.clone(),
+            cash.clone(),
+            BigDecimal::from(amount),
+        )
+        .unwrap()
+    };
+
+    ledger
+        .record_transaction(rent_payment("rent1", 20, 800))
+        .await
+        .unwrap();
+
+    // Fine as of Feb 10, but leaves cash at -300 from Feb 20 onwards
+    let result = ledger
+        .record_transaction(rent_payment("rent2", 10, 500))
+        .await;
+    match result {
+        Err(LedgerError::InsufficientBalance {
+            account_id,
+            shortfall,
+        }) => {
+            assert_eq!(account_id, cash);
+            assert_eq!(shortfall, BigDecimal::from(300));
+        }
+        other => panic!("expected insufficient balance, got {:?}", other),
+    }
+
+    let cash_balance = ledger.get_account_balance(&cash, None).await.unwrap();
+    assert_eq!(cash_balance, BigDecimal::from(200));
+
+    // The deposit rent1 relies on cannot be shrunk, moved past it or deleted
+    for edited in [investment(1, 500), investment(3, 1000)] {
+        assert!(matches!(
+            ledger.update_transaction(&edited).await,
+            Err(LedgerError::InsufficientBalance { .. })
+        ));
+    }
+    assert!(matches!(
+        ledger.delete_transaction("invest1").await,
+        Err(LedgerError::InsufficientBalance { .. })
+    ));
+    ledger
+        .update_transaction(&investment(1, 900))
+        .await
+        .unwrap();
+    let cash_balance = ledger.get_account_balance(&cash, None).await.unwrap();
+    assert_eq!(cash_balance, BigDecimal::from(100));
+}
+
+#[test]
+fn test_structured_errors() {
+    let unbalanced = TransactionBuilder::new(
+        "txn1".to_string(),
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        "Unbalanced".to_string(),
+    )
+    .debit("cash".to_string(), BigDecimal::from(100), None)
+    .credit("revenue".to_string(), BigDecimal::from(90), None)
+    .build()
+    .unwrap_err();
+
+    assert_eq!(unbalanced.code(), "unbalanced_transaction");
+    assert!(!unbalanced.is_retryable());
+    match unbalanced {
+        LedgerError::Unbalanced { debits, credits } => {
+            assert_eq!(debits, BigDecimal::from(100));
+            assert_eq!(credits, BigDecimal::from(90));
+        }
+        other => panic!("expected unbalanced error, got {:?}", other),
+    }
+
+    let io = std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out");
+    let storage = LedgerError::storage_with_source("saving account", true, io);
+    assert_eq!(storage.code(), "storage_error");
+    assert!(storage.is_retryable());
+    let source = std::error::Error::source(&storage).unwrap();
+    assert_eq!(source.to_string(), "connection timed out");
+}
+
+#[tokio::test]
+async fn test_simulate_transaction_does_not_persist() {
+    let storage = MemoryStorage::new();
+    let mut ledger = Ledger::new(storage);
+    let accounts = ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let cash = accounts["cash"].id.clone();
+    let revenue = accounts["sales_revenue"].id.clone();
+
+    let sale = patterns::create_sales_transaction(
+        "sale1".to_string(),
+        NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+        "Sale".to_string(),
+        cash.clone(),
+        revenue.clone(),
+        BigDecimal::from(2500),
+    )
+    .unwrap();
+
+    let result = ledger.simulate_transaction(&sale).await.unwrap();
+    assert_eq!(result.changes.len(), 2);
+    assert_eq!(result.changes[0].account_id, cash);
+    assert_eq!(result.changes[0].balance_before, BigDecimal::from(0));
+    assert_eq!(result.changes[0].balance_after, BigDecimal::from(2500));
+    assert_eq!(result.changes[1].account_id, revenue);
+    assert_eq!(result.changes[1].change, BigDecimal::from(2500));
+
+    assert!(ledger.get_transaction("sale1").await.unwrap().is_none());
+    assert_eq!(
+        ledger.get_account_balance(&cash, None).await.unwrap(),
+        BigDecimal::from(0)
+    );
+
+    let mut bad = sale.clone();
+    bad.entries[1].account_id = "missing".to_string();
+    assert!(matches!(
+        ledger.simulate_transaction(&bad).await,
+        Err(LedgerError::AccountNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_what_if_reporting_with_drafts() {
+    let storage = MemoryStorage::new();
+    let mut ledger = Ledger::new(storage);
+    let accounts = ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let cash = accounts["cash"].id.clone();
+    let rent = accounts["rent_expense"].id.clone();
+    let end = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+    let sale = patterns::create_sales_transaction(
+        "sale1".to_string(),
+        NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+        "Sale".to_string(),
+        cash.clone(),
+        accounts["sales_revenue"].id.clone(),
+        BigDecimal::from(10000),
+    )
+    .unwrap();
+    ledger.record_transaction(sale).await.unwrap();
+
+    let month_end_rent = patterns::create_expense_payment(
+        "rent1".to_string(),
+        end,
+        "January rent".to_string(),
+        rent.clone(),
+        cash.clone(),
+        BigDecimal::from(4000),
+    )
+    .unwrap();
+    ledger.save_draft(month_end_rent).await.unwrap();
+
+    // Drafts never touch balances or the posted transaction list
+    assert_eq!(
+        ledger.get_account_balance(&cash, Some(end)).await.unwrap(),
+        BigDecimal::from(10000)
+    );
+    assert_eq!(ledger.get_transactions(None, None).await.unwrap().len(), 1);
+    assert_eq!(ledger.list_drafts(None, None).await.unwrap().len(), 1);
+
+    let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let posted = ledger
+        .generate_income_statement_with_scope(start, end, ReportScope::Posted)
+        .await
+        .unwrap();
+    let preview = ledger
+        .generate_income_statement_with_scope(start, end, ReportScope::PostedAndDraft)
+        .await
+        .unwrap();
+    assert_eq!(posted.net_income, BigDecimal::from(10000));
+    assert_eq!(preview.net_income, BigDecimal::from(6000));
+
+    let preview_sheet = ledger
+        .generate_balance_sheet_with_scope(end, ReportScope::PostedAndDraft)
+        .await
+        .unwrap();
+    assert!(preview_sheet.is_balanced);
+    assert_eq!(preview_sheet.total_assets, BigDecimal::from(6000));
+
+    ledger.post_draft("rent1").await.unwrap();
+    assert!(ledger.list_drafts(None, None).await.unwrap().is_empty());
+    assert_eq!(
+        ledger.get_account_balance(&cash, None).await.unwrap(),
+        BigDecimal::from(6000)
+    );
+}
+
+#[tokio::test]
+async fn test_transaction_kinds_and_daybook() {
+    let storage = MemoryStorage::new();
+    let mut ledger = Ledger::new(storage);
+    let accounts = ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+
+    let investment = patterns::create_owner_investment(
+        "inv1".to_string(),
+        date(1),
+        "Capital".to_string(),
+        accounts["cash"].id.clone(),
+        accounts["owners_equity"].id.clone(),
+        BigDecimal::from(5000),
+    )
+    .unwrap();
+    assert_eq!(investment.kind, TransactionKind::Receipt);
+    ledger.record_transaction(investment).await.unwrap();
+
+    for (id, day, amount) in [("s2", 9, 700), ("s1", 4, 300)] {
+        let sale = patterns::create_sales_transaction(
+            id.to_string(),
+            date(day),
+            "Sale".to_string(),
+            accounts["cash"].id.clone(),
+            accounts["sales_revenue"].id.clone(),
+            BigDecimal::from(amount),
+        )
+        .unwrap();
+        ledger.record_transaction(sale).await.unwrap();
+    }
+
+    let journal = TransactionBuilder::new("j1".to_string(), date(10), "Reclass".to_string())
+        .debit(
+            accounts["service_revenue"].id.clone(),
+            BigDecimal::from(50),
+            None,
+        )
+        .credit(
+            accounts["sales_revenue"].id.clone(),
+            BigDecimal::from(50),
+            None,
+        )
+        .build()
+        .unwrap();
+    assert_eq!(journal.kind, TransactionKind::Journal);
+    ledger.record_transaction(journal).await.unwrap();
+
+    let sales_book = ledger
+        .generate_daybook(TransactionKind::Sales, date(1), date(31))
+        .await
+        .unwrap();
+    let ids: Vec<&str> = sales_book
+        .transactions
+        .iter()
+        .map(|t| t.id.as_str())
+        .collect();
+    assert_eq!(ids, ["s1", "s2"]);
+    assert_eq!(sales_book.total_amount, BigDecimal::from(1000));
+
+    let journals = ledger
+        .get_transactions_by_kind(TransactionKind::Journal, None, None)
+        .await
+        .unwrap();
+    assert_eq!(journals.len(), 1);
+}
+
+#[tokio::test]
+async fn test_two_step_bank_transfer() {
+    let storage = MemoryStorage::new();
+    let mut ledger = Ledger::new(storage);
+    for (id, name) in [
+        ("hdfc", "HDFC Bank"),
+        ("icici", "ICICI Bank"),
+        ("transit", "Funds in Transit"),
+    ] {
+        ledger
+            .create_account(id.to_string(), name.to_string(), AccountType::Asset, None)
+            .await
+            .unwrap();
+    }
+    ledger
+        .create_account(
+            "capital".to_string(),
+            "Capital".to_string(),
+            AccountType::Equity,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let funding = patterns::create_owner_investment(
+        "fund".to_string(),
+        NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(),
+        "Capital".to_string(),
+        "hdfc".to_string(),
+        "capital".to_string(),
+        BigDecimal::from(10000),
+    )
+    .unwrap();
+    ledger.record_transaction(funding).await.unwrap();
+
+    let transfer = patterns::create_bank_transfer(accounting_core::BankTransferParams {
+        id: "tr1".to_string(),
+        date: NaiveDate::from_ymd_opt(2024, 4, 2).unwrap(),
+        description: "HDFC to ICICI".to_string(),
+        from_bank_account_id: "hdfc".to_string(),
+        to_bank_account_id: "icici".to_string(),
+        amount: BigDecimal::from(4000),
+        in_transit_account_id: Some("transit".to_string()),
+    })
+    .unwrap();
+    assert_eq!(transfer.kind, TransactionKind::Contra);
+    ledger.record_transaction(transfer).await.unwrap();
+
+    assert_eq!(balance_of(&ledger, "transit").await, BigDecimal::from(4000));
+    assert_eq!(balance_of(&ledger, "icici").await, BigDecimal::from(0));
+
+    let clearing = patterns::create_transfer_clearing(
+        "tr1-clear".to_string(),
+        NaiveDate::from_ymd_opt(2024, 4, 3).unwrap(),
+        "ICICI confirmed receipt".to_string(),
+        "transit".to_string(),
+        "icici".to_string(),
+        BigDecimal::from(4000),
+    )
+    .unwrap();
+    ledger.record_transaction(clearing).await.unwrap();
+
+    assert_eq!(balance_of(&ledger, "transit").await, BigDecimal::from(0));
+    assert_eq!(balance_of(&ledger, "icici").await, BigDecimal::from(4000));
+    assert_eq!(balance_of(&ledger, "hdfc").await, BigDecimal::from(6000));
+}
+
+#[test]
+fn test_write_off_and_discount_patterns() {
+    let date = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+
+    let within =
+        patterns::create_small_balance_write_off(accounting_core::SmallBalanceWriteOffParams {
+            id: "wo1".to_string(),
+            date,
+            description: "Paise difference".to_string(),
+            party_account_id: "ar".to_string(),
+            write_off_account_id: "sundry".to_string(),
+            residual: "-0.40".parse().unwrap(),
+            threshold: BigDecimal::from(1),
+        })
+        .unwrap();
+    // Overpayment: debit the customer, credit sundry income
+    assert_eq!(within.entries[0].account_id, "ar");
+    assert_eq!(within.entries[1].account_id, "sundry");
+
+    let too_large =
+        patterns::create_small_balance_write_off(accounting_core::SmallBalanceWriteOffParams {
+            id: "wo2".to_string(),
+            date,
+            description: "Too large".to_string(),
+            party_account_id: "ar".to_string(),
+            write_off_account_id: "sundry".to_string(),
+            residual: BigDecimal::from(25),
+            threshold: BigDecimal::from(1),
+        });
+    assert!(too_large.is_err());
+
+    let settlement = patterns::create_settlement_with_discount_allowed(
+        accounting_core::SettlementDiscountParams {
+            id: "rcpt1".to_string(),
+            date,
+            description: "Settled with 2% discount".to_string(),
+            cash_account_id: "bank".to_string(),
+            party_account_id: "ar".to_string(),
+            discount_account_id: "discount_allowed".to_string(),
+            settled_amount: BigDecimal::from(10000),
+            discount: BigDecimal::from(200),
+        },
+    )
+    .unwrap();
+    assert_eq!(settlement.kind, TransactionKind::Receipt);
+    assert_eq!(settlement.entries[0].amount, BigDecimal::from(9800));
+    assert_eq!(settlement.total_credits(), BigDecimal::from(10000));
+}
+
+#[tokio::test]
+async fn test_inventory_sale_posts_cogs_and_reconciles() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let mut inventory = Inventory::new();
+    inventory
+        .add_item(InventoryItem::new(
+            "widget".to_string(),
+            "Widget".to_string(),
+            "pcs".to_string(),
+            ValuationMethod::Fifo,
+            "1300".to_string(),
+            "5000".to_string(),
+        ))
+        .unwrap();
+
+    let purchase_date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+    ledger
+        .record_transaction(
+            patterns::create_asset_purchase(
+                "pur1".to_string(),
+                purchase_date,
+                "Widgets purchased".to_string(),
+                "1300".to_string(),
+                "2000".to_string(),
+                BigDecimal::from(2000),
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    inventory
+        .receive_stock(
+            "widget",
+            purchase_date,
+            BigDecimal::from(20),
+            BigDecimal::from(100),
+            Some("pur1".to_string()),
+        )
+        .unwrap();
+
+    let sale_date = NaiveDate::from_ymd_opt(2024, 4, 10).unwrap();
+    let sale = patterns::create_sales_transaction(
+        "sale1".to_string(),
+        sale_date,
+        "Widgets sold".to_string(),
+        "1200".to_string(),
+        "4000".to_string(),
+        BigDecimal::from(1500),
+    )
+    .unwrap();
+    let cogs = ledger
+        .record_sale_with_cogs(
+            &mut inventory,
+            sale,
+            &[SaleLine::new("widget".to_string(), BigDecimal::from(8))],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(cogs.id, "sale1-cogs");
+    assert_eq!(cogs.total_debits(), BigDecimal::from(800));
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(800));
+    assert_eq!(
+        inventory.quantity_on_hand("widget").unwrap(),
+        BigDecimal::from(12)
+    );
+
+    let report = ledger
+        .generate_stock_valuation(&inventory, sale_date)
+        .await
+        .unwrap();
+    assert_eq!(report.total_value, BigDecimal::from(1200));
+    assert!(report.is_reconciled);
+
+    // Selling more than is on hand leaves both the ledger and stock untouched
+    let oversold = patterns::create_sales_transaction(
+        "sale2".to_string(),
+        sale_date,
+        "Too many widgets".to_string(),
+        "1200".to_string(),
+        "4000".to_string(),
+        BigDecimal::from(5000),
+    )
+    .unwrap();
+    assert!(ledger
+        .record_sale_with_cogs(
+            &mut inventory,
+            oversold,
+            &[SaleLine::new("widget".to_string(), BigDecimal::from(50))],
+        )
+        .await
+        .is_err());
+    assert!(ledger.get_transaction("sale2").await.unwrap().is_none());
+    assert_eq!(
+        inventory.quantity_on_hand("widget").unwrap(),
+        BigDecimal::from(12)
+    );
+}
+
+#[tokio::test]
+async fn test_landed_cost_capitalisation() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let mut inventory = Inventory::new();
+    for id in ["bolts", "nuts"] {
+        inventory
+            .add_item(InventoryItem::new(
+                id.to_string(),
+                id.to_string(),
+                "pcs".to_string(),
+                ValuationMethod::WeightedAverage,
+                "1300".to_string(),
+                "5000".to_string(),
+            ))
+            .unwrap();
+    }
+
+    let date = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+    ledger
+        .record_transaction(
+            patterns::create_asset_purchase(
+                "pur1".to_string(),
+                date,
+                "Imported fasteners".to_string(),
+                "1300".to_string(),
+                "2000".to_string(),
+                BigDecimal::from(4000),
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    inventory
+        .receive_stock(
+            "bolts",
+            date,
+            BigDecimal::from(100),
+            BigDecimal::from(30),
+            None,
+        )
+        .unwrap();
+    inventory
+        .receive_stock(
+            "nuts",
+            date,
+            BigDecimal::from(200),
+            BigDecimal::from(5),
+            None,
+        )
+        .unwrap();
+
+    let (allocations, journal) = ledger
+        .capitalize_landed_cost(
+            &mut inventory,
+            LandedCostParams {
+                id: "lc1".to_string(),
+                date,
+                description: "Freight and duty on pur1".to_string(),
+                lines: vec![
+                    PurchaseLine::new(
+                        "bolts".to_string(),
+                        BigDecimal::from(100),
+                        BigDecimal::from(3000),
+                    ),
+                    PurchaseLine::new(
+                        "nuts".to_string(),
+                        BigDecimal::from(200),
+                        BigDecimal::from(1000),
+                    ),
+                ],
+                charges: vec![
+                    LandedCharge {
+                        description: "Freight".to_string(),
+                        amount: BigDecimal::from(300),
+                        account_id: "2000".to_string(),
+                        basis: AllocationBasis::Quantity,
+                    },
+                    LandedCharge {
+                        description: "Customs duty".to_string(),
+                        amount: BigDecimal::from(400),
+                        account_id: "2000".to_string(),
+                        basis: AllocationBasis::Value,
+                    },
+                ],
+            },
+        )
+        .await
+        .unwrap();
+
+    // Bolts: 100 freight + 300 duty; nuts: 200 freight + 100 duty
+    assert_eq!(allocations[0].amount, BigDecimal::from(400));
+    assert_eq!(allocations[1].amount, BigDecimal::from(300));
+    assert_eq!(journal.total_debits(), BigDecimal::from(700));
+    assert_eq!(
+        inventory.value_on_hand("bolts").unwrap(),
+        BigDecimal::from(3400)
+    );
+
+    let report = ledger
+        .generate_stock_valuation(&inventory, date)
+        .await
+        .unwrap();
+    assert_eq!(report.total_value, BigDecimal::from(4700));
+    assert!(report.is_reconciled);
+}
+
+#[tokio::test]
+async fn test_overdue_interest_charge_with_gst() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    for (id, account_type) in [
+        ("ar", AccountType::Asset),
+        ("interest_income", AccountType::Income),
+        ("gst_payable", AccountType::Liability),
+    ] {
+        ledger
+            .create_account(id.to_string(), id.to_string(), account_type, None)
+            .await
+            .unwrap();
+    }
+
+    let due = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+    let as_of = NaiveDate::from_ymd_opt(2024, 4, 13).unwrap();
+    let invoice = OpenInvoice::new(
+        "inv1".to_string(),
+        "acme".to_string(),
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        due,
+        BigDecimal::from(100000),
+    );
+    let interest = OverdueInterestCalculator::new(InterestTerms::simple(BigDecimal::from(18)))
+        .calculate(&invoice, as_of)
+        .unwrap();
+    assert_eq!(interest.interest, BigDecimal::from(3600));
+
+    let charge =
+        patterns::create_overdue_interest_charge(accounting_core::OverdueInterestChargeParams {
+            id: "int1".to_string(),
+            date: as_of,
+            description: "Interest on inv1".to_string(),
+            receivables_account_id: "ar".to_string(),
+            interest_income_account_id: "interest_income".to_string(),
+            gst_payable_account_id: "gst_payable".to_string(),
+            interest: interest.interest,
+            gst_rate: BigDecimal::from(18),
+            amount_policy: ledger.currency_amount_policy(),
+        })
+        .unwrap();
+    ledger.record_transaction(charge).await.unwrap();
+
+    assert_eq!(balance_of(&ledger, "ar").await, BigDecimal::from(4248));
+    assert_eq!(
+        balance_of(&ledger, "gst_payable").await,
+        BigDecimal::from(648)
+    );
+}
+
+#[tokio::test]
+async fn test_maker_checker_approval() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    ledger.set_approval_threshold(Some(BigDecimal::from(50000)));
+    ledger.set_actor(Some(Actor::new("maker".to_string(), vec![])));
+
+    let date = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
+    let payment = |id: &str, amount: i32| {
+        patterns::create_expense_payment(
+            id.to_string(),
+            date,
+            "Vendor payment".to_string(),
+            "5000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(amount),
+        )
+        .unwrap()
+    };
+
+    // Below the threshold: posted directly
+    ledger
+        .record_transaction(payment("p1", 10000))
+        .await
+        .unwrap();
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(10000));
+
+    // Above the threshold: held for approval without touching balances
+    ledger
+        .record_transaction(payment("p2", 75000))
+        .await
+        .unwrap();
+    ledger
+        .record_transaction(payment("p3", 60000))
+        .await
+        .unwrap();
+    let pending = ledger.list_pending_approval(None, None).await.unwrap();
+    assert_eq!(pending.len(), 2);
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(10000));
+
+    // The maker cannot approve their own transaction
+    assert!(ledger.approve_transaction("p2", None).await.is_err());
+
+    ledger.set_actor(Some(Actor::new("checker".to_string(), vec![])));
+    let approved = ledger
+        .approve_transaction("p2", Some("Within budget".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(approved.status, TransactionStatus::Posted);
+    assert_eq!(approved.metadata["approved_by"], "checker");
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(85000));
+
+    let rejected = ledger
+        .reject_transaction("p3", "Duplicate invoice".to_string())
+        .await
+        .unwrap();
+    assert_eq!(rejected.status, TransactionStatus::Rejected);
+    assert_eq!(rejected.metadata["rejection_reason"], "Duplicate invoice");
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(85000));
+    assert!(ledger
+        .list_pending_approval(None, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_approval_cannot_be_bypassed() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    ledger.set_approval_threshold(Some(BigDecimal::from(50000)));
+    ledger.set_actor(Some(Actor::new("maker".to_string(), vec![])));
+
+    let date = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
+    let payment = |id: &str| {
+        patterns::create_expense_payment(
+            id.to_string(),
+            date,
+            "Vendor payment".to_string(),
+            "5000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(75000),
+        )
+        .unwrap()
+    };
+
+    // Posting a draft goes through approval like a direct posting
+    ledger.save_draft(payment("d1")).await.unwrap();
+    let held = ledger.post_draft("d1").await.unwrap();
+    assert_eq!(held.status, TransactionStatus::PendingApproval);
+    assert_eq!(held.metadata["submitted_by"], "maker");
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(0));
+
+    // An update cannot move a transaction out of approval
+    let mut forced = ledger.get_transaction("d1").await.unwrap().unwrap();
+    forced.status = TransactionStatus::Posted;
+    assert!(ledger.update_transaction(&forced).await.is_err());
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(0));
+
+    // Without a recorded maker nobody can approve, the maker included
+    ledger.set_actor(None);
+    ledger.record_transaction(payment("p1")).await.unwrap();
+    ledger.set_actor(Some(Actor::new("checker".to_string(), vec![])));
+    assert!(ledger.approve_transaction("p1", None).await.is_err());
+    ledger.approve_transaction("d1", None).await.unwrap();
+    assert_eq!(balance_of(&ledger, "5000").await, BigDecimal::from(75000));
+}
+
+#[tokio::test]
+async fn test_archived_accounts_reject_new_postings() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    let payment = |id: &str| {
+        patterns::create_expense_payment(
+            id.to_string(),
+            date,
+            "Rent".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(500),
+        )
+        .unwrap()
+    };
+
+    // Accounts with a balance cannot be archived
+    ledger
+        .record_transaction(
+            patterns::create_owner_investment(
+                "inv1".to_string(),
+                date,
+                "Capital".to_string(),
+                "1000".to_string(),
+                "3000".to_string(),
+                BigDecimal::from(1000),
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(ledger.archive_account("1000").await.is_err());
+
+    let archived = ledger.archive_account("4100").await.unwrap();
+    assert!(!archived.is_active());
+    let active = ledger.list_active_accounts().await.unwrap();
+    assert!(active.iter().all(|a| a.id != "4100"));
+    assert_eq!(
+        ledger.list_accounts().await.unwrap().len(),
+        active.len() + 1
+    );
+
+    let service_sale = patterns::create_sales_transaction(
+        "s1".to_string(),
+        date,
+        "Consulting".to_string(),
+        "1000".to_string(),
+        "4100".to_string(),
+        BigDecimal::from(300),
+    )
+    .unwrap();
+    let error = ledger.record_transaction(service_sale.clone()).await;
+    assert!(matches!(error, Err(LedgerError::AccountArchived(ref id)) if id == "4100"));
+
+    ledger.restore_account("4100").await.unwrap();
+    ledger.record_transaction(service_sale).await.unwrap();
+    ledger.record_transaction(payment("p1")).await.unwrap();
+}
+
+#[test]
+fn typed_metadata_round_trips_and_reads_legacy_strings() {
+    let mut account = Account::new(
+        "1010".to_string(),
+        "Fixed Deposit".to_string(),
+        AccountType::Asset,
+        None,
+    );
+    account
+        .metadata
+        .insert("principal".to_string(), BigDecimal::from(250000).into());
+    account.metadata.insert(
+        "maturity".to_string(),
+        NaiveDate::from_ymd_opt(2025, 3, 31).unwrap().into(),
+    );
+    account
+        .metadata
+        .insert("auto_renew".to_string(), true.into());
+    account.metadata.insert(
+        "nominees".to_string(),
+        vec![MetaValue::from("A. Rao"), MetaValue::from("S. Rao")].into(),
+    );
+
+    let json = serde_json::to_string(&account).unwrap();
+    let restored: Account = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.metadata, account.metadata);
+    assert_eq!(
+        restored.metadata["principal"].as_decimal(),
+        Some(BigDecimal::from(250000))
+    );
+    assert_eq!(
+        restored.metadata["maturity"].as_date(),
+        NaiveDate::from_ymd_opt(2025, 3, 31)
+    );
+    assert_eq!(restored.metadata["auto_renew"].as_bool(), Some(true));
+    assert_eq!(restored.metadata["nominees"].as_list().unwrap().len(), 2);
+
+    // Metadata written before typed values were supported is all strings
+    let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+    legacy["metadata"] = serde_json::json!({ "ifsc": "HDFC0000123", "branch_code": "0042" });
+    let legacy: Account = serde_json::from_value(legacy).unwrap();
+    assert_eq!(legacy.metadata["ifsc"], "HDFC0000123");
+    assert_eq!(legacy.metadata["branch_code"], "0042");
+    assert_eq!(legacy.metadata["branch_code"].as_i64(), Some(42));
+}
+
+#[tokio::test]
+async fn test_entry_ids_and_line_queries() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+
+    let investment = patterns::create_owner_investment(
+        "inv1".to_string(),
+        date,
+        "Capital".to_string(),
+        "1000".to_string(),
+        "3000".to_string(),
+        BigDecimal::from(5000),
+    )
+    .unwrap();
+    let rent = patterns::create_expense_payment(
+        "rent1".to_string(),
+        date.succ_opt().unwrap(),
+        "Rent".to_string(),
+        "6000".to_string(),
+        "1000".to_string(),
+        BigDecimal::from(800),
+    )
+    .unwrap();
+    let reversal =
+        patterns::create_reversal(&rent, "rent1-rev".to_string(), date, "Undo".to_string())
+            .unwrap();
+    assert!(rent
+        .entries
+        .iter()
+        .all(|e| reversal.find_entry(&e.id).is_none()));
+    ledger.record_transaction(investment).await.unwrap();
+    ledger.record_transaction(rent.clone()).await.unwrap();
+
+    let cash_lines = ledger
+        .get_entries(&EntryFilter {
+            account_id: Some("1000".to_string()),
+            ..EntryFilter::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(cash_lines.len(), 2);
+    assert_eq!(cash_lines[0].transaction_id, "inv1");
+    assert_eq!(cash_lines[1].transaction_id, "rent1");
+    assert_eq!(cash_lines[1].entry.entry_type, EntryType::Credit);
+
+    let credits = ledger
+        .get_entries(&EntryFilter {
+            entry_type: Some(EntryType::Credit),
+            start_date: date.succ_opt(),
+            ..EntryFilter::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(credits.len(), 1);
+
+    let line = ledger
+        .find_entry(&rent.entries[0].id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(line.transaction_id, "rent1");
+    assert_eq!(line.entry, rent.entries[0]);
+    assert!(ledger.find_entry("missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_tax_invoices_require_attachments() {
+    let validator = AttachmentRequiredValidator::new(Box::new(EnhancedTransactionValidator))
+        .require_for(TransactionKind::Sales);
+    let mut ledger = Ledger::with_validators(
+        MemoryStorage::new(),
+        Box::new(EnhancedAccountValidator),
+        Box::new(validator),
+    );
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    let mut documents = MemoryAttachmentStorage::new();
+    let date = NaiveDate::from_ymd_opt(2024, 5, 2).unwrap();
+    let sale = |id: &str| {
+        patterns::create_sales_transaction(
+            id.to_string(),
+            date,
+            "Consulting".to_string(),
+            "1200".to_string(),
+            "4100".to_string(),
+            BigDecimal::from(1180),
+        )
+        .unwrap()
+    };
+    let invoice_copy = Attachment::new(
+        "att-1".to_string(),
+        "INV-001.pdf".to_string(),
+        "application/pdf".to_string(),
+        "memory://att-1".to_string(),
+        "sha256:9f86d081".to_string(),
+    );
+    documents
+        .save_attachment(&invoice_copy, b"%PDF-1.7")
+        .await
+        .unwrap();
+
+    // Drafts may be saved without documents, postings may not
+    ledger.save_draft(sale("s1")).await.unwrap();
+    assert!(matches!(
+        ledger.record_transaction(sale("s2")).await,
+        Err(LedgerError::Validation(_))
+    ));
+
+    let mut attached = sale("s2");
+    attached.attachments.push(invoice_copy.clone());
+    ledger.record_transaction(attached).await.unwrap();
+    let stored = ledger.get_transaction("s2").await.unwrap().unwrap();
+    assert_eq!(stored.attachments, vec![invoice_copy.clone()]);
+    assert_eq!(
+        documents
+            .get_attachment_content(&stored.attachments[0].id)
+            .await
+            .unwrap()
+            .unwrap(),
+        b"%PDF-1.7".to_vec()
+    );
+
+    // Attachments may change after the period is locked without counting
+    // as edits
+    ledger.lock_period(date).unwrap();
+
+    // The last attachment of a posted invoice cannot be removed
+    assert!(ledger.remove_attachment("s2", "att-1").await.is_err());
+    let receipt = Attachment::new(
+        "att-2".to_string(),
+        "receipt.jpg".to_string(),
+        "image/jpeg".to_string(),
+        "memory://att-2".to_string(),
+        "sha256:2c26b46b".to_string(),
+    );
+    ledger.add_attachment("s2", receipt).await.unwrap();
+    let updated = ledger.remove_attachment("s2", "att-1").await.unwrap();
+    assert_eq!(updated.attachments.len(), 1);
+    assert_eq!(updated.attachments[0].id, "att-2");
+    assert_eq!(updated.updated_at, stored.updated_at);
+    let report = ledger.validate_integrity(date).await.unwrap();
+    assert!(!report
+        .issues
+        .iter()
+        .any(|i| matches!(i, IntegrityIssue::LockedPeriodTransaction { .. })));
+}
+
+#[tokio::test]
+async fn test_notes_and_correction_chain() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    ledger.set_actor(Some(Actor::new("accountant".to_string(), vec![])));
+    let date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+    let rent = |id: &str, amount: i32| {
+        patterns::create_expense_payment(
+            id.to_string(),
+            date,
+            "Rent".to_string(),
+            "6000".to_string(),
+            "1000".to_string(),
+            BigDecimal::from(amount),
+        )
+        .unwrap()
+    };
+
+    // Rent entered as 900 instead of 800, reversed and re-entered
+    let wrong = rent("rent-jul", 900);
+    ledger.record_transaction(wrong.clone()).await.unwrap();
+    let reversal = patterns::create_reversal(
+        &wrong,
+        "rent-jul-rev".to_string(),
+        date,
+        "Reverse wrong rent".to_string(),
+    )
+    .unwrap();
+    assert_eq!(reversal.corrects.as_deref(), Some("rent-jul"));
+    ledger.record_transaction(reversal).await.unwrap();
+    let mut corrected = rent("rent-jul-fix", 800);
+    corrected.corrects = Some("rent-jul-rev".to_string());
+    ledger.record_transaction(corrected).await.unwrap();
+
+    // Notes may be added after the period is locked without counting as edits
+    ledger.lock_period(date).unwrap();
+    let before = ledger.get_transaction("rent-jul").await.unwrap().unwrap();
+    let noted = ledger
+        .add_note("rent-jul", "Keyed 900 instead of 800".to_string())
+        .await
+        .unwrap();
+    assert_eq!(noted.notes.len(), 1);
+    assert_eq!(noted.notes[0].author.as_deref(), Some("accountant"));
+    assert_eq!(noted.updated_at, before.updated_at);
+    let report = ledger.validate_integrity(date).await.unwrap();
+    assert!(!report
+        .issues
+        .iter()
+        .any(|i| matches!(i, IntegrityIssue::LockedPeriodTransaction { .. })));
+    ledger.unlock_period().unwrap();
+
+    let direct = ledger.get_corrections("rent-jul").await.unwrap();
+    assert_eq!(direct.len(), 1);
+    assert_eq!(direct[0].id, "rent-jul-rev");
+    let chain: Vec<String> = ledger
+        .get_correction_chain("rent-jul-fix")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.id)
+        .collect();
+    assert_eq!(chain, vec!["rent-jul", "rent-jul-rev", "rent-jul-fix"]);
+
+    let mut dangling = rent("rent-aug", 800);
+    dangling.corrects = Some("missing".to_string());
+    assert!(matches!(
+        ledger.record_transaction(dangling).await,
+        Err(LedgerError::TransactionNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_fixed_clock_stamps_ledger_timestamps() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    let start = NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .and_hms_opt(9, 0, 0)
+        .unwrap()
+        .and_utc();
+    let clock = FixedClock::new(start);
+    ledger.set_clock(Arc::new(clock.clone()));
+
+    ledger
+        .create_account(
+            "1000".to_string(),
+            "Cash".to_string(),
+            AccountType::Asset,
+            None,
+        )
+        .await
+        .unwrap();
+    ledger
+        .create_account(
+            "3000".to_string(),
+            "Capital".to_string(),
+            AccountType::Equity,
+            None,
+        )
+        .await
+        .unwrap();
+
+    clock.advance(TimeDelta::hours(1));
+    let investment =
+        TransactionBuilder::new("inv".to_string(), start.date_naive(), "Capital".to_string())
+            .debit("1000".to_string(), BigDecimal::from(100), None)
+            .credit("3000".to_string(), BigDecimal::from(100), None)
+            .build()
+            .unwrap();
+    ledger.record_transaction(investment).await.unwrap();
+    ledger.lock_period(start.date_naive()).unwrap();
+
+    let later = start + TimeDelta::hours(1);
+    let cash = ledger.get_account("1000").await.unwrap().unwrap();
+    assert_eq!(cash.created_at, start);
+    assert_eq!(cash.updated_at, later);
+    let posted = ledger.get_transaction("inv").await.unwrap().unwrap();
+    assert_eq!(posted.created_at, later);
+    assert_eq!(posted.updated_at, later);
+    assert_eq!(ledger.period_lock().unwrap().locked_at, later);
+    let bank = ledger
+        .add_account(Account::new(
+            "1010".to_string(),
+            "Bank".to_string(),
+            AccountType::Asset,
+            None,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(bank.created_at, later);
+}
+
+#[tokio::test]
+async fn test_utc_timestamps_and_business_timezone() {
+    // Accounts saved before timestamps carried a timezone still load, as UTC
+    let legacy = r#"{
+        "id": "1000", "name": "Cash", "account_type": "Asset", "parent_id": null,
+        "balance": "0", "metadata": {},
+        "created_at": "2024-03-31T20:00:00", "updated_at": "2024-03-31T20:00:00.250"
+    }"#;
+    let account: Account = serde_json::from_str(legacy).unwrap();
+    let expected = NaiveDate::from_ymd_opt(2024, 3, 31)
+        .unwrap()
+        .and_hms_opt(20, 0, 0)
+        .unwrap()
+        .and_utc();
+    assert_eq!(account.created_at, expected);
+    assert_eq!(account.updated_at, expected + TimeDelta::milliseconds(250));
+
+    let json = serde_json::to_value(&account).unwrap();
+    assert_eq!(json["created_at"], "2024-03-31T20:00:00Z");
+    let reloaded: Account = serde_json::from_value(json).unwrap();
+    assert_eq!(reloaded.created_at, account.created_at);
+
+    // So do snapshots and other persisted records
+    let snapshot: BalanceSnapshot = serde_json::from_str(
+        r#"{"as_of": "2024-03-31", "captured_at": "2024-03-31T20:00:00", "balances": {}}"#,
+    )
+    .unwrap();
+    assert_eq!(snapshot.captured_at, expected);
+
+    // Late evening UTC is already the next business day in India
+    let ist = BusinessTimezone::india();
+    assert_eq!(
+        ist.local_date(&account.created_at),
+        NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()
+    );
+    assert_eq!(
+        ist.format(&account.created_at, "%Y-%m-%d %H:%M %:z"),
+        "2024-04-01 01:30 +05:30"
+    );
+    assert_eq!(ist.to_utc(ist.to_local(&expected).naive_local()), expected);
+
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.set_clock(Arc::new(FixedClock::new(expected)));
+    assert_eq!(ledger.business_today(), expected.date_naive());
+    ledger.set_business_timezone(ist);
+    assert_eq!(
+        ledger.business_today(),
+        NaiveDate::from_ymd_opt(2024, 4, 1).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_gst_components_posted_to_separate_accounts() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    ledger.setup_standard_chart_of_accounts().await.unwrap();
+    for (id, name, account_type) in [
+        ("2310", "CGST Output", AccountType::Liability),
+        ("2320", "SGST Output", AccountType::Liability),
+        ("2330", "IGST Output", AccountType::Liability),
+        ("2340", "Cess Output", AccountType::Liability),
+        ("1410", "CGST Input", AccountType::Asset),
+        ("1420", "SGST Input", AccountType::Asset),
+        ("1430", "IGST Input", AccountType::Asset),
+    ] {
+        ledger
+            .create_account(id.to_string(), name.to_string(), account_type, None)
+            .await
+            .unwrap();
+    }
+    let accounts = |cgst: &str, sgst: &str, igst: &str, cess: Option<&str>| GstAccounts {
+        cgst_account_id: cgst.to_string(),
+        sgst_account_id: sgst.to_string(),
+        igst_account_id: igst.to_string(),
+        cess_account_id: cess.map(str::to_string),
+    };
+    let date = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+
+    let intra_state = GstCalculator::new(false)
+        .calculate_by_category(BigDecimal::from(1000), GstCategory::Luxury, None)
+        .unwrap();
+    let sale = patterns::create_gst_sale(GstSaleParams {
+        id: "sale-1".to_string(),
+        date,
+        description: "Sale of a motor car part".to_string(),
+        receivables_account_id: "1200".to_string(),
+        revenue_account_id: "4000".to_string(),
+        output_accounts: accounts("2310", "2320", "2330", Some("2340")),
+        calculation: intra_state.clone(),
+        cess_amount: BigDecimal::from(150),
+    })
+    .unwrap();
+    // No IGST leg on an intra-state sale
+    assert_eq!(sale.entries.len(), 5);
+    ledger.record_transaction(sale).await.unwrap();
+    assert_eq!(balance_of(&ledger, "1200").await, BigDecimal::from(1430));
+    assert_eq!(balance_of(&ledger, "2310").await, BigDecimal::from(140));
+    assert_eq!(balance_of(&ledger, "2320").await, BigDecimal::from(140));
+    assert_eq!(balance_of(&ledger, "2340").await, BigDecimal::from(150));
+
+    let inter_state = GstCalculator::new(true)
+        .calculate_by_category(BigDecimal::from(500), GstCategory::Higher, None)
+        .unwrap();
+    let purchase = |cess_account: Option<&str>| {
+        patterns::create_gst_purchase(GstPurchaseParams {
+            id: "bill-1".to_string(),
+            date,
+            description: "Office chairs".to_string(),
+            expense_account_id: "6100".to_string(),
+            cash_or_payables_account_id: "2000".to_string(),
+            input_accounts: accounts("1410", "1420", "1430", cess_account),
+            calculation: inter_state.clone(),
+            cess_amount: BigDecimal::from(0),
+        })
+    };
+    ledger
+        .record_transaction(purchase(None).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(balance_of(&ledger, "1430").await, BigDecimal::from(90));
+    assert_eq!(balance_of(&ledger, "1410").await, BigDecimal::from(0));
+    assert_eq!(balance_of(&ledger, "2000").await, BigDecimal::from(590));
+
+    let missing_cess_account = patterns::create_gst_sale(GstSaleParams {
+        id: "sale-2".to_string(),
+        date,
+        description: "Sale".to_string(),
+        receivables_account_id: "1200".to_string(),
+        revenue_account_id: "4000".to_string(),
+        output_accounts: accounts("2310", "2320", "2330", None),
+        calculation: intra_state,
+        cess_amount: BigDecimal::from(150),
+    });
+    assert!(matches!(
+        missing_cess_account,
+        Err(LedgerError::Validation(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_batch_account_creation() {
+    let mut ledger = Ledger::new(MemoryStorage::new());
+    let new = |id: &str, account_type, parent: Option<&str>| {
+        NewAccount::new(
+            id.to_string(),
+            id.to_string(),
+            account_type,
+            parent.map(str::to_string),
+        )
+    };
+
+    // Children listed before their parents are saved after them
+    let created = ledger
+        .create_accounts(vec![
+            new("cash", AccountType::Asset, Some("current")),
+            new("current", AccountType::Asset, Some("assets")),
+            new("assets", AccountType::Asset, None),
+            new("sales", AccountType::Income, None),
+        ])
+        .await
+        .unwrap();
+    let order: Vec<&str> = created.iter().map(|a| a.id.as_str()).collect();
+    assert_eq!(order, vec!["assets", "sales", "current", "cash"]);
+    assert_eq!(ledger.get_account_path("cash").await.unwrap().len(), 3);
+
+    // One bad account rejects the whole batch
+    let bad_batches = [
+        vec![
+            new("bank", AccountType::Asset, Some("current")),
+            new("rent", AccountType::Expense, Some("current")),
+        ],
+        vec![
+            new("bank", AccountType::Asset, Some("missing")),
+            new("petty", AccountType::Asset, Some("current")),
+        ],
+        vec![
+            new("bank", AccountType::Asset, Some("petty")),
+            new("petty", AccountType::Asset, Some("bank")),
+        ],
+        vec![
+            new("bank", AccountType::Asset, None),
+            new("bank", AccountType::Asset, None),
+        ],
+    ];
+    for batch in bad_batches {
+        assert!(ledger.create_accounts(batch).await.is_err());
+    }
+    assert_eq!(ledger.list_accounts().await.unwrap().len(), 4);
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn test_json_schema_matches_serialized_form() {
+    let schema = serde_json::to_value(schemars::schema_for!(accounting_core::Transaction)).unwrap();
+    let properties = &schema["properties"];
+    assert_eq!(properties["created_at"]["format"], "date-time");
+    assert!(schema["required"]
+        .as_array()
+        .unwrap()
+        .contains(&"entries".into()));
+
+    let entry = &schema["$defs"]["Entry"]["properties"];
+    assert!(entry["amount"]["type"]
+        .as_array()
+        .unwrap()
+        .contains(&"string".into()));
+
+    let invoice = serde_json::to_value(schemars::schema_for!(GstInvoice)).unwrap();
+    assert!(invoice["properties"].get("line_items").is_some());
+}
+
+async fn balance_of(ledger: &Ledger<MemoryStorage>, account_id: &str) -> BigDecimal {
+    ledger.get_account_balance(account_id, None).await.unwrap()
+}